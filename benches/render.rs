@@ -0,0 +1,27 @@
+//! askama vs minijinja on the largest page, `pages/security.html`
+//!
+//! Release builds serve askama; debug builds serve minijinja, which loads and
+//! parses the template from disk on every render. Run from the project root
+//! (minijinja reads `templates/`):
+//!
+//! ```bash
+//! cargo bench --bench render
+//! ```
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use app::handlers::templates::SecurityPage;
+
+fn render(c: &mut Criterion) {
+    let page = SecurityPage {};
+    // Both engines have to produce the page before their speed matters
+    assert_eq!(page.render_askama(), page.render_minijinja());
+
+    let mut group = c.benchmark_group("render/security");
+    group.bench_function("askama", |b| b.iter(|| page.render_askama().unwrap()));
+    group.bench_function("minijinja", |b| b.iter(|| page.render_minijinja().unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, render);
+criterion_main!(benches);
@@ -0,0 +1,93 @@
+//! What the middleware stack costs, and CSRF token throughput
+//!
+//! `stack/*` sends the same `GET` to a trivial handler twice over: straight to
+//! the router, and through [`middleware::stack`] as `main` serves it (request
+//! IDs, logging, security headers, a new session, CSRF, the request-scoped
+//! connection, ...). The difference is the per-request overhead. `csrf/*` is
+//! the token work behind every form and every state-changing request.
+//!
+//! ```bash
+//! cargo bench --bench stack
+//! ```
+
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use axum::{body::Body, extract::Request, routing::get, Router};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use tower::ServiceExt;
+
+use app::{
+    config::{AppConfig, DatabaseConfig},
+    db, middleware,
+    models::AppState,
+    services::{CsrfSecret, Services},
+};
+
+fn request() -> Request {
+    Request::get("/bench").body(Body::empty()).unwrap()
+}
+
+fn stack(c: &mut Criterion) {
+    // Multi-threaded: services block in place on the database
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let state = runtime.block_on(async {
+        let db = db::init_pool(&DatabaseConfig {
+            url: "sqlite::memory:".into(),
+            ..DatabaseConfig::default()
+        })
+        .await
+        .unwrap();
+        Arc::new(AppState::new(
+            Services::new_default(SystemTime::now()),
+            db,
+            AppConfig::default(),
+        ))
+    });
+    let router = Router::new()
+        .route("/bench", get(|| async { "ok" }))
+        .with_state(state.clone());
+    let stacked = middleware::stack(router.clone(), state);
+
+    let response = runtime
+        .block_on(stacked.clone().oneshot(request()))
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let mut group = c.benchmark_group("stack");
+    group.bench_function("router", |b| {
+        b.iter(|| runtime.block_on(router.clone().oneshot(request())).unwrap())
+    });
+    group.bench_function("full", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(stacked.clone().oneshot(request()))
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn csrf(c: &mut Criterion) {
+    let secret = CsrfSecret::new("k4Jv9QeT2mXw7LpZ0sRb8NcY5hGd3FuA");
+    let session_id = "3b5c8e0f-2a47-4d1e-9c6b-7f0a1d2e3c4b";
+    let token = secret.generate_token(session_id);
+    assert!(secret.validate_token(&token, session_id));
+
+    let mut group = c.benchmark_group("csrf");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("generate", |b| b.iter(|| secret.generate_token(session_id)));
+    group.bench_function("validate", |b| {
+        b.iter(|| secret.validate_token(&token, session_id))
+    });
+    group.bench_function("reject", |b| {
+        b.iter(|| secret.validate_token(&token, "another-session"))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, stack, csrf);
+criterion_main!(benches);
@@ -0,0 +1,218 @@
+//! Embeds build information for `utils::build_info`: the git commit, the
+//! build time and the enabled cargo features. It renders the favicon set for
+//! `utils::favicons`, see [`favicons`]. With the `tailwind` feature it also
+//! runs the standalone Tailwind CLI over the templates, see [`tailwind`].
+//!
+//! Outside a git checkout (Docker builds exclude `.git/`) the commit comes
+//! from `GIT_SHA`. `SOURCE_DATE_EPOCH` pins the build time for reproducible
+//! builds.
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use resvg::{tiny_skia, usvg};
+use sha2::{Digest, Sha256};
+
+/// The one source of every favicon
+const FAVICON_SOURCE: &str = "static/favicon.svg";
+
+/// Sizes rendered from it: browser tabs (16, 32), the `.ico` (16, 32, 48),
+/// iOS home screens (180) and the web app manifest (192, 512)
+const FAVICON_SIZES: &[u32] = &[16, 32, 48, 180, 192, 512];
+
+/// Sizes packed into `favicon.ico`
+const ICO_SIZES: &[u32] = &[16, 32, 48];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let git_sha = env::var("GIT_SHA")
+        .ok()
+        .filter(|sha| !sha.is_empty())
+        .or_else(git_head)
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=APP_GIT_SHA={git_sha}");
+
+    let epoch = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs())
+        });
+    println!("cargo:rustc-env=APP_BUILT_AT={}", rfc3339(epoch));
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(str::to_string))
+        .filter(|feature| feature != "DEFAULT")
+        .map(|feature| feature.to_lowercase().replace('_', "-"))
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=APP_FEATURES={}", features.join(","));
+
+    favicons();
+
+    if env::var_os("CARGO_FEATURE_TAILWIND").is_some() {
+        tailwind();
+    }
+}
+
+/// Compile `static/tailwind/input.css` with the standalone Tailwind CLI
+/// (`TAILWIND_BIN`, else `tailwindcss` on `PATH`) into
+/// `$OUT_DIR/tailwind.css`: only the classes `templates/` use, minified.
+/// `utils::css` embeds it and serves it under a fingerprinted name.
+fn tailwind() {
+    println!("cargo:rerun-if-changed=tailwind.config.js");
+    println!("cargo:rerun-if-changed=static/tailwind");
+    println!("cargo:rerun-if-changed=templates");
+    println!("cargo:rerun-if-env-changed=TAILWIND_BIN");
+
+    let bin = env::var("TAILWIND_BIN").unwrap_or_else(|_| "tailwindcss".to_string());
+    let out = format!("{}/tailwind.css", env::var("OUT_DIR").unwrap());
+    let status = Command::new(&bin)
+        .args(["--config", "tailwind.config.js"])
+        .args(["--input", "static/tailwind/input.css"])
+        .args(["--output", &out])
+        .arg("--minify")
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => panic!("`{bin}` failed ({status}); see its output above"),
+        Err(e) => panic!(
+            "the `tailwind` feature needs the standalone Tailwind CLI (v3): `{bin}` \
+             couldn't be run ({e}). Download it from \
+             https://github.com/tailwindlabs/tailwindcss/releases and put it on PATH \
+             or point TAILWIND_BIN at it."
+        ),
+    }
+}
+
+/// Render [`FAVICON_SOURCE`] at [`FAVICON_SIZES`] into
+/// `$OUT_DIR/favicons/icon-<size>.png`, pack the [`ICO_SIZES`] into
+/// `favicon.ico`, and write `$OUT_DIR/favicons.rs`: the table
+/// `utils::favicons` embeds, with each file's fingerprinted name (first 8 hex
+/// digits of its SHA-256). The source SVG is part of the set.
+fn favicons() {
+    println!("cargo:rerun-if-changed={FAVICON_SOURCE}");
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let dir = out_dir.join("favicons");
+    fs::create_dir_all(&dir).unwrap();
+
+    let svg = fs::read(FAVICON_SOURCE).unwrap_or_else(|e| panic!("reading {FAVICON_SOURCE}: {e}"));
+    let tree = usvg::Tree::from_data(&svg, &usvg::Options::default())
+        .unwrap_or_else(|e| panic!("{FAVICON_SOURCE}: {e}"));
+
+    let mut files = vec![("favicon.svg".to_string(), 0, "image/svg+xml", svg)];
+    for &size in FAVICON_SIZES {
+        let mut pixmap = tiny_skia::Pixmap::new(size, size).unwrap();
+        let transform = tiny_skia::Transform::from_scale(
+            size as f32 / tree.size().width(),
+            size as f32 / tree.size().height(),
+        );
+        resvg::render(&tree, transform, &mut pixmap.as_mut());
+        let png = pixmap.encode_png().unwrap();
+        files.push((format!("icon-{size}.png"), size, "image/png", png));
+    }
+    let ico_images: Vec<(u32, &[u8])> = ICO_SIZES
+        .iter()
+        .map(|&size| {
+            let (.., png) = files.iter().find(|(_, s, ..)| *s == size).unwrap();
+            (size, png.as_slice())
+        })
+        .collect();
+    let ico = ico(&ico_images);
+    files.push(("favicon.ico".to_string(), 0, "image/x-icon", ico));
+
+    let mut table = String::from("&[\n");
+    for (name, size, content_type, bytes) in &files {
+        fs::write(dir.join(name), bytes).unwrap();
+        let hash: String = Sha256::digest(bytes)[..4]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        let (stem, extension) = name.rsplit_once('.').unwrap();
+        writeln!(
+            table,
+            "    Favicon {{ name: {name:?}, file_name: \"{stem}.{hash}.{extension}\", size: {size}, \
+             content_type: {content_type:?}, bytes: include_bytes!(concat!(env!(\"OUT_DIR\"), \"/favicons/{name}\")) }},"
+        )
+        .unwrap();
+    }
+    table.push(']');
+    fs::write(out_dir.join("favicons.rs"), table).unwrap();
+}
+
+/// An `.ico` holding `images` (size, PNG) as they are; every browser that
+/// still asks for `favicon.ico` reads PNG entries
+fn ico(images: &[(u32, &[u8])]) -> Vec<u8> {
+    let mut ico = Vec::new();
+    // ICONDIR: reserved, type 1 (icon), image count
+    ico.extend_from_slice(&0u16.to_le_bytes());
+    ico.extend_from_slice(&1u16.to_le_bytes());
+    ico.extend_from_slice(&(images.len() as u16).to_le_bytes());
+    // ICONDIRENTRY per image, then the images in the same order
+    let mut offset = 6 + 16 * images.len();
+    for &(size, png) in images {
+        // 0 stands for 256
+        let side = if size >= 256 { 0 } else { size as u8 };
+        ico.extend_from_slice(&[side, side, 0, 0]);
+        ico.extend_from_slice(&1u16.to_le_bytes()); // color planes
+        ico.extend_from_slice(&32u16.to_le_bytes()); // bits per pixel
+        ico.extend_from_slice(&(png.len() as u32).to_le_bytes());
+        ico.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += png.len();
+    }
+    for &(_, png) in images {
+        ico.extend_from_slice(png);
+    }
+    ico
+}
+
+/// `HEAD`'s commit, with `-dirty` when there are uncommitted changes
+fn git_head() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let sha = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    let dirty = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .output()
+        .is_ok_and(|status| !status.stdout.is_empty());
+    Some(if dirty { format!("{sha}-dirty") } else { sha })
+}
+
+/// `2026-03-14T09:30:00Z` for seconds since the epoch (no chrono in build
+/// scripts; days → civil date after Howard Hinnant's algorithm)
+fn rfc3339(epoch: u64) -> String {
+    let days = (epoch / 86_400) as i64;
+    let secs = epoch % 86_400;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
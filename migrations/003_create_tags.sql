@@ -0,0 +1,22 @@
+-- Item tags — names normalized to [a-z0-9-] by the application
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS item_tags (
+    item_id INTEGER NOT NULL REFERENCES items(id) ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (item_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_item_tags_tag ON item_tags (tag_id);
+
+-- Tag the seeded items like the in-memory service does
+INSERT OR IGNORE INTO tags (name) VALUES ('backend'), ('database'), ('ops'), ('setup');
+
+INSERT OR IGNORE INTO item_tags (item_id, tag_id)
+SELECT i.id, t.id FROM items i JOIN tags t ON
+    (i.title = 'Set up project' AND t.name IN ('backend', 'setup')) OR
+    (i.title = 'Add database' AND t.name IN ('backend', 'database')) OR
+    (i.title = 'Deploy' AND t.name = 'ops');
@@ -0,0 +1,30 @@
+-- Blog posts — Markdown bodies, rendered and sanitized at display time
+CREATE TABLE IF NOT EXISTS posts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    slug TEXT NOT NULL UNIQUE,
+    title TEXT NOT NULL,
+    summary TEXT NOT NULL DEFAULT '',
+    body TEXT NOT NULL DEFAULT '',
+    -- 'draft' or 'published'
+    status TEXT NOT NULL DEFAULT 'draft',
+    -- RFC 3339 UTC timestamps
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    published_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_posts_published ON posts (status, published_at);
+
+-- Seed with the same welcome post the in-memory service uses
+INSERT INTO posts (slug, title, summary, body, status, created_at, updated_at, published_at) VALUES (
+    'hello-world',
+    'Hello, World',
+    'A first post to show the blog end to end.',
+    'Posts are written in **Markdown** and sanitized before they''re rendered, so editors get formatting without getting a script tag through.
+
+Sign in at `/admin` to write your own — drafts stay private until you publish them.',
+    'published',
+    '2024-01-01T00:00:00Z',
+    '2024-01-01T00:00:00Z',
+    '2024-01-01T00:00:00Z'
+);
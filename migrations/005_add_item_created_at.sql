@@ -0,0 +1,3 @@
+-- Creation time for items, used by the items Atom feed.
+-- Existing rows get a fixed timestamp; new rows are stamped by the service.
+ALTER TABLE items ADD COLUMN created_at TEXT NOT NULL DEFAULT '2024-01-01T00:00:00Z';
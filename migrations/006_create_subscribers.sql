@@ -0,0 +1,11 @@
+-- Newsletter subscribers — double opt-in, see services::subscribers
+CREATE TABLE IF NOT EXISTS subscribers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- Trimmed and lowercased by the application
+    email TEXT NOT NULL UNIQUE,
+    -- 'pending', 'confirmed' or 'unsubscribed'
+    status TEXT NOT NULL DEFAULT 'pending',
+    -- RFC 3339 UTC timestamps
+    created_at TEXT NOT NULL,
+    confirmed_at TEXT
+);
@@ -0,0 +1,16 @@
+-- Incoming webhook deliveries, see services::webhook_events
+CREATE TABLE IF NOT EXISTS webhook_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- Route segment, e.g. 'github'
+    provider TEXT NOT NULL,
+    -- The provider's delivery id; redeliveries reuse it
+    event_id TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    -- Raw request body, as signed
+    payload TEXT NOT NULL,
+    -- 'received', 'processed' or 'failed'
+    status TEXT NOT NULL DEFAULT 'received',
+    -- RFC 3339 UTC timestamp
+    received_at TEXT NOT NULL,
+    UNIQUE (provider, event_id)
+);
@@ -0,0 +1,26 @@
+-- Calendar events, see services::calendar
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    location TEXT NOT NULL DEFAULT '',
+    -- UTC, 'YYYY-MM-DDTHH:MM:SSZ'; the end is exclusive
+    starts_at TEXT NOT NULL,
+    ends_at TEXT NOT NULL,
+    all_day INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS idx_events_starts_at ON events (starts_at);
+
+-- Seed with the same examples the in-memory service uses, in the month the
+-- database is created
+INSERT INTO events (title, description, location, starts_at, ends_at, all_day) VALUES
+    ('Sprint planning', 'Pick the next two weeks of work', 'Room 2',
+     strftime('%Y-%m-%dT09:00:00Z', 'now', 'start of month', '+2 days'),
+     strftime('%Y-%m-%dT10:00:00Z', 'now', 'start of month', '+2 days'), 0),
+    ('Release day', 'Tag, build and deploy', '',
+     strftime('%Y-%m-%dT00:00:00Z', 'now', 'start of month', '+14 days'),
+     strftime('%Y-%m-%dT00:00:00Z', 'now', 'start of month', '+15 days'), 1),
+    ('Team offsite', 'Two days away from the screen', 'Lake house',
+     strftime('%Y-%m-%dT00:00:00Z', 'now', 'start of month', '+20 days'),
+     strftime('%Y-%m-%dT00:00:00Z', 'now', 'start of month', '+22 days'), 1);
@@ -0,0 +1,14 @@
+-- In-app notifications, see services::notifications
+CREATE TABLE IF NOT EXISTS notifications (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- SHA-256 of the recipient session's ID (comments::owner_key)
+    recipient TEXT NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL DEFAULT '',
+    -- Where the notification leads; empty for none
+    link TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    read_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_recipient ON notifications (recipient, read_at);
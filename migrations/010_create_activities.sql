@@ -0,0 +1,13 @@
+-- Activity feed of writes and sign-ins, see services::activities
+CREATE TABLE IF NOT EXISTS activities (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- `<subject>.<verb>`, e.g. 'comment.created'
+    kind TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    -- Where the subject can be seen; empty for none
+    link TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_activities_kind ON activities (kind);
@@ -0,0 +1,10 @@
+-- Last time each session was seen, flushed from services::presence
+CREATE TABLE IF NOT EXISTS presence (
+    -- Session key, see services::comments::owner_key
+    key TEXT PRIMARY KEY,
+    -- Display name; empty for guests
+    name TEXT NOT NULL DEFAULT '',
+    last_seen TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_presence_last_seen ON presence (last_seen);
@@ -0,0 +1,7 @@
+-- Tenant scoping, see services::tenants. Existing rows belong to the
+-- default tenant.
+ALTER TABLE items ADD COLUMN tenant TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE comments ADD COLUMN tenant TEXT NOT NULL DEFAULT 'default';
+
+CREATE INDEX IF NOT EXISTS idx_items_tenant ON items (tenant);
+CREATE INDEX IF NOT EXISTS idx_comments_tenant ON comments (tenant);
@@ -0,0 +1,7 @@
+-- Manual ordering for items, see services::items::reposition. Positions are
+-- spaced 1024 apart so a move usually rewrites only the moved row; existing
+-- rows keep their creation order.
+ALTER TABLE items ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+UPDATE items SET position = id * 1024;
+
+CREATE INDEX IF NOT EXISTS idx_items_tenant_position ON items (tenant, position);
@@ -0,0 +1,26 @@
+-- Invoices issued by cart checkout, see services::invoices
+CREATE TABLE IF NOT EXISTS invoices (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- SHA-256 of the buyer session's ID (comments::owner_key)
+    owner TEXT NOT NULL,
+    -- Address lines, newline-separated
+    bill_to TEXT NOT NULL,
+    -- YYYY-MM-DD
+    issued TEXT NOT NULL,
+    due TEXT NOT NULL,
+    -- Percent, as it was when the invoice was issued
+    tax_rate INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_invoices_owner ON invoices (owner);
+
+-- Prices are copied in, so an invoice never changes with the catalogue
+CREATE TABLE IF NOT EXISTS invoice_lines (
+    invoice_id INTEGER NOT NULL REFERENCES invoices(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    quantity INTEGER NOT NULL,
+    unit_cents INTEGER NOT NULL,
+    PRIMARY KEY (invoice_id, position)
+);
@@ -0,0 +1,27 @@
+-- Item slugs and the slug history, see services::slugs
+CREATE TABLE IF NOT EXISTS slug_history (
+    -- 'item' or 'post'
+    kind TEXT NOT NULL,
+    -- Where the slugs are unique: the tenant for items, '' for posts
+    scope TEXT NOT NULL,
+    -- A slug the record used to have
+    slug TEXT NOT NULL,
+    target_id INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (kind, scope, slug)
+);
+
+CREATE INDEX IF NOT EXISTS idx_slug_history_target ON slug_history (kind, scope, target_id);
+
+-- Existing items get a placeholder slug, replaced when they're renamed; the
+-- seeded ones get the slugs the in-memory service gives them
+ALTER TABLE items ADD COLUMN slug TEXT NOT NULL DEFAULT '';
+UPDATE items SET slug = 'item-' || id;
+UPDATE items SET slug = CASE title
+    WHEN 'Set up project' THEN 'set-up-project'
+    WHEN 'Add database' THEN 'add-database'
+    WHEN 'Deploy' THEN 'deploy'
+    ELSE slug END
+WHERE id <= 3 AND tenant = 'default';
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_items_tenant_slug ON items (tenant, slug);
@@ -0,0 +1,11 @@
+-- Redirect rules managed at /admin/redirects, see services::redirects
+CREATE TABLE IF NOT EXISTS redirects (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- A path without query, matched exactly after URL normalization
+    from_path TEXT NOT NULL UNIQUE,
+    -- A local path or an absolute http(s) URL
+    target TEXT NOT NULL,
+    -- 301 or 302
+    status INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
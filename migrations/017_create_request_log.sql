@@ -0,0 +1,14 @@
+-- Requests as services::request_log records them, for /admin/requests. No
+-- IPs, query strings or headers.
+CREATE TABLE IF NOT EXISTS request_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- YYYY-MM-DD HH:MM:SS, UTC
+    created_at TEXT NOT NULL,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    duration_us INTEGER NOT NULL,
+    request_id TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_request_log_created_at ON request_log (created_at);
@@ -0,0 +1,18 @@
+-- Personal access tokens managed at /admin/tokens, see services::api_tokens.
+-- Only a digest of each token is kept; the token itself is shown once.
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    -- Space-separated: exports, feeds
+    scopes TEXT NOT NULL,
+    -- Hex SHA-256 of the whole token
+    token_hash TEXT NOT NULL UNIQUE,
+    -- The token's first characters, to tell tokens apart in the list
+    prefix TEXT NOT NULL,
+    -- YYYY-MM-DD HH:MM:SS, UTC
+    created_at TEXT NOT NULL,
+    -- Empty until first used
+    last_used_at TEXT NOT NULL DEFAULT '',
+    -- Empty until revoked
+    revoked_at TEXT NOT NULL DEFAULT ''
+);
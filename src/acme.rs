@@ -0,0 +1,85 @@
+//! ACME — the TLS certificate from Let's Encrypt, fetched and renewed
+//!
+//! With the `acme` feature and `[server.acme] domains` set, the app orders
+//! its own certificate and needs neither `tls_cert` nor a proxy in front. The
+//! CA proves each domain points here by TLS-ALPN-01: it connects to port 443
+//! and asks for the `acme-tls/1` protocol, which [`crate::server`] answers
+//! with [`Certificates::challenge`] instead of serving the app.
+//!
+//! [`start`] spawns the background task that does the ordering: it loads the
+//! account key and certificate from `cache_dir`, orders a certificate when
+//! there's none or it's close to expiring (30 days ahead), stores it, and
+//! sleeps until the next renewal. Until the first certificate arrives, TLS
+//! handshakes fail; failed orders are retried with growing backoff.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use rustls_acme::caches::DirCache;
+use tokio_rustls::rustls::{self, crypto::CryptoProvider, ServerConfig};
+use tokio_stream::StreamExt;
+
+use crate::config::AcmeConfig;
+
+/// The TLS configs for `[server.acme]`, both served from the certificate
+/// the background task keeps current
+pub struct Certificates {
+    /// For connections to the app
+    pub config: Arc<ServerConfig>,
+    /// For the CA's TLS-ALPN-01 challenge connections
+    pub challenge: Arc<ServerConfig>,
+}
+
+/// Start ordering and renewing the certificate for `config.domains`.
+/// `alpn` is what [`Certificates::config`] offers. Needs a tokio runtime.
+pub fn start(
+    config: &AcmeConfig,
+    provider: Arc<CryptoProvider>,
+    alpn: Vec<Vec<u8>>,
+) -> std::io::Result<Certificates> {
+    private_dir(Path::new(&config.cache_dir))?;
+    let mut state = rustls_acme::AcmeConfig::new_with_provider(&config.domains, provider.clone())
+        .contact(config.contact.iter().map(|email| format!("mailto:{email}")))
+        .cache(DirCache::new(config.cache_dir.clone()))
+        .directory_lets_encrypt(!config.staging)
+        .state();
+
+    let mut tls = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| std::io::Error::other(format!("TLS: {e}")))?
+        .with_no_client_auth()
+        .with_cert_resolver(state.resolver());
+    tls.alpn_protocols = alpn;
+    let certificates = Certificates {
+        config: Arc::new(tls),
+        challenge: state.challenge_rustls_config_with_provider(provider),
+    };
+
+    let domains = config.domains.join(",");
+    tokio::spawn(async move {
+        while let Some(event) = state.next().await {
+            match event {
+                Ok(event) => tracing::info!(domains, ?event, "ACME"),
+                Err(e) => tracing::warn!(domains, error = %e, "ACME certificate order failed"),
+            }
+        }
+    });
+    Ok(certificates)
+}
+
+/// `dir`, created if need be, readable by the app's user only: it holds the
+/// account and certificate private keys
+fn private_dir(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// Whether a handshake is the CA's TLS-ALPN-01 challenge
+pub fn is_challenge(hello: &rustls::server::ClientHello<'_>) -> bool {
+    rustls_acme::is_tls_alpn_challenge(hello)
+}
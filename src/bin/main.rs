@@ -1,96 +1,354 @@
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
-use axum::{middleware, routing::get, Router};
-use tower::ServiceBuilder;
-use tower_http::{services::ServeDir, trace::TraceLayer};
 use tracing::info;
 
 use app::{
     config::AppConfig,
-    db,
-    handlers::{partials, templates},
-    middleware as mw,
+    db, middleware as mw,
     models::AppState,
-    services::Services,
-    utils::logging,
+    server::{Listener, ListenerInfo},
+    services::{
+        avatars::Identicons,
+        backups,
+        breadcrumbs::RouteBreadcrumbs,
+        errors, live_config, mail,
+        og_image::ResvgOgImages,
+        replication::{self, Replication},
+        stats,
+        webhooks_out::WebhookPublisher,
+        AdminAuth, Backups, CsrfSecret, InMemorySessionStore, LiveConfig, LogLevel, Payments,
+        RequestLog, Services, Tenants, TokenSigner,
+    },
+    utils::{assets, logging},
 };
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Load config
-    let config = AppConfig::load().unwrap_or_else(|e| {
-        eprintln!("Config error: {}, using defaults", e);
-        AppConfig::default()
-    });
+    // Maintenance subcommands run instead of the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return run_command(&args).await;
+    }
+
+    // Load config — an invalid one stops startup with every problem listed
+    let config = match AppConfig::load() {
+        Ok(config) => config,
+        Err(errors) => {
+            eprintln!("{errors}");
+            std::process::exit(1);
+        }
+    };
 
     // Init logging
-    logging::init_logging(&config.logging.level)?;
+    let log_filter = logging::init_logging(&config.logging.level)?;
+
+    info!(
+        "Starting axum-htmx-app v{} ({})",
+        env!("CARGO_PKG_VERSION"),
+        config.environment.environment
+    );
+
+    // Debug builds render templates from disk — fail fast if they've drifted
+    // from their define_page!/define_partial! declarations or from askama.
+    #[cfg(debug_assertions)]
+    if let Err(errors) = app::utils::templates::check_template_parity() {
+        for error in &errors {
+            tracing::error!("{}", error);
+        }
+        return Err(format!("{} template parity error(s), see above", errors.len()).into());
+    }
 
-    info!("Starting axum-htmx-app v{}", env!("CARGO_PKG_VERSION"));
+    // One minified, fingerprinted stylesheet from static/css — a stylesheet
+    // lightningcss can't parse stops startup, like a broken template
+    let css_bundle = app::utils::css::install().map_err(|e| format!("CSS bundle: {e:#}"))?;
+    info!("CSS bundle {}", css_bundle.href());
 
-    // Initialize database pool and run migrations
-    let db = db::init_pool(&config.database.url)
-        .await
-        .expect("Failed to initialize database");
+    // A missing database comes back from the replica first, if configured
+    let replication = Arc::new(Replication::new(&config.replication, &config.database));
+    replication.restore_if_missing().await?;
+
+    // Initialize database pool and run migrations. An unreachable database
+    // degrades the app instead of stopping it; db::monitor reconnects.
+    let (db, db_health) = match db::connect(&config.database).await {
+        Ok(connected) => connected,
+        Err(e) => match app::schema::as_drift(&e) {
+            Some(drift) => {
+                eprintln!("{drift}");
+                std::process::exit(1);
+            }
+            None => return Err(e.into()),
+        },
+    };
+    db::monitor(db.clone(), db_health.clone());
+    if replication.is_enabled() && config.replication.checkpoint_interval_secs > 0 {
+        replication::schedule(
+            replication.clone(),
+            db.clone(),
+            db_health.clone(),
+            Duration::from_secs(config.replication.checkpoint_interval_secs),
+        );
+    }
 
     // Initialize services (includes CSRF secret + session store)
-    let services = Services::new_with_db(SystemTime::now(), db.clone());
+    let mut services = Services::new_with_db(SystemTime::now(), db.clone());
+    services.db_health = db_health;
+    services.breadcrumbs = Arc::new(RouteBreadcrumbs::new(&config.breadcrumbs.labels));
+
+    let og_cache = (!config.og.cache_dir.is_empty()).then(|| config.og.cache_dir.clone().into());
+    services.og_images = Arc::new(ResvgOgImages::new(&config.site.title, og_cache));
+    let avatar_cache =
+        (!config.avatars.cache_dir.is_empty()).then(|| config.avatars.cache_dir.clone().into());
+    services.avatars = Arc::new(Identicons::new(avatar_cache));
+    services.mail = match mail::from_config(&config.mail) {
+        Ok(mailer) => Arc::from(mailer),
+        Err(e) => {
+            tracing::error!(error = %e, "Mail transport unavailable, logging messages instead");
+            Arc::new(mail::LogMailer)
+        }
+    };
+    services.errors = match errors::from_config(
+        &config.errors,
+        &config.environment.environment,
+        services.jobs.clone(),
+    ) {
+        Ok(reporter) => Arc::from(reporter),
+        Err(e) => {
+            tracing::error!(error = %e, "Error reporting unavailable, logging errors only");
+            Arc::new(errors::NoopReporter)
+        }
+    };
+    // Unlike mail there's no safe fallback: a provider that can't start
+    // mustn't be quietly swapped for one that takes test cards
+    let provider = app::services::payments::from_config(&config.payments)
+        .map_err(|e| format!("Payments: {e}"))?;
+    if provider.name() == "fake" && config.is_production() {
+        tracing::warn!("Payments use the fake provider: no money moves");
+    }
+    services.payments = Arc::new(Payments::new(provider));
+    if config.server.allowed_hosts.is_empty() && config.is_production() {
+        tracing::warn!(
+            "Any Host header is answered; list the site's hosts in [server] allowed_hosts"
+        );
+    }
+    services.events = Arc::new(WebhookPublisher::new(
+        config.webhooks.endpoints.clone(),
+        services.jobs.clone(),
+    ));
+    services.tokens = TokenSigner::new(&config.security.token_secret);
+    if config.security.token_secret.is_empty() {
+        tracing::warn!(
+            "No token secret — emailed links stop working on restart; set APP__SECURITY__TOKEN_SECRET"
+        );
+    }
+    // Release builds can't get here without one, see AppConfig::validate
+    services.csrf = CsrfSecret::new(&config.security.csrf_secret);
+    if config.security.csrf_secret.is_empty() {
+        tracing::warn!("No CSRF secret — forms break on restart; set APP__SECURITY__CSRF_SECRET");
+    }
+    services.sessions = Arc::new(InMemorySessionStore::with_max(config.sessions.max_sessions));
+    services.tenants = Arc::new(Tenants::new(&config.tenancy));
+    services.log_level = Arc::new(LogLevel::new(Some(log_filter), &config.logging.level));
+    let log_level = services.log_level.clone();
+    services.live = Arc::new(
+        LiveConfig::new(config.clone()).with_log_level(move |level| log_level.configure(level)),
+    );
+    services.admin = AdminAuth::new(&config.admin.password);
+    if !services.admin.is_enabled() {
+        tracing::info!("Admin pages disabled — set APP__ADMIN__PASSWORD to enable /admin");
+    }
+
+    let backup_dir = (!config.backups.dir.is_empty()).then(|| config.backups.dir.clone().into());
+    services.backups = Arc::new(Backups::new(backup_dir, config.backups.keep));
+    if services.backups.is_enabled() && config.backups.interval_hours > 0 {
+        backups::schedule(
+            services.backups.clone(),
+            db.clone(),
+            services.db_health.clone(),
+            Duration::from_secs(config.backups.interval_hours * 60 * 60),
+        );
+    }
+
+    if config.request_log.enabled {
+        services.request_log = Arc::new(RequestLog::start(db.clone(), &config.request_log));
+    }
 
     // Shared state with services
-    let state = Arc::new(AppState::new(services, db));
+    let state = Arc::new(AppState::new(services, db, config.clone()));
+
+    // Sample request and session counts for the status card charts
+    let sampler = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(stats::SAMPLE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let sessions = sampler.services.sessions.count();
+            sampler.services.stats.sample(sessions);
+        }
+    });
+
+    // Probe how long queries wait for a connection, and warn when it's long
+    let prober = state.clone();
+    let slow_acquire = Duration::from_millis(config.database.slow_acquire_ms);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(stats::PROBE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if prober.services.db_health.is_up() {
+                prober.services.stats.probe(&prober.db, slow_acquire).await;
+            }
+        }
+    });
+
+    // Reloadable settings follow SIGHUP and edits to config/
+    let _config_watcher = live_config::watch(
+        state.services.live.clone(),
+        std::path::Path::new(app::config::CONFIG_DIR),
+    );
+
+    // Presence lives in memory; restore it, then write changes back
+    // periodically (and once more on shutdown, below)
+    state.services.presence.load();
+    let flusher = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(app::services::presence::FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            flusher.services.presence.flush();
+        }
+    });
 
     // ── Routes ──────────────────────────────────────────────────────────
-    // No JSON API. No Swagger. No CORS.
-    // Every route returns HTML — full pages or HTMX partials.
-
-    // HTMX partial routes (HTML fragments)
-    let partial_routes = Router::new()
-        .route("/partials/status-card", get(partials::status_card))
-        .route("/partials/item-list", get(partials::item_list))
-        .route("/partials/greeting", get(partials::greeting));
-
-    // Health check (no middleware — used by Docker HEALTHCHECK)
-    let health_route = Router::new().route("/healthz", get(app::handlers::healthz));
-
-    // Page routes (full HTML)
-    let app = Router::new()
-        .route("/", get(templates::home_page))
-        .route("/about", get(templates::about_page))
-        .route("/demo", get(templates::demo_page))
-        .route("/components", get(templates::components_page))
-        .route("/security", get(templates::security_page))
-        .merge(partial_routes)
-        .merge(health_route)
-        // Static files (vendored CSS, JS, fonts — no external CDN)
-        .nest_service("/static", ServeDir::new("static"))
-        // Inject shared state into extensions for middleware access
-        .layer(axum::Extension(state.clone()))
-        .with_state(state.clone())
-        // ── Middleware (applied bottom-up) ───────────────────────────────
-        .layer(
-            ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
-                .layer(middleware::from_fn(mw::request_logger))
-                .layer(middleware::from_fn(mw::security_headers))
-                .layer(middleware::from_fn(mw::session_middleware))
-                .layer(middleware::from_fn(mw::csrf_protection)),
-        );
+    // Pages, partials, feeds and downloads, see routes::router
+    let app = app::routes::router(state.clone());
+
+    // Dev live-reload: SSE endpoint + dev-reload.js injected into full pages
+    #[cfg(debug_assertions)]
+    let live_reload = app::utils::live_reload::LiveReload::start();
+    #[cfg(debug_assertions)]
+    let app = app.map(|router| {
+        router
+            .merge(
+                axum::Router::new()
+                    .route(
+                        "/dev/reload",
+                        axum::routing::get(app::utils::live_reload::reload_events),
+                    )
+                    .with_state(live_reload.clone()),
+            )
+            .layer(axum::middleware::from_fn(
+                app::utils::live_reload::inject_script,
+            ))
+    });
+
+    // ── Middleware ──────────────────────────────────────────────────────
+    // Request IDs, logging, headers, sessions, CSRF, ... in the order they
+    // run, see middleware::stack
+    let app = mw::stack(app, state.clone());
 
     // ── Start ───────────────────────────────────────────────────────────
 
-    let addr = format!("{}:{}", config.server.host, config.server.port);
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    // systemd's socket when it passed one (socket activation), else our own
+    let main = match app::systemd::listener()? {
+        Some(listener) => tokio::net::TcpListener::from_std(listener)?,
+        None => {
+            let addr = format!("{}:{}", config.server.host, config.server.port);
+            tokio::net::TcpListener::bind(&addr).await?
+        }
+    };
+    let mut listeners = vec![(Listener::from(main), ListenerInfo::main(&config.server))];
+    // …and any [[server.listeners]]
+    for listener in &config.server.listeners {
+        let bound = Listener::bind(&listener.address)
+            .await
+            .map_err(|e| format!("{}: {e}", listener.address))?;
+        listeners.push((bound, ListenerInfo::from(listener)));
+    }
 
-    info!("Listening on http://{}", addr);
+    for (listener, info) in &listeners {
+        let url = match listener {
+            Listener::Tcp(_) if info.tls => format!("https://{listener}"),
+            Listener::Tcp(_) => format!("http://{listener}"),
+            #[cfg(unix)]
+            Listener::Unix(..) => listener.to_string(),
+        };
+        info!(
+            listener = %info.name,
+            metrics = info.metrics,
+            admin = info.admin,
+            "Listening on {url}"
+        );
+    }
     info!("Security: CSP + CSRF + HttpOnly sessions + SRI + no external deps");
+    if let Err(e) = app::systemd::notify(app::systemd::READY) {
+        tracing::warn!(error = %e, "Failed to notify systemd");
+    }
+
+    let notification_hub = state.services.notification_hub.clone();
+    let activity_hub = state.services.activity_hub.clone();
+    let item_hub = state.services.item_hub.clone();
+    app::server::serve(listeners, app, &config.server, async move {
+        shutdown_signal().await;
+        info!("Shutting down...");
+        let _ = app::systemd::notify(app::systemd::STOPPING);
+        notification_hub.shutdown();
+        activity_hub.shutdown();
+        item_hub.shutdown();
+        #[cfg(debug_assertions)]
+        live_reload.shutdown();
+    })
+    .await?;
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async {
-            tokio::signal::ctrl_c().await.ok();
-            info!("Shutting down...");
-        })
-        .await?;
+    state.services.presence.flush();
+    // Fold the WAL in while the replicator can still see it
+    let _ = replication.checkpoint(&state.db).await;
+
+    Ok(())
+}
+
+/// Ctrl-C, or SIGTERM from systemd, Docker or Kubernetes
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => tracing::warn!(error = %e, "Can't listen for SIGTERM"),
+        }
+    }
+    tokio::signal::ctrl_c().await.ok();
+}
 
+/// `app assets vendor [--pin]`: refresh the vendored JavaScript from
+/// `static/js/vendor.toml` (see `utils::assets`), run from the project root
+async fn run_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let pin = match args.as_slice() {
+        ["assets", "vendor"] => false,
+        ["assets", "vendor", "--pin"] => true,
+        _ => {
+            eprintln!("usage: app [assets vendor [--pin]]");
+            std::process::exit(2);
+        }
+    };
+    match tokio::task::spawn_blocking(move || assets::vendor(std::path::Path::new("."), pin))
+        .await?
+    {
+        Ok(report) => {
+            for line in report {
+                println!("{line}");
+            }
+        }
+        Err(e) => {
+            eprintln!("assets vendor: {e:#}");
+            std::process::exit(1);
+        }
+    }
     Ok(())
 }
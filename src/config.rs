@@ -1,18 +1,255 @@
+//! Application configuration
+//!
+//! Layered, later sources winning: `config/default.toml`, then the profile
+//! named by `APP_ENV` (`config/{APP_ENV}.toml`, `development` when unset), then
+//! `APP__`-prefixed environment variables (`APP__SERVER__PORT=9000`). The
+//! result is checked by [`AppConfig::validate`], which reports every problem
+//! at once.
+//!
+//! Any setting can be read from a file instead, for Docker and Kubernetes
+//! secrets: `<key>_file` names the file and its contents become `<key>`, e.g.
+//! `APP__DATABASE__URL_FILE=/run/secrets/db_url` sets `database.url`.
+
+use config::Source;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::services::rate_limit::Limit;
+use crate::services::tenants;
+use crate::utils::cookies::SameSite;
 
-/// Application configuration loaded from config/app.toml + env vars
+/// Directory holding `default.toml` and the profiles
+pub const CONFIG_DIR: &str = "config";
+/// Profile used when `APP_ENV` is unset
+pub const DEFAULT_PROFILE: &str = "development";
+/// `<key>_file` reads `<key>` from the named file
+pub const FILE_SUFFIX: &str = "_file";
+/// Shortest CSRF secret accepted
+pub const MIN_SECRET_LEN: usize = 32;
+
+/// Application configuration, see the module docs for where it comes from
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub logging: LoggingConfig,
     pub environment: EnvironmentConfig,
     pub database: DatabaseConfig,
+    #[serde(default)]
+    pub breadcrumbs: BreadcrumbsConfig,
+    #[serde(default)]
+    pub site: SiteConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub robots: RobotsConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
+    #[serde(default)]
+    pub sessions: SessionsConfig,
+    #[serde(default)]
+    pub og: OgConfig,
+    #[serde(default)]
+    pub avatars: AvatarConfig,
+    /// Header policy per kind of static file (`[static_headers.fonts]`), see
+    /// [`crate::middleware::static_headers`]
+    #[serde(default)]
+    pub static_headers: BTreeMap<String, StaticHeadersConfig>,
+    /// Widgets other sites may frame, see [`crate::handlers::embed`]
+    #[serde(default)]
+    pub embeds: EmbedsConfig,
+    #[serde(default)]
+    pub backups: BackupsConfig,
+    #[serde(default)]
+    pub replication: ReplicationConfig,
+    #[serde(default)]
+    pub request_log: RequestLogConfig,
+    #[serde(default)]
+    pub mail: MailConfig,
+    #[serde(default)]
+    pub errors: ErrorsConfig,
+    #[serde(default)]
+    pub webhooks: WebhooksConfig,
+    #[serde(default)]
+    pub payments: PaymentsConfig,
+    #[serde(default)]
+    pub tenancy: TenancyConfig,
+    #[serde(default)]
+    pub urls: UrlsConfig,
+    #[serde(default)]
+    pub rate_limits: RateLimitsConfig,
+    #[serde(default)]
+    pub latency: LatencyConfig,
+    /// Feature flag → on/off; unknown flags are off, see
+    /// [`LiveConfig::feature`](crate::services::live_config::LiveConfig::feature)
+    #[serde(default)]
+    pub features: BTreeMap<String, bool>,
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    #[serde(default)]
+    pub announcement: AnnouncementConfig,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Believe `X-Forwarded-Proto` / `Forwarded: proto=`: set only when a
+    /// TLS-terminating proxy sits in front and sets (or strips) them
+    #[serde(default)]
+    pub trust_proxy: bool,
+    /// `Host` headers answered, see [`crate::utils::hosts`]; empty answers
+    /// any. Subdomain tenancy adds its base domain and everything under it.
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// PEM certificate chain; with `tls_key`, the app speaks HTTPS itself.
+    /// Empty serves plain HTTP, for a TLS-terminating proxy in front.
+    #[serde(default)]
+    pub tls_cert: String,
+    /// PEM private key for `tls_cert`
+    #[serde(default)]
+    pub tls_key: String,
+    /// Protocols, keep-alive and limits, see [`crate::server`]
+    #[serde(default)]
+    pub connections: ConnectionsConfig,
+    /// More addresses to serve on, next to `host:port`
+    #[serde(default)]
+    pub listeners: Vec<ListenerConfig>,
+    /// Certificates from Let's Encrypt instead of `tls_cert` (`acme`
+    /// feature)
+    #[serde(default)]
+    pub acme: AcmeConfig,
+}
+
+/// One `[[server.listeners]]`: an address to serve on besides `[server]
+/// host:port`, and what it serves differently, see [`crate::server`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ListenerConfig {
+    /// `host:port`, or `unix:<path>` for a Unix socket
+    pub address: String,
+    /// In logs; the address when empty
+    pub name: String,
+    /// Serve `/metrics` here; no other listener does
+    pub metrics: bool,
+    /// Serve the admin pages here
+    pub admin: bool,
+    /// Speak HTTPS with `[server] tls_cert` or ACME's certificate (TCP only)
+    pub tls: bool,
+}
+
+impl Default for ListenerConfig {
+    fn default() -> Self {
+        Self {
+            address: String::new(),
+            name: String::new(),
+            metrics: false,
+            admin: true,
+            tls: false,
+        }
+    }
+}
+
+/// `[server.acme]`: the TLS certificate from an ACME CA (Let's Encrypt),
+/// fetched and renewed by the app; needs the `acme` feature
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AcmeConfig {
+    /// Names on the certificate; empty turns ACME off. The CA checks each by
+    /// connecting to it on port 443 (TLS-ALPN-01), so no wildcards.
+    pub domains: Vec<String>,
+    /// Email addresses the CA may write to about the certificates
+    pub contact: Vec<String>,
+    /// Where the account key and certificate are kept across restarts
+    pub cache_dir: String,
+    /// Let's Encrypt's staging CA: untrusted certificates, generous rate
+    /// limits; for trying a setup out
+    pub staging: bool,
+}
+
+impl Default for AcmeConfig {
+    fn default() -> Self {
+        Self {
+            domains: Vec::new(),
+            contact: Vec::new(),
+            cache_dir: "data/acme".to_string(),
+            staging: false,
+        }
+    }
+}
+
+impl AcmeConfig {
+    pub fn enabled(&self) -> bool {
+        !self.domains.is_empty()
+    }
+}
+
+impl ServerConfig {
+    /// Whether the app terminates TLS itself: with `tls_cert` and `tls_key`,
+    /// or ACME's certificate
+    pub fn tls(&self) -> bool {
+        (!self.tls_cert.is_empty() && !self.tls_key.is_empty()) || self.acme.enabled()
+    }
+}
+
+/// `[server.connections]`: how connections are spoken to, see
+/// [`crate::server`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConnectionsConfig {
+    /// Accept HTTP/2: by prior knowledge over plain HTTP (h2c, as a proxy
+    /// speaks it), by ALPN over TLS. `false` speaks HTTP/1.1 only.
+    pub http2: bool,
+    /// Keep HTTP/1.1 connections open between requests
+    pub keep_alive: bool,
+    /// How long a client has to send a request's headers, waiting on an idle
+    /// keep-alive connection included; 0 waits forever
+    pub header_read_timeout_ms: u64,
+    /// Largest request header block: HTTP/1.1's read buffer (8192 at least),
+    /// HTTP/2's header list
+    pub max_header_bytes: usize,
+    /// HTTP/2 requests in flight on one connection
+    pub max_concurrent_streams: u32,
+    /// Seconds between HTTP/2 pings on a connection; 0 sends none
+    pub keep_alive_interval_secs: u64,
+    /// Seconds to wait for a ping's answer before closing the connection
+    pub keep_alive_timeout_secs: u64,
+}
+
+impl Default for ConnectionsConfig {
+    fn default() -> Self {
+        Self {
+            http2: true,
+            keep_alive: true,
+            header_read_timeout_ms: 30_000,
+            max_header_bytes: 64 * 1024,
+            max_concurrent_streams: 200,
+            keep_alive_interval_secs: 0,
+            keep_alive_timeout_secs: 20,
+        }
+    }
+}
+
+impl AppConfig {
+    /// `[server] allowed_hosts`, plus the tenant domains in subdomain mode
+    /// and the admin host; empty if any host goes
+    pub fn allowed_hosts(&self) -> Vec<String> {
+        let mut hosts = self.server.allowed_hosts.clone();
+        let base_domain = &self.tenancy.base_domain;
+        if !hosts.is_empty()
+            && self.tenancy.mode == TenancyMode::Subdomain
+            && !base_domain.is_empty()
+        {
+            hosts.push(base_domain.clone());
+            hosts.push(format!("*.{base_domain}"));
+        }
+        if !hosts.is_empty() && !self.admin.host.is_empty() {
+            hosts.push(self.admin.host.clone());
+        }
+        hosts
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -26,9 +263,727 @@ pub struct EnvironmentConfig {
     pub log_level: String,
 }
 
+/// SQLite connection and pragmas, applied to every connection by
+/// [`db::init_pool`](crate::db::init_pool)
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct DatabaseConfig {
     pub url: String,
+    /// `wal`, `delete`, `truncate`, `persist`, `memory` or `off`. WAL lets
+    /// reads run while a write is in progress.
+    pub journal_mode: String,
+    /// `off`, `normal`, `full` or `extra`; `normal` is safe with WAL
+    pub synchronous: String,
+    /// How long a connection waits for a lock before `SQLITE_BUSY`
+    pub busy_timeout_ms: u64,
+    /// Page cache per connection: positive counts pages, negative KiB
+    pub cache_size: i64,
+    pub foreign_keys: bool,
+    /// Connections for reads; writes always share a single connection
+    pub max_read_connections: u32,
+    /// WAL pages before SQLite checkpoints on its own; 0 leaves checkpoints
+    /// to [`replication`](crate::services::replication)
+    pub wal_autocheckpoint: u32,
+    /// Waiting longer than this for a connection logs a warning, see
+    /// [`Stats::probe`](crate::services::stats::Stats::probe)
+    pub slow_acquire_ms: u64,
+    /// How long polled reads are cached, see [`query_cache`](crate::query_cache);
+    /// 0 disables the cache
+    pub query_cache_ttl_ms: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: "sqlite://data.db?mode=rwc".to_string(),
+            journal_mode: "wal".to_string(),
+            synchronous: "normal".to_string(),
+            busy_timeout_ms: 5000,
+            cache_size: -20_000,
+            foreign_keys: true,
+            max_read_connections: 4,
+            wal_autocheckpoint: 1000,
+            slow_acquire_ms: 250,
+            query_cache_ttl_ms: 2000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BreadcrumbsConfig {
+    /// Route path → breadcrumb label, overriding the nav label or slug
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SiteConfig {
+    /// Site name, used where a page title isn't enough (feeds)
+    pub title: String,
+    /// Public origin without a trailing slash, e.g. `https://example.com`.
+    /// Used where URLs have to be absolute: feeds, emails, and the canonical
+    /// and Open Graph tags in page heads — links within pages stay relative.
+    pub base_url: String,
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        Self {
+            title: "Axum HTMX App".to_string(),
+            base_url: "http://localhost:8000".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AdminConfig {
+    /// Password for `/admin`. Empty disables the admin pages entirely — set
+    /// it through `APP__ADMIN__PASSWORD` rather than committing it.
+    #[serde(default)]
+    pub password: String,
+    /// Host the admin pages are served on, e.g. `admin.example.com`; other
+    /// hosts answer `/admin` with a 404. Empty serves them on every host.
+    #[serde(default)]
+    pub host: String,
+    /// `true` answers the feeds and `/items/export.csv` with a 401 unless the
+    /// request has an API token for them or comes from a signed-in admin
+    /// (tokens are managed at `/admin/tokens`)
+    #[serde(default)]
+    pub private_feeds: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RobotsConfig {
+    /// `false` answers every crawler with `Disallow: /` (staging, private
+    /// deployments)
+    #[serde(default = "default_true")]
+    pub allow_indexing: bool,
+    /// Extra paths to disallow, on top of routes annotated with
+    /// `sitemap_route!(.., exclude)`
+    #[serde(default)]
+    pub disallow: Vec<String>,
+}
+
+impl Default for RobotsConfig {
+    fn default() -> Self {
+        Self {
+            allow_indexing: true,
+            disallow: Vec::new(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Session storage limits, see [`crate::services::session`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SessionsConfig {
+    /// Sessions kept before the least recently used are evicted
+    pub max_sessions: usize,
+    /// Store a new visitor's session only once the cookie comes back or
+    /// something is saved in it
+    pub lazy: bool,
+    /// Paths starting with one of these never get a session: no cookie, no
+    /// CSRF token, nothing stored
+    pub exempt: Vec<String>,
+    /// When the cookie gets `Secure` (HTTPS only)
+    pub secure: CookieSecure,
+    /// Name a `Secure` cookie `__Host-sid`, which browsers only accept from
+    /// HTTPS, for the whole host (`Path=/`, no `Domain`); `sid` otherwise
+    pub host_prefix: bool,
+    /// `strict`, `lax` (keeps the session when following a link in from
+    /// another site) or `none` (needs `secure = "always"`)
+    pub same_site: SameSite,
+}
+
+impl SessionsConfig {
+    pub fn is_exempt(&self, path: &str) -> bool {
+        self.exempt
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_str()))
+    }
+
+    /// Whether the cookie on a response to an `https` (or not) request is
+    /// `Secure`
+    pub fn cookie_secure(&self, https: bool) -> bool {
+        match self.secure {
+            CookieSecure::Auto => https,
+            CookieSecure::Always => true,
+            CookieSecure::Never => false,
+        }
+    }
+
+    /// The cookie's name, `Secure` or not
+    pub fn cookie_name(&self, secure: bool) -> &'static str {
+        if secure && self.host_prefix {
+            crate::services::session::SESSION_COOKIE
+        } else {
+            crate::services::session::INSECURE_SESSION_COOKIE
+        }
+    }
+}
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self {
+            max_sessions: crate::services::session::DEFAULT_MAX_SESSIONS,
+            lazy: false,
+            exempt: [
+                "/static/",
+                "/healthz",
+                "/metrics",
+                "/robots.txt",
+                "/sitemap.xml",
+                "/items/feed.xml",
+                "/blog/feed.xml",
+                "/calendar.ics",
+                "/og.png",
+                "/avatar/",
+                "/.well-known/",
+                "/favicon.ico",
+                "/manifest.webmanifest",
+                "/sw.js",
+                "/offline",
+                "/embed/",
+            ]
+            .map(String::from)
+            .to_vec(),
+            secure: CookieSecure::Auto,
+            host_prefix: true,
+            same_site: SameSite::Strict,
+        }
+    }
+}
+
+/// `/.well-known/` documents
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SecurityConfig {
+    /// `Contact:` in security.txt (`mailto:` or `https:` URI). Empty serves no
+    /// security.txt at all.
+    #[serde(default)]
+    pub contact: String,
+    /// `Policy:` — URL of the disclosure policy (empty = omitted)
+    #[serde(default)]
+    pub policy: String,
+    /// `Preferred-Languages:`, comma-separated language tags
+    #[serde(default = "default_languages")]
+    pub preferred_languages: String,
+    /// Where `/.well-known/change-password` redirects (empty = 404)
+    #[serde(default)]
+    pub change_password_url: String,
+    /// Key for signed links in emails. Empty uses a random key, so links
+    /// stop working on restart — set `APP__SECURITY__TOKEN_SECRET` in
+    /// production.
+    #[serde(default)]
+    pub token_secret: String,
+    /// Key for CSRF tokens. Empty uses a random key per process (debug
+    /// builds only — release builds refuse to start without one, see
+    /// [`AppConfig::validate`]).
+    #[serde(default)]
+    pub csrf_secret: String,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            contact: String::new(),
+            policy: String::new(),
+            preferred_languages: default_languages(),
+            change_password_url: String::new(),
+            token_secret: String::new(),
+            csrf_secret: String::new(),
+        }
+    }
+}
+
+fn default_languages() -> String {
+    "en".to_string()
+}
+
+/// Generated Open Graph images (`/og.png`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OgConfig {
+    /// Directory for rendered PNGs. Empty renders every request afresh.
+    #[serde(default)]
+    pub cache_dir: String,
+}
+
+impl Default for OgConfig {
+    fn default() -> Self {
+        Self {
+            cache_dir: "data/og-cache".to_string(),
+        }
+    }
+}
+
+/// Generated identicons (`/avatar/:seed.svg`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AvatarConfig {
+    /// Directory for rendered SVGs. Empty renders every request afresh.
+    #[serde(default)]
+    pub cache_dir: String,
+}
+
+impl Default for AvatarConfig {
+    fn default() -> Self {
+        Self {
+            cache_dir: "data/avatar-cache".to_string(),
+        }
+    }
+}
+
+/// Cross-origin headers for one kind of static file. Everything else under
+/// `/static/` is `same-origin` and sends no CORS headers.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StaticHeadersConfig {
+    /// File extensions this applies to, without the dot (`woff2`)
+    pub extensions: Vec<String>,
+    /// `Cross-Origin-Resource-Policy`: `same-origin`, `same-site` or
+    /// `cross-origin`
+    pub cross_origin_resource_policy: String,
+    /// Origins answered with `Access-Control-Allow-Origin`
+    /// (`https://example.com`), or `*` for any; empty sends none
+    pub allow_origins: Vec<String>,
+}
+
+impl Default for StaticHeadersConfig {
+    fn default() -> Self {
+        Self {
+            extensions: Vec::new(),
+            cross_origin_resource_policy: "same-origin".to_string(),
+            allow_origins: Vec::new(),
+        }
+    }
+}
+
+/// `/embed/<widget>`: read-only widgets for other sites' iframes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EmbedsConfig {
+    /// Origins allowed to frame the widgets (`frame-ancestors`), e.g.
+    /// `https://grafana.example.com`. Empty turns embeds off (404).
+    pub frame_ancestors: Vec<String>,
+    /// Seconds between a widget's reloads; 0 never reloads
+    pub refresh_seconds: u64,
+}
+
+impl Default for EmbedsConfig {
+    fn default() -> Self {
+        Self {
+            frame_ancestors: Vec::new(),
+            refresh_seconds: 60,
+        }
+    }
+}
+
+/// Online database backups, see [`crate::services::backups`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BackupsConfig {
+    /// Where backups are written. Empty disables backups.
+    pub dir: String,
+    /// Hours between scheduled backups; 0 leaves them to `/admin/backups`
+    pub interval_hours: u64,
+    /// Backups kept; older ones are deleted after each new one
+    pub keep: usize,
+}
+
+impl Default for BackupsConfig {
+    fn default() -> Self {
+        Self {
+            dir: "data/backups".to_string(),
+            interval_hours: 24,
+            keep: 7,
+        }
+    }
+}
+
+/// Where panics and internal errors are reported, see
+/// [`crate::services::errors`]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ErrorsConfig {
+    /// Sentry or GlitchTip DSN; empty reports nowhere. Needs the
+    /// `error-reporting` feature. Set through `APP__ERRORS__DSN`.
+    pub dsn: String,
+    /// Tags each event; empty for `[environment] environment`
+    pub environment: String,
+}
+
+/// Requests written to the `request_log` table for `/admin/requests`, see
+/// [`crate::services::request_log`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RequestLogConfig {
+    pub enabled: bool,
+    /// Days a record is kept
+    pub retention_days: u64,
+    /// Milliseconds records wait to be written together
+    pub flush_interval_ms: u64,
+    /// Paths starting with one of these aren't logged
+    pub exclude: Vec<String>,
+}
+
+impl Default for RequestLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retention_days: 7,
+            flush_interval_ms: 1000,
+            exclude: vec![
+                "/static/".to_string(),
+                "/healthz".to_string(),
+                "/metrics".to_string(),
+                "/dev/".to_string(),
+            ],
+        }
+    }
+}
+
+/// Cooperation with an external replicator such as Litestream, see
+/// [`crate::services::replication`]. Commands run through `sh -c` with
+/// `{db}` replaced by the database file's path.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReplicationConfig {
+    /// Needs `database.journal_mode = "wal"` and
+    /// `database.wal_autocheckpoint = 0`
+    pub enabled: bool,
+    /// Run at startup when the database file is missing, e.g.
+    /// `litestream restore -if-replica-exists -o {db} {db}`
+    pub restore_command: String,
+    /// Run after `restore_command` brought a database back
+    pub post_restore_command: String,
+    /// Run before each checkpoint; a failure skips the checkpoint
+    pub pre_checkpoint_command: String,
+    /// Seconds between checkpoints; 0 checkpoints only at shutdown
+    pub checkpoint_interval_secs: u64,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            restore_command: String::new(),
+            post_restore_command: String::new(),
+            pre_checkpoint_command: String::new(),
+            checkpoint_interval_secs: 60,
+        }
+    }
+}
+
+/// Outgoing mail, see [`crate::services::mail`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MailConfig {
+    /// `log` (write messages to the log) or `smtp`
+    pub transport: String,
+    /// Sender, e.g. `Axum HTMX App <noreply@example.com>`
+    pub from: String,
+    /// Where contact form messages go (empty = `from`)
+    pub contact_to: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: String,
+    /// Set through `APP__MAIL__SMTP_PASSWORD` rather than committing it
+    pub smtp_password: String,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            transport: "log".to_string(),
+            from: "Axum HTMX App <noreply@localhost>".to_string(),
+            contact_to: String::new(),
+            smtp_host: String::new(),
+            smtp_port: 587,
+            smtp_username: String::new(),
+            smtp_password: String::new(),
+        }
+    }
+}
+
+impl MailConfig {
+    /// Recipient of contact form messages
+    pub fn contact_address(&self) -> &str {
+        if self.contact_to.is_empty() {
+            &self.from
+        } else {
+            &self.contact_to
+        }
+    }
+}
+
+/// Payment provider, see [`crate::services::payments`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PaymentsConfig {
+    /// `fake` (moves no money, takes test cards) is the only one built in
+    pub provider: String,
+}
+
+impl Default for PaymentsConfig {
+    fn default() -> Self {
+        Self {
+            provider: "fake".to_string(),
+        }
+    }
+}
+
+/// Webhooks in ([`crate::handlers::webhooks`]) and out
+/// ([`crate::services::webhooks_out`])
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct WebhooksConfig {
+    /// Provider → shared signing secret. `/webhooks/<provider>` is 404 until
+    /// its secret is set (`APP__WEBHOOKS__SECRETS__GITHUB=..`).
+    #[serde(default)]
+    pub secrets: BTreeMap<String, String>,
+    /// Where published events are sent
+    #[serde(default)]
+    pub endpoints: Vec<WebhookEndpoint>,
+}
+
+/// One `[[webhooks.endpoints]]` subscriber
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    /// Signs each request; empty sends them unsigned
+    #[serde(default)]
+    pub secret: String,
+    /// Event names to send (`item.created`); empty = all of them
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl WebhookEndpoint {
+    pub fn wants(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == event)
+    }
+}
+
+/// How requests are matched to tenants, see [`crate::services::tenants`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TenancyMode {
+    /// Every request belongs to the default tenant
+    #[default]
+    Off,
+    /// `<slug>.<base_domain>`
+    Subdomain,
+    /// `/t/<slug>/..`
+    Path,
+}
+
+/// Multi-tenancy
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TenancyConfig {
+    #[serde(default)]
+    pub mode: TenancyMode,
+    /// Subdomain mode: the host tenants are subdomains of, e.g. `example.com`.
+    /// The bare domain serves the default tenant.
+    #[serde(default)]
+    pub base_domain: String,
+    /// Slug → tenant. `default` themes the default tenant.
+    #[serde(default)]
+    pub tenants: BTreeMap<String, TenantConfig>,
+}
+
+/// One `[tenancy.tenants.<slug>]`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TenantConfig {
+    /// Shown in place of the app name (empty = the app name)
+    #[serde(default)]
+    pub name: String,
+    /// Design token overrides, e.g. `color-brand = "#e11d48"` sets
+    /// `--color-brand`
+    #[serde(default)]
+    pub theme: BTreeMap<String, String>,
+}
+
+/// When the session cookie is `Secure`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CookieSecure {
+    /// When the request came over HTTPS: through a trusted proxy
+    /// (`[server] trust_proxy`) or straight to the app
+    #[default]
+    Auto,
+    /// Always; plain-HTTP clients other than `localhost` lose their session
+    Always,
+    /// Never
+    Never,
+}
+
+/// What to do with a trailing slash, see [`crate::utils::urls`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingSlash {
+    /// `/about/` → `/about`
+    #[default]
+    Strip,
+    /// `/about` → `/about/`
+    Add,
+    /// Either is served as is
+    Keep,
+}
+
+/// URL normalization: non-canonical URLs get a `308` to the canonical one
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UrlsConfig {
+    pub trailing_slash: TrailingSlash,
+    /// `/blog//hello` → `/blog/hello`
+    pub collapse_slashes: bool,
+    /// `Example.COM` → `example.com`
+    pub lowercase_host: bool,
+}
+
+impl Default for UrlsConfig {
+    fn default() -> Self {
+        Self {
+            trailing_slash: TrailingSlash::Strip,
+            collapse_slashes: true,
+            lowercase_host: true,
+        }
+    }
+}
+
+/// Hourly limits for one rate-limited form
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RateLimitPolicy {
+    /// Submissions one session may make
+    pub per_session: u32,
+    /// Submissions the whole site may take
+    pub per_site: u32,
+}
+
+impl RateLimitPolicy {
+    pub fn session(&self) -> Limit {
+        Limit::per_hour(self.per_session)
+    }
+
+    pub fn site(&self) -> Limit {
+        Limit::per_hour(self.per_site)
+    }
+}
+
+/// How often one session may poll one partial
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PollingPolicy {
+    pub per_minute: u32,
+}
+
+impl PollingPolicy {
+    pub fn limit(&self) -> Limit {
+        Limit::per_minute(self.per_minute)
+    }
+}
+
+/// How many requests may be in flight at once before new ones are shed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LoadShedPolicy {
+    pub max_in_flight: u32,
+    /// Sent as `X-Retry-After-Ms` with the 503: how long clients should wait
+    /// before their first retry
+    pub retry_after_ms: u32,
+}
+
+/// Form and polling rate limits and load shedding, reloadable
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RateLimitsConfig {
+    pub contact: RateLimitPolicy,
+    pub newsletter: RateLimitPolicy,
+    /// Admin sign-in attempts, failed or not
+    pub admin_login: RateLimitPolicy,
+    /// Polled partials, see [`poll_limit`](crate::middleware::poll_limit)
+    pub polling: PollingPolicy,
+    /// Every request, see [`load_shed`](crate::middleware::load_shed)
+    pub load_shed: LoadShedPolicy,
+}
+
+impl Default for RateLimitsConfig {
+    fn default() -> Self {
+        Self {
+            contact: RateLimitPolicy {
+                per_session: 3,
+                per_site: 30,
+            },
+            newsletter: RateLimitPolicy {
+                per_session: 5,
+                per_site: 100,
+            },
+            admin_login: RateLimitPolicy {
+                per_session: 10,
+                per_site: 50,
+            },
+            polling: PollingPolicy { per_minute: 60 },
+            load_shed: LoadShedPolicy {
+                max_in_flight: 512,
+                retry_after_ms: 1000,
+            },
+        }
+    }
+}
+
+/// Latency budgets per route, reloadable; see
+/// [`request_logger`](crate::middleware::request_logger)
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LatencyConfig {
+    /// Milliseconds any route may take; `0` for no budget
+    pub default_budget_ms: u64,
+    /// Route pattern as registered (`/blog/:slug`) → milliseconds it may
+    /// take, overriding the default; `0` exempts the route
+    pub budgets: BTreeMap<String, u64>,
+}
+
+impl LatencyConfig {
+    /// The budget for `route`, if it has one
+    pub fn budget(&self, route: &str) -> Option<Duration> {
+        let ms = self
+            .budgets
+            .get(route)
+            .copied()
+            .unwrap_or(self.default_budget_ms);
+        (ms > 0).then(|| Duration::from_millis(ms))
+    }
+}
+
+/// Maintenance mode: everyone but admins gets a 503 page. Read-only mode is
+/// the lighter version: pages work, writes are refused.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    pub enabled: bool,
+    /// Shown on the maintenance page (empty = a generic message)
+    pub message: String,
+    /// Refuse state-changing requests; an admin can override it from
+    /// `/admin/config`
+    pub read_only: bool,
+}
+
+/// Banner above every page's content
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnnouncementConfig {
+    /// Empty shows no banner
+    pub message: String,
+    /// Alert style: `info`, `success`, `warning` or `danger`
+    pub level: String,
+}
+
+impl Default for AnnouncementConfig {
+    fn default() -> Self {
+        Self {
+            message: String::new(),
+            level: "info".to_string(),
+        }
+    }
 }
 
 impl Default for AppConfig {
@@ -37,6 +992,13 @@ impl Default for AppConfig {
             server: ServerConfig {
                 host: "0.0.0.0".to_string(),
                 port: 3000,
+                trust_proxy: false,
+                allowed_hosts: Vec::new(),
+                tls_cert: String::new(),
+                tls_key: String::new(),
+                connections: ConnectionsConfig::default(),
+                listeners: Vec::new(),
+                acme: AcmeConfig::default(),
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
@@ -45,22 +1007,403 @@ impl Default for AppConfig {
                 environment: "development".to_string(),
                 log_level: "info".to_string(),
             },
-            database: DatabaseConfig {
-                url: "sqlite://data.db?mode=rwc".to_string(),
-            },
+            database: DatabaseConfig::default(),
+            breadcrumbs: BreadcrumbsConfig::default(),
+            site: SiteConfig::default(),
+            admin: AdminConfig::default(),
+            robots: RobotsConfig::default(),
+            security: SecurityConfig::default(),
+            sessions: SessionsConfig::default(),
+            og: OgConfig::default(),
+            avatars: AvatarConfig::default(),
+            static_headers: BTreeMap::new(),
+            embeds: EmbedsConfig::default(),
+            backups: BackupsConfig::default(),
+            replication: ReplicationConfig::default(),
+            request_log: RequestLogConfig::default(),
+            mail: MailConfig::default(),
+            errors: ErrorsConfig::default(),
+            webhooks: WebhooksConfig::default(),
+            payments: PaymentsConfig::default(),
+            tenancy: TenancyConfig::default(),
+            urls: UrlsConfig::default(),
+            rate_limits: RateLimitsConfig::default(),
+            latency: LatencyConfig::default(),
+            features: BTreeMap::new(),
+            maintenance: MaintenanceConfig::default(),
+            announcement: AnnouncementConfig::default(),
         }
     }
 }
 
 impl AppConfig {
-    /// Load configuration from config/app.toml and environment variables
-    pub fn load() -> Result<Self, config::ConfigError> {
-        let builder = config::Config::builder()
-            .add_source(config::File::with_name("config/app").required(false))
-            .add_source(config::Environment::with_prefix("APP").separator("__"));
+    /// Load `config/default.toml`, the `APP_ENV` profile and `APP__*`
+    /// environment variables, then validate
+    pub fn load() -> Result<Self, ConfigErrors> {
+        let profile = std::env::var("APP_ENV")
+            .ok()
+            .filter(|profile| !profile.is_empty())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+        Self::load_from(Path::new(CONFIG_DIR), &profile, None)
+    }
+
+    /// [`load`](Self::load) from `dir` with the given profile. `vars` stands
+    /// in for the process environment when set (tests).
+    pub fn load_from(
+        dir: &Path,
+        profile: &str,
+        vars: Option<config::Map<String, String>>,
+    ) -> Result<Self, ConfigErrors> {
+        let valid_name = !profile.is_empty()
+            && profile
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        let profile_file = dir.join(format!("{profile}.toml"));
+        if !valid_name || !profile_file.is_file() {
+            return Err(ConfigErrors::single(
+                "APP_ENV",
+                format!("no profile {}", profile_file.display()),
+            ));
+        }
+
+        let mut table = config::Config::builder()
+            .add_source(config::File::from(dir.join("default.toml")))
+            .add_source(config::File::from(profile_file))
+            .add_source(
+                config::Environment::with_prefix("APP")
+                    .separator("__")
+                    .source(vars),
+            )
+            // The profile is the environment
+            .set_override("environment.environment", profile)
+            .and_then(|builder| builder.build())
+            .and_then(|config| config.collect())
+            .map_err(|e| ConfigErrors::single("", e.to_string()))?;
+        let mut errors = ConfigErrors::default();
+        read_files(&mut table, "", &mut errors);
+        errors.into_result()?;
+
+        let config: Self = config::Value::new(None, config::ValueKind::Table(table))
+            .try_deserialize()
+            .map_err(|e| ConfigErrors::single("", e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
 
-        let config = builder.build()?;
-        config.try_deserialize()
+    /// Check values the types can't: every problem, not just the first.
+    /// Release builds also require a real CSRF secret.
+    pub fn validate(&self) -> Result<(), ConfigErrors> {
+        self.check(cfg!(not(debug_assertions)))
+    }
+
+    fn check(&self, release: bool) -> Result<(), ConfigErrors> {
+        let mut errors = ConfigErrors::default();
+
+        if self.server.host.trim().is_empty() {
+            errors.push("server.host", "must not be empty");
+        }
+        if self.server.port == 0 {
+            errors.push("server.port", "must not be 0");
+        }
+        for pattern in &self.server.allowed_hosts {
+            if let Err(e) = crate::utils::hosts::check_pattern(pattern) {
+                errors.push("server.allowed_hosts", e);
+            }
+        }
+        if self.server.tls_cert.is_empty() != self.server.tls_key.is_empty() {
+            errors.push("server.tls_key", "tls_cert and tls_key go together");
+        }
+        for (key, path) in [
+            ("server.tls_cert", &self.server.tls_cert),
+            ("server.tls_key", &self.server.tls_key),
+        ] {
+            if !path.is_empty() && !Path::new(path).is_file() {
+                errors.push(key, format!("no file at {path}"));
+            }
+        }
+        let acme = &self.server.acme;
+        if acme.enabled() && !cfg!(feature = "acme") {
+            errors.push(
+                "server.acme.domains",
+                "this build lacks the `acme` feature (cargo build --features acme)",
+            );
+        }
+        if acme.enabled() && !self.server.tls_cert.is_empty() {
+            errors.push(
+                "server.acme.domains",
+                "set either tls_cert and tls_key or ACME domains, not both",
+            );
+        }
+        for domain in &acme.domains {
+            let name = crate::utils::hosts::host_name(domain);
+            let is_name = name.as_deref() == Some(domain.as_str())
+                && domain.parse::<std::net::Ipv4Addr>().is_err();
+            if !is_name {
+                errors.push(
+                    "server.acme.domains",
+                    format!("\"{domain}\" must be a lowercase host name, without port or wildcard"),
+                );
+            }
+        }
+        for contact in &acme.contact {
+            if !contact.contains('@') || contact.contains(':') {
+                errors.push(
+                    "server.acme.contact",
+                    format!("\"{contact}\" must be an email address"),
+                );
+            }
+        }
+        if acme.enabled() && acme.cache_dir.is_empty() {
+            errors.push(
+                "server.acme.cache_dir",
+                "must be set, or every start orders a new certificate",
+            );
+        }
+        for listener in &self.server.listeners {
+            let address = listener.address.as_str();
+            if address.is_empty() {
+                errors.push("server.listeners", "address must not be empty");
+            } else if let Some(path) = address.strip_prefix("unix:") {
+                if path.is_empty() {
+                    errors.push("server.listeners", "unix: needs a socket path");
+                }
+                if listener.tls {
+                    errors.push(
+                        "server.listeners",
+                        format!("{address}: TLS is for TCP listeners"),
+                    );
+                }
+            } else if address
+                .rsplit_once(':')
+                .is_none_or(|(_, port)| port.parse::<u16>().is_err())
+            {
+                errors.push(
+                    "server.listeners",
+                    format!("{address}: must be host:port or unix:<path>"),
+                );
+            }
+            if listener.tls && !self.server.tls() {
+                errors.push(
+                    "server.listeners",
+                    format!("{address}: tls needs [server] tls_cert and tls_key, or [server.acme]"),
+                );
+            }
+        }
+        let connections = &self.server.connections;
+        if connections.max_header_bytes < 8192 {
+            errors.push(
+                "server.connections.max_header_bytes",
+                "must be at least 8192",
+            );
+        }
+        if connections.max_concurrent_streams == 0 {
+            errors.push("server.connections.max_concurrent_streams", "must not be 0");
+        }
+        let admin_host = &self.admin.host;
+        if !admin_host.is_empty()
+            && (admin_host.starts_with('*')
+                || crate::utils::hosts::check_pattern(admin_host).is_err())
+        {
+            errors.push("admin.host", "must be a host name, without port or scheme");
+        }
+        let levels = ["trace", "debug", "info", "warn", "error"];
+        if !self.logging.level.contains('=') && !levels.contains(&self.logging.level.as_str()) {
+            errors.push(
+                "logging.level",
+                format!("must be one of {} or a filter directive", levels.join(", ")),
+            );
+        }
+        if !self.database.url.starts_with("sqlite:") {
+            errors.push("database.url", "must be a sqlite: URL");
+        }
+        let modes = ["wal", "delete", "truncate", "persist", "memory", "off"];
+        if !modes.contains(&self.database.journal_mode.as_str()) {
+            errors.push(
+                "database.journal_mode",
+                format!("must be one of {}", modes.join(", ")),
+            );
+        }
+        let levels = ["off", "normal", "full", "extra"];
+        if !levels.contains(&self.database.synchronous.as_str()) {
+            errors.push(
+                "database.synchronous",
+                format!("must be one of {}", levels.join(", ")),
+            );
+        }
+        if self.database.max_read_connections == 0 {
+            errors.push("database.max_read_connections", "must be at least 1");
+        }
+        if self.backups.keep == 0 {
+            errors.push("backups.keep", "must be at least 1");
+        }
+        if self.sessions.max_sessions == 0 {
+            errors.push("sessions.max_sessions", "must be at least 1");
+        }
+        if self.sessions.same_site == SameSite::None && self.sessions.secure != CookieSecure::Always
+        {
+            errors.push(
+                "sessions.same_site",
+                "\"none\" needs sessions.secure = \"always\"",
+            );
+        }
+        if self.request_log.retention_days == 0 {
+            errors.push("request_log.retention_days", "must be at least 1");
+        }
+        let mut claimed = std::collections::HashSet::new();
+        for (kind, policy) in &self.static_headers {
+            for extension in &policy.extensions {
+                let extension = extension.to_ascii_lowercase();
+                if extension.is_empty() || extension.contains(['.', '/']) {
+                    errors.push(
+                        format!("static_headers.{kind}.extensions"),
+                        format!("\"{extension}\" must be an extension without the dot"),
+                    );
+                } else if !claimed.insert(extension.clone()) {
+                    errors.push(
+                        format!("static_headers.{kind}.extensions"),
+                        format!("\"{extension}\" is in another [static_headers] kind too"),
+                    );
+                }
+            }
+            if !matches!(
+                policy.cross_origin_resource_policy.as_str(),
+                "same-origin" | "same-site" | "cross-origin"
+            ) {
+                errors.push(
+                    format!("static_headers.{kind}.cross_origin_resource_policy"),
+                    "must be \"same-origin\", \"same-site\" or \"cross-origin\"",
+                );
+            }
+            for origin in &policy.allow_origins {
+                if origin != "*" && !crate::utils::hosts::is_origin(origin) {
+                    errors.push(
+                        format!("static_headers.{kind}.allow_origins"),
+                        format!("\"{origin}\" must be scheme://host[:port] or \"*\""),
+                    );
+                }
+            }
+        }
+        for route in self.latency.budgets.keys() {
+            if !route.starts_with('/') {
+                errors.push(
+                    format!("latency.budgets.{route}"),
+                    "must be a route pattern starting with /",
+                );
+            }
+        }
+        if self.replication.enabled {
+            if self.database.journal_mode != "wal" {
+                errors.push("database.journal_mode", "must be \"wal\" for replication");
+            }
+            if self.database.wal_autocheckpoint != 0 {
+                errors.push(
+                    "database.wal_autocheckpoint",
+                    "must be 0 for replication, which checkpoints itself",
+                );
+            }
+        }
+        let base_url = &self.site.base_url;
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            errors.push("site.base_url", "must start with http:// or https://");
+        } else if base_url.ends_with('/') {
+            errors.push("site.base_url", "must not end with /");
+        }
+
+        let csrf_secret = &self.security.csrf_secret;
+        if csrf_secret.is_empty() {
+            if release {
+                errors.push(
+                    "security.csrf_secret",
+                    "is required in release builds (APP__SECURITY__CSRF_SECRET or csrf_secret_file)",
+                );
+            }
+        } else if is_placeholder(csrf_secret) {
+            errors.push(
+                "security.csrf_secret",
+                "is a placeholder, generate a real one",
+            );
+        } else if csrf_secret.len() < MIN_SECRET_LEN {
+            errors.push(
+                "security.csrf_secret",
+                format!("must be at least {MIN_SECRET_LEN} characters"),
+            );
+        }
+
+        match self.mail.transport.as_str() {
+            "log" => {}
+            "smtp" if self.mail.smtp_host.is_empty() => {
+                errors.push("mail.smtp_host", "is required with transport = \"smtp\"")
+            }
+            "smtp" => {}
+            _ => errors.push("mail.transport", "must be \"log\" or \"smtp\""),
+        }
+        if self.payments.provider != "fake" {
+            errors.push("payments.provider", "must be \"fake\"");
+        }
+        for (i, endpoint) in self.webhooks.endpoints.iter().enumerate() {
+            if !(endpoint.url.starts_with("http://") || endpoint.url.starts_with("https://")) {
+                errors.push(
+                    format!("webhooks.endpoints[{i}].url"),
+                    "must start with http:// or https://",
+                );
+            }
+        }
+
+        for (form, policy) in [
+            ("contact", self.rate_limits.contact),
+            ("newsletter", self.rate_limits.newsletter),
+            ("admin_login", self.rate_limits.admin_login),
+        ] {
+            if policy.per_session == 0 || policy.per_site == 0 {
+                errors.push(format!("rate_limits.{form}"), "limits must be at least 1");
+            }
+        }
+        for origin in &self.embeds.frame_ancestors {
+            if !crate::utils::hosts::is_origin(origin) {
+                errors.push(
+                    "embeds.frame_ancestors",
+                    format!("\"{origin}\" must be scheme://host[:port]"),
+                );
+            }
+        }
+        if self.rate_limits.polling.per_minute == 0 {
+            errors.push("rate_limits.polling.per_minute", "must be at least 1");
+        }
+        if self.rate_limits.load_shed.max_in_flight == 0 {
+            errors.push("rate_limits.load_shed.max_in_flight", "must be at least 1");
+        }
+        let levels = ["info", "success", "warning", "danger"];
+        if !levels.contains(&self.announcement.level.as_str()) {
+            errors.push(
+                "announcement.level",
+                format!("must be one of {}", levels.join(", ")),
+            );
+        }
+
+        if self.tenancy.mode == TenancyMode::Subdomain && self.tenancy.base_domain.is_empty() {
+            errors.push(
+                "tenancy.base_domain",
+                "is required with mode = \"subdomain\"",
+            );
+        }
+        for (slug, tenant) in &self.tenancy.tenants {
+            if !tenants::valid_slug(slug) {
+                errors.push(
+                    format!("tenancy.tenants.{slug}"),
+                    "slugs are lowercase letters, digits and -",
+                );
+            }
+            for (name, value) in &tenant.theme {
+                if !tenants::valid_theme_var(name, value) {
+                    errors.push(
+                        format!("tenancy.tenants.{slug}.theme.{name}"),
+                        "names are [a-z0-9-]; values letters, digits, spaces and #(),.%-",
+                    );
+                }
+            }
+        }
+
+        errors.into_result()
     }
 
     pub fn is_development(&self) -> bool {
@@ -71,3 +1414,459 @@ impl AppConfig {
         self.environment.environment == "production"
     }
 }
+
+/// Replace each non-empty `<key>_file` string in `table` with `<key>` = the
+/// file's contents, minus trailing whitespace. The file wins over any value
+/// `<key>` already has. `prefix` is the dotted path of
+/// `table`, for error keys.
+fn read_files(
+    table: &mut config::Map<String, config::Value>,
+    prefix: &str,
+    errors: &mut ConfigErrors,
+) {
+    // Sorted, so errors come out in the same order every time
+    let mut names: Vec<String> = table.keys().cloned().collect();
+    names.sort();
+    for name in names {
+        let path = match &mut table.get_mut(&name).unwrap().kind {
+            config::ValueKind::Table(inner) => {
+                read_files(inner, &format!("{prefix}{name}."), errors);
+                continue;
+            }
+            config::ValueKind::String(path) => path.clone(),
+            _ => continue,
+        };
+        let Some(key) = name.strip_suffix(FILE_SUFFIX) else {
+            continue;
+        };
+        // Gone either way, so a path never ends up where a value should be
+        table.remove(&name);
+        if path.is_empty() {
+            continue;
+        }
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                let value = config::Value::new(None, contents.trim_end().to_string());
+                table.insert(key.to_string(), value);
+            }
+            Err(e) => errors.push(format!("{prefix}{name}"), format!("can't read {path}: {e}")),
+        }
+    }
+}
+
+/// Secrets copied from docs and examples rather than generated
+fn is_placeholder(secret: &str) -> bool {
+    let normalized: String = secret
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    let mut chars = normalized.chars();
+    let repeated = chars.next().is_some_and(|first| chars.all(|c| c == first));
+    repeated
+        || [
+            "changeme",
+            "changethis",
+            "placeholder",
+            "replaceme",
+            "yoursecret",
+            "example",
+            "secretkey",
+        ]
+        .iter()
+        .any(|word| normalized.contains(word))
+}
+
+/// One invalid setting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Dotted path, e.g. `server.port`; empty when the file itself is bad
+    pub key: String,
+    pub message: String,
+}
+
+/// Everything wrong with a configuration, see [`AppConfig::validate`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl ConfigErrors {
+    fn single(key: &str, message: impl Into<String>) -> Self {
+        let mut errors = Self::default();
+        errors.push(key, message);
+        errors
+    }
+
+    fn push(&mut self, key: impl Into<String>, message: impl Into<String>) {
+        self.0.push(ConfigError {
+            key: key.into(),
+            message: message.into(),
+        });
+    }
+
+    fn into_result(self) -> Result<(), Self> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} configuration error(s):", self.0.len())?;
+        for error in &self.0 {
+            match error.key.as_str() {
+                "" => write!(f, "\n  {}", error.message)?,
+                key => write!(f, "\n  {key}: {}", error.message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A real CSRF secret, which release builds (`cargo test --release`)
+    /// require of every loaded config
+    const SECRET: (&str, &str) = (
+        "APP__SECURITY__CSRF_SECRET",
+        "k4Jv9QeT2mXw7LpZ0sRb8NcY5hGd3FuA",
+    );
+
+    fn vars(pairs: &[(&str, &str)]) -> config::Map<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_layering() {
+        let dir = Path::new(CONFIG_DIR);
+        let config = AppConfig::load_from(dir, "production", Some(vars(&[SECRET]))).unwrap();
+        assert!(config.is_production());
+        assert_eq!(config.server.port, 8000);
+
+        // Environment variables win over both files
+        let config = AppConfig::load_from(
+            dir,
+            "development",
+            Some(vars(&[("APP__SERVER__PORT", "9000"), SECRET])),
+        )
+        .unwrap();
+        assert!(config.is_development());
+        assert_eq!(config.server.port, 9000);
+
+        let missing = AppConfig::load_from(dir, "nope", Some(vars(&[]))).unwrap_err();
+        assert_eq!(missing.0[0].key, "APP_ENV");
+        assert!(AppConfig::load_from(dir, "../config/default", Some(vars(&[]))).is_err());
+    }
+
+    #[test]
+    fn test_file_settings() {
+        let path = std::env::temp_dir().join(format!("app-config-test-{}", std::process::id()));
+        std::fs::write(&path, "sqlite://secret.db\n").unwrap();
+        let path = path.to_str().unwrap();
+        let dir = Path::new(CONFIG_DIR);
+
+        let config = AppConfig::load_from(
+            dir,
+            "development",
+            Some(vars(&[
+                ("APP__DATABASE__URL_FILE", path),
+                ("APP__WEBHOOKS__SECRETS__GITHUB_FILE", path),
+                SECRET,
+            ])),
+        )
+        .unwrap();
+        assert_eq!(config.database.url, "sqlite://secret.db");
+        // Maps too, without leaving a `github_file` entry behind
+        assert_eq!(config.webhooks.secrets.len(), 1);
+        assert_eq!(config.webhooks.secrets["github"], "sqlite://secret.db");
+
+        let errors = AppConfig::load_from(
+            dir,
+            "development",
+            Some(vars(&[
+                ("APP__SECURITY__CSRF_SECRET_FILE", "/nonexistent/csrf"),
+                ("APP__MAIL__SMTP_PASSWORD_FILE", "/nonexistent/smtp"),
+            ])),
+        )
+        .unwrap_err();
+        let keys: Vec<_> = errors.0.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            ["mail.smtp_password_file", "security.csrf_secret_file"]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_csrf_secret() {
+        let mut config = AppConfig::default();
+        assert_eq!(config.check(false), Ok(()));
+        assert_eq!(
+            config.check(true).unwrap_err().0[0].key,
+            "security.csrf_secret"
+        );
+
+        for bad in [
+            "short",
+            "changeme-changeme-changeme-changeme",
+            &"x".repeat(40),
+        ] {
+            config.security.csrf_secret = bad.to_string();
+            assert!(config.check(false).is_err(), "{bad}");
+        }
+        config.security.csrf_secret = "k4Jv9QeT2mXw7LpZ0sRb8NcY5hGd3FuA".into();
+        assert_eq!(config.check(true), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_reports_every_error() {
+        assert_eq!(AppConfig::default().check(false), Ok(()));
+
+        let mut config = AppConfig::default();
+        config.server.port = 0;
+        config.database.journal_mode = "WAL2".into();
+        config.site.base_url = "https://example.com/".into();
+        config.mail.transport = "smtp".into();
+        config.payments.provider = "stripe".into();
+        config.tenancy.mode = TenancyMode::Subdomain;
+        let errors = config.check(false).unwrap_err();
+        let keys: Vec<_> = errors.0.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "server.port",
+                "database.journal_mode",
+                "site.base_url",
+                "mail.smtp_host",
+                "payments.provider",
+                "tenancy.base_domain"
+            ]
+        );
+        assert!(errors.to_string().starts_with("6 configuration error(s):"));
+    }
+
+    #[test]
+    fn test_latency_budgets() {
+        let dir = std::env::temp_dir().join(format!("app-latency-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::copy(
+            Path::new(CONFIG_DIR).join("default.toml"),
+            dir.join("default.toml"),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("development.toml"),
+            "[latency]\ndefault_budget_ms = 250\n\n\
+             [latency.budgets]\n\"/blog/:slug\" = 100\n\"/partials/item-list/changes\" = 0\n",
+        )
+        .unwrap();
+        let config = AppConfig::load_from(&dir, "development", Some(vars(&[SECRET]))).unwrap();
+        let latency = &config.latency;
+        assert_eq!(
+            latency.budget("/blog/:slug"),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(latency.budget("/"), Some(Duration::from_millis(250)));
+        assert_eq!(latency.budget("/partials/item-list/changes"), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut config = AppConfig::default();
+        config.latency.budgets.insert("blog/:slug".into(), 100);
+        let errors = config.check(false).unwrap_err();
+        assert_eq!(errors.0[0].key, "latency.budgets.blog/:slug");
+    }
+
+    #[test]
+    fn test_allowed_hosts() {
+        let mut config = AppConfig::default();
+        assert!(config.allowed_hosts().is_empty());
+
+        config.server.allowed_hosts = vec!["example.com".into()];
+        config.tenancy.mode = TenancyMode::Subdomain;
+        config.tenancy.base_domain = "apps.example.com".into();
+        assert_eq!(
+            config.allowed_hosts(),
+            ["example.com", "apps.example.com", "*.apps.example.com"]
+        );
+        config.admin.host = "admin.example.com".into();
+        assert_eq!(config.allowed_hosts().last().unwrap(), "admin.example.com");
+
+        config.server.allowed_hosts.push("example.com:8000".into());
+        config.admin.host = "*.example.com".into();
+        let errors = config.check(false).unwrap_err();
+        assert_eq!(errors.0[0].key, "server.allowed_hosts");
+        assert_eq!(errors.0[1].key, "admin.host");
+    }
+
+    #[test]
+    fn test_session_cookie_attributes() {
+        let mut config = AppConfig::default();
+        let sessions = &config.sessions;
+        assert!(sessions.cookie_secure(true));
+        assert!(!sessions.cookie_secure(false));
+        assert_eq!(sessions.cookie_name(true), "__Host-sid");
+        assert_eq!(sessions.cookie_name(false), "sid");
+
+        config.sessions.secure = CookieSecure::Always;
+        config.sessions.host_prefix = false;
+        assert!(config.sessions.cookie_secure(false));
+        assert_eq!(config.sessions.cookie_name(true), "sid");
+
+        config.sessions.secure = CookieSecure::Auto;
+        config.sessions.same_site = SameSite::None;
+        let errors = config.check(false).unwrap_err();
+        assert_eq!(errors.0[0].key, "sessions.same_site");
+    }
+
+    #[test]
+    fn test_replication_needs_manual_checkpoints() {
+        let mut config = AppConfig::default();
+        config.replication.enabled = true;
+        let errors = config.check(false).unwrap_err();
+        let keys: Vec<_> = errors.0.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, ["database.wal_autocheckpoint"]);
+
+        config.database.wal_autocheckpoint = 0;
+        assert_eq!(config.check(false), Ok(()));
+    }
+
+    #[test]
+    fn test_server_connections() {
+        let mut config = AppConfig::default();
+        assert!(!config.server.tls());
+        config.server.tls_cert = "Cargo.toml".into();
+        config.server.connections.max_header_bytes = 4096;
+        config.server.connections.max_concurrent_streams = 0;
+        let errors = config.check(false).unwrap_err();
+        let keys: Vec<_> = errors.0.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "server.tls_key",
+                "server.connections.max_header_bytes",
+                "server.connections.max_concurrent_streams"
+            ]
+        );
+
+        config.server.tls_key = "no-such-key.pem".into();
+        config.server.connections = ConnectionsConfig::default();
+        let errors = config.check(false).unwrap_err();
+        assert_eq!(errors.0[0].key, "server.tls_key");
+        assert!(config.server.tls());
+    }
+
+    #[test]
+    fn test_server_acme() {
+        let mut config = AppConfig::default();
+        config.server.acme.domains = vec!["example.com".into(), "www.example.com".into()];
+        config.server.acme.contact = vec!["ops@example.com".into()];
+        assert_eq!(config.check(false).is_ok(), cfg!(feature = "acme"));
+        assert!(config.server.tls());
+
+        config.server.tls_cert = "Cargo.toml".into();
+        config.server.tls_key = "Cargo.toml".into();
+        config.server.acme.domains = vec![
+            "*.example.com".into(),
+            "example.com:443".into(),
+            "127.0.0.1".into(),
+        ];
+        config.server.acme.contact = vec!["mailto:ops@example.com".into()];
+        config.server.acme.cache_dir = String::new();
+        let errors = config.check(false).unwrap_err();
+        let keys: Vec<_> = errors.0.iter().map(|e| e.key.as_str()).collect();
+        let keys = &keys[usize::from(!cfg!(feature = "acme"))..];
+        assert_eq!(
+            keys,
+            [
+                "server.acme.domains",
+                "server.acme.domains",
+                "server.acme.domains",
+                "server.acme.domains",
+                "server.acme.contact",
+                "server.acme.cache_dir"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_static_headers() {
+        let mut config = AppConfig::default();
+        config.static_headers.insert(
+            "fonts".into(),
+            StaticHeadersConfig {
+                extensions: vec!["woff2".into(), "woff".into()],
+                cross_origin_resource_policy: "cross-origin".into(),
+                allow_origins: vec!["https://embed.example.com".into()],
+            },
+        );
+        assert!(config.check(false).is_ok());
+
+        config.static_headers.insert(
+            "more".into(),
+            StaticHeadersConfig {
+                extensions: vec!["WOFF2".into(), ".ttf".into()],
+                cross_origin_resource_policy: "anyone".into(),
+                allow_origins: vec!["embed.example.com".into(), "*".into()],
+            },
+        );
+        let errors = config.check(false).unwrap_err();
+        let keys: Vec<_> = errors.0.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "static_headers.more.extensions",
+                "static_headers.more.extensions",
+                "static_headers.more.cross_origin_resource_policy",
+                "static_headers.more.allow_origins"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_embeds() {
+        let mut config = AppConfig::default();
+        config.embeds.frame_ancestors = vec!["https://grafana.example.com".into()];
+        assert!(config.check(false).is_ok());
+
+        config.embeds.frame_ancestors = vec!["*".into(), "https://ok.example.com/path".into()];
+        let errors = config.check(false).unwrap_err();
+        assert_eq!(errors.0.len(), 2);
+        assert!(errors.0.iter().all(|e| e.key == "embeds.frame_ancestors"));
+    }
+
+    #[test]
+    fn test_server_listeners() {
+        let listener = |address: &str, tls| ListenerConfig {
+            address: address.into(),
+            tls,
+            ..ListenerConfig::default()
+        };
+        let mut config = AppConfig::default();
+        config.server.listeners = vec![
+            listener("127.0.0.1:9100", false),
+            listener("unix:/run/app/tor.sock", false),
+            listener("[::1]:9100", false),
+        ];
+        assert!(config.check(false).is_ok());
+
+        config.server.listeners = vec![
+            listener("", false),
+            listener("unix:", false),
+            listener("localhost", false),
+            listener("unix:/run/app/tor.sock", true),
+        ];
+        let errors = config.check(false).unwrap_err();
+        assert!(errors.0.iter().all(|e| e.key == "server.listeners"));
+        // The last: TLS on a Unix socket, and no certificate either
+        assert_eq!(errors.0.len(), 5);
+    }
+}
@@ -1,31 +1,371 @@
 //! Database initialization and pool management
 //!
-//! Uses SQLx with SQLite. The pool is created once at startup and shared
+//! Uses SQLx with SQLite. The pools are created once at startup and shared
 //! across all handlers via AppState.
+//!
+//! SQLite allows one writer at a time. Rather than let concurrent writes
+//! (say, several HTMX posts at once) fight over the lock and fail with
+//! `SQLITE_BUSY`, [`Db`] has a single writer connection that writes queue
+//! for, next to a pool of readers that WAL lets run alongside it. The
+//! `[database]` pragmas apply to every connection.
+//!
+//! An unreachable database doesn't stop the app: [`connect`] falls back to
+//! pools that connect lazily, and [`monitor`] reconnects with backoff. Until
+//! then [`DbHealth`] reports the outage, pages that don't need the database
+//! keep working, and every page shows a banner.
 
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteSynchronous,
+};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tracing::info;
 
-/// Type alias for the database connection pool
-pub type Db = SqlitePool;
+use crate::config::DatabaseConfig;
+use crate::query_cache::QueryCache;
+use crate::schema;
+
+/// How long a query waits for a connection before failing, so requests
+/// during an outage fail fast instead of hanging. Writes queue for the
+/// writer, so a write's wait includes the writes ahead of it.
+const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(3);
+/// Time between checks while the database is up
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// First and longest wait between reconnection attempts
+const RETRY_MIN: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+
+/// The connection pools: readers, and the single writer, with the
+/// [`QueryCache`] the stores share
+#[derive(Debug, Clone)]
+pub struct Db {
+    reader: SqlitePool,
+    writer: SqlitePool,
+    cache: Arc<QueryCache>,
+}
+
+impl Db {
+    /// For queries that only read
+    pub fn reader(&self) -> &SqlitePool {
+        &self.reader
+    }
+
+    /// For anything that writes: INSERT, UPDATE, DELETE, and transactions
+    /// that do any of those
+    pub fn writer(&self) -> &SqlitePool {
+        &self.writer
+    }
 
-/// Initialize the SQLite connection pool and run migrations.
+    /// Results of hot reads; see [`crate::query_cache`]
+    pub fn cache(&self) -> &QueryCache {
+        &self.cache
+    }
+
+    pub async fn close(&self) {
+        self.reader.close().await;
+        self.writer.close().await;
+    }
+}
+
+/// Initialize the SQLite connection pools and run migrations.
 ///
-/// The `database_url` should be a SQLite connection string, e.g.:
+/// `config.url` should be a SQLite connection string, e.g.:
 /// - `sqlite://data.db?mode=rwc` (file-based, auto-create)
-/// - `sqlite::memory:` (in-memory, useful for tests)
-pub async fn init_pool(database_url: &str) -> Result<Db, sqlx::Error> {
-    info!("Connecting to database: {}", database_url);
+/// - `sqlite::memory:` (in-memory, useful for tests; one connection for
+///   everything, since each connection would get its own database)
+pub async fn init_pool(config: &DatabaseConfig) -> Result<Db, sqlx::Error> {
+    info!("Connecting to database: {}", config.url);
 
-    let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect(database_url)
-        .await?;
+    let options = connect_options(config)?;
+    // The writer first: it creates the file and switches it to WAL
+    let writer = writer_options(config).connect_with(options.clone()).await?;
+    let reader = if in_memory(&config.url) {
+        writer.clone()
+    } else {
+        reader_options(config).connect_with(options).await?
+    };
+    let db = Db {
+        reader,
+        writer,
+        cache: Arc::new(query_cache(config)),
+    };
 
-    // Run embedded migrations at startup
-    sqlx::migrate!("./migrations").run(&pool).await?;
+    // Run embedded migrations at startup, refusing a database that has
+    // drifted from this build
+    migrate(&db).await?;
 
     info!("Database migrations applied successfully");
 
-    Ok(pool)
+    Ok(db)
+}
+
+fn query_cache(config: &DatabaseConfig) -> QueryCache {
+    QueryCache::new(Duration::from_millis(config.query_cache_ttl_ms))
+}
+
+/// [`schema::MIGRATOR`] between the two [`schema`] checks
+async fn migrate(db: &Db) -> Result<(), sqlx::Error> {
+    schema::check_migrations(db.reader()).await?;
+    schema::MIGRATOR.run(db.writer()).await?;
+    schema::check_tables(db.reader()).await
+}
+
+/// [`init_pool`], or when that fails, pools that connect on first use and a
+/// [`DbHealth`] reporting the outage. Errors when the URL or a pragma can't
+/// be parsed, and on [`SchemaDrift`](schema::SchemaDrift), which waiting
+/// won't fix.
+pub async fn connect(config: &DatabaseConfig) -> Result<(Db, Arc<DbHealth>), sqlx::Error> {
+    match init_pool(config).await {
+        Ok(db) => Ok((db, Arc::new(DbHealth::up()))),
+        Err(e) if schema::as_drift(&e).is_some() => Err(e),
+        Err(e) => {
+            tracing::error!(error = %e, "Database unavailable, starting without it");
+            let options = connect_options(config)?;
+            let writer = writer_options(config).connect_lazy_with(options.clone());
+            let reader = if in_memory(&config.url) {
+                writer.clone()
+            } else {
+                reader_options(config).connect_lazy_with(options)
+            };
+            let db = Db {
+                reader,
+                writer,
+                cache: Arc::new(query_cache(config)),
+            };
+            Ok((db, Arc::new(DbHealth::down(e.to_string()))))
+        }
+    }
+}
+
+/// `config.url` with the `[database]` pragmas
+fn connect_options(config: &DatabaseConfig) -> Result<SqliteConnectOptions, sqlx::Error> {
+    Ok(SqliteConnectOptions::from_str(&config.url)?
+        .journal_mode(SqliteJournalMode::from_str(&config.journal_mode)?)
+        .synchronous(SqliteSynchronous::from_str(&config.synchronous)?)
+        .busy_timeout(Duration::from_millis(config.busy_timeout_ms))
+        .foreign_keys(config.foreign_keys)
+        .pragma("cache_size", config.cache_size.to_string())
+        .pragma("wal_autocheckpoint", config.wal_autocheckpoint.to_string()))
+}
+
+/// The database file `config.url` names; `None` for in-memory databases
+pub fn file_path(config: &DatabaseConfig) -> Option<std::path::PathBuf> {
+    if in_memory(&config.url) {
+        return None;
+    }
+    let options = SqliteConnectOptions::from_str(&config.url).ok()?;
+    Some(options.get_filename().to_path_buf())
+}
+
+fn writer_options(config: &DatabaseConfig) -> SqlitePoolOptions {
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(ACQUIRE_TIMEOUT)
+        .acquire_slow_threshold(Duration::from_millis(config.slow_acquire_ms))
+}
+
+fn reader_options(config: &DatabaseConfig) -> SqlitePoolOptions {
+    SqlitePoolOptions::new()
+        .max_connections(config.max_read_connections)
+        .acquire_timeout(ACQUIRE_TIMEOUT)
+        .acquire_slow_threshold(Duration::from_millis(config.slow_acquire_ms))
+}
+
+fn in_memory(url: &str) -> bool {
+    url.contains(":memory:") || url.contains("mode=memory")
+}
+
+/// Whether the database is reachable, as of the last check
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DbStatus {
+    pub up: bool,
+    /// Why the last check failed; empty while up
+    pub error: String,
+    /// When the database went down or came back
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// Database health, kept current by [`monitor`]
+pub struct DbHealth {
+    status: RwLock<DbStatus>,
+    // Migrations run once, on the first successful connection
+    migrated: AtomicBool,
+}
+
+impl DbHealth {
+    /// Connected and migrated
+    pub fn up() -> Self {
+        Self {
+            status: RwLock::new(DbStatus {
+                up: true,
+                ..DbStatus::default()
+            }),
+            migrated: AtomicBool::new(true),
+        }
+    }
+
+    /// Not connected yet; migrations still to run
+    pub fn down(error: String) -> Self {
+        Self {
+            status: RwLock::new(DbStatus {
+                up: false,
+                error,
+                since: Some(Utc::now()),
+            }),
+            migrated: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.status.read().unwrap().up
+    }
+
+    pub fn status(&self) -> DbStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    fn mark_up(&self) {
+        let mut status = self.status.write().unwrap();
+        if !status.up {
+            info!("Database reachable again");
+            *status = DbStatus {
+                up: true,
+                error: String::new(),
+                since: Some(Utc::now()),
+            };
+        }
+    }
+
+    fn mark_down(&self, error: String) {
+        let mut status = self.status.write().unwrap();
+        if status.up {
+            tracing::error!(error = %error, "Database unreachable, reconnecting");
+            status.since = Some(Utc::now());
+        }
+        status.up = false;
+        status.error = error;
+    }
+
+    /// Ping the database, running the migrations first if they haven't
+    /// been, and record the outcome
+    async fn check(&self, db: &Db) -> bool {
+        let result = async {
+            if !self.migrated.load(Ordering::Relaxed) {
+                migrate(db).await.inspect_err(|e| {
+                    if let Some(drift) = schema::as_drift(e) {
+                        tracing::error!("{drift}");
+                    }
+                })?;
+                self.migrated.store(true, Ordering::Relaxed);
+                info!("Database migrations applied successfully");
+            }
+            sqlx::query("SELECT 1").execute(db.reader()).await?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+        }
+        .await;
+        match result {
+            Ok(()) => self.mark_up(),
+            Err(e) => self.mark_down(e.to_string()),
+        }
+        self.is_up()
+    }
+}
+
+/// Check the database every [`CHECK_INTERVAL`] while it's up; while it's
+/// down, retry after 1s, 2s, 4s… up to a minute between attempts
+pub fn monitor(db: Db, health: Arc<DbHealth>) {
+    tokio::spawn(async move {
+        let mut backoff = RETRY_MIN;
+        loop {
+            if health.is_up() {
+                tokio::time::sleep(CHECK_INTERVAL).await;
+                health.check(&db).await;
+            } else {
+                tokio::time::sleep(backoff).await;
+                if health.check(&db).await {
+                    backoff = RETRY_MIN;
+                } else {
+                    tracing::debug!(retry_in = ?backoff * 2, "Database still unreachable");
+                    backoff = (backoff * 2).min(RETRY_MAX);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_in(dir: &std::path::Path) -> DatabaseConfig {
+        DatabaseConfig {
+            url: format!("sqlite://{}/app.db?mode=rwc", dir.display()),
+            ..DatabaseConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_starts_without_database_and_recovers() {
+        let dir = std::env::temp_dir().join(format!("app-db-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // The directory doesn't exist, so the file can't be created
+        let (db, health) = connect(&config_in(&dir)).await.unwrap();
+        assert!(!health.is_up());
+        assert!(health.status().since.is_some());
+        assert!(!health.check(&db).await);
+
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(health.check(&db).await);
+        assert_eq!(health.status().error, "");
+        // Migrated on reconnect
+        sqlx::query("SELECT COUNT(*) FROM items")
+            .execute(db.reader())
+            .await
+            .unwrap();
+
+        db.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_pragmas_apply_to_every_connection() {
+        let dir = std::env::temp_dir().join(format!("app-db-pragmas-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = DatabaseConfig {
+            busy_timeout_ms: 1234,
+            cache_size: -1000,
+            wal_autocheckpoint: 0,
+            ..config_in(&dir)
+        };
+        let db = init_pool(&config).await.unwrap();
+
+        for pool in [db.reader(), db.writer()] {
+            let pragma = |name: &'static str| async move {
+                sqlx::query_scalar::<_, i64>(&format!("PRAGMA {name}"))
+                    .fetch_one(pool)
+                    .await
+                    .unwrap()
+            };
+            let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+                .fetch_one(pool)
+                .await
+                .unwrap();
+            assert_eq!(journal_mode, "wal");
+            assert_eq!(pragma("busy_timeout").await, 1234);
+            assert_eq!(pragma("cache_size").await, -1000);
+            // 1 = normal
+            assert_eq!(pragma("synchronous").await, 1);
+            assert_eq!(pragma("foreign_keys").await, 1);
+            assert_eq!(pragma("wal_autocheckpoint").await, 0);
+        }
+        assert_eq!(db.writer().options().get_max_connections(), 1);
+
+        db.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
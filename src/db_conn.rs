@@ -0,0 +1,273 @@
+//! Request-scoped database connection — the [`DbConn`] extractor
+//!
+//! A handler that runs several queries can take `DbConn` instead of going
+//! back to the pool for each one. The connection is checked out from the
+//! reader pool the first time a request extracts it, kept in the request
+//! extensions by [`middleware::db_conn`](crate::middleware::db_conn), and
+//! returned to the pool when the response is ready. Requests that never
+//! extract one never check one out.
+//!
+//! Statements run through a `DbConn` are counted and timed per request
+//! ([`QueryLog`]); the middleware logs them with the request ID and warns
+//! about requests that run more than [`MANY_STATEMENTS`].
+//!
+//! Writes still go through `db.writer()`: the reader pool is for reads, see
+//! [`crate::db`].
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use sqlx::pool::PoolConnection;
+use sqlx::sqlite::{
+    Sqlite, SqliteConnection, SqlitePool, SqliteQueryResult, SqliteRow, SqliteStatement,
+    SqliteTypeInfo,
+};
+use sqlx::{Describe, Either, Execute, Executor};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::error::AppError;
+
+/// Statements in one request above which the middleware warns (an N+1
+/// query, most likely)
+pub const MANY_STATEMENTS: u64 = 50;
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + Send + 'a>>;
+
+/// Statements run through a request's [`DbConn`], and the time they took
+#[derive(Debug, Default)]
+pub struct QueryLog {
+    statements: AtomicU64,
+    micros: AtomicU64,
+}
+
+impl QueryLog {
+    pub fn statements(&self) -> u64 {
+        self.statements.load(Ordering::Relaxed)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_micros(self.micros.load(Ordering::Relaxed))
+    }
+
+    fn record(&self, elapsed: Duration) {
+        self.statements.fetch_add(1, Ordering::Relaxed);
+        self.micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Where a request's connection lives between extractions; put in the
+/// request extensions by the middleware
+#[derive(Clone)]
+pub struct ConnSlot(Arc<SlotInner>);
+
+struct SlotInner {
+    pool: SqlitePool,
+    conn: Mutex<Option<PoolConnection<Sqlite>>>,
+    log: Arc<QueryLog>,
+}
+
+impl ConnSlot {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self(Arc::new(SlotInner {
+            pool,
+            conn: Mutex::new(None),
+            log: Arc::new(QueryLog::default()),
+        }))
+    }
+
+    pub fn log(&self) -> &QueryLog {
+        &self.0.log
+    }
+
+    /// Hand the connection back to the pool
+    pub fn release(&self) {
+        self.0.conn.lock().unwrap().take();
+    }
+
+    /// The request's connection, checked out on first use. A `DbConn` still
+    /// held elsewhere means a second checkout, as if there were no slot.
+    async fn checkout(&self) -> Result<DbConn, sqlx::Error> {
+        let kept = self.0.conn.lock().unwrap().take();
+        let conn = match kept {
+            Some(conn) => conn,
+            None => self.0.pool.acquire().await?,
+        };
+        Ok(DbConn {
+            conn: Some(conn),
+            slot: self.clone(),
+        })
+    }
+}
+
+/// A pooled reader connection held for the rest of the request; see the
+/// module docs. Use `&mut conn` wherever sqlx takes an executor.
+pub struct DbConn {
+    // Some until dropped
+    conn: Option<PoolConnection<Sqlite>>,
+    slot: ConnSlot,
+}
+
+impl DbConn {
+    fn connection(&mut self) -> &mut SqliteConnection {
+        self.conn.as_mut().expect("held until drop")
+    }
+}
+
+impl Drop for DbConn {
+    fn drop(&mut self) {
+        // Back to the slot for the next extraction or the middleware
+        if let Some(conn) = self.conn.take() {
+            self.slot.0.conn.lock().unwrap().get_or_insert(conn);
+        }
+    }
+}
+
+impl fmt::Debug for DbConn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DbConn")
+            .field("statements", &self.slot.log().statements())
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for DbConn {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let slot = parts
+            .extensions
+            .get::<ConnSlot>()
+            .cloned()
+            .ok_or_else(|| AppError::internal("DbConn needs the db_conn middleware"))?;
+        Ok(slot.checkout().await?)
+    }
+}
+
+/// Records a statement's time when the statement's stream is dropped
+struct Timer {
+    log: Arc<QueryLog>,
+    started: Instant,
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.log.record(self.started.elapsed());
+    }
+}
+
+impl<'c> Executor<'c> for &'c mut DbConn {
+    type Database = Sqlite;
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxStream<'e, Result<Either<SqliteQueryResult, SqliteRow>, sqlx::Error>>
+    where
+        'c: 'e,
+        E: 'q + Execute<'q, Sqlite>,
+    {
+        let timer = Timer {
+            log: self.slot.0.log.clone(),
+            started: Instant::now(),
+        };
+        let stream = self.connection().fetch_many(query);
+        Box::pin(stream.map(move |step| {
+            let _ = &timer;
+            step
+        }))
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxFuture<'e, Result<Option<SqliteRow>, sqlx::Error>>
+    where
+        'c: 'e,
+        E: 'q + Execute<'q, Sqlite>,
+    {
+        let timer = Timer {
+            log: self.slot.0.log.clone(),
+            started: Instant::now(),
+        };
+        let future = self.connection().fetch_optional(query);
+        Box::pin(async move {
+            let row = future.await;
+            drop(timer);
+            row
+        })
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [SqliteTypeInfo],
+    ) -> BoxFuture<'e, Result<SqliteStatement<'q>, sqlx::Error>>
+    where
+        'c: 'e,
+    {
+        self.connection().prepare_with(sql, parameters)
+    }
+
+    fn describe<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+    ) -> BoxFuture<'e, Result<Describe<Sqlite>, sqlx::Error>>
+    where
+        'c: 'e,
+    {
+        self.connection().describe(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_one_connection_per_request() {
+        let db = crate::db::init_pool(&DatabaseConfig {
+            url: "sqlite::memory:".into(),
+            ..DatabaseConfig::default()
+        })
+        .await
+        .unwrap();
+        let slot = ConnSlot::new(db.reader().clone());
+
+        let mut conn = slot.checkout().await.unwrap();
+        let items: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM items")
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+        let titles: Vec<String> = sqlx::query_scalar("SELECT title FROM items")
+            .fetch_all(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(titles.len() as i64, items);
+        drop(conn);
+
+        // The second extraction gets the same connection back
+        assert_eq!(db.reader().num_idle(), 0);
+        let mut conn = slot.checkout().await.unwrap();
+        sqlx::query("SELECT 1").execute(&mut conn).await.unwrap();
+        drop(conn);
+        assert_eq!(slot.log().statements(), 3);
+
+        // The pool takes a dropped connection back on a spawned task
+        slot.release();
+        for _ in 0..100 {
+            if db.reader().num_idle() == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(db.reader().num_idle(), 1);
+    }
+}
@@ -1,14 +1,17 @@
 //! Error Handling — Typed errors with HTMX-aware responses
 //!
 //! Errors automatically render as HTML fragments suitable for HTMX swaps,
-//! with proper HTTP status codes and optional HX-Retarget headers.
+//! with proper HTTP status codes, see
+//! [`ErrorFragment`](crate::handlers::error_fragment::ErrorFragment).
 
 use axum::{
-    http::{header, StatusCode},
-    response::{Html, IntoResponse, Response},
+    http::StatusCode,
+    response::{IntoResponse, Response},
 };
 use thiserror::Error;
 
+use crate::handlers::error_fragment::ErrorFragment;
+
 pub type AppResult<T> = std::result::Result<T, AppError>;
 
 #[derive(Debug, Error)]
@@ -25,6 +28,9 @@ pub enum AppError {
     #[error("Validation failed: {0}")]
     Validation(String),
 
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 
@@ -42,6 +48,7 @@ impl AppError {
             AppError::NotFound(_) => StatusCode::NOT_FOUND,
             AppError::BadRequest(_) | AppError::Validation(_) => StatusCode::BAD_REQUEST,
             AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
             AppError::Internal(_) | AppError::Anyhow(_) | AppError::Database(_) => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
@@ -53,6 +60,7 @@ impl AppError {
         match self {
             AppError::NotFound(_) => "warning",
             AppError::BadRequest(_) | AppError::Validation(_) => "warning",
+            AppError::TooManyRequests(_) => "warning",
             AppError::Unauthorized => "danger",
             _ => "danger",
         }
@@ -64,43 +72,34 @@ impl AppError {
             AppError::NotFound(_) => "search",
             AppError::BadRequest(_) | AppError::Validation(_) => "exclamation-triangle",
             AppError::Unauthorized => "lock",
+            AppError::TooManyRequests(_) => "hourglass-split",
             _ => "x-circle",
         }
     }
 }
 
+/// Marks a response made from a `500` [`AppError`], carrying its message for
+/// [`report_errors`](crate::middleware::report_errors)
+#[derive(Debug, Clone)]
+pub struct InternalError(pub String);
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let status = self.status_code();
-        let alert_class = self.alert_class();
-        let icon = self.icon();
-        let message = self.to_string();
-
-        // Render as HTML fragment for HTMX
-        let body = format!(
-            r#"<div class="alert alert-{alert_class}" role="alert">
-    <div class="alert-title"><i class="bi bi-{icon}"></i> <strong>Error {code}</strong></div>
-    <div class="alert-body">{message}</div>
-</div>"#,
-            alert_class = alert_class,
-            icon = icon,
-            code = status.as_u16(),
-            message = message,
-        );
-
-        // Build response with HTMX-friendly headers
-        let mut response = (status, Html(body)).into_response();
-
-        // Tell HTMX to show error in a specific target (if toast/notification area exists)
-        response.headers_mut().insert(
-            header::HeaderName::from_static("hx-retarget"),
-            "#error-toast".parse().unwrap(),
-        );
-        response.headers_mut().insert(
-            header::HeaderName::from_static("hx-reswap"),
-            "innerHTML".parse().unwrap(),
-        );
-
+        // The message may echo user input; the fragment escapes it
+        let mut response = ErrorFragment::new(
+            status,
+            format!("Error {}", status.as_u16()),
+            self.to_string(),
+        )
+        .level(self.alert_class())
+        .icon(self.icon())
+        .into_response();
+        if status == StatusCode::INTERNAL_SERVER_ERROR {
+            response
+                .extensions_mut()
+                .insert(InternalError(self.to_string()));
+        }
         response
     }
 }
@@ -122,4 +121,15 @@ impl AppError {
     pub fn validation(msg: impl Into<String>) -> Self {
         Self::Validation(msg.into())
     }
+
+    pub fn too_many_requests(msg: impl Into<String>) -> Self {
+        Self::TooManyRequests(msg.into())
+    }
+}
+
+/// So handlers querying through [`DbConn`](crate::db_conn::DbConn) can use `?`
+impl From<sqlx::Error> for AppError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::Database(error.to_string())
+    }
 }
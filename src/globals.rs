@@ -0,0 +1,342 @@
+//! Template Globals — per-request context merged into every template
+//!
+//! The session middleware builds a [`TemplateGlobals`] for each request and
+//! runs the rest of the stack inside [`scope`]. `define_page!` and
+//! `define_partial!` read it back via [`TemplateGlobals::current_for`] and expose
+//! it to templates as `globals`, so handlers never deal with session cookies
+//! or CSRF tokens just to render a page.
+
+use serde::Serialize;
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+
+use crate::config::{AnnouncementConfig, SiteConfig};
+use crate::handlers::error_fragment;
+use crate::render::TemplateKind;
+use crate::services::breadcrumbs::Breadcrumb;
+use crate::services::flash::{self, FlashMessage};
+use crate::services::tenants::Tenant;
+use crate::services::{PresenceTracker, SessionStore};
+use crate::utils::build_info::BuildInfo;
+use crate::utils::hosts::HostInfo;
+use crate::utils::shortcuts::{self, Shortcut};
+
+/// Session data key holding the signed-in user's display name
+pub const USER_NAME_KEY: &str = "user_name";
+
+/// A sidebar link
+#[derive(Debug, Serialize)]
+pub struct NavItem {
+    pub href: &'static str,
+    pub label: &'static str,
+    /// Bootstrap icon name, without the `bi-` prefix
+    pub icon: &'static str,
+    /// Key exposed as `globals.current_page` while this link is active
+    pub page: &'static str,
+}
+
+/// A titled group of sidebar links
+#[derive(Debug, Serialize)]
+pub struct NavSection {
+    pub title: &'static str,
+    pub items: &'static [NavItem],
+}
+
+/// Sidebar navigation, rendered by `layouts/base.html`
+pub const NAV: &[NavSection] = &[
+    NavSection {
+        title: "Navigation",
+        items: &[
+            NavItem {
+                href: "/",
+                label: "Home",
+                icon: "house",
+                page: "home",
+            },
+            NavItem {
+                href: "/demo",
+                label: "Demo",
+                icon: "lightning",
+                page: "demo",
+            },
+            NavItem {
+                href: "/blog",
+                label: "Blog",
+                icon: "journal-text",
+                page: "blog",
+            },
+            NavItem {
+                href: "/calendar",
+                label: "Calendar",
+                icon: "calendar3",
+                page: "calendar",
+            },
+            NavItem {
+                href: "/components",
+                label: "Components",
+                icon: "grid-1x2",
+                page: "components",
+            },
+        ],
+    },
+    NavSection {
+        title: "Reference",
+        items: &[
+            NavItem {
+                href: "/security",
+                label: "Security",
+                icon: "shield-check",
+                page: "security",
+            },
+            NavItem {
+                href: "/about",
+                label: "About",
+                icon: "info-circle",
+                page: "about",
+            },
+            NavItem {
+                href: "/contact",
+                label: "Contact",
+                icon: "envelope",
+                page: "contact",
+            },
+        ],
+    },
+];
+
+/// The nav page for `path`: the item whose `href` is the longest
+/// segment-wise prefix, so `/demo/items/3` still highlights Demo. `/` only
+/// matches itself.
+pub fn nav_page(path: &str) -> &'static str {
+    NAV.iter()
+        .flat_map(|section| section.items)
+        .filter(|item| match path.strip_prefix(item.href) {
+            Some(rest) => item.href == path || (item.href != "/" && rest.starts_with('/')),
+            None => false,
+        })
+        .max_by_key(|item| item.href.len())
+        .map_or("", |item| item.page)
+}
+
+/// Context available to every template as `globals`
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateGlobals {
+    /// Request path, e.g. `/about`
+    pub current_path: String,
+    /// `current_path` in its canonical spelling, see [`crate::utils::urls`]
+    pub canonical_path: String,
+    /// `[site] base_url` plus the tenant's prefix, for absolute URLs
+    pub base_url: String,
+    /// [`NavItem::page`] of the sidebar link matching `current_path`
+    /// (empty when none does), see [`nav_page`]
+    pub current_page: &'static str,
+    /// CSRF token for this session, sent back by HTMX via `hx-headers`
+    pub csrf_token: String,
+    /// Pending flash messages (consumed by the first page rendered)
+    pub flash: Vec<FlashMessage>,
+    /// Display name of the signed-in user (empty when anonymous)
+    pub user_name: String,
+    /// Whether the session has signed in at `/admin/login`
+    pub is_admin: bool,
+    /// Crate version
+    pub version: &'static str,
+    /// Commit, build time and features; templates show the commit to admins
+    /// only
+    pub build: BuildInfo,
+    pub nav: &'static [NavSection],
+    /// Registered keyboard shortcuts, see [`crate::shortcut!`]
+    pub shortcuts: Vec<&'static Shortcut>,
+    /// What app.js shows when a request gets no answer, see
+    /// [`error_fragment::send_error`]
+    pub send_error: &'static str,
+    /// Trail to the current path, read by templates through `breadcrumbs()`
+    pub breadcrumbs: Vec<Breadcrumb>,
+    /// The request's tenant: name, link prefix and theme overrides
+    pub tenant: Tenant,
+    /// The host the request was made to: admin host, tenant subdomain or
+    /// the site
+    pub host: HostInfo,
+    /// `[announcement]` banner (reloadable); empty message shows none
+    pub announcement: AnnouncementConfig,
+    /// The database is unreachable; pages show a banner until it's back
+    pub db_down: bool,
+    /// Read-only mode: pages show a banner, writes are refused
+    pub read_only: bool,
+    /// Built with the `tailwind` feature: pages link the Tailwind stylesheet
+    pub tailwind: bool,
+    /// The request asked for `?view=print`: the layout renders the page
+    /// alone, without navigation, see [`is_print_view`]
+    pub print_view: bool,
+}
+
+impl Default for TemplateGlobals {
+    fn default() -> Self {
+        Self {
+            current_path: "/".to_string(),
+            canonical_path: "/".to_string(),
+            base_url: SiteConfig::default().base_url,
+            current_page: "",
+            csrf_token: String::new(),
+            flash: Vec::new(),
+            user_name: String::new(),
+            is_admin: false,
+            version: env!("CARGO_PKG_VERSION"),
+            build: BuildInfo::current(),
+            nav: NAV,
+            shortcuts: shortcuts::registered(),
+            send_error: error_fragment::send_error(),
+            breadcrumbs: Vec::new(),
+            tenant: Tenant::default(),
+            host: HostInfo::default(),
+            announcement: AnnouncementConfig::default(),
+            db_down: false,
+            read_only: false,
+            tailwind: crate::utils::css::TAILWIND,
+            print_view: false,
+        }
+    }
+}
+
+/// Whether a request's query asks for the print view (`view=print`). Any
+/// page takes it; the canonical URL stays the page's own, without the query.
+pub fn is_print_view(query: Option<&str>) -> bool {
+    query
+        .and_then(|query| serde_urlencoded::from_str::<Vec<(String, String)>>(query).ok())
+        .is_some_and(|pairs| {
+            pairs
+                .iter()
+                .any(|(key, value)| key == "view" && value == "print")
+        })
+}
+
+/// Where pending flash messages come from — taken lazily, on first render, so
+/// static files and redirects don't swallow them.
+#[derive(Clone)]
+pub struct FlashSource {
+    pub store: Arc<dyn SessionStore>,
+    pub session_id: String,
+}
+
+struct RequestGlobals {
+    globals: TemplateGlobals,
+    flash_source: Option<FlashSource>,
+    flash: OnceLock<Vec<FlashMessage>>,
+    presence: Option<Arc<PresenceTracker>>,
+}
+
+tokio::task_local! {
+    static REQUEST_GLOBALS: RequestGlobals;
+}
+
+impl TemplateGlobals {
+    /// Globals for the request being handled, or defaults outside a request
+    /// (tests, background tasks).
+    ///
+    /// Only pages consume pending flash messages. Partials get an empty list —
+    /// otherwise a polling fragment would swallow a flash meant for the next
+    /// page load.
+    pub fn current_for(kind: TemplateKind) -> TemplateGlobals {
+        REQUEST_GLOBALS
+            .try_with(|request| {
+                let mut globals = request.globals.clone();
+                if kind == TemplateKind::Page {
+                    let flash = request.flash.get_or_init(|| match &request.flash_source {
+                        Some(source) => flash::take(source.store.as_ref(), &source.session_id),
+                        None => Vec::new(),
+                    });
+                    globals.flash.extend(flash.iter().cloned());
+                } else {
+                    globals.flash.clear();
+                }
+                globals
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// The current request's breadcrumb trail — `breadcrumbs()` in templates
+pub fn breadcrumbs() -> Vec<Breadcrumb> {
+    REQUEST_GLOBALS
+        .try_with(|request| request.globals.breadcrumbs.clone())
+        .unwrap_or_default()
+}
+
+/// The current request's `base_url` and `canonical_path`, for
+/// [`SeoMeta`](crate::render::SeoMeta)
+pub fn page_urls() -> (String, String) {
+    REQUEST_GLOBALS
+        .try_with(|request| {
+            (
+                request.globals.base_url.clone(),
+                request.globals.canonical_path.clone(),
+            )
+        })
+        .unwrap_or_else(|_| {
+            let globals = TemplateGlobals::default();
+            (globals.base_url, globals.canonical_path)
+        })
+}
+
+/// Whether anyone going by `name` is online — `is_online(name)` in templates.
+/// Always false outside a request.
+pub fn is_online(name: &str) -> bool {
+    REQUEST_GLOBALS
+        .try_with(|request| {
+            request
+                .presence
+                .as_ref()
+                .is_some_and(|presence| presence.is_online(name))
+        })
+        .unwrap_or(false)
+}
+
+/// Run `future` (the rest of the request) with `globals` as the current globals
+pub async fn scope<F: Future>(
+    globals: TemplateGlobals,
+    flash_source: Option<FlashSource>,
+    presence: Option<Arc<PresenceTracker>>,
+    future: F,
+) -> F::Output {
+    let request = RequestGlobals {
+        globals,
+        flash_source,
+        flash: OnceLock::new(),
+        presence,
+    };
+    REQUEST_GLOBALS.scope(request, future).await
+}
+
+/// Synchronous [`scope`] — for rendering with specific globals outside a
+/// request, e.g. in snapshot tests.
+pub fn sync_scope<R>(globals: TemplateGlobals, f: impl FnOnce() -> R) -> R {
+    let request = RequestGlobals {
+        globals,
+        flash_source: None,
+        flash: OnceLock::new(),
+        presence: None,
+    };
+    REQUEST_GLOBALS.sync_scope(request, f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nav_page_matches_nested_routes() {
+        assert_eq!(nav_page("/"), "home");
+        assert_eq!(nav_page("/demo"), "demo");
+        assert_eq!(nav_page("/demo/items/3"), "demo");
+        assert_eq!(nav_page("/demolition"), "");
+        assert_eq!(nav_page("/unknown"), "");
+    }
+
+    #[test]
+    fn test_is_print_view() {
+        assert!(is_print_view(Some("view=print")));
+        assert!(is_print_view(Some("page=2&view=print")));
+        assert!(!is_print_view(Some("view=printer")));
+        assert!(!is_print_view(Some("preview=print")));
+        assert!(!is_print_view(None));
+    }
+}
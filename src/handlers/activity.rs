@@ -0,0 +1,293 @@
+//! Activity Feed Handlers — `/admin/activity`
+//!
+//! The page loads `/partials/activity-feed?subject=..`, newest first and
+//! grouped by day. "Load more" fetches the next page (`before=<id>`) in place
+//! of the button; `day` carries the last date shown so a day split across
+//! pages doesn't get a second heading.
+//!
+//! While the feed is open, `static/js/activity.js` listens on
+//! `/admin/activity/stream` and prepends each new entry, as a one-entry day
+//! group rendered by the server. Everything here is admin-only: the feed
+//! records sign-in attempts.
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use chrono::{Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+
+use crate::globals::USER_NAME_KEY;
+use crate::models::AppState;
+use crate::render::PageMeta;
+use crate::services::activities::{Activity, ActivityChange, NewActivity, SUBJECTS};
+use crate::services::avatars;
+
+/// Entries per page
+const PAGE_SIZE: usize = 20;
+
+crate::define_page!(
+    AdminActivityPage,
+    "pages/admin/activity.html",
+    {
+        // Empty for every subject
+        subject: String,
+        filters: Vec<ActivityFilter>
+    },
+    PageMeta::new("Activity")
+);
+
+crate::define_partial!(ActivityFeedPartial, "partials/activity_feed.html", {
+    subject: String,
+    days: Vec<ActivityDay>,
+    empty: bool,
+    // Another page follows: `next` is the `before` to ask for, `last_day`
+    // the last date on this one
+    more: bool,
+    next: u32,
+    last_day: String,
+    // The first page comes with the feed's wrapper; later ones slot into it
+    first_page: bool
+});
+
+crate::define_partial!(ActivityDayPartial, "partials/activity_day.html", {
+    day: ActivityDay
+});
+
+/// A filter chip above the feed
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivityFilter {
+    // Empty for "All"
+    pub subject: String,
+    pub label: String,
+    pub active: bool,
+}
+
+/// Entries from one day
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivityDay {
+    // `YYYY-MM-DD`
+    pub date: String,
+    // `Today`, `Yesterday` or e.g. `Monday 4 March 2024`
+    pub label: String,
+    // False when the day continues from the previous page
+    pub heading: bool,
+    pub entries: Vec<ActivityEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivityEntry {
+    pub id: u32,
+    pub kind: String,
+    // Bootstrap icon for the subject
+    pub icon: String,
+    pub actor: String,
+    pub avatar: String,
+    pub summary: String,
+    pub link: String,
+    pub has_link: bool,
+    // `HH:MM` UTC
+    pub time: String,
+}
+
+impl From<Activity> for ActivityEntry {
+    fn from(activity: Activity) -> Self {
+        let icon = match activity.subject() {
+            "item" => "check2-square",
+            "comment" => "chat-left-text",
+            "post" => "journal-text",
+            "admin" => "shield-lock",
+            _ => "activity",
+        };
+        Self {
+            id: activity.id,
+            icon: icon.into(),
+            avatar: avatars::url(&activity.actor),
+            has_link: !activity.link.is_empty(),
+            time: activity.created_at.get(11..16).unwrap_or_default().into(),
+            kind: activity.kind,
+            actor: activity.actor,
+            summary: activity.summary,
+            link: activity.link,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ActivityQuery {
+    pub subject: Option<String>,
+    pub before: Option<u32>,
+    pub day: Option<String>,
+}
+
+/// Record an activity and hand it to open feeds
+pub fn record(state: &AppState, activity: NewActivity) {
+    let activity = state.services.activities.record(activity);
+    state.services.activity_hub.recorded(&activity);
+}
+
+/// Who the session is, for activities without a better name
+pub fn actor(state: &AppState, sid: &str) -> String {
+    state
+        .services
+        .sessions
+        .get(sid)
+        .and_then(|session| session.data.get(USER_NAME_KEY).cloned())
+        .unwrap_or_else(|| "Anonymous".to_string())
+}
+
+/// A known subject, or `None` for everything
+fn selected_subject(query: &ActivityQuery) -> Option<&'static str> {
+    let subject = query.subject.as_deref()?;
+    SUBJECTS
+        .iter()
+        .map(|(name, _)| *name)
+        .find(|name| *name == subject)
+}
+
+fn day_label(date: &str, today: NaiveDate) -> String {
+    match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(day) if day == today => "Today".into(),
+        Ok(day) if day == today - Duration::days(1) => "Yesterday".into(),
+        Ok(day) => day.format("%A %-d %B %Y").to_string(),
+        Err(_) => date.to_string(),
+    }
+}
+
+/// Group newest-first activities by day. `continues` is the last date of the
+/// previous page.
+fn group_by_day(activities: Vec<Activity>, continues: &str, today: NaiveDate) -> Vec<ActivityDay> {
+    let mut days: Vec<ActivityDay> = Vec::new();
+    for activity in activities {
+        let date = activity.day().to_string();
+        match days.last_mut() {
+            Some(day) if day.date == date => day.entries.push(activity.into()),
+            _ => days.push(ActivityDay {
+                label: day_label(&date, today),
+                heading: !(days.is_empty() && date == continues),
+                date,
+                entries: vec![activity.into()],
+            }),
+        }
+    }
+    days
+}
+
+pub async fn activity_page(Query(query): Query<ActivityQuery>) -> AdminActivityPage {
+    let subject = selected_subject(&query).unwrap_or_default();
+    let mut filters = vec![ActivityFilter {
+        subject: String::new(),
+        label: "All".into(),
+        active: subject.is_empty(),
+    }];
+    filters.extend(SUBJECTS.iter().map(|(name, label)| ActivityFilter {
+        subject: name.to_string(),
+        label: label.to_string(),
+        active: *name == subject,
+    }));
+    AdminActivityPage {
+        subject: subject.into(),
+        filters,
+    }
+}
+
+/// One page of the feed
+pub async fn activity_feed(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ActivityQuery>,
+) -> ActivityFeedPartial {
+    let subject = selected_subject(&query);
+    let mut activities = state
+        .services
+        .activities
+        .list(subject, query.before, PAGE_SIZE + 1);
+    let more = activities.len() > PAGE_SIZE;
+    activities.truncate(PAGE_SIZE);
+
+    let next = activities.last().map(|a| a.id).unwrap_or_default();
+    let last_day = activities
+        .last()
+        .map(|a| a.day().to_string())
+        .unwrap_or_default();
+    let continues = query.day.as_deref().unwrap_or_default();
+    let days = group_by_day(activities, continues, Utc::now().date_naive());
+    ActivityFeedPartial {
+        subject: subject.unwrap_or_default().into(),
+        empty: days.is_empty(),
+        days,
+        more,
+        next,
+        last_day,
+        first_page: query.before.is_none(),
+    }
+}
+
+/// GET /admin/activity/stream — each new activity under the feed's filter,
+/// as a rendered one-entry day group
+pub async fn activity_stream(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ActivityQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let subject = selected_subject(&query);
+    let activities = BroadcastStream::new(state.services.activity_hub.subscribe())
+        .take_while(|change| !matches!(change, Ok(ActivityChange::Shutdown)))
+        .filter_map(move |change| match change {
+            Ok(ActivityChange::Recorded(activity))
+                if subject.is_none_or(|s| activity.subject() == s) =>
+            {
+                let today = Utc::now().date_naive();
+                let day = group_by_day(vec![activity], "", today).remove(0);
+                let html = ActivityDayPartial { day }.render_fragment().0;
+                Some(Ok(Event::default().event("activity").data(html)))
+            }
+            // A lagged stream missed entries; they show on the next load
+            _ => None,
+        });
+    Sse::new(activities).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activity(id: u32, created_at: &str) -> Activity {
+        Activity {
+            id,
+            kind: "item.created".into(),
+            actor: "Ada".into(),
+            summary: "Created an item".into(),
+            link: String::new(),
+            created_at: created_at.into(),
+        }
+    }
+
+    #[test]
+    fn test_group_by_day() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let days = group_by_day(
+            vec![
+                activity(4, "2024-03-05 10:00:00"),
+                activity(3, "2024-03-04 18:00:00"),
+                activity(2, "2024-03-04 09:00:00"),
+                activity(1, "2024-03-01 12:00:00"),
+            ],
+            "",
+            today,
+        );
+        let labels: Vec<_> = days.iter().map(|d| d.label.as_str()).collect();
+        assert_eq!(labels, ["Today", "Yesterday", "Friday 1 March 2024"]);
+        assert_eq!(days[1].entries.len(), 2);
+        assert_eq!(days[1].entries[0].time, "18:00");
+
+        // The next page picks up where the last left off
+        let days = group_by_day(
+            vec![activity(1, "2024-03-04 08:00:00")],
+            "2024-03-04",
+            today,
+        );
+        assert!(!days[0].heading);
+    }
+}
@@ -0,0 +1,1015 @@
+//! Admin Handlers — sign-in, the post editor, the webhook delivery log, the
+//! effective config, read-only mode, runtime log levels, backups, redirect
+//! rules and API tokens
+//!
+//! Everything except the sign-in form and backup downloads sits behind
+//! [`require_admin`](crate::middleware::require_admin); downloads check a
+//! signed link instead, so scripts can fetch backups too. Forms submit through
+//! HTMX, so the CSRF middleware checks them like any other write; on success
+//! the handlers answer with a redirect plus a flash message.
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+    Extension, Form,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+use crate::error::{AppError, AppResult};
+use crate::handlers::activity;
+use crate::handlers::partials::format_bytes;
+use crate::models::AppState;
+use crate::render::PageMeta;
+use crate::services::activities::NewActivity;
+use crate::services::admin::ADMIN_KEY;
+use crate::services::api_tokens::{self, ApiToken, Scope};
+use crate::services::backups::{self, Backup};
+use crate::services::flash::{self, FlashMessage};
+use crate::services::live_config::{self, ConfigRow};
+use crate::services::log_level::LogLevel;
+use crate::services::posts::{Post, PostInput, PostStatus};
+use crate::services::redirects::{self, Redirect};
+use crate::services::sanitize::{self, Profile, SanitizedHtml};
+use crate::services::session::{RotatedSession, SessionId};
+use crate::services::webhooks_out::Delivery;
+use crate::utils::clipboard::CopyButton;
+use crate::utils::htmx;
+
+crate::sitemap_route!("/admin", exclude);
+crate::sitemap_route!("/backups", exclude);
+
+/// Longest accepted post title, in characters
+const MAX_TITLE_CHARS: usize = 120;
+/// Longest accepted summary, in characters
+const MAX_SUMMARY_CHARS: usize = 300;
+/// Longest accepted Markdown body, in characters
+const MAX_BODY_CHARS: usize = 50_000;
+
+/// A row in the admin post list
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminPostRow {
+    pub id: u32,
+    pub slug: String,
+    pub title: String,
+    pub published: bool,
+    /// `YYYY-MM-DD HH:MM`
+    pub updated: String,
+}
+
+impl From<Post> for AdminPostRow {
+    fn from(post: Post) -> Self {
+        Self {
+            id: post.id,
+            published: post.status == PostStatus::Published,
+            updated: post.updated_at.replace('T', " ").chars().take(16).collect(),
+            slug: post.slug,
+            title: post.title,
+        }
+    }
+}
+
+/// A row in the backup list
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupRow {
+    pub name: String,
+    /// `48.2 MB`
+    pub size: String,
+    /// `YYYY-MM-DD HH:MM UTC`
+    pub created: String,
+    /// Signed download link, valid for [`backups::DOWNLOAD_TTL`]
+    pub url: String,
+}
+
+/// A row in the API token list
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenRow {
+    pub id: u32,
+    pub name: String,
+    /// `exports, feeds`
+    pub scopes: String,
+    /// The token's first characters
+    pub prefix: String,
+    /// `YYYY-MM-DD HH:MM`, UTC
+    pub created: String,
+    /// Same format, or `Never`
+    pub last_used: String,
+    pub revoked: bool,
+}
+
+impl From<ApiToken> for TokenRow {
+    fn from(token: ApiToken) -> Self {
+        let minutes = |at: &str| at.chars().take(16).collect::<String>();
+        Self {
+            id: token.id,
+            scopes: token
+                .scopes
+                .iter()
+                .map(|scope| scope.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            created: minutes(&token.created_at),
+            last_used: match token.last_used_at.as_str() {
+                "" => "Never".to_string(),
+                at => minutes(at),
+            },
+            revoked: token.is_revoked(),
+            prefix: token.prefix,
+            name: token.name,
+        }
+    }
+}
+
+impl BackupRow {
+    fn new(state: &AppState, backup: Backup) -> Self {
+        let token = state.services.tokens.sign(
+            backups::TOKEN_PURPOSE,
+            &backup.name,
+            Some(backups::DOWNLOAD_TTL),
+        );
+        Self {
+            size: format_bytes(backup.size),
+            created: backup.created_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            url: format!("/backups/download?token={token}"),
+            name: backup.name,
+        }
+    }
+}
+
+// =============================================================================
+// Templates
+// =============================================================================
+
+crate::define_page!(
+    AdminLoginPage,
+    "pages/admin/login.html",
+    {},
+    PageMeta::new("Admin sign-in")
+);
+
+crate::define_page!(
+    AdminPostsPage,
+    "pages/admin/posts.html",
+    {
+        posts: Vec<AdminPostRow>,
+        empty: bool
+    },
+    PageMeta::new("Posts")
+);
+
+crate::define_page!(
+    AdminPostEditPage,
+    "pages/admin/post_edit.html",
+    {
+        // 0 for a new post
+        id: u32,
+        title: String,
+        slug: String,
+        summary: String,
+        body: String,
+        published: bool,
+        preview: SanitizedHtml
+    },
+    PageMeta::new("Edit post")
+);
+
+crate::define_page!(
+    AdminWebhooksPage,
+    "pages/admin/webhooks.html",
+    {
+        // `url — events` for each configured endpoint
+        endpoints: Vec<String>,
+        no_endpoints: bool,
+        deliveries: Vec<Delivery>,
+        empty: bool
+    },
+    PageMeta::new("Webhooks")
+);
+
+crate::define_page!(
+    AdminConfigPage,
+    "pages/admin/config.html",
+    {
+        rows: Vec<ConfigRow>,
+        // `YYYY-MM-DD HH:MM:SS UTC`, empty before the first reload
+        last_reload: String,
+        // Why the last reload was rejected
+        error: String,
+        // Sections changed on disk that need a restart, comma-separated
+        restart_needed: String,
+        // Writes are refused right now
+        read_only: bool,
+        // Set from this page rather than `[maintenance] read_only`
+        read_only_pinned: bool
+    },
+    PageMeta::new("Config")
+);
+
+crate::define_page!(
+    AdminBackupsPage,
+    "pages/admin/backups.html",
+    {
+        // `[backups] dir` is set
+        enabled: bool,
+        dir: String,
+        // `[backups] interval_hours`; 0 for manual backups only
+        interval_hours: u64,
+        keep: usize,
+        // Newest first
+        backups: Vec<BackupRow>,
+        empty: bool
+    },
+    PageMeta::new("Backups")
+);
+
+crate::define_page!(
+    AdminRedirectsPage,
+    "pages/admin/redirects.html",
+    {
+        rules: Vec<Redirect>,
+        empty: bool
+    },
+    PageMeta::new("Redirects")
+);
+
+crate::define_partial!(RedirectsPartial, "partials/redirects.html", {
+    rules: Vec<Redirect>,
+    empty: bool
+});
+
+crate::define_page!(
+    AdminTokensPage,
+    "pages/admin/tokens.html",
+    {
+        tokens: Vec<TokenRow>,
+        empty: bool,
+        secret: String,
+        copy: CopyButton
+    },
+    PageMeta::new("API tokens")
+);
+
+crate::define_partial!(ApiTokensPartial, "partials/api_tokens.html", {
+    // Newest first, revoked ones included
+    tokens: Vec<TokenRow>,
+    empty: bool,
+    // The token just created, shown this once; empty otherwise
+    secret: String,
+    copy: CopyButton
+});
+
+crate::define_partial!(
+    LogLevelPartial,
+    "partials/log_level.html",
+    {
+        // The whole filter in effect
+        directives: String,
+        // `[logging] level`
+        configured: String,
+        // Override on top; empty when there's none
+        extra: String,
+        // `HH:MM UTC` the override ends
+        expires: String
+    }
+);
+
+crate::define_partial!(
+    WebhookDeliveriesPartial,
+    "partials/webhook_deliveries.html",
+    {
+        deliveries: Vec<Delivery>,
+        empty: bool
+    }
+);
+
+crate::define_partial!(PostPreviewPartial, "partials/post_preview.html", {
+    preview: SanitizedHtml
+});
+
+crate::gallery_example!(PostPreviewPartial,
+    title: "Post Preview",
+    description: "Markdown rendered and sanitized as the editor types.",
+    snippet: r##"<textarea name="body" hx-post="/admin/posts/preview" hx-trigger="input changed delay:400ms"
+          hx-target="#post-preview" hx-swap="outerHTML"></textarea>"##,
+    example: PostPreviewPartial {
+        preview: sanitize::clean(Profile::RichText, "<h2>Hello</h2><p>Rendered <em>live</em>.</p>"),
+    },
+);
+
+// =============================================================================
+// Sign-in
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    pub password: String,
+}
+
+pub async fn login_page(State(state): State<Arc<AppState>>) -> AppResult<AdminLoginPage> {
+    if !state.services.admin.is_enabled() {
+        return Err(AppError::not_found("Admin is disabled"));
+    }
+    Ok(AdminLoginPage {})
+}
+
+pub async fn login(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    headers: HeaderMap,
+    Form(form): Form<LoginForm>,
+) -> AppResult<Response> {
+    if !state.services.admin.is_enabled() {
+        return Err(AppError::not_found("Admin is disabled"));
+    }
+    // Per session, and site-wide against guessing from many sessions;
+    // `[rate_limits.admin_login]`, reloadable
+    let limits = state.services.live.get().rate_limits.admin_login;
+    let limiter = &state.services.rate_limiter;
+    if !limiter.check(&format!("admin_login:{sid}"), limits.session())
+        || !limiter.check("admin_login:*", limits.site())
+    {
+        tracing::warn!("admin sign-in rate limit reached");
+        return Err(AppError::too_many_requests(
+            "Too many sign-in attempts. Please try again later.",
+        ));
+    }
+    if !state.services.admin.verify(&form.password) {
+        tracing::warn!("failed admin sign-in");
+        activity::record(
+            &state,
+            NewActivity::new("admin.sign_in_failed", "Unknown", "Failed admin sign-in"),
+        );
+        return Err(AppError::Unauthorized);
+    }
+    activity::record(
+        &state,
+        NewActivity::new("admin.signed_in", "Admin", "Signed in"),
+    );
+
+    // A new ID, so one planted or seen before sign-in never becomes an admin's
+    let sessions = state.services.sessions.as_ref();
+    let session = sessions.rotate(&sid);
+    sessions.set_data(&session.id, ADMIN_KEY, "1".to_string());
+    flash::push(
+        sessions,
+        &session.id,
+        FlashMessage::new("success", "Signed in"),
+    );
+    let mut response = htmx::redirect(&headers, "/admin/posts");
+    response.extensions_mut().insert(RotatedSession(session.id));
+    Ok(response)
+}
+
+pub async fn logout(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    headers: HeaderMap,
+) -> Response {
+    let sessions = state.services.sessions.as_ref();
+    sessions.take_data(&sid, ADMIN_KEY);
+    let session = sessions.rotate(&sid);
+    activity::record(
+        &state,
+        NewActivity::new("admin.signed_out", "Admin", "Signed out"),
+    );
+    flash::push(
+        sessions,
+        &session.id,
+        FlashMessage::new("info", "Signed out"),
+    );
+    let mut response = htmx::redirect(&headers, "/");
+    response.extensions_mut().insert(RotatedSession(session.id));
+    response
+}
+
+// =============================================================================
+// Posts
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct PostForm {
+    pub title: String,
+    #[serde(default)]
+    pub slug: String,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub body: String,
+    /// Checkbox — present only when ticked
+    pub published: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PreviewForm {
+    #[serde(default)]
+    pub body: String,
+}
+
+/// Every post, drafts included
+pub async fn posts_page(State(state): State<Arc<AppState>>) -> AdminPostsPage {
+    let posts: Vec<AdminPostRow> = state
+        .services
+        .posts
+        .list_all()
+        .into_iter()
+        .map(AdminPostRow::from)
+        .collect();
+    AdminPostsPage {
+        empty: posts.is_empty(),
+        posts,
+    }
+}
+
+pub async fn new_post_page() -> AdminPostEditPage {
+    AdminPostEditPage {
+        id: 0,
+        title: String::new(),
+        slug: String::new(),
+        summary: String::new(),
+        body: String::new(),
+        published: false,
+        preview: SanitizedHtml::default(),
+    }
+}
+
+pub async fn edit_post_page(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u32>,
+) -> AppResult<AdminPostEditPage> {
+    let post = state
+        .services
+        .posts
+        .get_by_id(id)
+        .ok_or_else(|| AppError::not_found("Post not found"))?;
+    Ok(AdminPostEditPage {
+        id: post.id,
+        preview: sanitize::markdown(Profile::RichText, &post.body),
+        published: post.status == PostStatus::Published,
+        title: post.title,
+        slug: post.slug,
+        summary: post.summary,
+        body: post.body,
+    })
+}
+
+pub async fn create_post(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    headers: HeaderMap,
+    Form(form): Form<PostForm>,
+) -> AppResult<Response> {
+    let post = state.services.posts.create(validate(form)?);
+    record_post(&state, "post.created", "Created", &post);
+    saved(&state, &sid, &headers, &post)
+}
+
+pub async fn update_post(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    headers: HeaderMap,
+    Path(id): Path<u32>,
+    Form(form): Form<PostForm>,
+) -> AppResult<Response> {
+    let post = state
+        .services
+        .posts
+        .update(id, validate(form)?)
+        .ok_or_else(|| AppError::not_found("Post not found"))?;
+    record_post(&state, "post.updated", "Updated", &post);
+    saved(&state, &sid, &headers, &post)
+}
+
+pub async fn delete_post(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    headers: HeaderMap,
+    Path(id): Path<u32>,
+) -> AppResult<Response> {
+    let post = state
+        .services
+        .posts
+        .get_by_id(id)
+        .ok_or_else(|| AppError::not_found("Post not found"))?;
+    if !state.services.posts.delete(id) {
+        return Err(AppError::not_found("Post not found"));
+    }
+    activity::record(
+        &state,
+        NewActivity::new(
+            "post.deleted",
+            "Admin",
+            format!("Deleted post \"{}\"", post.title),
+        ),
+    );
+    flash::push(
+        state.services.sessions.as_ref(),
+        &sid,
+        FlashMessage::new("success", "Post deleted"),
+    );
+    Ok(htmx::redirect(&headers, "/admin/posts"))
+}
+
+/// Live Markdown preview for the editor
+pub async fn preview_post(Form(form): Form<PreviewForm>) -> PostPreviewPartial {
+    PostPreviewPartial {
+        preview: sanitize::markdown(Profile::RichText, &form.body),
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn validate(form: PostForm) -> AppResult<PostInput> {
+    let title = form.title.trim();
+    if title.is_empty() {
+        return Err(AppError::validation("Title can't be empty"));
+    }
+    for (field, value, max) in [
+        ("Titles", title, MAX_TITLE_CHARS),
+        ("Summaries", form.summary.trim(), MAX_SUMMARY_CHARS),
+        ("Posts", form.body.as_str(), MAX_BODY_CHARS),
+    ] {
+        if value.chars().count() > max {
+            return Err(AppError::validation(format!(
+                "{field} are limited to {max} characters"
+            )));
+        }
+    }
+
+    Ok(PostInput {
+        title: title.to_string(),
+        slug: form.slug.trim().to_string(),
+        summary: form.summary.trim().to_string(),
+        body: form.body,
+        status: if form.published.is_some() {
+            PostStatus::Published
+        } else {
+            PostStatus::Draft
+        },
+    })
+}
+
+/// Log a post write, linking to the post if it's public
+fn record_post(state: &AppState, kind: &str, verb: &str, post: &Post) {
+    let link = match post.status {
+        PostStatus::Published => format!("/blog/{}", post.slug),
+        PostStatus::Draft => format!("/admin/posts/{}/edit", post.id),
+    };
+    activity::record(
+        state,
+        NewActivity::new(kind, "Admin", format!("{verb} post \"{}\"", post.title)).link(link),
+    );
+}
+
+/// Back to the editor for the saved post, with a flash naming its URL
+fn saved(state: &AppState, sid: &str, headers: &HeaderMap, post: &Post) -> AppResult<Response> {
+    let message = match post.status {
+        PostStatus::Published => format!("Published at /blog/{}", post.slug),
+        PostStatus::Draft => "Draft saved".to_string(),
+    };
+    flash::push(
+        state.services.sessions.as_ref(),
+        sid,
+        FlashMessage::new("success", message),
+    );
+    Ok(htmx::redirect(
+        headers,
+        &format!("/admin/posts/{}/edit", post.id),
+    ))
+}
+
+// =============================================================================
+// Webhook delivery log
+// =============================================================================
+
+pub async fn webhooks_page(State(state): State<Arc<AppState>>) -> AdminWebhooksPage {
+    let endpoints: Vec<String> = state
+        .config
+        .webhooks
+        .endpoints
+        .iter()
+        .filter(|e| !e.url.is_empty())
+        .map(|e| {
+            if e.events.is_empty() {
+                format!("{} — all events", e.url)
+            } else {
+                format!("{} — {}", e.url, e.events.join(", "))
+            }
+        })
+        .collect();
+    let WebhookDeliveriesPartial { deliveries, empty } = webhook_deliveries(State(state)).await;
+    AdminWebhooksPage {
+        no_endpoints: endpoints.is_empty(),
+        endpoints,
+        deliveries,
+        empty,
+    }
+}
+
+/// Polled by the webhooks page
+pub async fn webhook_deliveries(State(state): State<Arc<AppState>>) -> WebhookDeliveriesPartial {
+    let deliveries = state.services.events.deliveries();
+    WebhookDeliveriesPartial {
+        empty: deliveries.is_empty(),
+        deliveries,
+    }
+}
+
+// =============================================================================
+// Effective config
+// =============================================================================
+
+pub async fn config_page(State(state): State<Arc<AppState>>) -> AdminConfigPage {
+    let live = &state.services.live;
+    let status = live.status();
+    AdminConfigPage {
+        rows: live_config::rows(&live.get()),
+        last_reload: status
+            .last_reload
+            .map(|at| at.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_default(),
+        error: status.error,
+        restart_needed: status.restart_needed.join(", "),
+        read_only: live.read_only(),
+        read_only_pinned: live.pinned_read_only().is_some(),
+    }
+}
+
+/// Reload from disk now, as SIGHUP would
+pub async fn reload_config(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    headers: HeaderMap,
+) -> Response {
+    let live = state.services.live.clone();
+    let reloaded = tokio::task::spawn_blocking(move || live.reload())
+        .await
+        .unwrap_or(false);
+    let message = if reloaded {
+        activity::record(
+            &state,
+            NewActivity::new("admin.config_reloaded", "Admin", "Reloaded the config"),
+        );
+        FlashMessage::new("success", "Config reloaded")
+    } else {
+        FlashMessage::new("danger", "Config has errors, nothing changed")
+    };
+    flash::push(state.services.sessions.as_ref(), &sid, message);
+    htmx::redirect(&headers, "/admin/config")
+}
+
+#[derive(Deserialize)]
+pub struct ReadOnlyForm {
+    /// `on`, `off`, or `config` to follow `[maintenance] read_only` again
+    pub mode: String,
+}
+
+/// Pin read-only mode on or off until restart, or hand it back to the config
+pub async fn set_read_only(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    headers: HeaderMap,
+    Form(form): Form<ReadOnlyForm>,
+) -> AppResult<Response> {
+    let (pinned, summary) = match form.mode.as_str() {
+        "on" => (Some(true), "Turned read-only mode on"),
+        "off" => (Some(false), "Turned read-only mode off"),
+        "config" => (None, "Handed read-only mode back to the config"),
+        _ => return Err(AppError::bad_request("Unknown read-only mode")),
+    };
+    let live = &state.services.live;
+    live.set_read_only(pinned);
+    activity::record(
+        &state,
+        NewActivity::new("admin.read_only", "Admin", summary),
+    );
+    let message = if live.read_only() {
+        "Read-only mode is on: writes are refused"
+    } else {
+        "Read-only mode is off"
+    };
+    flash::push(
+        state.services.sessions.as_ref(),
+        &sid,
+        FlashMessage::new("success", message),
+    );
+    Ok(htmx::redirect(&headers, "/admin/config"))
+}
+
+// =============================================================================
+// Backups
+// =============================================================================
+
+pub async fn backups_page(State(state): State<Arc<AppState>>) -> AdminBackupsPage {
+    let service = &state.services.backups;
+    let config = &state.config.backups;
+    let backups: Vec<BackupRow> = service
+        .list()
+        .into_iter()
+        .map(|backup| BackupRow::new(&state, backup))
+        .collect();
+    AdminBackupsPage {
+        enabled: service.is_enabled(),
+        dir: service
+            .dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default(),
+        interval_hours: config.interval_hours,
+        keep: config.keep,
+        empty: backups.is_empty(),
+        backups,
+    }
+}
+
+/// Back up now, on top of the schedule
+pub async fn create_backup(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    headers: HeaderMap,
+) -> Response {
+    let message = match state.services.backups.run(&state.db).await {
+        Ok(backup) => {
+            activity::record(
+                &state,
+                NewActivity::new(
+                    "admin.backup",
+                    "Admin",
+                    format!("Backed up the database to {}", backup.name),
+                ),
+            );
+            FlashMessage::new("success", format!("Backed up to {}", backup.name))
+        }
+        Err(e) => FlashMessage::new("danger", format!("Backup failed: {e}")),
+    };
+    flash::push(state.services.sessions.as_ref(), &sid, message);
+    htmx::redirect(&headers, "/admin/backups")
+}
+
+#[derive(Deserialize)]
+pub struct DownloadQuery {
+    pub token: String,
+}
+
+/// Stream a backup named by a signed link; no session needed
+pub async fn download_backup(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DownloadQuery>,
+    request: Request,
+) -> AppResult<Response> {
+    let name = state
+        .services
+        .tokens
+        .verify(backups::TOKEN_PURPOSE, &query.token)
+        .ok_or_else(|| AppError::not_found("This download link is invalid or has expired"))?;
+    let path = state
+        .services
+        .backups
+        .path(&name)
+        .ok_or_else(|| AppError::not_found("That backup no longer exists"))?;
+    let mut response = ServeFile::new(path)
+        .oneshot(request)
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?
+        .map(Body::new)
+        .into_response();
+    // Backup names are `backup-<digits and dashes>.db`, safe to quote as is
+    response.headers_mut().insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&format!("attachment; filename=\"{name}\""))
+            .expect("backup names are ASCII"),
+    );
+    Ok(response)
+}
+
+// =============================================================================
+// Log level
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct LogLevelForm {
+    pub directives: String,
+    /// How long the override lasts
+    pub minutes: u64,
+}
+
+impl LogLevelPartial {
+    fn new(level: &LogLevel) -> Self {
+        let current = level.current();
+        Self {
+            directives: level.directives(),
+            expires: current
+                .expires
+                .map(|at| at.format("%H:%M UTC").to_string())
+                .unwrap_or_default(),
+            configured: current.configured,
+            extra: current.extra,
+        }
+    }
+}
+
+/// Embedded in the config page
+pub async fn log_level(State(state): State<Arc<AppState>>) -> LogLevelPartial {
+    LogLevelPartial::new(&state.services.log_level)
+}
+
+/// Layer extra directives on the configured level for a while
+pub async fn set_log_level(
+    State(state): State<Arc<AppState>>,
+    Form(form): Form<LogLevelForm>,
+) -> AppResult<LogLevelPartial> {
+    let minutes = form.minutes.clamp(1, 60);
+    state
+        .services
+        .log_level
+        .set_override(&form.directives, Duration::from_secs(minutes * 60))
+        .map_err(AppError::validation)?;
+    activity::record(
+        &state,
+        NewActivity::new(
+            "admin.log_level",
+            "Admin",
+            format!("Logging {} for {minutes} minutes", form.directives.trim()),
+        ),
+    );
+    Ok(LogLevelPartial::new(&state.services.log_level))
+}
+
+/// Back to the configured level
+pub async fn reset_log_level(State(state): State<Arc<AppState>>) -> LogLevelPartial {
+    state.services.log_level.reset();
+    LogLevelPartial::new(&state.services.log_level)
+}
+
+// =============================================================================
+// Redirects
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct RedirectForm {
+    pub from_path: String,
+    pub target: String,
+    pub status: u16,
+}
+
+pub async fn redirects_page(State(state): State<Arc<AppState>>) -> AdminRedirectsPage {
+    let RedirectsPartial { rules, empty } = redirect_rules(&state);
+    AdminRedirectsPage { rules, empty }
+}
+
+fn redirect_rules(state: &AppState) -> RedirectsPartial {
+    let rules = state.services.redirects.list();
+    RedirectsPartial {
+        empty: rules.is_empty(),
+        rules,
+    }
+}
+
+/// Add a rule, or replace the one for the same path
+pub async fn save_redirect(
+    State(state): State<Arc<AppState>>,
+    Form(form): Form<RedirectForm>,
+) -> AppResult<RedirectsPartial> {
+    let rule = redirects::validate(&form.from_path, &form.target, form.status)
+        .map_err(AppError::validation)?;
+    let rule = state.services.redirects.save(rule);
+    activity::record(
+        &state,
+        NewActivity::new(
+            "admin.redirect",
+            "Admin",
+            format!(
+                "Redirected {} to {} ({})",
+                rule.from_path, rule.target, rule.status
+            ),
+        )
+        .link("/admin/redirects"),
+    );
+    Ok(redirect_rules(&state))
+}
+
+pub async fn delete_redirect(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u32>,
+) -> AppResult<RedirectsPartial> {
+    let rule = state
+        .services
+        .redirects
+        .list()
+        .into_iter()
+        .find(|rule| rule.id == id)
+        .ok_or_else(|| AppError::not_found("That redirect no longer exists"))?;
+    state.services.redirects.delete(id);
+    activity::record(
+        &state,
+        NewActivity::new(
+            "admin.redirect",
+            "Admin",
+            format!("Removed the redirect from {}", rule.from_path),
+        )
+        .link("/admin/redirects"),
+    );
+    Ok(redirect_rules(&state))
+}
+
+// =============================================================================
+// API tokens
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct TokenForm {
+    pub name: String,
+    /// Checkboxes — present only when ticked
+    pub exports: Option<String>,
+    pub feeds: Option<String>,
+    pub embeds: Option<String>,
+}
+
+pub async fn tokens_page(State(state): State<Arc<AppState>>) -> AdminTokensPage {
+    let ApiTokensPartial {
+        tokens,
+        empty,
+        secret,
+        copy,
+    } = api_tokens(&state, String::new());
+    AdminTokensPage {
+        tokens,
+        empty,
+        secret,
+        copy,
+    }
+}
+
+/// The token list, with `secret` (a token just created) on top
+fn api_tokens(state: &AppState, secret: String) -> ApiTokensPartial {
+    let tokens: Vec<TokenRow> = state
+        .services
+        .api_tokens
+        .list()
+        .into_iter()
+        .map(TokenRow::from)
+        .collect();
+    ApiTokensPartial {
+        empty: tokens.is_empty(),
+        tokens,
+        secret,
+        copy: CopyButton::target("#api-token-secret").aria_label("Copy the token"),
+    }
+}
+
+/// Create a token and show it, this once
+pub async fn create_token(
+    State(state): State<Arc<AppState>>,
+    Form(form): Form<TokenForm>,
+) -> AppResult<ApiTokensPartial> {
+    let scopes: Vec<Scope> = [
+        (Scope::Exports, &form.exports),
+        (Scope::Feeds, &form.feeds),
+        (Scope::Embeds, &form.embeds),
+    ]
+    .into_iter()
+    .filter(|(_, ticked)| ticked.is_some())
+    .map(|(scope, _)| scope)
+    .collect();
+    let token = api_tokens::validate(&form.name, &scopes).map_err(AppError::validation)?;
+    let (token, secret) = state.services.api_tokens.create(token);
+    activity::record(
+        &state,
+        NewActivity::new(
+            "admin.api_token",
+            "Admin",
+            format!("Created the API token {} ({})", token.name, token.prefix),
+        )
+        .link("/admin/tokens"),
+    );
+    Ok(api_tokens(&state, secret))
+}
+
+pub async fn revoke_token(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u32>,
+) -> AppResult<ApiTokensPartial> {
+    let token = state
+        .services
+        .api_tokens
+        .list()
+        .into_iter()
+        .find(|token| token.id == id && !token.is_revoked())
+        .ok_or_else(|| AppError::not_found("That token no longer exists"))?;
+    state.services.api_tokens.revoke(id);
+    activity::record(
+        &state,
+        NewActivity::new(
+            "admin.api_token",
+            "Admin",
+            format!("Revoked the API token {} ({})", token.name, token.prefix),
+        )
+        .link("/admin/tokens"),
+    );
+    Ok(api_tokens(&state, String::new()))
+}
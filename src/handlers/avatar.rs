@@ -0,0 +1,51 @@
+//! Avatar Handler — `/avatar/:seed.svg`
+//!
+//! Serves the identicon for any seed, see [`crate::services::avatars`]. Axum
+//! path parameters span a whole segment, so the route captures the file name
+//! and strips `.svg` itself. Templates get links from
+//! [`crate::services::avatars::url`].
+
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+use crate::models::AppState;
+use crate::services::avatars::MAX_SEED_BYTES;
+
+/// The same seed always draws the same image
+const AVATAR_CACHE_CONTROL: &str = "public, max-age=604800, immutable";
+
+pub async fn avatar(
+    State(state): State<Arc<AppState>>,
+    Path(file): Path<String>,
+) -> AppResult<Response> {
+    let seed = file
+        .strip_suffix(".svg")
+        .filter(|seed| !seed.trim().is_empty() && seed.len() <= MAX_SEED_BYTES)
+        .ok_or_else(|| AppError::not_found("No such avatar"))?
+        .to_string();
+
+    let service = state.services.avatars.clone();
+    let svg = tokio::task::spawn_blocking(move || service.svg(&seed))
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?;
+
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("image/svg+xml"),
+            ),
+            (
+                header::CACHE_CONTROL,
+                HeaderValue::from_static(AVATAR_CACHE_CONTROL),
+            ),
+        ],
+        svg,
+    )
+        .into_response())
+}
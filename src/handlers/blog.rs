@@ -0,0 +1,189 @@
+//! Blog Handlers — public post index and post pages
+//!
+//! The Atom feed lives in [`feeds`](crate::handlers::feeds).
+//!
+//! Only published posts are listed. A draft's page is visible to signed-in
+//! admins (as a preview) and a 404 to everyone else. A post whose slug
+//! changed still answers at its old slugs, with a `301` to the new one; other
+//! unknown slugs get the 404 page's suggestions.
+//!
+//! Post pages carry the post's title and summary in their head tags, and
+//! draft previews are marked `noindex`. Index pages past the first are their
+//! own canonical URL (`/blog?page=2`).
+
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::handlers::not_found;
+use crate::models::AppState;
+use crate::render::{PageMeta, WithSeo};
+use crate::services::admin;
+use crate::services::posts::{Post, PostStatus};
+use crate::services::sanitize::{self, Profile, SanitizedHtml};
+use crate::services::session::SessionId;
+use crate::utils::htmx;
+
+/// Posts per index page
+const PER_PAGE: usize = 5;
+
+/// A post in the index
+#[derive(Debug, Clone, Serialize)]
+pub struct PostSummary {
+    pub slug: String,
+    pub title: String,
+    pub summary: String,
+    /// `YYYY-MM-DD`
+    pub date: String,
+}
+
+impl From<Post> for PostSummary {
+    fn from(post: Post) -> Self {
+        Self {
+            date: display_date(&post),
+            slug: post.slug,
+            title: post.title,
+            summary: post.summary,
+        }
+    }
+}
+
+/// A post as its page renders it
+#[derive(Debug, Clone, Serialize)]
+pub struct PostView {
+    pub slug: String,
+    pub title: String,
+    pub summary: String,
+    pub body: SanitizedHtml,
+    pub date: String,
+    pub draft: bool,
+}
+
+impl From<Post> for PostView {
+    fn from(post: Post) -> Self {
+        Self {
+            date: display_date(&post),
+            body: sanitize::markdown(Profile::RichText, &post.body),
+            draft: post.status == PostStatus::Draft,
+            slug: post.slug,
+            title: post.title,
+            summary: post.summary,
+        }
+    }
+}
+
+/// Publication date, or the last edit for drafts
+fn display_date(post: &Post) -> String {
+    let at = post.published_at.as_deref().unwrap_or(&post.updated_at);
+    at.get(..10).unwrap_or(at).to_string()
+}
+
+crate::sitemap_route!("/blog");
+crate::shortcut!("g b", "/blog", "Go to the blog");
+
+// =============================================================================
+// Page Templates
+// =============================================================================
+
+crate::define_page!(
+    BlogIndexPage,
+    "pages/blog/index.html",
+    {
+        posts: Vec<PostSummary>,
+        empty: bool,
+        page: usize,
+        // 0 when there is no previous/next page
+        prev_page: usize,
+        next_page: usize
+    },
+    PageMeta::new("Blog")
+        .description("Notes and updates, written in Markdown.")
+        .feed("/blog/feed.xml")
+);
+
+crate::define_page!(
+    BlogPostPage,
+    "pages/blog/post.html",
+    {
+        post: PostView
+    },
+    PageMeta::new("Blog")
+        .og_type("article")
+        .feed("/blog/feed.xml")
+);
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct PageQuery {
+    pub page: Option<usize>,
+}
+
+/// Paginated index of published posts, newest first
+pub async fn blog_index(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PageQuery>,
+) -> WithSeo<BlogIndexPage> {
+    let total = state.services.posts.count_published();
+    let pages = total.div_ceil(PER_PAGE).max(1);
+    let page = query.page.unwrap_or(1).clamp(1, pages);
+
+    let posts: Vec<PostSummary> = state
+        .services
+        .posts
+        .list_published((page - 1) * PER_PAGE, PER_PAGE)
+        .into_iter()
+        .map(PostSummary::from)
+        .collect();
+    BlogIndexPage {
+        empty: posts.is_empty(),
+        posts,
+        page,
+        prev_page: page - 1,
+        next_page: if page < pages { page + 1 } else { 0 },
+    }
+    .seo(|seo| match page {
+        1 => seo,
+        page => seo.canonical(&format!("/blog?page={page}")),
+    })
+}
+
+/// A single post by slug; one of its old slugs answers `301` to the current
+pub async fn blog_post(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+) -> Response {
+    let posts = &state.services.posts;
+    let visible = |post: &Post| {
+        post.status == PostStatus::Published
+            || admin::is_admin(state.services.sessions.as_ref(), &sid)
+    };
+    if let Some(post) = posts.get_by_slug(&slug).filter(visible) {
+        let post = PostView::from(post);
+        let (title, summary, draft) = (post.title.clone(), post.summary.clone(), post.draft);
+        let page = BlogPostPage { post }.seo(|mut seo| {
+            seo = seo.title(title);
+            if !summary.is_empty() {
+                seo = seo.description(summary);
+            }
+            if draft {
+                seo = seo.noindex();
+            }
+            seo
+        });
+        return page.into_response();
+    }
+    match posts.get_by_old_slug(&slug).filter(visible) {
+        Some(moved) => htmx::moved_permanently(&format!("/blog/{}", moved.slug)),
+        None => not_found::respond(&state, &headers, &format!("/blog/{slug}")),
+    }
+}
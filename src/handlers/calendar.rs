@@ -0,0 +1,461 @@
+//! Calendar Handlers — month view and iCalendar export
+//!
+//! `/calendar?month=YYYY-MM` loads the month grid from
+//! `/partials/calendar?month=..`; the previous/next buttons swap the grid in
+//! place and push the page URL, so every month is bookmarkable. Without
+//! `month` (or with an unparseable one) the current month is shown.
+//!
+//! `/calendar.ics` lists every event for calendar apps to subscribe to. It's
+//! the same for everyone, so it's sent `Cache-Control: public` like the feeds.
+//!
+//! `/partials/datepicker` is the same month grid as a form control, loaded by
+//! [`Field::date_picker`](crate::utils::forms::Field::date_picker). Every
+//! button is an `hx-get` that re-renders the picker: the arrows change month,
+//! a day becomes the value of the picker's hidden input. Names come from the
+//! locale [`I18n`](crate::services::I18n) negotiates from `Accept-Language`.
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::models::AppState;
+use crate::render::PageMeta;
+use crate::services::calendar;
+use crate::services::i18n::{self, Locale};
+
+crate::sitemap_route!("/calendar");
+crate::shortcut!("g c", "/calendar", "Go to the calendar");
+
+/// How long calendar apps and shared caches may reuse `/calendar.ics`
+const ICS_CACHE_CONTROL: &str = "public, max-age=900";
+/// Events listed per day before the rest are summed up as `+N more`
+const EVENTS_PER_DAY: usize = 3;
+
+crate::define_page!(
+    CalendarPage,
+    "pages/calendar.html",
+    {
+        // `YYYY-MM`
+        month: String
+    },
+    PageMeta::new("Calendar").description("Month view of upcoming events, with an iCalendar feed.")
+);
+
+crate::define_partial!(CalendarPartial, "partials/calendar.html", {
+    // `YYYY-MM` of the neighbouring months
+    prev: String,
+    next: String,
+    // e.g. `March 2024`
+    label: String,
+    // Offer a way back when another month is shown
+    show_today: bool,
+    weeks: Vec<CalendarWeek>
+});
+
+crate::define_partial!(DatePickerPartial, "partials/datepicker.html", {
+    // The field's id and input name, see `Field::date_picker`
+    field: String,
+    name: String,
+    // Ids the group is described by, space-separated; empty for none
+    describedby: String,
+    // `YYYY-MM-DD`, empty until a day is chosen
+    value: String,
+    // Locale code for `lang`, and the month shown, e.g. `March 2024`
+    lang: String,
+    label: String,
+    // The chosen date written out, or the locale's "no date" text
+    chosen: String,
+    prev_url: String,
+    prev_label: String,
+    next_url: String,
+    next_label: String,
+    // Keep focus on the arrow that was pressed
+    focus_prev: bool,
+    focus_next: bool,
+    weekdays: Vec<PickerWeekday>,
+    weeks: Vec<PickerWeek>
+});
+
+crate::gallery_example!(DatePickerPartial,
+    title: "Date Picker",
+    description: "Field::date_picker: a month grid in the visitor's language; each day is an hx-get that re-renders the picker with that date in its hidden input.",
+    snippet: r##"{{ form.starts_on|safe }}"##,
+    example: date_picker(
+        &i18n::EN,
+        &DatePickerQuery {
+            field: "field-starts_on".into(),
+            name: "starts_on".into(),
+            selected: "2024-03-14".into(),
+            ..DatePickerQuery::default()
+        },
+        NaiveDate::from_ymd_opt(2024, 3, 5).expect("valid date"),
+    ),
+);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CalendarWeek {
+    pub days: Vec<CalendarDay>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CalendarDay {
+    // `YYYY-MM-DD`
+    pub date: String,
+    pub day: u32,
+    // The neighbouring months' days that pad the grid
+    pub outside: bool,
+    pub today: bool,
+    pub events: Vec<DayEvent>,
+    // Events beyond `EVENTS_PER_DAY`
+    pub more: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DayEvent {
+    pub title: String,
+    // `HH:MM`, empty for all-day events and continuation days
+    pub time: String,
+    pub all_day: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PickerWeekday {
+    // e.g. `Mo`, and `Monday` for screen readers
+    pub short: String,
+    pub long: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PickerWeek {
+    pub days: Vec<PickerDay>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PickerDay {
+    // `YYYY-MM-DD`
+    pub date: String,
+    pub day: u32,
+    // Written out for `aria-label`
+    pub label: String,
+    pub outside: bool,
+    pub today: bool,
+    pub selected: bool,
+    // Choosing this day, which shows its month; `focus` after that swap
+    pub url: String,
+    pub focus: bool,
+}
+
+#[derive(Deserialize)]
+pub struct MonthQuery {
+    pub month: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatePickerQuery {
+    pub field: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub describedby: String,
+    /// `YYYY-MM`; defaults to the selected date's month, then this one
+    pub month: String,
+    /// `YYYY-MM-DD`; anything else counts as no date
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub selected: String,
+    /// `prev`, `next` or `day`: what to focus after the swap
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub focus: String,
+}
+
+/// Years a month may be in. The grid and the previous and next links reach
+/// a month either side, so a month at the edge of chrono's range can't be
+/// shown.
+const YEARS: std::ops::RangeInclusive<i32> = 1..=9999;
+
+/// First day of `YYYY-MM`, in [`YEARS`]
+fn parse_month(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{}-01", value.trim()), "%Y-%m-%d")
+        .ok()
+        .filter(|first| YEARS.contains(&first.year()))
+}
+
+fn month_key(day: NaiveDate) -> String {
+    day.format("%Y-%m").to_string()
+}
+
+/// The requested month, or this one
+fn selected_month(query: &MonthQuery) -> NaiveDate {
+    query
+        .month
+        .as_deref()
+        .and_then(parse_month)
+        .unwrap_or_else(|| {
+            Utc::now()
+                .date_naive()
+                .with_day(1)
+                .expect("every month has a 1st")
+        })
+}
+
+pub async fn calendar_page(Query(query): Query<MonthQuery>) -> CalendarPage {
+    CalendarPage {
+        month: month_key(selected_month(&query)),
+    }
+}
+
+/// Month grid partial
+pub async fn calendar_partial(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<MonthQuery>,
+) -> CalendarPartial {
+    let first = selected_month(&query);
+    let today = Utc::now().date_naive();
+    let weeks = calendar::month_grid(first);
+    let grid_start = weeks[0][0];
+    let grid_end = weeks[weeks.len() - 1][6] + Duration::days(1);
+    let events = state.services.calendar.list_between(
+        grid_start.and_hms_opt(0, 0, 0).expect("midnight is valid"),
+        grid_end.and_hms_opt(0, 0, 0).expect("midnight is valid"),
+    );
+
+    let weeks = weeks
+        .iter()
+        .map(|week| CalendarWeek {
+            days: week
+                .iter()
+                .map(|&date| {
+                    let mut on_day: Vec<DayEvent> = events
+                        .iter()
+                        .filter(|e| e.days().any(|d| d == date))
+                        .map(|e| DayEvent {
+                            title: e.title.clone(),
+                            time: if e.all_day || e.starts_at.date() != date {
+                                String::new()
+                            } else {
+                                e.starts_at.format("%H:%M").to_string()
+                            },
+                            all_day: e.all_day,
+                        })
+                        .collect();
+                    let more = on_day.len().saturating_sub(EVENTS_PER_DAY);
+                    on_day.truncate(EVENTS_PER_DAY);
+                    CalendarDay {
+                        date: date.format("%Y-%m-%d").to_string(),
+                        day: date.day(),
+                        outside: date.month() != first.month(),
+                        today: date == today,
+                        events: on_day,
+                        more,
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+
+    CalendarPartial {
+        prev: month_key(first - chrono::Months::new(1)),
+        next: month_key(first + chrono::Months::new(1)),
+        label: first.format("%B %Y").to_string(),
+        show_today: first.year() != today.year() || first.month() != today.month(),
+        weeks,
+    }
+}
+
+/// Date picker partial, in the locale `Accept-Language` asks for
+pub async fn date_picker_partial(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<DatePickerQuery>,
+) -> DatePickerPartial {
+    let accept_language = headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let locale = state.services.i18n.negotiate(accept_language);
+    date_picker(locale, &query, Utc::now().date_naive())
+}
+
+/// The picker for `query` in `locale`
+pub fn date_picker(
+    locale: &Locale,
+    query: &DatePickerQuery,
+    today: NaiveDate,
+) -> DatePickerPartial {
+    let selected = NaiveDate::parse_from_str(query.selected.trim(), "%Y-%m-%d")
+        .ok()
+        .filter(|date| YEARS.contains(&date.year()));
+    let first = parse_month(&query.month)
+        .or_else(|| selected.and_then(|date| date.with_day(1)))
+        .or_else(|| today.with_day(1))
+        .expect("every month has a 1st");
+    let value = selected
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    // The same picker with `month`, `selected` and `focus` changed
+    let url = |month: NaiveDate, selected: &str, focus: &str| {
+        let params = DatePickerQuery {
+            field: query.field.clone(),
+            name: query.name.clone(),
+            describedby: query.describedby.clone(),
+            month: month_key(month),
+            selected: selected.to_string(),
+            focus: focus.to_string(),
+        };
+        format!(
+            "/partials/datepicker?{}",
+            serde_urlencoded::to_string(&params).unwrap_or_default()
+        )
+    };
+    let prev = first - chrono::Months::new(1);
+    let next = first + chrono::Months::new(1);
+
+    let weeks = calendar::month_grid(first)
+        .iter()
+        .map(|week| PickerWeek {
+            days: week
+                .iter()
+                .map(|&date| {
+                    let key = date.format("%Y-%m-%d").to_string();
+                    PickerDay {
+                        day: date.day(),
+                        label: locale.format_date(date),
+                        outside: date.month() != first.month(),
+                        today: date == today,
+                        selected: Some(date) == selected,
+                        url: url(date, &key, "day"),
+                        focus: query.focus == "day" && Some(date) == selected,
+                        date: key,
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+
+    DatePickerPartial {
+        field: query.field.clone(),
+        name: query.name.clone(),
+        describedby: query.describedby.clone(),
+        lang: locale.code.to_string(),
+        label: locale.format_month(first),
+        chosen: selected.map_or_else(
+            || locale.no_date.to_string(),
+            |date| locale.format_date(date),
+        ),
+        prev_url: url(prev, &value, "prev"),
+        prev_label: locale.previous_month.to_string(),
+        next_url: url(next, &value, "next"),
+        next_label: locale.next_month.to_string(),
+        focus_prev: query.focus == "prev",
+        focus_next: query.focus == "next",
+        weekdays: locale
+            .weekdays
+            .iter()
+            .zip(locale.weekdays_short)
+            .map(|(long, short)| PickerWeekday {
+                short: short.to_string(),
+                long: long.to_string(),
+            })
+            .collect(),
+        weeks,
+        value,
+    }
+}
+
+/// Every event as an iCalendar feed
+pub async fn calendar_ics(State(state): State<Arc<AppState>>) -> Response {
+    let site = &state.config.site;
+    // UIDs only need a stable domain: the base URL's host, without the port
+    let host = site
+        .base_url
+        .split("://")
+        .nth(1)
+        .unwrap_or(&site.base_url)
+        .split(['/', ':'])
+        .next()
+        .unwrap_or_default();
+    let ics = calendar::to_ics(&state.services.calendar.list_all(), host, &site.title);
+    (
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (header::CACHE_CONTROL, ICS_CACHE_CONTROL),
+        ],
+        ics,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_month() {
+        assert_eq!(parse_month("2024-03"), NaiveDate::from_ymd_opt(2024, 3, 1));
+        assert_eq!(parse_month("2024-13"), None);
+        assert_eq!(parse_month("march"), None);
+        assert_eq!(parse_month("9999-12"), NaiveDate::from_ymd_opt(9999, 12, 1));
+        assert_eq!(parse_month("+262142-12"), None);
+        assert_eq!(parse_month("-262143-01"), None);
+    }
+
+    #[test]
+    fn test_date_picker() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let query = DatePickerQuery {
+            field: "field-due".into(),
+            name: "due".into(),
+            selected: "2024-01-31".into(),
+            focus: "day".into(),
+            ..DatePickerQuery::default()
+        };
+        let picker = date_picker(&i18n::DE, &query, today);
+        // The selected date's month, not today's
+        assert_eq!(picker.label, "Januar 2024");
+        assert_eq!(picker.value, "2024-01-31");
+        assert_eq!(picker.chosen, "Mittwoch, 31. Januar 2024");
+        assert_eq!(picker.weekdays[0].short, "Mo");
+        assert_eq!(
+            picker.next_url,
+            "/partials/datepicker?field=field-due&name=due&month=2024-02&selected=2024-01-31&focus=next"
+        );
+        let days: Vec<&PickerDay> = picker.weeks.iter().flat_map(|w| &w.days).collect();
+        let chosen: Vec<&str> = days
+            .iter()
+            .filter(|d| d.selected && d.focus)
+            .map(|d| d.date.as_str())
+            .collect();
+        assert_eq!(chosen, ["2024-01-31"]);
+        assert!(!days.iter().any(|d| d.today));
+
+        let picker = date_picker(
+            &i18n::EN,
+            &DatePickerQuery {
+                month: "2024-03".into(),
+                selected: "31/01/2024".into(),
+                ..query
+            },
+            today,
+        );
+        assert_eq!(picker.label, "March 2024");
+        assert_eq!(picker.value, "");
+        assert_eq!(picker.chosen, "No date chosen");
+
+        // Out of range: today's month, no date
+        let picker = date_picker(
+            &i18n::EN,
+            &DatePickerQuery {
+                month: "+262142-12".into(),
+                selected: "+262142-12-31".into(),
+                ..DatePickerQuery::default()
+            },
+            today,
+        );
+        assert_eq!(picker.label, "March 2024");
+        assert_eq!(picker.value, "");
+    }
+}
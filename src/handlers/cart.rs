@@ -0,0 +1,395 @@
+//! Cart Handlers — a session cart with out-of-band updates
+//!
+//! `/partials/cart` renders the catalogue and the cart (see
+//! [`crate::services::cart`]) as one `#cart` region. Every change answers
+//! with that region re-rendered and the cart badge out-of-band, so the count
+//! elsewhere on the page follows in the same swap:
+//!
+//! ```html
+//! <span id="cart-badge"></span>
+//! <div hx-get="/partials/cart" hx-trigger="load" hx-swap="outerHTML"></div>
+//! ```
+//!
+//! Checking out issues an invoice (see [`crate::services::invoices`]) and
+//! answers with three fragments: the emptied cart as the main swap, plus
+//! `#cart-receipt`, linking to the invoice, and `#cart-badge` out-of-band. A
+//! checkout with problems comes back as the cart with each message under its
+//! field.
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Extension, Form,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+use crate::handlers::documents::money;
+use crate::models::AppState;
+use crate::services::cart::{self, Cart, CATALOGUE, MAX_QUANTITY};
+use crate::services::comments::owner_key;
+use crate::services::invoices::{InvoiceLine, NewInvoice};
+use crate::services::mail;
+use crate::services::session::SessionId;
+use crate::utils::forms::Field;
+
+crate::sitemap_route!("/cart", exclude);
+
+/// Longest accepted name, in characters
+const MAX_NAME_CHARS: usize = 100;
+
+// =============================================================================
+// Templates
+// =============================================================================
+
+crate::define_partial!(CartPartial, "partials/cart.html", {
+    cart: CartView,
+    checkout: CheckoutFields
+});
+
+crate::gallery_example!(CartPartial,
+    title: "Cart",
+    description: "Session cart: add, change quantities and remove, each answered with the cart and an out-of-band #cart-badge.",
+    snippet: r##"<span id="cart-badge"></span>
+<div hx-get="/partials/cart" hx-trigger="load" hx-swap="outerHTML"></div>"##,
+    example: CartPartial {
+        cart: CartView::new(&example_cart()),
+        checkout: CheckoutFields::default(),
+    },
+);
+
+crate::define_partial!(CartCheckoutPartial, "partials/cart_checkout.html", {
+    cart: CartView,
+    checkout: CheckoutFields,
+    receipt: Receipt
+});
+
+crate::gallery_example!(CartCheckoutPartial,
+    title: "Cart Checkout",
+    description: "A multi-fragment answer: the emptied cart, with #cart-receipt and #cart-badge out-of-band.",
+    snippet: r##"<form hx-post="/cart/checkout" hx-target="#cart" hx-swap="outerHTML">…</form>
+<div id="cart-receipt"></div>"##,
+    example: CartCheckoutPartial {
+        cart: CartView::new(&Cart::default()),
+        checkout: CheckoutFields::default(),
+        receipt: Receipt {
+            order: "INV-2024-0042".into(),
+            name: "Ada".into(),
+            email: "ada@example.com".into(),
+            items: "3 items".into(),
+            total: money(3_360),
+            invoice: "/invoices/42".into(),
+        },
+    },
+);
+
+fn example_cart() -> Cart {
+    let mut cart = Cart::default();
+    cart.add("mug", 2);
+    cart.add("stickers", 1);
+    cart
+}
+
+/// A catalogue entry with its Add button
+#[derive(Debug, Clone, Serialize)]
+pub struct ProductView {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub price: String,
+}
+
+/// A cart line as the templates see it
+#[derive(Debug, Clone, Serialize)]
+pub struct CartLineView {
+    pub id: String,
+    pub name: String,
+    pub quantity: u32,
+    pub price: String,
+    pub amount: String,
+}
+
+/// `#cart` and the badge
+#[derive(Debug, Clone, Serialize)]
+pub struct CartView {
+    pub products: Vec<ProductView>,
+    pub lines: Vec<CartLineView>,
+    pub empty: bool,
+    // Units, for the badge
+    pub count: u32,
+    pub total: String,
+    pub max_quantity: u32,
+}
+
+impl CartView {
+    pub fn new(cart: &Cart) -> Self {
+        Self {
+            products: CATALOGUE
+                .iter()
+                .map(|p| ProductView {
+                    id: p.id,
+                    name: p.name,
+                    price: money(p.price_cents),
+                })
+                .collect(),
+            lines: cart
+                .lines()
+                .iter()
+                .filter_map(|line| {
+                    let product = cart::product(&line.product)?;
+                    Some(CartLineView {
+                        id: line.product.clone(),
+                        name: product.name.to_string(),
+                        quantity: line.quantity,
+                        price: money(product.price_cents),
+                        amount: money(product.price_cents * line.quantity as u64),
+                    })
+                })
+                .collect(),
+            empty: cart.is_empty(),
+            count: cart.count(),
+            total: money(cart.total_cents()),
+            max_quantity: MAX_QUANTITY,
+        }
+    }
+}
+
+/// A placed order, shown out-of-band by checkout
+#[derive(Debug, Clone, Serialize)]
+pub struct Receipt {
+    // The invoice number
+    pub order: String,
+    pub name: String,
+    pub email: String,
+    // `1 item`, `3 items`
+    pub items: String,
+    // With tax, as invoiced
+    pub total: String,
+    // The invoice page
+    pub invoice: String,
+}
+
+// =============================================================================
+// Checkout form
+// =============================================================================
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CheckoutForm {
+    pub name: String,
+    pub email: String,
+}
+
+/// What's wrong with each field of a checkout; empty when it's fine
+#[derive(Debug, Default)]
+pub struct CheckoutErrors {
+    pub name: String,
+    pub email: String,
+}
+
+impl CheckoutErrors {
+    fn check(name: &str, email: &str) -> Self {
+        let mut errors = Self::default();
+        if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+            errors.name = format!("Names must be 1 to {MAX_NAME_CHARS} characters");
+        }
+        if !mail::is_valid_address(email) {
+            errors.email = "That email address doesn't look right".to_string();
+        }
+        errors
+    }
+
+    fn is_empty(&self) -> bool {
+        self.name.is_empty() && self.email.is_empty()
+    }
+}
+
+/// The checkout form's fields, `checkout` in the partials
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckoutFields {
+    pub name: Field,
+    pub email: Field,
+}
+
+impl CheckoutFields {
+    /// The fields holding `values`, marked with `errors`
+    pub fn new(values: &CheckoutForm, errors: CheckoutErrors) -> Self {
+        Self {
+            name: Field::text("name", "Name")
+                .id("checkout-name")
+                .value(&values.name)
+                .error(errors.name)
+                .required()
+                .attr("maxlength", MAX_NAME_CHARS)
+                .attr("autocomplete", "name"),
+            email: Field::input("email", "email", "Email")
+                .id("checkout-email")
+                .value(&values.email)
+                .hint("For the receipt. Nothing is sent in the demo.")
+                .error(errors.email)
+                .required()
+                .attr("maxlength", 254)
+                .attr("autocomplete", "email"),
+        }
+    }
+}
+
+impl Default for CheckoutFields {
+    fn default() -> Self {
+        Self::new(&CheckoutForm::default(), CheckoutErrors::default())
+    }
+}
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+fn render(cart: &Cart) -> CartPartial {
+    CartPartial {
+        cart: CartView::new(cart),
+        checkout: CheckoutFields::default(),
+    }
+}
+
+/// Load the session's cart, change it, store it, and render it
+fn update(
+    state: &AppState,
+    sid: &str,
+    change: impl FnOnce(&mut Cart) -> AppResult<()>,
+) -> AppResult<CartPartial> {
+    let sessions = state.services.sessions.as_ref();
+    let mut cart = Cart::load(sessions, sid);
+    change(&mut cart)?;
+    cart.save(sessions, sid);
+    Ok(render(&cart))
+}
+
+/// GET /partials/cart
+pub async fn cart_partial(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+) -> CartPartial {
+    render(&Cart::load(state.services.sessions.as_ref(), &sid))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddForm {
+    pub product: String,
+}
+
+/// POST /cart/add — one more of a product
+pub async fn add_to_cart(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Form(form): Form<AddForm>,
+) -> AppResult<CartPartial> {
+    update(&state, &sid, |cart| {
+        if cart.add(&form.product, 1) {
+            Ok(())
+        } else {
+            Err(AppError::not_found("No such product"))
+        }
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuantityForm {
+    pub quantity: String,
+}
+
+/// POST /cart/:product/quantity — zero removes the line
+pub async fn set_quantity(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Path(product): Path<String>,
+    Form(form): Form<QuantityForm>,
+) -> AppResult<CartPartial> {
+    let quantity: u32 = form.quantity.trim().parse().map_err(|_| {
+        AppError::validation(format!("Quantities are whole numbers, 0 to {MAX_QUANTITY}"))
+    })?;
+    update(&state, &sid, |cart| {
+        if cart.set_quantity(&product, quantity) {
+            Ok(())
+        } else {
+            Err(AppError::not_found("That's no longer in your cart"))
+        }
+    })
+}
+
+/// POST /cart/:product/remove — a line that's already gone is no error,
+/// so a double click is harmless
+pub async fn remove_from_cart(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Path(product): Path<String>,
+) -> AppResult<CartPartial> {
+    update(&state, &sid, |cart| {
+        cart.remove(&product);
+        Ok(())
+    })
+}
+
+/// POST /cart/checkout — place the order: the cart empties and the receipt
+/// and badge follow out-of-band
+pub async fn checkout(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Form(form): Form<CheckoutForm>,
+) -> AppResult<Response> {
+    let sessions = state.services.sessions.as_ref();
+    let cart = Cart::load(sessions, &sid);
+    if cart.is_empty() {
+        return Err(AppError::bad_request("Your cart is empty"));
+    }
+
+    let name = form.name.trim();
+    let email = form.email.trim();
+    let errors = CheckoutErrors::check(name, email);
+    if !errors.is_empty() {
+        // A 200, so HTMX swaps the cart back in with the messages
+        return Ok(CartPartial {
+            cart: CartView::new(&cart),
+            checkout: CheckoutFields::new(&form, errors),
+        }
+        .into_response());
+    }
+
+    // Priced now, so the invoice keeps what the cart cost
+    let lines = cart
+        .lines()
+        .iter()
+        .filter_map(|line| {
+            let product = cart::product(&line.product)?;
+            Some(InvoiceLine {
+                description: product.name.to_string(),
+                quantity: line.quantity,
+                unit_cents: product.price_cents,
+            })
+        })
+        .collect();
+    let invoice = state.services.invoices.create(NewInvoice {
+        owner: owner_key(&sid),
+        bill_to: vec![name.to_string(), email.to_string()],
+        lines,
+    });
+    let receipt = Receipt {
+        order: invoice.number(),
+        name: name.to_string(),
+        email: email.to_string(),
+        items: match cart.count() {
+            1 => "1 item".to_string(),
+            count => format!("{count} items"),
+        },
+        total: money(invoice.total_cents()),
+        invoice: format!("/invoices/{}", invoice.id),
+    };
+    tracing::info!(order = %receipt.order, items = cart.count(), "demo order placed");
+    let emptied = Cart::default();
+    emptied.save(sessions, &sid);
+
+    Ok(CartCheckoutPartial {
+        cart: CartView::new(&emptied),
+        checkout: CheckoutFields::default(),
+        receipt,
+    }
+    .into_response())
+}
@@ -0,0 +1,314 @@
+//! Comment Handlers — threaded comments demo
+//!
+//! A realistic user-generated-content flow: every write goes through the CSRF
+//! middleware, bodies are sanitized with the `Comment` profile before they're
+//! rendered, and only the session that posted a comment may edit or delete it.
+//! Writes return re-rendered fragments that replace the optimistic preview
+//! drawn by `static/js/comments.js`.
+
+use axum::{
+    extract::{Path, State},
+    Extension, Form,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+use crate::globals::USER_NAME_KEY;
+use crate::handlers::{activity, notifications};
+use crate::models::AppState;
+use crate::services::activities::NewActivity;
+use crate::services::avatars;
+use crate::services::comments::{self, Comment};
+use crate::services::notifications::NewNotification;
+use crate::services::sanitize::{self, Profile, SanitizedHtml};
+use crate::services::session::SessionId;
+
+/// Longest accepted comment body, in characters
+const MAX_BODY_CHARS: usize = 2000;
+/// Longest accepted author name, in characters
+const MAX_AUTHOR_CHARS: usize = 40;
+/// Replies nested deeper than this are drawn at this depth
+const MAX_INDENT: usize = 4;
+
+/// A comment as the templates see it
+#[derive(Debug, Clone, Serialize)]
+pub struct CommentView {
+    pub id: u32,
+    pub author: String,
+    /// Identicon for the author, see [`avatars::url`]
+    pub avatar: String,
+    pub body: SanitizedHtml,
+    pub created_at: String,
+    pub edited: bool,
+    pub deleted: bool,
+    /// Nesting depth, capped at [`MAX_INDENT`]
+    pub indent: usize,
+    /// Whether the current session posted it
+    pub can_edit: bool,
+}
+
+impl CommentView {
+    fn new(comment: Comment, depth: usize, owner: &str) -> Self {
+        Self {
+            id: comment.id,
+            body: sanitize::clean(Profile::Comment, &comment.body),
+            can_edit: !comment.deleted && comment.owner == owner,
+            avatar: avatars::url(&comment.author),
+            author: comment.author,
+            created_at: comment.created_at,
+            edited: comment.edited,
+            deleted: comment.deleted,
+            indent: depth.min(MAX_INDENT),
+        }
+    }
+}
+
+// =============================================================================
+// Partial Templates
+// =============================================================================
+
+crate::define_partial!(CommentThreadPartial, "partials/comment_thread.html", {
+    comments: Vec<CommentView>,
+    empty: bool
+});
+
+crate::gallery_example!(CommentThreadPartial,
+    title: "Comment Thread",
+    description: "Comments with their reply form; loaded lazily and replaced whole on every post.",
+    snippet: r##"<div hx-get="/partials/comments" hx-trigger="load" hx-swap="outerHTML"></div>"##,
+    example: CommentThreadPartial { comments: Vec::new(), empty: true },
+);
+
+crate::define_partial!(CommentPartial, "partials/comment.html", {
+    comment: CommentView
+});
+
+crate::gallery_example!(CommentPartial,
+    title: "Comment",
+    description: "One comment with its reply form; the edit form swaps back to it on save or cancel.",
+    snippet: r##"<button hx-get="/comments/7" hx-target="#comment-7" hx-swap="outerHTML">Cancel</button>"##,
+    example: CommentPartial {
+        comment: CommentView {
+            id: 0,
+            author: "Ada".to_string(),
+            avatar: avatars::url("Ada"),
+            body: sanitize::clean(Profile::Comment, "Swapped in with <strong>one</strong> request."),
+            created_at: "2024-03-01 09:30".to_string(),
+            edited: true,
+            deleted: false,
+            indent: 0,
+            can_edit: false,
+        },
+    },
+);
+
+crate::define_partial!(CommentEditPartial, "partials/comment_edit.html", {
+    comment: CommentView,
+    body: String
+});
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct NewCommentForm {
+    pub parent_id: Option<u32>,
+    #[serde(default)]
+    pub author: String,
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+pub struct EditCommentForm {
+    pub body: String,
+}
+
+/// The whole thread, with the post form
+pub async fn comment_thread(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+) -> CommentThreadPartial {
+    render_thread(&state, &sid)
+}
+
+/// Post a comment or reply, returning the re-rendered thread
+pub async fn create_comment(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Form(form): Form<NewCommentForm>,
+) -> AppResult<CommentThreadPartial> {
+    let body = validate_body(&form.body)?;
+    let parent = match form.parent_id {
+        Some(parent_id) => match state.services.comments.get_by_id(parent_id) {
+            Some(parent) if !parent.deleted => Some(parent),
+            _ => return Err(AppError::not_found("The comment you replied to is gone")),
+        },
+        None => None,
+    };
+
+    // Signed-in users post under their name; everyone else picks one
+    let author = state
+        .services
+        .sessions
+        .get(&sid)
+        .and_then(|session| session.data.get(USER_NAME_KEY).cloned())
+        .unwrap_or(form.author);
+    let author = match author.trim() {
+        "" => "Anonymous".to_string(),
+        name if name.chars().count() > MAX_AUTHOR_CHARS => {
+            return Err(AppError::validation(format!(
+                "Names are limited to {MAX_AUTHOR_CHARS} characters"
+            )))
+        }
+        name => name.to_string(),
+    };
+
+    let owner = comments::owner_key(&sid);
+    // Guests are online under the name they last posted as
+    if author != "Anonymous" {
+        state.services.presence.touch(&owner, Some(&author));
+    }
+    let comment = state
+        .services
+        .comments
+        .create(form.parent_id, author, body, owner.clone());
+
+    let summary = if parent.is_some() {
+        "Replied to a comment"
+    } else {
+        "Posted a comment"
+    };
+    activity::record(
+        &state,
+        NewActivity::new("comment.created", &comment.author, summary)
+            .link(format!("/demo#comment-{}", comment.id)),
+    );
+
+    // Tell the parent's author, unless they're answering themselves
+    if let Some(parent) = parent.filter(|parent| parent.owner != owner) {
+        notifications::notify(
+            &state,
+            NewNotification {
+                recipient: parent.owner,
+                title: format!("{} replied to your comment", comment.author),
+                body: String::new(),
+                link: format!("/demo#comment-{}", comment.id),
+            },
+        );
+    }
+    Ok(render_thread(&state, &sid))
+}
+
+/// A single comment — used to cancel an edit
+pub async fn show_comment(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Path(id): Path<u32>,
+) -> AppResult<CommentPartial> {
+    let (comment, depth) = find_in_thread(&state, id)?;
+    Ok(CommentPartial {
+        comment: CommentView::new(comment, depth, &comments::owner_key(&sid)),
+    })
+}
+
+/// Edit form, swapped in place of the comment
+pub async fn edit_comment(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Path(id): Path<u32>,
+) -> AppResult<CommentEditPartial> {
+    let (comment, depth) = find_owned(&state, &sid, id)?;
+    let body = comment.body.clone();
+    Ok(CommentEditPartial {
+        comment: CommentView::new(comment, depth, &comments::owner_key(&sid)),
+        body,
+    })
+}
+
+/// Save an edit, returning the updated comment
+pub async fn update_comment(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Path(id): Path<u32>,
+    Form(form): Form<EditCommentForm>,
+) -> AppResult<CommentPartial> {
+    let (_, depth) = find_owned(&state, &sid, id)?;
+    let body = validate_body(&form.body)?;
+    let comment = state
+        .services
+        .comments
+        .update(id, body)
+        .ok_or_else(|| AppError::not_found("Comment not found"))?;
+    activity::record(
+        &state,
+        NewActivity::new("comment.updated", &comment.author, "Edited a comment")
+            .link(format!("/demo#comment-{}", comment.id)),
+    );
+    Ok(CommentPartial {
+        comment: CommentView::new(comment, depth, &comments::owner_key(&sid)),
+    })
+}
+
+/// Delete a comment, returning the re-rendered thread
+pub async fn delete_comment(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Path(id): Path<u32>,
+) -> AppResult<CommentThreadPartial> {
+    let (comment, _) = find_owned(&state, &sid, id)?;
+    state.services.comments.delete(id);
+    activity::record(
+        &state,
+        NewActivity::new("comment.deleted", comment.author, "Deleted a comment"),
+    );
+    Ok(render_thread(&state, &sid))
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn render_thread(state: &AppState, sid: &str) -> CommentThreadPartial {
+    let owner = comments::owner_key(sid);
+    let comments: Vec<CommentView> = comments::thread(state.services.comments.list_all())
+        .into_iter()
+        .map(|(comment, depth)| CommentView::new(comment, depth, &owner))
+        .collect();
+    CommentThreadPartial {
+        empty: comments.is_empty(),
+        comments,
+    }
+}
+
+/// Reject bodies that are blank, too long, or nothing but disallowed markup
+fn validate_body(body: &str) -> AppResult<String> {
+    let body = body.trim();
+    if body.chars().count() > MAX_BODY_CHARS {
+        return Err(AppError::validation(format!(
+            "Comments are limited to {MAX_BODY_CHARS} characters"
+        )));
+    }
+    if sanitize::clean(Profile::Comment, body).is_empty() {
+        return Err(AppError::validation("Comment can't be empty"));
+    }
+    Ok(body.to_string())
+}
+
+/// The comment with its thread depth, so re-rendered fragments keep their indent
+fn find_in_thread(state: &AppState, id: u32) -> AppResult<(Comment, usize)> {
+    comments::thread(state.services.comments.list_all())
+        .into_iter()
+        .find(|(comment, _)| comment.id == id)
+        .ok_or_else(|| AppError::not_found("Comment not found"))
+}
+
+/// [`find_in_thread`], restricted to the current session's live comments
+fn find_owned(state: &AppState, sid: &str, id: u32) -> AppResult<(Comment, usize)> {
+    let (comment, depth) = find_in_thread(state, id)?;
+    if comment.deleted || comment.owner != comments::owner_key(sid) {
+        return Err(AppError::Unauthorized);
+    }
+    Ok((comment, depth))
+}
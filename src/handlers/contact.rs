@@ -0,0 +1,228 @@
+//! Contact Form — the end-to-end form example
+//!
+//! A full page with an HTMX form, and a POST handler that runs the usual
+//! guards before doing any work: honeypot, input validation, then per-session
+//! and site-wide rate limits. The message goes out through the mail service
+//! with the visitor as `Reply-To`, and the form is swapped for a success
+//! fragment. Invalid input comes back as the form itself, with each problem
+//! next to its field (see [`crate::utils::forms`]); rate limits and delivery
+//! failures render into `#error-toast` like everywhere else.
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Extension, Form,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+use crate::models::AppState;
+use crate::render::PageMeta;
+use crate::services::mail::{self, Email};
+use crate::services::session::SessionId;
+use crate::utils::forms::Field;
+use crate::utils::honeypot;
+
+crate::sitemap_route!("/contact");
+
+/// Longest accepted name, in characters
+const MAX_NAME_CHARS: usize = 100;
+/// Shortest and longest accepted message, in characters
+const MIN_MESSAGE_CHARS: usize = 10;
+const MAX_MESSAGE_CHARS: usize = 5000;
+
+crate::define_page!(
+    ContactPage,
+    "pages/contact.html",
+    {
+        form: ContactFields
+    },
+    PageMeta::new("Contact").description("Send us a message.")
+);
+
+crate::define_partial!(ContactFormPartial, "partials/contact_form.html", {
+    form: ContactFields
+});
+
+crate::gallery_example!(ContactFormPartial,
+    title: "Contact Form",
+    description: "Form fields from utils::forms; a submission with problems comes back with each message under its field.",
+    snippet: r##"<form id="contact-form" hx-post="/contact" hx-target="this" hx-swap="outerHTML">
+    {{ form.email|safe }}
+    {% include "components/_csrf.html" %}
+</form>"##,
+    example: ContactFormPartial {
+        form: ContactFields::new(
+            &ContactForm {
+                name: "Ada".to_string(),
+                email: "ada@".to_string(),
+                ..ContactForm::default()
+            },
+            ContactErrors {
+                email: "That email address doesn't look right".to_string(),
+                ..ContactErrors::default()
+            },
+        ),
+    },
+);
+
+crate::define_partial!(ContactSentPartial, "partials/contact_sent.html", {
+    name: String
+});
+
+crate::gallery_example!(ContactSentPartial,
+    title: "Contact Sent",
+    description: "Replaces the contact form once the message is accepted.",
+    snippet: r##"<form id="contact-form" hx-post="/contact" hx-target="this" hx-swap="outerHTML">
+    …
+</form>"##,
+    example: ContactSentPartial { name: "Ada".to_string() },
+);
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ContactForm {
+    pub name: String,
+    pub email: String,
+    pub message: String,
+    /// Honeypot, see [`honeypot`]
+    #[serde(default)]
+    pub website: String,
+}
+
+/// What's wrong with each field of a submission; empty when it's fine
+#[derive(Debug, Default)]
+pub struct ContactErrors {
+    pub name: String,
+    pub email: String,
+    pub message: String,
+}
+
+impl ContactErrors {
+    fn check(name: &str, email: &str, message: &str) -> Self {
+        let mut errors = Self::default();
+        if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+            errors.name = format!("Names must be 1 to {MAX_NAME_CHARS} characters");
+        }
+        if !mail::is_valid_address(email) {
+            errors.email = "That email address doesn't look right".to_string();
+        }
+        let length = message.chars().count();
+        if !(MIN_MESSAGE_CHARS..=MAX_MESSAGE_CHARS).contains(&length) {
+            errors.message =
+                format!("Messages must be {MIN_MESSAGE_CHARS} to {MAX_MESSAGE_CHARS} characters");
+        }
+        errors
+    }
+
+    fn is_empty(&self) -> bool {
+        self.name.is_empty() && self.email.is_empty() && self.message.is_empty()
+    }
+}
+
+/// The contact form's fields, `form` in the page and the partial
+#[derive(Debug, Clone, Serialize)]
+pub struct ContactFields {
+    pub name: Field,
+    pub email: Field,
+    pub message: Field,
+}
+
+impl ContactFields {
+    /// The fields holding `values`, marked with `errors`
+    pub fn new(values: &ContactForm, errors: ContactErrors) -> Self {
+        Self {
+            name: Field::text("name", "Name")
+                .id("contact-name")
+                .value(&values.name)
+                .error(errors.name)
+                .required()
+                .attr("maxlength", MAX_NAME_CHARS)
+                .attr("autocomplete", "name"),
+            email: Field::input("email", "email", "Email")
+                .id("contact-email")
+                .value(&values.email)
+                .hint("We'll reply to this address.")
+                .error(errors.email)
+                .required()
+                .attr("maxlength", 254)
+                .attr("autocomplete", "email"),
+            message: Field::textarea("message", "Message")
+                .id("contact-message")
+                .value(&values.message)
+                .error(errors.message)
+                .required()
+                .attr("rows", 6)
+                .attr("minlength", MIN_MESSAGE_CHARS)
+                .attr("maxlength", MAX_MESSAGE_CHARS),
+        }
+    }
+}
+
+impl Default for ContactFields {
+    fn default() -> Self {
+        Self::new(&ContactForm::default(), ContactErrors::default())
+    }
+}
+
+pub async fn contact_page() -> ContactPage {
+    ContactPage {
+        form: ContactFields::default(),
+    }
+}
+
+pub async fn send_contact(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Form(form): Form<ContactForm>,
+) -> AppResult<Response> {
+    let name = form.name.trim().to_string();
+    if honeypot::is_tripped(&form.website) {
+        tracing::info!("contact form honeypot tripped");
+        return Ok(ContactSentPartial { name }.into_response());
+    }
+
+    let email = form.email.trim();
+    let message = form.message.trim();
+    let errors = ContactErrors::check(&name, email, message);
+    if !errors.is_empty() {
+        // A 200, so HTMX swaps the form back in with the messages
+        return Ok(ContactFormPartial {
+            form: ContactFields::new(&form, errors),
+        }
+        .into_response());
+    }
+
+    // Per session, and site-wide to bound mail volume under abuse;
+    // `[rate_limits.contact]`, reloadable
+    let limits = state.services.live.get().rate_limits.contact;
+    let limiter = &state.services.rate_limiter;
+    if !limiter.check(&format!("contact:{sid}"), limits.session()) {
+        return Err(AppError::too_many_requests(
+            "You've sent several messages already. Please try again later.",
+        ));
+    }
+    if !limiter.check("contact:*", limits.site()) {
+        tracing::warn!("contact form site-wide limit reached");
+        return Err(AppError::too_many_requests(
+            "We're receiving a lot of messages. Please try again later.",
+        ));
+    }
+
+    let outgoing = Email {
+        to: state.config.mail.contact_address().to_string(),
+        reply_to: Some(email.to_string()),
+        subject: format!("Contact form: {name}"),
+        body: format!("From: {name} <{email}>\n\n{message}\n"),
+    };
+    let mailer = state.services.mail.clone();
+    tokio::task::spawn_blocking(move || mailer.send(&outgoing))
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?
+        .map_err(|e| {
+            tracing::error!(error = %e, "contact form delivery failed");
+            AppError::internal("Your message couldn't be sent. Please try again later.")
+        })?;
+
+    Ok(ContactSentPartial { name }.into_response())
+}
@@ -0,0 +1,425 @@
+//! Document Handlers — printable pages with PDF downloads
+//!
+//! The paperwork internal tools end up producing: invoices and a report over
+//! the demo items. Each is an ordinary page that prints without the app
+//! around it, and with the `pdf` feature also a PDF download built from the
+//! same view data:
+//!
+//! | Page | PDF |
+//! |------|-----|
+//! | `/documents/invoice` (fixed sample) | `/documents/invoice.pdf` |
+//! | `/invoices/:id` (issued by cart checkout) | `/invoices/:id/pdf` |
+//! | `/documents/report` | `/documents/report.pdf` |
+//!
+//! Without the feature the pages still render; the download button is
+//! replaced by a note and the PDF routes don't exist.
+
+use axum::{
+    extract::{Path, State},
+    Extension,
+};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+use crate::models::AppState;
+use crate::render::PageMeta;
+use crate::services::comments::owner_key;
+use crate::services::invoices::{self, Invoice, InvoiceLine};
+use crate::services::session::SessionId;
+
+crate::sitemap_route!("/documents/invoice");
+crate::sitemap_route!("/documents/report");
+crate::sitemap_route!("/invoices", exclude);
+
+/// Whether this build can produce PDFs
+const PDF_ENABLED: bool = cfg!(feature = "pdf");
+
+crate::define_page!(
+    InvoicePage,
+    "pages/documents/invoice.html",
+    {
+        invoice: InvoiceView,
+        pdf: bool
+    },
+    PageMeta::new("Invoice").description("Example invoice, downloadable as a PDF.")
+);
+
+crate::define_page!(
+    ReportPage,
+    "pages/documents/report.html",
+    {
+        report: ReportView,
+        pdf: bool
+    },
+    PageMeta::new("Report").description("Example report over the demo items, downloadable as a PDF.")
+);
+
+// =============================================================================
+// Invoice
+// =============================================================================
+
+/// Who the invoices are from
+const ISSUER: [&str; 3] = [
+    "Example Software Ltd",
+    "1 Harbour Street",
+    "Bristol BS1 4AA",
+];
+
+/// An invoice, formatted for display
+#[derive(Debug, Clone, Serialize)]
+pub struct InvoiceView {
+    pub number: String,
+    pub issued: String,
+    pub due: String,
+    pub from: Vec<String>,
+    pub bill_to: Vec<String>,
+    pub lines: Vec<InvoiceLineView>,
+    pub subtotal: String,
+    // e.g. `VAT (20%)`
+    pub tax_label: String,
+    pub tax: String,
+    pub total: String,
+    // The download link, `.pdf` routes only
+    pub pdf_url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InvoiceLineView {
+    pub description: String,
+    pub quantity: String,
+    pub unit_price: String,
+    pub amount: String,
+}
+
+impl InvoiceView {
+    pub fn new(invoice: &Invoice, pdf_url: impl Into<String>) -> Self {
+        Self {
+            number: invoice.number(),
+            issued: invoice.issued.to_string(),
+            due: invoice.due.to_string(),
+            from: ISSUER.iter().map(|l| l.to_string()).collect(),
+            bill_to: invoice.bill_to.clone(),
+            lines: invoice
+                .lines
+                .iter()
+                .map(|l| InvoiceLineView {
+                    description: l.description.clone(),
+                    quantity: l.quantity.to_string(),
+                    unit_price: money(l.unit_cents),
+                    amount: money(l.amount_cents()),
+                })
+                .collect(),
+            subtotal: money(invoice.subtotal_cents()),
+            tax_label: format!("VAT ({}%)", invoice.tax_rate),
+            tax: money(invoice.tax_cents()),
+            total: money(invoice.total_cents()),
+            pdf_url: pdf_url.into(),
+        }
+    }
+}
+
+/// The sample invoice, fixed data that isn't stored
+pub fn sample_invoice() -> InvoiceView {
+    let line = |description: &str, quantity, unit_cents| InvoiceLine {
+        description: description.into(),
+        quantity,
+        unit_cents,
+    };
+    let invoice = Invoice {
+        id: 42,
+        owner: String::new(),
+        bill_to: vec![
+            "Ada Lovelace".into(),
+            "Analytical Engines plc".into(),
+            "12 St James's Square".into(),
+            "London SW1Y 4JH".into(),
+        ],
+        issued: NaiveDate::from_ymd_opt(2024, 3, 1).expect("valid date"),
+        due: NaiveDate::from_ymd_opt(2024, 3, 31).expect("valid date"),
+        tax_rate: invoices::TAX_RATE,
+        lines: vec![
+            line("Application hosting (monthly)", 1, 4_900),
+            line("Support hours", 6, 9_500),
+            line("TLS certificate renewal", 1, 0),
+        ],
+    };
+    InvoiceView::new(&invoice, "/documents/invoice.pdf")
+}
+
+/// `£1,234.50`
+pub fn money(cents: u64) -> String {
+    let pounds = (cents / 100).to_string();
+    let mut grouped = String::new();
+    for (i, digit) in pounds.chars().enumerate() {
+        if i > 0 && (pounds.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    format!("£{grouped}.{:02}", cents % 100)
+}
+
+pub async fn invoice_page() -> InvoicePage {
+    InvoicePage {
+        invoice: sample_invoice(),
+        pdf: PDF_ENABLED,
+    }
+}
+
+/// One of the session's invoices; anyone else's is as good as missing
+fn stored_invoice(state: &AppState, sid: &str, id: u32) -> AppResult<InvoiceView> {
+    state
+        .services
+        .invoices
+        .get(&owner_key(sid), id)
+        .map(|invoice| InvoiceView::new(&invoice, format!("/invoices/{id}/pdf")))
+        .ok_or_else(|| AppError::not_found("No such invoice"))
+}
+
+/// GET /invoices/:id
+pub async fn stored_invoice_page(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Path(id): Path<u32>,
+) -> AppResult<InvoicePage> {
+    Ok(InvoicePage {
+        invoice: stored_invoice(&state, &sid, id)?,
+        pdf: PDF_ENABLED,
+    })
+}
+
+// =============================================================================
+// Report
+// =============================================================================
+
+/// The item report, formatted for display
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportView {
+    // `YYYY-MM-DD HH:MM UTC`
+    pub generated: String,
+    pub total: usize,
+    pub done: usize,
+    pub pending: usize,
+    pub tags: Vec<TagCount>,
+    pub no_tags: bool,
+    pub items: Vec<ReportRow>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagCount {
+    pub name: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportRow {
+    pub id: u32,
+    pub title: String,
+    pub status: String,
+    // Space-separated
+    pub tags: String,
+}
+
+fn report(state: &AppState) -> ReportView {
+    let items = state.services.items.list_all();
+    let done = items.iter().filter(|i| i.done).count();
+    let mut tags: BTreeMap<&str, usize> = BTreeMap::new();
+    for tag in items.iter().flat_map(|i| &i.tags) {
+        *tags.entry(tag).or_default() += 1;
+    }
+    let tags: Vec<TagCount> = tags
+        .into_iter()
+        .map(|(name, count)| TagCount {
+            name: name.to_string(),
+            count,
+        })
+        .collect();
+
+    ReportView {
+        generated: chrono::Utc::now().format("%Y-%m-%d %H:%M UTC").to_string(),
+        total: items.len(),
+        done,
+        pending: items.len() - done,
+        no_tags: tags.is_empty(),
+        tags,
+        items: items
+            .iter()
+            .map(|item| ReportRow {
+                id: item.id,
+                title: item.title.clone(),
+                status: if item.done { "Done" } else { "Pending" }.into(),
+                tags: item.tags.join(" "),
+            })
+            .collect(),
+    }
+}
+
+pub async fn report_page(State(state): State<Arc<AppState>>) -> ReportPage {
+    ReportPage {
+        report: report(&state),
+        pdf: PDF_ENABLED,
+    }
+}
+
+// =============================================================================
+// PDF downloads (`pdf` feature)
+// =============================================================================
+
+#[cfg(feature = "pdf")]
+pub use pdf_downloads::{invoice_pdf, report_pdf, stored_invoice_pdf};
+
+#[cfg(feature = "pdf")]
+mod pdf_downloads {
+    use axum::{
+        extract::{Path, State},
+        http::{header, HeaderValue},
+        response::{IntoResponse, Response},
+        Extension,
+    };
+    use std::sync::Arc;
+
+    use super::{report, sample_invoice, stored_invoice, InvoiceView, ReportView};
+    use crate::error::AppResult;
+    use crate::models::AppState;
+    use crate::services::pdf::{Align, Column, Document, Table};
+    use crate::services::session::SessionId;
+
+    pub async fn invoice_pdf() -> Response {
+        invoice_download(&sample_invoice())
+    }
+
+    /// GET /invoices/:id/pdf
+    pub async fn stored_invoice_pdf(
+        State(state): State<Arc<AppState>>,
+        Extension(SessionId(sid)): Extension<SessionId>,
+        Path(id): Path<u32>,
+    ) -> AppResult<Response> {
+        Ok(invoice_download(&stored_invoice(&state, &sid, id)?))
+    }
+
+    fn invoice_download(invoice: &InvoiceView) -> Response {
+        let filename = format!("{}.pdf", invoice.number);
+        download(&filename, invoice_document(invoice))
+    }
+
+    pub async fn report_pdf(State(state): State<Arc<AppState>>) -> Response {
+        download("item-report.pdf", report_document(&report(&state)))
+    }
+
+    fn download(filename: &str, document: Document) -> Response {
+        (
+            [
+                (
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/pdf"),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    HeaderValue::from_str(&format!("attachment; filename=\"{filename}\""))
+                        .expect("filenames here are ASCII"),
+                ),
+            ],
+            document.render(),
+        )
+            .into_response()
+    }
+
+    fn invoice_document(invoice: &InvoiceView) -> Document {
+        let mut lines = Table::new(vec![
+            Column::new("Description", 5.0, Align::Left),
+            Column::new("Qty", 1.0, Align::Right),
+            Column::new("Unit price", 2.0, Align::Right),
+            Column::new("Amount", 2.0, Align::Right),
+        ]);
+        for line in &invoice.lines {
+            lines = lines.row(vec![
+                line.description.clone(),
+                line.quantity.clone(),
+                line.unit_price.clone(),
+                line.amount.clone(),
+            ]);
+        }
+        let summary = |label: &str, value: &str| {
+            vec![String::new(), String::new(), label.into(), value.into()]
+        };
+        let lines = lines
+            .summary(summary("Subtotal", &invoice.subtotal))
+            .summary(summary(&invoice.tax_label, &invoice.tax))
+            .summary(summary("Total", &invoice.total));
+
+        Document::new(format!("Invoice {}", invoice.number))
+            .field("Issued", &invoice.issued)
+            .field("Due", &invoice.due)
+            .heading("From")
+            .text(invoice.from.join("\n"))
+            .heading("Bill to")
+            .text(invoice.bill_to.join("\n"))
+            .space(12.0)
+            .table(lines)
+            .text(format!(
+                "Please pay {} by {}, quoting {}.",
+                invoice.total, invoice.due, invoice.number
+            ))
+    }
+
+    fn report_document(report: &ReportView) -> Document {
+        let mut tags = Table::new(vec![
+            Column::new("Tag", 3.0, Align::Left),
+            Column::new("Items", 1.0, Align::Right),
+        ]);
+        for tag in &report.tags {
+            tags = tags.row(vec![format!("#{}", tag.name), tag.count.to_string()]);
+        }
+        let mut items = Table::new(vec![
+            Column::new("#", 1.0, Align::Right),
+            Column::new("Title", 6.0, Align::Left),
+            Column::new("Status", 2.0, Align::Left),
+            Column::new("Tags", 4.0, Align::Left),
+        ]);
+        for item in &report.items {
+            items = items.row(vec![
+                item.id.to_string(),
+                item.title.clone(),
+                item.status.clone(),
+                item.tags.clone(),
+            ]);
+        }
+
+        let document = Document::new("Item report")
+            .field("Generated", &report.generated)
+            .field("Items", report.total.to_string())
+            .field("Done", report.done.to_string())
+            .field("Pending", report.pending.to_string())
+            .heading("By tag");
+        let document = if report.no_tags {
+            document.text("No items are tagged.")
+        } else {
+            document.table(tags)
+        };
+        document.heading("Items").table(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_money() {
+        assert_eq!(money(0), "£0.00");
+        assert_eq!(money(4_905), "£49.05");
+        assert_eq!(money(123_456_700), "£1,234,567.00");
+    }
+
+    #[test]
+    fn test_invoice_totals() {
+        let invoice = sample_invoice();
+        // 49.00 + 6 × 95.00 + 0.00, plus 20%
+        assert_eq!(invoice.subtotal, "£619.00");
+        assert_eq!(invoice.tax, "£123.80");
+        assert_eq!(invoice.total, "£742.80");
+    }
+}
@@ -0,0 +1,163 @@
+//! Embed Handlers — read-only widgets other internal tools can put in an iframe
+//!
+//! `/embed/<widget>?token=…` renders one widget on a bare page: `status` (the
+//! status card) or `items` (the item list without its controls, `&tag=` to
+//! filter). Browsers send no `Authorization` header with an iframe's request,
+//! so the API token (scope `embeds`, see [`crate::services::api_tokens`])
+//! rides in the query string. The page reloads itself every
+//! `[embeds] refresh_seconds`, with a meta refresh, so it needs no script.
+//!
+//! Every other page answers `frame-ancestors 'none'`; these name the origins
+//! in `[embeds] frame_ancestors` instead (see
+//! [`EmbedResponse`](crate::middleware::EmbedResponse)). With none configured
+//! the routes are a 404. They get no session: a third-party iframe doesn't
+//! keep cookies anyway, and nothing here writes.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+use crate::handlers::partials;
+use crate::middleware::EmbedResponse;
+use crate::models::AppState;
+use crate::render::PageMeta;
+use crate::services::api_tokens::Scope;
+use crate::services::items::{self, Item};
+
+crate::sitemap_route!("/embed", exclude);
+
+/// The widgets `/embed/<widget>` serves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Widget {
+    Status,
+    Items,
+}
+
+impl Widget {
+    pub const ALL: [Widget; 2] = [Widget::Status, Widget::Items];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Widget::Status => "status",
+            Widget::Items => "items",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|widget| widget.as_str() == s)
+    }
+}
+
+crate::define_page!(
+    EmbedPage,
+    "pages/embed.html",
+    {
+        // The widget's name, for its wrapper's class
+        widget: String,
+        // The widget, rendered
+        body: String,
+        // Seconds between reloads; 0 never
+        refresh_seconds: u64
+    },
+    PageMeta::new("Embed").layout("layouts/embed.html")
+);
+
+crate::define_partial!(EmbedItemsPartial, "partials/embed_items.html", {
+    items: Vec<Item>,
+    empty: bool
+});
+
+#[derive(Deserialize)]
+pub struct EmbedQuery {
+    #[serde(default)]
+    pub token: String,
+    #[serde(default)]
+    pub tag: String,
+}
+
+/// GET /embed/:widget
+pub async fn embed(
+    State(state): State<Arc<AppState>>,
+    Path(widget): Path<String>,
+    Query(query): Query<EmbedQuery>,
+) -> AppResult<Response> {
+    let embeds = &state.config.embeds;
+    let widget = Widget::parse(&widget)
+        .filter(|_| !embeds.frame_ancestors.is_empty())
+        .ok_or_else(|| AppError::not_found("No such widget"))?;
+
+    match state.services.api_tokens.authenticate(&query.token) {
+        Some(token) if token.allows(Scope::Embeds) => {}
+        Some(_) => {
+            return Ok((
+                StatusCode::FORBIDDEN,
+                "This token doesn't have the embeds scope.",
+            )
+                .into_response())
+        }
+        None => {
+            return Ok((
+                StatusCode::UNAUTHORIZED,
+                "Add a working API token: /embed/<widget>?token=<token>.",
+            )
+                .into_response())
+        }
+    }
+
+    let body = match widget {
+        Widget::Status => {
+            partials::status_card(State(state.clone()))
+                .await
+                .render_fragment()
+                .0
+        }
+        Widget::Items => {
+            let items = match items::normalize_tag(&query.tag) {
+                Some(tag) => state.services.items.list_tagged(&tag),
+                None => state.services.items.list_all(),
+            };
+            EmbedItemsPartial {
+                empty: items.is_empty(),
+                items,
+            }
+            .render_fragment()
+            .0
+        }
+    };
+
+    let mut response = EmbedPage {
+        widget: widget.as_str().to_string(),
+        body,
+        refresh_seconds: embeds.refresh_seconds,
+    }
+    .seo(|seo| seo.noindex())
+    .into_response();
+    response.extensions_mut().insert(EmbedResponse {
+        frame_ancestors: embeds.frame_ancestors.join(" "),
+    });
+    // A framing page with `Cross-Origin-Embedder-Policy` of its own only
+    // loads frames that opt in
+    response.headers_mut().insert(
+        HeaderName::from_static("cross-origin-resource-policy"),
+        HeaderValue::from_static("cross-origin"),
+    );
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_widget_names() {
+        for widget in Widget::ALL {
+            assert_eq!(Widget::parse(widget.as_str()), Some(widget));
+        }
+        assert_eq!(Widget::parse("Status"), None);
+    }
+}
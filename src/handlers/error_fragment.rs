@@ -0,0 +1,210 @@
+//! Error Fragments — the one shape every HTMX error answer takes
+//!
+//! A 4xx/5xx answer to an HTMX request is an [`ErrorFragment`]: the standard
+//! alert plus a "Try again" button, sent with `HX-Retarget: #error-toast` and
+//! `HX-Reswap: innerHTML`. HTMX doesn't swap error responses itself;
+//! `static/js/app.js` does, on `htmx:responseError`, into the retarget (or
+//! the closest `data-error-target` region of the element that asked), and its
+//! Try again reissues the request that failed.
+//!
+//! An answer sent with `X-Retry-After-Ms` ([`ErrorFragment::retry_after_ms`])
+//! says the request is worth repeating unchanged, e.g. when load is shed.
+//! app.js then reissues a failed GET by itself after that long, doubling the
+//! wait on every further failure, and only shows the fragment once it gives
+//! up.
+//!
+//! A request that never got an answer (`htmx:sendError`) has nothing to swap,
+//! so the layouts carry [`send_error`] in a `<template>` for app.js instead.
+//!
+//! ```ignore
+//! ErrorFragment::new(StatusCode::SERVICE_UNAVAILABLE, "Read-only", "Try again shortly.")
+//!     .icon("pause-circle")
+//!     .retry_after(300)
+//!     .into_response()
+//! ```
+
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::sync::LazyLock;
+
+use crate::utils::html::HtmlFragment;
+
+/// Where error fragments land unless the requesting element names a region
+pub const ERROR_REGION: &str = "#error-toast";
+
+/// Milliseconds until an automatic retry may succeed, see
+/// [`ErrorFragment::retry_after_ms`]
+pub const RETRY_AFTER_MS: &str = "x-retry-after-ms";
+
+/// An error fragment's title and message, kept on the response so it can be
+/// answered as plain text to clients that don't take HTML (see
+/// [`plain_text_for_non_html`](crate::middleware::plain_text_for_non_html))
+#[derive(Debug, Clone)]
+pub struct ErrorText {
+    pub title: String,
+    pub message: String,
+}
+
+/// An HTMX error answer: status, alert and retry affordance
+#[derive(Debug, Clone)]
+pub struct ErrorFragment {
+    status: StatusCode,
+    level: &'static str,
+    icon: &'static str,
+    title: String,
+    message: String,
+    retry_after: Option<u32>,
+    retry_after_ms: Option<u32>,
+}
+
+impl ErrorFragment {
+    /// `danger` for 5xx, `warning` for anything else
+    pub fn new(status: StatusCode, title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            level: if status.is_server_error() {
+                "danger"
+            } else {
+                "warning"
+            },
+            icon: "x-circle",
+            title: title.into(),
+            message: message.into(),
+            retry_after: None,
+            retry_after_ms: None,
+        }
+    }
+
+    /// Alert level: `warning`, `danger`, …
+    pub fn level(mut self, level: &'static str) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Bootstrap Icons name, without `bi-`
+    pub fn icon(mut self, icon: &'static str) -> Self {
+        self.icon = icon;
+        self
+    }
+
+    /// Send `Retry-After`, in seconds
+    pub fn retry_after(mut self, seconds: u32) -> Self {
+        self.retry_after = Some(seconds);
+        self
+    }
+
+    /// Ask app.js to retry a failed GET by itself after `ms`, backing off
+    /// from there. Also sends `Retry-After`, rounded up to whole seconds.
+    pub fn retry_after_ms(mut self, ms: u32) -> Self {
+        self.retry_after_ms = Some(ms);
+        self.retry_after = Some(ms.div_ceil(1000));
+        self
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The markup alone
+    pub fn html(&self) -> HtmlFragment {
+        fragment(self.level, self.icon, &self.title, &self.message)
+    }
+}
+
+impl IntoResponse for ErrorFragment {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, self.html()).into_response();
+        let headers = response.headers_mut();
+        headers.insert(
+            header::HeaderName::from_static("hx-retarget"),
+            HeaderValue::from_static(ERROR_REGION),
+        );
+        headers.insert(
+            header::HeaderName::from_static("hx-reswap"),
+            HeaderValue::from_static("innerHTML"),
+        );
+        if let Some(seconds) = self.retry_after {
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        if let Some(ms) = self.retry_after_ms {
+            headers.insert(RETRY_AFTER_MS, HeaderValue::from(ms));
+        }
+        response.extensions_mut().insert(ErrorText {
+            title: self.title,
+            message: self.message,
+        });
+        response
+    }
+}
+
+/// The fragment app.js shows when a request got no answer at all, e.g. the
+/// network is down or the server is restarting
+pub fn send_error() -> &'static str {
+    static HTML: LazyLock<String> = LazyLock::new(|| {
+        fragment(
+            "danger",
+            "wifi-off",
+            "No connection",
+            "The server couldn't be reached. Check your connection and try again.",
+        )
+        .to_string()
+    });
+    &HTML
+}
+
+fn fragment(level: &'static str, icon: &'static str, title: &str, message: &str) -> HtmlFragment {
+    HtmlFragment::new()
+        .raw(r#"<div class="alert alert-"#)
+        .raw(level)
+        .raw(r#"" role="alert">"#)
+        .raw("\n    <div class=\"alert-title\"><i class=\"bi bi-")
+        .raw(icon)
+        .raw(r#""></i> <strong>"#)
+        .text(title)
+        .raw("</strong></div>\n    <div class=\"alert-body\">")
+        .text(message)
+        .raw(
+            "</div>\n    <div class=\"alert-actions\">\
+             <button type=\"button\" class=\"btn btn-sm btn-outline-secondary\" data-error-retry>\
+             <i class=\"bi bi-arrow-clockwise\"></i> Try again</button>\
+             <button type=\"button\" class=\"btn btn-sm btn-outline-secondary\" data-error-dismiss>Dismiss</button>\
+             </div>\n</div>",
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_error_fragment_carries_retry_and_headers() {
+        let response = ErrorFragment::new(StatusCode::SERVICE_UNAVAILABLE, "Busy", "<later>")
+            .retry_after(30)
+            .into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let headers = response.headers();
+        assert_eq!(headers["hx-retarget"], ERROR_REGION);
+        assert_eq!(headers["hx-reswap"], "innerHTML");
+        assert_eq!(headers[header::RETRY_AFTER], "30");
+        assert!(!headers.contains_key(RETRY_AFTER_MS));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.starts_with(r#"<div class="alert alert-danger" role="alert">"#));
+        assert!(body.contains("&lt;later&gt;"));
+        assert!(body.contains("data-error-retry"));
+        assert!(send_error().contains("data-error-retry"));
+    }
+
+    #[test]
+    fn test_retry_after_ms_rounds_retry_after_up() {
+        let response = ErrorFragment::new(StatusCode::SERVICE_UNAVAILABLE, "Busy", "Later")
+            .retry_after_ms(1500)
+            .into_response();
+        assert_eq!(response.headers()[RETRY_AFTER_MS], "1500");
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+    }
+}
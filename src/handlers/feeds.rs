@@ -0,0 +1,282 @@
+//! Feed Handlers — Atom feeds for blog posts and items
+//!
+//! Feeds are public and identical for every visitor, so unlike pages they are
+//! cacheable: each response carries `Cache-Control: public`, a strong `ETag`
+//! and `Last-Modified`, and a matching `If-None-Match` gets a bodiless 304.
+//! [`security_headers`](crate::middleware::security_headers) keeps a
+//! handler's own `Cache-Control`, and the session middleware leaves public
+//! responses without a session cookie.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::models::AppState;
+use crate::services::sanitize::{self, Profile, SanitizedHtml};
+use crate::utils::html::escape;
+
+/// Entries per feed
+const FEED_ENTRIES: usize = 20;
+/// How long clients and shared caches may reuse a feed
+const FEED_CACHE_CONTROL: &str = "public, max-age=900";
+/// `<updated>` for a feed with no entries
+const EPOCH: &str = "1970-01-01T00:00:00Z";
+
+// =============================================================================
+// Feed Builder
+// =============================================================================
+
+/// One `<entry>`
+struct FeedEntry {
+    title: String,
+    url: String,
+    published: Option<String>,
+    updated: String,
+    summary: String,
+    content: Option<SanitizedHtml>,
+}
+
+/// Assembles an Atom document. Text is escaped here, so callers pass it raw;
+/// timestamps must already be RFC 3339.
+struct FeedBuilder {
+    title: String,
+    author: String,
+    base_url: String,
+    /// Path of the feed itself, e.g. `/blog/feed.xml`
+    self_path: String,
+    /// Path of the HTML page the feed mirrors, e.g. `/blog`
+    alternate_path: String,
+    entries: Vec<FeedEntry>,
+}
+
+impl FeedBuilder {
+    fn new(state: &AppState, title: &str, self_path: &str, alternate_path: &str) -> Self {
+        let site = &state.config.site;
+        Self {
+            title: title.to_string(),
+            author: site.title.clone(),
+            base_url: site.base_url.trim_end_matches('/').to_string(),
+            self_path: self_path.to_string(),
+            alternate_path: alternate_path.to_string(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Absolute URL for a site path
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base_url)
+    }
+
+    fn entry(&mut self, entry: FeedEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Latest entry update — the feed's own `<updated>`
+    fn updated(&self) -> &str {
+        self.entries
+            .iter()
+            .map(|entry| entry.updated.as_str())
+            .max()
+            .unwrap_or(EPOCH)
+    }
+
+    fn render(&self) -> String {
+        let alternate = escape(&self.url(&self.alternate_path));
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        xml.push_str(&format!("  <title>{}</title>\n", escape(&self.title)));
+        xml.push_str(&format!("  <id>{alternate}</id>\n"));
+        xml.push_str(&format!(
+            "  <link rel=\"self\" href=\"{}\"/>\n",
+            escape(&self.url(&self.self_path))
+        ));
+        xml.push_str(&format!(
+            "  <link rel=\"alternate\" type=\"text/html\" href=\"{alternate}\"/>\n"
+        ));
+        xml.push_str(&format!("  <updated>{}</updated>\n", self.updated()));
+        xml.push_str(&format!(
+            "  <author><name>{}</name></author>\n",
+            escape(&self.author)
+        ));
+        for entry in &self.entries {
+            let url = escape(&entry.url);
+            xml.push_str("  <entry>\n");
+            xml.push_str(&format!("    <title>{}</title>\n", escape(&entry.title)));
+            xml.push_str(&format!("    <id>{url}</id>\n"));
+            xml.push_str(&format!("    <link rel=\"alternate\" href=\"{url}\"/>\n"));
+            if let Some(published) = &entry.published {
+                xml.push_str(&format!("    <published>{published}</published>\n"));
+            }
+            xml.push_str(&format!("    <updated>{}</updated>\n", entry.updated));
+            if !entry.summary.is_empty() {
+                xml.push_str(&format!(
+                    "    <summary>{}</summary>\n",
+                    escape(&entry.summary)
+                ));
+            }
+            if let Some(content) = &entry.content {
+                xml.push_str(&format!(
+                    "    <content type=\"html\">{}</content>\n",
+                    escape(content.as_str())
+                ));
+            }
+            xml.push_str("  </entry>\n");
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+
+    /// The rendered feed with caching headers, or a 304 when the client's
+    /// `If-None-Match` already names this version
+    fn into_response(self, request_headers: &HeaderMap) -> Response {
+        let xml = self.render();
+        let etag = etag(&xml);
+        let last_modified = http_date(self.updated());
+
+        let mut response = if if_none_match(request_headers, &etag) {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            (
+                [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+                xml,
+            )
+                .into_response()
+        };
+        let h = response.headers_mut();
+        h.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(FEED_CACHE_CONTROL),
+        );
+        if let Ok(value) = HeaderValue::from_str(&etag) {
+            h.insert(header::ETAG, value);
+        }
+        if let Some(value) = last_modified.and_then(|d| HeaderValue::from_str(&d).ok()) {
+            h.insert(header::LAST_MODIFIED, value);
+        }
+        response
+    }
+}
+
+/// Strong validator: a quoted prefix of the body's SHA-256
+fn etag(body: &str) -> String {
+    let digest = hex::encode(Sha256::digest(body.as_bytes()));
+    format!("\"{}\"", &digest[..32])
+}
+
+/// Whether `If-None-Match` lists `etag` (or `*`)
+fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// RFC 3339 → IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`)
+fn http_date(rfc3339: &str) -> Option<String> {
+    chrono::DateTime::parse_from_rfc3339(rfc3339)
+        .ok()
+        .map(|at| {
+            at.with_timezone(&chrono::Utc)
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string()
+        })
+}
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+/// Atom feed of the latest published posts
+pub async fn posts_feed(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    let mut feed = FeedBuilder::new(&state, &state.config.site.title, "/blog/feed.xml", "/blog");
+    for post in state.services.posts.list_published(0, FEED_ENTRIES) {
+        feed.entry(FeedEntry {
+            url: feed.url(&format!("/blog/{}", post.slug)),
+            content: Some(sanitize::markdown(Profile::RichText, &post.body)),
+            title: post.title,
+            published: post.published_at,
+            updated: post.updated_at,
+            summary: post.summary,
+        });
+    }
+    feed.into_response(&headers)
+}
+
+/// Atom feed of the most recently created items
+pub async fn items_feed(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    let title = format!("{} — Items", state.config.site.title);
+    let mut feed = FeedBuilder::new(&state, &title, "/items/feed.xml", "/demo");
+
+    let mut items = state.services.items.list_all();
+    items.sort_by(|a, b| (&b.created_at, b.id).cmp(&(&a.created_at, a.id)));
+    for item in items.into_iter().take(FEED_ENTRIES) {
+        feed.entry(FeedEntry {
+            url: feed.url(&format!("/demo#item-{}", item.id)),
+            title: item.title,
+            published: Some(item.created_at.clone()),
+            updated: item.created_at,
+            summary: item.description,
+            content: None,
+        });
+    }
+    feed.into_response(&headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed() -> FeedBuilder {
+        FeedBuilder {
+            title: "Notes & <News>".into(),
+            author: "Site".into(),
+            base_url: "https://example.com".into(),
+            self_path: "/feed.xml".into(),
+            alternate_path: "/".into(),
+            entries: vec![FeedEntry {
+                title: "A \"quoted\" title".into(),
+                url: "https://example.com/a?x=1&y=2".into(),
+                published: None,
+                updated: "2024-03-01T12:00:00Z".into(),
+                summary: String::new(),
+                content: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_render_escapes_text() {
+        let xml = feed().render();
+        assert!(xml.contains("<title>Notes &amp; &lt;News&gt;</title>"));
+        assert!(xml.contains("<id>https://example.com/a?x=1&amp;y=2</id>"));
+        assert!(xml.contains("<updated>2024-03-01T12:00:00Z</updated>"));
+        assert!(!xml.contains("<summary>"));
+    }
+
+    #[test]
+    fn test_conditional_response() {
+        let response = feed().into_response(&HeaderMap::new());
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::LAST_MODIFIED],
+            "Fri, 01 Mar 2024 12:00:00 GMT"
+        );
+        let etag = response.headers()[header::ETAG].clone();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag);
+        let response = feed().into_response(&headers);
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            FEED_CACHE_CONTROL
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Component gallery — the partials shown on `/components`
+//!
+//! A partial joins the gallery with [`gallery_example!`](crate::gallery_example)
+//! next to its `define_partial!`: a title, a sentence on what it's for, the
+//! HTMX markup that loads it, and an example context. `/components` renders
+//! every entry with its example, so the page shows the templates as they are
+//! now, and lists the partials that have no entry yet.
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::render::{registered_templates, TemplateKind};
+
+/// A [`gallery_example!`](crate::gallery_example) declaration, collected at
+/// link time
+pub struct GalleryEntry {
+    /// The partial's struct name
+    pub name: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    /// HTMX markup that loads the partial
+    pub snippet: &'static str,
+    /// Renders the example context
+    pub render: fn() -> String,
+}
+
+inventory::collect!(GalleryEntry);
+
+/// Add a partial to the gallery on `/components`, rendered with `example`:
+///
+/// ```ignore
+/// crate::gallery_example!(GreetingPartial,
+///     title: "Greeting",
+///     description: "Form response swapped in below the form",
+///     snippet: r#"<form hx-get="/partials/greeting" hx-target="#greeting">…</form>"#,
+///     example: GreetingPartial { name: sanitize::clean(Profile::Inline, "Ada") },
+/// );
+/// ```
+#[macro_export]
+macro_rules! gallery_example {
+    (
+        $name:ident,
+        title: $title:literal,
+        description: $description:literal,
+        snippet: $snippet:literal,
+        example: $example:expr $(,)?
+    ) => {
+        $crate::render::inventory::submit! {
+            $crate::handlers::gallery::GalleryEntry {
+                name: stringify!($name),
+                title: $title,
+                description: $description,
+                snippet: $snippet,
+                render: || {
+                    let example: $name = $example;
+                    example.render_fragment().0
+                },
+            }
+        }
+    };
+}
+
+/// A partial rendered by the server from its example context. Renders
+/// verbatim (use `|safe`).
+#[derive(Debug, Clone)]
+pub struct RenderedPartial(String);
+
+impl fmt::Display for RenderedPartial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for RenderedPartial {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// A gallery card
+#[derive(Debug, Clone, Serialize)]
+pub struct GalleryItem {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    /// Template path relative to `templates/`
+    pub template: &'static str,
+    pub snippet: &'static str,
+    pub html: RenderedPartial,
+}
+
+/// Every gallery entry rendered, by title
+pub fn items() -> Vec<GalleryItem> {
+    let mut items: Vec<GalleryItem> = inventory::iter::<GalleryEntry>
+        .into_iter()
+        .map(|entry| GalleryItem {
+            name: entry.name,
+            title: entry.title,
+            description: entry.description,
+            template: registered_templates()
+                .find(|spec| spec.name == entry.name)
+                .map_or("", |spec| spec.path),
+            snippet: entry.snippet,
+            html: RenderedPartial((entry.render)()),
+        })
+        .collect();
+    items.sort_by_key(|item| item.title);
+    items
+}
+
+/// Partials without a gallery entry, by name
+pub fn missing() -> Vec<&'static str> {
+    let mut missing: Vec<&'static str> = registered_templates()
+        .filter(|spec| spec.kind == TemplateKind::Partial)
+        .map(|spec| spec.name)
+        .filter(|name| {
+            !inventory::iter::<GalleryEntry>
+                .into_iter()
+                .any(|entry| entry.name == *name)
+        })
+        .collect();
+    missing.sort_unstable();
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_are_partials_that_render() {
+        let items = items();
+        assert!(!items.is_empty());
+        for item in &items {
+            assert!(!item.template.is_empty(), "{} isn't a partial", item.name);
+            assert!(
+                !item.html.0.contains("Template error") && !item.html.0.contains("Template Error"),
+                "{}: {}",
+                item.name,
+                item.html
+            );
+        }
+        assert!(!missing().contains(&"GreetingPartial"));
+    }
+}
@@ -0,0 +1,135 @@
+//! Item Handlers — an item's own page, addressed by its slug
+//!
+//! `/items/:slug` shows one of the current tenant's items with a form to
+//! rename it. Renaming changes the slug too; the old one stays in the slug
+//! history (see [`crate::services::slugs`]), so links to it answer `301` with
+//! the new address instead of a 404.
+//!
+//! The list, tags and ordering live with the other item partials in
+//! [`crate::handlers::partials`].
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Extension, Form,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+use crate::handlers::partials::MAX_TITLE_CHARS;
+use crate::handlers::{activity, not_found};
+use crate::models::AppState;
+use crate::render::PageMeta;
+use crate::services::activities::NewActivity;
+use crate::services::flash::{self, FlashMessage};
+use crate::services::items::Item;
+use crate::services::session::SessionId;
+use crate::services::tenants;
+use crate::utils::forms::Field;
+use crate::utils::htmx;
+
+crate::define_page!(
+    ItemPage,
+    "pages/item.html",
+    {
+        item: Item,
+        title: Field
+    },
+    PageMeta::new("Item").description("One item, renamed without breaking links to it.")
+);
+
+/// The rename form's title field, holding `value`
+pub fn title_field(value: &str, error: impl Into<String>) -> Field {
+    Field::text("title", "Title")
+        .id("item-title")
+        .value(value)
+        .hint("The address follows the title. Links to the old one keep working.")
+        .error(error)
+        .required()
+        .attr("maxlength", MAX_TITLE_CHARS)
+}
+
+/// GET /items/:slug — an old slug answers `301` to the current one, an
+/// unknown one the 404 page with suggestions
+pub async fn item_page(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+) -> Response {
+    let items = &state.services.items;
+    if let Some(item) = items.get_by_slug(&slug) {
+        let title = title_field(&item.title, "");
+        let name = item.title.clone();
+        return ItemPage { item, title }
+            .seo(|seo| seo.title(name))
+            .into_response();
+    }
+    match items.get_by_old_slug(&slug) {
+        Some(moved) => htmx::moved_permanently(&format!("/items/{}", moved.slug)),
+        None => not_found::respond(&state, &headers, &format!("/items/{slug}")),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenameForm {
+    pub title: String,
+}
+
+/// POST /items/:slug/rename — then on to the item's new address
+pub async fn rename_item(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+    Form(form): Form<RenameForm>,
+) -> AppResult<Response> {
+    let items = &state.services.items;
+    let item = items
+        .get_by_slug(&slug)
+        .ok_or_else(|| AppError::not_found("Item not found"))?;
+
+    let title = form.title.trim();
+    let error = if title.is_empty() {
+        "Title can't be empty".to_string()
+    } else if title.chars().count() > MAX_TITLE_CHARS {
+        format!("Titles are limited to {MAX_TITLE_CHARS} characters")
+    } else {
+        String::new()
+    };
+    if !error.is_empty() {
+        // A 200, so the page comes back with the message under the field
+        return Ok(ItemPage {
+            title: title_field(&form.title, error),
+            item,
+        }
+        .into_response());
+    }
+
+    let renamed = items
+        .rename(item.id, title.to_string())
+        .ok_or_else(|| AppError::not_found("Item not found"))?;
+    state.services.item_hub.changed(&tenants::current());
+    let link = format!("/items/{}", renamed.slug);
+    activity::record(
+        &state,
+        NewActivity::new(
+            "item.renamed",
+            activity::actor(&state, &sid),
+            format!("Renamed item \"{}\" to \"{}\"", item.title, renamed.title),
+        )
+        .link(&link),
+    );
+    if renamed.slug != item.slug {
+        flash::push(
+            state.services.sessions.as_ref(),
+            &sid,
+            FlashMessage::new(
+                "success",
+                format!("Renamed. /items/{} now redirects here.", item.slug),
+            ),
+        );
+    }
+    Ok(htmx::redirect(&headers, &link))
+}
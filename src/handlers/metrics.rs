@@ -0,0 +1,204 @@
+//! Metrics — `/metrics` in the Prometheus text format
+//!
+//! The figures behind the status card ([`Stats::snapshot`]) for a scraper:
+//! request totals, p95 latency, memory, sessions and the database pools.
+//! Only listeners with `metrics = true` serve it (see
+//! [`ListenerInfo`](crate::server::ListenerInfo)) — typically one on
+//! localhost — so it's never public by accident.
+//!
+//! [`Stats::snapshot`]: crate::services::stats::Stats::snapshot
+
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use crate::models::AppState;
+use crate::services::stats::{PoolSnapshot, Snapshot, ACQUIRE_BUCKETS};
+
+crate::sitemap_route!("/metrics", exclude);
+
+/// Content type of the Prometheus text exposition format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+pub async fn metrics(State(state): State<Arc<AppState>>) -> Response {
+    let snapshot = state
+        .services
+        .stats
+        .snapshot(&state.db, state.services.sessions.count());
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], render(&snapshot)).into_response()
+}
+
+/// `snapshot` as exposition text
+pub fn render(snapshot: &Snapshot) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: &[(String, f64)]| {
+        let _ = writeln!(out, "# HELP app_{name} {help}");
+        let _ = writeln!(out, "# TYPE app_{name} {kind}");
+        for (labels, value) in samples {
+            let _ = writeln!(out, "app_{name}{labels} {value}");
+        }
+    };
+    let one = |value: f64| [(String::new(), value)];
+
+    let started = snapshot
+        .started_at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    metric(
+        "start_time_seconds",
+        "gauge",
+        "When the process started, in seconds since the epoch",
+        &one(started.as_secs() as f64),
+    );
+    metric(
+        "requests_total",
+        "counter",
+        "Requests answered",
+        &one(snapshot.requests as f64),
+    );
+    metric(
+        "server_errors_total",
+        "counter",
+        "Responses with a 5xx status",
+        &one(snapshot.server_errors as f64),
+    );
+    metric(
+        "over_budget_total",
+        "counter",
+        "Requests slower than their route's latency budget",
+        &one(snapshot.over_budget as f64),
+    );
+    if let Some(p95) = snapshot.p95_latency {
+        metric(
+            "latency_p95_seconds",
+            "gauge",
+            "95th percentile latency of recent requests",
+            &one(p95.as_secs_f64()),
+        );
+    }
+    if let Some(memory) = snapshot.memory_bytes {
+        metric(
+            "resident_memory_bytes",
+            "gauge",
+            "Resident memory of the process",
+            &one(memory as f64),
+        );
+    }
+    metric(
+        "active_sessions",
+        "gauge",
+        "Sessions in the store",
+        &one(snapshot.active_sessions as f64),
+    );
+
+    let pools = [
+        ("reader", &snapshot.db_reader),
+        ("writer", &snapshot.db_writer),
+    ];
+    let per_pool = |figure: fn(&PoolSnapshot) -> f64| {
+        pools
+            .iter()
+            .map(|(name, pool)| (format!("{{pool=\"{name}\"}}"), figure(pool)))
+            .collect::<Vec<_>>()
+    };
+    metric(
+        "db_connections",
+        "gauge",
+        "Open database connections",
+        &per_pool(|pool| pool.size as f64),
+    );
+    metric(
+        "db_connections_idle",
+        "gauge",
+        "Idle database connections",
+        &per_pool(|pool| pool.idle as f64),
+    );
+    metric(
+        "db_connections_max",
+        "gauge",
+        "Most database connections the pool opens",
+        &per_pool(|pool| pool.max as f64),
+    );
+    metric(
+        "db_slow_acquires_total",
+        "counter",
+        "Probed connection acquires slower than [database] slow_acquire_ms",
+        &per_pool(|pool| pool.slow_acquires as f64),
+    );
+
+    // Cumulative, as Prometheus histograms are; the overflow bucket is +Inf
+    let mut buckets = Vec::new();
+    for (name, pool) in pools {
+        let mut seen = 0;
+        for (i, count) in pool.acquire_waits.iter().enumerate() {
+            seen += count;
+            let le = ACQUIRE_BUCKETS
+                .get(i)
+                .map_or("+Inf".to_string(), |bound| bound.as_secs_f64().to_string());
+            buckets.push((
+                format!("_bucket{{pool=\"{name}\",le=\"{le}\"}}"),
+                seen as f64,
+            ));
+        }
+        buckets.push((format!("_count{{pool=\"{name}\"}}"), seen as f64));
+    }
+    metric(
+        "db_acquire_wait_seconds",
+        "histogram",
+        "Probed waits for a database connection",
+        &buckets,
+    );
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_render() {
+        let pool = PoolSnapshot {
+            size: 2,
+            idle: 1,
+            max: 4,
+            acquire_waits: vec![3, 1, 0, 0, 0, 0, 1],
+            slow_acquires: 1,
+        };
+        let snapshot = Snapshot {
+            started_at: SystemTime::UNIX_EPOCH + Duration::from_secs(1000),
+            requests: 42,
+            server_errors: 1,
+            p95_latency: Some(Duration::from_millis(250)),
+            over_budget: 0,
+            memory_bytes: None,
+            db_reader: pool.clone(),
+            db_writer: PoolSnapshot::default(),
+            active_sessions: 3,
+        };
+        let text = render(&snapshot);
+
+        assert!(text.contains("# TYPE app_requests_total counter\napp_requests_total 42\n"));
+        assert!(text.contains("app_start_time_seconds 1000\n"));
+        assert!(text.contains("app_latency_p95_seconds 0.25\n"));
+        assert!(!text.contains("resident_memory"));
+        assert!(text.contains("app_db_connections{pool=\"reader\"} 2\n"));
+        assert!(
+            text.contains("app_db_acquire_wait_seconds_bucket{pool=\"reader\",le=\"0.001\"} 3\n")
+        );
+        assert!(
+            text.contains("app_db_acquire_wait_seconds_bucket{pool=\"reader\",le=\"0.005\"} 4\n")
+        );
+        assert!(
+            text.contains("app_db_acquire_wait_seconds_bucket{pool=\"reader\",le=\"+Inf\"} 5\n")
+        );
+        assert!(text.contains("app_db_acquire_wait_seconds_count{pool=\"reader\"} 5\n"));
+        assert!(text.contains("app_db_acquire_wait_seconds_count{pool=\"writer\"} 0\n"));
+    }
+}
@@ -1,7 +1,90 @@
+pub mod activity;
+pub mod admin;
+pub mod avatar;
+pub mod blog;
+pub mod calendar;
+pub mod cart;
+pub mod comments;
+pub mod contact;
+pub mod documents;
+pub mod embed;
+pub mod error_fragment;
+pub mod feeds;
+pub mod gallery;
+pub mod items;
+pub mod metrics;
+pub mod newsletter;
+pub mod not_found;
+pub mod notifications;
+pub mod og;
 pub mod partials;
+pub mod payments;
+pub mod presence;
+pub mod pwa;
+pub mod request_log;
+pub mod seo;
+pub mod share;
 pub mod templates;
+pub mod webhooks;
+pub mod well_known;
 
-/// Lightweight health check — no auth, no session, no template rendering
-pub async fn healthz() -> &'static str {
-    "ok"
+use axum::{
+    extract::State,
+    http::{Method, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use std::sync::Arc;
+
+use crate::handlers::error_fragment::ErrorFragment;
+use crate::models::AppState;
+use crate::services::session::SessionId;
+use crate::utils::build_info::BuildInfo;
+
+crate::sitemap_route!("/healthz", exclude);
+crate::sitemap_route!("/version", exclude);
+
+/// Lightweight health check — no auth, no session, no template rendering.
+/// A database outage reports `degraded` but stays a 200: the process is
+/// alive and serving, and restarting it wouldn't bring the database back.
+pub async fn healthz(State(state): State<Arc<AppState>>) -> &'static str {
+    if state.services.db_health.is_up() {
+        "ok"
+    } else {
+        "degraded: database unavailable"
+    }
+}
+
+/// Every route's method fallback ([`crate::routes::router`]): `OPTIONS` gets
+/// `204 No Content`, any other method the route lacks `405` with the error
+/// fragment. Axum adds the route's `Allow` header to either, and
+/// [`allow_options`](crate::middleware::allow_options) adds `OPTIONS` to it.
+pub async fn method_not_allowed(method: Method) -> Response {
+    if method == Method::OPTIONS {
+        return StatusCode::NO_CONTENT.into_response();
+    }
+    ErrorFragment::new(
+        StatusCode::METHOD_NOT_ALLOWED,
+        "Method not allowed",
+        format!("This address doesn't take {method} requests."),
+    )
+    .icon("slash-circle")
+    .into_response()
+}
+
+/// What's running, as plain text. Anyone sees the version and features; the
+/// commit and build time are for signed-in admins only.
+pub async fn version(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+) -> String {
+    let build = BuildInfo::current();
+    let mut text = format!("version: {}\nfeatures: {}\n", build.version, build.features);
+    if crate::services::admin::is_admin(state.services.sessions.as_ref(), &sid) {
+        text.push_str(&format!(
+            "git_sha: {}\nbuilt_at: {}\n",
+            build.git_sha, build.built_at
+        ));
+    }
+    text
 }
@@ -0,0 +1,275 @@
+//! Newsletter Handlers — double opt-in signup, confirmation and unsubscribe
+//!
+//! The signup form (on the blog index) records a pending subscriber and
+//! queues a confirmation email on the job queue; the response never says
+//! whether the address was already on the list. Links in emails carry signed
+//! tokens (see [`crate::services::tokens`]):
+//!
+//! - `/newsletter/confirm?token=..` confirms straight away. The token expires.
+//! - `/newsletter/unsubscribe?token=..` asks first, then POSTs, so link
+//!   scanners that prefetch URLs can't unsubscribe anyone. The token doesn't
+//!   expire.
+//!
+//! Admins download the list from `/admin/subscribers.csv`.
+
+use axum::{
+    extract::{Query, State},
+    response::Response,
+    Extension, Form,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::{AppError, AppResult};
+use crate::models::AppState;
+use crate::render::PageMeta;
+use crate::services::mail::{self, Email};
+use crate::services::session::SessionId;
+use crate::services::subscribers::{normalize_email, SubscriberStatus};
+use crate::utils::{csv, honeypot};
+
+/// Token purposes — a confirm token can't unsubscribe and vice versa
+const CONFIRM_PURPOSE: &str = "newsletter-confirm";
+const UNSUBSCRIBE_PURPOSE: &str = "newsletter-unsubscribe";
+/// How long a confirmation link works
+const CONFIRM_TTL: Duration = Duration::from_secs(48 * 3600);
+
+// =============================================================================
+// Templates
+// =============================================================================
+
+crate::define_page!(
+    NewsletterConfirmPage,
+    "pages/newsletter/confirm.html",
+    {
+        // false for an invalid or expired link
+        confirmed: bool,
+        email: String,
+        unsubscribe_url: String
+    },
+    PageMeta::new("Newsletter")
+);
+
+crate::define_page!(
+    NewsletterUnsubscribePage,
+    "pages/newsletter/unsubscribe.html",
+    {
+        valid: bool,
+        token: String,
+        email: String
+    },
+    PageMeta::new("Unsubscribe")
+);
+
+crate::define_partial!(
+    NewsletterSignupPartial,
+    "partials/newsletter_signup.html",
+    {}
+);
+
+crate::gallery_example!(NewsletterSignupPartial,
+    title: "Newsletter Signup",
+    description: "Email signup form; posts to itself and is replaced by the answer.",
+    snippet: r##"<form id="newsletter-signup" hx-post="/newsletter" hx-target="this" hx-swap="outerHTML">
+    <input type="email" name="email" class="form-control" required>
+    <button class="btn btn-primary" type="submit">Subscribe</button>
+</form>"##,
+    example: NewsletterSignupPartial {},
+);
+
+crate::define_partial!(NewsletterPendingPartial, "partials/newsletter_pending.html", {
+    email: String
+});
+
+crate::gallery_example!(NewsletterPendingPartial,
+    title: "Newsletter Pending",
+    description: "Replaces the signup form; says the same whether or not the address was already subscribed.",
+    snippet: r##"<form hx-post="/newsletter" hx-target="this" hx-swap="outerHTML">…</form>"##,
+    example: NewsletterPendingPartial { email: "ada@example.com".to_string() },
+);
+
+crate::define_partial!(
+    NewsletterUnsubscribedPartial,
+    "partials/newsletter_unsubscribed.html",
+    {
+        email: String
+    }
+);
+
+crate::gallery_example!(NewsletterUnsubscribedPartial,
+    title: "Newsletter Unsubscribed",
+    description: "Replaces the confirm button on the unsubscribe page.",
+    snippet: r##"<form hx-post="/newsletter/unsubscribe" hx-target="this" hx-swap="outerHTML">…</form>"##,
+    example: NewsletterUnsubscribedPartial { email: "ada@example.com".to_string() },
+);
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct SignupForm {
+    pub email: String,
+    /// Honeypot, see [`honeypot`]
+    #[serde(default)]
+    pub website: String,
+}
+
+#[derive(Deserialize)]
+pub struct TokenQuery {
+    #[serde(default)]
+    pub token: String,
+}
+
+pub async fn subscribe(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Form(form): Form<SignupForm>,
+) -> AppResult<NewsletterPendingPartial> {
+    let email = normalize_email(&form.email);
+    if honeypot::is_tripped(&form.website) {
+        tracing::info!("newsletter honeypot tripped");
+        return Ok(NewsletterPendingPartial { email });
+    }
+    if !mail::is_valid_address(&email) {
+        return Err(AppError::validation(
+            "That email address doesn't look right",
+        ));
+    }
+
+    // Signups per session, and confirmation emails site-wide;
+    // `[rate_limits.newsletter]`, reloadable
+    let limits = state.services.live.get().rate_limits.newsletter;
+    let limiter = &state.services.rate_limiter;
+    if !limiter.check(&format!("newsletter:{sid}"), limits.session())
+        || !limiter.check("newsletter:*", limits.site())
+    {
+        return Err(AppError::too_many_requests(
+            "Too many signups. Please try again later.",
+        ));
+    }
+
+    let subscriber = state.services.subscribers.subscribe(&email);
+    if subscriber.status == SubscriberStatus::Pending {
+        send_confirmation(&state, &email);
+    }
+    Ok(NewsletterPendingPartial { email })
+}
+
+/// Queue the confirmation email; delivery is retried by the job queue
+fn send_confirmation(state: &AppState, address: &str) {
+    let tokens = &state.services.tokens;
+    let base_url = state.config.site.base_url.trim_end_matches('/');
+    let confirm_url = format!(
+        "{base_url}/newsletter/confirm?token={}",
+        tokens.sign(CONFIRM_PURPOSE, address, Some(CONFIRM_TTL))
+    );
+    let email = Email {
+        to: address.to_string(),
+        reply_to: None,
+        subject: format!("Confirm your subscription to {}", state.config.site.title),
+        body: format!(
+            "Follow this link within 48 hours to start getting new posts:\n\n\
+             {confirm_url}\n\n\
+             Didn't sign up? Ignore this email and you won't hear from us again.\n\n\
+             Unsubscribe: {}\n",
+            unsubscribe_url(state, address)
+        ),
+    };
+    let mailer = state.services.mail.clone();
+    state.services.jobs.enqueue(
+        "newsletter-confirm",
+        Box::new(move || mailer.send(&email).map_err(|e| e.to_string())),
+    );
+}
+
+fn unsubscribe_url(state: &AppState, address: &str) -> String {
+    format!(
+        "{}/newsletter/unsubscribe?token={}",
+        state.config.site.base_url.trim_end_matches('/'),
+        state
+            .services
+            .tokens
+            .sign(UNSUBSCRIBE_PURPOSE, address, None)
+    )
+}
+
+pub async fn confirm(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TokenQuery>,
+) -> NewsletterConfirmPage {
+    let confirmed = state
+        .services
+        .tokens
+        .verify(CONFIRM_PURPOSE, &query.token)
+        .and_then(|email| state.services.subscribers.confirm(&email));
+    match confirmed {
+        Some(subscriber) => NewsletterConfirmPage {
+            confirmed: true,
+            unsubscribe_url: unsubscribe_url(&state, &subscriber.email),
+            email: subscriber.email,
+        },
+        None => NewsletterConfirmPage {
+            confirmed: false,
+            email: String::new(),
+            unsubscribe_url: String::new(),
+        },
+    }
+}
+
+/// Confirmation step — the link alone changes nothing
+pub async fn unsubscribe_page(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TokenQuery>,
+) -> NewsletterUnsubscribePage {
+    let email = state
+        .services
+        .tokens
+        .verify(UNSUBSCRIBE_PURPOSE, &query.token);
+    NewsletterUnsubscribePage {
+        valid: email.is_some(),
+        email: email.unwrap_or_default(),
+        token: query.token,
+    }
+}
+
+pub async fn unsubscribe(
+    State(state): State<Arc<AppState>>,
+    Form(form): Form<TokenQuery>,
+) -> AppResult<NewsletterUnsubscribedPartial> {
+    let email = state
+        .services
+        .tokens
+        .verify(UNSUBSCRIBE_PURPOSE, &form.token)
+        .ok_or_else(|| AppError::bad_request("This unsubscribe link is invalid"))?;
+    // Unknown addresses get the same answer: the outcome is identical
+    state.services.subscribers.unsubscribe(&email);
+    Ok(NewsletterUnsubscribedPartial { email })
+}
+
+// =============================================================================
+// Admin export
+// =============================================================================
+
+/// Every subscriber as CSV, unsubscribed ones included
+pub async fn export_subscribers(State(state): State<Arc<AppState>>) -> Response {
+    let rows = state
+        .services
+        .subscribers
+        .list_all()
+        .into_iter()
+        .map(|subscriber| {
+            vec![
+                subscriber.email,
+                subscriber.status.as_str().to_string(),
+                subscriber.created_at,
+                subscriber.confirmed_at.unwrap_or_default(),
+            ]
+        });
+    csv::download(
+        "subscribers.csv",
+        &["email", "status", "created_at", "confirmed_at"],
+        rows,
+    )
+}
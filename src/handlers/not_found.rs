@@ -0,0 +1,283 @@
+//! Not Found — the router's fallback, and the admin report of what it saw
+//!
+//! A path with no route gets a 404 page that suggests where the visitor may
+//! have meant to go: the public routes (everything `sitemap_route!` lists),
+//! the newest posts and the current tenant's items, ranked by edit distance
+//! to the path asked for (see [`suggest`]). Both sides are cut to
+//! [`MAX_PATH_CHARS`] and at most [`MAX_CANDIDATES`] are scanned, so a long
+//! path or a big catalogue can't make a 404 expensive. HTMX requests get the usual
+//! error fragment instead. Post and item pages answer unknown slugs the same
+//! way, through [`respond`].
+//!
+//! Each miss is counted in [`NotFoundLog`](crate::services::NotFoundLog);
+//! `/admin/not-found` lists the most frequent, refreshed every 30 seconds.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::error::AppError;
+use crate::handlers::seo;
+use crate::models::AppState;
+use crate::render::PageMeta;
+use crate::services::not_found::NotFoundHit;
+use crate::utils::htmx;
+
+/// Most suggestions shown
+pub const MAX_SUGGESTIONS: usize = 3;
+/// Newest posts considered
+const RECENT_POSTS: usize = 20;
+/// Paths the admin report lists
+const REPORT_SIZE: usize = 50;
+/// Characters of a path compared; anything longer is cut here first
+const MAX_PATH_CHARS: usize = 200;
+/// Candidates scanned per 404: the public routes, then posts, then items
+const MAX_CANDIDATES: usize = 500;
+
+crate::define_page!(
+    NotFoundPage,
+    "pages/not_found.html",
+    {
+        path: String,
+        // Paths, best match first; the template adds the tenant prefix
+        suggestions: Vec<String>,
+        empty: bool
+    },
+    PageMeta::new("Page not found")
+);
+
+crate::define_page!(
+    AdminNotFoundPage,
+    "pages/admin/not_found.html",
+    {
+        hits: Vec<NotFoundHit>,
+        empty: bool
+    },
+    PageMeta::new("Not found report")
+);
+
+crate::define_partial!(NotFoundReportPartial, "partials/not_found_report.html", {
+    hits: Vec<NotFoundHit>,
+    empty: bool
+});
+
+// =============================================================================
+// Suggestions
+// =============================================================================
+
+/// Levenshtein distance, by character
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn last_segment(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or_default()
+}
+
+/// Edits from `a` to `b`, if no more than a third of the characters
+fn close(a: &str, b: &str) -> Option<usize> {
+    let (a_len, b_len) = (a.chars().count(), b.chars().count());
+    let longest = a_len.max(b_len);
+    // It takes at least as many edits as the lengths differ by
+    if a_len.abs_diff(b_len) * 3 > longest {
+        return None;
+    }
+    let edits = distance(a, b);
+    (edits * 3 <= longest).then_some(edits)
+}
+
+/// `path` lowercased and cut to [`MAX_PATH_CHARS`]
+fn normalize(path: &str) -> String {
+    path.chars()
+        .take(MAX_PATH_CHARS)
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// How far `candidate` is from `wanted` (both lowercase), if it's close
+/// enough to suggest. The last segments are also compared on their own, so
+/// `/hello-wrld` finds `/blog/hello-world`, and a candidate that merely
+/// continues `wanted` (`/blog/hello` → `/blog/hello-world`) counts as one
+/// edit.
+fn score(wanted: &str, candidate: &str) -> Option<usize> {
+    let (last_wanted, last_candidate) = (last_segment(wanted), last_segment(candidate));
+    let continues = candidate.starts_with(wanted)
+        || (last_wanted.len() >= 3 && last_candidate.starts_with(last_wanted));
+    if continues {
+        return Some(1);
+    }
+    let segment = close(last_wanted, last_candidate)
+        .filter(|_| !last_wanted.is_empty())
+        .map(|edits| edits + 1);
+    match (close(wanted, candidate), segment) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Up to `limit` of the first [`MAX_CANDIDATES`] `candidates` near `path`,
+/// best first
+pub fn suggest(path: &str, candidates: &[String], limit: usize) -> Vec<String> {
+    let wanted = normalize(path.trim_end_matches('/'));
+    if wanted.is_empty() {
+        return Vec::new();
+    }
+    let mut ranked: Vec<(usize, &String)> = candidates
+        .iter()
+        .take(MAX_CANDIDATES)
+        .filter(|candidate| candidate.as_str() != "/")
+        .filter_map(|candidate| Some((score(&wanted, &normalize(candidate))?, candidate)))
+        .collect();
+    ranked.sort();
+    ranked.dedup_by(|a, b| a.1 == b.1);
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// What a 404 may point to: public routes, recent posts, the tenant's items
+fn candidates(state: &AppState) -> Vec<String> {
+    let services = &state.services;
+    let mut paths: Vec<String> = seo::sitemap_paths()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    paths.extend(
+        services
+            .posts
+            .list_published(0, RECENT_POSTS)
+            .into_iter()
+            .map(|post| format!("/blog/{}", post.slug)),
+    );
+    paths.extend(
+        services
+            .items
+            .list_all()
+            .into_iter()
+            .map(|item| format!("/items/{}", item.slug)),
+    );
+    paths.truncate(MAX_CANDIDATES);
+    paths
+}
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+/// Router fallback: any path without a route
+pub async fn not_found(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    uri: Uri,
+) -> Response {
+    respond(&state, &headers, uri.path())
+}
+
+/// The 404 for `path`, counted: the page with suggestions, or the error
+/// fragment for HTMX. Handlers whose route matched but whose record doesn't
+/// exist (a mistyped slug) answer with it too.
+pub fn respond(state: &AppState, headers: &HeaderMap, path: &str) -> Response {
+    let referer = headers
+        .get(header::REFERER)
+        .and_then(|value| value.to_str().ok());
+    state.services.not_found.record(path, referer);
+
+    if htmx::is_htmx(headers) {
+        return AppError::not_found(format!("Nothing at {path}")).into_response();
+    }
+    let suggestions = suggest(path, &candidates(state), MAX_SUGGESTIONS);
+    let page = NotFoundPage {
+        empty: suggestions.is_empty(),
+        suggestions,
+        path: path.to_string(),
+    };
+    (StatusCode::NOT_FOUND, page).into_response()
+}
+
+/// GET /admin/not-found
+pub async fn report_page(State(state): State<Arc<AppState>>) -> AdminNotFoundPage {
+    let NotFoundReportPartial { hits, empty } = report(State(state)).await;
+    AdminNotFoundPage { hits, empty }
+}
+
+/// GET /admin/not-found/report — polled by the page
+pub async fn report(State(state): State<Arc<AppState>>) -> NotFoundReportPartial {
+    let hits = state.services.not_found.top(REPORT_SIZE);
+    NotFoundReportPartial {
+        empty: hits.is_empty(),
+        hits,
+    }
+}
+
+/// DELETE /admin/not-found — start counting afresh
+pub async fn clear_report(State(state): State<Arc<AppState>>) -> NotFoundReportPartial {
+    state.services.not_found.clear();
+    report(State(state)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("about", "about"), 0);
+        assert_eq!(distance("abuot", "about"), 2);
+        assert_eq!(distance("", "pay"), 3);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let candidates: Vec<String> = ["/", "/about", "/blog", "/blog/hello-world", "/pay"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(suggest("/abuot", &candidates, 3), ["/about"]);
+        assert_eq!(suggest("/Blog/", &candidates, 3)[0], "/blog");
+        assert_eq!(
+            suggest("/hello-wrld", &candidates, 3),
+            ["/blog/hello-world"]
+        );
+        assert_eq!(
+            suggest("/blog/hello", &candidates, 3)[0],
+            "/blog/hello-world"
+        );
+        assert!(suggest("/wp-login.php", &candidates, 3).is_empty());
+        // A long candidate doesn't excuse a short segment that's all wrong
+        let report = vec!["/documents/report".to_string()];
+        assert!(suggest("/abuot", &report, 3).is_empty());
+        assert!(suggest("/", &candidates, 3).is_empty());
+    }
+
+    #[test]
+    fn test_suggest_bounded() {
+        // A huge path is cut before it's compared
+        let huge = format!("/about{}", "x".repeat(100_000));
+        let candidates = vec!["/about".to_string()];
+        assert!(suggest(&huge, &candidates, 3).is_empty());
+        assert_eq!(normalize(&huge).chars().count(), MAX_PATH_CHARS);
+        assert_eq!(close("about", &"x".repeat(100_000)), None);
+        // Candidates past the cap aren't looked at
+        let mut many: Vec<String> = (0..MAX_CANDIDATES).map(|i| format!("/z{i}")).collect();
+        many.push("/about".into());
+        assert!(suggest("/abuot", &many, 3).is_empty());
+        many.insert(0, "/about".into());
+        assert_eq!(suggest("/abuot", &many, 3), ["/about"]);
+    }
+}
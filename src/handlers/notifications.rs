@@ -0,0 +1,227 @@
+//! Notification Handlers — bell, dropdown, mark-as-read and live updates
+//!
+//! The layout's header holds a bell that loads `/partials/notifications/bell`
+//! (icon plus unread badge) and, when opened, the dropdown list from
+//! `/partials/notifications`. Marking read answers with the re-rendered list
+//! and an out-of-band bell, so the badge drops in the same swap.
+//!
+//! `/notifications/stream` is a per-session SSE stream: whenever
+//! [`notify`] or a mark-as-read changes a recipient's notifications, their
+//! open tabs get a `notifications` event and `static/js/notifications.js`
+//! has HTMX refetch the bell.
+
+use axum::{
+    extract::{Path, State},
+    response::sse::{Event, KeepAlive, Sse},
+    Extension,
+};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+
+use crate::error::{AppError, AppResult};
+use crate::models::AppState;
+use crate::services::comments::owner_key;
+use crate::services::notifications::{NewNotification, Notification, NotificationChange};
+use crate::services::session::SessionId;
+
+/// Notifications shown in the dropdown
+const LIST_LIMIT: usize = 10;
+/// Badge counts above this read `9+`
+const BADGE_MAX: usize = 9;
+
+crate::define_partial!(NotificationBellPartial, "partials/notification_bell.html", {
+    bell: NotificationBell
+});
+
+crate::define_partial!(NotificationListPartial, "partials/notification_list.html", {
+    notifications: Vec<NotificationView>,
+    empty: bool,
+    has_unread: bool,
+    // Rendered out-of-band alongside the list
+    bell: NotificationBell
+});
+
+/// The bell's unread badge
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationBell {
+    pub unread: usize,
+    // `3`, or `9+` past BADGE_MAX
+    pub badge: String,
+    pub has_unread: bool,
+    // Marked `hx-swap-oob` when sent alongside another swap
+    pub oob: bool,
+}
+
+impl NotificationBell {
+    pub fn new(unread: usize, oob: bool) -> Self {
+        Self {
+            unread,
+            badge: if unread > BADGE_MAX {
+                format!("{BADGE_MAX}+")
+            } else {
+                unread.to_string()
+            },
+            has_unread: unread > 0,
+            oob,
+        }
+    }
+}
+
+/// A notification as the templates see it
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationView {
+    pub id: u32,
+    pub title: String,
+    pub body: String,
+    pub link: String,
+    pub has_link: bool,
+    pub created_at: String,
+    pub unread: bool,
+}
+
+impl From<Notification> for NotificationView {
+    fn from(notification: Notification) -> Self {
+        Self {
+            id: notification.id,
+            has_link: !notification.link.is_empty(),
+            title: notification.title,
+            body: notification.body,
+            link: notification.link,
+            created_at: notification.created_at,
+            unread: !notification.read,
+        }
+    }
+}
+
+/// Store a notification and tell the recipient's open tabs
+pub fn notify(state: &AppState, notification: NewNotification) -> Notification {
+    let notification = state.services.notifications.create(notification);
+    state
+        .services
+        .notification_hub
+        .changed(&notification.recipient);
+    notification
+}
+
+fn bell(state: &AppState, recipient: &str, oob: bool) -> NotificationBell {
+    NotificationBell::new(state.services.notifications.unread_count(recipient), oob)
+}
+
+fn render_list(state: &AppState, recipient: &str) -> NotificationListPartial {
+    let notifications: Vec<NotificationView> = state
+        .services
+        .notifications
+        .list(recipient, LIST_LIMIT)
+        .into_iter()
+        .map(NotificationView::from)
+        .collect();
+    let bell = bell(state, recipient, true);
+    NotificationListPartial {
+        empty: notifications.is_empty(),
+        has_unread: bell.has_unread,
+        notifications,
+        bell,
+    }
+}
+
+/// Bell icon with the unread badge
+pub async fn notification_bell(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+) -> NotificationBellPartial {
+    NotificationBellPartial {
+        bell: bell(&state, &owner_key(&sid), false),
+    }
+}
+
+/// Dropdown list, with the bell out-of-band
+pub async fn notification_list(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+) -> NotificationListPartial {
+    render_list(&state, &owner_key(&sid))
+}
+
+/// Mark one notification read
+pub async fn mark_read(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Path(id): Path<u32>,
+) -> AppResult<NotificationListPartial> {
+    let recipient = owner_key(&sid);
+    if !state.services.notifications.mark_read(&recipient, id) {
+        return Err(AppError::not_found("No such notification"));
+    }
+    state.services.notification_hub.changed(&recipient);
+    Ok(render_list(&state, &recipient))
+}
+
+/// Mark every notification read
+pub async fn mark_all_read(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+) -> NotificationListPartial {
+    let recipient = owner_key(&sid);
+    if state.services.notifications.mark_all_read(&recipient) > 0 {
+        state.services.notification_hub.changed(&recipient);
+    }
+    render_list(&state, &recipient)
+}
+
+/// Demo: send this session a notification. Answers with the bell
+/// out-of-band, so the badge updates even without the stream.
+pub async fn send_demo(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+) -> NotificationBellPartial {
+    let recipient = owner_key(&sid);
+    notify(
+        &state,
+        NewNotification {
+            recipient: recipient.clone(),
+            title: format!(
+                "Test notification at {}",
+                chrono::Utc::now().format("%H:%M:%S")
+            ),
+            body: "Sent from the demo page.".into(),
+            link: "/demo".into(),
+        },
+    );
+    NotificationBellPartial {
+        bell: bell(&state, &recipient, true),
+    }
+}
+
+/// GET /notifications/stream — SSE events when this session's notifications
+/// change
+pub async fn notification_stream(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let recipient = owner_key(&sid);
+    let changes = BroadcastStream::new(state.services.notification_hub.subscribe())
+        .take_while(|change| !matches!(change, Ok(NotificationChange::Shutdown)))
+        .filter_map(move |change| match change {
+            Ok(NotificationChange::Recipient(changed)) if changed != recipient => None,
+            Ok(NotificationChange::Shutdown) => None,
+            // A lagged receiver may have missed its own change — refresh anyway
+            Ok(NotificationChange::Recipient(_)) | Err(_) => {
+                Some(Ok(Event::default().event("notifications").data("changed")))
+            }
+        });
+    Sse::new(changes).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_badge() {
+        assert!(!NotificationBell::new(0, false).has_unread);
+        assert_eq!(NotificationBell::new(3, false).badge, "3");
+        assert_eq!(NotificationBell::new(12, false).badge, "9+");
+    }
+}
@@ -0,0 +1,57 @@
+//! Open Graph Image Handler — `/og.png?title=..&description=..`
+//!
+//! `components/_head.html` points `og:image` here for every page that doesn't
+//! declare its own image. Text is cut to the service's limits before
+//! rendering, and rasterizing runs on the blocking pool.
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+use crate::models::AppState;
+use crate::services::og_image::{truncate, MAX_DESCRIPTION_CHARS, MAX_TITLE_CHARS};
+
+/// Rendered cards never change for the same text
+const OG_CACHE_CONTROL: &str = "public, max-age=86400";
+
+#[derive(Deserialize)]
+pub struct OgQuery {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+pub async fn og_image(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<OgQuery>,
+) -> AppResult<Response> {
+    let title = match truncate(&query.title, MAX_TITLE_CHARS) {
+        title if title.is_empty() => state.config.site.title.clone(),
+        title => title,
+    };
+    let description = truncate(&query.description, MAX_DESCRIPTION_CHARS);
+
+    let images = state.services.og_images.clone();
+    let png = tokio::task::spawn_blocking(move || images.render(&title, &description))
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?
+        .map_err(AppError::internal)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_static("image/png")),
+            (
+                header::CACHE_CONTROL,
+                HeaderValue::from_static(OG_CACHE_CONTROL),
+            ),
+        ],
+        png,
+    )
+        .into_response())
+}
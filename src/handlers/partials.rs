@@ -4,14 +4,34 @@
 //! HTMX swaps them into the existing page for SPA-like interactivity.
 
 use axum::{
-    extract::{Query, State},
-    response::{Html, IntoResponse},
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, Method, Uri},
+    response::{IntoResponse, Response},
+    Extension, Form,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::error::{AppError, AppResult};
+use crate::handlers::activity;
 use crate::models::AppState;
-use crate::services::items::Item;
+use crate::render;
+use crate::services::activities::NewActivity;
+use crate::services::autocomplete::Suggestion;
+use crate::services::charts::{self, Chart};
+use crate::services::items::{self, Item};
+use crate::services::sanitize::{self, Profile, SanitizedHtml};
+use crate::services::session::SessionId;
+use crate::services::slugs;
+use crate::services::stats::{self, PoolSnapshot};
+use crate::services::tenants;
+use crate::utils::csv;
+use crate::utils::htmx::{HxMorph, HxStopPolling, HxTransition};
+use crate::utils::shortcuts;
+
+crate::sitemap_route!("/partials", exclude);
 
 // =============================================================================
 // Partial Templates — using the macro for dual-mode rendering
@@ -20,48 +40,804 @@ use crate::services::items::Item;
 crate::define_partial!(StatusCardPartial, "partials/status_card.html", {
     status: String,
     uptime: String,
-    version: String
+    // `YYYY-MM-DD HH:MM UTC`
+    started: String,
+    version: String,
+    // The rest are formatted for display; `–` when unknown
+    requests: String,
+    server_errors: String,
+    p95_latency: String,
+    // Requests over their route's `[latency]` budget
+    over_budget: String,
+    memory: String,
+    // `in use / open`
+    db_pool: String,
+    db_max: String,
+    // Bucket bound holding the p95 probed wait, e.g. `≤ 5 ms`, per pool
+    db_read_wait: String,
+    db_write_wait: String,
+    // Probed waits over `[database] slow_acquire_ms`
+    db_slow_acquires: String,
+    // The template with the slowest mean render, its engine and figures
+    slow_template: String,
+    slow_template_engine: String,
+    slow_template_mean: String,
+    slow_template_renders: String,
+    request_rate: String,
+    sessions: String,
+    request_chart: Chart,
+    session_chart: Chart,
+    // Probed waits per bucket, both pools
+    db_wait_chart: Chart
 });
 
 crate::define_partial!(ItemListPartial, "partials/item_list.html", {
-    items: Vec<Item>
+    items: Vec<Item>,
+    empty: bool,
+    tags: Vec<TagFilter>,
+    active_tag: String,
+    // `ItemHub` version the list is current as of, for the long poll
+    version: u64
+});
+
+crate::define_partial!(SortableListPartial, "partials/sortable_list.html", {
+    items: Vec<Item>,
+    // Answering a reorder: swap the list in out of band
+    oob: bool,
+    // The item whose up or down button keeps focus after a move, or 0
+    focus_up: u32,
+    focus_down: u32
+});
+
+crate::gallery_example!(SortableListPartial,
+    title: "Sortable List",
+    description: "Items dragged by their handle (static/js/sortable.js) or moved with the arrow buttons; the new order is posted to /items/reorder and the list comes back out of band.",
+    snippet: r##"<form id="sortable-items" hx-post="/items/reorder" hx-trigger="end, submit" hx-swap="none">
+    <ul data-sortable>
+        <li class="sortable-item" data-id="1">
+            <input type="hidden" name="id" value="1">
+            <span class="sortable-handle"><i class="bi bi-grip-vertical"></i></span> Write tests
+            <button type="submit" name="move" value="1:down">Down</button>
+        </li>
+    </ul>
+</form>
+<script src="/static/js/sortable-list.js"></script>"##,
+    example: SortableListPartial {
+        items: ["Set up project", "Write tests", "Ship it"]
+            .into_iter()
+            .zip(1..)
+            .map(|(title, id)| Item {
+                id,
+                slug: slugs::slugify(title),
+                title: title.into(),
+                description: String::new(),
+                done: false,
+                tags: Vec::new(),
+                created_at: String::new(),
+                tenant: String::new(),
+                position: i64::from(id) * items::POSITION_GAP,
+            })
+            .collect(),
+        oob: false,
+        focus_up: 0,
+        focus_down: 0,
+    },
+);
+
+crate::define_partial!(ItemListChangesPartial, "partials/item_list_changes.html", {
+    version: u64,
+    active_tag: String
+});
+
+/// A filter chip above the item list
+#[derive(Debug, Clone, Serialize)]
+pub struct TagFilter {
+    pub name: String,
+    pub active: bool,
+}
+
+crate::define_partial!(GreetingPartial, "partials/greeting.html", {
+    name: SanitizedHtml
+});
+
+crate::gallery_example!(GreetingPartial,
+    title: "Greeting",
+    description: "A form's answer, swapped in below it. The name may carry inline formatting, so it's sanitized.",
+    snippet: r##"<form hx-get="/partials/greeting" hx-target="#greeting-target" hx-swap="innerHTML">
+    <input type="text" name="name" class="form-control">
+    <button class="btn btn-success" type="submit">Greet</button>
+</form>
+<div id="greeting-target"></div>"##,
+    example: GreetingPartial { name: sanitize::clean(Profile::Inline, "<em>Ada</em>") },
+);
+
+crate::define_partial!(ProgressPartial, "partials/progress.html", {
+    // Unix milliseconds when the task started
+    started: i64,
+    percent: u32,
+    done: bool,
+    // The polling wrapper around the bar, for the request that starts a task
+    start: bool
+});
+
+crate::gallery_example!(ProgressPartial,
+    title: "Progress",
+    description: "A task's progress, polled every second until the answer comes back with status 286.",
+    snippet: r##"<button class="btn btn-primary btn-sm" hx-get="/partials/progress"
+        hx-target="#progress-demo" hx-swap="outerHTML">Start a task</button>
+<div id="progress-demo"></div>"##,
+    example: ProgressPartial { started: 0, percent: 40, done: false, start: false },
+);
+
+crate::define_partial!(AutocompletePartial, "partials/autocomplete.html", {
+    suggestions: Vec<Suggestion>,
+    // A query that matched nothing; an empty one shows nothing at all
+    no_matches: bool
+});
+
+crate::gallery_example!(AutocompletePartial,
+    title: "Autocomplete",
+    description: "Options for an ARIA combobox, from a pluggable suggestion source; static/js/combobox.js adds the keyboard.",
+    snippet: r##"<input type="text" name="tag" role="combobox" aria-autocomplete="list" aria-expanded="false"
+       aria-controls="tag-list" autocomplete="off"
+       hx-get="/partials/autocomplete?src=tags&amp;field=tag" hx-trigger="input changed delay:200ms, focus"
+       hx-target="#tag-list" hx-sync="this:replace">
+<ul id="tag-list" class="combobox-list" role="listbox" hidden></ul>"##,
+    example: AutocompletePartial {
+        suggestions: vec![
+            Suggestion { value: "backend".into(), label: "#backend".into(), hint: String::new() },
+            Suggestion { value: "Set up project".into(), label: "Set up project".into(), hint: "done".into() },
+        ],
+        no_matches: false,
+    },
+);
+
+crate::define_partial!(DemoTabPartial, "partials/demo_tab.html", {
+    facts: Vec<TabFact>,
+    // `HH:MM:SS UTC`, to show the panel was fetched once
+    loaded_at: String
+});
+
+crate::gallery_example!(DemoTabPartial,
+    title: "Tab Panel",
+    description: "Panel content for utils::tabs; each tab fetches its panel with hx-trigger=\"click once\" the first time it opens.",
+    snippet: r##"<div class="tab-nav" role="tablist" aria-label="Workspace">
+    <button type="button" role="tab" id="demo-tabs-items-tab" aria-selected="true" aria-controls="demo-tabs-items"
+            hx-get="/partials/demo-tab/items" hx-trigger="click once" hx-target="#demo-tabs-items">Items</button>
+</div>
+<div role="tabpanel" id="demo-tabs-items" aria-labelledby="demo-tabs-items-tab" tabindex="0"></div>"##,
+    example: DemoTabPartial {
+        facts: vec![
+            TabFact { label: "Items".into(), value: "4".into() },
+            TabFact { label: "Done".into(), value: "1".into() },
+        ],
+        loaded_at: "09:30:00 UTC".into(),
+    },
+);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TabFact {
+    pub label: String,
+    pub value: String,
+}
+
+crate::define_partial!(ShortcutsPartial, "partials/shortcuts.html", {
+    shortcuts: Vec<ShortcutHelp>
+});
+
+crate::gallery_example!(ShortcutsPartial,
+    title: "Keyboard Shortcuts",
+    description: "The help dialog behind ?, listing every shortcut! registration; static/js/shortcuts.js fetches it into <dialog id=\"shortcut-help\">.",
+    snippet: r##"crate::shortcut!("g d", "/demo", "Go to the demo");"##,
+    example: ShortcutsPartial {
+        shortcuts: vec![
+            ShortcutHelp { keys: vec!["?"], label: "Show keyboard shortcuts" },
+            ShortcutHelp { keys: vec!["g", "d"], label: "Go to the demo" },
+        ],
+    },
+);
+
+/// A row of the shortcut help: the steps of its key sequence and what it does
+#[derive(Debug, Clone, Serialize)]
+pub struct ShortcutHelp {
+    pub keys: Vec<&'static str>,
+    pub label: &'static str,
+}
+
+crate::define_partial!(OfflineBannerPartial, "partials/offline_banner.html", {});
+
+crate::gallery_example!(OfflineBannerPartial,
+    title: "Offline Banner",
+    description: "Shown by static/js/app.js while the server can't be reached; forms marked data-offline-queue are kept and replayed, with their Idempotency-Key, once it can.",
+    snippet: r##"<form hx-post="/items" data-offline-queue="item-form">…</form>"##,
+    example: OfflineBannerPartial {},
+);
+
+crate::define_partial!(MorphDemoPartial, "partials/morph_demo.html", {
+    // "morph" or "outerHTML"
+    mode: &'static str,
+    time: String,
+    percent: u32
 });
 
 // =============================================================================
 // Partial Handlers
 // =============================================================================
 
-/// Status card partial — shows server health on the dashboard
-pub async fn status_card(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+/// Status card partial — shows server health and runtime stats on the
+/// dashboard, with charts of the last few minutes of traffic
+pub async fn status_card(State(state): State<Arc<AppState>>) -> StatusCardPartial {
     let health = state.services.health.get_status();
+    let stats = &state.services.stats;
+    let snapshot = stats.snapshot(&state.db, state.services.sessions.count());
+    let request_rate = stats.request_rate();
+    let sessions = stats.active_sessions();
+    let (reader, writer) = (&snapshot.db_reader, &snapshot.db_writer);
+    let open = reader.size + writer.size;
+    let in_use = open as usize - (reader.idle + writer.idle).min(open as usize);
+    let waits: Vec<f64> = reader
+        .acquire_waits
+        .iter()
+        .zip(&writer.acquire_waits)
+        .map(|(read, write)| (read + write) as f64)
+        .collect();
+
+    let slowest = render::render_timings().into_iter().next();
 
     StatusCardPartial {
         status: health.status,
         uptime: health.uptime_formatted,
+        started: chrono::DateTime::<chrono::Utc>::from(snapshot.started_at)
+            .format("%Y-%m-%d %H:%M UTC")
+            .to_string(),
         version: health.version,
+        requests: snapshot.requests.to_string(),
+        server_errors: snapshot.server_errors.to_string(),
+        p95_latency: snapshot.p95_latency.map_or("–".to_string(), format_latency),
+        over_budget: snapshot.over_budget.to_string(),
+        memory: snapshot.memory_bytes.map_or("–".to_string(), format_bytes),
+        db_pool: format!("{in_use} / {open}"),
+        db_max: (reader.max + writer.max).to_string(),
+        db_read_wait: format_wait(reader),
+        db_write_wait: format_wait(writer),
+        db_slow_acquires: (reader.slow_acquires + writer.slow_acquires).to_string(),
+        slow_template: slowest.map_or("–".to_string(), |(path, _, _)| path.to_string()),
+        slow_template_engine: slowest.map_or(String::new(), |(_, engine, _)| engine.to_string()),
+        slow_template_mean: slowest.map_or("–".to_string(), |(_, _, timing)| {
+            format_latency(timing.mean())
+        }),
+        slow_template_renders: slowest
+            .map_or(0, |(_, _, timing)| timing.renders)
+            .to_string(),
+        request_rate: request_rate
+            .last()
+            .map_or("–".to_string(), |v| format!("{v:.0}")),
+        sessions: snapshot.active_sessions.to_string(),
+        request_chart: charts::sparkline(&request_rate, "Requests per minute"),
+        session_chart: charts::bars(&sessions, "Active sessions"),
+        db_wait_chart: charts::bars(&waits, "Connection waits by duration"),
+    }
+}
+
+/// `≤ 5 ms`, `> 1 s`, or `–` before the first probe
+fn format_wait(pool: &PoolSnapshot) -> String {
+    match pool.p95_wait() {
+        Some(bound) => format!("≤ {}", format_latency(bound)),
+        None if pool.acquire_waits.iter().any(|count| *count > 0) => {
+            let slowest = stats::ACQUIRE_BUCKETS[stats::ACQUIRE_BUCKETS.len() - 1];
+            format!("> {}", format_latency(slowest))
+        }
+        None => "–".to_string(),
+    }
+}
+
+/// `0.4 ms`, `12 ms`, `1.2 s`
+fn format_latency(latency: Duration) -> String {
+    let ms = latency.as_secs_f64() * 1000.0;
+    if ms < 10.0 {
+        format!("{ms:.1} ms")
+    } else if ms < 1000.0 {
+        format!("{ms:.0} ms")
+    } else {
+        format!("{:.1} s", ms / 1000.0)
+    }
+}
+
+/// `512 KB`, `48.2 MB`, `1.1 GB`
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const MB: f64 = 1024.0 * 1024.0;
+    let bytes = bytes as f64;
+    if bytes < MB {
+        format!("{:.0} KB", bytes / 1024.0)
+    } else if bytes < 1024.0 * MB {
+        format!("{:.1} MB", bytes / MB)
+    } else {
+        format!("{:.1} GB", bytes / (1024.0 * MB))
+    }
+}
+
+/// Longest accepted item title, in characters
+pub const MAX_TITLE_CHARS: usize = 80;
+
+/// Item list partial — returns a list of items as an HTML fragment.
+///
+/// With `?tag=` it becomes a filter: only items carrying that tag are listed
+/// (an empty tag means all of them), and `HX-Push-Url` moves the page's own
+/// `?tag=` along so every filter is bookmarkable.
+pub async fn item_list(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<ItemListQuery>,
+) -> Response {
+    let Some(tag) = query.tag else {
+        return render_item_list(&state, "").into_response();
+    };
+    // Filtering by tag animates the list from one set of items to the other
+    let tag = items::normalize_tag(&tag).unwrap_or_default();
+    let partial = HxTransition("outerHTML", render_item_list(&state, &tag));
+    match push_url(&headers, &tag) {
+        Some(url) => ([("HX-Push-Url", url)], partial).into_response(),
+        None => partial.into_response(),
+    }
+}
+
+/// How long a long poll stays parked before it's answered with a fresh poll
+const LONG_POLL_TIMEOUT: Duration = Duration::from_secs(25);
+
+/// Item list long poll — a middle ground between polling and SSE. The
+/// request is parked until the tenant's items change after `?since=` (an
+/// [`ItemHub`](crate::services::ItemHub) version), then answered with the
+/// re-rendered list. After [`LONG_POLL_TIMEOUT`] with no change it answers
+/// with a new poll instead, which replaces itself (`HX-Retarget: this`) and
+/// asks again. `HEAD` gets that answer right away.
+pub async fn item_list_changes(
+    State(state): State<Arc<AppState>>,
+    method: Method,
+    Query(query): Query<ItemListChangesQuery>,
+) -> Response {
+    let tag = query
+        .tag
+        .as_deref()
+        .and_then(items::normalize_tag)
+        .unwrap_or_default();
+    let hub = &state.services.item_hub;
+    // `HEAD` only wants the headers; answer it as if nothing changed
+    if method != Method::HEAD
+        && hub
+            .wait(&tenants::current(), query.since, LONG_POLL_TIMEOUT)
+            .await
+    {
+        return render_item_list(&state, &tag).into_response();
     }
-    .render_response()
+    let poll = ItemListChangesPartial {
+        version: query.since,
+        active_tag: tag,
+    };
+    ([("HX-Retarget", "this")], poll).into_response()
 }
 
-/// Item list partial — returns a list of items as an HTML fragment
-pub async fn item_list(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let items = state.services.items.list_all();
-    ItemListPartial { items }.render_response()
+/// Add an item, returning the re-rendered list under the same filter
+pub async fn create_item(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Form(form): Form<NewItemForm>,
+) -> AppResult<ItemListPartial> {
+    let title = form.title.trim();
+    if title.is_empty() {
+        return Err(AppError::validation("Title can't be empty"));
+    }
+    if title.chars().count() > MAX_TITLE_CHARS {
+        return Err(AppError::validation(format!(
+            "Titles are limited to {MAX_TITLE_CHARS} characters"
+        )));
+    }
+
+    let item = state
+        .services
+        .items
+        .create(title.to_string(), form.description.trim().to_string());
+    let tags = items::parse_tags(&form.tags);
+    state.services.items.set_tags(item.id, tags.clone());
+    state.services.item_hub.changed(&tenants::current());
+    activity::record(
+        &state,
+        NewActivity::new(
+            "item.created",
+            activity::actor(&state, &sid),
+            format!("Created item \"{}\"", item.title),
+        )
+        .link("/demo"),
+    );
+    state.services.events.publish(
+        "item.created",
+        serde_json::to_value(Item { tags, ..item }).unwrap_or_default(),
+    );
+
+    let tag = items::normalize_tag(&form.tag).unwrap_or_default();
+    Ok(render_item_list(&state, &tag))
 }
 
-/// Greeting partial — demonstrates HTMX form submission returning a fragment
-pub async fn greeting(Query(params): Query<GreetingQuery>) -> impl IntoResponse {
-    let name = params.name.unwrap_or_else(|| "World".to_string());
-    Html(format!(
-        r#"<div class="alert alert-success">
-            <div class="alert-title"><i class="bi bi-check-circle"></i> <strong>Hello, {}!</strong></div>
-            <div class="alert-body">This fragment was loaded via HTMX.</div>
-        </div>"#,
-        html_escape::encode_text(&name)
-    ))
+/// The items in their saved order, with drag handles and move buttons
+pub async fn sortable_items(State(state): State<Arc<AppState>>) -> SortableListPartial {
+    render_sortable_list(&state, false, None)
+}
+
+/// Save a new item order: the list's hidden `id` fields in their new order
+/// after a drag, or as they were plus `move=<id>:up|down` from a move
+/// button. Either way the answer is the saved list, out of band, so an order
+/// the service rejects (a stale page, say) snaps back.
+pub async fn reorder_items(
+    State(state): State<Arc<AppState>>,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> SortableListPartial {
+    let mut ids: Vec<u32> = fields
+        .iter()
+        .filter(|(name, _)| name == "id")
+        .filter_map(|(_, value)| value.parse().ok())
+        .collect();
+    let moved = fields
+        .iter()
+        .find(|(name, _)| name == "move")
+        .and_then(|(_, value)| apply_move(&mut ids, value));
+    if state.services.items.reorder(&ids) {
+        state.services.item_hub.changed(&tenants::current());
+    }
+    render_sortable_list(&state, true, moved)
+}
+
+/// The item list as CSV. Takes the list's own `?tag=`, so the export button
+/// downloads exactly what is on screen, in the same order.
+pub async fn export_items(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ItemListQuery>,
+) -> Response {
+    let tag = query
+        .tag
+        .as_deref()
+        .and_then(items::normalize_tag)
+        .unwrap_or_default();
+    let filename = if tag.is_empty() {
+        "items.csv".to_string()
+    } else {
+        format!("items-{tag}.csv")
+    };
+    let rows = filtered_items(&state, &tag).into_iter().map(|item| {
+        vec![
+            item.id.to_string(),
+            item.title,
+            item.description,
+            if item.done { "done" } else { "pending" }.to_string(),
+            item.tags.join(" "),
+            item.created_at,
+        ]
+    });
+    csv::download(
+        &filename,
+        &["id", "title", "description", "status", "tags", "created_at"],
+        rows,
+    )
+}
+
+/// Autocomplete options — `?src=tags&q=ba` renders the `role="option"`
+/// items for a combobox's listbox. Unknown sources are a 404.
+pub async fn autocomplete(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AutocompleteQuery>,
+) -> AppResult<AutocompletePartial> {
+    let text = query.rest.get(&query.field).unwrap_or(&query.q);
+    let suggestions = state
+        .services
+        .autocomplete
+        .suggest(&query.src, text)
+        .ok_or_else(|| AppError::not_found(format!("No suggestion source {:?}", query.src)))?;
+    Ok(AutocompletePartial {
+        no_matches: suggestions.is_empty() && !text.trim().is_empty(),
+        suggestions,
+    })
+}
+
+/// A demo page tab panel — `items`, `tags` or `server`; anything else is a 404
+pub async fn demo_tab(
+    State(state): State<Arc<AppState>>,
+    Path(tab): Path<String>,
+) -> AppResult<DemoTabPartial> {
+    let fact = |label: &str, value: String| TabFact {
+        label: label.to_string(),
+        value,
+    };
+    let facts = match tab.as_str() {
+        "items" => {
+            let all = state.services.items.list_all();
+            let done = all.iter().filter(|item| item.done).count();
+            vec![
+                fact("Items", all.len().to_string()),
+                fact("Done", done.to_string()),
+                fact("Open", (all.len() - done).to_string()),
+            ]
+        }
+        "tags" => {
+            let all = state.services.items.list_all();
+            state
+                .services
+                .items
+                .list_tags()
+                .into_iter()
+                .map(|tag| {
+                    let uses = all.iter().filter(|item| item.tags.contains(&tag)).count();
+                    fact(&format!("#{tag}"), uses.to_string())
+                })
+                .collect()
+        }
+        "server" => {
+            let health = state.services.health.get_status();
+            vec![
+                fact("Version", health.version),
+                fact("Uptime", health.uptime_formatted),
+                fact("Sessions", state.services.sessions.count().to_string()),
+            ]
+        }
+        _ => return Err(AppError::not_found(format!("No tab {tab:?}"))),
+    };
+    Ok(DemoTabPartial {
+        facts,
+        loaded_at: chrono::Utc::now().format("%H:%M:%S UTC").to_string(),
+    })
+}
+
+/// The `?` help dialog: the keys shortcuts.js handles itself, then every
+/// registered navigation shortcut
+pub async fn shortcuts() -> ShortcutsPartial {
+    let builtin = [
+        ("?", "Show keyboard shortcuts"),
+        ("esc", "Close this dialog"),
+    ];
+    let shortcuts = builtin
+        .into_iter()
+        .chain(
+            shortcuts::registered()
+                .into_iter()
+                .map(|shortcut| (shortcut.keys, shortcut.label)),
+        )
+        .map(|(keys, label)| ShortcutHelp {
+            keys: keys.split_whitespace().collect(),
+            label,
+        })
+        .collect();
+    ShortcutsPartial { shortcuts }
+}
+
+/// Greeting partial — demonstrates HTMX form submission returning a fragment.
+/// The name is untrusted input that may carry inline formatting, so it goes
+/// through the sanitizer and is rendered with `|safe`.
+pub async fn greeting(Query(params): Query<GreetingQuery>) -> GreetingPartial {
+    let name = sanitize::clean(Profile::Inline, params.name.as_deref().unwrap_or_default());
+    GreetingPartial {
+        name: if name.is_empty() {
+            sanitize::clean(Profile::Inline, "World")
+        } else {
+            name
+        },
+    }
+}
+
+/// How long the pretend task behind [`progress`] takes
+const DEMO_TASK: Duration = Duration::from_secs(5);
+
+/// Progress of a pretend task — demonstrates ending a poll loop from the
+/// server. Without `?started=` it starts one and returns the element that
+/// polls; polls get the bar, and the last one is [`HxStopPolling`], so HTMX
+/// stops asking once the task is done.
+pub async fn progress(Query(query): Query<ProgressQuery>) -> Response {
+    let now = chrono::Utc::now().timestamp_millis();
+    let Some(started) = query.started else {
+        return ProgressPartial {
+            started: now,
+            percent: 0,
+            done: false,
+            start: true,
+        }
+        .into_response();
+    };
+    let total = DEMO_TASK.as_millis() as i64;
+    let percent = ((now - started).clamp(0, total) * 100 / total) as u32;
+    let partial = ProgressPartial {
+        started,
+        percent,
+        done: percent == 100,
+        start: false,
+    };
+    if partial.done {
+        HxStopPolling(partial).into_response()
+    } else {
+        partial.into_response()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ProgressQuery {
+    pub started: Option<i64>,
+}
+
+/// A panel of the components page's morphing demo, polled every few seconds.
+/// `?mode=morph` answers with [`HxMorph`], so the panel is updated in place
+/// and its note field keeps what was typed; otherwise it's swapped out whole
+/// and the note is lost. The bar fills over 30s either way, but only the
+/// morphed one animates, since its element survives the swap.
+pub async fn morph_demo(Query(query): Query<MorphDemoQuery>) -> Response {
+    let now = chrono::Utc::now();
+    let morph = query.mode.as_deref() == Some("morph");
+    let partial = MorphDemoPartial {
+        mode: if morph { "morph" } else { "outerHTML" },
+        time: now.format("%H:%M:%S").to_string(),
+        percent: (now.timestamp().rem_euclid(30) * 100 / 29) as u32,
+    };
+    if morph {
+        HxMorph(partial).into_response()
+    } else {
+        partial.into_response()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MorphDemoQuery {
+    pub mode: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct GreetingQuery {
     pub name: Option<String>,
 }
+
+#[derive(Deserialize)]
+pub struct AutocompleteQuery {
+    /// Suggestion source, see [`crate::services::autocomplete`]
+    pub src: String,
+    #[serde(default)]
+    pub q: String,
+    /// Name of the parameter holding the query, when it isn't `q`: an input
+    /// sends its value under its own name
+    #[serde(default)]
+    pub field: String,
+    #[serde(flatten)]
+    pub rest: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+pub struct ItemListQuery {
+    pub tag: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ItemListChangesQuery {
+    pub since: u64,
+    pub tag: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NewItemForm {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    /// Comma-separated, as submitted by the tag input
+    #[serde(default)]
+    pub tags: String,
+    /// Filter active when the form was submitted
+    #[serde(default)]
+    pub tag: String,
+}
+
+/// Items carrying `tag`, or all of them when it's empty
+fn filtered_items(state: &AppState, tag: &str) -> Vec<Item> {
+    if tag.is_empty() {
+        state.services.items.list_all()
+    } else {
+        state.services.items.list_tagged(tag)
+    }
+}
+
+fn render_item_list(state: &AppState, tag: &str) -> ItemListPartial {
+    // Read first: a change made while listing shows up in the next poll
+    let version = state.services.item_hub.version();
+    let items = filtered_items(state, tag);
+    ItemListPartial {
+        empty: items.is_empty(),
+        items,
+        tags: state
+            .services
+            .items
+            .list_tags()
+            .into_iter()
+            .map(|name| TagFilter {
+                active: name == tag,
+                name,
+            })
+            .collect(),
+        active_tag: tag.to_string(),
+        version,
+    }
+}
+
+/// Apply a move button's `<id>:up` or `<id>:down` to `ids`, returning the
+/// item and whether its up button should keep focus: the down button does
+/// once the item reaches the top, where up is disabled, and vice versa.
+fn apply_move(ids: &mut [u32], value: &str) -> Option<(u32, bool)> {
+    let (id, direction) = value.split_once(':')?;
+    let id: u32 = id.parse().ok()?;
+    let from = ids.iter().position(|&each| each == id)?;
+    let to = match direction {
+        "up" => from.checked_sub(1)?,
+        "down" => Some(from + 1).filter(|&to| to < ids.len())?,
+        _ => return None,
+    };
+    ids.swap(from, to);
+    let up = if direction == "up" {
+        to > 0
+    } else {
+        to == ids.len() - 1
+    };
+    Some((id, up))
+}
+
+fn render_sortable_list(
+    state: &AppState,
+    oob: bool,
+    moved: Option<(u32, bool)>,
+) -> SortableListPartial {
+    let (focus_up, focus_down) = match moved {
+        Some((id, true)) => (id, 0),
+        Some((id, false)) => (0, id),
+        None => (0, 0),
+    };
+    SortableListPartial {
+        items: state.services.items.list_all(),
+        oob,
+        focus_up,
+        focus_down,
+    }
+}
+
+/// The browser's current path with `?tag=` set to `tag` (bare path when
+/// empty). `None` when the request didn't come from HTMX or the URL already
+/// matches, so reloading a filtered page doesn't stack history entries.
+fn push_url(headers: &HeaderMap, tag: &str) -> Option<HeaderValue> {
+    let current: Uri = headers.get("HX-Current-URL")?.to_str().ok()?.parse().ok()?;
+    let current_tag = current
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("tag="))
+        .unwrap_or_default();
+    if current_tag == tag {
+        return None;
+    }
+
+    // Tags are normalized to [a-z0-9-], so they need no percent-encoding
+    let url = if tag.is_empty() {
+        current.path().to_string()
+    } else {
+        format!("{}?tag={tag}", current.path())
+    };
+    HeaderValue::from_str(&url).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_move_swaps_and_picks_focus() {
+        let mut ids = vec![1, 2, 3];
+        assert_eq!(apply_move(&mut ids, "2:up"), Some((2, false)));
+        assert_eq!(ids, [2, 1, 3]);
+        assert_eq!(apply_move(&mut ids, "1:down"), Some((1, true)));
+        assert_eq!(ids, [2, 3, 1]);
+        assert_eq!(apply_move(&mut ids, "3:up"), Some((3, false)));
+        assert_eq!(ids, [3, 2, 1]);
+        // Past either end, unknown ids and junk leave the order alone
+        assert_eq!(apply_move(&mut ids, "3:up"), None);
+        assert_eq!(apply_move(&mut ids, "1:down"), None);
+        assert_eq!(apply_move(&mut ids, "9:up"), None);
+        assert_eq!(apply_move(&mut ids, "2:sideways"), None);
+        assert_eq!(ids, [3, 2, 1]);
+    }
+}
@@ -0,0 +1,263 @@
+//! Payment Handlers — checkout against the configured provider
+//!
+//! `/pay` picks an amount and `POST /payments` creates the intent, answering
+//! with `#payment`: the confirm form. `POST /payments/:id/confirm` hands the
+//! provider the method; while the payment is processing, `#payment` polls
+//! `/partials/payments/:id` until the outcome is in, and the last answer is
+//! [`HxStopPolling`]. Outcomes also arrive as webhooks
+//! (`/webhooks/<provider>`, see [`crate::handlers::webhooks`]) and are
+//! applied by [`reconcile`]; the poll then just picks them up.
+//!
+//! See [`crate::services::payments`] for the flow and for slotting in a real
+//! provider.
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Extension, Form,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+use crate::handlers::documents::money;
+use crate::models::AppState;
+use crate::render::PageMeta;
+use crate::services::comments::owner_key;
+use crate::services::payments::{PaymentError, PaymentIntent, PaymentStatus, Payments, TEST_CARDS};
+use crate::services::session::SessionId;
+use crate::services::webhook_events::WebhookEvent;
+use crate::utils::forms::Field;
+use crate::utils::htmx::HxStopPolling;
+
+crate::sitemap_route!("/pay");
+crate::sitemap_route!("/payments", exclude);
+
+/// What the demo offers to pay, in pence
+const AMOUNTS: &[u64] = &[500, 1_000, 2_500];
+/// Preselected in the form
+const DEFAULT_AMOUNT: u64 = 1_000;
+/// What every demo payment is for
+const DESCRIPTION: &str = "Tip jar";
+
+// =============================================================================
+// Templates
+// =============================================================================
+
+crate::define_page!(
+    PayPage,
+    "pages/pay.html",
+    {
+        amount: Field,
+        provider: &'static str
+    },
+    PageMeta::new("Payments").description("A checkout flow against a swappable payment provider.")
+);
+
+crate::define_partial!(PaymentPartial, "partials/payment.html", {
+    payment: PaymentView,
+    // How to pay, while the payment waits for confirmation
+    method: Field
+});
+
+crate::gallery_example!(PaymentPartial,
+    title: "Payment",
+    description: "A payment intent: the confirm form, then a status that polls until the provider settles it.",
+    snippet: r##"<form hx-post="/payments" hx-target="#payment" hx-swap="outerHTML">…</form>"##,
+    example: PaymentPartial {
+        payment: PaymentView {
+            id: "3f2a9c".into(),
+            amount: money(DEFAULT_AMOUNT),
+            description: DESCRIPTION.into(),
+            reference: "fake_pi_7d41e0".into(),
+            status: PaymentStatus::RequiresConfirmation.as_str(),
+            failure: String::new(),
+        },
+        method: method_field(TEST_CARDS),
+    },
+);
+
+/// A payment as the templates see it
+#[derive(Debug, Clone, Serialize)]
+pub struct PaymentView {
+    pub id: String,
+    pub amount: String,
+    pub description: String,
+    pub reference: String,
+    /// [`PaymentStatus::as_str`]
+    pub status: &'static str,
+    pub failure: String,
+}
+
+impl From<PaymentIntent> for PaymentView {
+    fn from(intent: PaymentIntent) -> Self {
+        Self {
+            id: intent.id,
+            amount: money(intent.amount_cents),
+            description: intent.description,
+            reference: intent.reference,
+            status: intent.status.as_str(),
+            failure: intent.failure,
+        }
+    }
+}
+
+/// Test cards when the provider has them, else the token its card fields
+/// would produce
+pub fn method_field(test_methods: &[(&'static str, &'static str)]) -> Field {
+    if test_methods.is_empty() {
+        return Field::text("method", "Payment method")
+            .id("payment-method")
+            .hint("The token your provider's card fields produce.")
+            .required();
+    }
+    Field::radios("method", "Test card", test_methods.iter().copied())
+        .id("payment-method")
+        .value(test_methods[0].0)
+}
+
+fn render(payments: &Payments, intent: PaymentIntent) -> PaymentPartial {
+    PaymentPartial {
+        method: method_field(payments.provider().test_methods()),
+        payment: PaymentView::from(intent),
+    }
+}
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+/// Run a provider call off the async runtime
+async fn blocking<T: Send + 'static>(
+    state: &Arc<AppState>,
+    call: impl FnOnce(&Payments) -> T + Send + 'static,
+) -> AppResult<T> {
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || call(&state.services.payments))
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))
+}
+
+fn payment_error(error: PaymentError) -> AppError {
+    match error {
+        PaymentError::NotFound => AppError::not_found("No such payment"),
+        e => {
+            tracing::error!(error = %e, "payment provider call failed");
+            AppError::internal("The payment provider couldn't be reached. Please try again.")
+        }
+    }
+}
+
+/// The amounts on offer, the default picked
+pub fn amount_field() -> Field {
+    Field::radios(
+        "amount",
+        "Amount",
+        AMOUNTS
+            .iter()
+            .map(|cents| (cents.to_string(), money(*cents))),
+    )
+    .id("payment-amount")
+    .value(DEFAULT_AMOUNT.to_string())
+}
+
+/// GET /pay
+pub async fn pay_page(State(state): State<Arc<AppState>>) -> PayPage {
+    PayPage {
+        amount: amount_field(),
+        provider: state.services.payments.provider().name(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateForm {
+    pub amount: String,
+}
+
+/// POST /payments — step 1, the intent
+pub async fn create_payment(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Form(form): Form<CreateForm>,
+) -> AppResult<PaymentPartial> {
+    let amount = form
+        .amount
+        .parse::<u64>()
+        .ok()
+        .filter(|cents| AMOUNTS.contains(cents))
+        .ok_or_else(|| AppError::validation("Pick one of the amounts"))?;
+    let owner = owner_key(&sid);
+    blocking(&state, move |payments| {
+        let intent = payments
+            .create(&owner, amount, DESCRIPTION)
+            .map_err(payment_error)?;
+        Ok(render(payments, intent))
+    })
+    .await?
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmForm {
+    pub method: String,
+}
+
+/// POST /payments/:id/confirm — step 2, pay
+pub async fn confirm_payment(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Path(id): Path<String>,
+    Form(form): Form<ConfirmForm>,
+) -> AppResult<PaymentPartial> {
+    let owner = owner_key(&sid);
+    blocking(&state, move |payments| {
+        let intent = payments
+            .confirm(&owner, &id, form.method.trim())
+            .map_err(payment_error)?;
+        Ok(render(payments, intent))
+    })
+    .await?
+}
+
+/// GET /partials/payments/:id — the status, polled while processing
+pub async fn payment_status(
+    State(state): State<Arc<AppState>>,
+    Extension(SessionId(sid)): Extension<SessionId>,
+    Path(id): Path<String>,
+) -> AppResult<Response> {
+    let owner = owner_key(&sid);
+    let partial = blocking(&state, move |payments| {
+        payments
+            .refresh(&owner, &id)
+            .map(|intent| render(payments, intent))
+            .ok_or_else(|| AppError::not_found("No such payment"))
+    })
+    .await??;
+    Ok(
+        if partial.payment.status == PaymentStatus::Processing.as_str() {
+            partial.into_response()
+        } else {
+            HxStopPolling(partial).into_response()
+        },
+    )
+}
+
+// =============================================================================
+// Webhooks
+// =============================================================================
+
+crate::webhook_handler!("fake", "*", reconcile);
+
+/// Step 3: apply a provider's webhook to its payment. A real provider's
+/// deliveries are routed here the same way:
+/// `crate::webhook_handler!("<provider>", "*", reconcile);`
+pub fn reconcile(state: &AppState, event: &WebhookEvent) -> Result<(), String> {
+    let payments = &state.services.payments;
+    // Not the provider in use, so not about any payment made here
+    if event.provider != payments.provider().name() {
+        return Ok(());
+    }
+    if !payments.reconcile(&event.event_type, &event.payload) {
+        tracing::info!(event_type = %event.event_type, delivery = %event.event_id, "payment webhook ignored");
+    }
+    Ok(())
+}
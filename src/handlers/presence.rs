@@ -0,0 +1,95 @@
+//! Presence Handlers — `/partials/online-users`
+//!
+//! A "who's online" list: named sessions seen within
+//! [`ONLINE_WINDOW`](crate::services::presence::ONLINE_WINDOW) with their
+//! identicons, plus a count of everyone else. The fragment polls itself, so
+//! it can sit on any page. Templates ask about one person with
+//! `is_online(name)`, see [`crate::globals::is_online`].
+
+use axum::extract::State;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::models::AppState;
+use crate::services::avatars;
+use crate::services::presence::Seen;
+
+crate::define_partial!(OnlineUsersPartial, "partials/online_users.html", {
+    users: Vec<OnlineUser>,
+    has_users: bool,
+    // Online sessions without a name, e.g. `1 guest`, `3 guests`
+    guests_label: String,
+    has_guests: bool,
+    empty: bool
+});
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OnlineUser {
+    pub name: String,
+    pub avatar: String,
+}
+
+impl OnlineUsersPartial {
+    /// Build from online entries, most recent first. Sessions sharing a name
+    /// are one user.
+    pub fn new(online: Vec<Seen>) -> Self {
+        let mut names = HashSet::new();
+        let mut users = Vec::new();
+        let mut guests = 0;
+        for entry in online {
+            if entry.name.is_empty() {
+                guests += 1;
+            } else if names.insert(entry.name.to_lowercase()) {
+                users.push(OnlineUser {
+                    avatar: avatars::url(&entry.name),
+                    name: entry.name,
+                });
+            }
+        }
+        Self {
+            empty: users.is_empty() && guests == 0,
+            has_users: !users.is_empty(),
+            has_guests: guests > 0,
+            guests_label: match guests {
+                1 => "1 guest".into(),
+                n => format!("{n} guests"),
+            },
+            users,
+        }
+    }
+}
+
+/// Who's online right now
+pub async fn online_users(State(state): State<Arc<AppState>>) -> OnlineUsersPartial {
+    OnlineUsersPartial::new(state.services.presence.online())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn seen(key: &str, name: &str) -> Seen {
+        Seen {
+            key: key.into(),
+            name: name.into(),
+            last_seen: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_groups_names_and_counts_guests() {
+        let partial = OnlineUsersPartial::new(vec![
+            seen("a", "Ada"),
+            seen("b", ""),
+            seen("c", "ada"),
+            seen("d", "Grace"),
+        ]);
+        let names: Vec<_> = partial.users.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, ["Ada", "Grace"]);
+        assert_eq!(partial.guests_label, "1 guest");
+        assert!(!partial.empty);
+        assert!(OnlineUsersPartial::new(Vec::new()).empty);
+    }
+}
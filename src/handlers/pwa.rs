@@ -0,0 +1,164 @@
+//! PWA Handlers — the web app manifest, the service worker and its offline page
+//!
+//! `components/_head.html` links `/manifest.webmanifest`, so browsers can
+//! install the site with the favicon set ([`favicons`]). `app.js` registers
+//! `/sw.js`, which keeps a copy of the offline page and what it loads (the
+//! shell) and answers a page load the network can't with that page. Pages
+//! themselves are never served from the cache.
+//!
+//! The worker's source is `static/js/sw.js`; [`service_worker`] puts the
+//! shell's URLs and a version in front of it. The CSS bundle's name changes
+//! with its content, so a new stylesheet means a new version, and browsers
+//! replace the worker and its cache.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderName, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::models::AppState;
+use crate::render::PageMeta;
+use crate::utils::{assets, css, favicons};
+
+/// Where the offline page is served, and cached by the worker
+pub const OFFLINE_PATH: &str = "/offline";
+
+/// The worker, before [`service_worker`] adds the shell
+const WORKER_SOURCE: &str = include_str!("../../static/js/sw.js");
+
+/// `--color-brand` and `--color-background` of `components/_tokens.html`:
+/// the installed app's title bar and splash screen
+const THEME_COLOR: &str = "#6366f1";
+const BACKGROUND_COLOR: &str = "#ffffff";
+
+/// Browsers look for a new manifest now and then, not on every load
+const MANIFEST_CACHE_CONTROL: &str = "public, max-age=3600";
+
+crate::define_page!(
+    OfflinePage,
+    "pages/offline.html",
+    {},
+    PageMeta::new("Offline")
+        .description("The connection is down.")
+        .layout("layouts/minimal.html")
+);
+
+/// What the worker caches: the offline page and every file it loads
+pub fn shell() -> Vec<String> {
+    let mut shell = vec![
+        OFFLINE_PATH.to_string(),
+        assets::asset(css::BUNDLE_ASSET),
+        "/static/fonts/bootstrap-icons.woff2".to_string(),
+    ];
+    // The icons `favicon_links()` names
+    shell.extend(favicons::linked().map(favicons::Favicon::href));
+    // The scripts of `components/_head.html` and the minimal layout
+    shell.extend(
+        [
+            "htmx.min.js",
+            "head-support.js",
+            "idiomorph.js",
+            "sortable.js",
+            "mousetrap.js",
+            "clipboard.js",
+            "app.js",
+        ]
+        .map(|script| format!("/static/js/{script}")),
+    );
+    if let Some(tailwind) = css::tailwind() {
+        shell.push(tailwind.href());
+    }
+    shell
+}
+
+/// GET /manifest.webmanifest
+pub async fn manifest(State(state): State<Arc<AppState>>) -> Response {
+    let title = &state.config.site.title;
+    let icon = |name: &str| favicons::get(name).map(favicons::Favicon::href);
+    let manifest = json!({
+        "name": title,
+        "short_name": title,
+        "start_url": "/",
+        "scope": "/",
+        "display": "standalone",
+        "theme_color": THEME_COLOR,
+        "background_color": BACKGROUND_COLOR,
+        "icons": [
+            { "src": icon("favicon.svg"), "sizes": "any", "type": "image/svg+xml" },
+            { "src": icon("icon-192.png"), "sizes": "192x192", "type": "image/png", "purpose": "any maskable" },
+            { "src": icon("icon-512.png"), "sizes": "512x512", "type": "image/png", "purpose": "any maskable" },
+        ],
+    });
+    (
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/manifest+json"),
+            ),
+            (
+                header::CACHE_CONTROL,
+                HeaderValue::from_static(MANIFEST_CACHE_CONTROL),
+            ),
+        ],
+        manifest.to_string(),
+    )
+        .into_response()
+}
+
+/// The worker's script: `OFFLINE`, `SHELL` and `VERSION`, then
+/// `static/js/sw.js`
+pub fn worker_script() -> String {
+    let shell = serde_json::to_string(&shell()).unwrap_or_default();
+    let mut hash = Sha256::new();
+    hash.update(shell.as_bytes());
+    hash.update(WORKER_SOURCE.as_bytes());
+    let version = &hex::encode(hash.finalize())[..8];
+    format!(
+        "var OFFLINE = {OFFLINE_PATH:?};\nvar SHELL = {shell};\nvar VERSION = {version:?};\n\n{WORKER_SOURCE}"
+    )
+}
+
+/// GET /sw.js — served from the root with `Service-Worker-Allowed: /`, so
+/// the worker's scope is the whole site. `no-cache` makes browsers check for
+/// a new version on every visit.
+pub async fn service_worker() -> Response {
+    (
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("text/javascript; charset=utf-8"),
+            ),
+            (header::CACHE_CONTROL, HeaderValue::from_static("no-cache")),
+            (
+                HeaderName::from_static("service-worker-allowed"),
+                HeaderValue::from_static("/"),
+            ),
+        ],
+        worker_script(),
+    )
+        .into_response()
+}
+
+/// GET /offline — what the worker shows for a page it couldn't load
+pub async fn offline_page() -> impl IntoResponse {
+    OfflinePage {}.seo(|seo| seo.noindex())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worker_script() {
+        let script = worker_script();
+        assert!(script.starts_with("var OFFLINE = \"/offline\";\nvar SHELL = [\"/offline\","));
+        assert!(script.contains("\"/static/js/htmx.min.js\""));
+        assert!(script.ends_with(WORKER_SOURCE));
+        // Same shell, same version
+        assert_eq!(script, worker_script());
+    }
+}
@@ -0,0 +1,172 @@
+//! Request Log Handlers — `/admin/requests`
+//!
+//! Browses what [`RequestLog`](crate::services::RequestLog) has written: the
+//! newest 100 requests matching the filters (part of the path, status class,
+//! time window) and the 50th, 90th and 99th percentile durations over every
+//! match. The list is `/admin/requests/log`, refreshed every 10 seconds and
+//! whenever a filter changes. With `[request_log] enabled = false` the page
+//! says how to turn it on.
+
+use axum::extract::{Query, State};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::models::AppState;
+use crate::render::PageMeta;
+use crate::services::request_log::{LoggedRequest, RequestFilter};
+
+/// Status classes offered, `0` for any
+const STATUSES: &[(u16, &str)] = &[
+    (0, "Any status"),
+    (2, "2xx"),
+    (3, "3xx"),
+    (4, "4xx"),
+    (5, "5xx"),
+];
+/// Time windows offered, in minutes; `0` for everything kept
+const WINDOWS: &[(u64, &str)] = &[
+    (0, "Everything kept"),
+    (15, "Last 15 minutes"),
+    (60, "Last hour"),
+    (1440, "Last 24 hours"),
+];
+
+crate::define_page!(
+    AdminRequestsPage,
+    "pages/admin/requests.html",
+    {
+        // `[request_log] enabled`
+        enabled: bool,
+        path: String,
+        statuses: Vec<FilterOption>,
+        windows: Vec<FilterOption>,
+        // The list, as in `RequestLogPartial`
+        rows: Vec<RequestRow>,
+        empty: bool,
+        count: u64,
+        p50: String,
+        p90: String,
+        p99: String,
+        dropped: u64
+    },
+    PageMeta::new("Requests")
+);
+
+crate::define_partial!(RequestLogPartial, "partials/request_log.html", {
+    rows: Vec<RequestRow>,
+    empty: bool,
+    // Requests matching, and their durations as `12.3 ms`
+    count: u64,
+    p50: String,
+    p90: String,
+    p99: String,
+    // Records dropped since startup because the queue was full
+    dropped: u64
+});
+
+/// A choice in one of the filter selects
+#[derive(Debug, Clone, Serialize)]
+pub struct FilterOption {
+    pub value: String,
+    pub label: String,
+    pub selected: bool,
+}
+
+/// A row in the request list
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestRow {
+    pub created_at: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    /// `12.3 ms`
+    pub duration: String,
+    pub request_id: String,
+    /// 4xx
+    pub warning: bool,
+    /// 5xx
+    pub error: bool,
+}
+
+impl From<LoggedRequest> for RequestRow {
+    fn from(request: LoggedRequest) -> Self {
+        Self {
+            duration: millis(request.duration_us),
+            warning: (400..500).contains(&request.status),
+            error: request.status >= 500,
+            created_at: request.created_at,
+            method: request.method,
+            path: request.path,
+            status: request.status,
+            request_id: request.request_id,
+        }
+    }
+}
+
+/// Microseconds as `12.3 ms`
+fn millis(us: u64) -> String {
+    format!("{:.1} ms", us as f64 / 1000.0)
+}
+
+fn options<T: PartialEq + ToString>(choices: &[(T, &str)], current: &T) -> Vec<FilterOption> {
+    choices
+        .iter()
+        .map(|(value, label)| FilterOption {
+            value: value.to_string(),
+            label: label.to_string(),
+            selected: value == current,
+        })
+        .collect()
+}
+
+/// GET /admin/requests
+pub async fn requests_page(
+    State(state): State<Arc<AppState>>,
+    Query(filter): Query<RequestFilter>,
+) -> AdminRequestsPage {
+    let RequestLogPartial {
+        rows,
+        empty,
+        count,
+        p50,
+        p90,
+        p99,
+        dropped,
+    } = log_partial(&state, &filter);
+    AdminRequestsPage {
+        enabled: state.services.request_log.is_enabled(),
+        statuses: options(STATUSES, &filter.status),
+        windows: options(WINDOWS, &filter.minutes),
+        path: filter.path,
+        rows,
+        empty,
+        count,
+        p50,
+        p90,
+        p99,
+        dropped,
+    }
+}
+
+/// GET /admin/requests/log — the list, with the page's filters
+pub async fn request_log(
+    State(state): State<Arc<AppState>>,
+    Query(filter): Query<RequestFilter>,
+) -> RequestLogPartial {
+    log_partial(&state, &filter)
+}
+
+fn log_partial(state: &AppState, filter: &RequestFilter) -> RequestLogPartial {
+    let service = &state.services.request_log;
+    let page = service.query(filter);
+    let rows: Vec<RequestRow> = page.rows.into_iter().map(RequestRow::from).collect();
+    RequestLogPartial {
+        empty: rows.is_empty(),
+        rows,
+        count: page.latency.count,
+        p50: millis(page.latency.p50),
+        p90: millis(page.latency.p90),
+        p99: millis(page.latency.p99),
+        dropped: service.dropped(),
+    }
+}
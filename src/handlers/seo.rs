@@ -0,0 +1,191 @@
+//! SEO Handlers — `/robots.txt` and `/sitemap.xml`
+//!
+//! Page routes opt in to the sitemap where they are handled, with
+//! [`sitemap_route!`](crate::sitemap_route):
+//!
+//! ```ignore
+//! crate::sitemap_route!("/about");            // listed in sitemap.xml
+//! crate::sitemap_route!("/admin", exclude);   // Disallow: in robots.txt
+//! ```
+//!
+//! Routes without an annotation appear in neither. Published blog posts are
+//! added to the sitemap from the post service.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::config::RobotsConfig;
+use crate::models::AppState;
+use crate::services::posts::Post;
+use crate::utils::html::escape;
+
+/// How long clients and shared caches may reuse either document
+const SEO_CACHE_CONTROL: &str = "public, max-age=3600";
+
+/// A `sitemap_route!` annotation, collected at link time
+pub struct SitemapRoute {
+    /// Route path, e.g. `/about`. Excluded paths cover everything below them.
+    pub path: &'static str,
+    /// `false` keeps crawlers out instead of listing the route
+    pub include: bool,
+}
+
+inventory::collect!(SitemapRoute);
+
+/// Annotate a route for the sitemap, or with `exclude` for robots.txt
+#[macro_export]
+macro_rules! sitemap_route {
+    ($path:literal) => {
+        $crate::sitemap_route!(@register $path, true);
+    };
+    ($path:literal, exclude) => {
+        $crate::sitemap_route!(@register $path, false);
+    };
+    (@register $path:literal, $include:expr) => {
+        $crate::render::inventory::submit! {
+            $crate::handlers::seo::SitemapRoute {
+                path: $path,
+                include: $include,
+            }
+        }
+    };
+}
+
+/// Annotated paths with the given `include` flag, sorted
+fn annotated(include: bool) -> Vec<&'static str> {
+    let mut paths: Vec<&'static str> = inventory::iter::<SitemapRoute>
+        .into_iter()
+        .filter(|route| route.include == include)
+        .map(|route| route.path)
+        .collect();
+    paths.sort_unstable();
+    paths.dedup();
+    paths
+}
+
+/// Paths the sitemap lists; also what a 404 suggests from, see
+/// [`crate::handlers::not_found`]
+pub fn sitemap_paths() -> Vec<&'static str> {
+    annotated(true)
+}
+
+fn text_response(content_type: &'static str, body: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_static(content_type)),
+            (
+                header::CACHE_CONTROL,
+                HeaderValue::from_static(SEO_CACHE_CONTROL),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+// =============================================================================
+// robots.txt
+// =============================================================================
+
+fn render_robots(config: &RobotsConfig, excluded: &[&str], sitemap_url: &str) -> String {
+    let mut txt = String::from("User-agent: *\n");
+    if !config.allow_indexing {
+        txt.push_str("Disallow: /\n");
+        return txt;
+    }
+    let mut disallow: Vec<&str> = excluded.to_vec();
+    disallow.extend(config.disallow.iter().map(String::as_str));
+    if disallow.is_empty() {
+        txt.push_str("Disallow:\n");
+    }
+    for path in disallow {
+        txt.push_str(&format!("Disallow: {path}\n"));
+    }
+    txt.push_str(&format!("\nSitemap: {sitemap_url}\n"));
+    txt
+}
+
+pub async fn robots_txt(State(state): State<Arc<AppState>>) -> Response {
+    let sitemap_url = format!(
+        "{}/sitemap.xml",
+        state.config.site.base_url.trim_end_matches('/')
+    );
+    text_response(
+        "text/plain; charset=utf-8",
+        render_robots(&state.config.robots, &annotated(false), &sitemap_url),
+    )
+}
+
+// =============================================================================
+// sitemap.xml
+// =============================================================================
+
+fn render_sitemap(base_url: &str, paths: &[&str], posts: &[Post]) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for path in paths {
+        xml.push_str(&format!(
+            "  <url><loc>{}</loc></url>\n",
+            escape(&format!("{base_url}{path}"))
+        ));
+    }
+    for post in posts {
+        let lastmod = post.updated_at.get(..10).unwrap_or(&post.updated_at);
+        xml.push_str(&format!(
+            "  <url><loc>{}</loc><lastmod>{lastmod}</lastmod></url>\n",
+            escape(&format!("{base_url}/blog/{}", post.slug))
+        ));
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+pub async fn sitemap_xml(State(state): State<Arc<AppState>>) -> Response {
+    let posts = &state.services.posts;
+    let published = posts.list_published(0, posts.count_published());
+    text_response(
+        "application/xml; charset=utf-8",
+        render_sitemap(&state.config.site.base_url, &annotated(true), &published),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_robots() {
+        let mut config = RobotsConfig {
+            allow_indexing: true,
+            disallow: vec!["/private".into()],
+        };
+        let txt = render_robots(&config, &["/admin"], "https://example.com/sitemap.xml");
+        assert_eq!(
+            txt,
+            "User-agent: *\nDisallow: /admin\nDisallow: /private\n\n\
+             Sitemap: https://example.com/sitemap.xml\n"
+        );
+
+        config.allow_indexing = false;
+        let txt = render_robots(&config, &["/admin"], "https://example.com/sitemap.xml");
+        assert_eq!(txt, "User-agent: *\nDisallow: /\n");
+    }
+
+    #[test]
+    fn test_annotations_split() {
+        let included = annotated(true);
+        let excluded = annotated(false);
+        assert!(included.contains(&"/about"));
+        assert!(excluded.contains(&"/admin"));
+        assert!(!included.iter().any(|path| excluded.contains(path)));
+
+        let xml = render_sitemap("https://example.com/", &included, &[]);
+        assert!(xml.contains("<loc>https://example.com/about</loc>"));
+        assert!(!xml.contains("/admin"));
+    }
+}
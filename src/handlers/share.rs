@@ -0,0 +1,224 @@
+//! Share Handlers — short-lived links to a page
+//!
+//! The share partial asks how long a link should last, then `POST /share`
+//! mints it: `/s/<token>`, where the token is the page's path signed by
+//! [`TokenSigner`](crate::services::TokenSigner) with an expiry. Opening the
+//! link redirects to the page until then, and is a 404 after. Links are
+//! absolute, built on `[site] base_url` and the tenant's prefix, so they work
+//! pasted anywhere.
+//!
+//! ```html
+//! <div hx-get="/partials/share?path=/components&title=Components" hx-trigger="load" hx-swap="outerHTML"></div>
+//! ```
+
+use axum::{
+    extract::{Path, Query, State},
+    response::Redirect,
+    Extension, Form,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::{AppError, AppResult};
+use crate::models::AppState;
+use crate::services::tenants::Tenant;
+use crate::utils::clipboard::CopyButton;
+
+crate::sitemap_route!("/s", exclude);
+
+/// [`TokenSigner`](crate::services::TokenSigner) purpose of share links
+const TOKEN_PURPOSE: &str = "share";
+/// How long a link may last, in hours, as offered by the form
+const LIFETIMES: &[(u64, &str)] = &[(1, "1 hour"), (24, "1 day"), (168, "7 days")];
+/// Preselected in the form
+const DEFAULT_HOURS: u64 = 24;
+
+// =============================================================================
+// Templates
+// =============================================================================
+
+crate::define_partial!(SharePartial, "partials/share.html", {
+    path: String,
+    title: String,
+    lifetimes: Vec<Lifetime>
+});
+
+crate::gallery_example!(SharePartial,
+    title: "Share",
+    description: "Mints a signed link to a page that stops working after the chosen time; the answer (Share Link) swaps in place of the form.",
+    snippet: r##"<div hx-get="/partials/share?path=/components&title=Components" hx-trigger="load" hx-swap="outerHTML"></div>"##,
+    example: SharePartial {
+        path: "/components".into(),
+        title: "Components".into(),
+        lifetimes: lifetimes(DEFAULT_HOURS),
+    },
+);
+
+crate::define_partial!(ShareLinkPartial, "partials/share_link.html", {
+    title: String,
+    url: String,
+    copy: CopyButton,
+    // `YYYY-MM-DD HH:MM UTC`
+    expires: String,
+    // The empty form again, for another link
+    form_url: String
+});
+
+crate::gallery_example!(ShareLinkPartial,
+    title: "Share Link",
+    description: "A minted share link with a copy button (utils::clipboard) and, where the browser has one, its share sheet.",
+    snippet: r##"<form hx-post="/share" hx-target="closest .share" hx-swap="outerHTML">…</form>"##,
+    example: ShareLinkPartial {
+        title: "Components".into(),
+        url: "http://localhost:8000/s/L2NvbXBvbmVudHM.1767225600.example".into(),
+        copy: CopyButton::text("http://localhost:8000/s/L2NvbXBvbmVudHM.1767225600.example")
+            .aria_label("Copy the share link"),
+        expires: "2026-01-01 00:00 UTC".into(),
+        form_url: "/partials/share?path=%2Fcomponents&title=Components".into(),
+    },
+);
+
+/// A choice in the form's lifetime select
+#[derive(Debug, Clone, Serialize)]
+pub struct Lifetime {
+    pub hours: u64,
+    pub label: &'static str,
+    pub selected: bool,
+}
+
+fn lifetimes(selected: u64) -> Vec<Lifetime> {
+    LIFETIMES
+        .iter()
+        .map(|&(hours, label)| Lifetime {
+            hours,
+            label,
+            selected: hours == selected,
+        })
+        .collect()
+}
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShareQuery {
+    pub path: String,
+    #[serde(default)]
+    pub title: String,
+}
+
+/// The share form for `?path=` (a local path, e.g. `/components`)
+pub async fn share_form(Query(query): Query<ShareQuery>) -> AppResult<SharePartial> {
+    let path = local_path(&query.path)?;
+    Ok(SharePartial {
+        title: title_or_path(&query.title, &path),
+        path,
+        lifetimes: lifetimes(DEFAULT_HOURS),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct ShareForm {
+    pub path: String,
+    #[serde(default)]
+    pub title: String,
+    pub hours: u64,
+}
+
+/// Mint a link to `path` lasting one of the offered [`LIFETIMES`]
+pub async fn create_share(
+    State(state): State<Arc<AppState>>,
+    tenant: Option<Extension<Tenant>>,
+    Form(form): Form<ShareForm>,
+) -> AppResult<ShareLinkPartial> {
+    let path = local_path(&form.path)?;
+    if !LIFETIMES.iter().any(|&(hours, _)| hours == form.hours) {
+        return Err(AppError::validation("Pick one of the offered lifetimes"));
+    }
+    let ttl = Duration::from_secs(form.hours * 3600);
+    let token = state.services.tokens.sign(TOKEN_PURPOSE, &path, Some(ttl));
+    let prefix = tenant
+        .map(|Extension(tenant)| tenant.prefix)
+        .unwrap_or_default();
+    let url = format!(
+        "{}{prefix}/s/{token}",
+        state.config.site.base_url.trim_end_matches('/')
+    );
+    let expires = chrono::Utc::now() + chrono::Duration::hours(form.hours as i64);
+    let title = title_or_path(&form.title, &path);
+    let form_url = format!(
+        "/partials/share?{}",
+        serde_urlencoded::to_string(ShareQuery {
+            path,
+            title: title.clone(),
+        })
+        .unwrap_or_default()
+    );
+    Ok(ShareLinkPartial {
+        copy: CopyButton::text(&url).aria_label("Copy the share link"),
+        url,
+        title,
+        expires: expires.format("%Y-%m-%d %H:%M UTC").to_string(),
+        form_url,
+    })
+}
+
+/// Follow a share link to its page; expired or tampered links are a 404
+pub async fn open_share(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> AppResult<Redirect> {
+    let path = state
+        .services
+        .tokens
+        .verify(TOKEN_PURPOSE, &token)
+        .ok_or_else(|| AppError::not_found("This share link is invalid or has expired"))?;
+    Ok(Redirect::to(&path))
+}
+
+/// `path` if it's a path on this site: `/x`, never `//host` or `/\host`,
+/// which browsers treat as another origin
+fn local_path(path: &str) -> AppResult<String> {
+    let path = path.trim();
+    let local = path.starts_with('/')
+        && !path.starts_with("//")
+        && !path.starts_with("/\\")
+        && !path.chars().any(char::is_control);
+    if local {
+        Ok(path.to_string())
+    } else {
+        Err(AppError::validation(
+            "Only pages on this site can be shared",
+        ))
+    }
+}
+
+fn title_or_path(title: &str, path: &str) -> String {
+    match title.trim() {
+        "" => path.to_string(),
+        title => title.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_path_refuses_other_origins() {
+        assert_eq!(local_path(" /components ").unwrap(), "/components");
+        assert_eq!(local_path("/blog?tag=rust").unwrap(), "/blog?tag=rust");
+        for path in [
+            "",
+            "components",
+            "//evil.example",
+            "/\\evil.example",
+            "https://evil.example",
+            "/a\nb",
+        ] {
+            assert!(local_path(path).is_err(), "{path:?}");
+        }
+    }
+}
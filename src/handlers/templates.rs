@@ -4,98 +4,203 @@
 //! - Debug: minijinja hot-reloads templates from disk  
 //! - Release: askama compiles templates into the binary
 
-use axum::{extract::State, http::header, response::IntoResponse};
-use std::sync::Arc;
+use axum::extract::Query;
 
-use crate::models::AppState;
-use crate::services::session::SESSION_COOKIE;
+use crate::handlers::gallery::{self, GalleryItem};
+use crate::handlers::partials::ItemListQuery;
+use crate::render::PageMeta;
+use crate::services::items;
+use crate::utils::clipboard::CopyButton;
+use crate::utils::forms::Field;
+use crate::utils::loading::{Indicator, Skeleton};
+use crate::utils::tabs::Tabs;
 
-// Define pages using the macro — one line per page instead of ~20!
-crate::define_page!(HomePage, "pages/home.html", { current_page: &'static str, csrf_token: String });
-crate::define_page!(AboutPage, "pages/about.html", { current_page: &'static str, csrf_token: String });
-crate::define_page!(DemoPage, "pages/demo.html", { current_page: &'static str, csrf_token: String });
-crate::define_page!(ComponentsPage, "pages/components.html", { current_page: &'static str, csrf_token: String });
-crate::define_page!(SecurityPage, "pages/security.html", { current_page: &'static str, csrf_token: String });
+crate::sitemap_route!("/");
+crate::sitemap_route!("/about");
+crate::sitemap_route!("/demo");
+crate::sitemap_route!("/components");
+crate::sitemap_route!("/security");
 
-/// Extract session ID from request cookies
-fn get_session_id(headers: &axum::http::HeaderMap) -> Option<String> {
-    headers
-        .get(header::COOKIE)
-        .and_then(|v| v.to_str().ok())
-        .and_then(|cookies| {
-            cookies.split(';').find_map(|c| {
-                let c = c.trim();
-                c.strip_prefix(&format!("{}=", SESSION_COOKIE))
-                    .map(|v| v.to_string())
-            })
-        })
-}
+crate::shortcut!("g h", "/", "Go home");
+crate::shortcut!("g a", "/about", "Go to About");
+crate::shortcut!("g d", "/demo", "Go to the demo");
+crate::shortcut!("g g", "/components", "Go to the component gallery");
+crate::shortcut!("g s", "/security", "Go to Security");
+
+// Define pages using the macro — one declaration per page. CSRF token, flash
+// messages, nav (including the active link) and version come from `globals`,
+// injected per request.
+crate::define_page!(HomePage, "pages/home.html", {}, PageMeta::new("Home").description(
+    "Production-ready Axum + HTMX stack with strict CSP, SRI, CSRF protection and zero external dependencies."
+));
+crate::define_page!(
+    AboutPage,
+    "pages/about.html",
+    {},
+    PageMeta::new("About")
+        .description("Hardened full-stack Rust web application. No API. No external dependencies.")
+);
+crate::define_page!(
+    DemoPage,
+    "pages/demo.html",
+    {
+        tag: String,
+        // Combobox jumping to a tag filter
+        tag_search: Field,
+        // Lazily loaded panels, see `demo_tabs`
+        tabs: Tabs,
+        // The Loading Indicators card
+        loading_indicator: Indicator,
+        loading_skeleton: Skeleton
+    },
+    PageMeta::new("Demo")
+        .description("Interactive HTMX examples — all server-rendered, no JS frameworks.")
+);
+crate::define_page!(
+    ComponentsPage,
+    "pages/components.html",
+    {
+        gallery: Vec<GalleryItem>,
+        // One of each `utils::forms` control
+        fields: Vec<Field>,
+        // `utils::clipboard` examples: one reads the input beside it
+        copy_input: CopyButton,
+        copy_text: CopyButton,
+        // Partials with no `gallery_example!`
+        missing: Vec<&'static str>,
+        has_missing: bool
+    },
+    PageMeta::new("Components")
+        .description("Living style guide for every component in the design system.")
+);
+crate::define_page!(
+    SecurityPage,
+    "pages/security.html",
+    {},
+    PageMeta::new("Security")
+        .description("How this application is hardened, from code to deployment.")
+);
+crate::define_page!(
+    MaintenancePage,
+    "pages/maintenance.html",
+    {
+        // `[maintenance] message`; empty shows a generic one
+        message: String
+    },
+    PageMeta::new("Down for maintenance").layout("layouts/minimal.html")
+);
+crate::define_page!(
+    ErrorPage,
+    "pages/error.html",
+    {
+        // `X-Request-Id` of the failed request, to quote in a bug report
+        request_id: String
+    },
+    PageMeta::new("Something went wrong")
+);
 
 // =============================================================================
 // Page Handlers — thin wrappers that delegate to templates
 // =============================================================================
 
-pub async fn home_page(
-    State(state): State<Arc<AppState>>,
-    headers: axum::http::HeaderMap,
-) -> impl IntoResponse {
-    let sid = get_session_id(&headers).unwrap_or_default();
-    let csrf_token = state.services.csrf.generate_token(&sid);
-    HomePage {
-        current_page: "home",
-        csrf_token,
-    }
-    .render_response()
+pub async fn home_page() -> HomePage {
+    HomePage {}
 }
 
-pub async fn about_page(
-    State(state): State<Arc<AppState>>,
-    headers: axum::http::HeaderMap,
-) -> impl IntoResponse {
-    let sid = get_session_id(&headers).unwrap_or_default();
-    let csrf_token = state.services.csrf.generate_token(&sid);
-    AboutPage {
-        current_page: "about",
-        csrf_token,
-    }
-    .render_response()
+pub async fn about_page() -> AboutPage {
+    AboutPage {}
 }
 
-pub async fn demo_page(
-    State(state): State<Arc<AppState>>,
-    headers: axum::http::HeaderMap,
-) -> impl IntoResponse {
-    let sid = get_session_id(&headers).unwrap_or_default();
-    let csrf_token = state.services.csrf.generate_token(&sid);
+/// `?tag=` preselects the tag filter, so filtered views can be bookmarked
+pub async fn demo_page(Query(query): Query<ItemListQuery>) -> DemoPage {
+    let tag = query
+        .tag
+        .as_deref()
+        .and_then(items::normalize_tag)
+        .unwrap_or_default();
     DemoPage {
-        current_page: "demo",
-        csrf_token,
+        tag_search: tag_search(&tag),
+        tag,
+        tabs: demo_tabs(),
+        loading_indicator: demo_indicator(),
+        loading_skeleton: Skeleton::lines(2).label("Loading the greeting…"),
     }
-    .render_response()
 }
 
-pub async fn components_page(
-    State(state): State<Arc<AppState>>,
-    headers: axum::http::HeaderMap,
-) -> impl IntoResponse {
-    let sid = get_session_id(&headers).unwrap_or_default();
-    let csrf_token = state.services.csrf.generate_token(&sid);
+/// The spinner of the demo page's Loading Indicators card
+pub fn demo_indicator() -> Indicator {
+    Indicator::new("loading-spinner", "Loading…")
+}
+
+/// The demo page's tabs, each panel from `/partials/demo-tab/<slug>`
+pub fn demo_tabs() -> Tabs {
+    Tabs::new("demo-tabs", "Workspace")
+        .tab("items", "Items", "/partials/demo-tab/items")
+        .tab("tags", "Tags", "/partials/demo-tab/tags")
+        .tab("server", "Server", "/partials/demo-tab/server")
+}
+
+/// The demo page's autocomplete, suggesting tags in use
+pub fn tag_search(tag: &str) -> Field {
+    Field::combobox("tag", "Tag", "tags")
+        .id("tag-search")
+        .value(tag)
+        .hint("Arrow keys move through the suggestions, Enter picks one.")
+        .attr("placeholder", "Start typing a tag…")
+}
+
+/// The style guide, led by the gallery of registered partials
+pub async fn components_page() -> ComponentsPage {
+    let missing = gallery::missing();
     ComponentsPage {
-        current_page: "components",
-        csrf_token,
+        gallery: gallery::items(),
+        fields: field_examples(),
+        copy_input: CopyButton::target("#copy-example").aria_label("Copy the command"),
+        copy_text: CopyButton::text("Hello from the clipboard").label("Copy a greeting"),
+        has_missing: !missing.is_empty(),
+        missing,
     }
-    .render_response()
 }
 
-pub async fn security_page(
-    State(state): State<Arc<AppState>>,
-    headers: axum::http::HeaderMap,
-) -> impl IntoResponse {
-    let sid = get_session_id(&headers).unwrap_or_default();
-    let csrf_token = state.services.csrf.generate_token(&sid);
-    SecurityPage {
-        current_page: "security",
-        csrf_token,
-    }
-    .render_response()
+pub async fn security_page() -> SecurityPage {
+    SecurityPage {}
+}
+
+/// One of each form control for the components page, ids prefixed so they
+/// can't clash with a real form
+pub fn field_examples() -> Vec<Field> {
+    vec![
+        Field::text("title", "Text")
+            .id("example-title")
+            .hint("Hints are linked with aria-describedby.")
+            .attr("placeholder", "Enter text..."),
+        Field::input("email", "email", "Email")
+            .id("example-email")
+            .value("ada@")
+            .error("That email address doesn't look right")
+            .required(),
+        Field::select(
+            "size",
+            "Select",
+            [("s", "Small"), ("m", "Medium"), ("l", "Large")],
+        )
+        .id("example-size")
+        .value("m"),
+        Field::file("attachment", "File")
+            .id("example-attachment")
+            .attr("accept", "image/*"),
+        Field::radios("plan", "Radio group", [("free", "Free"), ("pro", "Pro")])
+            .id("example-plan")
+            .value("free"),
+        Field::checkbox("agree", "Checkbox", true).id("example-agree"),
+        Field::combobox("item", "Combobox", "items")
+            .id("example-item")
+            .attr("placeholder", "Search items…"),
+        Field::date_picker("starts_on", "Date picker")
+            .id("example-starts-on")
+            .hint("Month and day names follow Accept-Language."),
+        Field::textarea("notes", "Textarea")
+            .id("example-notes")
+            .attr("rows", 3),
+    ]
 }
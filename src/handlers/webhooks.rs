@@ -0,0 +1,244 @@
+//! Webhook Handlers — signed deliveries from third-party services
+//!
+//! `POST /webhooks/:provider` accepts a delivery once its HMAC-SHA256
+//! signature checks out against `[webhooks.secrets] <provider>`. Providers
+//! without a secret don't exist (404). The route is exempt from CSRF
+//! ([`csrf_exempt!`](crate::csrf_exempt)); the signature over the raw body
+//! stands in for it.
+//!
+//! Each delivery is stored once in `webhook_events` (redeliveries are
+//! acknowledged and dropped), then handed to the handlers registered for its
+//! provider and event type on the job queue:
+//!
+//! ```ignore
+//! crate::webhook_handler!("github", "push", on_push);
+//!
+//! fn on_push(state: &AppState, event: &WebhookEvent) -> Result<(), String> { .. }
+//! ```
+//!
+//! An `Err` marks the event `failed` and the job is retried, running every
+//! matching handler again — keep handlers idempotent.
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+
+use crate::models::AppState;
+use crate::services::webhook_events::{NewWebhookEvent, WebhookEvent, WebhookStatus};
+
+crate::csrf_exempt!("/webhooks/");
+
+/// How a provider signs and labels its deliveries
+pub struct SignatureScheme {
+    /// Header carrying the hex HMAC-SHA256 of the raw body
+    pub signature_header: &'static str,
+    /// Text before the hex digest, e.g. `sha256=`
+    pub signature_prefix: &'static str,
+    /// Header with the delivery id, stable across redeliveries
+    pub id_header: &'static str,
+    /// Header naming the event type
+    pub event_header: &'static str,
+}
+
+const GITHUB: SignatureScheme = SignatureScheme {
+    signature_header: "x-hub-signature-256",
+    signature_prefix: "sha256=",
+    id_header: "x-github-delivery",
+    event_header: "x-github-event",
+};
+
+/// For any provider without its own scheme — and for your own services
+const GENERIC: SignatureScheme = SignatureScheme {
+    signature_header: "x-webhook-signature",
+    signature_prefix: "sha256=",
+    id_header: "x-webhook-id",
+    event_header: "x-webhook-event",
+};
+
+fn scheme(provider: &str) -> &'static SignatureScheme {
+    match provider {
+        "github" => &GITHUB,
+        _ => &GENERIC,
+    }
+}
+
+/// A `webhook_handler!` registration, collected at link time
+pub struct WebhookHandler {
+    pub provider: &'static str,
+    /// Event type to handle, or `*` for all of them
+    pub event: &'static str,
+    pub handler: fn(&AppState, &WebhookEvent) -> Result<(), String>,
+}
+
+inventory::collect!(WebhookHandler);
+
+/// Run `$handler` for `$provider` deliveries of type `$event` (`"*"` = any)
+#[macro_export]
+macro_rules! webhook_handler {
+    ($provider:literal, $event:literal, $handler:path) => {
+        $crate::render::inventory::submit! {
+            $crate::handlers::webhooks::WebhookHandler {
+                provider: $provider,
+                event: $event,
+                handler: $handler,
+            }
+        }
+    };
+}
+
+fn handlers_for(event: &WebhookEvent) -> Vec<&'static WebhookHandler> {
+    inventory::iter::<WebhookHandler>
+        .into_iter()
+        .filter(|h| h.provider == event.provider && (h.event == "*" || h.event == event.event_type))
+        .collect()
+}
+
+pub async fn receive(
+    State(state): State<Arc<AppState>>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(secret) = state
+        .config
+        .webhooks
+        .secrets
+        .get(&provider)
+        .filter(|s| !s.is_empty())
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let scheme = scheme(&provider);
+    if !verify_signature(secret.as_bytes(), scheme, &headers, &body) {
+        tracing::warn!(provider = %provider, "webhook signature mismatch");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let Some(event_id) = header(&headers, scheme.id_header) else {
+        return (StatusCode::BAD_REQUEST, "missing delivery id").into_response();
+    };
+    let Ok(payload) = String::from_utf8(body.to_vec()) else {
+        return (StatusCode::BAD_REQUEST, "body is not UTF-8").into_response();
+    };
+    let delivery = NewWebhookEvent {
+        event_type: header(&headers, scheme.event_header).unwrap_or_default(),
+        provider,
+        event_id,
+        payload,
+    };
+
+    match state.services.webhook_events.record(delivery) {
+        Some(event) => {
+            dispatch(state, event);
+            StatusCode::ACCEPTED.into_response()
+        }
+        // Already on file — a retry of something we acknowledged before
+        None => StatusCode::OK.into_response(),
+    }
+}
+
+/// Queue the registered handlers for `event`
+fn dispatch(state: Arc<AppState>, event: WebhookEvent) {
+    let handlers = handlers_for(&event);
+    if handlers.is_empty() {
+        tracing::info!(provider = %event.provider, event_type = %event.event_type, "unhandled webhook");
+        state
+            .services
+            .webhook_events
+            .set_status(event.id, WebhookStatus::Processed);
+        return;
+    }
+
+    let jobs = state.services.jobs.clone();
+    jobs.enqueue(
+        "webhook",
+        Box::new(move || {
+            let result = handlers
+                .iter()
+                .try_for_each(|h| (h.handler)(&state, &event));
+            let status = match result {
+                Ok(()) => WebhookStatus::Processed,
+                Err(_) => WebhookStatus::Failed,
+            };
+            state.services.webhook_events.set_status(event.id, status);
+            result
+        }),
+    );
+}
+
+fn header(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Constant-time check of the scheme's signature header against `body`
+fn verify_signature(
+    secret: &[u8],
+    scheme: &SignatureScheme,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> bool {
+    let Some(signature) = header(headers, scheme.signature_header) else {
+        return false;
+    };
+    let Some(digest) = signature
+        .strip_prefix(scheme.signature_prefix)
+        .and_then(|hex_digest| hex::decode(hex_digest).ok())
+    else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(body);
+    mac.verify_slice(&digest).is_ok()
+}
+
+// =============================================================================
+// Built-in handlers
+// =============================================================================
+
+crate::webhook_handler!("github", "ping", github_ping);
+
+/// GitHub sends `ping` when a webhook is created — proof the secret matches
+fn github_ping(_state: &AppState, event: &WebhookEvent) -> Result<(), String> {
+    tracing::info!(delivery = %event.event_id, "GitHub webhook connected");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn signed(secret: &[u8], body: &[u8]) -> HeaderMap {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-hub-signature-256",
+            HeaderValue::from_str(&signature).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let body = br#"{"zen":"Keep it logically awesome."}"#;
+        let headers = signed(b"secret", body);
+
+        assert!(verify_signature(b"secret", &GITHUB, &headers, body));
+        assert!(!verify_signature(b"other", &GITHUB, &headers, body));
+        assert!(!verify_signature(b"secret", &GITHUB, &headers, b"{}"));
+        // Right digest, wrong header for the scheme
+        assert!(!verify_signature(b"secret", &GENERIC, &headers, body));
+    }
+}
@@ -0,0 +1,171 @@
+//! Well-Known Handlers — documents under `/.well-known/` (RFC 8615)
+//!
+//! Every document is registered with [`well_known!`](crate::well_known) next
+//! to the function that builds it, and [`router`] serves them all from a
+//! single `/.well-known/:name` route:
+//!
+//! ```ignore
+//! crate::well_known!("nodeinfo", nodeinfo);
+//!
+//! fn nodeinfo(state: &AppState) -> Response { .. }
+//! ```
+//!
+//! Unregistered names are a plain 404.
+
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+    Router,
+};
+use std::sync::Arc;
+
+use crate::config::SecurityConfig;
+use crate::models::AppState;
+
+/// How long clients and shared caches may reuse `security.txt`
+const SECURITY_TXT_CACHE_CONTROL: &str = "public, max-age=86400";
+/// How far ahead `security.txt` sets `Expires:` (RFC 9116 wants < 1 year)
+const SECURITY_TXT_LIFETIME_DAYS: i64 = 180;
+
+/// A `well_known!` registration, collected at link time
+pub struct WellKnownDoc {
+    /// Path segment after `/.well-known/`, e.g. `security.txt`
+    pub name: &'static str,
+    pub handler: fn(&AppState) -> Response,
+}
+
+inventory::collect!(WellKnownDoc);
+
+/// Register `$handler` as `/.well-known/$name`
+#[macro_export]
+macro_rules! well_known {
+    ($name:literal, $handler:path) => {
+        $crate::render::inventory::submit! {
+            $crate::handlers::well_known::WellKnownDoc {
+                name: $name,
+                handler: $handler,
+            }
+        }
+    };
+}
+
+/// Every registered document name, sorted
+pub fn registered() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = inventory::iter::<WellKnownDoc>
+        .into_iter()
+        .map(|doc| doc.name)
+        .collect();
+    names.sort_unstable();
+    names
+}
+
+/// `/.well-known/:name`, dispatching to the registered documents
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new().route("/.well-known/:name", get(serve))
+}
+
+async fn serve(State(state): State<Arc<AppState>>, Path(name): Path<String>) -> Response {
+    match inventory::iter::<WellKnownDoc>
+        .into_iter()
+        .find(|doc| doc.name == name)
+    {
+        Some(doc) => (doc.handler)(&state),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+// =============================================================================
+// security.txt (RFC 9116)
+// =============================================================================
+
+crate::well_known!("security.txt", security_txt);
+
+fn render_security_txt(security: &SecurityConfig, base_url: &str, expires: &str) -> Option<String> {
+    if security.contact.is_empty() {
+        return None;
+    }
+    let base_url = base_url.trim_end_matches('/');
+    let mut txt = format!("Contact: {}\n", security.contact);
+    txt.push_str(&format!("Expires: {expires}\n"));
+    if !security.policy.is_empty() {
+        txt.push_str(&format!("Policy: {}\n", security.policy));
+    }
+    if !security.preferred_languages.is_empty() {
+        txt.push_str(&format!(
+            "Preferred-Languages: {}\n",
+            security.preferred_languages
+        ));
+    }
+    txt.push_str(&format!("Canonical: {base_url}/.well-known/security.txt\n"));
+    Some(txt)
+}
+
+/// Disclosure contact from `[security]`; 404 until one is configured, since
+/// `Contact:` is the one required field
+fn security_txt(state: &AppState) -> Response {
+    let expires = (chrono::Utc::now() + chrono::Duration::days(SECURITY_TXT_LIFETIME_DAYS))
+        .format("%Y-%m-%dT00:00:00Z")
+        .to_string();
+    let config = &state.config;
+    match render_security_txt(&config.security, &config.site.base_url, &expires) {
+        Some(txt) => (
+            [
+                (
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("text/plain; charset=utf-8"),
+                ),
+                (
+                    header::CACHE_CONTROL,
+                    HeaderValue::from_static(SECURITY_TXT_CACHE_CONTROL),
+                ),
+            ],
+            txt,
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+// =============================================================================
+// change-password (W3C "A Well-Known URL for Changing Passwords")
+// =============================================================================
+
+crate::well_known!("change-password", change_password);
+
+/// Redirect to `[security] change_password_url`; 404 while it's unset
+fn change_password(state: &AppState) -> Response {
+    match state.config.security.change_password_url.as_str() {
+        "" => StatusCode::NOT_FOUND.into_response(),
+        url => Redirect::to(url).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPIRES: &str = "2030-01-01T00:00:00Z";
+
+    #[test]
+    fn test_security_txt_fields() {
+        let mut security = SecurityConfig::default();
+        assert!(render_security_txt(&security, "https://example.com", EXPIRES).is_none());
+
+        security.contact = "mailto:security@example.com".into();
+        let txt = render_security_txt(&security, "https://example.com/", EXPIRES).unwrap();
+        assert_eq!(
+            txt,
+            "Contact: mailto:security@example.com\n\
+             Expires: 2030-01-01T00:00:00Z\n\
+             Preferred-Languages: en\n\
+             Canonical: https://example.com/.well-known/security.txt\n"
+        );
+    }
+
+    #[test]
+    fn test_registered_documents() {
+        assert_eq!(registered(), ["change-password", "security.txt"]);
+    }
+}
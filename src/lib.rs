@@ -15,15 +15,24 @@
 //! 3. REST API endpoints return JSON for programmatic access
 //! 4. Both page templates and partials share the same design system
 
+#[cfg(feature = "acme")]
+pub mod acme;
 pub mod config;
 pub mod db;
+pub mod db_conn;
 pub mod error;
+pub mod globals;
 pub mod handlers;
 pub mod middleware;
 pub mod models;
+pub mod query_cache;
 #[macro_use]
 pub mod render;
+pub mod routes;
+pub mod schema;
+pub mod server;
 pub mod services;
+pub mod systemd;
 pub mod utils;
 
 pub use config::AppConfig;
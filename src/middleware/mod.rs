@@ -1,33 +1,128 @@
 //! Hardened HTTP Middleware
 //!
 //! Security-first middleware stack:
-//! - Strict security headers (CSP with SRI, no external resources)
-//! - CSRF validation on all state-changing requests
+//! - Strict security headers (CSP with SRI, no external resources), with
+//!   cross-origin access per kind of static file (see [`static_headers`])
+//! - CSRF validation on all state-changing requests (opt out per path with
+//!   `csrf_exempt!`)
 //! - Session management via HttpOnly cookies
-//! - Request logging with timing (no sensitive data leaked)
-//! - Server header stripping
+//! - Request logging with timing and request IDs (no sensitive data leaked)
+//! - A request-scoped database connection for [`DbConn`](crate::db_conn::DbConn),
+//!   checked out innermost, after every check that might refuse the request
+//!   (see [`db_conn`])
+//! - Panics answered with the 500 page instead of a dropped connection, and
+//!   reported with internal errors (see [`report_errors`])
+//! - Admin-only route guard, and API tokens for scripts (see [`api_token`])
+//! - A per-session limit on polled partials, and polls answered with just
+//!   what changed (see [`diff_fragments`])
+//! - Maintenance and read-only modes
+//! - Unknown hosts turned away when `[server] allowed_hosts` is set (wraps
+//!   the whole router, see [`allowed_hosts`])
+//! - URL normalization: `308` to the canonical spelling (wraps the whole
+//!   router, see [`normalize_url`])
+//! - Tenant resolution (wraps the whole router, see [`resolve_tenant`])
+//! - Redirect rules managed by admins (wraps the whole router, see
+//!   [`redirects`])
+//! - Server header stripping (admins see the build instead)
+//! - Plain text instead of markup for clients that don't take HTML
+//! - `HEAD` answered without a body, whatever made the response (wraps the
+//!   whole router, see [`head_requests`])
+//! - `OPTIONS` listed in every `Allow` header (wraps the whole router, see
+//!   [`allow_options`])
 
 use axum::{
-    extract::Request,
-    http::{header, Method, StatusCode},
-    middleware::Next,
-    response::{Html, IntoResponse, Response},
+    body::{Body, HttpBody},
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderName, HeaderValue, Method, StatusCode, Uri},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use tower::{Layer, Service, ServiceBuilder};
+use tower_http::{
+    catch_panic::CatchPanicLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
 };
 
+use crate::db_conn::{ConnSlot, MANY_STATEMENTS};
+use crate::error::{AppError, InternalError};
+use crate::globals::{self, FlashSource, TemplateGlobals, USER_NAME_KEY};
+use crate::handlers::error_fragment::{ErrorFragment, ErrorText, ERROR_REGION};
+use crate::handlers::templates::{ErrorPage, MaintenancePage};
 use crate::models::AppState;
-use crate::services::session::SESSION_COOKIE;
+use crate::routes::HostRouter;
+use crate::services::admin::ADMIN_KEY;
+use crate::services::api_tokens::{self, ApiToken, Scope};
+use crate::services::comments::owner_key;
+use crate::services::errors::{ErrorContext, ErrorEvent, ErrorKind};
+use crate::services::fragment_diffs::SentRender;
+use crate::services::idempotency::{Claim, StoredResponse};
+use crate::services::request_log::RequestRecord;
+use crate::services::session::{
+    self, RotatedSession, Session, SessionId, LAZY_SESSION, SESSION_TTL,
+};
+use crate::services::tenants::{self, Tenant, TenantId};
+use crate::utils::build_info::BuildInfo;
+use crate::utils::cookies::{Cookies, SetCookie};
+use crate::utils::forms;
+use crate::utils::hosts::{self, HostInfo, HostKind};
+use crate::utils::html::HtmlFragment;
+use crate::utils::html_diff::{self, Shape};
+use crate::utils::htmx;
+use crate::utils::urls::{self, Normalized};
+use std::any::Any;
 use std::sync::Arc;
 
-/// SRI hash for the vendored htmx.min.js — update if the file changes.
-/// Generate with: openssl dgst -sha384 -binary static/js/htmx.min.js | openssl base64 -A
+/// Request header carrying the request ID, set (unless the client sent one)
+/// by `SetRequestIdLayer` and echoed on the response
+pub const REQUEST_ID: &str = "x-request-id";
+
+/// SRI hash for the vendored htmx.min.js — `app assets vendor` updates it
+/// with the file (see `static/js/vendor.toml`). By hand:
+/// openssl dgst -sha384 -binary static/js/htmx.min.js | openssl base64 -A
 const HTMX_SRI_HASH: &str =
     "sha384-wS5l5IKJBvK6sPTKa2WZ1js3d947pvWXbPJ1OmWfEuxLgeHcEbjUUA5i9V5ZkpCw";
+/// SRI hash for the vendored head-support extension, generated the same way
+const HEAD_SUPPORT_SRI_HASH: &str =
+    "sha384-H7VTYLquWyzS33rKaAUdS7H4uO+DqtZg82bedNVIH8q+NWLbarHbESzCfX7/s8EA";
+/// SRI hash for the vendored idiomorph extension, generated the same way
+const IDIOMORPH_SRI_HASH: &str =
+    "sha384-qd1/aeZKxnX2Mi79KCdLLNxEr4A6BK5Tb67tzIoG3Mf9T5lh+VeNYhKJ7CRbWYuK";
+/// SRI hash for the vendored Sortable library, generated the same way
+const SORTABLE_SRI_HASH: &str =
+    "sha384-9cKCS1LpqD/M7VNsUZUbOcE4Uk/n0npgSRMPct2HcpCHbSNOYjEaihsWsjpYswxt";
+/// SRI hash for the vendored Mousetrap library, generated the same way
+const MOUSETRAP_SRI_HASH: &str =
+    "sha384-E9xAWWiFITmRoBasE1YKi5dqR4s46NBQ5qeiY5fL75bY3LBn9AE26yZ5zT7Ac+8W";
+/// SRI hash for the vendored ClipboardJS library, generated the same way
+const CLIPBOARD_SRI_HASH: &str =
+    "sha384-8CYhPwYlLELodlcQV713V9ZikA3DlCVaXFDpjHfP8Z36gpddf/Vrt47XmKDsCttu";
 
 // ─── Security Headers ───────────────────────────────────────────────────────
 
+/// Marks a response to a signed-in admin, so [`security_headers`] names the
+/// build in the `Server` header. Set by [`session_middleware`].
+#[derive(Debug, Clone, Copy)]
+pub struct AdminResponse;
+
+/// Marks a response other sites may frame: [`security_headers`] names these
+/// origins in `frame-ancestors` and sends no `X-Frame-Options`. Set by
+/// [`crate::handlers::embed`].
+#[derive(Debug, Clone)]
+pub struct EmbedResponse {
+    /// Space-separated origins, from `[embeds] frame_ancestors`
+    pub frame_ancestors: String,
+}
+
 /// Hardened security headers — strict CSP, no external resources, no leaks
 pub async fn security_headers(request: Request, next: Next) -> Response {
     let mut response = next.run(request).await;
+    let frame_ancestors = response
+        .extensions()
+        .get::<EmbedResponse>()
+        .map(|embed| embed.frame_ancestors.clone());
     let h = response.headers_mut();
 
     // Content Security Policy — only allow self + SRI-hashed JS files
@@ -36,15 +131,18 @@ pub async fn security_headers(request: Request, next: Next) -> Response {
         header::HeaderName::from_static("content-security-policy"),
         format!(
             "default-src 'self'; \
-             script-src 'self' '{HTMX_SRI_HASH}'; \
+             script-src 'self' '{HTMX_SRI_HASH}' '{HEAD_SUPPORT_SRI_HASH}' '{IDIOMORPH_SRI_HASH}' '{SORTABLE_SRI_HASH}' '{MOUSETRAP_SRI_HASH}' '{CLIPBOARD_SRI_HASH}'; \
              style-src 'self' 'unsafe-inline'; \
              img-src 'self' data:; \
              font-src 'self'; \
              connect-src 'self'; \
-             frame-ancestors 'none'; \
+             worker-src 'self'; \
+             manifest-src 'self'; \
+             frame-ancestors {}; \
              base-uri 'self'; \
              form-action 'self'; \
-             object-src 'none'"
+             object-src 'none'",
+            frame_ancestors.as_deref().unwrap_or("'none'")
         )
         .parse()
         .unwrap(),
@@ -56,11 +154,14 @@ pub async fn security_headers(request: Request, next: Next) -> Response {
         header::HeaderValue::from_static("nosniff"),
     );
 
-    // Prevent framing (clickjacking)
-    h.insert(
-        header::HeaderName::from_static("x-frame-options"),
-        header::HeaderValue::from_static("DENY"),
-    );
+    // Prevent framing (clickjacking), except for embeds, which CSP's
+    // `frame-ancestors` limits instead
+    if frame_ancestors.is_none() {
+        h.insert(
+            header::HeaderName::from_static("x-frame-options"),
+            header::HeaderValue::from_static("DENY"),
+        );
+    }
 
     // XSS protection (legacy browsers)
     h.insert(
@@ -88,16 +189,33 @@ pub async fn security_headers(request: Request, next: Next) -> Response {
         ),
     );
 
-    // Strip server identification
+    // Strip server identification, except towards signed-in admins
     h.remove(header::SERVER);
-    h.insert(header::SERVER, header::HeaderValue::from_static(""));
+    // The build description comes from the build environment; one that
+    // isn't a valid header value is left out rather than panicking
+    let server = response
+        .extensions()
+        .get::<AdminResponse>()
+        .and_then(|_| {
+            HeaderValue::from_str(&format!(
+                "axum-htmx-app/{}",
+                BuildInfo::current().describe()
+            ))
+            .ok()
+        })
+        .unwrap_or(header::HeaderValue::from_static(""));
+    let h = response.headers_mut();
+    h.insert(header::SERVER, server);
 
-    // Prevent caching of sensitive pages
-    h.insert(
-        header::CACHE_CONTROL,
-        header::HeaderValue::from_static("no-store, no-cache, must-revalidate"),
-    );
-    h.insert(header::PRAGMA, header::HeaderValue::from_static("no-cache"));
+    // Prevent caching of sensitive pages, unless the handler opted in to
+    // caching itself (public feeds set their own Cache-Control)
+    if !h.contains_key(header::CACHE_CONTROL) {
+        h.insert(
+            header::CACHE_CONTROL,
+            header::HeaderValue::from_static("no-store, no-cache, must-revalidate"),
+        );
+        h.insert(header::PRAGMA, header::HeaderValue::from_static("no-cache"));
+    }
 
     // Cross-Origin policies
     h.insert(
@@ -108,24 +226,165 @@ pub async fn security_headers(request: Request, next: Next) -> Response {
         header::HeaderName::from_static("cross-origin-embedder-policy"),
         header::HeaderValue::from_static("require-corp"),
     );
-    h.insert(
-        header::HeaderName::from_static("cross-origin-resource-policy"),
-        header::HeaderValue::from_static("same-origin"),
-    );
+    // Unless the static files' policy said otherwise (see static_headers)
+    h.entry(header::HeaderName::from_static(
+        "cross-origin-resource-policy",
+    ))
+    .or_insert(header::HeaderValue::from_static("same-origin"));
+
+    response
+}
+
+// ─── Static File Headers ────────────────────────────────────────────────────
+
+/// Cross-origin headers for static files, per `[static_headers.<kind>]`
+/// matching the file's extension: its `Cross-Origin-Resource-Policy`, and
+/// `Access-Control-Allow-Origin` for an `Origin` it allows. Another origin
+/// embedding the site's fonts needs both — a font is fetched in CORS mode,
+/// and a page with COEP of its own refuses `same-origin` resources. Files of
+/// no configured kind stay `same-origin` ([`security_headers`]).
+pub async fn static_headers(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let extension = request
+        .uri()
+        .path()
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    let Some(policy) = state.config.static_headers.values().find(|policy| {
+        policy
+            .extensions
+            .iter()
+            .any(|candidate| candidate.eq_ignore_ascii_case(&extension))
+    }) else {
+        return next.run(request).await;
+    };
+    let origin = request.headers().get(header::ORIGIN).cloned();
+
+    let mut response = next.run(request).await;
+    let h = response.headers_mut();
+    if let Ok(corp) = HeaderValue::from_str(&policy.cross_origin_resource_policy) {
+        h.insert(
+            HeaderName::from_static("cross-origin-resource-policy"),
+            corp,
+        );
+    }
+    if policy.allow_origins.iter().any(|allowed| allowed == "*") {
+        h.insert(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        );
+    } else if !policy.allow_origins.is_empty() {
+        // The answer depends on who asks
+        h.append(header::VARY, HeaderValue::from_static("origin"));
+        let allowed = origin.filter(|origin| {
+            policy
+                .allow_origins
+                .iter()
+                .any(|allowed| origin.as_bytes() == allowed.as_bytes())
+        });
+        if let Some(origin) = allowed {
+            h.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        }
+    }
+    response
+}
 
+// ─── Content Negotiation ────────────────────────────────────────────────────
+
+/// Answers clients that don't take HTML ([`htmx::accepts_html`]), such as a
+/// probe sending `Accept: text/plain`, with a few lines of plain text
+/// instead of a page or fragment: the status, an error's title and message
+/// (problem-style), and the request ID. Status and other headers (a
+/// redirect's `Location`, `Retry-After`) are kept. Every HTML response gets
+/// `Vary: Accept`, so a cache keeps the two apart.
+pub async fn plain_text_for_non_html(request: Request, next: Next) -> Response {
+    let accepts_html = htmx::accepts_html(request.headers());
+    let request_id = request.headers().get(REQUEST_ID).cloned();
+    let mut response = next.run(request).await;
+    let html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|t| t.starts_with("text/html"));
+    if !html {
+        return response;
+    }
     response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept"));
+    if accepts_html {
+        return response;
+    }
+
+    let status = response.status();
+    let mut text = format!(
+        "{} {}\n",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default()
+    );
+    if let Some(error) = response.extensions().get::<ErrorText>() {
+        text.push_str(&format!("title: {}\n", error.title));
+        text.push_str(&format!("detail: {}\n", error.message));
+    }
+    if let Some(id) = request_id.as_ref().and_then(|v| v.to_str().ok()) {
+        text.push_str(&format!("request-id: {id}\n"));
+    }
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    Response::from_parts(parts, Body::from(text))
 }
 
 // ─── CSRF Protection ────────────────────────────────────────────────────────
 
+/// A `csrf_exempt!` registration, collected at link time
+pub struct CsrfExempt {
+    /// Path prefix, e.g. `/webhooks/`
+    pub prefix: &'static str,
+}
+
+inventory::collect!(CsrfExempt);
+
+/// Skip CSRF checks for every path starting with `$prefix`. Only for routes
+/// that authenticate requests some other way (signed webhooks) — browsers
+/// must never reach them with ambient credentials that matter.
+#[macro_export]
+macro_rules! csrf_exempt {
+    ($prefix:literal) => {
+        $crate::render::inventory::submit! {
+            $crate::middleware::CsrfExempt { prefix: $prefix }
+        }
+    };
+}
+
+fn is_csrf_exempt(path: &str) -> bool {
+    inventory::iter::<CsrfExempt>
+        .into_iter()
+        .any(|exempt| path.starts_with(exempt.prefix))
+}
+
+/// Largest urlencoded body searched for a `_csrf` field
+const CSRF_FORM_LIMIT: usize = 64 * 1024;
+
 /// CSRF middleware — validates token on all state-changing requests.
 /// The token must be sent as `X-CSRF-Token` header (HTMX sends this automatically
-/// via `hx-headers` attribute on the body tag).
+/// via `hx-headers` attribute on the body tag). Without the header, a
+/// urlencoded form's `_csrf` field counts instead (`components/_csrf.html`),
+/// so forms work without JavaScript.
 pub async fn csrf_protection(request: Request, next: Next) -> Response {
     let method = request.method().clone();
 
-    // Only validate on state-changing methods
-    if matches!(method, Method::GET | Method::HEAD | Method::OPTIONS) {
+    // Only validate on state-changing methods, outside exempt paths
+    if matches!(method, Method::GET | Method::HEAD | Method::OPTIONS)
+        || is_csrf_exempt(request.uri().path())
+    {
         return next.run(request).await;
     }
 
@@ -136,18 +395,17 @@ pub async fn csrf_protection(request: Request, next: Next) -> Response {
         .get("x-csrf-token")
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
+    let (request, csrf_header) = match csrf_header {
+        Some(token) => (request, Some(token)),
+        None => csrf_form_token(request).await,
+    };
 
-    let session_id = request
-        .headers()
-        .get(header::COOKIE)
-        .and_then(|v| v.to_str().ok())
-        .and_then(|cookies| {
-            cookies.split(';').find_map(|c| {
-                let c = c.trim();
-                c.strip_prefix(&format!("{}=", SESSION_COOKIE))
-                    .map(|v| v.to_string())
-            })
-        });
+    let session_id = state.as_ref().and_then(|state| {
+        let (name, _) = session_cookie(state, &request);
+        Cookies::from_headers(request.headers())
+            .get(name)
+            .map(str::to_string)
+    });
 
     match (state, csrf_header, session_id) {
         (Some(state), Some(token), Some(sid)) => {
@@ -165,53 +423,97 @@ pub async fn csrf_protection(request: Request, next: Next) -> Response {
     }
 }
 
+/// The `_csrf` field of a urlencoded body, handing back the request with its
+/// body restored. Bodies over [`CSRF_FORM_LIMIT`] aren't searched (and are
+/// dropped, since the request is refused anyway).
+async fn csrf_form_token(request: Request) -> (Request, Option<String>) {
+    let is_form = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/x-www-form-urlencoded"));
+    if !is_form {
+        return (request, None);
+    }
+    let (parts, body) = request.into_parts();
+    match axum::body::to_bytes(body, CSRF_FORM_LIMIT).await {
+        Ok(bytes) => {
+            let token = forms::csrf_token_in(&bytes);
+            (Request::from_parts(parts, Body::from(bytes)), token)
+        }
+        Err(_) => (Request::from_parts(parts, Body::empty()), None),
+    }
+}
+
 fn csrf_error(msg: &str) -> Response {
-    let body = format!(
-        r#"<div class="alert alert-danger" role="alert">
-    <div class="alert-title"><i class="bi bi-shield-x"></i> <strong>Forbidden</strong></div>
-    <div class="alert-body">{}</div>
-</div>"#,
-        msg
-    );
-    (StatusCode::FORBIDDEN, Html(body)).into_response()
+    ErrorFragment::new(StatusCode::FORBIDDEN, "Forbidden", msg)
+        .level("danger")
+        .icon("shield-x")
+        .into_response()
 }
 
 // ─── Session Middleware ─────────────────────────────────────────────────────
 
+/// The session cookie's name for this request, and whether it's `Secure`:
+/// per `[sessions] secure`, over HTTPS or not
+fn session_cookie(state: &AppState, request: &Request) -> (&'static str, bool) {
+    let https = request
+        .extensions()
+        .get::<crate::server::ListenerInfo>()
+        .is_some_and(|listener| listener.tls)
+        || crate::utils::cookies::is_https(
+            request.uri(),
+            request.headers(),
+            state.config.server.trust_proxy,
+        );
+    let secure = state.config.sessions.cookie_secure(https);
+    (state.config.sessions.cookie_name(secure), secure)
+}
+
 /// Session middleware — ensures every request has a valid session.
 /// Creates a new session if none exists or if the session has expired.
-/// Injects CSRF token into response for HTMX to pick up.
-pub async fn session_middleware(request: Request, next: Next) -> Response {
+/// Injects CSRF token into response for HTMX to pick up, and runs the rest of
+/// the request with [`TemplateGlobals`] set so templates can read them.
+///
+/// Paths under `[sessions] exempt` (assets, health checks, feeds) skip all
+/// of that: handlers there get no [`SessionId`], and templates the defaults.
+pub async fn session_middleware(mut request: Request, next: Next) -> Response {
     let state = match request.extensions().get::<Arc<AppState>>().cloned() {
         Some(s) => s,
         None => return next.run(request).await,
     };
+    if state.config.sessions.is_exempt(request.uri().path()) {
+        return next.run(request).await;
+    }
 
     // Try to extract existing session ID from cookie
-    let existing_sid = request
-        .headers()
-        .get(header::COOKIE)
-        .and_then(|v| v.to_str().ok())
-        .and_then(|cookies| {
-            cookies.split(';').find_map(|c| {
-                let c = c.trim();
-                c.strip_prefix(&format!("{}=", SESSION_COOKIE))
-                    .map(|v| v.to_string())
-            })
-        });
+    let (cookie_name, secure) = session_cookie(&state, &request);
+    let existing_sid = Cookies::from_headers(request.headers())
+        .get(cookie_name)
+        .map(str::to_string);
 
     // Validate or create session
-    let (session, _is_new) = match existing_sid {
-        Some(ref sid) => {
-            match state.services.sessions.get(sid) {
-                Some(session) => {
-                    state.services.sessions.touch(sid);
-                    (session, false)
-                }
-                None => (state.services.sessions.create(), true), // Expired or invalid
-            }
+    let sessions = &state.services.sessions;
+    let tokens = &state.services.tokens;
+    let lazy = state.config.sessions.lazy;
+    let stored = existing_sid.as_deref().and_then(|sid| sessions.get(sid));
+    let (session, is_new) = match (stored, existing_sid) {
+        (Some(session), _) => {
+            sessions.touch(&session.id);
+            (session, false)
+        }
+        // A lazy session's cookie came back: the client keeps cookies, so
+        // store it under the ID it already has
+        (None, Some(sid)) if lazy && tokens.verify(LAZY_SESSION, &sid).is_some() => {
+            (sessions.create_with_id(&sid), false)
         }
-        None => (state.services.sessions.create(), true),
+        // Not stored until the cookie comes back or a handler saves data
+        (None, _) if lazy => {
+            let sid = tokens.sign(LAZY_SESSION, &session::new_id(), Some(SESSION_TTL));
+            (Session::new(&sid), true)
+        }
+        // Expired, invalid or missing
+        (None, _) => (sessions.create(), true),
     };
 
     // Generate CSRF token for this session
@@ -221,16 +523,87 @@ pub async fn session_middleware(request: Request, next: Next) -> Response {
         .sessions
         .update_csrf(&session.id, &csrf_token);
 
-    let mut response = next.run(request).await;
+    // Per-request template globals (flash messages are taken lazily, on render)
+    let path = request.uri().path();
+    let tenant = request
+        .extensions()
+        .get::<Tenant>()
+        .cloned()
+        .unwrap_or_default();
+    let template_globals = TemplateGlobals {
+        current_path: path.to_string(),
+        canonical_path: urls::canonical_path(path, &state.config.urls),
+        base_url: format!(
+            "{}{}",
+            state.config.site.base_url.trim_end_matches('/'),
+            tenant.prefix
+        ),
+        current_page: globals::nav_page(path),
+        csrf_token: csrf_token.clone(),
+        user_name: session.data.get(USER_NAME_KEY).cloned().unwrap_or_default(),
+        is_admin: session.data.contains_key(ADMIN_KEY),
+        breadcrumbs: state.services.breadcrumbs.trail(path),
+        tenant,
+        host: request
+            .extensions()
+            .get::<HostInfo>()
+            .cloned()
+            .unwrap_or_default(),
+        announcement: state.services.live.get().announcement.clone(),
+        db_down: !state.services.db_health.is_up(),
+        read_only: state.services.live.read_only(),
+        print_view: globals::is_print_view(request.uri().query()),
+        ..TemplateGlobals::default()
+    };
+    let flash_source = FlashSource {
+        store: state.services.sessions.clone(),
+        session_id: session.id.clone(),
+    };
+    request
+        .extensions_mut()
+        .insert(SessionId(session.id.clone()));
 
-    // Set session cookie (always — refreshes expiry)
-    let cookie_value = format!(
-        "{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age=3600",
-        SESSION_COOKIE, session.id
-    );
-    response
-        .headers_mut()
-        .append(header::SET_COOKIE, cookie_value.parse().unwrap());
+    // Only returning sessions count as present, so cookieless clients (bots,
+    // health checks) don't each add a guest. Signed-in sessions go by their
+    // user name; others keep whatever name they last commented under.
+    let presence = state.services.presence.clone();
+    if !is_new {
+        presence.touch(
+            &owner_key(&session.id),
+            session.data.get(USER_NAME_KEY).map(String::as_str),
+        );
+    }
+
+    let mut response = globals::scope(
+        template_globals,
+        Some(flash_source),
+        Some(presence),
+        next.run(request),
+    )
+    .await;
+
+    // Publicly cacheable responses must not carry anything per-session, or a
+    // shared cache would hand one visitor's cookie and token to the next
+    if is_public(&response) {
+        return response;
+    }
+
+    // A handler that signed in or out moved the session to a new ID
+    let (session_id, csrf_token) = match response.extensions_mut().remove::<RotatedSession>() {
+        Some(RotatedSession(sid)) => {
+            let csrf_token = state.services.csrf.generate_token(&sid);
+            sessions.update_csrf(&sid, &csrf_token);
+            (sid, csrf_token)
+        }
+        None => (session.id, csrf_token),
+    };
+
+    // Set session cookie (refreshes expiry)
+    SetCookie::new(cookie_name, session_id.as_str())
+        .max_age(SESSION_TTL)
+        .secure(secure)
+        .same_site(state.config.sessions.same_site)
+        .append_to(response.headers_mut());
 
     // Inject CSRF token as a response header for HTMX to read
     response.headers_mut().insert(
@@ -238,6 +611,11 @@ pub async fn session_middleware(request: Request, next: Next) -> Response {
         csrf_token.parse().unwrap(),
     );
 
+    // Checked after the handler, so signing in or out applies right away
+    if crate::services::admin::is_admin(state.services.sessions.as_ref(), &session_id) {
+        response.extensions_mut().insert(AdminResponse);
+    }
+
     // Periodically cleanup expired sessions (every ~100th request)
     if rand::random::<u8>() < 3 {
         state.services.sessions.cleanup_expired();
@@ -246,25 +624,1058 @@ pub async fn session_middleware(request: Request, next: Next) -> Response {
     response
 }
 
+/// Whether the handler marked the response `Cache-Control: public`
+fn is_public(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            v.split(',')
+                .any(|d| d.trim().eq_ignore_ascii_case("public"))
+        })
+}
+
+// ─── HEAD Requests ──────────────────────────────────────────────────────────
+
+/// Answers `HEAD` with the status and headers `GET` would get, and no body.
+/// Axum strips the body from a routed `GET` handler's response, but not from
+/// one made elsewhere (another middleware, a nested service), so this drops
+/// it for everything, keeping a `Content-Length` when the length is known.
+/// A streamed body (SSE, CSV) is dropped unread, which ends the stream. A
+/// handler that waits before answering (a long poll) checks the method
+/// itself.
+pub async fn head_requests(request: Request, next: Next) -> Response {
+    if request.method() != Method::HEAD {
+        return next.run(request).await;
+    }
+    let (mut parts, body) = next.run(request).await.into_parts();
+    if !parts.headers.contains_key(header::CONTENT_LENGTH) {
+        if let Some(length) = body.size_hint().exact().filter(|&length| length > 0) {
+            parts
+                .headers
+                .insert(header::CONTENT_LENGTH, HeaderValue::from(length));
+        }
+    }
+    Response::from_parts(parts, Body::empty())
+}
+
+// ─── Allowed Methods ────────────────────────────────────────────────────────
+
+/// Adds `OPTIONS` to the `Allow` header axum puts on a route's `OPTIONS`
+/// and `405` answers ([`crate::handlers::method_not_allowed`]). Axum lists
+/// only the methods the route registered, and every route takes `OPTIONS`.
+/// Runs outside the router, where axum has set the header.
+pub async fn allow_options(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let allow = response
+        .headers()
+        .get(header::ALLOW)
+        .and_then(|v| v.to_str().ok())
+        .filter(|allow| !allow.split(',').any(|m| m.trim() == "OPTIONS"))
+        .map(|allow| format!("{allow},OPTIONS"));
+    if let Some(allow) = allow.and_then(|allow| HeaderValue::try_from(allow).ok()) {
+        response.headers_mut().insert(header::ALLOW, allow);
+    }
+    response
+}
+
+// ─── Allowed Hosts ──────────────────────────────────────────────────────────
+
+/// Answers a request for a host outside `[server] allowed_hosts` (see
+/// [`crate::utils::hosts`]) with a bare `400`, before anything can echo the
+/// host back: no redirect, no page, no session. The host comes from the
+/// `Host` header, or the URI's authority over HTTP/2. `/healthz` and
+/// `/metrics` are exempt, since orchestrators and scrapers go by IP address.
+pub async fn allowed_hosts(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let patterns = state.config.allowed_hosts();
+    if patterns.is_empty() || matches!(request.uri().path(), "/healthz" | "/metrics") {
+        return next.run(request).await;
+    }
+    let host = hosts::request_host(request.headers(), request.uri()).and_then(hosts::host_name);
+    match host {
+        Some(host) if hosts::allowed(&patterns, &host) => next.run(request).await,
+        host => {
+            tracing::debug!(
+                host = host.as_deref().unwrap_or(""),
+                "Request for a host not allowed"
+            );
+            (StatusCode::BAD_REQUEST, "400 Bad Request\nunknown host\n").into_response()
+        }
+    }
+}
+
+// ─── URL Normalization ──────────────────────────────────────────────────────
+
+/// Answers non-canonical URLs (`/about/`, `//about`, `Example.COM`) with a
+/// `308` to the canonical one, per `[urls]`; see [`crate::utils::urls`].
+///
+/// Apply it outside [`resolve_tenant`], around the whole router: URLs that
+/// would otherwise 404 have to reach it, and with `trailing_slash = "add"`
+/// it takes the slash off before routing.
+pub async fn normalize_url(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let uri = request.uri();
+    let host = hosts::request_host(request.headers(), uri);
+    match urls::normalize(host, uri.path(), uri.query(), &state.config.urls) {
+        Normalized::Unchanged => {}
+        Normalized::Redirect(location) => {
+            return match HeaderValue::from_str(&location) {
+                Ok(location) => (
+                    StatusCode::PERMANENT_REDIRECT,
+                    [(header::LOCATION, location)],
+                )
+                    .into_response(),
+                Err(_) => StatusCode::BAD_REQUEST.into_response(),
+            };
+        }
+        Normalized::Route(path) => {
+            if !set_path(&mut request, path) {
+                return StatusCode::BAD_REQUEST.into_response();
+            }
+        }
+    }
+    next.run(request).await
+}
+
+/// Route `request` as `path`, keeping its query; `false` if that isn't a
+/// valid URI
+fn set_path(request: &mut Request, path: String) -> bool {
+    let path_and_query = match request.uri().query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path,
+    };
+    let mut parts = request.uri().clone().into_parts();
+    match path_and_query.parse() {
+        Ok(path_and_query) => parts.path_and_query = Some(path_and_query),
+        Err(_) => return false,
+    }
+    if let Ok(uri) = Uri::from_parts(parts) {
+        *request.uri_mut() = uri;
+    }
+    true
+}
+
+// ─── Redirect Rules ─────────────────────────────────────────────────────────
+
+/// Answers `GET` and `HEAD` requests for a path with a redirect rule (see
+/// [`crate::services::redirects`]) with its `301` or `302`; everything else
+/// goes on to the router.
+///
+/// Apply it inside [`resolve_tenant`], around the whole router, so rules see
+/// paths without the tenant prefix (and the `Location` gets it back), and
+/// paths with no route any more still reach it.
+pub async fn redirects(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !matches!(*request.method(), Method::GET | Method::HEAD) {
+        return next.run(request).await;
+    }
+    let uri = request.uri();
+    let Some(rule) = state.services.redirects.find(uri.path()) else {
+        return next.run(request).await;
+    };
+    let status = if rule.permanent() {
+        StatusCode::MOVED_PERMANENTLY
+    } else {
+        StatusCode::FOUND
+    };
+    match HeaderValue::from_str(&rule.location(uri.query())) {
+        Ok(location) => (status, [(header::LOCATION, location)]).into_response(),
+        Err(_) => next.run(request).await,
+    }
+}
+
+// ─── Tenant Resolution ──────────────────────────────────────────────────────
+
+/// Tenant middleware — resolves the request's tenant (see
+/// [`Tenants::resolve`](crate::services::tenants::Tenants::resolve)), runs
+/// the rest of the request as that tenant and exposes it as a [`TenantId`].
+/// Unknown tenants are a 404; the admin host is the default tenant.
+///
+/// In path mode the `/t/<slug>` prefix is stripped before routing, so apply
+/// this around the whole router (`from_fn_with_state(..).layer(app)`) —
+/// `Router::layer` middleware only runs once a route has matched. Redirects
+/// get the prefix put back.
+pub async fn resolve_tenant(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let resolved = {
+        let headers = request.headers();
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        // The admin host isn't a tenant, even under the base domain
+        let host = hosts::request_host(headers, request.uri())
+            .filter(|host| HostInfo::new(Some(host), &state.config).kind != HostKind::Admin);
+        state
+            .services
+            .tenants
+            .resolve(host, request.uri().path(), header("hx-current-url"))
+    };
+    let Some(resolved) = resolved else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    if let Some(path) = resolved.path {
+        if !set_path(&mut request, path) {
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    }
+
+    let tenant = resolved.tenant;
+    let prefix = tenant.prefix.clone();
+    request
+        .extensions_mut()
+        .insert(TenantId(tenant.slug.clone()));
+    let slug = tenant.slug.clone();
+    request.extensions_mut().insert(tenant);
+
+    let mut response = tenants::scope(slug, next.run(request)).await;
+
+    if !prefix.is_empty() {
+        for name in ["location", "hx-redirect", "hx-location"] {
+            let prefixed = response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .filter(|target| target.starts_with('/') && !target.starts_with("//"))
+                .and_then(|target| HeaderValue::from_str(&format!("{prefix}{target}")).ok());
+            if let Some(value) = prefixed {
+                response.headers_mut().insert(name, value);
+            }
+        }
+    }
+    response
+}
+
+// ─── Admin Guard ────────────────────────────────────────────────────────────
+
+/// Admin guard — apply with `route_layer` to routes that need a signed-in
+/// admin. Everyone else is sent to `/admin/login`; with no admin password
+/// configured the routes don't exist as far as visitors can tell (404).
+/// Requests [`api_token`] let in count as the admin's.
+pub async fn require_admin(request: Request, next: Next) -> Response {
+    let state = request.extensions().get::<Arc<AppState>>().cloned();
+    let session_id = request.extensions().get::<SessionId>().cloned();
+
+    match (state, session_id) {
+        (Some(state), _) if !state.services.admin.is_enabled() => {
+            StatusCode::NOT_FOUND.into_response()
+        }
+        (Some(_), _) if request.extensions().get::<ApiToken>().is_some() => next.run(request).await,
+        (Some(state), Some(SessionId(sid)))
+            if crate::services::admin::is_admin(state.services.sessions.as_ref(), &sid) =>
+        {
+            next.run(request).await
+        }
+        _ => htmx::redirect(request.headers(), "/admin/login"),
+    }
+}
+
+/// API token guard — apply with `route_layer(from_fn_with_state(scope, ..))`
+/// to the exports and feeds. A request with `Authorization: Bearer` gets in
+/// with a working token for `scope`, carrying the [`ApiToken`] as an
+/// extension (for [`require_admin`] behind it), and a 401 or 403 otherwise.
+/// Requests without one go on as before, unless `[admin] private_feeds` asks
+/// for a token from everyone but signed-in admins. The feeds are exempt from
+/// sessions, so the admin's session is looked up from the cookie here.
+///
+/// An answer let in by a token or a session is marked `private, no-store`,
+/// so no shared cache hands it to someone without either.
+pub async fn api_token(State(scope): State<Scope>, mut request: Request, next: Next) -> Response {
+    let Some(state) = request.extensions().get::<Arc<AppState>>().cloned() else {
+        return next.run(request).await;
+    };
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(api_tokens::bearer)
+        .map(str::to_string);
+
+    match bearer {
+        Some(secret) => match state.services.api_tokens.authenticate(&secret) {
+            Some(token) if token.allows(scope) => {
+                request.extensions_mut().insert(token);
+                not_stored(next.run(request).await)
+            }
+            Some(_) => token_refused(
+                StatusCode::FORBIDDEN,
+                &format!(r#"Bearer error="insufficient_scope", scope="{scope}""#),
+                "This token doesn't have the scope for this URL.",
+            ),
+            None => token_refused(
+                StatusCode::UNAUTHORIZED,
+                r#"Bearer error="invalid_token""#,
+                "This token is unknown or revoked.",
+            ),
+        },
+        None if state.config.admin.private_feeds => {
+            let session_id = match request.extensions().get::<SessionId>() {
+                Some(SessionId(sid)) => Some(sid.clone()),
+                None => {
+                    let (cookie_name, _) = session_cookie(&state, &request);
+                    Cookies::from_headers(request.headers())
+                        .get(cookie_name)
+                        .map(str::to_string)
+                }
+            };
+            let is_admin = session_id.is_some_and(|sid| {
+                crate::services::admin::is_admin(state.services.sessions.as_ref(), &sid)
+            });
+            if is_admin {
+                not_stored(next.run(request).await)
+            } else {
+                token_refused(
+                    StatusCode::UNAUTHORIZED,
+                    "Bearer",
+                    "Send an API token: Authorization: Bearer <token>.",
+                )
+            }
+        }
+        None => next.run(request).await,
+    }
+}
+
+/// `response`, kept out of every cache
+fn not_stored(mut response: Response) -> Response {
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("private, no-store"),
+    );
+    response
+}
+
+/// A token refusal, for scripts: plain text and `WWW-Authenticate`
+fn token_refused(status: StatusCode, challenge: &str, message: &'static str) -> Response {
+    let mut response = (status, message).into_response();
+    if let Ok(value) = HeaderValue::from_str(challenge) {
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, value);
+    }
+    response
+}
+
+// ─── Listener Guards ────────────────────────────────────────────────────────
+
+/// The [`ListenerInfo`](crate::server::ListenerInfo) a request came in on;
+/// requests from elsewhere (tests) count as the main listener's
+fn listener(request: &Request) -> crate::server::ListenerInfo {
+    request
+        .extensions()
+        .get::<crate::server::ListenerInfo>()
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// The 404 a missing route would get, so a guarded route doesn't give
+/// itself away
+fn not_found(request: &Request) -> Response {
+    match request.extensions().get::<Arc<AppState>>() {
+        Some(state) => {
+            crate::handlers::not_found::respond(state, request.headers(), request.uri().path())
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// `/metrics` guard — apply with `route_layer`: a 404 unless the listener
+/// has `metrics = true`
+pub async fn metrics_listener(request: Request, next: Next) -> Response {
+    if listener(&request).metrics {
+        next.run(request).await
+    } else {
+        not_found(&request)
+    }
+}
+
+/// Admin pages guard — apply with `route_layer`: a 404 on listeners with
+/// `admin = false`
+pub async fn admin_listener(request: Request, next: Next) -> Response {
+    if listener(&request).admin {
+        next.run(request).await
+    } else {
+        not_found(&request)
+    }
+}
+
+// ─── Maintenance Mode ───────────────────────────────────────────────────────
+
+/// Paths that keep working during maintenance: health checks, metrics,
+/// assets, and the admin pages, so an admin can sign in and see the site
+const MAINTENANCE_EXEMPT: &[&str] = &["/healthz", "/metrics", "/static/", "/admin"];
+
+/// While `[maintenance] enabled` (reloadable), everyone but admins gets a 503
+/// maintenance page. HTMX requests get `HX-Refresh` so the whole tab shows it.
+/// Runs inside the session middleware, which it needs to recognize admins.
+pub async fn maintenance(request: Request, next: Next) -> Response {
+    let Some(state) = request.extensions().get::<Arc<AppState>>().cloned() else {
+        return next.run(request).await;
+    };
+    let config = state.services.live.get();
+    if !config.maintenance.enabled {
+        return next.run(request).await;
+    }
+
+    let path = request.uri().path();
+    // Whole segments only, so `/admin` and `/admin/..` but not `/administer`
+    let exempt = MAINTENANCE_EXEMPT.iter().any(|prefix| {
+        path.strip_prefix(prefix)
+            .is_some_and(|rest| prefix.ends_with('/') || rest.is_empty() || rest.starts_with('/'))
+    });
+    let is_admin = request
+        .extensions()
+        .get::<SessionId>()
+        .is_some_and(|SessionId(sid)| {
+            crate::services::admin::is_admin(state.services.sessions.as_ref(), sid)
+        });
+    if exempt || is_admin {
+        return next.run(request).await;
+    }
+
+    let page = MaintenancePage {
+        message: config.maintenance.message.clone(),
+    };
+    let mut response = (StatusCode::SERVICE_UNAVAILABLE, page).into_response();
+    let headers = response.headers_mut();
+    headers.insert(header::RETRY_AFTER, HeaderValue::from_static("300"));
+    if htmx::is_htmx(request.headers()) {
+        headers.insert(
+            header::HeaderName::from_static("hx-refresh"),
+            HeaderValue::from_static("true"),
+        );
+    }
+    response
+}
+
+/// Writes that keep working in read-only mode: signing in and out, the
+/// admin controls that change nothing stored (including the ones that turn
+/// it off again), and backups, which only read the database
+const READ_ONLY_EXEMPT: &[&str] = &[
+    "/admin/login",
+    "/admin/logout",
+    "/admin/read-only",
+    "/admin/config/reload",
+    "/admin/logging",
+    "/admin/backups",
+];
+
+/// In read-only mode ([`LiveConfig::read_only`]), every state-changing
+/// request gets a 503 notice in `#error-toast` instead of reaching its
+/// handler; GET, HEAD and OPTIONS work as usual. Admins are held to it too,
+/// since the point is that nothing writes.
+///
+/// [`LiveConfig::read_only`]: crate::services::LiveConfig::read_only
+pub async fn read_only(request: Request, next: Next) -> Response {
+    let Some(state) = request.extensions().get::<Arc<AppState>>().cloned() else {
+        return next.run(request).await;
+    };
+    let safe = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    if safe || !state.services.live.read_only() || READ_ONLY_EXEMPT.contains(&request.uri().path())
+    {
+        return next.run(request).await;
+    }
+
+    ErrorFragment::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Read-only",
+        "The site is read-only for maintenance, so changes can't be saved right now. Please try again shortly.",
+    )
+    .icon("pause-circle")
+    .retry_after(300)
+    .into_response()
+}
+
+// ─── Load Shedding ──────────────────────────────────────────────────────────
+
+/// Paths never shed: health checks and metrics must answer under load,
+/// assets are cheap, and the long poll and event streams stay open for as
+/// long as a tab does, so counting them would let idle tabs fill the limit
+const LOAD_SHED_EXEMPT: &[&str] = &[
+    "/healthz",
+    "/metrics",
+    "/static/",
+    "/partials/item-list/changes",
+    "/notifications/stream",
+    "/admin/activity/stream",
+    "/dev/reload",
+];
+
+/// Once `[rate_limits.load_shed] max_in_flight` (reloadable) requests are
+/// being handled, new ones get a 503 straight away instead of queueing. It
+/// carries `X-Retry-After-Ms`, so HTMX retries GETs by itself after that
+/// long, backing off (see [`ErrorFragment::retry_after_ms`]).
+pub async fn load_shed(request: Request, next: Next) -> Response {
+    let Some(state) = request.extensions().get::<Arc<AppState>>().cloned() else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    if LOAD_SHED_EXEMPT
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+    {
+        return next.run(request).await;
+    }
+    let policy = state.services.live.get().rate_limits.load_shed;
+    let Some(_guard) = state.services.in_flight.enter(policy.max_in_flight) else {
+        tracing::warn!(
+            path = %path,
+            max_in_flight = policy.max_in_flight,
+            "Shedding load"
+        );
+        return ErrorFragment::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Busy",
+            "The server is busy right now. Trying again shortly…",
+        )
+        .icon("hourglass-split")
+        .retry_after_ms(policy.retry_after_ms)
+        .into_response();
+    };
+    next.run(request).await
+}
+
+// ─── Idempotency ────────────────────────────────────────────────────────────
+
+/// Request header naming a submission, see [`idempotency`]
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Set on an answer replayed from the store
+const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+/// Longest key accepted
+const MAX_IDEMPOTENCY_KEY: usize = 100;
+/// Larger answers (and streamed ones) are sent but not kept, so a repeat
+/// runs the handler again
+const IDEMPOTENCY_BODY_LIMIT: usize = 256 * 1024;
+
+/// A write carrying `Idempotency-Key` runs its handler once per key: a
+/// repeat (say, replayed by app.js after the connection dropped) gets the
+/// stored answer, marked `Idempotent-Replayed: true`, and one arriving while
+/// the first is still handled gets a 409. Server errors aren't kept, so
+/// those can be retried. Keys are per session and path, see
+/// [`IdempotencyStore`](crate::services::IdempotencyStore). Runs inside the
+/// CSRF check, so only genuine submissions claim keys.
+pub async fn idempotency(request: Request, next: Next) -> Response {
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY).cloned() else {
+        return next.run(request).await;
+    };
+    let state = request.extensions().get::<Arc<AppState>>().cloned();
+    let session_id = request.extensions().get::<SessionId>().cloned();
+    let (Some(state), Some(SessionId(sid))) = (state, session_id) else {
+        return next.run(request).await;
+    };
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return next.run(request).await;
+    }
+    let key = match key.to_str() {
+        Ok(key)
+            if !key.is_empty()
+                && key.len() <= MAX_IDEMPOTENCY_KEY
+                && key.bytes().all(|b| b.is_ascii_graphic()) =>
+        {
+            format!("{sid}:{} {}:{key}", request.method(), request.uri().path())
+        }
+        _ => return AppError::bad_request("Invalid Idempotency-Key").into_response(),
+    };
+
+    let store = &state.services.idempotency;
+    match store.claim(&key) {
+        Claim::Done(stored) => {
+            let mut response = (stored.status, stored.body).into_response();
+            response.headers_mut().extend(stored.headers);
+            response.headers_mut().insert(
+                header::HeaderName::from_static(IDEMPOTENT_REPLAYED),
+                HeaderValue::from_static("true"),
+            );
+            return response;
+        }
+        Claim::Pending => {
+            return ErrorFragment::new(
+                StatusCode::CONFLICT,
+                "Still saving",
+                "This submission is already being saved.",
+            )
+            .icon("hourglass-split")
+            .into_response();
+        }
+        Claim::New => {}
+    }
+
+    // Released on every path that doesn't complete it, including a panic
+    // in the handler and the client hanging up mid-request
+    let claim = store.hold(key);
+    let response = next.run(request).await;
+    if response.status().is_server_error() {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    // Streams and large answers go out as they are, and aren't kept
+    let fits = body
+        .size_hint()
+        .exact()
+        .is_some_and(|len| len <= IDEMPOTENCY_BODY_LIMIT as u64);
+    if !fits {
+        return Response::from_parts(parts, body);
+    }
+    let Ok(body) = axum::body::to_bytes(body, IDEMPOTENCY_BODY_LIMIT).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let mut headers = parts.headers.clone();
+    headers.remove(header::SET_COOKIE);
+    headers.remove(header::CONTENT_LENGTH);
+    claim.complete(StoredResponse {
+        status: parts.status,
+        headers,
+        body: body.clone(),
+    });
+    Response::from_parts(parts, Body::from(body))
+}
+
+// ─── Polling Limit ──────────────────────────────────────────────────────────
+
+/// How long the client is told to back off, matching the limit's window
+const POLL_RETRY_AFTER: &str = "60";
+
+/// Polling guard — apply with `route_layer` to routes that pages poll with
+/// `hx-trigger="every Ns"`. A session polling one of them more than
+/// `[rate_limits.polling] per_minute` (reloadable) gets `286`, which stops
+/// that element's polling; its content stays put and `#error-toast` says why.
+/// Requests from outside HTMX get a plain `429` instead.
+pub async fn poll_limit(request: Request, next: Next) -> Response {
+    let state = request.extensions().get::<Arc<AppState>>().cloned();
+    let session_id = request.extensions().get::<SessionId>().cloned();
+    let (Some(state), Some(SessionId(sid))) = (state, session_id) else {
+        return next.run(request).await;
+    };
+    let limit = state.services.live.get().rate_limits.polling.limit();
+    let key = format!("poll:{sid}:{}", request.uri().path());
+    if state.services.rate_limiter.check(&key, limit) {
+        return next.run(request).await;
+    }
+
+    tracing::info!(path = %request.uri().path(), "Polling too often, told to stop");
+    if !htmx::is_htmx(request.headers()) {
+        let mut response =
+            AppError::too_many_requests("Too many requests. Please slow down.").into_response();
+        let headers = response.headers_mut();
+        headers.insert(
+            header::RETRY_AFTER,
+            HeaderValue::from_static(POLL_RETRY_AFTER),
+        );
+        return response;
+    }
+    let body = HtmlFragment::alert(
+        "warning",
+        "pause-circle",
+        "Live updates paused",
+        "This page was refreshing too often. Reload it to resume live updates.",
+    );
+    let mut response = htmx::HxStopPolling(body).into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::RETRY_AFTER,
+        HeaderValue::from_static(POLL_RETRY_AFTER),
+    );
+    headers.insert(
+        header::HeaderName::from_static("hx-retarget"),
+        HeaderValue::from_static(ERROR_REGION),
+    );
+    headers.insert(
+        header::HeaderName::from_static("hx-reswap"),
+        HeaderValue::from_static("innerHTML"),
+    );
+    response
+}
+
+// ─── Fragment Diffs ─────────────────────────────────────────────────────────
+
+/// Header carrying a render's digest: set on each answer [`diff_fragments`]
+/// could diff, and sent back by app.js with the target's next request
+pub const FRAGMENT_DIGEST: &str = "x-fragment-digest";
+/// Larger renders (and streamed ones) go out whole
+const FRAGMENT_BODY_LIMIT: usize = 512 * 1024;
+
+/// Diffing guard — apply with `route_layer` to large partials that pages
+/// poll into the same target. An HTMX `GET` is rendered as usual, then
+/// compared with the render last sent into its `HX-Target` by this session
+/// (see [`FragmentDiffs`](crate::services::FragmentDiffs)). When the page
+/// still shows that one (its `X-Fragment-Digest` matches) and the children
+/// line up by id, only the changed children go out, as out-of-band swaps
+/// with `HX-Reswap: none`; nothing changed is a `204`. Anything else — a
+/// first load, another tab, a child without an id, an error — gets the full
+/// render. Diffed children are the target's own (`innerHTML`), or its root's
+/// when the render is the target itself (`outerHTML`), see
+/// [`html_diff::children`].
+pub async fn diff_fragments(request: Request, next: Next) -> Response {
+    let state = request.extensions().get::<Arc<AppState>>().cloned();
+    let session_id = request.extensions().get::<SessionId>().cloned();
+    let target = request
+        .headers()
+        .get("HX-Target")
+        .and_then(|v| v.to_str().ok())
+        .filter(|target| !target.is_empty())
+        .map(str::to_string);
+    let (Some(state), Some(SessionId(sid)), Some(target)) = (state, session_id, target) else {
+        return next.run(request).await;
+    };
+    if request.method() != Method::GET || !htmx::is_htmx(request.headers()) {
+        return next.run(request).await;
+    }
+    let key = format!("{sid}:{}#{target}", request.uri());
+    let shown = request
+        .headers()
+        .get(FRAGMENT_DIGEST)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let diffs = &state.services.fragment_diffs;
+    let response = next.run(request).await;
+    let headers = response.headers();
+    let is_html = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|t| t.starts_with("text/html"));
+    let fits = response
+        .body()
+        .size_hint()
+        .exact()
+        .is_some_and(|len| len <= FRAGMENT_BODY_LIMIT as u64);
+    // Swapped somewhere else, or some other way: not the render to diff
+    let redirected = headers.contains_key("HX-Retarget") || headers.contains_key("HX-Reswap");
+    if response.status() != StatusCode::OK || !is_html || !fits || redirected {
+        diffs.forget(&key);
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, FRAGMENT_BODY_LIMIT).await else {
+        diffs.forget(&key);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let Some((root, children)) = std::str::from_utf8(&body)
+        .ok()
+        .and_then(|html| html_diff::children(html, &target))
+    else {
+        diffs.forget(&key);
+        return Response::from_parts(parts, Body::from(body));
+    };
+
+    let render = SentRender {
+        digest: hex::encode(&Sha256::digest(&body)[..8]),
+        shape: Shape::new(root, &children),
+    };
+    let patch = diffs
+        .replace(&key, render.clone())
+        .filter(|previous| shown.as_deref() == Some(previous.digest.as_str()))
+        .and_then(|previous| html_diff::patch(&previous.shape, &render.shape, &children));
+    if let Ok(digest) = HeaderValue::from_str(&render.digest) {
+        parts.headers.insert(FRAGMENT_DIGEST, digest);
+    }
+    match patch {
+        Some(patch) if patch.is_empty() => {
+            parts.status = StatusCode::NO_CONTENT;
+            parts.headers.remove(header::CONTENT_TYPE);
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::empty())
+        }
+        Some(patch) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            parts
+                .headers
+                .insert("HX-Reswap", HeaderValue::from_static("none"));
+            Response::from_parts(parts, Body::from(patch))
+        }
+        None => Response::from_parts(parts, Body::from(body)),
+    }
+}
+
+// ─── Panics ─────────────────────────────────────────────────────────────────
+
+/// Marks the bare 500 that [`panic_response`] answers a panic with, carrying
+/// the panic message for [`render_panic`]
+#[derive(Debug, Clone)]
+pub struct Panicked(pub String);
+
+/// Handler for `CatchPanicLayer::custom`: a 500 marked [`Panicked`]. It can't
+/// see the request, so logging and the body are left to [`render_panic`].
+pub fn panic_response(panic: Box<dyn Any + Send + 'static>) -> Response {
+    let message = panic
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_else(|| "non-string panic payload".to_string());
+    let mut response = StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    response.extensions_mut().insert(Panicked(message));
+    response
+}
+
+/// Logs a caught panic with the request ID and renders the 500 page, or an
+/// alert in `#error-toast` for HTMX requests. Has to run inside
+/// [`session_middleware`] so the page gets its globals.
+pub async fn render_panic(request: Request, next: Next) -> Response {
+    let request_id = request_id(&request);
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let is_htmx = htmx::is_htmx(request.headers());
+
+    let response = next.run(request).await;
+    let Some(Panicked(message)) = response.extensions().get::<Panicked>() else {
+        return response;
+    };
+    tracing::error!(
+        request_id = %request_id,
+        method = %method,
+        path = %path,
+        panic = %message,
+        "Handler panicked"
+    );
+
+    if is_htmx {
+        ErrorFragment::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error 500",
+            format!("Something went wrong (reference {request_id})"),
+        )
+        .icon("x-octagon")
+        .into_response()
+    } else {
+        (StatusCode::INTERNAL_SERVER_ERROR, ErrorPage { request_id }).into_response()
+    }
+}
+
+/// Hands caught panics and `500`s from an [`AppError`] (marked
+/// [`InternalError`]) to the [`ErrorReporter`](crate::services::ErrorReporter),
+/// with the request's method, path, request ID and tenant. Has to run between
+/// [`render_panic`], which replaces the marked response, and the
+/// `CatchPanicLayer`.
+pub async fn report_errors(request: Request, next: Next) -> Response {
+    let Some(state) = request.extensions().get::<Arc<AppState>>().cloned() else {
+        return next.run(request).await;
+    };
+    let context = ErrorContext {
+        method: request.method().to_string(),
+        path: request.uri().path().to_string(),
+        request_id: request_id(&request),
+        tenant: request
+            .extensions()
+            .get::<TenantId>()
+            .map(|tenant| tenant.0.clone())
+            .unwrap_or_default(),
+    };
+
+    let response = next.run(request).await;
+    let extensions = response.extensions();
+    let error = if let Some(Panicked(message)) = extensions.get::<Panicked>() {
+        Some((ErrorKind::Panic, message))
+    } else if let Some(InternalError(message)) = extensions.get::<InternalError>() {
+        tracing::error!(
+            request_id = %context.request_id,
+            method = %context.method,
+            path = %context.path,
+            error = %message,
+            "Internal error"
+        );
+        Some((ErrorKind::Internal, message))
+    } else {
+        None
+    };
+    if let Some((kind, message)) = error {
+        state
+            .services
+            .errors
+            .report(ErrorEvent::new(kind, message.clone(), context));
+    }
+    response
+}
+
+/// `X-Request-Id` as set by `SetRequestIdLayer`; empty without one
+fn request_id(request: &Request) -> String {
+    request
+        .headers()
+        .get(REQUEST_ID)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+// ─── Database Connection ────────────────────────────────────────────────────
+
+/// Make a [`DbConn`](crate::db_conn::DbConn) available to the handler, and
+/// return its connection to the pool once the response is ready. Logs the
+/// statements it ran, and warns past
+/// [`MANY_STATEMENTS`](crate::db_conn::MANY_STATEMENTS).
+pub async fn db_conn(mut request: Request, next: Next) -> Response {
+    let Some(state) = request.extensions().get::<Arc<AppState>>().cloned() else {
+        return next.run(request).await;
+    };
+    let request_id = request_id(&request);
+    let slot = ConnSlot::new(state.db.reader().clone());
+    request.extensions_mut().insert(slot.clone());
+
+    let response = next.run(request).await;
+
+    slot.release();
+    let log = slot.log();
+    if log.statements() > MANY_STATEMENTS {
+        tracing::warn!(
+            request_id = %request_id,
+            statements = log.statements(),
+            db_ms = log.elapsed().as_millis() as u64,
+            "Many statements in one request; batch them or join instead of querying per row"
+        );
+    } else if log.statements() > 0 {
+        tracing::debug!(
+            request_id = %request_id,
+            statements = log.statements(),
+            db_ms = log.elapsed().as_millis() as u64,
+            "request database use"
+        );
+    }
+    response
+}
+
 // ─── Request Logging ────────────────────────────────────────────────────────
 
-/// Request logging middleware — logs method, path, status and duration.
+/// Request logging middleware — logs request ID, method, path, status and
+/// duration, and reports them to [`Stats`](crate::services::Stats) and the
+/// [`RequestLog`](crate::services::RequestLog).
 /// Does NOT log query strings, headers, or bodies (no data leaks).
+///
+/// A request slower than its route's `[latency]` budget gets a warning
+/// naming the route, and is counted on the status card. Requests no route
+/// matched have no budget.
 pub async fn request_logger(request: Request, next: Next) -> Response {
+    let state = request.extensions().get::<Arc<AppState>>().cloned();
+    let request_id = request_id(&request);
     let method = request.method().to_string();
     let path = request.uri().path().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched| matched.as_str().to_string());
     let start = std::time::Instant::now();
 
     let response = next.run(request).await;
 
     let duration = start.elapsed();
     tracing::info!(
+        request_id = %request_id,
         method = %method,
         path = %path,
         status = response.status().as_u16(),
         duration_ms = duration.as_millis() as u64,
         "request"
     );
+    if let Some(state) = state {
+        let status = response.status().as_u16();
+        state.services.stats.record_request(duration, status);
+        let budget = route
+            .as_deref()
+            .and_then(|route| state.services.live.get().latency.budget(route));
+        if let (Some(route), Some(budget)) = (&route, budget) {
+            if duration > budget {
+                tracing::warn!(
+                    request_id = %request_id,
+                    method = %method,
+                    route = %route,
+                    budget_ms = budget.as_millis() as u64,
+                    duration_ms = duration.as_millis() as u64,
+                    "Request over its route's latency budget"
+                );
+                state.services.stats.record_over_budget();
+            }
+        }
+        state.services.request_log.record(RequestRecord {
+            method,
+            path,
+            status,
+            duration,
+            request_id,
+        });
+    }
 
     response
 }
+
+// ─── The Stack ──────────────────────────────────────────────────────────────
+
+/// Wrap the routed app in every middleware above, in the order they run.
+/// The layers every route gets come first (outermost first); URL
+/// normalization, tenant resolution and redirect rules wrap the whole router,
+/// since they change or answer a request before it's routed (or before
+/// [`HostRouter`] picks a router by host). `main` serves
+/// the result, and `benches/stack.rs` measures it.
+pub fn stack(
+    app: impl Into<HostRouter>,
+    state: Arc<AppState>,
+) -> impl Service<Request, Response = Response, Error = Infallible, Future: Send + 'static>
+       + Clone
+       + Send
+       + 'static {
+    // Each host's router gets its own copy, so the layers see the route
+    // that matched
+    let layers = || {
+        ServiceBuilder::new()
+            // Every request gets an X-Request-Id, echoed on the response
+            .layer(SetRequestIdLayer::new(
+                HeaderName::from_static(REQUEST_ID),
+                MakeRequestUuid,
+            ))
+            .layer(PropagateRequestIdLayer::new(HeaderName::from_static(
+                REQUEST_ID,
+            )))
+            .layer(TraceLayer::new_for_http())
+            // Inject shared state into extensions — must wrap every
+            // middleware below that reads it (sessions, CSRF)
+            .layer(axum::Extension(state.clone()))
+            .layer(middleware::from_fn(request_logger))
+            .layer(middleware::from_fn(security_headers))
+            // Inside the headers, so plain-text answers keep them too
+            .layer(middleware::from_fn(plain_text_for_non_html))
+            // Before sessions, so shedding a request costs next to nothing
+            .layer(middleware::from_fn(load_shed))
+            .layer(middleware::from_fn(session_middleware))
+            // A panic below becomes the 500 page, not a dropped connection
+            .layer(middleware::from_fn(render_panic))
+            // Between the two, so it sees the panic before it's rendered
+            .layer(middleware::from_fn(report_errors))
+            .layer(CatchPanicLayer::custom(panic_response))
+            .layer(middleware::from_fn(maintenance))
+            .layer(middleware::from_fn(read_only))
+            .layer(middleware::from_fn(csrf_protection))
+            // Inside the CSRF check, so only genuine submissions claim keys
+            .layer(middleware::from_fn(idempotency))
+            // Innermost, so refused requests never check out a connection
+            .layer(middleware::from_fn(db_conn))
+    };
+    let app = app.into().map(|router| router.layer(layers()));
+
+    // Redirect rules wrap the router too, so moved paths needn't have a route
+    let app = middleware::from_fn_with_state(state.clone(), redirects).layer(app);
+    // Tenant resolution wraps the router itself: path mode rewrites the URI,
+    // which has to happen before routing
+    let app = middleware::from_fn_with_state(state.clone(), resolve_tenant).layer(app);
+    // Outside that, so `/t/acme/about/` is redirected before it's resolved
+    let app = middleware::from_fn_with_state(state.clone(), normalize_url).layer(app);
+    // Outside that, so an unknown host is never redirected to
+    let app = middleware::from_fn_with_state(state, allowed_hosts).layer(app);
+    // Outside the router, where axum has set `Allow`
+    let app = middleware::from_fn(allow_options).layer(app);
+    // Outermost, so no response keeps a body for `HEAD`
+    middleware::from_fn(head_requests).layer(app)
+}
@@ -1,3 +1,6 @@
+use std::sync::Arc;
+
+use crate::config::AppConfig;
 use crate::db::Db;
 use crate::services::Services;
 
@@ -6,10 +9,15 @@ use crate::services::Services;
 pub struct AppState {
     pub services: Services,
     pub db: Db,
+    pub config: Arc<AppConfig>,
 }
 
 impl AppState {
-    pub fn new(services: Services, db: Db) -> Self {
-        Self { services, db }
+    pub fn new(services: Services, db: Db, config: AppConfig) -> Self {
+        Self {
+            services,
+            db,
+            config: Arc::new(config),
+        }
     }
 }
@@ -0,0 +1,385 @@
+//! Query cache — read-through caching of hot query results
+//!
+//! Partials that HTMX polls (the notification bell, the item list and its
+//! tag chips) would otherwise run the same queries every few seconds for
+//! every open tab. [`QueryCache::get_or_load`] keeps each result for
+//! `[database] query_cache_ttl_ms`, keyed by a name for the query (e.g.
+//! `items.list_all`) and its parameters.
+//!
+//! Every entry names the tables it read. The stores call
+//! [`QueryCache::invalidate`] with the tables a write touched, so their own
+//! writes show up on the next read; the TTL bounds how stale a result can get
+//! after a write that went around the stores. Errors are never cached.
+//!
+//! Loads are single-flight: when an entry expires under a dozen tabs polling
+//! the same partial, the first miss runs the query and the others wait for
+//! its result instead of running it too. If that load fails (or its request
+//! goes away), each waiter runs its own.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Entries kept at most; past this, expired ones are dropped, and if that
+/// isn't enough, all of them
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    query: &'static str,
+    params: String,
+}
+
+struct Entry {
+    tables: &'static [&'static str],
+    value: Arc<dyn Any + Send + Sync>,
+    expires: Instant,
+}
+
+/// A load in progress, which misses for the same key wait on
+struct Flight {
+    id: u64,
+    tables: &'static [&'static str],
+    // `None` until the load is done; then its value, or `None` if it failed
+    landed: watch::Receiver<Option<Option<Arc<dyn Any + Send + Sync>>>>,
+}
+
+/// Whether a miss runs the load or waits for one
+enum Role {
+    Lead(Leading),
+    Wait(watch::Receiver<Option<Option<Arc<dyn Any + Send + Sync>>>>),
+}
+
+/// The running load's side of its [`Flight`]. A request that goes away
+/// mid-load drops it, which releases the waiters, and the next miss for the
+/// key starts a new flight.
+struct Leading {
+    id: u64,
+    landed: watch::Sender<Option<Option<Arc<dyn Any + Send + Sync>>>>,
+}
+
+/// Cached query results, shared by every store through [`Db`](crate::db::Db)
+pub struct QueryCache {
+    // Zero disables caching
+    ttl: Duration,
+    entries: Mutex<HashMap<Key, Entry>>,
+    flights: Mutex<HashMap<Key, Flight>>,
+    // Bumped by every invalidation, so a load that raced a write isn't stored
+    generation: AtomicU64,
+    next_flight: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+impl QueryCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+            flights: Mutex::new(HashMap::new()),
+            generation: AtomicU64::new(0),
+            next_flight: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+        }
+    }
+
+    /// A cache that never keeps anything
+    pub fn disabled() -> Self {
+        Self::new(Duration::ZERO)
+    }
+
+    /// The cached result of `query` with `params`, or `load`'s, which is kept
+    /// until the TTL runs out or one of `tables` is written to. `params`
+    /// must cover everything bound to the query, the tenant included. While
+    /// a load for the same key is running, this waits for its result.
+    pub async fn get_or_load<T, P, F, Fut>(
+        &self,
+        tables: &'static [&'static str],
+        query: &'static str,
+        params: P,
+        load: F,
+    ) -> Result<T, sqlx::Error>
+    where
+        T: Clone + Send + Sync + 'static,
+        P: Debug,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, sqlx::Error>>,
+    {
+        if self.ttl.is_zero() {
+            return load().await;
+        }
+        let key = Key {
+            query,
+            params: format!("{params:?}"),
+        };
+        if let Some(value) = self.cached::<T>(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(value);
+        }
+
+        let leading = match self.join(&key, tables) {
+            Role::Lead(leading) => {
+                // A load may have landed between the lookup and joining
+                if let Some(value) = self.cached::<T>(&key) {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    self.land(&key, leading, Some(Arc::new(value.clone())));
+                    return Ok(value);
+                }
+                leading
+            }
+            Role::Wait(mut landed) => {
+                let value = landed
+                    .wait_for(Option::is_some)
+                    .await
+                    .ok()
+                    .and_then(|landed| landed.clone().flatten())
+                    .and_then(|value| value.downcast_ref::<T>().cloned());
+                if let Some(value) = value {
+                    self.coalesced.fetch_add(1, Ordering::Relaxed);
+                    return Ok(value);
+                }
+                // The load failed or was dropped: run our own, uncached
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                return load().await;
+            }
+        };
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let generation = self.generation.load(Ordering::Acquire);
+        let value = match load().await {
+            Ok(value) => value,
+            Err(e) => {
+                self.land(&key, leading, None);
+                return Err(e);
+            }
+        };
+        let shared: Arc<dyn Any + Send + Sync> = Arc::new(value.clone());
+        let mut entries = self.entries.lock().unwrap();
+        if self.generation.load(Ordering::Acquire) == generation {
+            if entries.len() >= MAX_ENTRIES {
+                let now = Instant::now();
+                entries.retain(|_, entry| entry.expires > now);
+                if entries.len() >= MAX_ENTRIES {
+                    entries.clear();
+                }
+            }
+            entries.insert(
+                key.clone(),
+                Entry {
+                    tables,
+                    value: shared.clone(),
+                    expires: Instant::now() + self.ttl,
+                },
+            );
+        }
+        drop(entries);
+        self.land(&key, leading, Some(shared));
+        Ok(value)
+    }
+
+    fn cached<T: Clone + 'static>(&self, key: &Key) -> Option<T> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(key)
+            .filter(|entry| entry.expires > Instant::now())
+            .and_then(|entry| entry.value.downcast_ref::<T>().cloned())
+    }
+
+    /// Wait for the load running for `key`, or start one
+    fn join(&self, key: &Key, tables: &'static [&'static str]) -> Role {
+        let mut flights = self.flights.lock().unwrap();
+        // A flight whose load was dropped has no sender left
+        let live = |flight: &Flight| flight.landed.has_changed().is_ok();
+        if let Some(flight) = flights.get(key).filter(|flight| live(flight)) {
+            return Role::Wait(flight.landed.clone());
+        }
+        if flights.len() >= MAX_ENTRIES {
+            flights.retain(|_, flight| live(flight));
+        }
+        let id = self.next_flight.fetch_add(1, Ordering::Relaxed);
+        let (sender, landed) = watch::channel(None);
+        flights.insert(key.clone(), Flight { id, tables, landed });
+        Role::Lead(Leading { id, landed: sender })
+    }
+
+    /// End the flight for `key`, handing its waiters `value`
+    fn land(&self, key: &Key, leading: Leading, value: Option<Arc<dyn Any + Send + Sync>>) {
+        let mut flights = self.flights.lock().unwrap();
+        // An invalidation may have ended it already, and another started
+        if flights
+            .get(key)
+            .is_some_and(|flight| flight.id == leading.id)
+        {
+            flights.remove(key);
+        }
+        drop(flights);
+        leading.landed.send_replace(Some(value));
+    }
+
+    /// Forget every result read from one of `tables`; call after writing
+    /// to them
+    pub fn invalidate(&self, tables: &[&str]) {
+        let mut entries = self.entries.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::Release);
+        entries.retain(|_, entry| !entry.tables.iter().any(|table| tables.contains(table)));
+        drop(entries);
+        // Reads after the write don't wait for a load that started before it
+        self.flights
+            .lock()
+            .unwrap()
+            .retain(|_, flight| !flight.tables.iter().any(|table| tables.contains(table)));
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Misses answered by another request's load
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for QueryCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryCache")
+            .field("ttl", &self.ttl)
+            .field("hits", &self.hits())
+            .field("misses", &self.misses())
+            .field("coalesced", &self.coalesced())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cached_until_written() {
+        let cache = QueryCache::new(Duration::from_secs(60));
+        let loads = AtomicU64::new(0);
+        let count = |tenant: &'static str| {
+            cache.get_or_load(&["items"], "items.count", tenant, || async {
+                Ok(loads.fetch_add(1, Ordering::Relaxed))
+            })
+        };
+
+        assert_eq!(count("default").await.unwrap(), 0);
+        assert_eq!(count("default").await.unwrap(), 0);
+        // Other parameters are another entry
+        assert_eq!(count("acme").await.unwrap(), 1);
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+
+        cache.invalidate(&["tags"]);
+        assert_eq!(count("default").await.unwrap(), 0);
+        cache.invalidate(&["items"]);
+        assert_eq!(count("default").await.unwrap(), 2);
+
+        // Failed loads aren't kept
+        let failed: Result<u64, _> = cache
+            .get_or_load(&["items"], "items.broken", (), || async {
+                Err(sqlx::Error::RowNotFound)
+            })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(cache.entries.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_misses_share_a_load() {
+        let cache = QueryCache::new(Duration::from_secs(60));
+        let loads = AtomicU64::new(0);
+        let count = || {
+            cache.get_or_load(&["items"], "items.count", (), || async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Ok(loads.fetch_add(1, Ordering::Relaxed))
+            })
+        };
+        let (a, b, c) = tokio::join!(count(), count(), count());
+        assert_eq!((a.unwrap(), b.unwrap(), c.unwrap()), (0, 0, 0));
+        assert_eq!((cache.misses(), cache.coalesced()), (1, 2));
+        assert!(cache.flights.lock().unwrap().is_empty());
+
+        // A write while loading: later reads don't wait for the old load,
+        // whose result isn't kept
+        cache.invalidate(&["items"]);
+        let (a, b) = tokio::join!(count(), async {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            cache.invalidate(&["items"]);
+            count().await
+        });
+        assert_eq!((a.unwrap(), b.unwrap()), (1, 2));
+        assert_eq!(count().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_or_dropped_load_isnt_shared() {
+        let cache = QueryCache::new(Duration::from_secs(60));
+        let loads = AtomicU64::new(0);
+        let count = || {
+            cache.get_or_load(&["items"], "items.count", (), || async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                match loads.fetch_add(1, Ordering::Relaxed) {
+                    0 => Err(sqlx::Error::RowNotFound),
+                    n => Ok(n),
+                }
+            })
+        };
+        // The first load fails; each waiter runs its own
+        let (a, b, c) = tokio::join!(count(), count(), count());
+        assert!(a.is_err());
+        let (b, c) = (b.unwrap(), c.unwrap());
+        assert_eq!((b.min(c), b.max(c)), (1, 2));
+
+        // The first request goes away mid-load
+        let (dropped, waited) = tokio::join!(
+            tokio::time::timeout(Duration::from_millis(5), count()),
+            count()
+        );
+        assert!(dropped.is_err());
+        assert_eq!(waited.unwrap(), 3);
+        assert_eq!(count().await.unwrap(), 4);
+        assert_eq!(count().await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_and_expired() {
+        let cache = QueryCache::disabled();
+        for expected in [1, 2] {
+            let loaded = cache
+                .get_or_load(&["items"], "items.one", (), || async { Ok(expected) })
+                .await;
+            assert_eq!(loaded.unwrap(), expected);
+        }
+
+        let cache = QueryCache::new(Duration::from_millis(1));
+        let _ = cache
+            .get_or_load(&["items"], "items.one", (), || async { Ok(1) })
+            .await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let loaded = cache
+            .get_or_load(&["items"], "items.one", (), || async { Ok(2) })
+            .await;
+        assert_eq!(loaded.unwrap(), 2);
+    }
+}
@@ -2,106 +2,641 @@
 //!
 //! This module eliminates code duplication by providing macros that generate
 //! both the askama struct (release) and minijinja renderer (debug) from a single definition.
+//!
+//! Both engines are compiled into every build so the snapshot tests can render
+//! each template twice and catch the two drifting apart. Which engine serves a
+//! request is still decided by `debug_assertions`.
+//!
+//! Each macro invocation also registers a [`TemplateSpec`] so debug builds can
+//! check every template against its declared context at startup
+//! (see [`crate::utils::templates::check_template_parity`]).
+//!
+//! Templates render through a generated view that derefs to the declared
+//! struct and adds implicit fields — `globals`
+//! ([`TemplateGlobals`](crate::globals::TemplateGlobals)) everywhere, plus
+//! `meta` ([`PageMeta`]) and `seo` ([`SeoMeta`]) on pages — so handlers only
+//! fill in their own context.
+//! Template functions such as `breadcrumbs()`, `is_online(name)`, `asset(path)` and `favicon_links()` are `fn` fields on that view,
+//! mirrored by minijinja globals in [`crate::utils::templates`].
+//!
+//! Any page renders as a print view with `?view=print`
+//! ([`TemplateGlobals::print_view`](crate::globals::TemplateGlobals::print_view)).
+//! askama fixes the layout at compile time, so the layouts check the flag
+//! themselves: no sidebar, header or breadcrumbs, and every `<details>` open.
+//!
+//! Every render runs in a `render` span carrying the template and engine, and
+//! logs its duration at debug level, so a trace tells template time apart from
+//! the queries around it. [`render_timings`] keeps the totals per template for
+//! the status card.
 
-/// Macro to define a page template that works in both debug and release mode.
-/// - Debug: hot-reloads from disk via minijinja
-/// - Release: compiled into the binary via askama
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::globals;
+
+#[doc(hidden)]
+pub use inventory;
+
+/// Per-page `<head>` metadata, declared once in `define_page!` and rendered
+/// by the shared `components/_head.html` include as `meta`. The head-support
+/// extension merges it into the page on boosted navigation, so the title,
+/// description and feed follow the page without a full load.
+///
+/// Built with const methods so it can live in the macro invocation:
 ///
-/// # Example
 /// ```ignore
-/// define_page!(HomePage, "pages/home.html", { current_page: &'static str });
+/// PageMeta::new("About").description("What this app is")
 /// ```
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PageMeta {
+    /// Layout the page template extends. askama resolves `{% extends %}` at
+    /// compile time, so the template still names it; the startup parity check
+    /// rejects a template that extends a different layout.
+    pub layout: &'static str,
+    /// Page title, suffixed with the site name in `<title>`
+    pub title: &'static str,
+    /// `<meta name="description">` and `og:description`
+    pub description: &'static str,
+    /// `og:type`
+    pub og_type: &'static str,
+    /// `og:image` path (empty = a card generated by `/og.png`)
+    pub og_image: &'static str,
+    /// `twitter:card`: `summary_large_image` or `summary`
+    pub twitter_card: &'static str,
+    /// Atom feed advertised with `<link rel="alternate">` (empty = none).
+    /// Boosted navigation adds and removes it with the page, see
+    /// `static/js/head-support.js`.
+    pub feed: &'static str,
+}
+
+impl PageMeta {
+    /// Metadata for pages that don't declare any
+    pub const DEFAULT: PageMeta = PageMeta::new("Page");
+
+    pub const fn new(title: &'static str) -> Self {
+        Self {
+            layout: "layouts/base.html",
+            title,
+            description: "Hardened Axum + HTMX full-stack web application",
+            og_type: "website",
+            og_image: "",
+            twitter_card: "summary_large_image",
+            feed: "",
+        }
+    }
+
+    pub const fn layout(mut self, layout: &'static str) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub const fn description(mut self, description: &'static str) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub const fn og_type(mut self, og_type: &'static str) -> Self {
+        self.og_type = og_type;
+        self
+    }
+
+    pub const fn og_image(mut self, og_image: &'static str) -> Self {
+        self.og_image = og_image;
+        self
+    }
+
+    pub const fn twitter_card(mut self, twitter_card: &'static str) -> Self {
+        self.twitter_card = twitter_card;
+        self
+    }
+
+    pub const fn feed(mut self, feed: &'static str) -> Self {
+        self.feed = feed;
+        self
+    }
+}
+
+/// A page's [`PageMeta`] resolved for the request being rendered, as the
+/// head tags read it (`seo`). URLs are absolute, built on `[site] base_url`
+/// and the tenant's prefix: `canonical` is the page's own address in its
+/// canonical spelling (see [`crate::utils::urls`]), without the query.
+///
+/// Handlers that know better — a post's title, a draft that shouldn't be
+/// indexed — override it for one response with the `seo` method
+/// `define_page!` adds:
+///
+/// ```ignore
+/// BlogPostPage { post }.seo(|seo| seo.title(&post.title).noindex())
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct SeoMeta {
+    pub title: String,
+    pub description: String,
+    pub canonical: String,
+    pub og_type: String,
+    /// Empty until rendered, then the `/og.png` card unless one was set
+    pub og_image: String,
+    /// `og_image` is the generated card, so its size is known (1200×630)
+    pub og_image_generated: bool,
+    pub twitter_card: String,
+    /// Adds `<meta name="robots" content="noindex">`
+    pub noindex: bool,
+    // `[site] base_url` plus the tenant prefix, for making paths absolute
+    #[serde(skip)]
+    base_url: String,
+}
+
+impl SeoMeta {
+    /// `meta` for the current request
+    pub fn new(meta: &PageMeta) -> Self {
+        let (base_url, canonical_path) = globals::page_urls();
+        let seo = Self {
+            title: meta.title.to_string(),
+            description: meta.description.to_string(),
+            canonical: format!("{base_url}{canonical_path}"),
+            og_type: meta.og_type.to_string(),
+            og_image: String::new(),
+            og_image_generated: false,
+            twitter_card: meta.twitter_card.to_string(),
+            noindex: false,
+            base_url,
+        };
+        if meta.og_image.is_empty() {
+            seo
+        } else {
+            seo.og_image(meta.og_image)
+        }
+    }
+
+    /// `path` on this site, or an absolute URL as is
+    fn absolute(&self, path: &str) -> String {
+        if path.starts_with("https://") || path.starts_with("http://") {
+            path.to_string()
+        } else {
+            format!("{}{path}", self.base_url)
+        }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// A path (`/blog?page=2`) or an absolute URL
+    pub fn canonical(mut self, path: &str) -> Self {
+        self.canonical = self.absolute(path);
+        self
+    }
+
+    pub fn og_type(mut self, og_type: impl Into<String>) -> Self {
+        self.og_type = og_type.into();
+        self
+    }
+
+    /// A path or an absolute URL
+    pub fn og_image(mut self, path: &str) -> Self {
+        self.og_image = self.absolute(path);
+        self.og_image_generated = false;
+        self
+    }
+
+    pub fn twitter_card(mut self, twitter_card: impl Into<String>) -> Self {
+        self.twitter_card = twitter_card.into();
+        self
+    }
+
+    pub fn noindex(mut self) -> Self {
+        self.noindex = true;
+        self
+    }
+
+    /// What a page rendered now gets: the response's override if there is
+    /// one (see [`WithSeo`]), else `meta`, with the card filled in
+    pub fn resolve(meta: &PageMeta) -> Self {
+        let mut seo = SEO_OVERRIDE
+            .with(|current| current.borrow().clone())
+            .unwrap_or_else(|| Self::new(meta));
+        if seo.og_image.is_empty() {
+            let query = serde_urlencoded::to_string([
+                ("title", seo.title.as_str()),
+                ("description", seo.description.as_str()),
+            ])
+            .unwrap_or_default();
+            seo.og_image = seo.absolute(&format!("/og.png?{query}"));
+            seo.og_image_generated = true;
+        }
+        seo
+    }
+}
+
+thread_local! {
+    // Rendering is synchronous, so the override only has to outlive one call
+    static SEO_OVERRIDE: RefCell<Option<SeoMeta>> = const { RefCell::new(None) };
+}
+
+fn with_seo<R>(seo: &SeoMeta, render: impl FnOnce() -> R) -> R {
+    let previous = SEO_OVERRIDE.with(|current| current.replace(Some(seo.clone())));
+    let rendered = render();
+    SEO_OVERRIDE.with(|current| *current.borrow_mut() = previous);
+    rendered
+}
+
+/// What `define_page!` implements, so [`WithSeo`] can render any page
+pub trait Page {
+    const META: PageMeta;
+
+    fn render_askama(&self) -> Result<String, String>;
+    fn render_minijinja(&self) -> Result<String, String>;
+    fn render_response(self) -> axum::response::Html<String>;
+}
+
+/// A page rendered with its head metadata overridden, from the `seo`
+/// method `define_page!` adds
+pub struct WithSeo<P> {
+    pub page: P,
+    pub seo: SeoMeta,
+}
+
+impl<P: Page> WithSeo<P> {
+    pub fn render_askama(&self) -> Result<String, String> {
+        with_seo(&self.seo, || self.page.render_askama())
+    }
+
+    pub fn render_minijinja(&self) -> Result<String, String> {
+        with_seo(&self.seo, || self.page.render_minijinja())
+    }
+}
+
+impl<P: Page> axum::response::IntoResponse for WithSeo<P> {
+    fn into_response(self) -> axum::response::Response {
+        let Self { page, seo } = self;
+        with_seo(&seo, || page.render_response()).into_response()
+    }
+}
+
+/// One template's renders with one engine, since startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderTiming {
+    pub renders: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl RenderTiming {
+    pub fn mean(&self) -> Duration {
+        match self.renders {
+            0 => Duration::ZERO,
+            renders => Duration::from_nanos((self.total.as_nanos() / renders as u128) as u64),
+        }
+    }
+}
+
+// Keyed by template path, then engine
+static RENDER_TIMINGS: Mutex<BTreeMap<(&str, &str), RenderTiming>> = Mutex::new(BTreeMap::new());
+
+/// Run one render of `path` with `engine` in a `render` span, and log and
+/// record how long it took
+#[doc(hidden)]
+pub fn timed<R>(path: &'static str, engine: &'static str, render: impl FnOnce() -> R) -> R {
+    let span = tracing::debug_span!("render", template = path, engine);
+    let _entered = span.enter();
+    let started = Instant::now();
+    let rendered = render();
+    let elapsed = started.elapsed();
+    tracing::debug!(
+        duration_us = elapsed.as_micros() as u64,
+        "template rendered"
+    );
+
+    let mut timings = RENDER_TIMINGS.lock().unwrap();
+    let timing = timings.entry((path, engine)).or_default();
+    timing.renders += 1;
+    timing.total += elapsed;
+    timing.max = timing.max.max(elapsed);
+    rendered
+}
+
+/// Template path, engine and figures for everything rendered since startup,
+/// slowest mean first
+pub fn render_timings() -> Vec<(&'static str, &'static str, RenderTiming)> {
+    let mut timings: Vec<_> = RENDER_TIMINGS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(&(path, engine), timing)| (path, engine, *timing))
+        .collect();
+    timings.sort_by_key(|(_, _, timing)| std::cmp::Reverse(timing.mean()));
+    timings
+}
+
+/// Whether a template is a full page or an HTMX fragment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateKind {
+    /// Full document, extends a layout
+    Page,
+    /// Fragment swapped in by HTMX, never extends a layout
+    Partial,
+}
+
+/// A `define_page!` / `define_partial!` declaration, collected at link time
+pub struct TemplateSpec {
+    /// Rust struct name
+    pub name: &'static str,
+    /// Template path relative to `templates/`
+    pub path: &'static str,
+    /// Declared context fields
+    pub fields: &'static [&'static str],
+    /// Implicit fields the macro adds (`globals`, `meta`)
+    pub implicit: &'static [&'static str],
+    pub kind: TemplateKind,
+    /// Layout the template must extend (pages only)
+    pub layout: Option<&'static str>,
+}
+
+inventory::collect!(TemplateSpec);
+
+/// Every template declared through the macros, in no particular order
+pub fn registered_templates() -> impl Iterator<Item = &'static TemplateSpec> {
+    inventory::iter::<TemplateSpec>.into_iter()
+}
+
+/// Shared core of `define_page!` and `define_partial!`: the context struct,
+/// its registry entry, and one render method per engine.
+///
+/// `globals` and any `$extra` fields are implicit context added to every
+/// render. askama sees them on a private view struct that derefs to `$name`,
+/// so template variables resolve to either without the handler filling them in.
+#[doc(hidden)]
 #[macro_export]
-macro_rules! define_page {
-    ($name:ident, $path:literal, { $($field:ident : $ty:ty),* $(,)? }) => {
-        // Release: compiled askama template
-        #[cfg(not(debug_assertions))]
-        #[derive(askama::Template)]
-        #[template(path = $path)]
+macro_rules! __define_template {
+    (
+        $kind:ident, $name:ident, $path:literal, layout: $layout:expr,
+        { $($field:ident : $ty:ty),* $(,)? },
+        [ $($extra:ident : $extra_ty:ty = $extra_val:expr),* $(,)? ]
+    ) => {
         pub struct $name {
             $(pub $field: $ty,)*
         }
 
-        // Debug: runtime rendering struct (matches askama struct shape)
-        #[cfg(debug_assertions)]
-        pub struct $name {
-            $(pub $field: $ty,)*
+        $crate::render::inventory::submit! {
+            $crate::render::TemplateSpec {
+                name: stringify!($name),
+                path: $path,
+                fields: &[$(stringify!($field)),*],
+                implicit: &["globals", $(stringify!($extra)),*],
+                kind: $crate::render::TemplateKind::$kind,
+                layout: $layout,
+            }
         }
 
         impl $name {
-            pub fn render_response(self) -> axum::response::Html<String> {
+            /// Render with the compiled askama template
+            pub fn render_askama(&self) -> Result<String, String> {
+                // Implicit fields are unused by templates that don't read them
+                #[allow(dead_code)]
+                #[derive(askama::Template)]
+                #[template(path = $path)]
+                struct View<'a> {
+                    context: &'a $name,
+                    globals: $crate::globals::TemplateGlobals,
+                    // askama calls `breadcrumbs()` as `(&self.breadcrumbs)()`
+                    breadcrumbs: fn() -> Vec<$crate::services::breadcrumbs::Breadcrumb>,
+                    is_online: fn(&str) -> bool,
+                    asset: fn(&str) -> String,
+                    favicon_links: fn() -> String,
+                    $($extra: $extra_ty,)*
+                }
+
+                impl std::ops::Deref for View<'_> {
+                    type Target = $name;
+
+                    fn deref(&self) -> &$name {
+                        self.context
+                    }
+                }
+
+                $crate::render::timed($path, "askama", || {
+                    let view = View {
+                        context: self,
+                        globals: $crate::globals::TemplateGlobals::current_for($crate::render::TemplateKind::$kind),
+                        breadcrumbs: $crate::globals::breadcrumbs,
+                        is_online: $crate::globals::is_online,
+                        asset: $crate::utils::assets::asset,
+                        favicon_links: $crate::utils::favicons::links,
+                        $($extra: $extra_val,)*
+                    };
+                    askama::Template::render(&view).map_err(|e| e.to_string())
+                })
+            }
+
+            /// Render with minijinja, loading the template from disk
+            pub fn render_minijinja(&self) -> Result<String, String> {
+                $crate::render::timed($path, "minijinja", || {
+                    let ctx = serde_json::json!({
+                        $(stringify!($field): &self.$field,)*
+                        "globals": $crate::globals::TemplateGlobals::current_for($crate::render::TemplateKind::$kind),
+                        $(stringify!($extra): $extra_val,)*
+                    });
+                    $crate::utils::templates::render_template($path, ctx)
+                })
+            }
+
+            /// Render with the engine for this build
+            fn render_active(&self) -> Result<String, String> {
                 #[cfg(not(debug_assertions))]
                 {
-                    use askama::Template;
-                    axum::response::Html(self.render().unwrap_or_else(|e| {
-                        format!("<h1>Template Error</h1><pre>{}</pre>", e)
-                    }))
+                    self.render_askama()
                 }
 
                 #[cfg(debug_assertions)]
                 {
-                    use $crate::utils::templates::render_template;
-                    use serde_json::json;
-
-                    let ctx = json!({ $(stringify!($field): self.$field,)* });
-                    match render_template($path, ctx) {
-                        Ok(html) => axum::response::Html(html),
-                        Err(e) => axum::response::Html(format!(
-                            r#"<html><body style="font-family:monospace;padding:2rem">
-                            <h1 style="color:#ef4444">Template Error</h1>
-                            <pre style="background:#1e1e1e;color:#f8f8f2;padding:1rem;border-radius:8px;overflow-x:auto">{}</pre>
-                            <p>Fix the template and refresh.</p>
-                            </body></html>"#,
-                            e
-                        )),
-                    }
+                    self.render_minijinja()
                 }
             }
         }
     };
 }
 
-/// Macro to define a partial template (HTML fragment for HTMX).
-/// Same dual-mode behavior as define_page.
+/// Macro to define a page template that works in both debug and release mode.
+/// - Debug: hot-reloads from disk via minijinja
+/// - Release: compiled into the binary via askama
+///
+/// An optional trailing [`PageMeta`] sets the layout, title, description and
+/// Open Graph fields; the template reads it as `meta`, and resolved for the
+/// request (absolute canonical and image URLs) as `seo`. Without one the page
+/// gets [`PageMeta::DEFAULT`]. `.seo(|seo| ..)` on a page overrides the
+/// resolved metadata for one response.
+///
+/// # Example
+/// ```ignore
+/// define_page!(HomePage, "pages/home.html", {});
+///
+/// define_page!(ItemPage, "pages/item.html", {
+///     item: Item,
+/// }, PageMeta::new("Item").description("A single item"));
+/// ```
 #[macro_export]
-macro_rules! define_partial {
-    ($name:ident, $path:literal, { $($field:ident : $ty:ty),* $(,)? }) => {
-        #[cfg(not(debug_assertions))]
-        #[derive(askama::Template)]
-        #[template(path = $path)]
-        pub struct $name {
-            $(pub $field: $ty,)*
+macro_rules! define_page {
+    ($name:ident, $path:literal, { $($field:ident : $ty:ty),* $(,)? } $(,)?) => {
+        $crate::define_page!($name, $path, { $($field : $ty),* }, $crate::render::PageMeta::DEFAULT);
+    };
+    ($name:ident, $path:literal, { $($field:ident : $ty:ty),* $(,)? }, $meta:expr $(,)?) => {
+        $crate::__define_template!(
+            Page, $name, $path, layout: Some($name::META.layout),
+            { $($field : $ty),* },
+            [
+                meta: &'static $crate::render::PageMeta = &$name::META,
+                seo: $crate::render::SeoMeta = $crate::render::SeoMeta::resolve(&$name::META),
+            ]
+        );
+
+        impl $name {
+            /// Head metadata rendered into the layout
+            pub const META: $crate::render::PageMeta = $meta;
+
+            /// Render with the head metadata adjusted for this response, e.g.
+            /// a record's title, see [`SeoMeta`]($crate::render::SeoMeta)
+            pub fn seo(
+                self,
+                adjust: impl FnOnce($crate::render::SeoMeta) -> $crate::render::SeoMeta,
+            ) -> $crate::render::WithSeo<Self> {
+                $crate::render::WithSeo {
+                    seo: adjust($crate::render::SeoMeta::new(&Self::META)),
+                    page: self,
+                }
+            }
+
+            pub fn render_response(self) -> axum::response::Html<String> {
+                match self.render_active() {
+                    Ok(html) => axum::response::Html(html),
+                    #[cfg(not(debug_assertions))]
+                    Err(e) => $crate::utils::html::HtmlFragment::new()
+                        .raw("<h1>Template Error</h1><pre>")
+                        .text(e)
+                        .raw("</pre>")
+                        .into_html(),
+                    #[cfg(debug_assertions)]
+                    Err(e) => $crate::utils::html::HtmlFragment::new()
+                        .raw(r#"<html><body style="font-family:monospace;padding:2rem">
+                        <h1 style="color:#ef4444">Template Error</h1>
+                        <pre style="background:#1e1e1e;color:#f8f8f2;padding:1rem;border-radius:8px;overflow-x:auto">"#)
+                        .text(e)
+                        .raw(r#"</pre>
+                        <p>Fix the template and refresh.</p>
+                        </body></html>"#)
+                        .into_html(),
+                }
+            }
         }
 
-        #[cfg(debug_assertions)]
-        pub struct $name {
-            $(pub $field: $ty,)*
+        impl $crate::render::Page for $name {
+            const META: $crate::render::PageMeta = $name::META;
+
+            fn render_askama(&self) -> Result<String, String> {
+                $name::render_askama(self)
+            }
+
+            fn render_minijinja(&self) -> Result<String, String> {
+                $name::render_minijinja(self)
+            }
+
+            fn render_response(self) -> axum::response::Html<String> {
+                $name::render_response(self)
+            }
+        }
+
+        impl axum::response::IntoResponse for $name {
+            fn into_response(self) -> axum::response::Response {
+                self.render_response().into_response()
+            }
         }
+    };
+}
+
+/// Macro to define a partial template (HTML fragment for HTMX).
+/// Same dual-mode behavior as define_page, but the template is rendered on
+/// its own — no layout — and errors come back as an inline alert fragment.
+///
+/// The struct implements `IntoResponse`, so handlers can return it directly:
+///
+/// ```ignore
+/// define_partial!(Widget, "partials/widget.html", { label: String });
+///
+/// pub async fn widget() -> Widget {
+///     Widget { label: "hello".into() }
+/// }
+/// ```
+#[macro_export]
+macro_rules! define_partial {
+    ($name:ident, $path:literal, { $($field:ident : $ty:ty),* $(,)? }) => {
+        $crate::__define_template!(Partial, $name, $path, layout: None, { $($field : $ty),* }, []);
 
         impl $name {
-            pub fn render_response(self) -> axum::response::Html<String> {
-                #[cfg(not(debug_assertions))]
-                {
-                    use askama::Template;
-                    axum::response::Html(self.render().unwrap_or_else(|e| {
-                        format!(r#"<div class="alert alert-danger">Template error: {}</div>"#, e)
-                    }))
+            /// Render just this fragment, skipping any layout
+            pub fn render_fragment(self) -> axum::response::Html<String> {
+                match self.render_active() {
+                    Ok(html) => axum::response::Html(html),
+                    #[cfg(not(debug_assertions))]
+                    Err(e) => $crate::utils::html::HtmlFragment::new()
+                        .raw(r#"<div class="alert alert-danger">Template error: "#)
+                        .text(e)
+                        .raw("</div>")
+                        .into_html(),
+                    #[cfg(debug_assertions)]
+                    Err(e) => $crate::utils::html::HtmlFragment::new()
+                        .raw(r#"<div class="alert alert-danger"><strong>Template Error:</strong> "#)
+                        .text(e)
+                        .raw("</div>")
+                        .into_html(),
                 }
+            }
 
-                #[cfg(debug_assertions)]
-                {
-                    use $crate::utils::templates::render_template;
-                    use serde_json::json;
-
-                    let ctx = json!({ $(stringify!($field): self.$field,)* });
-                    match render_template($path, ctx) {
-                        Ok(html) => axum::response::Html(html),
-                        Err(e) => axum::response::Html(format!(
-                            r#"<div class="alert alert-danger"><strong>Template Error:</strong> {}</div>"#, e
-                        )),
-                    }
-                }
+            pub fn render_response(self) -> axum::response::Html<String> {
+                self.render_fragment()
+            }
+        }
+
+        impl axum::response::IntoResponse for $name {
+            fn into_response(self) -> axum::response::Response {
+                self.render_fragment().into_response()
             }
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timed_records_per_template_and_engine() {
+        for _ in 0..3 {
+            timed("test/timed.html", "askama", || ());
+        }
+        let rendered = timed("test/timed.html", "minijinja", || "html");
+        assert_eq!(rendered, "html");
+
+        let timings = render_timings();
+        let timing = |engine: &str| {
+            timings
+                .iter()
+                .find(|(path, e, _)| *path == "test/timed.html" && *e == engine)
+                .map(|(_, _, timing)| *timing)
+                .unwrap()
+        };
+        assert_eq!(timing("askama").renders, 3);
+        assert_eq!(timing("minijinja").renders, 1);
+        assert!(timing("askama").max <= timing("askama").total);
+        assert!(timings.windows(2).all(|w| w[0].2.mean() >= w[1].2.mean()));
+
+        assert_eq!(RenderTiming::default().mean(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,436 @@
+//! Routes — every path the app answers, and its handler
+//!
+//! No JSON API. No Swagger. No CORS. Every route returns HTML — full pages
+//! or HTMX partials — apart from feeds, crawler documents, downloads and
+//! event streams. `main` wraps [`router`] in the dev live-reload routes and
+//! [`middleware::stack`](crate::middleware::stack); tests drive it the same
+//! way.
+//!
+//! [`router`] is a [`HostRouter`]: one [`Router`] per kind of host (see
+//! [`HostKind`]). With `[admin] host` set, only that host has the admin
+//! routes, and tenant subdomains never do — admin pages belong to the
+//! deployment, not a tenant.
+
+use axum::{
+    extract::Request,
+    middleware,
+    response::Response,
+    routing::{delete, future::RouteFuture, get, get_service, post, put},
+    Router,
+};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::Service;
+use tower_http::services::ServeDir;
+
+use crate::config::AppConfig;
+use crate::handlers::{
+    activity, admin, avatar, blog, calendar, cart, comments, contact, documents, embed, feeds,
+    items, newsletter, not_found, notifications, og, partials, payments, presence, pwa,
+    request_log, seo, share, templates, webhooks, well_known,
+};
+use crate::middleware as mw;
+use crate::models::AppState;
+use crate::services::api_tokens::Scope;
+use crate::utils::hosts::{HostInfo, HostKind};
+
+/// The app's routes, with `state`, dispatched by host
+pub fn router(state: Arc<AppState>) -> HostRouter {
+    let with_admin = finish(site_routes().merge(admin_routes()), &state);
+    let without_admin = finish(site_routes(), &state);
+    let site = if state.config.admin.host.is_empty() {
+        with_admin.clone()
+    } else {
+        without_admin.clone()
+    };
+    HostRouter::new(&state.config, site)
+        .admin(with_admin)
+        .tenant(without_admin)
+}
+
+/// Everything but the admin routes
+fn site_routes() -> Router<Arc<AppState>> {
+    // HTMX partial routes (HTML fragments)
+    // Polled partials are guarded against runaway `hx-trigger="every Ns"`
+    let polled = || middleware::from_fn(mw::poll_limit);
+    // Feeds and exports also take API tokens, see mw::api_token
+    let token = |scope| middleware::from_fn_with_state(scope, mw::api_token);
+    let partial_routes = Router::new()
+        .route(
+            "/partials/status-card",
+            get(partials::status_card)
+                .route_layer(middleware::from_fn(mw::diff_fragments))
+                .route_layer(polled()),
+        )
+        .route("/partials/item-list", get(partials::item_list))
+        .route(
+            "/partials/item-list/changes",
+            get(partials::item_list_changes),
+        )
+        .route("/items", post(partials::create_item))
+        .route("/items/reorder", post(partials::reorder_items))
+        .route("/partials/sortable-items", get(partials::sortable_items))
+        .route(
+            "/items/feed.xml",
+            get(feeds::items_feed).route_layer(token(Scope::Feeds)),
+        )
+        .route(
+            "/items/export.csv",
+            get(partials::export_items).route_layer(token(Scope::Exports)),
+        )
+        .route("/items/:slug", get(items::item_page))
+        .route("/items/:slug/rename", post(items::rename_item))
+        .route("/partials/greeting", get(partials::greeting))
+        .route("/partials/shortcuts", get(partials::shortcuts))
+        .route("/partials/share", get(share::share_form))
+        .route("/share", post(share::create_share))
+        .route("/s/:token", get(share::open_share))
+        .route("/partials/autocomplete", get(partials::autocomplete))
+        .route("/partials/demo-tab/:tab", get(partials::demo_tab))
+        .route("/partials/progress", get(partials::progress))
+        .route(
+            "/partials/morph-demo",
+            get(partials::morph_demo).route_layer(polled()),
+        )
+        .route(
+            "/partials/online-users",
+            get(presence::online_users).route_layer(polled()),
+        );
+
+    // Comments demo — writes are CSRF-checked by the middleware stack
+    let comment_routes = Router::new()
+        .route("/partials/comments", get(comments::comment_thread))
+        .route("/comments", post(comments::create_comment))
+        .route(
+            "/comments/:id",
+            get(comments::show_comment)
+                .put(comments::update_comment)
+                .delete(comments::delete_comment),
+        )
+        .route("/comments/:id/edit", get(comments::edit_comment));
+
+    // Shopping cart demo — kept in the session, badge updated out-of-band
+    let cart_routes = Router::new()
+        .route("/partials/cart", get(cart::cart_partial))
+        .route("/cart/add", post(cart::add_to_cart))
+        .route("/cart/:product/quantity", post(cart::set_quantity))
+        .route("/cart/:product/remove", post(cart::remove_from_cart))
+        .route("/cart/checkout", post(cart::checkout));
+
+    // Payments demo — intent, confirm, status polled until it settles
+    let payment_routes = Router::new()
+        .route("/pay", get(payments::pay_page))
+        .route("/payments", post(payments::create_payment))
+        .route("/payments/:id/confirm", post(payments::confirm_payment))
+        .route(
+            "/partials/payments/:id",
+            get(payments::payment_status).route_layer(polled()),
+        );
+
+    // Notification center — bell, dropdown, mark-as-read and the live stream
+    let notification_routes = Router::new()
+        .route(
+            "/partials/notifications",
+            get(notifications::notification_list),
+        )
+        .route(
+            "/partials/notifications/bell",
+            get(notifications::notification_bell),
+        )
+        .route("/notifications/read", post(notifications::mark_all_read))
+        .route("/notifications/:id/read", post(notifications::mark_read))
+        .route("/notifications/demo", post(notifications::send_demo))
+        .route(
+            "/notifications/stream",
+            get(notifications::notification_stream),
+        );
+
+    // Blog — public pages and the Atom feed
+    let blog_routes = Router::new()
+        .route("/newsletter", post(newsletter::subscribe))
+        .route("/newsletter/confirm", get(newsletter::confirm))
+        .route(
+            "/newsletter/unsubscribe",
+            get(newsletter::unsubscribe_page).post(newsletter::unsubscribe),
+        )
+        .route("/blog", get(blog::blog_index))
+        .route(
+            "/blog/feed.xml",
+            get(feeds::posts_feed).route_layer(token(Scope::Feeds)),
+        )
+        .route("/blog/:slug", get(blog::blog_post));
+
+    // Health check (no middleware — used by Docker HEALTHCHECK)
+    let health_route = Router::new().route("/healthz", get(crate::handlers::healthz));
+
+    // Prometheus metrics — only on listeners with `metrics = true`
+    let metrics_route = Router::new().route(
+        "/metrics",
+        get(crate::handlers::metrics::metrics)
+            .route_layer(middleware::from_fn(mw::metrics_listener)),
+    );
+
+    // Build information — more of it for admins
+    let version_route = Router::new().route("/version", get(crate::handlers::version));
+
+    // Crawler documents, built from sitemap_route! annotations, link preview
+    // images and identicons
+    let seo_routes = Router::new()
+        .route("/robots.txt", get(seo::robots_txt))
+        .route("/sitemap.xml", get(seo::sitemap_xml))
+        .route("/og.png", get(og::og_image))
+        .route("/avatar/:file", get(avatar::avatar));
+
+    // Installable app: manifest, service worker and the page it shows offline
+    let pwa_routes = Router::new()
+        .route("/manifest.webmanifest", get(pwa::manifest))
+        .route("/sw.js", get(pwa::service_worker))
+        .route(pwa::OFFLINE_PATH, get(pwa::offline_page));
+
+    // Read-only widgets for other sites' iframes, see handlers::embed
+    let embed_routes = Router::new().route("/embed/:widget", get(embed::embed));
+
+    // Signed deliveries from other services — CSRF-exempt, see handlers::webhooks
+    let webhook_routes = Router::new().route("/webhooks/:provider", post(webhooks::receive));
+
+    // Calendar — month view, its grid partial, the date picker and the iCalendar feed
+    let calendar_routes = Router::new()
+        .route("/calendar", get(calendar::calendar_page))
+        .route("/partials/calendar", get(calendar::calendar_partial))
+        .route("/partials/datepicker", get(calendar::date_picker_partial))
+        .route(
+            "/calendar.ics",
+            get(calendar::calendar_ics).route_layer(token(Scope::Feeds)),
+        );
+
+    // Printable documents; the PDF downloads only exist with the `pdf` feature
+    let document_routes = Router::new()
+        .route("/documents/invoice", get(documents::invoice_page))
+        .route("/documents/report", get(documents::report_page))
+        .route("/invoices/:id", get(documents::stored_invoice_page));
+    #[cfg(feature = "pdf")]
+    let document_routes = document_routes
+        .route("/documents/invoice.pdf", get(documents::invoice_pdf))
+        .route("/documents/report.pdf", get(documents::report_pdf))
+        .route("/invoices/:id/pdf", get(documents::stored_invoice_pdf));
+
+    // Page routes (full HTML)
+    Router::new()
+        .route("/", get(templates::home_page))
+        .route("/about", get(templates::about_page))
+        .route("/demo", get(templates::demo_page))
+        .route("/components", get(templates::components_page))
+        .route("/security", get(templates::security_page))
+        .route(
+            "/contact",
+            get(contact::contact_page).post(contact::send_contact),
+        )
+        .merge(partial_routes)
+        .merge(comment_routes)
+        .merge(cart_routes)
+        .merge(payment_routes)
+        .merge(notification_routes)
+        .merge(blog_routes)
+        .merge(calendar_routes)
+        .merge(document_routes)
+        .merge(health_route)
+        .merge(metrics_route)
+        .merge(version_route)
+        .merge(seo_routes)
+        .merge(pwa_routes)
+        .merge(embed_routes)
+        .merge(webhook_routes)
+        .merge(well_known::router())
+}
+
+/// Admin pages — everything but sign-in requires an admin session
+fn admin_routes() -> Router<Arc<AppState>> {
+    let polled = || middleware::from_fn(mw::poll_limit);
+    Router::new()
+        .route(
+            "/admin/posts",
+            get(admin::posts_page).post(admin::create_post),
+        )
+        .route("/admin/posts/new", get(admin::new_post_page))
+        .route("/admin/posts/preview", post(admin::preview_post))
+        .route(
+            "/admin/posts/:id",
+            put(admin::update_post).delete(admin::delete_post),
+        )
+        .route("/admin/posts/:id/edit", get(admin::edit_post_page))
+        .route("/admin/logout", post(admin::logout))
+        .route("/admin/activity", get(activity::activity_page))
+        .route("/admin/activity/stream", get(activity::activity_stream))
+        .route("/partials/activity-feed", get(activity::activity_feed))
+        .route("/admin/webhooks", get(admin::webhooks_page))
+        .route(
+            "/admin/webhooks/deliveries",
+            get(admin::webhook_deliveries).route_layer(polled()),
+        )
+        .route(
+            "/admin/not-found",
+            get(not_found::report_page).delete(not_found::clear_report),
+        )
+        .route(
+            "/admin/not-found/report",
+            get(not_found::report).route_layer(polled()),
+        )
+        .route(
+            "/admin/redirects",
+            get(admin::redirects_page).post(admin::save_redirect),
+        )
+        .route("/admin/redirects/:id", delete(admin::delete_redirect))
+        .route(
+            "/admin/tokens",
+            get(admin::tokens_page).post(admin::create_token),
+        )
+        .route("/admin/tokens/:id", delete(admin::revoke_token))
+        .route("/admin/requests", get(request_log::requests_page))
+        .route(
+            "/admin/requests/log",
+            get(request_log::request_log).route_layer(polled()),
+        )
+        .route("/admin/config", get(admin::config_page))
+        .route("/admin/config/reload", post(admin::reload_config))
+        .route("/admin/read-only", post(admin::set_read_only))
+        .route(
+            "/admin/backups",
+            get(admin::backups_page).post(admin::create_backup),
+        )
+        .route(
+            "/admin/logging",
+            get(admin::log_level)
+                .post(admin::set_log_level)
+                .delete(admin::reset_log_level),
+        )
+        .route_layer(middleware::from_fn(mw::require_admin))
+        .route("/admin/login", get(admin::login_page).post(admin::login))
+        // An admin's session or an API token for exports; the token is
+        // checked first so `require_admin` can let it through
+        .route(
+            "/admin/subscribers.csv",
+            get(newsletter::export_subscribers)
+                .route_layer(middleware::from_fn(mw::require_admin))
+                .route_layer(middleware::from_fn_with_state(
+                    Scope::Exports,
+                    mw::api_token,
+                )),
+        )
+        .route("/backups/download", get(admin::download_backup))
+        .route_layer(middleware::from_fn(mw::admin_listener))
+}
+
+/// `routes` plus static files and the fallbacks, with `state`
+fn finish(routes: Router<Arc<AppState>>, state: &Arc<AppState>) -> Router {
+    // Cross-origin headers per kind of file, see `[static_headers]`
+    let static_headers = || middleware::from_fn_with_state(state.clone(), mw::static_headers);
+    routes
+        // Static files (vendored CSS, JS, fonts — no external CDN); the CSS
+        // bundle is built at startup and the favicons at build time, both
+        // served from memory
+        .route(
+            "/static/dist/:file",
+            get(crate::utils::css::serve).layer(static_headers()),
+        )
+        .route(
+            "/static/icons/:file",
+            get(crate::utils::favicons::serve).layer(static_headers()),
+        )
+        .route("/favicon.ico", get(crate::utils::favicons::favicon_ico))
+        .nest_service(
+            "/static",
+            get_service(ServeDir::new("static"))
+                .fallback(crate::handlers::method_not_allowed)
+                .layer(static_headers()),
+        )
+        // `OPTIONS`, and `405` with the error fragment, for every route above
+        .method_not_allowed_fallback(crate::handlers::method_not_allowed)
+        // Anything else: the 404 page, with suggestions
+        .fallback(not_found::not_found)
+        .with_state(state.clone())
+}
+
+// ============================================================================
+// Host dispatch
+// ============================================================================
+
+/// Routes a request by its host (see [`HostInfo`]) to the [`Router`] for
+/// that kind of host, the site's unless another was given, and makes the
+/// [`HostInfo`] available to handlers. A plain [`Router`] converts into one
+/// that serves every host.
+#[derive(Clone)]
+pub struct HostRouter {
+    config: Arc<AppConfig>,
+    site: Router,
+    admin: Option<Router>,
+    tenant: Option<Router>,
+}
+
+impl HostRouter {
+    /// `site` serves every host until [`admin`](Self::admin) or
+    /// [`tenant`](Self::tenant) takes some over. Hosts are told apart by
+    /// `[admin] host` and `[tenancy]`.
+    pub fn new(config: &AppConfig, site: Router) -> Self {
+        Self {
+            config: Arc::new(config.clone()),
+            site,
+            admin: None,
+            tenant: None,
+        }
+    }
+
+    /// Serve `[admin] host` with `router`
+    pub fn admin(mut self, router: Router) -> Self {
+        self.admin = Some(router);
+        self
+    }
+
+    /// Serve tenant subdomains with `router`
+    pub fn tenant(mut self, router: Router) -> Self {
+        self.tenant = Some(router);
+        self
+    }
+
+    /// Change every host's router the same way, e.g. to add layers
+    pub fn map(self, mut f: impl FnMut(Router) -> Router) -> Self {
+        Self {
+            config: self.config,
+            site: f(self.site),
+            admin: self.admin.map(&mut f),
+            tenant: self.tenant.map(&mut f),
+        }
+    }
+
+    fn router_for(&mut self, kind: HostKind) -> &mut Router {
+        let router = match kind {
+            HostKind::Site => None,
+            HostKind::Admin => self.admin.as_mut(),
+            HostKind::Tenant => self.tenant.as_mut(),
+        };
+        router.unwrap_or(&mut self.site)
+    }
+}
+
+impl From<Router> for HostRouter {
+    fn from(router: Router) -> Self {
+        Self::new(&AppConfig::default(), router)
+    }
+}
+
+impl Service<Request> for HostRouter {
+    type Response = Response;
+    type Error = Infallible;
+    type Future = RouteFuture<Infallible>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        let host = HostInfo::from_request(request.headers(), request.uri(), &self.config);
+        let router = self.router_for(host.kind);
+        request.extensions_mut().insert(host);
+        router.call(request)
+    }
+}
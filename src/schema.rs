@@ -0,0 +1,308 @@
+//! Schema checks — refuse to run against a database this build doesn't fit
+//!
+//! Before migrating, [`check_migrations`] compares the migrations recorded in
+//! `_sqlx_migrations` with the ones embedded in the binary: one applied that
+//! this build doesn't know (the database was migrated by a newer version),
+//! one edited since it was applied, or one that failed partway. After
+//! migrating, [`check_tables`] confirms every table and column in
+//! [`ESSENTIAL`] exists, which catches hand edits and half-restored files the
+//! migration table can't show. Either way [`SchemaDrift`] lists every
+//! problem and [`crate::db::connect`] stops startup, rather than the app
+//! failing on its first query.
+
+use sqlx::migrate::Migrator;
+use sqlx::SqlitePool;
+use std::fmt;
+
+/// The migrations in `migrations/`, embedded at build time
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Tables and the columns the stores query, as of the latest migration.
+/// Extend this with each migration that adds a table or column.
+pub const ESSENTIAL: &[(&str, &[&str])] = &[
+    (
+        "items",
+        &[
+            "id",
+            "title",
+            "description",
+            "done",
+            "created_at",
+            "tenant",
+            "position",
+            "slug",
+        ],
+    ),
+    ("tags", &["id", "name"]),
+    ("item_tags", &["item_id", "tag_id"]),
+    (
+        "comments",
+        &[
+            "id",
+            "parent_id",
+            "author",
+            "body",
+            "owner",
+            "created_at",
+            "updated_at",
+            "deleted",
+            "tenant",
+        ],
+    ),
+    (
+        "posts",
+        &[
+            "id",
+            "slug",
+            "title",
+            "summary",
+            "body",
+            "status",
+            "created_at",
+            "updated_at",
+            "published_at",
+        ],
+    ),
+    (
+        "subscribers",
+        &["id", "email", "status", "created_at", "confirmed_at"],
+    ),
+    (
+        "webhook_events",
+        &[
+            "id",
+            "provider",
+            "event_id",
+            "event_type",
+            "payload",
+            "status",
+            "received_at",
+        ],
+    ),
+    (
+        "events",
+        &[
+            "id",
+            "title",
+            "description",
+            "location",
+            "starts_at",
+            "ends_at",
+            "all_day",
+        ],
+    ),
+    (
+        "notifications",
+        &[
+            "id",
+            "recipient",
+            "title",
+            "body",
+            "link",
+            "created_at",
+            "read_at",
+        ],
+    ),
+    (
+        "activities",
+        &["id", "kind", "actor", "summary", "link", "created_at"],
+    ),
+    ("presence", &["key", "name", "last_seen"]),
+    ("slug_history", &["kind", "scope", "slug", "target_id"]),
+    (
+        "invoices",
+        &["id", "owner", "bill_to", "issued", "due", "tax_rate"],
+    ),
+    (
+        "invoice_lines",
+        &[
+            "invoice_id",
+            "position",
+            "description",
+            "quantity",
+            "unit_cents",
+        ],
+    ),
+    (
+        "redirects",
+        &["id", "from_path", "target", "status", "created_at"],
+    ),
+    (
+        "request_log",
+        &[
+            "id",
+            "created_at",
+            "method",
+            "path",
+            "status",
+            "duration_us",
+            "request_id",
+        ],
+    ),
+    (
+        "api_tokens",
+        &[
+            "id",
+            "name",
+            "scopes",
+            "token_hash",
+            "prefix",
+            "created_at",
+            "last_used_at",
+            "revoked_at",
+        ],
+    ),
+];
+
+/// Every way the database differs from what this build expects
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaDrift(pub Vec<String>);
+
+impl fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "database schema doesn't match this build:")?;
+        for problem in &self.0 {
+            writeln!(f, "  - {problem}")?;
+        }
+        write!(
+            f,
+            "Run the build that last migrated this database, or restore a backup made by this one."
+        )
+    }
+}
+
+impl std::error::Error for SchemaDrift {}
+
+/// Applied migrations against [`MIGRATOR`]; see the module docs. A database
+/// that was never migrated has nothing to compare.
+pub async fn check_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'",
+    )
+    .fetch_one(pool)
+    .await?;
+    if !exists {
+        return Ok(());
+    }
+    let applied: Vec<(i64, String, bool, Vec<u8>)> = sqlx::query_as(
+        "SELECT version, description, success, checksum FROM _sqlx_migrations ORDER BY version",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut problems = Vec::new();
+    for (version, description, success, checksum) in applied {
+        let embedded = MIGRATOR
+            .iter()
+            .find(|m| m.version == version && !m.migration_type.is_down_migration());
+        match embedded {
+            None => problems.push(format!(
+                "migration {version} ({description}) is applied but not in this build; \
+                 a newer version migrated the database"
+            )),
+            Some(_) if !success => problems.push(format!(
+                "migration {version} ({description}) failed partway; repair the schema by hand, \
+                 then delete its row from _sqlx_migrations"
+            )),
+            Some(migration) if *migration.checksum != *checksum => problems.push(format!(
+                "migration {version} ({description}) was edited after it was applied"
+            )),
+            Some(_) => {}
+        }
+    }
+    drift(problems)
+}
+
+/// [`ESSENTIAL`] against the tables in the database
+pub async fn check_tables(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let mut problems = Vec::new();
+    for (table, columns) in ESSENTIAL {
+        let present: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(pool)
+            .await?;
+        if present.is_empty() {
+            problems.push(format!("table {table} is missing"));
+            continue;
+        }
+        let missing: Vec<&str> = columns
+            .iter()
+            .copied()
+            .filter(|column| !present.iter().any(|p| p == column))
+            .collect();
+        if !missing.is_empty() {
+            problems.push(format!(
+                "table {table} is missing column(s) {}",
+                missing.join(", ")
+            ));
+        }
+    }
+    drift(problems)
+}
+
+fn drift(problems: Vec<String>) -> Result<(), sqlx::Error> {
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(sqlx::Error::Configuration(Box::new(SchemaDrift(problems))))
+    }
+}
+
+/// The drift report inside `error`, if that's what it is
+pub fn as_drift(error: &sqlx::Error) -> Option<&SchemaDrift> {
+    match error {
+        sqlx::Error::Configuration(source) => source.downcast_ref(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+    use crate::db;
+
+    #[tokio::test]
+    async fn test_drift_stops_startup() {
+        let dir = std::env::temp_dir().join(format!("app-schema-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = DatabaseConfig {
+            url: format!("sqlite://{}/app.db?mode=rwc", dir.display()),
+            ..DatabaseConfig::default()
+        };
+        let db = db::init_pool(&config).await.unwrap();
+        check_tables(db.reader()).await.unwrap();
+
+        for statement in [
+            "UPDATE _sqlx_migrations SET checksum = x'00' WHERE version = 1",
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+             VALUES (999, 'from the future', 1, x'00', 0)",
+            "DROP TABLE presence",
+            "ALTER TABLE posts DROP COLUMN summary",
+        ] {
+            sqlx::query(statement).execute(db.writer()).await.unwrap();
+        }
+        let error = check_migrations(db.reader()).await.unwrap_err();
+        assert_eq!(as_drift(&error).unwrap().0.len(), 2);
+        let error = check_tables(db.reader()).await.unwrap_err();
+        assert_eq!(
+            as_drift(&error).unwrap().0,
+            [
+                "table posts is missing column(s) summary",
+                "table presence is missing"
+            ]
+        );
+        db.close().await;
+
+        // A drifted database refuses to start instead of running degraded
+        let Err(error) = db::connect(&config).await else {
+            panic!("started on a drifted database");
+        };
+        let report = as_drift(&error).unwrap().to_string();
+        assert!(report.contains("migration 1 (create items) was edited"));
+        assert!(report.contains("migration 999 (from the future) is applied but not in this build"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
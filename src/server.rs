@@ -0,0 +1,418 @@
+//! Server — accepting connections and speaking HTTP on them
+//!
+//! `main` hands [`serve`] its listeners and the stacked app. Every connection
+//! goes through hyper's auto builder, tuned by `[server.connections]`:
+//! HTTP/1.1, and unless `http2 = false`, HTTP/2 — on plain TCP when the
+//! client starts with the HTTP/2 preface (h2c with prior knowledge, as a
+//! proxy speaks it), over TLS when ALPN picks `h2`.
+//!
+//! TLS is terminated here when `[server] tls_cert` and `tls_key` are set,
+//! or with the `acme` feature, `[server.acme] domains` (see [`crate::acme`]);
+//! otherwise the app speaks plain HTTP and a proxy in front does HTTPS.
+//!
+//! Besides `[server] host:port`, `[[server.listeners]]` adds addresses to
+//! serve on: TCP, or a Unix socket (`unix:<path>`, e.g. for Tor). Every
+//! request carries the [`ListenerInfo`] of the listener it came in on, which
+//! decides what's served there — `/metrics` only where `metrics = true`, the
+//! admin pages unless `admin = false`.
+
+use axum::{body::Body, extract::Request, response::Response};
+use hyper::body::Incoming;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo, TokioTimer},
+    server::{
+        conn::auto,
+        graceful::{GracefulShutdown, Watcher},
+    },
+    service::TowerToHyperService,
+};
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio_rustls::{
+    rustls::{
+        self,
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+        server::Acceptor,
+    },
+    server::TlsStream,
+    LazyConfigAcceptor,
+};
+use tower::{Service, ServiceExt};
+
+use crate::config::{ConnectionsConfig, ListenerConfig, ServerConfig};
+
+/// How long a client has to finish the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long open connections get to finish their requests at shutdown
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Request extension: the listener a request came in on, and what it serves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenerInfo {
+    /// `main` for `[server] host:port`
+    pub name: Arc<str>,
+    /// Whether `/metrics` is served
+    pub metrics: bool,
+    /// Whether the admin pages are served
+    pub admin: bool,
+    /// Whether the connection is TLS the app terminated itself
+    pub tls: bool,
+}
+
+impl ListenerInfo {
+    /// `[server] host:port` (or systemd's socket)
+    pub fn main(config: &ServerConfig) -> Self {
+        Self {
+            name: "main".into(),
+            metrics: false,
+            admin: true,
+            tls: config.tls(),
+        }
+    }
+}
+
+impl Default for ListenerInfo {
+    fn default() -> Self {
+        Self::main(&crate::AppConfig::default().server)
+    }
+}
+
+impl From<&ListenerConfig> for ListenerInfo {
+    fn from(config: &ListenerConfig) -> Self {
+        let name = if config.name.is_empty() {
+            &config.address
+        } else {
+            &config.name
+        };
+        Self {
+            name: name.as_str().into(),
+            metrics: config.metrics,
+            admin: config.admin,
+            tls: config.tls,
+        }
+    }
+}
+
+/// A bound socket connections are accepted from
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, std::path::PathBuf),
+}
+
+impl Listener {
+    /// Bind `address`: `host:port`, or `unix:<path>`. A socket left at the
+    /// path by an earlier run is replaced; any other file is an error.
+    pub async fn bind(address: &str) -> io::Result<Self> {
+        let Some(path) = address.strip_prefix("unix:") else {
+            return Ok(Self::Tcp(TcpListener::bind(address).await?));
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                std::fs::remove_file(path)?;
+            }
+            let listener = tokio::net::UnixListener::bind(path)?;
+            Ok(Self::Unix(listener, path.into()))
+        }
+        #[cfg(not(unix))]
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{path}: Unix sockets need a Unix"),
+        ))
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(listener) => match listener.local_addr() {
+                Ok(address) => write!(f, "{address}"),
+                Err(_) => f.write_str("tcp"),
+            },
+            #[cfg(unix)]
+            Self::Unix(_, path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl From<TcpListener> for Listener {
+    fn from(listener: TcpListener) -> Self {
+        Self::Tcp(listener)
+    }
+}
+
+/// Serve `app` on every listener until `shutdown` completes, then wait up to
+/// [`SHUTDOWN_TIMEOUT`] for open connections to finish their requests. Unix
+/// socket files are removed on the way out.
+pub async fn serve<S>(
+    listeners: Vec<(Listener, ListenerInfo)>,
+    app: S,
+    config: &ServerConfig,
+    shutdown: impl Future<Output = ()>,
+) -> io::Result<()>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    let builder = Arc::new(builder(&config.connections));
+    let tls = if config.tls() {
+        Some(tls(config)?)
+    } else {
+        None
+    };
+    let graceful = Arc::new(GracefulShutdown::new());
+    let (stop, stopped) = watch::channel(false);
+
+    let mut loops = tokio::task::JoinSet::new();
+    for (listener, info) in listeners {
+        let tls = tls.clone().filter(|_| info.tls);
+        loops.spawn(accept_loop(
+            listener,
+            info,
+            app.clone(),
+            builder.clone(),
+            tls,
+            graceful.clone(),
+            stopped.clone(),
+        ));
+    }
+
+    shutdown.await;
+    let _ = stop.send(true);
+    while loops.join_next().await.is_some() {}
+    // Only the accept loops shared it, and they're done: connections hold
+    // watchers, which don't keep it alive
+    let graceful = Arc::into_inner(graceful).expect("accept loops have ended");
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, graceful.shutdown())
+        .await
+        .is_err()
+    {
+        tracing::warn!("Connections still open after {SHUTDOWN_TIMEOUT:?}, not waiting any longer");
+    }
+    Ok(())
+}
+
+/// Accept on `listener` until told to stop, serving each connection on its
+/// own task
+async fn accept_loop<S>(
+    listener: Listener,
+    info: ListenerInfo,
+    app: S,
+    builder: Arc<auto::Builder<TokioExecutor>>,
+    tls: Option<Tls>,
+    graceful: Arc<GracefulShutdown>,
+    mut stopped: watch::Receiver<bool>,
+) where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    loop {
+        let accepted = tokio::select! {
+            accepted = accept(&listener) => accepted,
+            _ = stopped.wait_for(|stopped| *stopped) => break,
+        };
+        let connection = match accepted {
+            Ok(connection) => connection,
+            // Out of file descriptors and the like: back off, don't spin
+            Err(e) => {
+                tracing::warn!(listener = %info.name, error = %e, "Failed to accept a connection");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let served = Served {
+            app: app.clone(),
+            info: info.clone(),
+            builder: builder.clone(),
+            graceful: graceful.watcher(),
+        };
+        match connection {
+            Accepted::Tcp(stream) => match tls.clone() {
+                Some(tls) => {
+                    tokio::spawn(async move {
+                        match tokio::time::timeout(HANDSHAKE_TIMEOUT, tls.accept(stream)).await {
+                            Ok(Ok(Some(stream))) => served.run(stream).await,
+                            Ok(Ok(None)) => {}
+                            Ok(Err(e)) => tracing::debug!(error = %e, "TLS handshake failed"),
+                            Err(_) => tracing::debug!("TLS handshake timed out"),
+                        }
+                    });
+                }
+                None => {
+                    tokio::spawn(served.run(stream));
+                }
+            },
+            #[cfg(unix)]
+            Accepted::Unix(stream) => {
+                tokio::spawn(served.run(stream));
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    if let Listener::Unix(_, path) = &listener {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+enum Accepted {
+    Tcp(tokio::net::TcpStream),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixStream),
+}
+
+async fn accept(listener: &Listener) -> io::Result<Accepted> {
+    match listener {
+        Listener::Tcp(listener) => Ok(Accepted::Tcp(listener.accept().await?.0)),
+        #[cfg(unix)]
+        Listener::Unix(listener, _) => Ok(Accepted::Unix(listener.accept().await?.0)),
+    }
+}
+
+/// What a connection is served with
+struct Served<S> {
+    app: S,
+    info: ListenerInfo,
+    builder: Arc<auto::Builder<TokioExecutor>>,
+    graceful: Watcher,
+}
+
+impl<S> Served<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    /// Serve requests on `stream` until either side closes it, marking each
+    /// with the listener's [`ListenerInfo`]
+    async fn run<I>(self, stream: I)
+    where
+        I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let info = self.info;
+        let service =
+            TowerToHyperService::new(self.app.map_request(move |request: Request<Incoming>| {
+                let mut request = request.map(Body::new);
+                request.extensions_mut().insert(info.clone());
+                request
+            }));
+        let connection = self.builder.serve_connection(TokioIo::new(stream), service);
+        let _ = self.graceful.watch(connection).await;
+    }
+}
+
+/// Hyper's connection builder, per `[server.connections]`
+fn builder(config: &ConnectionsConfig) -> auto::Builder<TokioExecutor> {
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+    let timeout = (config.header_read_timeout_ms > 0)
+        .then(|| Duration::from_millis(config.header_read_timeout_ms));
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .keep_alive(config.keep_alive)
+        .header_read_timeout(timeout)
+        .max_buf_size(config.max_header_bytes);
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .max_concurrent_streams(config.max_concurrent_streams)
+        .max_header_list_size(u32::try_from(config.max_header_bytes).unwrap_or(u32::MAX))
+        .keep_alive_interval(
+            (config.keep_alive_interval_secs > 0)
+                .then(|| Duration::from_secs(config.keep_alive_interval_secs)),
+        )
+        .keep_alive_timeout(Duration::from_secs(config.keep_alive_timeout_secs));
+    if config.http2 {
+        builder
+    } else {
+        builder.http1_only()
+    }
+}
+
+/// TLS configs for the connections [`serve`] accepts
+#[derive(Clone)]
+struct Tls {
+    config: Arc<rustls::ServerConfig>,
+    /// With ACME: for the CA's challenge connections
+    #[cfg(feature = "acme")]
+    challenge: Option<Arc<rustls::ServerConfig>>,
+}
+
+impl Tls {
+    /// The handshake on `stream`; `None` when it was an ACME challenge,
+    /// answered and done with
+    async fn accept(
+        &self,
+        stream: tokio::net::TcpStream,
+    ) -> io::Result<Option<TlsStream<tokio::net::TcpStream>>> {
+        let start = LazyConfigAcceptor::new(Acceptor::default(), stream).await?;
+        #[cfg(feature = "acme")]
+        if let Some(challenge) = &self.challenge {
+            if crate::acme::is_challenge(&start.client_hello()) {
+                use tokio::io::AsyncWriteExt;
+                start
+                    .into_stream(challenge.clone())
+                    .await?
+                    .shutdown()
+                    .await?;
+                return Ok(None);
+            }
+        }
+        Ok(Some(start.into_stream(self.config.clone()).await?))
+    }
+}
+
+/// TLS with the configured certificate, or ACME's, offering `h2` by ALPN
+/// unless HTTP/2 is off
+fn tls(config: &ServerConfig) -> io::Result<Tls> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let alpn = if config.connections.http2 {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+    if config.acme.enabled() {
+        #[cfg(feature = "acme")]
+        {
+            let certificates = crate::acme::start(&config.acme, provider, alpn)?;
+            return Ok(Tls {
+                config: certificates.config,
+                challenge: Some(certificates.challenge),
+            });
+        }
+        #[cfg(not(feature = "acme"))]
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "server.acme.domains is set, but this build lacks the `acme` feature",
+        ));
+    }
+
+    let invalid = |what: &str, e: &dyn std::fmt::Display| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{what}: {e}"))
+    };
+    let certs = CertificateDer::pem_file_iter(&config.tls_cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| invalid(&config.tls_cert, &e))?;
+    let key =
+        PrivateKeyDer::from_pem_file(&config.tls_key).map_err(|e| invalid(&config.tls_key, &e))?;
+    let mut tls = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| invalid("TLS", &e))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid(&config.tls_cert, &e))?;
+    tls.alpn_protocols = alpn;
+    Ok(Tls {
+        config: Arc::new(tls),
+        #[cfg(feature = "acme")]
+        challenge: None,
+    })
+}
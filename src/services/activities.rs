@@ -0,0 +1,292 @@
+//! Activity Service — a log of writes and sign-ins for the admin feed
+//!
+//! Handlers record what happened as a kind (`<subject>.<verb>`, e.g.
+//! `comment.created`), who did it and a one-line summary. The feed filters on
+//! the subject, see [`SUBJECTS`], and pages backwards by ID so new entries
+//! never shift a page that's already loaded. [`ActivityHub`] hands each new
+//! entry to open feed streams.
+
+use serde::Serialize;
+use std::sync::RwLock;
+use tokio::sync::broadcast;
+
+/// Subjects the feed can filter on, with their labels
+pub const SUBJECTS: &[(&str, &str)] = &[
+    ("item", "Items"),
+    ("comment", "Comments"),
+    ("post", "Posts"),
+    ("admin", "Sign-ins"),
+];
+
+/// Activity data model
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Activity {
+    pub id: u32,
+    pub kind: String,
+    pub actor: String,
+    pub summary: String,
+    /// Where the subject can be seen; empty for none
+    pub link: String,
+    /// `YYYY-MM-DD HH:MM:SS` UTC
+    pub created_at: String,
+}
+
+impl Activity {
+    /// The part of the kind before the dot
+    pub fn subject(&self) -> &str {
+        subject(&self.kind)
+    }
+
+    /// `YYYY-MM-DD`
+    pub fn day(&self) -> &str {
+        self.created_at.get(..10).unwrap_or(&self.created_at)
+    }
+}
+
+fn subject(kind: &str) -> &str {
+    kind.split('.').next().unwrap_or(kind)
+}
+
+/// An activity before it's stored
+#[derive(Debug, Clone)]
+pub struct NewActivity {
+    pub kind: String,
+    pub actor: String,
+    pub summary: String,
+    pub link: String,
+}
+
+impl NewActivity {
+    pub fn new(kind: &str, actor: impl Into<String>, summary: impl Into<String>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            actor: actor.into(),
+            summary: summary.into(),
+            link: String::new(),
+        }
+    }
+
+    pub fn link(mut self, link: impl Into<String>) -> Self {
+        self.link = link.into();
+        self
+    }
+}
+
+/// Activity service trait — defines storage for the feed
+pub trait ActivityService: Send + Sync {
+    fn record(&self, activity: NewActivity) -> Activity;
+    /// Newest first, optionally limited to one subject and to IDs below
+    /// `before`
+    fn list(&self, subject: Option<&str>, before: Option<u32>, limit: usize) -> Vec<Activity>;
+}
+
+fn now() -> String {
+    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// ============================================================================
+// Live feed
+// ============================================================================
+
+/// What a feed stream hears about
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActivityChange {
+    Recorded(Activity),
+    /// Server is shutting down — close open streams so shutdown isn't blocked
+    Shutdown,
+}
+
+/// Broadcasts new activities to open feed streams
+pub struct ActivityHub {
+    tx: broadcast::Sender<ActivityChange>,
+}
+
+impl ActivityHub {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(64);
+        Self { tx }
+    }
+
+    pub fn recorded(&self, activity: &Activity) {
+        let _ = self.tx.send(ActivityChange::Recorded(activity.clone()));
+    }
+
+    /// End every open stream (call before graceful shutdown)
+    pub fn shutdown(&self) {
+        let _ = self.tx.send(ActivityChange::Shutdown);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ActivityChange> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for ActivityHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// In-memory implementation
+// ============================================================================
+
+/// In-memory activity storage (good for prototyping, tests)
+pub struct InMemoryActivityService {
+    activities: RwLock<Vec<Activity>>,
+    next_id: RwLock<u32>,
+}
+
+impl InMemoryActivityService {
+    pub fn new() -> Self {
+        Self {
+            activities: RwLock::new(Vec::new()),
+            next_id: RwLock::new(1),
+        }
+    }
+}
+
+impl Default for InMemoryActivityService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActivityService for InMemoryActivityService {
+    fn record(&self, activity: NewActivity) -> Activity {
+        let mut next_id = self.next_id.write().unwrap();
+        let activity = Activity {
+            id: *next_id,
+            kind: activity.kind,
+            actor: activity.actor,
+            summary: activity.summary,
+            link: activity.link,
+            created_at: now(),
+        };
+        *next_id += 1;
+        self.activities.write().unwrap().push(activity.clone());
+        activity
+    }
+
+    fn list(&self, subject: Option<&str>, before: Option<u32>, limit: usize) -> Vec<Activity> {
+        self.activities
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|a| subject.is_none_or(|s| a.subject() == s))
+            .filter(|a| before.is_none_or(|id| a.id < id))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+// ============================================================================
+// SQLx Implementation — SQLite-backed activity storage
+// ============================================================================
+
+use crate::db::Db;
+
+pub struct SqliteActivityService {
+    db: Db,
+}
+
+impl SqliteActivityService {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+}
+
+/// Row type returned by SQLx queries
+#[derive(sqlx::FromRow)]
+struct ActivityRow {
+    id: i64,
+    kind: String,
+    actor: String,
+    summary: String,
+    link: String,
+    created_at: String,
+}
+
+impl From<ActivityRow> for Activity {
+    fn from(row: ActivityRow) -> Self {
+        Activity {
+            id: row.id as u32,
+            kind: row.kind,
+            actor: row.actor,
+            summary: row.summary,
+            link: row.link,
+            created_at: row.created_at,
+        }
+    }
+}
+
+const ACTIVITY_COLUMNS: &str = "id, kind, actor, summary, link, created_at";
+
+impl ActivityService for SqliteActivityService {
+    fn record(&self, activity: NewActivity) -> Activity {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let row = sqlx::query_as::<_, ActivityRow>(&format!(
+                    "INSERT INTO activities (kind, actor, summary, link, created_at) \
+                     VALUES (?, ?, ?, ?, ?) RETURNING {ACTIVITY_COLUMNS}"
+                ))
+                .bind(&activity.kind)
+                .bind(&activity.actor)
+                .bind(&activity.summary)
+                .bind(&activity.link)
+                .bind(now())
+                .fetch_one(self.db.writer())
+                .await
+                .expect("Failed to insert activity");
+                Activity::from(row)
+            })
+        })
+    }
+
+    fn list(&self, subject: Option<&str>, before: Option<u32>, limit: usize) -> Vec<Activity> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, ActivityRow>(&format!(
+                    "SELECT {ACTIVITY_COLUMNS} FROM activities \
+                     WHERE (?1 IS NULL OR kind LIKE ?1 || '.%') AND (?2 IS NULL OR id < ?2) \
+                     ORDER BY id DESC LIMIT ?3"
+                ))
+                .bind(subject)
+                .bind(before.map(i64::from))
+                .bind(limit as i64)
+                .fetch_all(self.db.reader())
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(Activity::from)
+                .collect()
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_filters_and_pages() {
+        let service = InMemoryActivityService::new();
+        for i in 0..5 {
+            service.record(NewActivity::new("item.created", "Ada", format!("Item {i}")));
+        }
+        service.record(NewActivity::new("comment.created", "Grace", "Hello"));
+
+        let page = service.list(None, None, 3);
+        assert_eq!(page.iter().map(|a| a.id).collect::<Vec<_>>(), [6, 5, 4]);
+        let next = service.list(None, Some(4), 3);
+        assert_eq!(next.iter().map(|a| a.id).collect::<Vec<_>>(), [3, 2, 1]);
+
+        let comments = service.list(Some("comment"), None, 10);
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].subject(), "comment");
+        assert_eq!(comments[0].day().len(), 10);
+    }
+}
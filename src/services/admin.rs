@@ -0,0 +1,65 @@
+//! Admin Access — a single shared password guarding `/admin`
+//!
+//! Deliberately minimal: one password from `[admin]` in the config, checked
+//! once at sign-in, after which the session carries [`ADMIN_KEY`]. With no
+//! password configured every admin route answers 404.
+
+use sha2::{Digest, Sha256};
+
+use super::csrf::constant_time_eq;
+use super::SessionStore;
+
+/// Session data key marking a signed-in admin
+pub const ADMIN_KEY: &str = "admin";
+
+/// Verifies the admin password. Holds only its SHA-256 digest.
+#[derive(Clone, Default)]
+pub struct AdminAuth {
+    digest: Option<[u8; 32]>,
+}
+
+impl AdminAuth {
+    /// An empty password disables admin access
+    pub fn new(password: &str) -> Self {
+        Self {
+            digest: (!password.is_empty()).then(|| Sha256::digest(password.as_bytes()).into()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.digest.is_some()
+    }
+
+    /// Compare digests in constant time, so timing leaks neither the
+    /// password nor its length
+    pub fn verify(&self, candidate: &str) -> bool {
+        match &self.digest {
+            Some(digest) => constant_time_eq(&Sha256::digest(candidate.as_bytes()), digest),
+            None => false,
+        }
+    }
+}
+
+/// Whether the session has signed in as admin
+pub fn is_admin(store: &dyn SessionStore, session_id: &str) -> bool {
+    store
+        .get(session_id)
+        .is_some_and(|session| session.data.contains_key(ADMIN_KEY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let auth = AdminAuth::new("hunter2");
+        assert!(auth.is_enabled());
+        assert!(auth.verify("hunter2"));
+        assert!(!auth.verify("hunter"));
+
+        let disabled = AdminAuth::new("");
+        assert!(!disabled.is_enabled());
+        assert!(!disabled.verify(""));
+    }
+}
@@ -0,0 +1,415 @@
+//! API Tokens — personal access tokens for scripts, managed at `/admin/tokens`
+//!
+//! There is no JSON API, but the exports and feeds are worth fetching from a
+//! cron job or a spreadsheet. A script sends `Authorization: Bearer <token>`
+//! and [`crate::middleware::api_token`] lets it in where the token's scopes
+//! allow: [`Scope::Exports`] for the CSV downloads (including the admin-only
+//! subscriber list), [`Scope::Feeds`] for the Atom and iCalendar feeds.
+//! [`Scope::Embeds`] tokens go in the query string of an iframe's
+//! `/embed/<widget>` instead, see [`crate::handlers::embed`].
+//!
+//! Tokens belong to the admin, the one account there is. A token is shown
+//! once, when created; only its SHA-256 digest is stored, so a leaked
+//! database doesn't leak working tokens. Tokens are long and random, so a
+//! plain digest looked up by value is enough — no salt, no slow hash.
+//! Revoking keeps the row, with when it was last used, for the record.
+
+use rand::RngCore;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::RwLock;
+
+/// Every token starts with this, so secret scanners and people can tell
+/// what it is
+pub const TOKEN_PREFIX: &str = "aht_";
+
+/// Random bytes in a token, hex-encoded after [`TOKEN_PREFIX`]
+const TOKEN_BYTES: usize = 32;
+
+/// Characters of a token kept in the clear, to tell tokens apart
+const SHOWN_CHARS: usize = TOKEN_PREFIX.len() + 6;
+
+/// Longest accepted token name, in characters
+pub const MAX_NAME_CHARS: usize = 80;
+
+/// What a token may fetch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// `/items/export.csv` and `/admin/subscribers.csv`
+    Exports,
+    /// `/items/feed.xml`, `/blog/feed.xml` and `/calendar.ics`
+    Feeds,
+    /// `/embed/<widget>?token=…`
+    Embeds,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 3] = [Scope::Exports, Scope::Feeds, Scope::Embeds];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Exports => "exports",
+            Scope::Feeds => "feeds",
+            Scope::Embeds => "embeds",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scope| scope.as_str() == s)
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One token, without its secret
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiToken {
+    pub id: u32,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// The token's first characters, e.g. `aht_3f2a9c`
+    pub prefix: String,
+    /// `YYYY-MM-DD HH:MM:SS`, UTC
+    pub created_at: String,
+    /// Same format; empty until first used
+    pub last_used_at: String,
+    /// Same format; empty while the token works
+    pub revoked_at: String,
+}
+
+impl ApiToken {
+    pub fn is_revoked(&self) -> bool {
+        !self.revoked_at.is_empty()
+    }
+
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+/// A token as submitted, once [`validate`]d
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewApiToken {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+/// Check a submitted token; the message says what to fix
+pub fn validate(name: &str, scopes: &[Scope]) -> Result<NewApiToken, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Name the token after what will use it".into());
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(format!("Names are at most {MAX_NAME_CHARS} characters"));
+    }
+    if scopes.is_empty() {
+        return Err("Pick at least one scope".into());
+    }
+    // In `Scope::ALL` order, once each
+    let scopes = Scope::ALL
+        .into_iter()
+        .filter(|scope| scopes.contains(scope))
+        .collect();
+    Ok(NewApiToken {
+        name: name.to_string(),
+        scopes,
+    })
+}
+
+/// A new random token: [`TOKEN_PREFIX`] and 64 hex digits
+pub fn generate() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{TOKEN_PREFIX}{}", hex::encode(bytes))
+}
+
+/// What's stored in place of `token`
+pub fn digest(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// The token of an `Authorization: Bearer` header; `None` for any other
+/// scheme, which is left to whatever else reads the header
+pub fn bearer(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+fn join_scopes(scopes: &[Scope]) -> String {
+    scopes
+        .iter()
+        .map(|scope| scope.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_scopes(scopes: &str) -> Vec<Scope> {
+    scopes.split_whitespace().filter_map(Scope::parse).collect()
+}
+
+/// API token service trait — defines storage for tokens
+pub trait ApiTokenService: Send + Sync {
+    /// Every token, revoked ones included, newest first
+    fn list(&self) -> Vec<ApiToken>;
+    /// Store a new token; returns it and the secret, which isn't kept
+    fn create(&self, token: NewApiToken) -> (ApiToken, String);
+    /// `false` if there was no such token or it was already revoked
+    fn revoke(&self, id: u32) -> bool;
+    /// The working token `secret` is, noting that it was used
+    fn authenticate(&self, secret: &str) -> Option<ApiToken>;
+}
+
+fn now() -> String {
+    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// ============================================================================
+// In-memory implementation
+// ============================================================================
+
+/// In-memory token storage (good for prototyping, tests)
+pub struct InMemoryApiTokenService {
+    /// Each token with its digest
+    tokens: RwLock<Vec<(ApiToken, String)>>,
+    next_id: RwLock<u32>,
+}
+
+impl InMemoryApiTokenService {
+    pub fn new() -> Self {
+        Self {
+            tokens: RwLock::new(Vec::new()),
+            next_id: RwLock::new(1),
+        }
+    }
+}
+
+impl Default for InMemoryApiTokenService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ApiTokenService for InMemoryApiTokenService {
+    fn list(&self) -> Vec<ApiToken> {
+        let tokens = self.tokens.read().unwrap();
+        tokens
+            .iter()
+            .rev()
+            .map(|(token, _)| token.clone())
+            .collect()
+    }
+
+    fn create(&self, token: NewApiToken) -> (ApiToken, String) {
+        let secret = generate();
+        let mut next_id = self.next_id.write().unwrap();
+        let saved = ApiToken {
+            id: *next_id,
+            name: token.name,
+            scopes: token.scopes,
+            prefix: secret[..SHOWN_CHARS].to_string(),
+            created_at: now(),
+            last_used_at: String::new(),
+            revoked_at: String::new(),
+        };
+        *next_id += 1;
+        self.tokens
+            .write()
+            .unwrap()
+            .push((saved.clone(), digest(&secret)));
+        (saved, secret)
+    }
+
+    fn revoke(&self, id: u32) -> bool {
+        let mut tokens = self.tokens.write().unwrap();
+        match tokens
+            .iter_mut()
+            .find(|(token, _)| token.id == id && !token.is_revoked())
+        {
+            Some((token, _)) => {
+                token.revoked_at = now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn authenticate(&self, secret: &str) -> Option<ApiToken> {
+        let digest = digest(secret);
+        let mut tokens = self.tokens.write().unwrap();
+        let (token, _) = tokens
+            .iter_mut()
+            .find(|(token, hash)| *hash == digest && !token.is_revoked())?;
+        token.last_used_at = now();
+        Some(token.clone())
+    }
+}
+
+// ============================================================================
+// SQLx Implementation — SQLite-backed token storage
+// ============================================================================
+
+use crate::db::Db;
+
+pub struct SqliteApiTokenService {
+    db: Db,
+}
+
+impl SqliteApiTokenService {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+}
+
+/// Row type returned by SQLx queries
+#[derive(sqlx::FromRow)]
+struct ApiTokenRow {
+    id: i64,
+    name: String,
+    scopes: String,
+    prefix: String,
+    created_at: String,
+    last_used_at: String,
+    revoked_at: String,
+}
+
+impl From<ApiTokenRow> for ApiToken {
+    fn from(row: ApiTokenRow) -> Self {
+        Self {
+            id: row.id as u32,
+            name: row.name,
+            scopes: parse_scopes(&row.scopes),
+            prefix: row.prefix,
+            created_at: row.created_at,
+            last_used_at: row.last_used_at,
+            revoked_at: row.revoked_at,
+        }
+    }
+}
+
+const COLUMNS: &str = "id, name, scopes, prefix, created_at, last_used_at, revoked_at";
+
+// Not cached: a revoked token has to stop working on the next request, and
+// every use writes `last_used_at` anyway
+impl ApiTokenService for SqliteApiTokenService {
+    fn list(&self) -> Vec<ApiToken> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, ApiTokenRow>(&format!(
+                    "SELECT {COLUMNS} FROM api_tokens ORDER BY id DESC"
+                ))
+                .fetch_all(self.db.reader())
+                .await
+                .map(|rows| rows.into_iter().map(ApiToken::from).collect())
+                .unwrap_or_default()
+            })
+        })
+    }
+
+    fn create(&self, token: NewApiToken) -> (ApiToken, String) {
+        let secret = generate();
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let row = sqlx::query_as::<_, ApiTokenRow>(&format!(
+                    "INSERT INTO api_tokens (name, scopes, token_hash, prefix, created_at) \
+                     VALUES (?, ?, ?, ?, ?) RETURNING {COLUMNS}"
+                ))
+                .bind(&token.name)
+                .bind(join_scopes(&token.scopes))
+                .bind(digest(&secret))
+                .bind(&secret[..SHOWN_CHARS])
+                .bind(now())
+                .fetch_one(self.db.writer())
+                .await
+                .expect("Failed to create API token");
+                (row.into(), secret)
+            })
+        })
+    }
+
+    fn revoke(&self, id: u32) -> bool {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let result = sqlx::query(
+                    "UPDATE api_tokens SET revoked_at = ? WHERE id = ? AND revoked_at = ''",
+                )
+                .bind(now())
+                .bind(id as i64)
+                .execute(self.db.writer())
+                .await
+                .expect("Failed to revoke API token");
+                result.rows_affected() > 0
+            })
+        })
+    }
+
+    fn authenticate(&self, secret: &str) -> Option<ApiToken> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, ApiTokenRow>(&format!(
+                    "UPDATE api_tokens SET last_used_at = ? \
+                     WHERE token_hash = ? AND revoked_at = '' RETURNING {COLUMNS}"
+                ))
+                .bind(now())
+                .bind(digest(secret))
+                .fetch_optional(self.db.writer())
+                .await
+                .ok()
+                .flatten()
+                .map(ApiToken::from)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let token = validate(" cron ", &[Scope::Feeds, Scope::Exports, Scope::Feeds]).unwrap();
+        assert_eq!(token.name, "cron");
+        assert_eq!(token.scopes, [Scope::Exports, Scope::Feeds]);
+
+        assert!(validate("  ", &[Scope::Feeds]).is_err());
+        assert!(validate("cron", &[]).is_err());
+        assert!(validate(&"x".repeat(MAX_NAME_CHARS + 1), &[Scope::Feeds]).is_err());
+    }
+
+    #[test]
+    fn test_bearer() {
+        assert_eq!(bearer("Bearer aht_123"), Some("aht_123"));
+        assert_eq!(bearer("bearer  aht_123 "), Some("aht_123"));
+        assert_eq!(bearer("Basic dXNlcjpwdw=="), None);
+        assert_eq!(bearer("Bearer"), None);
+    }
+
+    #[test]
+    fn test_create_authenticate_revoke() {
+        let service = InMemoryApiTokenService::new();
+        let (token, secret) = service.create(validate("cron", &[Scope::Feeds]).unwrap());
+        assert!(secret.starts_with(TOKEN_PREFIX));
+        assert_eq!(secret.len(), TOKEN_PREFIX.len() + 2 * TOKEN_BYTES);
+        assert!(secret.starts_with(&token.prefix));
+        assert!(token.last_used_at.is_empty());
+
+        let used = service.authenticate(&secret).unwrap();
+        assert_eq!(used.id, token.id);
+        assert!(used.allows(Scope::Feeds));
+        assert!(!used.allows(Scope::Exports));
+        assert!(!used.last_used_at.is_empty());
+        assert!(service.authenticate(&generate()).is_none());
+
+        assert!(service.revoke(token.id));
+        assert!(!service.revoke(token.id));
+        assert!(service.authenticate(&secret).is_none());
+        assert!(service.list()[0].is_revoked());
+    }
+}
@@ -0,0 +1,157 @@
+//! Autocomplete — suggestion sources behind `/partials/autocomplete`
+//!
+//! A source is anything implementing [`SuggestionSource`]; [`Autocomplete`]
+//! maps the `src` query parameter to one. Sources are registered when
+//! [`Services`](crate::services::Services) is built, so swapping one (say,
+//! for a search index) is a one-line change there. Like the services they
+//! read from, sources are scoped to the current tenant.
+
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::services::items::ItemService;
+
+/// Most suggestions a source is asked for
+pub const MAX_SUGGESTIONS: usize = 8;
+
+/// One option in the listbox
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Suggestion {
+    /// What the input is set to when picked
+    pub value: String,
+    pub label: String,
+    /// Secondary text after the label; empty for none
+    pub hint: String,
+}
+
+/// A source of suggestions
+pub trait SuggestionSource: Send + Sync {
+    /// Up to `limit` suggestions for `query` (trimmed, possibly empty), best
+    /// first. An empty query may return the most useful entries or nothing.
+    fn suggest(&self, query: &str, limit: usize) -> Vec<Suggestion>;
+}
+
+/// Sources by the name `?src=` asks for
+#[derive(Default)]
+pub struct Autocomplete {
+    sources: Vec<(&'static str, Arc<dyn SuggestionSource>)>,
+}
+
+impl Autocomplete {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `source` as `name`, replacing any source already there
+    pub fn with(mut self, name: &'static str, source: Arc<dyn SuggestionSource>) -> Self {
+        self.sources.retain(|(existing, _)| *existing != name);
+        self.sources.push((name, source));
+        self
+    }
+
+    /// The default sources: `tags` and `items`
+    pub fn with_defaults(items: Arc<dyn ItemService>) -> Self {
+        Self::new()
+            .with("tags", Arc::new(TagSource(items.clone())))
+            .with("items", Arc::new(ItemSource(items)))
+    }
+
+    /// Suggestions from the source called `src`; `None` if there's none
+    pub fn suggest(&self, src: &str, query: &str) -> Option<Vec<Suggestion>> {
+        let (_, source) = self.sources.iter().find(|(name, _)| *name == src)?;
+        Some(source.suggest(query.trim(), MAX_SUGGESTIONS))
+    }
+}
+
+/// Rank `candidates` against `query`, case-insensitively: those starting with
+/// it first, then those containing it, each group in its original order
+fn ranked<T>(candidates: Vec<T>, query: &str, key: impl Fn(&T) -> &str) -> Vec<T> {
+    let query = query.to_lowercase();
+    let (mut prefix, mut contains) = (Vec::new(), Vec::new());
+    for candidate in candidates {
+        let text = key(&candidate).to_lowercase();
+        if text.starts_with(&query) {
+            prefix.push(candidate);
+        } else if text.contains(&query) {
+            contains.push(candidate);
+        }
+    }
+    prefix.append(&mut contains);
+    prefix
+}
+
+/// Tags in use, `#tag`; every tag (alphabetically) for an empty query
+pub struct TagSource(pub Arc<dyn ItemService>);
+
+impl SuggestionSource for TagSource {
+    fn suggest(&self, query: &str, limit: usize) -> Vec<Suggestion> {
+        let query = query.trim_start_matches('#');
+        ranked(self.0.list_tags(), query, |tag| tag)
+            .into_iter()
+            .take(limit)
+            .map(|tag| Suggestion {
+                label: format!("#{tag}"),
+                value: tag,
+                hint: String::new(),
+            })
+            .collect()
+    }
+}
+
+/// Item titles; nothing for an empty query
+pub struct ItemSource(pub Arc<dyn ItemService>);
+
+impl SuggestionSource for ItemSource {
+    fn suggest(&self, query: &str, limit: usize) -> Vec<Suggestion> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        ranked(self.0.list_all(), query, |item| &item.title)
+            .into_iter()
+            .take(limit)
+            .map(|item| Suggestion {
+                value: item.title.clone(),
+                label: item.title,
+                hint: if item.done { "done" } else { "" }.to_string(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::items::InMemoryItemService;
+
+    #[test]
+    fn test_tags_rank_prefix_matches_first() {
+        let items: Arc<dyn ItemService> = Arc::new(InMemoryItemService::new());
+        let item = items.create("Ship it".into(), String::new());
+        items.set_tags(
+            item.id,
+            vec!["backend".into(), "frontend".into(), "end-to-end".into()],
+        );
+        let autocomplete = Autocomplete::with_defaults(items);
+
+        let values: Vec<String> = autocomplete
+            .suggest("tags", " #END ")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect();
+        assert_eq!(values, ["end-to-end", "backend", "frontend"]);
+        // Seeded tags too: backend, database, ops, setup
+        assert_eq!(autocomplete.suggest("tags", "").unwrap().len(), 6);
+        assert_eq!(autocomplete.suggest("tags", "zzz").unwrap(), vec![]);
+        assert_eq!(autocomplete.suggest("items", "").unwrap(), vec![]);
+        assert_eq!(
+            autocomplete.suggest("items", "SET UP").unwrap(),
+            vec![Suggestion {
+                value: "Set up project".into(),
+                label: "Set up project".into(),
+                hint: "done".into(),
+            }]
+        );
+        assert!(autocomplete.suggest("nope", "a").is_none());
+    }
+}
@@ -0,0 +1,146 @@
+//! Avatars — deterministic SVG identicons
+//!
+//! Every seed (a name, an email address) maps to the same 5×5 symmetric
+//! pattern in a colour picked from its hash, so people get a recognisable
+//! avatar without uploads or a third-party service. Seeds are trimmed and
+//! lowercased first: `Ada@Example.com` and `ada@example.com` match.
+//!
+//! Only the hash of the seed reaches the SVG, never its text, so the image is
+//! safe to serve from the app's own origin. Rendered files are cached on disk
+//! by the same hash.
+
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Cells per side; columns past the middle mirror the left half
+const GRID: usize = 5;
+/// Cached files kept at most; past this, avatars are rendered but not
+/// stored, so arbitrary seeds can't fill the disk
+const MAX_CACHED: usize = 5000;
+/// Longest accepted seed, in bytes
+pub const MAX_SEED_BYTES: usize = 256;
+
+/// Avatar service trait — allows a stub in tests
+pub trait AvatarService: Send + Sync {
+    /// SVG markup for `seed`. May touch the disk: call from `spawn_blocking`.
+    fn svg(&self, seed: &str) -> String;
+}
+
+/// Draws identicons, caching them in `cache_dir` when one is set
+pub struct Identicons {
+    cache_dir: Option<PathBuf>,
+}
+
+impl Identicons {
+    pub fn new(cache_dir: Option<PathBuf>) -> Self {
+        Self { cache_dir }
+    }
+
+    fn store(&self, dir: &PathBuf, path: &PathBuf, svg: &str) {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            tracing::warn!(error = %e, "can't create avatar cache");
+            return;
+        }
+        let cached = std::fs::read_dir(dir).map(|d| d.count()).unwrap_or(0);
+        if cached >= MAX_CACHED {
+            return;
+        }
+        if let Err(e) = std::fs::write(path, svg) {
+            tracing::warn!(error = %e, "can't write avatar cache");
+        }
+    }
+}
+
+impl AvatarService for Identicons {
+    fn svg(&self, seed: &str) -> String {
+        let digest = digest(seed);
+        let Some(dir) = &self.cache_dir else {
+            return identicon(&digest);
+        };
+        let path = dir.join(format!("{}.svg", hex::encode(&digest[..16])));
+        if let Ok(svg) = std::fs::read_to_string(&path) {
+            return svg;
+        }
+        let svg = identicon(&digest);
+        self.store(dir, &path, &svg);
+        svg
+    }
+}
+
+fn digest(seed: &str) -> [u8; 32] {
+    Sha256::digest(seed.trim().to_lowercase().as_bytes()).into()
+}
+
+/// The pattern and colour for a seed's digest
+fn identicon(digest: &[u8; 32]) -> String {
+    // Hue from the first two bytes; cells from the bits that follow
+    let hue = u16::from_be_bytes([digest[0], digest[1]]) % 360;
+    let mut cells = String::new();
+    for row in 0..GRID {
+        for column in 0..GRID.div_ceil(2) {
+            let bit = row * GRID.div_ceil(2) + column;
+            if digest[2 + bit / 8] & (1 << (bit % 8)) == 0 {
+                continue;
+            }
+            let _ = write!(cells, "M{column} {row}h1v1h-1z");
+            let mirror = GRID - 1 - column;
+            if mirror != column {
+                let _ = write!(cells, "M{mirror} {row}h1v1h-1z");
+            }
+        }
+    }
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-0.5 -0.5 6 6" width="64" height="64" shape-rendering="crispEdges"><rect x="-0.5" y="-0.5" width="6" height="6" fill="hsl({hue} 45% 92%)"/><path d="{cells}" fill="hsl({hue} 55% 45%)"/></svg>"#
+    )
+}
+
+/// Path of the avatar for `seed`, percent-encoded for use in `src`
+pub fn url(seed: &str) -> String {
+    let mut url = String::from("/avatar/");
+    for byte in seed.trim().to_lowercase().bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            url.push(byte as char);
+        } else {
+            let _ = write!(url, "%{byte:02X}");
+        }
+    }
+    url.push_str(".svg");
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identicon_is_deterministic() {
+        let service = Identicons::new(None);
+        assert_eq!(service.svg("ada"), service.svg("  Ada "));
+        assert_ne!(service.svg("ada"), service.svg("grace"));
+        assert!(!service.svg("<script>").contains("script"));
+    }
+
+    #[test]
+    fn test_identicon_is_symmetric() {
+        let svg = identicon(&[0xff; 32]);
+        // Every cell filled: 3 columns drawn, 2 of them mirrored, on 5 rows
+        assert_eq!(svg.matches("h1v1h-1z").count(), 25);
+    }
+
+    #[test]
+    fn test_cache() {
+        let dir = std::env::temp_dir().join(format!("avatars-{}", uuid::Uuid::new_v4()));
+        let service = Identicons::new(Some(dir.clone()));
+        let svg = service.svg("ada");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        assert_eq!(service.svg("ADA"), svg);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_url() {
+        assert_eq!(url("Ada"), "/avatar/ada.svg");
+        assert_eq!(url("a b/c@d.e"), "/avatar/a%20b%2Fc%40d.e.svg");
+    }
+}
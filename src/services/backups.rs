@@ -0,0 +1,228 @@
+//! Backups — consistent online copies of the SQLite database
+//!
+//! `VACUUM INTO` writes a compacted snapshot of the database as of one
+//! moment, while the app keeps reading and writing. Backups run every
+//! `[backups] interval_hours` ([`schedule`]) and from `/admin/backups`, land
+//! in `[backups] dir` as `backup-<UTC time>.db`, and only the newest
+//! `[backups] keep` are kept. Downloads go through signed, expiring links
+//! ([`TOKEN_PURPOSE`]), so a script can fetch one without an admin session.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::db::{Db, DbHealth};
+
+/// [`TokenSigner`](crate::services::TokenSigner) purpose of download links
+pub const TOKEN_PURPOSE: &str = "backup";
+/// How long a download link works
+pub const DOWNLOAD_TTL: Duration = Duration::from_secs(60 * 60);
+
+const PREFIX: &str = "backup-";
+const EXTENSION: &str = ".db";
+
+/// A finished backup file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Backup {
+    /// File name, e.g. `backup-20260314-093000-000.db`
+    pub name: String,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Writes, lists and prunes backups, see the module docs
+pub struct Backups {
+    // None when backups are disabled
+    dir: Option<PathBuf>,
+    keep: usize,
+    // One backup at a time
+    running: tokio::sync::Mutex<()>,
+}
+
+impl Backups {
+    pub fn new(dir: Option<PathBuf>, keep: usize) -> Self {
+        Self {
+            dir,
+            keep: keep.max(1),
+            running: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    /// Back up `db` now, then delete all but the newest `keep` backups
+    pub async fn run(&self, db: &Db) -> Result<Backup, String> {
+        let dir = self.dir.as_ref().ok_or("Backups are disabled")?;
+        let _running = self.running.lock().await;
+        std::fs::create_dir_all(dir).map_err(|e| format!("can't create {}: {e}", dir.display()))?;
+
+        let name = format!(
+            "{PREFIX}{}{EXTENSION}",
+            Utc::now().format("%Y%m%d-%H%M%S-%3f")
+        );
+        // Written under a temporary name, so a backup that fails halfway is
+        // never listed or downloaded
+        let partial = dir.join(format!("{name}.partial"));
+        let _ = std::fs::remove_file(&partial);
+        let result = sqlx::query("VACUUM INTO ?")
+            .bind(partial.to_string_lossy().into_owned())
+            .execute(db.reader())
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|_| std::fs::rename(&partial, dir.join(&name)).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&partial);
+            tracing::error!(error = %e, "Backup failed");
+            return Err(e);
+        }
+
+        self.prune();
+        let backup = self
+            .list()
+            .into_iter()
+            .find(|backup| backup.name == name)
+            .ok_or("backup vanished after writing")?;
+        tracing::info!(name = %backup.name, size = backup.size, "Backup written");
+        Ok(backup)
+    }
+
+    /// Finished backups, newest first
+    pub fn list(&self) -> Vec<Backup> {
+        let Some(entries) = self.dir.as_ref().and_then(|dir| dir.read_dir().ok()) else {
+            return Vec::new();
+        };
+        let mut backups: Vec<Backup> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                if !is_backup_name(&name) {
+                    return None;
+                }
+                let metadata = entry.metadata().ok()?;
+                Some(Backup {
+                    name,
+                    size: metadata.len(),
+                    created_at: metadata.modified().ok()?.into(),
+                })
+            })
+            .collect();
+        // The names sort by time
+        backups.sort_by(|a, b| b.name.cmp(&a.name));
+        backups
+    }
+
+    pub fn latest(&self) -> Option<Backup> {
+        self.list().into_iter().next()
+    }
+
+    /// The file of backup `name`; `None` for anything that isn't one
+    pub fn path(&self, name: &str) -> Option<PathBuf> {
+        let path = self.dir.as_ref()?.join(name);
+        (is_backup_name(name) && path.is_file()).then_some(path)
+    }
+
+    fn prune(&self) {
+        for backup in self.list().into_iter().skip(self.keep) {
+            if let Some(path) = self.path(&backup.name) {
+                match std::fs::remove_file(&path) {
+                    Ok(()) => tracing::info!(name = %backup.name, "Old backup deleted"),
+                    Err(e) => tracing::warn!(error = %e, "Can't delete {}", path.display()),
+                }
+            }
+        }
+    }
+
+    /// Where backups go, for display
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+}
+
+/// `backup-<digits and dashes>.db`, nothing that could leave the directory
+fn is_backup_name(name: &str) -> bool {
+    name.strip_prefix(PREFIX)
+        .and_then(|rest| rest.strip_suffix(EXTENSION))
+        .is_some_and(|stamp| {
+            !stamp.is_empty() && stamp.chars().all(|c| c.is_ascii_digit() || c == '-')
+        })
+}
+
+/// Back up every `every`, skipping while the database is down
+pub fn schedule(backups: Arc<Backups>, db: Db, health: Arc<DbHealth>, every: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        // The first tick is immediate; a restart shouldn't mean a backup
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if health.is_up() {
+                let _ = backups.run(&db).await;
+            } else {
+                tracing::warn!("Database down, skipping the scheduled backup");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_backups_are_restorable_and_pruned() {
+        let dir = std::env::temp_dir().join(format!("app-backups-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = crate::db::init_pool(&DatabaseConfig {
+            url: format!("sqlite://{}/app.db?mode=rwc", dir.display()),
+            ..DatabaseConfig::default()
+        })
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO items (title, description, created_at) VALUES ('a', 'b', 'now')")
+            .execute(db.writer())
+            .await
+            .unwrap();
+
+        let backups = Backups::new(Some(dir.join("backups")), 2);
+        for _ in 0..3 {
+            backups.run(&db).await.unwrap();
+        }
+        let list = backups.list();
+        assert_eq!(list.len(), 2);
+        assert_eq!(backups.latest(), list.first().cloned());
+
+        // The copy is a working database with the data in it
+        let copy = sqlx::SqlitePool::connect(&format!(
+            "sqlite://{}",
+            backups.path(&list[0].name).unwrap().display()
+        ))
+        .await
+        .unwrap();
+        let titles: Vec<String> = sqlx::query_scalar("SELECT title FROM items")
+            .fetch_all(&copy)
+            .await
+            .unwrap();
+        assert!(titles.contains(&"a".to_string()));
+
+        copy.close().await;
+        db.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_only_backup_files_resolve() {
+        let backups = Backups::new(Some(std::env::temp_dir()), 7);
+        assert!(is_backup_name("backup-20260314-093000-000.db"));
+        assert!(!is_backup_name("backup-../../etc/passwd.db"));
+        assert!(!is_backup_name("backup-.db"));
+        assert!(!is_backup_name("app.db"));
+        assert_eq!(backups.path("../app.db"), None);
+        assert_eq!(Backups::new(None, 7).path("backup-1.db"), None);
+    }
+}
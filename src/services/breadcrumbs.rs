@@ -0,0 +1,117 @@
+//! Breadcrumb Service — labeled trails for route paths
+//!
+//! Labels come from the sidebar [`NAV`](crate::globals::NAV), then from
+//! `[breadcrumbs.labels]` in the config; any other segment is title-cased
+//! from its slug. Templates render the trail via `breadcrumbs()`.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::globals::NAV;
+
+/// One link in a trail
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Breadcrumb {
+    pub label: String,
+    pub href: String,
+}
+
+/// Breadcrumb service trait — allows custom trails per deployment
+pub trait BreadcrumbService: Send + Sync {
+    /// Trail from the home page to `path`, inclusive. Empty for `/` itself.
+    fn trail(&self, path: &str) -> Vec<Breadcrumb>;
+}
+
+/// Derives trails from the path segments, labeling each prefix
+pub struct RouteBreadcrumbs {
+    labels: HashMap<String, String>,
+}
+
+impl RouteBreadcrumbs {
+    /// Nav labels, with `overrides` (path → label) taking precedence
+    pub fn new(overrides: &BTreeMap<String, String>) -> Self {
+        let mut labels: HashMap<String, String> = NAV
+            .iter()
+            .flat_map(|section| section.items)
+            .map(|item| (item.href.to_string(), item.label.to_string()))
+            .collect();
+        labels.extend(
+            overrides
+                .iter()
+                .map(|(path, label)| (path.clone(), label.clone())),
+        );
+        Self { labels }
+    }
+
+    fn label(&self, href: &str, segment: &str) -> String {
+        self.labels
+            .get(href)
+            .cloned()
+            .unwrap_or_else(|| humanize(segment))
+    }
+}
+
+impl Default for RouteBreadcrumbs {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new())
+    }
+}
+
+impl BreadcrumbService for RouteBreadcrumbs {
+    fn trail(&self, path: &str) -> Vec<Breadcrumb> {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        if segments.is_empty() {
+            return Vec::new();
+        }
+
+        let mut trail = vec![Breadcrumb {
+            label: self.label("/", ""),
+            href: "/".to_string(),
+        }];
+        let mut href = String::new();
+        for segment in segments {
+            href.push('/');
+            href.push_str(segment);
+            trail.push(Breadcrumb {
+                label: self.label(&href, segment),
+                href: href.clone(),
+            });
+        }
+        trail
+    }
+}
+
+/// `my-first_post` → `My First Post`
+fn humanize(segment: &str) -> String {
+    segment
+        .split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trail_labels() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert("/demo".to_string(), "Live Demo".to_string());
+        let crumbs = RouteBreadcrumbs::new(&overrides);
+
+        assert!(crumbs.trail("/").is_empty());
+
+        let trail = crumbs.trail("/demo/first_item-draft/");
+        let labels: Vec<&str> = trail.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, ["Home", "Live Demo", "First Item Draft"]);
+        assert_eq!(trail[2].href, "/demo/first_item-draft");
+    }
+}
@@ -0,0 +1,456 @@
+//! Calendar Service — dated events for the month view and `/calendar.ics`
+//!
+//! Times are UTC throughout. An event ends exclusively: one running
+//! 09:00–10:00 has `ends_at` 10:00, and an all-day event on the 14th runs
+//! from midnight on the 14th to midnight on the 15th. [`Event::days`] gives
+//! the dates an event covers, for placing it on a grid.
+//!
+//! [`month_grid`] and [`to_ics`] are pure helpers shared by the handlers.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::Serialize;
+use std::fmt::Write;
+use std::sync::RwLock;
+
+/// Storage format for event times
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Event data model
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Event {
+    pub id: u32,
+    pub title: String,
+    pub description: String,
+    pub location: String,
+    pub starts_at: NaiveDateTime,
+    /// Exclusive
+    pub ends_at: NaiveDateTime,
+    /// Starts and ends at midnight; shown without times
+    pub all_day: bool,
+}
+
+impl Event {
+    /// Every date the event touches, first to last
+    pub fn days(&self) -> impl Iterator<Item = NaiveDate> {
+        let first = self.starts_at.date();
+        // Ending at midnight doesn't touch the next day
+        let last = (self.ends_at - Duration::seconds(1)).date().max(first);
+        first.iter_days().take_while(move |day| *day <= last)
+    }
+}
+
+/// An event before it's stored
+#[derive(Debug, Clone)]
+pub struct NewEvent {
+    pub title: String,
+    pub description: String,
+    pub location: String,
+    pub starts_at: NaiveDateTime,
+    pub ends_at: NaiveDateTime,
+    pub all_day: bool,
+}
+
+/// Calendar service trait — defines storage for events
+pub trait CalendarService: Send + Sync {
+    /// Every event, earliest first
+    fn list_all(&self) -> Vec<Event>;
+    /// Events overlapping `[from, to)`, earliest first
+    fn list_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> Vec<Event>;
+    fn create(&self, event: NewEvent) -> Event;
+}
+
+/// Sample events in the current month, matching the migration's seed
+fn seed() -> Vec<NewEvent> {
+    let today = Utc::now().date_naive();
+    let month_start = today.with_day(1).expect("every month has a 1st");
+    let at = |days: i64, hour: u32| {
+        (month_start + Duration::days(days))
+            .and_time(NaiveTime::from_hms_opt(hour, 0, 0).expect("hours below 24 are valid"))
+    };
+    vec![
+        NewEvent {
+            title: "Sprint planning".into(),
+            description: "Pick the next two weeks of work".into(),
+            location: "Room 2".into(),
+            starts_at: at(2, 9),
+            ends_at: at(2, 10),
+            all_day: false,
+        },
+        NewEvent {
+            title: "Release day".into(),
+            description: "Tag, build and deploy".into(),
+            location: String::new(),
+            starts_at: at(14, 0),
+            ends_at: at(15, 0),
+            all_day: true,
+        },
+        NewEvent {
+            title: "Team offsite".into(),
+            description: "Two days away from the screen".into(),
+            location: "Lake house".into(),
+            starts_at: at(20, 0),
+            ends_at: at(22, 0),
+            all_day: true,
+        },
+    ]
+}
+
+/// In-memory event storage (good for prototyping, tests)
+pub struct InMemoryCalendarService {
+    events: RwLock<Vec<Event>>,
+}
+
+impl InMemoryCalendarService {
+    pub fn new() -> Self {
+        let service = Self {
+            events: RwLock::new(Vec::new()),
+        };
+        for event in seed() {
+            service.create(event);
+        }
+        service
+    }
+}
+
+impl Default for InMemoryCalendarService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CalendarService for InMemoryCalendarService {
+    fn list_all(&self) -> Vec<Event> {
+        let mut events = self.events.read().unwrap().clone();
+        events.sort_by_key(|e| (e.starts_at, e.id));
+        events
+    }
+
+    fn list_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> Vec<Event> {
+        self.list_all()
+            .into_iter()
+            .filter(|e| e.starts_at < to && e.ends_at > from)
+            .collect()
+    }
+
+    fn create(&self, event: NewEvent) -> Event {
+        let mut events = self.events.write().unwrap();
+        let event = Event {
+            id: events.iter().map(|e| e.id).max().unwrap_or(0) + 1,
+            title: event.title,
+            description: event.description,
+            location: event.location,
+            starts_at: event.starts_at,
+            ends_at: event.ends_at,
+            all_day: event.all_day,
+        };
+        events.push(event.clone());
+        event
+    }
+}
+
+// ============================================================================
+// SQLx Implementation — SQLite-backed event storage
+// ============================================================================
+
+use crate::db::Db;
+
+pub struct SqliteCalendarService {
+    db: Db,
+}
+
+impl SqliteCalendarService {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+}
+
+/// Row type returned by SQLx queries
+#[derive(sqlx::FromRow)]
+struct EventRow {
+    id: i64,
+    title: String,
+    description: String,
+    location: String,
+    starts_at: String,
+    ends_at: String,
+    all_day: i32,
+}
+
+fn parse_timestamp(value: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(value, TIMESTAMP_FORMAT).unwrap_or_default()
+}
+
+fn format_timestamp(at: NaiveDateTime) -> String {
+    at.format(TIMESTAMP_FORMAT).to_string()
+}
+
+impl From<EventRow> for Event {
+    fn from(row: EventRow) -> Self {
+        Event {
+            id: row.id as u32,
+            title: row.title,
+            description: row.description,
+            location: row.location,
+            starts_at: parse_timestamp(&row.starts_at),
+            ends_at: parse_timestamp(&row.ends_at),
+            all_day: row.all_day != 0,
+        }
+    }
+}
+
+const EVENT_COLUMNS: &str = "id, title, description, location, starts_at, ends_at, all_day";
+
+impl CalendarService for SqliteCalendarService {
+    fn list_all(&self) -> Vec<Event> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, EventRow>(&format!(
+                    "SELECT {EVENT_COLUMNS} FROM events ORDER BY starts_at, id"
+                ))
+                .fetch_all(self.db.reader())
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(Event::from)
+                .collect()
+            })
+        })
+    }
+
+    fn list_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> Vec<Event> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                // Timestamps share one format, so they compare as text
+                sqlx::query_as::<_, EventRow>(&format!(
+                    "SELECT {EVENT_COLUMNS} FROM events \
+                     WHERE starts_at < ? AND ends_at > ? ORDER BY starts_at, id"
+                ))
+                .bind(format_timestamp(to))
+                .bind(format_timestamp(from))
+                .fetch_all(self.db.reader())
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(Event::from)
+                .collect()
+            })
+        })
+    }
+
+    fn create(&self, event: NewEvent) -> Event {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, EventRow>(&format!(
+                    "INSERT INTO events \
+                     (title, description, location, starts_at, ends_at, all_day) \
+                     VALUES (?, ?, ?, ?, ?, ?) RETURNING {EVENT_COLUMNS}"
+                ))
+                .bind(&event.title)
+                .bind(&event.description)
+                .bind(&event.location)
+                .bind(format_timestamp(event.starts_at))
+                .bind(format_timestamp(event.ends_at))
+                .bind(event.all_day as i32)
+                .fetch_one(self.db.writer())
+                .await
+                .expect("Failed to insert event")
+                .into()
+            })
+        })
+    }
+}
+
+// ============================================================================
+// Month grid
+// ============================================================================
+
+/// The weeks (Monday first) that cover the month containing `day`. Days
+/// from the neighbouring months fill the first and last week.
+pub fn month_grid(day: NaiveDate) -> Vec<[NaiveDate; 7]> {
+    let first = day.with_day(1).expect("every month has a 1st");
+    let next_month = first
+        .checked_add_months(chrono::Months::new(1))
+        .expect("dates this far out are in range");
+    let mut monday = first - Duration::days(first.weekday().num_days_from_monday() as i64);
+    let mut weeks = Vec::new();
+    while monday < next_month {
+        weeks.push(std::array::from_fn(|i| monday + Duration::days(i as i64)));
+        monday += Duration::days(7);
+    }
+    weeks
+}
+
+// ============================================================================
+// iCalendar export
+// ============================================================================
+
+/// `events` as an RFC 5545 calendar. `host` makes the UIDs globally unique
+/// (e.g. `example.com`); `name` is what calendar apps show for the feed.
+pub fn to_ics(events: &[Event], host: &str, name: &str) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!(
+            "PRODID:-//{}//{}//EN",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        ),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", ics_text(name)),
+    ];
+    let stamp = Utc::now().naive_utc().format("%Y%m%dT%H%M%SZ").to_string();
+    for event in events {
+        lines.push("BEGIN:VEVENT".into());
+        lines.push(format!("UID:event-{}@{host}", event.id));
+        lines.push(format!("DTSTAMP:{stamp}"));
+        if event.all_day {
+            lines.push(format!(
+                "DTSTART;VALUE=DATE:{}",
+                event.starts_at.format("%Y%m%d")
+            ));
+            lines.push(format!(
+                "DTEND;VALUE=DATE:{}",
+                event.ends_at.format("%Y%m%d")
+            ));
+        } else {
+            lines.push(format!(
+                "DTSTART:{}",
+                event.starts_at.format("%Y%m%dT%H%M%SZ")
+            ));
+            lines.push(format!("DTEND:{}", event.ends_at.format("%Y%m%dT%H%M%SZ")));
+        }
+        lines.push(format!("SUMMARY:{}", ics_text(&event.title)));
+        if !event.description.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", ics_text(&event.description)));
+        }
+        if !event.location.is_empty() {
+            lines.push(format!("LOCATION:{}", ics_text(&event.location)));
+        }
+        lines.push("END:VEVENT".into());
+    }
+    lines.push("END:VCALENDAR".into());
+
+    let mut ics = String::new();
+    for line in lines {
+        let _ = write!(ics, "{}\r\n", fold(&line));
+    }
+    ics
+}
+
+/// Escape a TEXT value: backslash, `;`, `,` and newlines
+fn ics_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Break lines longer than 75 octets, continuing with a leading space.
+/// Never splits a UTF-8 character.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            // The leading space counts toward the next line
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn event(starts_at: NaiveDateTime, ends_at: NaiveDateTime, all_day: bool) -> Event {
+        Event {
+            id: 7,
+            title: "Review; notes, etc".into(),
+            description: "Line one\nLine two".into(),
+            location: String::new(),
+            starts_at,
+            ends_at,
+            all_day,
+        }
+    }
+
+    #[test]
+    fn test_days() {
+        let days = |e: Event| e.days().map(|d| d.day()).collect::<Vec<_>>();
+        // An all-day event ends at midnight, which isn't another day
+        let offsite = event(at("2024-03-20", "00:00"), at("2024-03-22", "00:00"), true);
+        assert_eq!(days(offsite), vec![20, 21]);
+        let late = event(at("2024-03-20", "23:00"), at("2024-03-21", "01:00"), false);
+        assert_eq!(days(late), vec![20, 21]);
+    }
+
+    #[test]
+    fn test_list_between() {
+        let service = InMemoryCalendarService {
+            events: RwLock::new(Vec::new()),
+        };
+        let meeting = service.create(NewEvent {
+            title: "Meeting".into(),
+            description: String::new(),
+            location: String::new(),
+            starts_at: at("2024-03-31", "23:00"),
+            ends_at: at("2024-04-01", "01:00"),
+            all_day: false,
+        });
+        let april = |from, to| service.list_between(at(from, "00:00"), at(to, "00:00"));
+        // Overlapping either month counts
+        assert_eq!(april("2024-03-01", "2024-04-01"), vec![meeting.clone()]);
+        assert_eq!(april("2024-04-01", "2024-05-01"), vec![meeting]);
+        assert!(april("2024-04-02", "2024-05-01").is_empty());
+    }
+
+    #[test]
+    fn test_month_grid() {
+        // March 2024 starts on a Friday and ends on a Sunday
+        let weeks = month_grid(NaiveDate::from_ymd_opt(2024, 3, 15).unwrap());
+        assert_eq!(weeks.len(), 5);
+        assert_eq!(weeks[0][0], NaiveDate::from_ymd_opt(2024, 2, 26).unwrap());
+        assert_eq!(weeks[4][6], NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+    }
+
+    #[test]
+    fn test_to_ics() {
+        let events = [
+            event(at("2024-03-20", "00:00"), at("2024-03-22", "00:00"), true),
+            event(at("2024-03-05", "09:00"), at("2024-03-05", "10:30"), false),
+        ];
+        let ics = to_ics(&events, "example.com", "Team");
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("UID:event-7@example.com\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240320\r\nDTEND;VALUE=DATE:20240322\r\n"));
+        assert!(ics.contains("DTSTART:20240305T090000Z\r\nDTEND:20240305T103000Z\r\n"));
+        assert!(ics.contains("SUMMARY:Review\\; notes\\, etc\r\n"));
+        assert!(ics.contains("DESCRIPTION:Line one\\nLine two\r\n"));
+        assert!(!ics.contains("LOCATION"));
+    }
+
+    #[test]
+    fn test_fold() {
+        let line = format!("SUMMARY:{}", "é".repeat(40));
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|l| l.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
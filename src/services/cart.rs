@@ -0,0 +1,183 @@
+//! Cart — a shopping cart kept in the session
+//!
+//! No database: the cart is JSON in the session under [`CART`], so it lasts
+//! as long as the session does and belongs to one browser. Products come
+//! from the fixed [`CATALOGUE`]; a real shop would look them up in storage
+//! and store prices with the order, not the cart.
+
+use serde::{Deserialize, Serialize};
+
+use super::session::SessionKey;
+use super::SessionStore;
+
+/// Session data key holding the cart
+pub const CART: SessionKey<Cart> = SessionKey::new("_cart");
+
+/// Most of one product a cart holds
+pub const MAX_QUANTITY: u32 = 99;
+
+/// Something for sale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Product {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub price_cents: u64,
+}
+
+/// What the demo shop sells
+pub const CATALOGUE: &[Product] = &[
+    Product {
+        id: "mug",
+        name: "Enamel mug",
+        price_cents: 1_200,
+    },
+    Product {
+        id: "tote",
+        name: "Canvas tote",
+        price_cents: 1_800,
+    },
+    Product {
+        id: "stickers",
+        name: "Sticker sheet",
+        price_cents: 400,
+    },
+    Product {
+        id: "hoodie",
+        name: "Hoodie",
+        price_cents: 4_500,
+    },
+];
+
+/// The catalogue entry with `id`
+pub fn product(id: &str) -> Option<&'static Product> {
+    CATALOGUE.iter().find(|p| p.id == id)
+}
+
+/// One product and how many of it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CartLine {
+    pub product: String,
+    pub quantity: u32,
+}
+
+/// Lines in the order they were first added
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cart {
+    lines: Vec<CartLine>,
+}
+
+impl Cart {
+    /// The session's cart, empty if it has none
+    pub fn load(store: &dyn SessionStore, session_id: &str) -> Self {
+        CART.get(store, session_id).unwrap_or_default()
+    }
+
+    /// Keep the cart in the session; an empty one isn't stored at all
+    pub fn save(&self, store: &dyn SessionStore, session_id: &str) {
+        if self.lines.is_empty() {
+            CART.take(store, session_id);
+        } else {
+            CART.set(store, session_id, self);
+        }
+    }
+
+    /// Add `quantity` of a catalogue product, up to [`MAX_QUANTITY`].
+    /// `false` for a product that isn't in the catalogue.
+    pub fn add(&mut self, id: &str, quantity: u32) -> bool {
+        if product(id).is_none() {
+            return false;
+        }
+        match self.lines.iter_mut().find(|l| l.product == id) {
+            Some(line) => line.quantity = line.quantity.saturating_add(quantity).min(MAX_QUANTITY),
+            None if quantity > 0 => self.lines.push(CartLine {
+                product: id.to_string(),
+                quantity: quantity.min(MAX_QUANTITY),
+            }),
+            None => {}
+        }
+        true
+    }
+
+    /// Change a line's quantity, capped at [`MAX_QUANTITY`]; zero removes it.
+    /// `false` when the product isn't in the cart.
+    pub fn set_quantity(&mut self, id: &str, quantity: u32) -> bool {
+        let Some(line) = self.lines.iter_mut().find(|l| l.product == id) else {
+            return false;
+        };
+        if quantity == 0 {
+            self.remove(id)
+        } else {
+            line.quantity = quantity.min(MAX_QUANTITY);
+            true
+        }
+    }
+
+    /// `false` when the product wasn't in the cart
+    pub fn remove(&mut self, id: &str) -> bool {
+        let before = self.lines.len();
+        self.lines.retain(|l| l.product != id);
+        self.lines.len() != before
+    }
+
+    pub fn lines(&self) -> &[CartLine] {
+        &self.lines
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Items in the cart, counting each unit
+    pub fn count(&self) -> u32 {
+        self.lines.iter().map(|l| l.quantity).sum()
+    }
+
+    /// Sum of every line at catalogue prices. Lines whose product has left
+    /// the catalogue count for nothing.
+    pub fn total_cents(&self) -> u64 {
+        self.lines
+            .iter()
+            .filter_map(|l| product(&l.product).map(|p| p.price_cents * l.quantity as u64))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::InMemorySessionStore;
+
+    #[test]
+    fn test_cart_quantities() {
+        let mut cart = Cart::default();
+        assert!(cart.add("mug", 2));
+        assert!(cart.add("mug", 1));
+        assert!(cart.add("stickers", 200));
+        assert!(cart.add("stickers", u32::MAX));
+        assert!(!cart.add("teapot", 1));
+        assert_eq!(cart.lines()[0].quantity, 3);
+        assert_eq!(cart.lines()[1].quantity, MAX_QUANTITY);
+        assert_eq!(cart.count(), 3 + MAX_QUANTITY);
+        assert_eq!(cart.total_cents(), 3 * 1_200 + MAX_QUANTITY as u64 * 400);
+
+        assert!(cart.set_quantity("stickers", 0));
+        assert!(!cart.set_quantity("stickers", 1));
+        assert!(cart.remove("mug"));
+        assert!(cart.is_empty());
+    }
+
+    #[test]
+    fn test_cart_lives_in_the_session() {
+        let store = InMemorySessionStore::new();
+        let session = store.create();
+
+        let mut cart = Cart::load(&store, &session.id);
+        cart.add("tote", 1);
+        cart.save(&store, &session.id);
+        assert_eq!(Cart::load(&store, &session.id).count(), 1);
+
+        cart.remove("tote");
+        cart.save(&store, &session.id);
+        assert!(store.get(&session.id).unwrap().data.is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+//! Charts — small inline SVGs rendered on the server
+//!
+//! Sparklines and bar charts for dashboard cards: no client-side library, no
+//! script, and they refresh along with whatever partial embeds them. Charts
+//! scale to their container's width (the `viewBox` is stretched) and draw in
+//! `currentColor`, so CSS decides the colour.
+//!
+//! The markup is built from numbers and escaped labels only, so [`Chart`]
+//! renders verbatim (`{{ chart|safe }}`).
+
+use serde::{Serialize, Serializer};
+use std::fmt::{self, Write};
+
+use crate::utils::html::escape;
+
+/// `viewBox` width; the rendered width follows the container
+const WIDTH: f64 = 120.0;
+/// `viewBox` height, and the rendered height in pixels
+const HEIGHT: f64 = 32.0;
+/// Gap between bars, in `viewBox` units
+const BAR_GAP: f64 = 1.0;
+
+/// An SVG chart. Renders verbatim (use `|safe`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chart(String);
+
+impl Chart {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Chart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Chart {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// Opening `<svg>` tag; `label` becomes the accessible name
+fn open(label: &str) -> String {
+    format!(
+        r#"<svg class="chart" viewBox="0 0 {WIDTH} {HEIGHT}" width="100%" height="{HEIGHT}" preserveAspectRatio="none" role="img" aria-label="{}">"#,
+        escape(label)
+    )
+}
+
+/// Largest value, or 1 so an all-zero series sits on the baseline
+fn ceiling(values: &[f64]) -> f64 {
+    let max = values
+        .iter()
+        .copied()
+        .filter(|v| v.is_finite())
+        .fold(0.0, f64::max);
+    if max > 0.0 {
+        max
+    } else {
+        1.0
+    }
+}
+
+/// Height of `value` above the bottom edge, leaving a pixel for the stroke
+fn scale(value: f64, ceiling: f64) -> f64 {
+    let value = if value.is_finite() {
+        value.max(0.0)
+    } else {
+        0.0
+    };
+    (value / ceiling) * (HEIGHT - 2.0)
+}
+
+/// A line over `values` (oldest first) with a faint fill underneath
+pub fn sparkline(values: &[f64], label: &str) -> Chart {
+    let mut svg = open(label);
+    if values.len() >= 2 {
+        let ceiling = ceiling(values);
+        let step = WIDTH / (values.len() - 1) as f64;
+        let mut points = String::new();
+        for (i, value) in values.iter().enumerate() {
+            let y = HEIGHT - 1.0 - scale(*value, ceiling);
+            let _ = write!(points, "{:.1},{:.1} ", i as f64 * step, y);
+        }
+        let points = points.trim_end();
+        let _ = write!(
+            svg,
+            r#"<polygon points="0,{HEIGHT} {points} {WIDTH},{HEIGHT}" fill="currentColor" fill-opacity="0.15"/><polyline points="{points}" fill="none" stroke="currentColor" stroke-width="1.5" vector-effect="non-scaling-stroke"/>"#
+        );
+    } else {
+        // Not enough data for a line yet: draw the baseline
+        let _ = write!(
+            svg,
+            r#"<line x1="0" y1="{y}" x2="{WIDTH}" y2="{y}" stroke="currentColor" stroke-opacity="0.3" vector-effect="non-scaling-stroke"/>"#,
+            y = HEIGHT - 1.0
+        );
+    }
+    svg.push_str("</svg>");
+    Chart(svg)
+}
+
+/// One bar per value (oldest first)
+pub fn bars(values: &[f64], label: &str) -> Chart {
+    let mut svg = open(label);
+    if !values.is_empty() {
+        let ceiling = ceiling(values);
+        let slot = WIDTH / values.len() as f64;
+        let width = (slot - BAR_GAP).max(0.5);
+        for (i, value) in values.iter().enumerate() {
+            // Zero still gets a sliver, so the time axis stays readable
+            let height = scale(*value, ceiling).max(1.0);
+            let _ = write!(
+                svg,
+                r#"<rect x="{:.1}" y="{:.1}" width="{width:.1}" height="{height:.1}" fill="currentColor"/>"#,
+                i as f64 * slot,
+                HEIGHT - height
+            );
+        }
+    }
+    svg.push_str("</svg>");
+    Chart(svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline() {
+        let chart = sparkline(&[0.0, 15.0, 30.0], "Requests <per minute>");
+        let svg = chart.as_str();
+        assert!(svg.contains(r#"aria-label="Requests &lt;per minute&gt;""#));
+        // Scaled to the largest value: bottom, middle, top
+        assert!(svg.contains(r#"<polyline points="0.0,31.0 60.0,16.0 120.0,1.0""#));
+
+        assert!(sparkline(&[5.0], "one").as_str().contains("<line"));
+        assert!(!sparkline(&[f64::NAN, 1.0], "nan").as_str().contains("NaN"));
+    }
+
+    #[test]
+    fn test_bars() {
+        let chart = bars(&[0.0, 4.0], "Sessions");
+        assert_eq!(chart.as_str().matches("<rect").count(), 2);
+        assert!(chart
+            .as_str()
+            .contains(r#"y="2.0" width="59.0" height="30.0""#));
+        assert!(!bars(&[], "empty").as_str().contains("<rect"));
+    }
+}
@@ -0,0 +1,408 @@
+//! Comment Service — threaded user comments
+//!
+//! Bodies are stored as submitted and sanitized on the way out (see
+//! [`crate::services::sanitize`]), so tightening the allowlist applies to
+//! existing comments too. Each comment records a hash of the posting
+//! session's ID as its owner; only that session may edit or delete it.
+//! Threads are per tenant, see [`crate::services::tenants::current`].
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::sync::RwLock;
+
+use crate::services::tenants;
+
+/// Comment data model
+#[derive(Debug, Clone, Serialize)]
+pub struct Comment {
+    pub id: u32,
+    pub parent_id: Option<u32>,
+    pub author: String,
+    /// Raw submitted body — sanitize before rendering
+    pub body: String,
+    /// Owner key, see [`owner_key`]
+    #[serde(skip)]
+    pub owner: String,
+    pub created_at: String,
+    pub edited: bool,
+    /// Soft-deleted: kept as a placeholder because it still has replies
+    pub deleted: bool,
+    /// Owning tenant's slug
+    #[serde(skip)]
+    pub tenant: String,
+}
+
+/// Comment service trait — defines operations for comment threads
+pub trait CommentService: Send + Sync {
+    /// Every comment, oldest first
+    fn list_all(&self) -> Vec<Comment>;
+    fn get_by_id(&self, id: u32) -> Option<Comment>;
+    fn create(
+        &self,
+        parent_id: Option<u32>,
+        author: String,
+        body: String,
+        owner: String,
+    ) -> Comment;
+    fn update(&self, id: u32, body: String) -> Option<Comment>;
+    /// Remove a comment. One with replies is soft-deleted so the thread
+    /// keeps its shape.
+    fn delete(&self, id: u32) -> bool;
+}
+
+/// Owner key for a session — a hash, so the table never holds live session IDs
+pub fn owner_key(session_id: &str) -> String {
+    hex::encode(Sha256::digest(session_id.as_bytes()))
+}
+
+/// Order comments depth-first (replies under their parent, oldest first at
+/// each level) and pair each with its nesting depth. Replies whose parent is
+/// missing are dropped.
+pub fn thread(comments: Vec<Comment>) -> Vec<(Comment, usize)> {
+    fn visit(
+        parent_id: Option<u32>,
+        depth: usize,
+        comments: &[Comment],
+        out: &mut Vec<(Comment, usize)>,
+    ) {
+        for comment in comments.iter().filter(|c| c.parent_id == parent_id) {
+            out.push((comment.clone(), depth));
+            visit(Some(comment.id), depth + 1, comments, out);
+        }
+    }
+
+    let mut out = Vec::with_capacity(comments.len());
+    visit(None, 0, &comments, &mut out);
+    out
+}
+
+fn now() -> String {
+    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// In-memory comment storage (good for prototyping, tests)
+pub struct InMemoryCommentService {
+    comments: RwLock<Vec<Comment>>,
+    next_id: RwLock<u32>,
+}
+
+impl InMemoryCommentService {
+    pub fn new() -> Self {
+        Self {
+            comments: RwLock::new(Vec::new()),
+            next_id: RwLock::new(1),
+        }
+    }
+}
+
+impl Default for InMemoryCommentService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommentService for InMemoryCommentService {
+    fn list_all(&self) -> Vec<Comment> {
+        let tenant = tenants::current();
+        self.comments
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|c| c.tenant == tenant)
+            .cloned()
+            .collect()
+    }
+
+    fn get_by_id(&self, id: u32) -> Option<Comment> {
+        let tenant = tenants::current();
+        self.comments
+            .read()
+            .unwrap()
+            .iter()
+            .find(|c| c.id == id && c.tenant == tenant)
+            .cloned()
+    }
+
+    fn create(
+        &self,
+        parent_id: Option<u32>,
+        author: String,
+        body: String,
+        owner: String,
+    ) -> Comment {
+        let mut next_id = self.next_id.write().unwrap();
+        let comment = Comment {
+            id: *next_id,
+            parent_id,
+            author,
+            body,
+            owner,
+            created_at: now(),
+            edited: false,
+            deleted: false,
+            tenant: tenants::current(),
+        };
+        *next_id += 1;
+
+        self.comments.write().unwrap().push(comment.clone());
+        comment
+    }
+
+    fn update(&self, id: u32, body: String) -> Option<Comment> {
+        let tenant = tenants::current();
+        let mut comments = self.comments.write().unwrap();
+        let comment = comments
+            .iter_mut()
+            .find(|c| c.id == id && !c.deleted && c.tenant == tenant)?;
+        comment.body = body;
+        comment.edited = true;
+        Some(comment.clone())
+    }
+
+    fn delete(&self, id: u32) -> bool {
+        let tenant = tenants::current();
+        let mut comments = self.comments.write().unwrap();
+        if !comments.iter().any(|c| c.id == id && c.tenant == tenant) {
+            return false;
+        }
+        if comments.iter().any(|c| c.parent_id == Some(id)) {
+            match comments.iter_mut().find(|c| c.id == id) {
+                Some(comment) => {
+                    comment.deleted = true;
+                    comment.body.clear();
+                    true
+                }
+                None => false,
+            }
+        } else {
+            let len_before = comments.len();
+            comments.retain(|c| c.id != id);
+            let deleted = comments.len() < len_before;
+            // Placeholders whose last reply just went are dropped too
+            loop {
+                let orphaned: Vec<u32> = comments
+                    .iter()
+                    .filter(|c| c.deleted && !comments.iter().any(|r| r.parent_id == Some(c.id)))
+                    .map(|c| c.id)
+                    .collect();
+                if orphaned.is_empty() {
+                    break;
+                }
+                comments.retain(|c| !orphaned.contains(&c.id));
+            }
+            deleted
+        }
+    }
+}
+
+// ============================================================================
+// SQLx Implementation — SQLite-backed comment storage
+// ============================================================================
+
+use crate::db::Db;
+
+pub struct SqliteCommentService {
+    db: Db,
+}
+
+impl SqliteCommentService {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+}
+
+/// Row type returned by SQLx queries
+#[derive(sqlx::FromRow)]
+struct CommentRow {
+    id: i64,
+    parent_id: Option<i64>,
+    author: String,
+    body: String,
+    owner: String,
+    created_at: String,
+    updated_at: Option<String>,
+    deleted: i32,
+    tenant: String,
+}
+
+impl From<CommentRow> for Comment {
+    fn from(row: CommentRow) -> Self {
+        Comment {
+            id: row.id as u32,
+            parent_id: row.parent_id.map(|id| id as u32),
+            author: row.author,
+            body: row.body,
+            owner: row.owner,
+            created_at: row.created_at,
+            edited: row.updated_at.is_some(),
+            deleted: row.deleted != 0,
+            tenant: row.tenant,
+        }
+    }
+}
+
+const COMMENT_COLUMNS: &str =
+    "id, parent_id, author, body, owner, created_at, updated_at, deleted, tenant";
+
+impl CommentService for SqliteCommentService {
+    fn list_all(&self) -> Vec<Comment> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, CommentRow>(&format!(
+                    "SELECT {COMMENT_COLUMNS} FROM comments WHERE tenant = ? ORDER BY id"
+                ))
+                .bind(tenants::current())
+                .fetch_all(self.db.reader())
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(Comment::from)
+                .collect()
+            })
+        })
+    }
+
+    fn get_by_id(&self, id: u32) -> Option<Comment> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, CommentRow>(&format!(
+                    "SELECT {COMMENT_COLUMNS} FROM comments WHERE id = ? AND tenant = ?"
+                ))
+                .bind(id as i64)
+                .bind(tenants::current())
+                .fetch_optional(self.db.reader())
+                .await
+                .ok()
+                .flatten()
+                .map(Comment::from)
+            })
+        })
+    }
+
+    fn create(
+        &self,
+        parent_id: Option<u32>,
+        author: String,
+        body: String,
+        owner: String,
+    ) -> Comment {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let row = sqlx::query_as::<_, CommentRow>(&format!(
+                    "INSERT INTO comments (parent_id, author, body, owner, created_at, tenant) \
+                     VALUES (?, ?, ?, ?, ?, ?) RETURNING {COMMENT_COLUMNS}"
+                ))
+                .bind(parent_id.map(i64::from))
+                .bind(&author)
+                .bind(&body)
+                .bind(&owner)
+                .bind(now())
+                .bind(tenants::current())
+                .fetch_one(self.db.writer())
+                .await
+                .expect("Failed to insert comment");
+                Comment::from(row)
+            })
+        })
+    }
+
+    fn update(&self, id: u32, body: String) -> Option<Comment> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, CommentRow>(&format!(
+                    "UPDATE comments SET body = ?, updated_at = ? \
+                     WHERE id = ? AND deleted = 0 AND tenant = ? RETURNING {COMMENT_COLUMNS}"
+                ))
+                .bind(&body)
+                .bind(now())
+                .bind(id as i64)
+                .bind(tenants::current())
+                .fetch_optional(self.db.writer())
+                .await
+                .ok()
+                .flatten()
+                .map(Comment::from)
+            })
+        })
+    }
+
+    fn delete(&self, id: u32) -> bool {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let tenant = tenants::current();
+                // Soft-delete when replies hang off this comment
+                let result = sqlx::query(
+                    "UPDATE comments SET deleted = 1, body = '' WHERE id = ? AND tenant = ? \
+                     AND EXISTS (SELECT 1 FROM comments WHERE parent_id = ?)",
+                )
+                .bind(id as i64)
+                .bind(&tenant)
+                .bind(id as i64)
+                .execute(self.db.writer())
+                .await;
+                if matches!(result, Ok(ref r) if r.rows_affected() > 0) {
+                    return true;
+                }
+
+                let result = sqlx::query("DELETE FROM comments WHERE id = ? AND tenant = ?")
+                    .bind(id as i64)
+                    .bind(&tenant)
+                    .execute(self.db.writer())
+                    .await;
+                let deleted = matches!(result, Ok(r) if r.rows_affected() > 0);
+
+                // Placeholders whose last reply just went are dropped too
+                while let Ok(r) = sqlx::query(
+                    "DELETE FROM comments WHERE deleted = 1 AND NOT EXISTS \
+                     (SELECT 1 FROM comments AS reply WHERE reply.parent_id = comments.id)",
+                )
+                .execute(self.db.writer())
+                .await
+                {
+                    if r.rows_affected() == 0 {
+                        break;
+                    }
+                }
+                deleted
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_order_and_soft_delete() {
+        let service = InMemoryCommentService::new();
+        let root = service.create(None, "Ada".into(), "First".into(), "a".into());
+        let second = service.create(None, "Bob".into(), "Second".into(), "b".into());
+        let reply = service.create(Some(root.id), "Bob".into(), "Reply".into(), "b".into());
+
+        let order: Vec<(u32, usize)> = thread(service.list_all())
+            .iter()
+            .map(|(c, depth)| (c.id, *depth))
+            .collect();
+        assert_eq!(order, [(root.id, 0), (reply.id, 1), (second.id, 0)]);
+
+        // Has a reply → placeholder stays; leaf → gone, taking the placeholder
+        assert!(service.delete(root.id));
+        assert!(service.get_by_id(root.id).unwrap().deleted);
+        assert!(service.delete(reply.id));
+        assert!(service.get_by_id(reply.id).is_none());
+        assert!(service.get_by_id(root.id).is_none());
+    }
+
+    #[test]
+    fn test_scoped_to_tenant() {
+        let service = InMemoryCommentService::new();
+        let comment = service.create(None, "Ada".into(), "Hi".into(), "a".into());
+        tenants::sync_scope("acme", || {
+            assert!(service.list_all().is_empty());
+            assert!(service.update(comment.id, "Edited".into()).is_none());
+            assert!(!service.delete(comment.id));
+        });
+        assert_eq!(service.list_all().len(), 1);
+    }
+}
@@ -28,6 +28,16 @@ impl CsrfSecret {
         Self(key)
     }
 
+    /// Use the configured `[security] csrf_secret`; empty generates one, so
+    /// tokens don't survive a restart or work across instances
+    pub fn new(secret: &str) -> Self {
+        if secret.is_empty() {
+            Self::generate()
+        } else {
+            Self(secret.as_bytes().to_vec())
+        }
+    }
+
     /// Generate a CSRF token bound to a session ID
     pub fn generate_token(&self, session_id: &str) -> String {
         // Random nonce
@@ -77,7 +87,7 @@ impl CsrfSecret {
 }
 
 /// Constant-time byte comparison to prevent timing attacks
-fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
     if a.len() != b.len() {
         return false;
     }
@@ -110,4 +120,11 @@ mod tests {
         let t2 = secret.generate_token("session");
         assert_ne!(t1, t2); // Different nonces
     }
+
+    #[test]
+    fn test_configured_secret_survives_restart() {
+        let token = CsrfSecret::new("a-configured-secret").generate_token("session");
+        assert!(CsrfSecret::new("a-configured-secret").validate_token(&token, "session"));
+        assert!(!CsrfSecret::new("").validate_token(&token, "session"));
+    }
 }
@@ -0,0 +1,68 @@
+//! Flash Messages — one-shot notices carried across a redirect or swap
+//!
+//! Stored as JSON in the session under [`FLASH_KEY`] and consumed by the next
+//! template render (see [`crate::globals::TemplateGlobals`]).
+
+use serde::{Deserialize, Serialize};
+
+use super::SessionStore;
+
+/// Session data key holding pending flash messages
+pub const FLASH_KEY: &str = "_flash";
+
+/// A single flash notice. `level` maps to an `alert-*` CSS class.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlashMessage {
+    pub level: String,
+    pub message: String,
+}
+
+impl FlashMessage {
+    pub fn new(level: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            level: level.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Queue a flash message for the session's next rendered template
+pub fn push(store: &dyn SessionStore, session_id: &str, flash: FlashMessage) {
+    let mut pending = take(store, session_id);
+    pending.push(flash);
+    if let Ok(json) = serde_json::to_string(&pending) {
+        store.set_data(session_id, FLASH_KEY, json);
+    }
+}
+
+/// Remove and return every pending flash message for the session
+pub fn take(store: &dyn SessionStore, session_id: &str) -> Vec<FlashMessage> {
+    store
+        .take_data(session_id, FLASH_KEY)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::InMemorySessionStore;
+
+    #[test]
+    fn test_push_then_take_once() {
+        let store = InMemorySessionStore::new();
+        let session = store.create();
+
+        push(&store, &session.id, FlashMessage::new("success", "Saved"));
+        push(
+            &store,
+            &session.id,
+            FlashMessage::new("warning", "Check input"),
+        );
+
+        let flashes = take(&store, &session.id);
+        assert_eq!(flashes.len(), 2);
+        assert_eq!(flashes[0].message, "Saved");
+        assert!(take(&store, &session.id).is_empty());
+    }
+}
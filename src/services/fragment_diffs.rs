@@ -0,0 +1,140 @@
+//! Fragment Diffs — the last render sent into each polled target
+//!
+//! [`diff_fragments`](crate::middleware::diff_fragments) answers a poll with
+//! just the children that changed since the render the page already shows.
+//! This remembers that render per session, URL and `HX-Target`: its digest,
+//! which the page sends back to show it's still showing it, and its
+//! [`Shape`]. Entries idle for [`ENTRY_TTL`] are forgotten, and past
+//! [`MAX_ENTRIES`] the least recently polled go first; everything is in
+//! memory and starts over on restart, which only costs a full render.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::utils::html_diff::Shape;
+
+/// How long an unpolled target is remembered
+pub const ENTRY_TTL: Duration = Duration::from_secs(10 * 60);
+/// Targets tracked before the least recently polled are forgotten early
+pub const MAX_ENTRIES: usize = 10_000;
+
+/// A render that was sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentRender {
+    /// Short hex SHA-256 of the body
+    pub digest: String,
+    pub shape: Shape,
+}
+
+pub struct FragmentDiffs {
+    entries: Mutex<Entries>,
+    max_entries: usize,
+}
+
+#[derive(Default)]
+struct Entries {
+    by_key: HashMap<String, (Instant, SentRender)>,
+    /// `(last_poll, key)` of every entry, least recently polled first
+    by_access: BTreeSet<(Instant, String)>,
+}
+
+impl Entries {
+    fn remove(&mut self, key: &str) -> Option<(Instant, SentRender)> {
+        let (at, render) = self.by_key.remove(key)?;
+        self.by_access.remove(&(at, key.to_string()));
+        Some((at, render))
+    }
+}
+
+impl FragmentDiffs {
+    pub fn new() -> Self {
+        Self::with_max(MAX_ENTRIES)
+    }
+
+    /// Diffs for at most `max_entries` targets
+    pub fn with_max(max_entries: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries::default()),
+            max_entries: max_entries.max(1),
+        }
+    }
+
+    /// Remember `render` as the last one sent for `key`, returning the one
+    /// before it
+    pub fn replace(&self, key: &str, render: SentRender) -> Option<SentRender> {
+        self.replace_at(key, render, Instant::now())
+    }
+
+    fn replace_at(&self, key: &str, render: SentRender, now: Instant) -> Option<SentRender> {
+        let mut entries = self.entries.lock().unwrap();
+        let previous = entries
+            .remove(key)
+            .filter(|(at, _)| now.duration_since(*at) < ENTRY_TTL)
+            .map(|(_, previous)| previous);
+        // Idle entries first, then the least recently polled to make room
+        while let Some((at, oldest)) = entries.by_access.first().cloned() {
+            if now.duration_since(at) < ENTRY_TTL && entries.by_key.len() < self.max_entries {
+                break;
+            }
+            entries.remove(&oldest);
+        }
+        entries.by_access.insert((now, key.to_string()));
+        entries.by_key.insert(key.to_string(), (now, render));
+        previous
+    }
+
+    /// Forget `key`, whose last answer wasn't diffable
+    pub fn forget(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+    }
+}
+
+impl Default for FragmentDiffs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::html_diff;
+
+    fn render(digest: &str) -> SentRender {
+        let (root, children) = html_diff::children(r#"<div id="a">1</div>"#, "t").unwrap();
+        SentRender {
+            digest: digest.to_string(),
+            shape: Shape::new(root, &children),
+        }
+    }
+
+    #[test]
+    fn test_replace() {
+        let diffs = FragmentDiffs::new();
+        let start = Instant::now();
+        assert_eq!(diffs.replace_at("k", render("1"), start), None);
+        assert_eq!(diffs.replace_at("k", render("2"), start), Some(render("1")));
+        assert_eq!(diffs.replace_at("other", render("3"), start), None);
+
+        let later = start + ENTRY_TTL;
+        assert_eq!(diffs.replace_at("k", render("4"), later), None);
+        diffs.forget("k");
+        assert_eq!(diffs.replace("k", render("5")), None);
+    }
+
+    #[test]
+    fn test_least_recently_polled_go_first_when_full() {
+        let diffs = FragmentDiffs::with_max(2);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        diffs.replace_at("a", render("1"), at(0));
+        diffs.replace_at("b", render("2"), at(1));
+        diffs.replace_at("a", render("3"), at(2));
+        diffs.replace_at("c", render("4"), at(3));
+
+        assert_eq!(diffs.entries.lock().unwrap().by_key.len(), 2);
+        assert_eq!(diffs.replace_at("a", render("5"), at(4)), Some(render("3")));
+        assert_eq!(diffs.replace_at("b", render("6"), at(5)), None);
+    }
+}
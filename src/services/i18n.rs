@@ -0,0 +1,250 @@
+//! I18n Service — locale negotiation and date names
+//!
+//! [`I18n`] picks the [`Locale`] that best matches a request's
+//! `Accept-Language` header, falling back to the first one registered
+//! (English by default). A locale carries month and weekday names and the
+//! patterns dates are written with, enough for server-rendered widgets like
+//! the date picker to speak the visitor's language. Weeks start on Monday
+//! everywhere, like [`crate::services::calendar::month_grid`].
+
+use chrono::{Datelike, NaiveDate};
+
+/// Names and patterns for one language
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    /// Primary language subtag, as used in `lang` attributes: `en`, `de`…
+    pub code: &'static str,
+    pub months: [&'static str; 12],
+    /// Monday first
+    pub weekdays: [&'static str; 7],
+    /// Monday first
+    pub weekdays_short: [&'static str; 7],
+    /// `{month}` and `{year}`, e.g. `{month} {year}`
+    pub month_year: &'static str,
+    /// `{weekday}`, `{day}`, `{month}` and `{year}`
+    pub long_date: &'static str,
+    pub previous_month: &'static str,
+    pub next_month: &'static str,
+    pub no_date: &'static str,
+}
+
+impl Locale {
+    pub fn month_name(&self, date: NaiveDate) -> &'static str {
+        self.months[date.month0() as usize]
+    }
+
+    pub fn weekday_name(&self, date: NaiveDate) -> &'static str {
+        self.weekdays[date.weekday().num_days_from_monday() as usize]
+    }
+
+    /// e.g. `March 2024`
+    pub fn format_month(&self, date: NaiveDate) -> String {
+        self.month_year
+            .replace("{month}", self.month_name(date))
+            .replace("{year}", &date.year().to_string())
+    }
+
+    /// e.g. `Thursday, 14 March 2024`
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        self.long_date
+            .replace("{weekday}", self.weekday_name(date))
+            .replace("{day}", &date.day().to_string())
+            .replace("{month}", self.month_name(date))
+            .replace("{year}", &date.year().to_string())
+    }
+}
+
+pub const EN: Locale = Locale {
+    code: "en",
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    weekdays: [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ],
+    weekdays_short: ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"],
+    month_year: "{month} {year}",
+    long_date: "{weekday}, {day} {month} {year}",
+    previous_month: "Previous month",
+    next_month: "Next month",
+    no_date: "No date chosen",
+};
+
+pub const DE: Locale = Locale {
+    code: "de",
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    weekdays: [
+        "Montag",
+        "Dienstag",
+        "Mittwoch",
+        "Donnerstag",
+        "Freitag",
+        "Samstag",
+        "Sonntag",
+    ],
+    weekdays_short: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+    month_year: "{month} {year}",
+    long_date: "{weekday}, {day}. {month} {year}",
+    previous_month: "Vorheriger Monat",
+    next_month: "Nächster Monat",
+    no_date: "Kein Datum gewählt",
+};
+
+pub const FR: Locale = Locale {
+    code: "fr",
+    months: [
+        "janvier",
+        "février",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "août",
+        "septembre",
+        "octobre",
+        "novembre",
+        "décembre",
+    ],
+    weekdays: [
+        "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+    ],
+    weekdays_short: ["lu", "ma", "me", "je", "ve", "sa", "di"],
+    month_year: "{month} {year}",
+    long_date: "{weekday} {day} {month} {year}",
+    previous_month: "Mois précédent",
+    next_month: "Mois suivant",
+    no_date: "Aucune date choisie",
+};
+
+pub const ES: Locale = Locale {
+    code: "es",
+    months: [
+        "enero",
+        "febrero",
+        "marzo",
+        "abril",
+        "mayo",
+        "junio",
+        "julio",
+        "agosto",
+        "septiembre",
+        "octubre",
+        "noviembre",
+        "diciembre",
+    ],
+    weekdays: [
+        "lunes",
+        "martes",
+        "miércoles",
+        "jueves",
+        "viernes",
+        "sábado",
+        "domingo",
+    ],
+    weekdays_short: ["lu", "ma", "mi", "ju", "vi", "sá", "do"],
+    month_year: "{month} de {year}",
+    long_date: "{weekday}, {day} de {month} de {year}",
+    previous_month: "Mes anterior",
+    next_month: "Mes siguiente",
+    no_date: "Ninguna fecha elegida",
+};
+
+/// The locales on offer; the first is the fallback
+pub struct I18n {
+    locales: Vec<Locale>,
+}
+
+impl I18n {
+    /// `locales` must not be empty; the first is used when nothing matches
+    pub fn new(locales: Vec<Locale>) -> Self {
+        assert!(!locales.is_empty(), "I18n needs at least one locale");
+        Self { locales }
+    }
+
+    pub fn locale(&self, code: &str) -> Option<&Locale> {
+        self.locales
+            .iter()
+            .find(|locale| locale.code.eq_ignore_ascii_case(code))
+    }
+
+    /// The best match for an `Accept-Language` header, by quality and then
+    /// order, comparing primary subtags: `de-AT;q=0.8` matches `de`
+    pub fn negotiate(&self, accept_language: &str) -> &Locale {
+        let mut ranges: Vec<(&str, f32)> = accept_language
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // Stable, so equal qualities keep the client's order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges
+            .into_iter()
+            .find_map(|(tag, _)| self.locale(tag.split('-').next().unwrap_or(tag)))
+            .unwrap_or(&self.locales[0])
+    }
+}
+
+impl Default for I18n {
+    /// English, German, French and Spanish
+    fn default() -> Self {
+        Self::new(vec![EN, DE, FR, ES])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_and_format() {
+        let i18n = I18n::default();
+        assert_eq!(i18n.negotiate("").code, "en");
+        assert_eq!(i18n.negotiate("ja, *;q=0.1").code, "en");
+        assert_eq!(i18n.negotiate("de-AT,de;q=0.9,en;q=0.8").code, "de");
+        assert_eq!(i18n.negotiate("en;q=0.5, fr-CA;q=0.7").code, "fr");
+        assert_eq!(i18n.negotiate("es;q=0, FR").code, "fr");
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        assert_eq!(EN.format_month(date), "March 2024");
+        assert_eq!(EN.format_date(date), "Thursday, 14 March 2024");
+        assert_eq!(DE.format_date(date), "Donnerstag, 14. März 2024");
+        assert_eq!(ES.format_month(date), "marzo de 2024");
+    }
+}
@@ -0,0 +1,233 @@
+//! Idempotency — a repeated submission gets the first one's answer
+//!
+//! A client that can't tell whether its POST arrived (the connection dropped
+//! before the response did) sends it again with the same `Idempotency-Key`
+//! header. [`idempotency`](crate::middleware::idempotency) claims the key
+//! here before running the handler and stores the response after, so the
+//! repeat is answered from the store and the handler runs once. Keys are
+//! scoped by the caller (session and path) and forgotten after [`KEY_TTL`],
+//! or sooner once [`MAX_KEYS`] are held; everything is in memory and starts
+//! over on restart.
+
+use axum::body::Bytes;
+use axum::http::{HeaderMap, StatusCode};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a key is remembered
+pub const KEY_TTL: Duration = Duration::from_secs(24 * 3600);
+/// Keys held before the oldest are forgotten early
+pub const MAX_KEYS: usize = 10_000;
+
+/// A response kept for replaying
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+#[derive(Debug, Clone)]
+enum Entry {
+    /// The first request is still being handled
+    Pending,
+    Done(StoredResponse),
+}
+
+/// What [`IdempotencyStore::claim`] found
+#[derive(Debug, Clone)]
+pub enum Claim {
+    /// First use: handle the request, then [`complete`](IdempotencyStore::complete)
+    /// or [`release`](IdempotencyStore::release) the key
+    New,
+    /// The first request with this key hasn't finished yet
+    Pending,
+    /// Answer with this
+    Done(StoredResponse),
+}
+
+pub struct IdempotencyStore {
+    entries: Mutex<Entries>,
+    max_keys: usize,
+}
+
+#[derive(Default)]
+struct Entries {
+    by_key: HashMap<String, (Instant, Entry)>,
+    /// `(stored_at, key)` of every entry, oldest first
+    by_age: BTreeSet<(Instant, String)>,
+}
+
+impl Entries {
+    fn remove(&mut self, key: &str) {
+        if let Some((at, _)) = self.by_key.remove(key) {
+            self.by_age.remove(&(at, key.to_string()));
+        }
+    }
+
+    /// Store `entry`, dropping expired keys and then the oldest to make room
+    fn insert(&mut self, key: &str, now: Instant, entry: Entry, max_keys: usize) {
+        self.remove(key);
+        while let Some((at, oldest)) = self.by_age.first().cloned() {
+            if now.duration_since(at) < KEY_TTL && self.by_key.len() < max_keys {
+                break;
+            }
+            self.remove(&oldest);
+        }
+        self.by_age.insert((now, key.to_string()));
+        self.by_key.insert(key.to_string(), (now, entry));
+    }
+}
+
+impl IdempotencyStore {
+    pub fn new() -> Self {
+        Self::with_max(MAX_KEYS)
+    }
+
+    /// A store holding at most `max_keys`
+    pub fn with_max(max_keys: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries::default()),
+            max_keys: max_keys.max(1),
+        }
+    }
+
+    /// Look `key` up, claiming it when it's new
+    pub fn claim(&self, key: &str) -> Claim {
+        self.claim_at(key, Instant::now())
+    }
+
+    fn claim_at(&self, key: &str, now: Instant) -> Claim {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.by_key.get(key) {
+            Some((at, entry)) if now.duration_since(*at) < KEY_TTL => match entry {
+                Entry::Pending => Claim::Pending,
+                Entry::Done(response) => Claim::Done(response.clone()),
+            },
+            _ => {
+                entries.insert(key, now, Entry::Pending, self.max_keys);
+                Claim::New
+            }
+        }
+    }
+
+    /// Hold a key [`claim`](Self::claim) found new until its answer is
+    /// stored; dropping the guard first releases it
+    pub fn hold(&self, key: String) -> ClaimGuard<'_> {
+        ClaimGuard {
+            store: self,
+            key,
+            completed: false,
+        }
+    }
+
+    /// Store the answer to a claimed key
+    pub fn complete(&self, key: &str, response: StoredResponse) {
+        self.entries.lock().unwrap().insert(
+            key,
+            Instant::now(),
+            Entry::Done(response),
+            self.max_keys,
+        );
+    }
+
+    /// Forget a claimed key whose answer isn't worth replaying (a server
+    /// error), so the next attempt runs the handler again
+    pub fn release(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+    }
+}
+
+impl Default for IdempotencyStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A claimed key, released when dropped before
+/// [`complete`](ClaimGuard::complete) — so a handler that panics, or a
+/// request abandoned mid-flight, doesn't leave the key pending for a day
+pub struct ClaimGuard<'a> {
+    store: &'a IdempotencyStore,
+    key: String,
+    completed: bool,
+}
+
+impl ClaimGuard<'_> {
+    /// Store the answer, keeping the key
+    pub fn complete(mut self, response: StoredResponse) {
+        self.store.complete(&self.key, response);
+        self.completed = true;
+    }
+}
+
+impl Drop for ClaimGuard<'_> {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.store.release(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_then_replay() {
+        let store = IdempotencyStore::new();
+        let start = Instant::now();
+        assert!(matches!(store.claim_at("a", start), Claim::New));
+        assert!(matches!(store.claim_at("a", start), Claim::Pending));
+        store.complete(
+            "a",
+            StoredResponse {
+                status: StatusCode::CREATED,
+                headers: HeaderMap::new(),
+                body: Bytes::from_static(b"done"),
+            },
+        );
+        match store.claim("a") {
+            Claim::Done(response) => assert_eq!(response.body, "done"),
+            other => panic!("expected the stored response, got {other:?}"),
+        }
+        assert!(matches!(
+            store.claim_at("a", Instant::now() + KEY_TTL),
+            Claim::New
+        ));
+
+        assert!(matches!(store.claim("b"), Claim::New));
+        store.release("b");
+        assert!(matches!(store.claim("b"), Claim::New));
+    }
+
+    #[test]
+    fn test_dropped_guard_releases() {
+        let store = IdempotencyStore::new();
+        assert!(matches!(store.claim("a"), Claim::New));
+        drop(store.hold("a".into()));
+        assert!(matches!(store.claim("a"), Claim::New));
+
+        store.hold("a".into()).complete(StoredResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+        });
+        assert!(matches!(store.claim("a"), Claim::Done(_)));
+    }
+
+    #[test]
+    fn test_oldest_keys_go_first_when_full() {
+        let store = IdempotencyStore::with_max(2);
+        let start = Instant::now();
+        for (i, key) in ["a", "b", "c"].into_iter().enumerate() {
+            let at = start + Duration::from_secs(i as u64);
+            assert!(matches!(store.claim_at(key, at), Claim::New));
+        }
+        assert_eq!(store.entries.lock().unwrap().by_key.len(), 2);
+        assert!(matches!(store.claim("c"), Claim::Pending));
+        assert!(matches!(store.claim("b"), Claim::Pending));
+        assert!(matches!(store.claim("a"), Claim::New));
+    }
+}
@@ -0,0 +1,349 @@
+//! Invoice Service — issued invoices and their lines
+//!
+//! An invoice is written once and never edited: its lines carry their own
+//! descriptions and prices, and the tax rate is the one it was issued under,
+//! so it reads the same after the catalogue or [`TAX_RATE`] changes. Like
+//! notifications, invoices belong to an owner key
+//! ([`crate::services::comments::owner_key`]) and [`InvoiceService::get`]
+//! only finds the owner's, so ids in URLs give nothing away.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::sync::RwLock;
+
+/// Tax added to the subtotal of new invoices, in percent
+pub const TAX_RATE: u64 = 20;
+
+/// Days from issue to due date
+pub const PAYMENT_TERMS_DAYS: u64 = 30;
+
+/// One line of an invoice
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InvoiceLine {
+    pub description: String,
+    pub quantity: u32,
+    pub unit_cents: u64,
+}
+
+impl InvoiceLine {
+    pub fn amount_cents(&self) -> u64 {
+        self.quantity as u64 * self.unit_cents
+    }
+}
+
+/// Invoice data model
+#[derive(Debug, Clone, Serialize)]
+pub struct Invoice {
+    pub id: u32,
+    /// Owner key, see [`crate::services::comments::owner_key`]
+    #[serde(skip)]
+    pub owner: String,
+    /// Name and address, one line each
+    pub bill_to: Vec<String>,
+    pub issued: NaiveDate,
+    pub due: NaiveDate,
+    /// Percent
+    pub tax_rate: u64,
+    pub lines: Vec<InvoiceLine>,
+}
+
+impl Invoice {
+    /// `INV-2024-0042`: the issue year and the id
+    pub fn number(&self) -> String {
+        format!("INV-{}-{:04}", self.issued.format("%Y"), self.id)
+    }
+
+    pub fn subtotal_cents(&self) -> u64 {
+        self.lines.iter().map(InvoiceLine::amount_cents).sum()
+    }
+
+    /// Rounded half up to the cent
+    pub fn tax_cents(&self) -> u64 {
+        (self.subtotal_cents() * self.tax_rate + 50) / 100
+    }
+
+    pub fn total_cents(&self) -> u64 {
+        self.subtotal_cents() + self.tax_cents()
+    }
+}
+
+/// An invoice before it's issued
+#[derive(Debug, Clone)]
+pub struct NewInvoice {
+    pub owner: String,
+    pub bill_to: Vec<String>,
+    pub lines: Vec<InvoiceLine>,
+}
+
+/// Invoice service trait — defines storage for invoices
+pub trait InvoiceService: Send + Sync {
+    /// Issue an invoice today, at [`TAX_RATE`], due in [`PAYMENT_TERMS_DAYS`]
+    fn create(&self, invoice: NewInvoice) -> Invoice;
+    /// One of the owner's invoices; `None` for anyone else's
+    fn get(&self, owner: &str, id: u32) -> Option<Invoice>;
+}
+
+/// Issue and due dates for an invoice issued now
+fn dates() -> (NaiveDate, NaiveDate) {
+    let issued = chrono::Utc::now().date_naive();
+    let due = issued + chrono::Days::new(PAYMENT_TERMS_DAYS);
+    (issued, due)
+}
+
+// ============================================================================
+// In-memory implementation
+// ============================================================================
+
+/// In-memory invoice storage (good for prototyping, tests)
+pub struct InMemoryInvoiceService {
+    invoices: RwLock<Vec<Invoice>>,
+}
+
+impl InMemoryInvoiceService {
+    pub fn new() -> Self {
+        Self {
+            invoices: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for InMemoryInvoiceService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InvoiceService for InMemoryInvoiceService {
+    fn create(&self, invoice: NewInvoice) -> Invoice {
+        let mut invoices = self.invoices.write().unwrap();
+        let (issued, due) = dates();
+        let invoice = Invoice {
+            id: invoices.len() as u32 + 1,
+            owner: invoice.owner,
+            bill_to: invoice.bill_to,
+            issued,
+            due,
+            tax_rate: TAX_RATE,
+            lines: invoice.lines,
+        };
+        invoices.push(invoice.clone());
+        invoice
+    }
+
+    fn get(&self, owner: &str, id: u32) -> Option<Invoice> {
+        self.invoices
+            .read()
+            .unwrap()
+            .iter()
+            .find(|i| i.id == id && i.owner == owner)
+            .cloned()
+    }
+}
+
+// ============================================================================
+// SQLx Implementation — SQLite-backed invoice storage
+// ============================================================================
+
+use crate::db::Db;
+
+pub struct SqliteInvoiceService {
+    db: Db,
+}
+
+impl SqliteInvoiceService {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+}
+
+/// Row type returned by SQLx queries
+#[derive(sqlx::FromRow)]
+struct InvoiceRow {
+    id: i64,
+    owner: String,
+    bill_to: String,
+    issued: String,
+    due: String,
+    tax_rate: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct InvoiceLineRow {
+    description: String,
+    quantity: i64,
+    unit_cents: i64,
+}
+
+/// Dates are stored as `YYYY-MM-DD`
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+fn parse_date(value: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(value, DATE_FORMAT).unwrap_or_default()
+}
+
+impl InvoiceRow {
+    fn into_invoice(self, lines: Vec<InvoiceLineRow>) -> Invoice {
+        Invoice {
+            id: self.id as u32,
+            owner: self.owner,
+            bill_to: self.bill_to.lines().map(str::to_string).collect(),
+            issued: parse_date(&self.issued),
+            due: parse_date(&self.due),
+            tax_rate: self.tax_rate as u64,
+            lines: lines
+                .into_iter()
+                .map(|l| InvoiceLine {
+                    description: l.description,
+                    quantity: l.quantity as u32,
+                    unit_cents: l.unit_cents as u64,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// What the cached reads read, and so what every write invalidates
+const TABLES: &[&str] = &["invoices", "invoice_lines"];
+
+impl InvoiceService for SqliteInvoiceService {
+    fn create(&self, invoice: NewInvoice) -> Invoice {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let (issued, due) = dates();
+                let mut tx = self
+                    .db
+                    .writer()
+                    .begin()
+                    .await
+                    .expect("Failed to start invoice transaction");
+                let id: i64 = sqlx::query_scalar(
+                    "INSERT INTO invoices (owner, bill_to, issued, due, tax_rate, created_at) \
+                     VALUES (?, ?, ?, ?, ?, ?) RETURNING id",
+                )
+                .bind(&invoice.owner)
+                .bind(invoice.bill_to.join("\n"))
+                .bind(issued.format(DATE_FORMAT).to_string())
+                .bind(due.format(DATE_FORMAT).to_string())
+                .bind(TAX_RATE as i64)
+                .bind(chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string())
+                .fetch_one(&mut *tx)
+                .await
+                .expect("Failed to insert invoice");
+                for (position, line) in invoice.lines.iter().enumerate() {
+                    sqlx::query(
+                        "INSERT INTO invoice_lines \
+                         (invoice_id, position, description, quantity, unit_cents) \
+                         VALUES (?, ?, ?, ?, ?)",
+                    )
+                    .bind(id)
+                    .bind(position as i64)
+                    .bind(&line.description)
+                    .bind(line.quantity as i64)
+                    .bind(line.unit_cents as i64)
+                    .execute(&mut *tx)
+                    .await
+                    .expect("Failed to insert invoice line");
+                }
+                tx.commit().await.expect("Failed to commit invoice");
+                self.db.cache().invalidate(TABLES);
+                Invoice {
+                    id: id as u32,
+                    owner: invoice.owner,
+                    bill_to: invoice.bill_to,
+                    issued,
+                    due,
+                    tax_rate: TAX_RATE,
+                    lines: invoice.lines,
+                }
+            })
+        })
+    }
+
+    fn get(&self, owner: &str, id: u32) -> Option<Invoice> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let load = || async {
+                    let Some(row) = sqlx::query_as::<_, InvoiceRow>(
+                        "SELECT id, owner, bill_to, issued, due, tax_rate FROM invoices \
+                         WHERE id = ? AND owner = ?",
+                    )
+                    .bind(id as i64)
+                    .bind(owner)
+                    .fetch_optional(self.db.reader())
+                    .await?
+                    else {
+                        return Ok(None);
+                    };
+                    let lines = sqlx::query_as::<_, InvoiceLineRow>(
+                        "SELECT description, quantity, unit_cents FROM invoice_lines \
+                         WHERE invoice_id = ? ORDER BY position",
+                    )
+                    .bind(id as i64)
+                    .fetch_all(self.db.reader())
+                    .await?;
+                    Ok(Some(row.into_invoice(lines)))
+                };
+                self.db
+                    .cache()
+                    .get_or_load(TABLES, "invoices.get", (owner, id), load)
+                    .await
+                    .ok()
+                    .flatten()
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new(owner: &str) -> NewInvoice {
+        NewInvoice {
+            owner: owner.into(),
+            bill_to: vec!["Ada Lovelace".into(), "ada@example.com".into()],
+            lines: vec![
+                InvoiceLine {
+                    description: "Enamel mug".into(),
+                    quantity: 3,
+                    unit_cents: 1_200,
+                },
+                InvoiceLine {
+                    description: "Sticker sheet".into(),
+                    quantity: 1,
+                    unit_cents: 405,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_invoice_totals() {
+        let invoice = InMemoryInvoiceService::new().create(new("ada"));
+        assert_eq!(invoice.subtotal_cents(), 4_005);
+        // 20% of 40.05 is 8.01
+        assert_eq!(invoice.tax_cents(), 801);
+        assert_eq!(invoice.total_cents(), 4_806);
+        assert_eq!(invoice.due - invoice.issued, chrono::Duration::days(30));
+        assert!(invoice.number().ends_with("-0001"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sqlite_owner_only() {
+        let db = crate::db::init_pool(&crate::config::DatabaseConfig {
+            url: "sqlite::memory:".into(),
+            ..crate::config::DatabaseConfig::default()
+        })
+        .await
+        .unwrap();
+        let service = SqliteInvoiceService::new(db);
+        let issued = service.create(new("ada"));
+
+        let found = service.get("ada", issued.id).unwrap();
+        assert_eq!(found.bill_to, issued.bill_to);
+        assert_eq!(found.lines, issued.lines);
+        assert_eq!(found.issued, issued.issued);
+        assert_eq!(found.total_cents(), 4_806);
+        assert!(service.get("grace", issued.id).is_none());
+    }
+}
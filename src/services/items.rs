@@ -2,32 +2,325 @@
 //!
 //! Provides CRUD operations for items. Default implementation uses in-memory storage.
 //! Can be swapped for database-backed implementation (SQLx, etc.)
+//!
+//! Items carry free-form tags, normalized by [`normalize_tag`] so they are safe
+//! to drop into URLs (`/partials/item-list?tag=...`) without encoding.
+//!
+//! Every method is scoped to the [current tenant](crate::services::tenants::current):
+//! another tenant's items can't be listed, fetched or changed.
+//!
+//! Lists come in the order people arrange them in: each item has a
+//! `position`, and [`ItemService::reorder`] moves items into the gaps between
+//! their new neighbours (see [`reposition`]) so a drag usually rewrites a
+//! single row.
+//!
+//! Each item has a slug, unique within its tenant (see
+//! [`crate::services::slugs`]). Renaming an item gives it a new slug and keeps
+//! the old one in the slug history, where [`ItemService::get_by_old_slug`]
+//! finds it.
+//!
+//! [`ItemHub`] numbers changes to each tenant's items, so a long poll
+//! (`/partials/item-list/changes?since=`) can wait for the next one.
 
 use serde::{Deserialize, Serialize};
-use std::sync::RwLock;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::services::slugs::{self, SlugHistory, SlugKind};
+use crate::services::tenants::{self, DEFAULT_TENANT};
 
 /// Item data model
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Item {
     pub id: u32,
+    /// Unique within the tenant, see [`crate::services::slugs`]
+    #[serde(default)]
+    pub slug: String,
     pub title: String,
     pub description: String,
     pub done: bool,
+    /// Normalized tag names, sorted
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// RFC 3339 UTC timestamp
+    #[serde(default)]
+    pub created_at: String,
+    /// Owning tenant's slug
+    #[serde(skip)]
+    pub tenant: String,
+    /// Sort key within the tenant's items, see [`reposition`]
+    #[serde(default)]
+    pub position: i64,
+}
+
+/// Spacing between neighbouring positions, and from the last item to a new one
+pub const POSITION_GAP: i64 = 1024;
+
+/// Most tags a single item can carry
+pub const MAX_TAGS_PER_ITEM: usize = 5;
+/// Longest tag name, in characters
+const MAX_TAG_CHARS: usize = 24;
+
+/// Lowercase `raw`, turn whitespace and underscores into `-` and drop
+/// everything else that isn't ASCII alphanumeric. `None` if nothing is left.
+pub fn normalize_tag(raw: &str) -> Option<String> {
+    let mut tag = String::new();
+    for c in raw.trim().chars() {
+        match c {
+            c if c.is_ascii_alphanumeric() => tag.push(c.to_ascii_lowercase()),
+            c if (c.is_whitespace() || c == '-' || c == '_') && !tag.ends_with('-') => {
+                tag.push('-')
+            }
+            _ => {}
+        }
+    }
+    let tag: String = tag.trim_matches('-').chars().take(MAX_TAG_CHARS).collect();
+    let tag = tag.trim_end_matches('-');
+    (!tag.is_empty()).then(|| tag.to_string())
+}
+
+/// Normalize, dedupe and sort a comma-separated tag list, keeping at most
+/// [`MAX_TAGS_PER_ITEM`]
+pub fn parse_tags(raw: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in raw.split(',').filter_map(normalize_tag) {
+        if !tags.contains(&tag) && tags.len() < MAX_TAGS_PER_ITEM {
+            tags.push(tag);
+        }
+    }
+    tags.sort();
+    tags
 }
 
 /// Item service trait — defines operations for item management
 pub trait ItemService: Send + Sync {
     fn list_all(&self) -> Vec<Item>;
+    /// Items carrying `tag`
+    fn list_tagged(&self, tag: &str) -> Vec<Item>;
+    /// Every tag in use, sorted
+    fn list_tags(&self) -> Vec<String>;
     fn get_by_id(&self, id: u32) -> Option<Item>;
+    fn get_by_slug(&self, slug: &str) -> Option<Item>;
+    /// The item that used to be at `slug`, to redirect to its current one
+    fn get_by_old_slug(&self, slug: &str) -> Option<Item>;
     fn create(&self, title: String, description: String) -> Item;
+    /// Change an item's title, and its slug to match
+    fn rename(&self, id: u32, title: String) -> Option<Item>;
     fn toggle_done(&self, id: u32) -> Option<Item>;
+    /// Replace an item's tags (already normalized, see [`parse_tags`])
+    fn set_tags(&self, id: u32, tags: Vec<String>) -> Option<Item>;
     fn delete(&self, id: u32) -> bool;
+    /// Arrange the current tenant's items in the order of `ids`, which must
+    /// list each of them exactly once; otherwise nothing changes and it's
+    /// `false`
+    fn reorder(&self, ids: &[u32]) -> bool;
+}
+
+// ============================================================================
+// Ordering
+// ============================================================================
+
+/// New positions for items whose current positions, listed in their new
+/// order, are `current`: `(index, position)` for each item that has to move.
+/// The longest run already in increasing order stays put and the rest are
+/// spread evenly through the gaps around it. When a gap is too narrow,
+/// everything is renumbered [`POSITION_GAP`] apart.
+pub fn reposition(current: &[i64]) -> Vec<(usize, i64)> {
+    let keep = longest_increasing(current);
+    let mut positions = current.to_vec();
+    let mut i = 0;
+    while i < positions.len() {
+        if keep[i] {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < positions.len() && !keep[i] {
+            i += 1;
+        }
+        // Between kept neighbours, which haven't moved
+        let len = (i - start) as i64;
+        let before = start.checked_sub(1).map(|j| positions[j]);
+        let after = positions.get(i).copied();
+        let (first, step) = match (before, after) {
+            (Some(lo), Some(hi)) => match (hi - lo) / (len + 1) {
+                0 => return renumber(current),
+                step => (lo + step, step),
+            },
+            (Some(lo), None) => (lo + POSITION_GAP, POSITION_GAP),
+            (None, Some(hi)) => (hi - POSITION_GAP * len, POSITION_GAP),
+            (None, None) => (POSITION_GAP, POSITION_GAP),
+        };
+        for (k, position) in positions[start..i].iter_mut().enumerate() {
+            *position = first + step * k as i64;
+        }
+    }
+    changes(current, &positions)
+}
+
+/// Every item [`POSITION_GAP`] apart, in order
+fn renumber(current: &[i64]) -> Vec<(usize, i64)> {
+    let positions: Vec<i64> = (1..=current.len() as i64)
+        .map(|n| n * POSITION_GAP)
+        .collect();
+    changes(current, &positions)
+}
+
+fn changes(current: &[i64], positions: &[i64]) -> Vec<(usize, i64)> {
+    current
+        .iter()
+        .zip(positions)
+        .enumerate()
+        .filter(|(_, (old, new))| old != new)
+        .map(|(index, (_, &new))| (index, new))
+        .collect()
+}
+
+/// Which of `values` make up a longest strictly increasing subsequence
+fn longest_increasing(values: &[i64]) -> Vec<bool> {
+    // `tails[k]`: index of the smallest last value of an increasing run of length k + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; values.len()];
+    for (i, &value) in values.iter().enumerate() {
+        let k = tails.partition_point(|&t| values[t] < value);
+        previous[i] = k.checked_sub(1).map(|k| tails[k]);
+        if k == tails.len() {
+            tails.push(i);
+        } else {
+            tails[k] = i;
+        }
+    }
+    let mut keep = vec![false; values.len()];
+    let mut next = tails.last().copied();
+    while let Some(i) = next {
+        keep[i] = true;
+        next = previous[i];
+    }
+    keep
+}
+
+/// The positions of `current` (`(id, position)` pairs) in the order of
+/// `ids`, if `ids` lists every one of them exactly once
+fn positions_in_order(current: &[(u32, i64)], ids: &[u32]) -> Option<Vec<i64>> {
+    if ids.len() != current.len() {
+        return None;
+    }
+    let mut seen = std::collections::HashSet::new();
+    ids.iter()
+        .map(|id| {
+            let (_, position) = current.iter().find(|(each, _)| each == id)?;
+            seen.insert(id).then_some(*position)
+        })
+        .collect()
+}
+
+// ============================================================================
+// Change feed
+// ============================================================================
+
+/// What a long poll hears about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemChange {
+    /// This tenant's items changed
+    Tenant(String),
+    /// Server is shutting down — release parked requests so shutdown isn't
+    /// blocked
+    Shutdown,
+}
+
+/// Numbers item changes and broadcasts them to parked long polls. Versions
+/// count up from 0 on every start and are shared by all tenants; each tenant
+/// remembers the version of its last change.
+pub struct ItemHub {
+    tx: broadcast::Sender<ItemChange>,
+    version: AtomicU64,
+    changed_at: Mutex<HashMap<String, u64>>,
+}
+
+impl ItemHub {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(64);
+        Self {
+            tx,
+            version: AtomicU64::new(0),
+            changed_at: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The latest version; a list rendered now is current as of it
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    /// Record a change to `tenant`'s items and wake its long polls
+    pub fn changed(&self, tenant: &str) {
+        let mut changed_at = self.changed_at.lock().unwrap();
+        let version = self.version.fetch_add(1, Ordering::AcqRel) + 1;
+        changed_at.insert(tenant.to_string(), version);
+        drop(changed_at);
+        let _ = self.tx.send(ItemChange::Tenant(tenant.to_string()));
+    }
+
+    /// Wait for `tenant`'s items to change after version `since`, at most
+    /// `timeout`. `true` when they have (or may have: a `since` from before
+    /// a restart, or a lagging receiver); `false` on timeout and shutdown.
+    pub async fn wait(&self, tenant: &str, since: u64, timeout: Duration) -> bool {
+        // Subscribed before checking, so a change in between isn't missed
+        let mut rx = self.tx.subscribe();
+        let last_change = self.changed_at.lock().unwrap().get(tenant).copied();
+        if since > self.version() || last_change.is_some_and(|version| version > since) {
+            return true;
+        }
+        let changed = async {
+            loop {
+                match rx.recv().await {
+                    Ok(ItemChange::Tenant(changed)) if changed != tenant => continue,
+                    Ok(ItemChange::Tenant(_)) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                        return true
+                    }
+                    Ok(ItemChange::Shutdown) | Err(broadcast::error::RecvError::Closed) => {
+                        return false
+                    }
+                }
+            }
+        };
+        tokio::time::timeout(timeout, changed)
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Release every parked long poll (call before graceful shutdown)
+    pub fn shutdown(&self) {
+        let _ = self.tx.send(ItemChange::Shutdown);
+    }
+}
+
+impl Default for ItemHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A slug for `title`, free among `taken`
+fn item_slug(title: &str, taken: &[String]) -> String {
+    slugs::unique(&[title], "item", |slug| taken.iter().any(|t| t == slug))
+}
+
+/// Creation time of the seeded items, matching the migration
+const SEEDED_AT: &str = "2024-01-01T00:00:00Z";
+
+fn now() -> String {
+    chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()
 }
 
 /// In-memory item storage (good for prototyping, tests)
 pub struct InMemoryItemService {
     items: RwLock<Vec<Item>>,
     next_id: RwLock<u32>,
+    history: SlugHistory,
 }
 
 impl InMemoryItemService {
@@ -36,27 +329,43 @@ impl InMemoryItemService {
         let items = vec![
             Item {
                 id: 1,
+                slug: "set-up-project".into(),
                 title: "Set up project".into(),
                 description: "Scaffold Axum + HTMX boilerplate".into(),
                 done: true,
+                tags: vec!["backend".into(), "setup".into()],
+                created_at: SEEDED_AT.into(),
+                tenant: DEFAULT_TENANT.into(),
+                position: POSITION_GAP,
             },
             Item {
                 id: 2,
+                slug: "add-database".into(),
                 title: "Add database".into(),
                 description: "Integrate SQLite or Postgres".into(),
                 done: false,
+                tags: vec!["backend".into(), "database".into()],
+                created_at: SEEDED_AT.into(),
+                tenant: DEFAULT_TENANT.into(),
+                position: 2 * POSITION_GAP,
             },
             Item {
                 id: 3,
+                slug: "deploy".into(),
                 title: "Deploy".into(),
                 description: "Containerize and ship to production".into(),
                 done: false,
+                tags: vec!["ops".into()],
+                created_at: SEEDED_AT.into(),
+                tenant: DEFAULT_TENANT.into(),
+                position: 3 * POSITION_GAP,
             },
         ];
 
         Self {
             items: RwLock::new(items),
             next_id: RwLock::new(4),
+            history: SlugHistory::new(),
         }
     }
 }
@@ -67,27 +376,72 @@ impl Default for InMemoryItemService {
     }
 }
 
+impl InMemoryItemService {
+    /// The current tenant's items, in order
+    fn scoped(&self) -> Vec<Item> {
+        let tenant = tenants::current();
+        let mut items: Vec<Item> = self
+            .items
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|i| i.tenant == tenant)
+            .cloned()
+            .collect();
+        items.sort_by_key(|i| (i.position, i.id));
+        items
+    }
+}
+
 impl ItemService for InMemoryItemService {
     fn list_all(&self) -> Vec<Item> {
-        self.items.read().unwrap().clone()
+        self.scoped()
+    }
+
+    fn list_tagged(&self, tag: &str) -> Vec<Item> {
+        self.scoped()
+            .into_iter()
+            .filter(|i| i.tags.iter().any(|t| t == tag))
+            .collect()
+    }
+
+    fn list_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.scoped().into_iter().flat_map(|i| i.tags).collect();
+        tags.sort();
+        tags.dedup();
+        tags
     }
 
     fn get_by_id(&self, id: u32) -> Option<Item> {
-        self.items
-            .read()
-            .unwrap()
-            .iter()
-            .find(|i| i.id == id)
-            .cloned()
+        self.scoped().into_iter().find(|i| i.id == id)
+    }
+
+    fn get_by_slug(&self, slug: &str) -> Option<Item> {
+        self.scoped().into_iter().find(|i| i.slug == slug)
+    }
+
+    fn get_by_old_slug(&self, slug: &str) -> Option<Item> {
+        self.get_by_id(self.history.resolve(&tenants::current(), slug)?)
     }
 
     fn create(&self, title: String, description: String) -> Item {
         let mut next_id = self.next_id.write().unwrap();
+        let scoped = self.scoped();
+        let last = scoped.last().map_or(0, |i| i.position);
+        let taken: Vec<String> = scoped.into_iter().map(|i| i.slug).collect();
+        let tenant = tenants::current();
+        let slug = item_slug(&title, &taken);
+        self.history.claim(&tenant, &slug);
         let item = Item {
             id: *next_id,
+            slug,
             title,
             description,
             done: false,
+            tags: Vec::new(),
+            created_at: now(),
+            tenant,
+            position: last + POSITION_GAP,
         };
         *next_id += 1;
 
@@ -95,37 +449,89 @@ impl ItemService for InMemoryItemService {
         item
     }
 
+    fn rename(&self, id: u32, title: String) -> Option<Item> {
+        let tenant = tenants::current();
+        let mut items = self.items.write().unwrap();
+        let taken: Vec<String> = items
+            .iter()
+            .filter(|i| i.tenant == tenant && i.id != id)
+            .map(|i| i.slug.clone())
+            .collect();
+        let item = items
+            .iter_mut()
+            .find(|i| i.id == id && i.tenant == tenant)?;
+        let slug = item_slug(&title, &taken);
+        self.history.moved(&tenant, id, &item.slug, &slug);
+        item.slug = slug;
+        item.title = title;
+        Some(item.clone())
+    }
+
     fn toggle_done(&self, id: u32) -> Option<Item> {
+        let tenant = tenants::current();
         let mut items = self.items.write().unwrap();
-        if let Some(item) = items.iter_mut().find(|i| i.id == id) {
-            item.done = !item.done;
-            Some(item.clone())
-        } else {
-            None
-        }
+        let item = items
+            .iter_mut()
+            .find(|i| i.id == id && i.tenant == tenant)?;
+        item.done = !item.done;
+        Some(item.clone())
+    }
+
+    fn set_tags(&self, id: u32, tags: Vec<String>) -> Option<Item> {
+        let tenant = tenants::current();
+        let mut items = self.items.write().unwrap();
+        let item = items
+            .iter_mut()
+            .find(|i| i.id == id && i.tenant == tenant)?;
+        item.tags = tags;
+        Some(item.clone())
     }
 
     fn delete(&self, id: u32) -> bool {
+        let tenant = tenants::current();
         let mut items = self.items.write().unwrap();
         let len_before = items.len();
-        items.retain(|i| i.id != id);
+        items.retain(|i| i.id != id || i.tenant != tenant);
+        self.history.forget(&tenant, id);
         items.len() < len_before
     }
+
+    fn reorder(&self, ids: &[u32]) -> bool {
+        let tenant = tenants::current();
+        let mut items = self.items.write().unwrap();
+        let current: Vec<(u32, i64)> = items
+            .iter()
+            .filter(|i| i.tenant == tenant)
+            .map(|i| (i.id, i.position))
+            .collect();
+        let Some(positions) = positions_in_order(&current, ids) else {
+            return false;
+        };
+        for (index, position) in reposition(&positions) {
+            if let Some(item) = items
+                .iter_mut()
+                .find(|i| i.id == ids[index] && i.tenant == tenant)
+            {
+                item.position = position;
+            }
+        }
+        true
+    }
 }
 
 // ============================================================================
 // SQLx Implementation — SQLite-backed item storage
 // ============================================================================
 
-use sqlx::sqlite::SqlitePool;
+use crate::db::Db;
 
 pub struct SqliteItemService {
-    pool: SqlitePool,
+    db: Db,
 }
 
 impl SqliteItemService {
-    pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+    pub fn new(db: Db) -> Self {
+        Self { db }
     }
 }
 
@@ -133,19 +539,87 @@ impl SqliteItemService {
 #[derive(sqlx::FromRow)]
 struct ItemRow {
     id: i64,
+    slug: String,
     title: String,
     description: String,
     done: i32,
+    created_at: String,
+    tenant: String,
+    position: i64,
 }
 
 impl From<ItemRow> for Item {
     fn from(row: ItemRow) -> Self {
         Item {
             id: row.id as u32,
+            slug: row.slug,
             title: row.title,
             description: row.description,
             done: row.done != 0,
+            tags: Vec::new(),
+            created_at: row.created_at,
+            tenant: row.tenant,
+            position: row.position,
+        }
+    }
+}
+
+const ITEM_COLUMNS: &str = "id, slug, title, description, done, created_at, tenant, position";
+/// What the cached lists read, and so what every write invalidates
+const ITEM_TABLES: &[&str] = &["items", "item_tags", "tags"];
+
+impl SqliteItemService {
+    /// Fill in each item's tags with one query over just these items
+    async fn with_tags(&self, mut items: Vec<Item>) -> Vec<Item> {
+        if items.is_empty() {
+            return items;
+        }
+        let placeholders = vec!["?"; items.len()].join(", ");
+        let sql = format!(
+            "SELECT it.item_id, t.name FROM item_tags it \
+             JOIN tags t ON t.id = it.tag_id \
+             WHERE it.item_id IN ({placeholders}) ORDER BY t.name"
+        );
+        let mut query = sqlx::query_as::<_, (i64, String)>(&sql);
+        for item in &items {
+            query = query.bind(item.id as i64);
+        }
+        let rows = query.fetch_all(self.db.reader()).await.unwrap_or_default();
+
+        let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+        for (item_id, name) in rows {
+            tags.entry(item_id).or_default().push(name);
+        }
+        for item in &mut items {
+            item.tags = tags.remove(&(item.id as i64)).unwrap_or_default();
         }
+        items
+    }
+
+    /// Slugs of the tenant's items, except `id`. Read them in the write
+    /// transaction that takes the new slug: the writer is one connection, so
+    /// no other create can take the same slug in between.
+    async fn taken_slugs(conn: &mut sqlx::SqliteConnection, tenant: &str, id: u32) -> Vec<String> {
+        sqlx::query_scalar("SELECT slug FROM items WHERE tenant = ? AND id != ?")
+            .bind(tenant)
+            .bind(id as i64)
+            .fetch_all(conn)
+            .await
+            .unwrap_or_default()
+    }
+
+    /// The current tenant's item `id`, without tags
+    async fn find(&self, id: u32) -> Option<Item> {
+        sqlx::query_as::<_, ItemRow>(&format!(
+            "SELECT {ITEM_COLUMNS} FROM items WHERE id = ? AND tenant = ?"
+        ))
+        .bind(id as i64)
+        .bind(tenants::current())
+        .fetch_optional(self.db.reader())
+        .await
+        .ok()
+        .flatten()
+        .map(Item::from)
     }
 }
 
@@ -154,31 +628,111 @@ impl ItemService for SqliteItemService {
         // Block on async query from sync trait — runs on the tokio runtime
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(async {
-                sqlx::query_as::<_, ItemRow>(
-                    "SELECT id, title, description, done FROM items ORDER BY id",
-                )
-                .fetch_all(&self.pool)
-                .await
-                .unwrap_or_default()
-                .into_iter()
-                .map(Item::from)
-                .collect()
+                let tenant = tenants::current();
+                let load = || async {
+                    let items = sqlx::query_as::<_, ItemRow>(&format!(
+                        "SELECT {ITEM_COLUMNS} FROM items WHERE tenant = ? ORDER BY position, id"
+                    ))
+                    .bind(&tenant)
+                    .fetch_all(self.db.reader())
+                    .await?
+                    .into_iter()
+                    .map(Item::from)
+                    .collect();
+                    Ok(self.with_tags(items).await)
+                };
+                self.db
+                    .cache()
+                    .get_or_load(ITEM_TABLES, "items.list_all", &tenant, load)
+                    .await
+                    .unwrap_or_default()
+            })
+        })
+    }
+
+    fn list_tagged(&self, tag: &str) -> Vec<Item> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let tenant = tenants::current();
+                let load = || async {
+                    let items = sqlx::query_as::<_, ItemRow>(&format!(
+                        "SELECT {ITEM_COLUMNS} FROM items WHERE tenant = ? AND id IN \
+                         (SELECT it.item_id FROM item_tags it JOIN tags t ON t.id = it.tag_id \
+                          WHERE t.name = ?) ORDER BY position, id"
+                    ))
+                    .bind(&tenant)
+                    .bind(tag)
+                    .fetch_all(self.db.reader())
+                    .await?
+                    .into_iter()
+                    .map(Item::from)
+                    .collect();
+                    Ok(self.with_tags(items).await)
+                };
+                self.db
+                    .cache()
+                    .get_or_load(ITEM_TABLES, "items.list_tagged", (&tenant, tag), load)
+                    .await
+                    .unwrap_or_default()
+            })
+        })
+    }
+
+    fn list_tags(&self) -> Vec<String> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let tenant = tenants::current();
+                let load = || {
+                    sqlx::query_scalar(
+                        "SELECT DISTINCT t.name FROM tags t \
+                         JOIN item_tags it ON it.tag_id = t.id \
+                         JOIN items i ON i.id = it.item_id \
+                         WHERE i.tenant = ? ORDER BY t.name",
+                    )
+                    .bind(&tenant)
+                    .fetch_all(self.db.reader())
+                };
+                self.db
+                    .cache()
+                    .get_or_load(ITEM_TABLES, "items.list_tags", &tenant, load)
+                    .await
+                    .unwrap_or_default()
             })
         })
     }
 
     fn get_by_id(&self, id: u32) -> Option<Item> {
+        tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(self.find(id)))
+    }
+
+    fn get_by_slug(&self, slug: &str) -> Option<Item> {
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(async {
-                sqlx::query_as::<_, ItemRow>(
-                    "SELECT id, title, description, done FROM items WHERE id = ?",
-                )
-                .bind(id as i64)
-                .fetch_optional(&self.pool)
+                let item = sqlx::query_as::<_, ItemRow>(&format!(
+                    "SELECT {ITEM_COLUMNS} FROM items WHERE slug = ? AND tenant = ?"
+                ))
+                .bind(slug)
+                .bind(tenants::current())
+                .fetch_optional(self.db.reader())
                 .await
                 .ok()
                 .flatten()
-                .map(Item::from)
+                .map(Item::from)?;
+                self.with_tags(vec![item]).await.pop()
+            })
+        })
+    }
+
+    fn get_by_old_slug(&self, slug: &str) -> Option<Item> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let id =
+                    slugs::resolve(self.db.reader(), SlugKind::Item, &tenants::current(), slug)
+                        .await
+                        .ok()
+                        .flatten()?;
+                let item = self.find(id).await?;
+                self.with_tags(vec![item]).await.pop()
             })
         })
     }
@@ -186,40 +740,119 @@ impl ItemService for SqliteItemService {
     fn create(&self, title: String, description: String) -> Item {
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(async {
-                let row = sqlx::query_as::<_, ItemRow>(
-                    "INSERT INTO items (title, description) VALUES (?, ?) RETURNING id, title, description, done"
-                )
-                    .bind(&title)
-                    .bind(&description)
-                    .fetch_one(&self.pool)
+                let tenant = tenants::current();
+                let mut tx = self
+                    .db
+                    .writer()
+                    .begin()
                     .await
-                    .expect("Failed to insert item");
+                    .expect("Failed to start item transaction");
+                let slug = item_slug(&title, &Self::taken_slugs(&mut tx, &tenant, 0).await);
+                // After the tenant's last item
+                let row = sqlx::query_as::<_, ItemRow>(&format!(
+                    "INSERT INTO items (slug, title, description, created_at, tenant, position) \
+                     VALUES (?, ?, ?, ?, ?, (SELECT COALESCE(MAX(position), 0) + {POSITION_GAP} \
+                                             FROM items WHERE tenant = ?)) \
+                     RETURNING {ITEM_COLUMNS}"
+                ))
+                .bind(&slug)
+                .bind(&title)
+                .bind(&description)
+                .bind(now())
+                .bind(&tenant)
+                .bind(&tenant)
+                .fetch_one(&mut *tx)
+                .await
+                .expect("Failed to insert item");
+                slugs::claim(&mut tx, SlugKind::Item, &tenant, &slug)
+                    .await
+                    .expect("Failed to claim item slug");
+                tx.commit().await.expect("Failed to commit item");
+                self.db.cache().invalidate(ITEM_TABLES);
                 Item::from(row)
             })
         })
     }
 
+    fn rename(&self, id: u32, title: String) -> Option<Item> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let tenant = tenants::current();
+                let item = self.find(id).await?;
+                let mut tx = self.db.writer().begin().await.ok()?;
+                let slug = item_slug(&title, &Self::taken_slugs(&mut tx, &tenant, id).await);
+                sqlx::query("UPDATE items SET title = ?, slug = ? WHERE id = ? AND tenant = ?")
+                    .bind(&title)
+                    .bind(&slug)
+                    .bind(id as i64)
+                    .bind(&tenant)
+                    .execute(&mut *tx)
+                    .await
+                    .ok()?;
+                slugs::moved(&mut tx, SlugKind::Item, &tenant, id, &item.slug, &slug)
+                    .await
+                    .ok()?;
+                tx.commit().await.ok()?;
+                self.db.cache().invalidate(ITEM_TABLES);
+
+                let item = self.find(id).await?;
+                self.with_tags(vec![item]).await.pop()
+            })
+        })
+    }
+
     fn toggle_done(&self, id: u32) -> Option<Item> {
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(async {
                 // Toggle done: flip 0↔1
                 sqlx::query(
-                    "UPDATE items SET done = CASE WHEN done = 0 THEN 1 ELSE 0 END WHERE id = ?",
+                    "UPDATE items SET done = CASE WHEN done = 0 THEN 1 ELSE 0 END \
+                     WHERE id = ? AND tenant = ?",
                 )
                 .bind(id as i64)
-                .execute(&self.pool)
+                .bind(tenants::current())
+                .execute(self.db.writer())
                 .await
                 .ok()?;
+                self.db.cache().invalidate(ITEM_TABLES);
 
-                sqlx::query_as::<_, ItemRow>(
-                    "SELECT id, title, description, done FROM items WHERE id = ?",
-                )
-                .bind(id as i64)
-                .fetch_optional(&self.pool)
-                .await
-                .ok()
-                .flatten()
-                .map(Item::from)
+                self.find(id).await
+            })
+        })
+    }
+
+    fn set_tags(&self, id: u32, tags: Vec<String>) -> Option<Item> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                // Another tenant's item is as good as missing
+                let item = self.find(id).await?;
+
+                let mut tx = self.db.writer().begin().await.ok()?;
+                sqlx::query("DELETE FROM item_tags WHERE item_id = ?")
+                    .bind(id as i64)
+                    .execute(&mut *tx)
+                    .await
+                    .ok()?;
+                for tag in &tags {
+                    sqlx::query("INSERT OR IGNORE INTO tags (name) VALUES (?)")
+                        .bind(tag)
+                        .execute(&mut *tx)
+                        .await
+                        .ok()?;
+                    sqlx::query(
+                        "INSERT INTO item_tags (item_id, tag_id) \
+                         SELECT ?, id FROM tags WHERE name = ?",
+                    )
+                    .bind(id as i64)
+                    .bind(tag)
+                    .execute(&mut *tx)
+                    .await
+                    .ok()?;
+                }
+                tx.commit().await.ok()?;
+                self.db.cache().invalidate(ITEM_TABLES);
+
+                self.with_tags(vec![item]).await.pop()
             })
         })
     }
@@ -227,12 +860,232 @@ impl ItemService for SqliteItemService {
     fn delete(&self, id: u32) -> bool {
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(async {
-                let result = sqlx::query("DELETE FROM items WHERE id = ?")
+                let tenant = tenants::current();
+                let Ok(mut tx) = self.db.writer().begin().await else {
+                    return false;
+                };
+                let deleted = sqlx::query("DELETE FROM items WHERE id = ? AND tenant = ?")
                     .bind(id as i64)
-                    .execute(&self.pool)
-                    .await;
-                matches!(result, Ok(r) if r.rows_affected() > 0)
+                    .bind(&tenant)
+                    .execute(&mut *tx)
+                    .await
+                    .is_ok_and(|r| r.rows_affected() > 0);
+                let deleted = deleted
+                    && slugs::forget(&mut tx, SlugKind::Item, &tenant, id)
+                        .await
+                        .is_ok()
+                    && tx.commit().await.is_ok();
+                self.db.cache().invalidate(ITEM_TABLES);
+                deleted
+            })
+        })
+    }
+
+    fn reorder(&self, ids: &[u32]) -> bool {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let tenant = tenants::current();
+                let Ok(mut tx) = self.db.writer().begin().await else {
+                    return false;
+                };
+                let current: Vec<(u32, i64)> = sqlx::query_as::<_, (i64, i64)>(
+                    "SELECT id, position FROM items WHERE tenant = ?",
+                )
+                .bind(&tenant)
+                .fetch_all(&mut *tx)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(id, position)| (id as u32, position))
+                .collect();
+                let Some(positions) = positions_in_order(&current, ids) else {
+                    return false;
+                };
+                for (index, position) in reposition(&positions) {
+                    let updated =
+                        sqlx::query("UPDATE items SET position = ? WHERE id = ? AND tenant = ?")
+                            .bind(position)
+                            .bind(ids[index] as i64)
+                            .bind(&tenant)
+                            .execute(&mut *tx)
+                            .await;
+                    if updated.is_err() {
+                        return false;
+                    }
+                }
+                if tx.commit().await.is_err() {
+                    return false;
+                }
+                self.db.cache().invalidate(ITEM_TABLES);
+                true
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hub_wakes_the_tenant() {
+        let hub = std::sync::Arc::new(ItemHub::new());
+        let since = hub.version();
+        let short = Duration::from_millis(20);
+        assert!(!hub.wait("default", since, short).await);
+
+        let parked = tokio::spawn({
+            let hub = hub.clone();
+            async move { hub.wait("default", since, Duration::from_secs(5)).await }
+        });
+        tokio::task::yield_now().await;
+        hub.changed("acme");
+        hub.changed("default");
+        assert!(parked.await.unwrap());
+
+        // Changed already: answers at once, until the poll catches up
+        assert!(hub.wait("default", since, short).await);
+        assert!(!hub.wait("default", hub.version(), short).await);
+        // Another tenant's change is no reason to wake
+        assert!(!hub.wait("acme", hub.version(), short).await);
+        // A version from before a restart
+        assert!(hub.wait("default", hub.version() + 10, short).await);
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(normalize_tag("  Rust Lang! "), Some("rust-lang".into()));
+        assert_eq!(normalize_tag("<script>"), Some("script".into()));
+        assert_eq!(normalize_tag("--"), None);
+        assert_eq!(
+            parse_tags("ops, Backend,backend,,a,b,c,d"),
+            ["a", "b", "backend", "c", "ops"]
+        );
+    }
+
+    #[test]
+    fn test_reposition_moves_as_few_items_as_it_can() {
+        let gap = POSITION_GAP;
+        // Nothing moved
+        assert_eq!(reposition(&[gap, 2 * gap, 3 * gap]), vec![]);
+        // Last to first: only it moves, in front
+        assert_eq!(reposition(&[3 * gap, gap, 2 * gap]), vec![(0, 0)]);
+        // First to last: after the others
+        assert_eq!(reposition(&[2 * gap, 3 * gap, gap]), vec![(2, 4 * gap)]);
+        // Into the middle of a gap
+        assert_eq!(
+            reposition(&[gap, 3 * gap, 2 * gap]),
+            vec![(1, gap + gap / 2)]
+        );
+        // No room left between 1 and 2: everything is renumbered
+        assert_eq!(
+            reposition(&[1, 3, 2]),
+            vec![(0, gap), (1, 2 * gap), (2, 3 * gap)]
+        );
+        // Ties count as out of order
+        assert_eq!(reposition(&[0, 0]), vec![(0, -gap)]);
+    }
+
+    #[test]
+    fn test_in_memory_reorder() {
+        let service = InMemoryItemService::new();
+        let added = service.create("Fourth".into(), String::new());
+        assert_eq!(added.position, 4 * POSITION_GAP);
+
+        assert!(service.reorder(&[3, 1, 2, added.id]));
+        let ids: Vec<u32> = service.list_all().iter().map(|i| i.id).collect();
+        assert_eq!(ids, [3, 1, 2, added.id]);
+        assert_eq!(service.get_by_id(3).unwrap().position, 0);
+
+        // Missing, repeated or unknown ids change nothing
+        assert!(!service.reorder(&[1, 2, 3]));
+        assert!(!service.reorder(&[1, 1, 2, 3]));
+        assert!(!service.reorder(&[1, 2, 3, 99]));
+        let unchanged: Vec<u32> = service.list_all().iter().map(|i| i.id).collect();
+        assert_eq!(unchanged, ids);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sqlite_reorder() {
+        let db = crate::db::init_pool(&crate::config::DatabaseConfig {
+            url: "sqlite::memory:".into(),
+            ..crate::config::DatabaseConfig::default()
+        })
+        .await
+        .unwrap();
+        let service = SqliteItemService::new(db);
+        // Migrated rows are spaced out by id
+        let positions: Vec<i64> = service.list_all().iter().map(|i| i.position).collect();
+        assert_eq!(
+            positions,
+            [POSITION_GAP, 2 * POSITION_GAP, 3 * POSITION_GAP]
+        );
+        let added = service.create("Fourth".into(), String::new());
+        assert_eq!(added.position, 4 * POSITION_GAP);
+
+        assert!(service.reorder(&[1, added.id, 2, 3]));
+        let ids: Vec<u32> = service.list_all().iter().map(|i| i.id).collect();
+        assert_eq!(ids, [1, added.id, 2, 3]);
+        assert!(!service.reorder(&[1, 2, 3]));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_sqlite_concurrent_creates_get_distinct_slugs() {
+        let db = crate::db::init_pool(&crate::config::DatabaseConfig {
+            url: "sqlite::memory:".into(),
+            ..crate::config::DatabaseConfig::default()
+        })
+        .await
+        .unwrap();
+        let service = std::sync::Arc::new(SqliteItemService::new(db));
+        let creates: Vec<_> = (0..8)
+            .map(|_| {
+                let service = service.clone();
+                tokio::spawn(async move { service.create("Twin".into(), String::new()).slug })
             })
+            .collect();
+        let mut slugs = Vec::new();
+        for create in creates {
+            slugs.push(create.await.unwrap());
+        }
+        slugs.sort();
+        slugs.dedup();
+        assert_eq!(slugs.len(), 8);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sqlite_tags_per_item() {
+        let db = crate::db::init_pool(&crate::config::DatabaseConfig {
+            url: "sqlite::memory:".into(),
+            ..crate::config::DatabaseConfig::default()
         })
+        .await
+        .unwrap();
+        let service = SqliteItemService::new(db);
+        let added = service.create("Tagged".into(), String::new());
+        service.set_tags(added.id, vec!["zeta".into(), "alpha".into()]);
+        let untagged = service.create("Untagged".into(), String::new());
+
+        let items = service.list_all();
+        let tags = |id| items.iter().find(|i| i.id == id).unwrap().tags.clone();
+        assert_eq!(tags(added.id), ["alpha", "zeta"]);
+        assert!(tags(untagged.id).is_empty());
+    }
+
+    #[test]
+    fn test_in_memory_scoped_to_tenant() {
+        let service = InMemoryItemService::new();
+        let item = tenants::sync_scope("acme", || {
+            let item = service.create("Acme item".into(), String::new());
+            assert_eq!(service.list_all().len(), 1);
+            // The seeded items belong to the default tenant
+            assert!(service.get_by_id(1).is_none());
+            assert!(!service.delete(1));
+            item
+        });
+        assert_eq!(item.tenant, "acme");
+        assert_eq!(service.list_all().len(), 3);
+        assert!(service.get_by_id(item.id).is_none());
+        assert!(service.toggle_done(item.id).is_none());
     }
 }
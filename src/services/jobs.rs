@@ -0,0 +1,73 @@
+//! Background Jobs — fire-and-forget work off the request path
+//!
+//! Handlers enqueue slow side effects (sending mail) and respond right away.
+//! Each job runs on Tokio's blocking pool and is retried with exponential
+//! backoff; a job that still fails is logged and dropped. Jobs live in
+//! memory only, so anything queued is lost on restart.
+
+use std::time::Duration;
+
+/// Attempts per job, including the first
+const MAX_ATTEMPTS: u32 = 3;
+/// Delay before the first retry, doubled for each one after
+const RETRY_BASE: Duration = Duration::from_secs(2);
+
+/// A unit of work; `Err` schedules a retry
+pub type Job = Box<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+/// Job queue trait — allows running jobs inline in tests
+pub trait JobQueue: Send + Sync {
+    /// Run `job` in the background; `name` identifies it in logs
+    fn enqueue(&self, name: &'static str, job: Job);
+}
+
+/// Runs each job on the blocking pool as soon as it's enqueued
+pub struct BlockingJobQueue;
+
+impl JobQueue for BlockingJobQueue {
+    fn enqueue(&self, name: &'static str, job: Job) {
+        let run = move || run_with_retries(name, &job);
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(run);
+            }
+            // Outside a runtime (CLI tools, tests): run it now
+            Err(_) => run(),
+        }
+    }
+}
+
+fn run_with_retries(name: &'static str, job: &Job) {
+    for attempt in 1..=MAX_ATTEMPTS {
+        match job() {
+            Ok(()) => return,
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                let delay = RETRY_BASE * 2u32.pow(attempt - 1);
+                tracing::warn!(job = name, attempt, error = %e, "job failed, retrying in {delay:?}");
+                std::thread::sleep(delay);
+            }
+            Err(e) => tracing::error!(job = name, attempt, error = %e, "job failed, giving up"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_runs_inline_without_runtime() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        BlockingJobQueue.enqueue(
+            "count",
+            Box::new(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }),
+        );
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+}
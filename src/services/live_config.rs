@@ -0,0 +1,371 @@
+//! Live Config — settings that change without a restart
+//!
+//! Most of [`AppConfig`] is read once at startup. The [`RELOADABLE`] sections
+//! (log level, rate limits, latency budgets, feature flags, maintenance mode,
+//! the announcement banner) are read through [`LiveConfig`] instead, which [`watch`] reloads on
+//! SIGHUP or when a file in `config/` changes. A reload that fails validation
+//! is logged and changes nothing; edits to any other section are reported as
+//! needing a restart.
+
+use chrono::{DateTime, Utc};
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::config::{AppConfig, ConfigErrors};
+
+/// Top-level config sections applied on reload
+pub const RELOADABLE: &[&str] = &[
+    "logging",
+    "rate_limits",
+    "latency",
+    "features",
+    "maintenance",
+    "announcement",
+];
+
+/// Quiet period before a burst of file events triggers one reload
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Shown in place of secret values
+const REDACTED: &str = "••••••••";
+
+/// Outcome of the last reload
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReloadStatus {
+    pub last_reload: Option<DateTime<Utc>>,
+    /// Why the last reload was rejected; empty when it applied
+    pub error: String,
+    /// Sections that differ on disk from what's running
+    pub restart_needed: Vec<String>,
+}
+
+/// Called with the new `[logging] level` when it changes
+type LogLevelHook = Box<dyn Fn(&str) -> Result<(), String> + Send + Sync>;
+
+/// The effective config: startup values, with [`RELOADABLE`] sections as of
+/// the last successful reload
+pub struct LiveConfig {
+    config: RwLock<Arc<AppConfig>>,
+    status: RwLock<ReloadStatus>,
+    on_log_level: Option<LogLevelHook>,
+    // Read-only mode as an admin pinned it; None follows the config
+    read_only: RwLock<Option<bool>>,
+}
+
+impl LiveConfig {
+    pub fn new(config: AppConfig) -> Self {
+        Self {
+            config: RwLock::new(Arc::new(config)),
+            status: RwLock::new(ReloadStatus::default()),
+            on_log_level: None,
+            read_only: RwLock::new(None),
+        }
+    }
+
+    /// Apply log level changes with `hook`, e.g.
+    /// [`LogLevel::configure`](crate::services::LogLevel::configure)
+    pub fn with_log_level(
+        mut self,
+        hook: impl Fn(&str) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        self.on_log_level = Some(Box::new(hook));
+        self
+    }
+
+    pub fn get(&self) -> Arc<AppConfig> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Whether `[features] <name>` is on; unknown flags are off
+    pub fn feature(&self, name: &str) -> bool {
+        self.get().features.get(name).copied().unwrap_or(false)
+    }
+
+    /// Whether writes are refused: as an admin pinned it, otherwise
+    /// `[maintenance] read_only`
+    pub fn read_only(&self) -> bool {
+        self.pinned_read_only()
+            .unwrap_or_else(|| self.get().maintenance.read_only)
+    }
+
+    /// Read-only mode as pinned with [`set_read_only`](Self::set_read_only)
+    pub fn pinned_read_only(&self) -> Option<bool> {
+        *self.read_only.read().unwrap()
+    }
+
+    /// Pin read-only mode on or off, over the config and across reloads,
+    /// until restart; `None` follows the config again
+    pub fn set_read_only(&self, read_only: Option<bool>) {
+        *self.read_only.write().unwrap() = read_only;
+        tracing::warn!(?read_only, "Read-only mode changed");
+    }
+
+    pub fn status(&self) -> ReloadStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    /// Load the config again ([`AppConfig::load`]) and [`apply`](Self::apply)
+    /// it. Reads files: call from `spawn_blocking`.
+    pub fn reload(&self) -> bool {
+        self.apply(AppConfig::load())
+    }
+
+    /// Take the [`RELOADABLE`] sections from `loaded`, keeping the rest.
+    /// `false` when `loaded` is an error, which leaves everything as it was.
+    pub fn apply(&self, loaded: Result<AppConfig, ConfigErrors>) -> bool {
+        let mut status = self.status.write().unwrap();
+        status.last_reload = Some(Utc::now());
+        let loaded = match loaded {
+            Ok(loaded) => loaded,
+            Err(errors) => {
+                tracing::error!("Config reload rejected, keeping the running config\n{errors}");
+                status.error = errors.to_string();
+                return false;
+            }
+        };
+
+        let mut config = AppConfig::clone(&self.get());
+        status.error.clear();
+        status.restart_needed = changed_sections(&config, &loaded)
+            .into_iter()
+            .filter(|section| !RELOADABLE.contains(&section.as_str()))
+            .collect();
+        if !status.restart_needed.is_empty() {
+            tracing::warn!(
+                "Config reloaded; restart to apply changes to {}",
+                status.restart_needed.join(", ")
+            );
+        }
+
+        if loaded.logging.level != config.logging.level {
+            if let Some(hook) = &self.on_log_level {
+                if let Err(e) = hook(&loaded.logging.level) {
+                    tracing::error!(error = %e, "Couldn't change the log level");
+                }
+            }
+        }
+        config.logging = loaded.logging;
+        config.rate_limits = loaded.rate_limits;
+        config.latency = loaded.latency;
+        config.features = loaded.features;
+        config.maintenance = loaded.maintenance;
+        config.announcement = loaded.announcement;
+        *self.config.write().unwrap() = Arc::new(config);
+        tracing::info!("Config reloaded");
+        true
+    }
+}
+
+/// Top-level sections that differ between `a` and `b`
+fn changed_sections(a: &AppConfig, b: &AppConfig) -> Vec<String> {
+    let (Ok(Value::Object(a)), Ok(Value::Object(b))) =
+        (serde_json::to_value(a), serde_json::to_value(b))
+    else {
+        return Vec::new();
+    };
+    a.iter()
+        .filter(|(section, value)| b.get(section.as_str()) != Some(value))
+        .map(|(section, _)| section.clone())
+        .collect()
+}
+
+/// Reload on SIGHUP and whenever a `.toml` file in `dir` changes. The
+/// returned watcher has to be kept alive; `None` when the directory can't be
+/// watched, which leaves SIGHUP.
+pub fn watch(live: Arc<LiveConfig>, dir: &Path) -> Option<notify::RecommendedWatcher> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<()>();
+
+    #[cfg(unix)]
+    {
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            use tokio::signal::unix::{signal, SignalKind};
+            let mut hangups = match signal(SignalKind::hangup()) {
+                Ok(hangups) => hangups,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Can't listen for SIGHUP");
+                    return;
+                }
+            };
+            while hangups.recv().await.is_some() {
+                tracing::info!("SIGHUP, reloading config");
+                let _ = tx.send(());
+            }
+        });
+    }
+
+    let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let changed = res.is_ok_and(|event| {
+            !event.kind.is_access()
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        });
+        if changed {
+            let _ = tx.send(());
+        }
+    })
+    .and_then(|mut watcher| {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    });
+    let watcher = match watcher {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            tracing::warn!(error = %e, "Not watching {} for changes", dir.display());
+            None
+        }
+    };
+
+    tokio::spawn(async move {
+        while rx.recv().await.is_some() {
+            // Editors often write a file several times per save
+            while let Ok(Some(())) = tokio::time::timeout(DEBOUNCE, rx.recv()).await {}
+            let live = live.clone();
+            let _ = tokio::task::spawn_blocking(move || live.reload()).await;
+        }
+    });
+    watcher
+}
+
+/// One setting as the admin config page lists it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigRow {
+    /// Dotted path, e.g. `server.port`
+    pub key: String,
+    /// JSON rendering, or [`REDACTED`] for secrets
+    pub value: String,
+    pub reloadable: bool,
+}
+
+/// Every setting in `config`, secrets redacted
+pub fn rows(config: &AppConfig) -> Vec<ConfigRow> {
+    let mut rows = Vec::new();
+    if let Ok(value) = serde_json::to_value(config) {
+        flatten(&value, "", &mut rows);
+    }
+    rows
+}
+
+fn flatten(value: &Value, key: &str, rows: &mut Vec<ConfigRow>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (name, value) in map {
+                let key = if key.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{key}.{name}")
+                };
+                flatten(value, &key, rows);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (i, value) in items.iter().enumerate() {
+                flatten(value, &format!("{key}[{i}]"), rows);
+            }
+        }
+        _ => {
+            let secret = ["password", "secret", "token", "dsn"]
+                .iter()
+                .any(|word| key.contains(word));
+            let section = key.split(['.', '[']).next().unwrap_or_default();
+            rows.push(ConfigRow {
+                key: key.to_string(),
+                value: match value {
+                    Value::String(s) if secret && !s.is_empty() => REDACTED.to_string(),
+                    _ => value.to_string(),
+                },
+                reloadable: RELOADABLE.contains(&section),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_apply_takes_only_reloadable_sections() {
+        let levels = Arc::new(Mutex::new(Vec::new()));
+        let seen = levels.clone();
+        let live = LiveConfig::new(AppConfig::default()).with_log_level(move |level| {
+            seen.lock().unwrap().push(level.to_string());
+            Ok(())
+        });
+
+        let mut loaded = AppConfig::default();
+        loaded.logging.level = "debug".into();
+        loaded.maintenance.enabled = true;
+        loaded.features.insert("new_editor".into(), true);
+        loaded.server.port = 9999;
+        assert!(live.apply(Ok(loaded)));
+
+        let config = live.get();
+        assert!(config.maintenance.enabled);
+        assert!(live.feature("new_editor"));
+        assert!(!live.feature("unknown"));
+        // Not reloadable: kept, and flagged
+        assert_eq!(config.server.port, 3000);
+        assert_eq!(live.status().restart_needed, ["server"]);
+        assert_eq!(*levels.lock().unwrap(), ["debug"]);
+    }
+
+    #[test]
+    fn test_pinned_read_only_outlasts_reloads() {
+        let live = LiveConfig::new(AppConfig::default());
+        assert!(!live.read_only());
+
+        live.set_read_only(Some(true));
+        assert!(live.apply(Ok(AppConfig::default())));
+        assert!(live.read_only());
+
+        let mut loaded = AppConfig::default();
+        loaded.maintenance.read_only = true;
+        live.set_read_only(Some(false));
+        assert!(live.apply(Ok(loaded)));
+        assert!(!live.read_only());
+
+        live.set_read_only(None);
+        assert!(live.read_only());
+    }
+
+    #[test]
+    fn test_rejected_reload_changes_nothing() {
+        let live = LiveConfig::new(AppConfig::default());
+        let errors = ConfigErrors(vec![crate::config::ConfigError {
+            key: "server.port".into(),
+            message: "must not be 0".into(),
+        }]);
+        assert!(!live.apply(Err(errors)));
+        assert!(live.status().error.contains("server.port"));
+        assert!(!live.get().maintenance.enabled);
+    }
+
+    #[test]
+    fn test_rows_redact_secrets() {
+        let mut config = AppConfig::default();
+        config.admin.password = "hunter2".into();
+        config
+            .webhooks
+            .secrets
+            .insert("github".into(), "s3cr3t".into());
+        let rows = rows(&config);
+        let row = |key: &str| rows.iter().find(|row| row.key == key).unwrap().clone();
+
+        assert_eq!(row("admin.password").value, REDACTED);
+        assert_eq!(row("webhooks.secrets.github").value, REDACTED);
+        // Unset secrets show as unset
+        assert_eq!(row("security.token_secret").value, "\"\"");
+        assert_eq!(row("server.port").value, "3000");
+        assert!(!row("server.port").reloadable);
+        assert!(row("maintenance.enabled").reloadable);
+        assert!(rows.iter().all(|row| !row.value.contains("hunter2")));
+    }
+}
@@ -0,0 +1,220 @@
+//! Log Level — the tracing filter, adjustable at runtime
+//!
+//! The filter is the configured `[logging] level` plus, optionally, extra
+//! directives an admin layers on top from `/admin/logging`, e.g.
+//! `app::services::mail=debug`. Extras always expire, so a debugging session
+//! can't leave the logs noisy for good. Config reloads change the configured
+//! level underneath without dropping an override.
+
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::utils::logging::{self, LogFilter};
+
+/// Longest accepted override, in characters
+pub const MAX_DIRECTIVES_CHARS: usize = 500;
+/// Longest an override may last
+pub const MAX_OVERRIDE: Duration = Duration::from_secs(60 * 60);
+
+/// What's in effect
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLevelState {
+    /// `[logging] level`
+    pub configured: String,
+    /// Extra directives on top of it; empty when there's no override
+    pub extra: String,
+    pub expires: Option<DateTime<Utc>>,
+}
+
+/// Owns the tracing filter, see the module docs
+pub struct LogLevel {
+    // None in tests and anywhere tracing wasn't initialized through
+    // `logging::init_logging`; changes are tracked but apply nowhere
+    filter: Option<LogFilter>,
+    state: Mutex<Inner>,
+}
+
+struct Inner {
+    current: LogLevelState,
+    // Bumped by every new or cleared override, so a stale expiry timer does
+    // nothing. Config reloads leave it alone: the override keeps its timer.
+    generation: u64,
+}
+
+impl LogLevel {
+    pub fn new(filter: Option<LogFilter>, configured: &str) -> Self {
+        Self {
+            filter,
+            state: Mutex::new(Inner {
+                current: LogLevelState {
+                    configured: configured.to_string(),
+                    extra: String::new(),
+                    expires: None,
+                },
+                generation: 0,
+            }),
+        }
+    }
+
+    pub fn current(&self) -> LogLevelState {
+        self.state.lock().unwrap().current.clone()
+    }
+
+    /// The full filter in effect, e.g. `app=info,tower_http=debug,app::db=debug`
+    pub fn directives(&self) -> String {
+        combined(&self.state.lock().unwrap().current)
+    }
+
+    /// New `[logging] level` (config reload). An override stays on top.
+    pub fn configure(&self, level: &str) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        let next = LogLevelState {
+            configured: level.to_string(),
+            ..state.current.clone()
+        };
+        self.apply(&mut state, next)
+    }
+
+    /// Add `extra` directives on top of the configured level for `duration`
+    /// (at most [`MAX_OVERRIDE`]), replacing any earlier override
+    pub fn set_override(self: &Arc<Self>, extra: &str, duration: Duration) -> Result<(), String> {
+        let extra = extra.trim();
+        if extra.is_empty() {
+            return Err("Enter at least one directive".to_string());
+        }
+        if extra.chars().count() > MAX_DIRECTIVES_CHARS {
+            return Err(format!(
+                "Directives are limited to {MAX_DIRECTIVES_CHARS} characters"
+            ));
+        }
+        let duration = duration.min(MAX_OVERRIDE);
+
+        let generation = {
+            let mut state = self.state.lock().unwrap();
+            let next = LogLevelState {
+                extra: extra.to_string(),
+                expires: Some(
+                    Utc::now() + chrono::Duration::from_std(duration).unwrap_or_default(),
+                ),
+                ..state.current.clone()
+            };
+            self.apply(&mut state, next)?;
+            state.generation += 1;
+            state.generation
+        };
+        tracing::warn!(directives = extra, ?duration, "Log level override");
+
+        let this = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(duration).await;
+            this.expire(generation);
+        });
+        Ok(())
+    }
+
+    /// Drop the override, back to the configured level
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.clear(&mut state);
+    }
+
+    fn expire(&self, generation: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.generation == generation {
+            tracing::info!("Log level override expired");
+            self.clear(&mut state);
+        }
+    }
+
+    fn clear(&self, state: &mut Inner) {
+        let next = LogLevelState {
+            extra: String::new(),
+            expires: None,
+            ..state.current.clone()
+        };
+        // The configured level was applied before, so it applies again
+        let _ = self.apply(state, next);
+        state.generation += 1;
+    }
+
+    /// Install `next` if its filter parses; leave everything as it was if not
+    fn apply(&self, state: &mut Inner, next: LogLevelState) -> Result<(), String> {
+        let directives = combined(&next);
+        match &self.filter {
+            Some(filter) => filter.set(&directives)?,
+            None => {
+                tracing_subscriber::EnvFilter::try_new(&directives).map_err(|e| e.to_string())?;
+            }
+        }
+        state.current = next;
+        Ok(())
+    }
+}
+
+fn combined(state: &LogLevelState) -> String {
+    let base = logging::directives(&state.configured);
+    if state.extra.is_empty() {
+        base
+    } else {
+        format!("{base},{}", state.extra)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_override_layers_on_configured_level() {
+        let level = Arc::new(LogLevel::new(None, "info"));
+        level
+            .set_override("app::services::mail=debug", Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(
+            level.directives(),
+            "app=info,tower_http=debug,app::services::mail=debug"
+        );
+
+        // A config reload keeps the override on top
+        level.configure("warn").unwrap();
+        assert_eq!(level.current().extra, "app::services::mail=debug");
+        assert!(level.directives().starts_with("app=warn,"));
+
+        level.reset();
+        assert_eq!(level.directives(), "app=warn,tower_http=debug");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_override_changes_nothing() {
+        let level = Arc::new(LogLevel::new(None, "info"));
+        assert!(level.set_override("", Duration::from_secs(60)).is_err());
+        assert!(level
+            .set_override("app=loud", Duration::from_secs(60))
+            .is_err());
+        assert_eq!(level.current().extra, "");
+    }
+
+    #[tokio::test]
+    async fn test_only_the_latest_override_expires() {
+        let level = Arc::new(LogLevel::new(None, "info"));
+        level
+            .set_override("app=debug", Duration::from_secs(60))
+            .unwrap();
+        let first = level.state.lock().unwrap().generation;
+        level
+            .set_override("app=trace", Duration::from_secs(60))
+            .unwrap();
+
+        // The first override's timer fires after it was replaced
+        level.expire(first);
+        assert_eq!(level.current().extra, "app=trace");
+
+        // A config reload in between doesn't orphan the timer
+        let latest = level.state.lock().unwrap().generation;
+        level.configure("warn").unwrap();
+        level.expire(latest);
+        assert_eq!(level.current().extra, "");
+        assert_eq!(level.current().expires, None);
+    }
+}
@@ -0,0 +1,142 @@
+//! Mail Service — outgoing email behind a swappable transport
+//!
+//! `[mail] transport` picks the implementation: `log` (the default) only
+//! writes messages to the log, which is enough for development; `smtp` relays
+//! through a server with TLS. Sending blocks, so async handlers call
+//! [`MailService::send`] from `spawn_blocking`.
+
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Address, Message, SmtpTransport, Transport};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::config::MailConfig;
+
+/// How long an SMTP exchange may take before the send fails
+const SMTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A plain-text message. `from` is the transport's configured sender.
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub reply_to: Option<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Error)]
+pub enum MailError {
+    #[error("invalid address: {0}")]
+    Address(String),
+    #[error("mail transport misconfigured: {0}")]
+    Config(String),
+    #[error("delivery failed: {0}")]
+    Delivery(String),
+}
+
+/// Mail service trait — allows a log-only or recording transport in tests
+pub trait MailService: Send + Sync {
+    fn send(&self, email: &Email) -> Result<(), MailError>;
+}
+
+/// Whether `address` is a bare address lettre will accept (`a@b.c`, no
+/// display name)
+pub fn is_valid_address(address: &str) -> bool {
+    address.len() <= 254 && address.parse::<Address>().is_ok()
+}
+
+/// Build the configured transport
+pub fn from_config(config: &MailConfig) -> Result<Box<dyn MailService>, MailError> {
+    match config.transport.as_str() {
+        "log" => Ok(Box::new(LogMailer)),
+        "smtp" => Ok(Box::new(SmtpMailer::new(config)?)),
+        other => Err(MailError::Config(format!("unknown transport {other:?}"))),
+    }
+}
+
+/// Logs each message instead of sending it
+pub struct LogMailer;
+
+impl MailService for LogMailer {
+    fn send(&self, email: &Email) -> Result<(), MailError> {
+        tracing::info!(to = %email.to, subject = %email.subject, "mail (log transport)");
+        tracing::debug!(body = %email.body, "mail body");
+        Ok(())
+    }
+}
+
+/// Relays through an SMTP server — implicit TLS on port 465, STARTTLS on
+/// any other
+pub struct SmtpMailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &MailConfig) -> Result<Self, MailError> {
+        if config.smtp_host.is_empty() {
+            return Err(MailError::Config("smtp_host is empty".into()));
+        }
+        let builder = if config.smtp_port == 465 {
+            SmtpTransport::relay(&config.smtp_host)
+        } else {
+            SmtpTransport::starttls_relay(&config.smtp_host)
+        }
+        .map_err(|e| MailError::Config(e.to_string()))?
+        .port(config.smtp_port)
+        .timeout(Some(SMTP_TIMEOUT));
+        let builder = if config.smtp_username.is_empty() {
+            builder
+        } else {
+            builder.credentials(Credentials::new(
+                config.smtp_username.clone(),
+                config.smtp_password.clone(),
+            ))
+        };
+
+        Ok(Self {
+            transport: builder.build(),
+            from: mailbox(&config.from)?,
+        })
+    }
+}
+
+impl MailService for SmtpMailer {
+    fn send(&self, email: &Email) -> Result<(), MailError> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .to(mailbox(&email.to)?)
+            .subject(&email.subject);
+        if let Some(reply_to) = &email.reply_to {
+            message = message.reply_to(mailbox(reply_to)?);
+        }
+        let message = message
+            .body(email.body.clone())
+            .map_err(|e| MailError::Delivery(e.to_string()))?;
+
+        self.transport
+            .send(&message)
+            .map(|_| ())
+            .map_err(|e| MailError::Delivery(e.to_string()))
+    }
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, MailError> {
+    address
+        .parse()
+        .map_err(|_| MailError::Address(address.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_validation() {
+        assert!(is_valid_address("someone@example.com"));
+        assert!(!is_valid_address("someone"));
+        assert!(!is_valid_address("a@b.c\r\nBcc: x@y.z"));
+        assert!(!is_valid_address("Someone <someone@example.com>"));
+    }
+}
@@ -5,45 +5,279 @@
 
 use std::sync::Arc;
 
+pub mod activities;
+pub mod admin;
+pub mod api_tokens;
+pub mod autocomplete;
+pub mod avatars;
+pub mod backups;
+pub mod breadcrumbs;
+pub mod calendar;
+pub mod cart;
+pub mod charts;
+pub mod comments;
 pub mod csrf;
+pub mod errors;
+pub mod flash;
+pub mod fragment_diffs;
 pub mod health;
+pub mod i18n;
+pub mod idempotency;
+pub mod invoices;
 pub mod items;
+pub mod jobs;
+pub mod live_config;
+pub mod log_level;
+pub mod mail;
+pub mod not_found;
+pub mod notifications;
+pub mod og_image;
+pub mod payments;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod posts;
+pub mod presence;
+pub mod rate_limit;
+pub mod redirects;
+pub mod replication;
+pub mod request_log;
+pub mod sanitize;
 pub mod session;
+pub mod slugs;
+pub mod stats;
+pub mod subscribers;
+pub mod tenants;
+pub mod tokens;
+pub mod webhook_events;
+pub mod webhooks_out;
 
+pub use activities::{ActivityHub, ActivityService};
+pub use admin::AdminAuth;
+pub use api_tokens::ApiTokenService;
+pub use autocomplete::Autocomplete;
+pub use avatars::AvatarService;
+pub use backups::Backups;
+pub use breadcrumbs::BreadcrumbService;
+pub use calendar::CalendarService;
+pub use comments::CommentService;
 pub use csrf::CsrfSecret;
+pub use errors::ErrorReporter;
+pub use fragment_diffs::FragmentDiffs;
 pub use health::HealthService;
-pub use items::ItemService;
+pub use i18n::I18n;
+pub use idempotency::IdempotencyStore;
+pub use invoices::InvoiceService;
+pub use items::{ItemHub, ItemService};
+pub use jobs::JobQueue;
+pub use live_config::LiveConfig;
+pub use log_level::LogLevel;
+pub use mail::MailService;
+pub use not_found::NotFoundLog;
+pub use notifications::{NotificationHub, NotificationService};
+pub use og_image::OgImageService;
+pub use payments::Payments;
+pub use posts::PostService;
+pub use presence::PresenceTracker;
+pub use rate_limit::{InFlight, RateLimiter};
+pub use redirects::RedirectService;
+pub use request_log::RequestLog;
 pub use session::{InMemorySessionStore, SessionStore};
+pub use stats::Stats;
+pub use subscribers::SubscriberService;
+pub use tenants::Tenants;
+pub use tokens::TokenSigner;
+pub use webhook_events::WebhookEventService;
+pub use webhooks_out::EventPublisher;
 
-use crate::db::Db;
+use crate::db::{Db, DbHealth};
 
 /// Application services container — injected into handlers via State
 #[derive(Clone)]
 pub struct Services {
     pub health: Arc<dyn HealthService>,
     pub items: Arc<dyn ItemService>,
+    /// Wakes item-list long polls on changes
+    pub item_hub: Arc<ItemHub>,
+    /// Suggestion sources for `/partials/autocomplete`
+    pub autocomplete: Arc<Autocomplete>,
+    pub comments: Arc<dyn CommentService>,
+    pub posts: Arc<dyn PostService>,
+    pub subscribers: Arc<dyn SubscriberService>,
+    pub calendar: Arc<dyn CalendarService>,
+    /// Month and weekday names by `Accept-Language`
+    pub i18n: Arc<I18n>,
+    pub notifications: Arc<dyn NotificationService>,
+    /// Tells open notification streams what changed
+    pub notification_hub: Arc<NotificationHub>,
+    pub activities: Arc<dyn ActivityService>,
+    /// Hands new activities to open feed streams
+    pub activity_hub: Arc<ActivityHub>,
+    /// Last-seen times, flushed to storage by a background task
+    pub presence: Arc<PresenceTracker>,
+    pub webhook_events: Arc<dyn WebhookEventService>,
     pub sessions: Arc<dyn SessionStore>,
     pub csrf: CsrfSecret,
+    pub breadcrumbs: Arc<dyn BreadcrumbService>,
+    /// Log-only until configured, see [`mail::from_config`]
+    pub mail: Arc<dyn MailService>,
+    /// Reports nowhere until configured, see [`errors::from_config`]
+    pub errors: Arc<dyn ErrorReporter>,
+    /// The fake provider until configured, see [`payments::from_config`]
+    pub payments: Arc<Payments>,
+    pub invoices: Arc<dyn InvoiceService>,
+    /// Rules applied before routing, see [`crate::middleware::redirects`]
+    pub redirects: Arc<dyn RedirectService>,
+    /// Bearer tokens for the exports and feeds, see
+    /// [`api_token`](crate::middleware::api_token)
+    pub api_tokens: Arc<dyn ApiTokenService>,
+    pub rate_limiter: Arc<RateLimiter>,
+    /// Requests being handled, see [`load_shed`](crate::middleware::load_shed)
+    pub in_flight: Arc<InFlight>,
+    /// Paths that had no route, for the admin report
+    pub not_found: Arc<NotFoundLog>,
+    /// Answers kept for repeated submissions, see
+    /// [`idempotency`](crate::middleware::idempotency)
+    pub idempotency: Arc<IdempotencyStore>,
+    /// Renders sent into polled targets, see
+    /// [`diff_fragments`](crate::middleware::diff_fragments)
+    pub fragment_diffs: Arc<FragmentDiffs>,
+    pub stats: Arc<Stats>,
+    /// Records nothing until configured, see [`RequestLog::start`]
+    pub request_log: Arc<RequestLog>,
+    pub jobs: Arc<dyn JobQueue>,
+    /// Sends nowhere until configured, see [`webhooks_out::WebhookPublisher`]
+    pub events: Arc<dyn EventPublisher>,
+    /// Random per process until configured, see [`TokenSigner::new`]
+    pub tokens: TokenSigner,
+    /// Uncached until configured, see [`og_image::ResvgOgImages::new`]
+    pub og_images: Arc<dyn OgImageService>,
+    /// Uncached until configured, see [`avatars::Identicons::new`]
+    pub avatars: Arc<dyn AvatarService>,
+    /// Disabled until configured, see [`AdminAuth::new`]
+    pub admin: AdminAuth,
+    /// Just the default tenant until configured, see [`Tenants::new`]
+    pub tenants: Arc<Tenants>,
+    /// Reloadable settings; defaults until configured, see [`live_config`]
+    pub live: Arc<LiveConfig>,
+    /// Tracing filter; changes apply nowhere until configured, see [`LogLevel::new`]
+    pub log_level: Arc<LogLevel>,
+    /// Whether the database is reachable; always up until configured, see
+    /// [`crate::db::connect`]
+    pub db_health: Arc<DbHealth>,
+    /// Disabled until configured, see [`backups`]
+    pub backups: Arc<Backups>,
 }
 
 impl Services {
     /// Create services with SQLite-backed item storage
     pub fn new_with_db(start_time: std::time::SystemTime, db: Db) -> Self {
+        let items: Arc<dyn ItemService> = Arc::new(items::SqliteItemService::new(db.clone()));
         Self {
             health: Arc::new(health::DefaultHealthService::new(start_time)),
-            items: Arc::new(items::SqliteItemService::new(db)),
+            autocomplete: Arc::new(Autocomplete::with_defaults(items.clone())),
+            items,
+            item_hub: Arc::new(ItemHub::new()),
+            comments: Arc::new(comments::SqliteCommentService::new(db.clone())),
+            posts: Arc::new(posts::SqlitePostService::new(db.clone())),
+            subscribers: Arc::new(subscribers::SqliteSubscriberService::new(db.clone())),
+            calendar: Arc::new(calendar::SqliteCalendarService::new(db.clone())),
+            i18n: Arc::new(I18n::default()),
+            notifications: Arc::new(notifications::SqliteNotificationService::new(db.clone())),
+            notification_hub: Arc::new(NotificationHub::new()),
+            activities: Arc::new(activities::SqliteActivityService::new(db.clone())),
+            activity_hub: Arc::new(ActivityHub::new()),
+            presence: Arc::new(PresenceTracker::new(Arc::new(
+                presence::SqlitePresenceStore::new(db.clone()),
+            ))),
+            invoices: Arc::new(invoices::SqliteInvoiceService::new(db.clone())),
+            redirects: Arc::new(redirects::SqliteRedirectService::new(db.clone())),
+            api_tokens: Arc::new(api_tokens::SqliteApiTokenService::new(db.clone())),
+            webhook_events: Arc::new(webhook_events::SqliteWebhookEventService::new(db)),
             sessions: Arc::new(InMemorySessionStore::new()),
             csrf: CsrfSecret::generate(),
+            breadcrumbs: Arc::new(breadcrumbs::RouteBreadcrumbs::default()),
+            mail: Arc::new(mail::LogMailer),
+            errors: Arc::new(errors::NoopReporter),
+            payments: Arc::new(Payments::new(Box::new(payments::FakeProvider::new(
+                payments::FAKE_SETTLE,
+            )))),
+            rate_limiter: Arc::new(RateLimiter::new()),
+            in_flight: Arc::new(InFlight::new()),
+            not_found: Arc::new(NotFoundLog::new()),
+            idempotency: Arc::new(IdempotencyStore::new()),
+            fragment_diffs: Arc::new(FragmentDiffs::new()),
+            stats: Arc::new(Stats::new(start_time)),
+            request_log: Arc::new(RequestLog::disabled()),
+            jobs: Arc::new(jobs::BlockingJobQueue),
+            events: Arc::new(webhooks_out::WebhookPublisher::new(
+                Vec::new(),
+                Arc::new(jobs::BlockingJobQueue),
+            )),
+            tokens: TokenSigner::new(""),
+            og_images: Arc::new(og_image::ResvgOgImages::new("Axum HTMX App", None)),
+            avatars: Arc::new(avatars::Identicons::new(None)),
+            admin: AdminAuth::default(),
+            tenants: Arc::new(Tenants::default()),
+            live: Arc::new(LiveConfig::new(crate::config::AppConfig::default())),
+            log_level: Arc::new(LogLevel::new(None, "info")),
+            db_health: Arc::new(DbHealth::up()),
+            backups: Arc::new(Backups::new(None, 7)),
         }
     }
 
     /// Create services with in-memory implementations (fallback / tests)
     pub fn new_default(start_time: std::time::SystemTime) -> Self {
+        let items: Arc<dyn ItemService> = Arc::new(items::InMemoryItemService::new());
         Self {
             health: Arc::new(health::DefaultHealthService::new(start_time)),
-            items: Arc::new(items::InMemoryItemService::new()),
+            autocomplete: Arc::new(Autocomplete::with_defaults(items.clone())),
+            items,
+            item_hub: Arc::new(ItemHub::new()),
+            comments: Arc::new(comments::InMemoryCommentService::new()),
+            posts: Arc::new(posts::InMemoryPostService::new()),
+            subscribers: Arc::new(subscribers::InMemorySubscriberService::new()),
+            calendar: Arc::new(calendar::InMemoryCalendarService::new()),
+            i18n: Arc::new(I18n::default()),
+            notifications: Arc::new(notifications::InMemoryNotificationService::new()),
+            notification_hub: Arc::new(NotificationHub::new()),
+            activities: Arc::new(activities::InMemoryActivityService::new()),
+            activity_hub: Arc::new(ActivityHub::new()),
+            presence: Arc::new(PresenceTracker::new(Arc::new(
+                presence::InMemoryPresenceStore::new(),
+            ))),
+            invoices: Arc::new(invoices::InMemoryInvoiceService::new()),
+            redirects: Arc::new(redirects::InMemoryRedirectService::new()),
+            api_tokens: Arc::new(api_tokens::InMemoryApiTokenService::new()),
+            webhook_events: Arc::new(webhook_events::InMemoryWebhookEventService::new()),
             sessions: Arc::new(InMemorySessionStore::new()),
             csrf: CsrfSecret::generate(),
+            breadcrumbs: Arc::new(breadcrumbs::RouteBreadcrumbs::default()),
+            mail: Arc::new(mail::LogMailer),
+            errors: Arc::new(errors::NoopReporter),
+            payments: Arc::new(Payments::new(Box::new(payments::FakeProvider::new(
+                payments::FAKE_SETTLE,
+            )))),
+            rate_limiter: Arc::new(RateLimiter::new()),
+            in_flight: Arc::new(InFlight::new()),
+            not_found: Arc::new(NotFoundLog::new()),
+            idempotency: Arc::new(IdempotencyStore::new()),
+            fragment_diffs: Arc::new(FragmentDiffs::new()),
+            stats: Arc::new(Stats::new(start_time)),
+            request_log: Arc::new(RequestLog::disabled()),
+            jobs: Arc::new(jobs::BlockingJobQueue),
+            events: Arc::new(webhooks_out::WebhookPublisher::new(
+                Vec::new(),
+                Arc::new(jobs::BlockingJobQueue),
+            )),
+            tokens: TokenSigner::new(""),
+            og_images: Arc::new(og_image::ResvgOgImages::new("Axum HTMX App", None)),
+            avatars: Arc::new(avatars::Identicons::new(None)),
+            admin: AdminAuth::default(),
+            tenants: Arc::new(Tenants::default()),
+            live: Arc::new(LiveConfig::new(crate::config::AppConfig::default())),
+            log_level: Arc::new(LogLevel::new(None, "info")),
+            db_health: Arc::new(DbHealth::up()),
+            backups: Arc::new(Backups::new(None, 7)),
         }
     }
 }
@@ -0,0 +1,154 @@
+//! 404 Log — which missing paths are asked for, and how often
+//!
+//! Every request the router has no route for is counted here by
+//! [`not_found`](crate::handlers::not_found::not_found), and the admin
+//! report at `/admin/not-found` lists the most frequent. A path that keeps
+//! showing up is a broken link somewhere (the last `Referer` says where) or
+//! a page worth adding — or a redirect.
+//!
+//! Counts live in memory and reset on restart. At most [`MAX_PATHS`] paths
+//! are kept; past that, the one seen longest ago makes room, so a crawler
+//! probing random URLs can't grow the log without bound.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Distinct paths kept
+pub const MAX_PATHS: usize = 500;
+/// Longer paths are cut to this many characters
+pub const MAX_PATH_CHARS: usize = 200;
+
+/// One missing path, as the report shows it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NotFoundHit {
+    pub path: String,
+    pub count: u64,
+    /// `YYYY-MM-DD HH:MM:SS`, UTC
+    pub last_seen: String,
+    /// The last page that linked here; empty when none said
+    pub referer: String,
+}
+
+#[derive(Debug)]
+struct Entry {
+    count: u64,
+    last_seen: DateTime<Utc>,
+    referer: String,
+}
+
+/// Counts of missing paths
+#[derive(Debug, Default)]
+pub struct NotFoundLog {
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl NotFoundLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a request for `path`, which has no route
+    pub fn record(&self, path: &str, referer: Option<&str>) {
+        self.record_at(path, referer, Utc::now());
+    }
+
+    fn record_at(&self, path: &str, referer: Option<&str>, now: DateTime<Utc>) {
+        let path: String = path.chars().take(MAX_PATH_CHARS).collect();
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(&path) && entries.len() >= MAX_PATHS {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_seen)
+                .map(|(path, _)| path.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        let entry = entries.entry(path).or_insert(Entry {
+            count: 0,
+            last_seen: now,
+            referer: String::new(),
+        });
+        entry.count += 1;
+        entry.last_seen = now;
+        if let Some(referer) = referer.filter(|r| !r.is_empty()) {
+            entry.referer = referer.chars().take(MAX_PATH_CHARS).collect();
+        }
+    }
+
+    /// The `limit` most requested paths, most recent first among equals
+    pub fn top(&self, limit: usize) -> Vec<NotFoundHit> {
+        let entries = self.entries.lock().unwrap();
+        let mut hits: Vec<(&String, &Entry)> = entries.iter().collect();
+        hits.sort_by(|(_, a), (_, b)| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| b.last_seen.cmp(&a.last_seen))
+        });
+        hits.into_iter()
+            .take(limit)
+            .map(|(path, entry)| NotFoundHit {
+                path: path.clone(),
+                count: entry.count,
+                last_seen: entry.last_seen.format("%Y-%m-%d %H:%M:%S").to_string(),
+                referer: entry.referer.clone(),
+            })
+            .collect()
+    }
+
+    /// Distinct paths counted
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget everything, e.g. once the broken links are fixed
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_paths() {
+        let log = NotFoundLog::new();
+        log.record("/abuot", Some("https://example.com/links"));
+        log.record("/abuot", None);
+        log.record("/wp-login.php", None);
+
+        let top = log.top(10);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].path, "/abuot");
+        assert_eq!(top[0].count, 2);
+        // A request without a referer keeps the last one known
+        assert_eq!(top[0].referer, "https://example.com/links");
+        assert_eq!(log.top(1).len(), 1);
+    }
+
+    #[test]
+    fn test_oldest_path_makes_room() {
+        let log = NotFoundLog::new();
+        let start = Utc::now();
+        for n in 0..MAX_PATHS {
+            log.record_at(
+                &format!("/{n}"),
+                None,
+                start + chrono::Duration::seconds(n as i64),
+            );
+        }
+        log.record_at("/new", None, start + chrono::Duration::days(1));
+
+        assert_eq!(log.len(), MAX_PATHS);
+        let paths: Vec<String> = log.top(MAX_PATHS).into_iter().map(|h| h.path).collect();
+        assert!(paths.contains(&"/new".to_string()));
+        assert!(!paths.contains(&"/0".to_string()));
+    }
+}
@@ -0,0 +1,390 @@
+//! Notification Service — in-app notifications with an unread count
+//!
+//! Notifications are addressed to a recipient key: the hash of a session's ID
+//! from [`crate::services::comments::owner_key`], so anything that knows who
+//! posted a comment can notify them. Storage sits behind
+//! [`NotificationService`]; [`NotificationHub`] tells open `/notifications/stream`
+//! connections whose notifications changed, so badges update without polling.
+
+use serde::Serialize;
+use std::sync::RwLock;
+use tokio::sync::broadcast;
+
+/// Notification data model
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub id: u32,
+    /// Recipient key, see [`crate::services::comments::owner_key`]
+    #[serde(skip)]
+    pub recipient: String,
+    pub title: String,
+    pub body: String,
+    /// Where the notification leads; empty for none
+    pub link: String,
+    pub created_at: String,
+    pub read: bool,
+}
+
+/// A notification before it's stored
+#[derive(Debug, Clone)]
+pub struct NewNotification {
+    pub recipient: String,
+    pub title: String,
+    pub body: String,
+    pub link: String,
+}
+
+/// Notification service trait — defines storage for notifications
+pub trait NotificationService: Send + Sync {
+    /// The recipient's latest notifications, newest first
+    fn list(&self, recipient: &str, limit: usize) -> Vec<Notification>;
+    fn unread_count(&self, recipient: &str) -> usize;
+    fn create(&self, notification: NewNotification) -> Notification;
+    /// Mark one of the recipient's notifications read. False if it isn't
+    /// theirs or doesn't exist.
+    fn mark_read(&self, recipient: &str, id: u32) -> bool;
+    /// Mark every notification for the recipient read, returning how many
+    /// were unread
+    fn mark_all_read(&self, recipient: &str) -> usize;
+}
+
+fn now() -> String {
+    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// ============================================================================
+// Change feed
+// ============================================================================
+
+/// What a stream hears about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationChange {
+    /// This recipient's notifications or unread count changed
+    Recipient(String),
+    /// Server is shutting down — close open streams so shutdown isn't blocked
+    Shutdown,
+}
+
+/// Broadcasts notification changes to open streams
+pub struct NotificationHub {
+    tx: broadcast::Sender<NotificationChange>,
+}
+
+impl NotificationHub {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(64);
+        Self { tx }
+    }
+
+    /// Tell the recipient's open streams to refresh
+    pub fn changed(&self, recipient: &str) {
+        let _ = self
+            .tx
+            .send(NotificationChange::Recipient(recipient.to_string()));
+    }
+
+    /// End every open stream (call before graceful shutdown)
+    pub fn shutdown(&self) {
+        let _ = self.tx.send(NotificationChange::Shutdown);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<NotificationChange> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for NotificationHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// In-memory implementation
+// ============================================================================
+
+/// In-memory notification storage (good for prototyping, tests)
+pub struct InMemoryNotificationService {
+    notifications: RwLock<Vec<Notification>>,
+    next_id: RwLock<u32>,
+}
+
+impl InMemoryNotificationService {
+    pub fn new() -> Self {
+        Self {
+            notifications: RwLock::new(Vec::new()),
+            next_id: RwLock::new(1),
+        }
+    }
+}
+
+impl Default for InMemoryNotificationService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NotificationService for InMemoryNotificationService {
+    fn list(&self, recipient: &str, limit: usize) -> Vec<Notification> {
+        self.notifications
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|n| n.recipient == recipient)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn unread_count(&self, recipient: &str) -> usize {
+        self.notifications
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|n| n.recipient == recipient && !n.read)
+            .count()
+    }
+
+    fn create(&self, notification: NewNotification) -> Notification {
+        let mut next_id = self.next_id.write().unwrap();
+        let notification = Notification {
+            id: *next_id,
+            recipient: notification.recipient,
+            title: notification.title,
+            body: notification.body,
+            link: notification.link,
+            created_at: now(),
+            read: false,
+        };
+        *next_id += 1;
+        self.notifications
+            .write()
+            .unwrap()
+            .push(notification.clone());
+        notification
+    }
+
+    fn mark_read(&self, recipient: &str, id: u32) -> bool {
+        let mut notifications = self.notifications.write().unwrap();
+        match notifications
+            .iter_mut()
+            .find(|n| n.id == id && n.recipient == recipient)
+        {
+            Some(notification) => {
+                notification.read = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn mark_all_read(&self, recipient: &str) -> usize {
+        let mut notifications = self.notifications.write().unwrap();
+        let mut marked = 0;
+        for notification in notifications
+            .iter_mut()
+            .filter(|n| n.recipient == recipient && !n.read)
+        {
+            notification.read = true;
+            marked += 1;
+        }
+        marked
+    }
+}
+
+// ============================================================================
+// SQLx Implementation — SQLite-backed notification storage
+// ============================================================================
+
+use crate::db::Db;
+
+pub struct SqliteNotificationService {
+    db: Db,
+}
+
+impl SqliteNotificationService {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+}
+
+/// Row type returned by SQLx queries
+#[derive(sqlx::FromRow)]
+struct NotificationRow {
+    id: i64,
+    recipient: String,
+    title: String,
+    body: String,
+    link: String,
+    created_at: String,
+    read_at: Option<String>,
+}
+
+impl From<NotificationRow> for Notification {
+    fn from(row: NotificationRow) -> Self {
+        Notification {
+            id: row.id as u32,
+            recipient: row.recipient,
+            title: row.title,
+            body: row.body,
+            link: row.link,
+            created_at: row.created_at,
+            read: row.read_at.is_some(),
+        }
+    }
+}
+
+const NOTIFICATION_COLUMNS: &str = "id, recipient, title, body, link, created_at, read_at";
+/// What the cached reads read, and so what every write invalidates
+const TABLES: &[&str] = &["notifications"];
+
+impl NotificationService for SqliteNotificationService {
+    fn list(&self, recipient: &str, limit: usize) -> Vec<Notification> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let load = || async {
+                    let rows = sqlx::query_as::<_, NotificationRow>(&format!(
+                        "SELECT {NOTIFICATION_COLUMNS} FROM notifications \
+                         WHERE recipient = ? ORDER BY id DESC LIMIT ?"
+                    ))
+                    .bind(recipient)
+                    .bind(limit as i64)
+                    .fetch_all(self.db.reader())
+                    .await?;
+                    Ok(rows.into_iter().map(Notification::from).collect())
+                };
+                self.db
+                    .cache()
+                    .get_or_load(TABLES, "notifications.list", (recipient, limit), load)
+                    .await
+                    .unwrap_or_default()
+            })
+        })
+    }
+
+    fn unread_count(&self, recipient: &str) -> usize {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let load = || {
+                    sqlx::query_scalar::<_, i64>(
+                        "SELECT COUNT(*) FROM notifications WHERE recipient = ? AND read_at IS NULL",
+                    )
+                    .bind(recipient)
+                    .fetch_one(self.db.reader())
+                };
+                self.db
+                    .cache()
+                    .get_or_load(TABLES, "notifications.unread_count", recipient, load)
+                    .await
+                    .unwrap_or(0) as usize
+            })
+        })
+    }
+
+    fn create(&self, notification: NewNotification) -> Notification {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let row = sqlx::query_as::<_, NotificationRow>(&format!(
+                    "INSERT INTO notifications (recipient, title, body, link, created_at) \
+                     VALUES (?, ?, ?, ?, ?) RETURNING {NOTIFICATION_COLUMNS}"
+                ))
+                .bind(&notification.recipient)
+                .bind(&notification.title)
+                .bind(&notification.body)
+                .bind(&notification.link)
+                .bind(now())
+                .fetch_one(self.db.writer())
+                .await
+                .expect("Failed to insert notification");
+                self.db.cache().invalidate(TABLES);
+                Notification::from(row)
+            })
+        })
+    }
+
+    fn mark_read(&self, recipient: &str, id: u32) -> bool {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                // Already-read notifications keep their first read time
+                let result = sqlx::query(
+                    "UPDATE notifications SET read_at = COALESCE(read_at, ?) \
+                     WHERE id = ? AND recipient = ?",
+                )
+                .bind(now())
+                .bind(id as i64)
+                .bind(recipient)
+                .execute(self.db.writer())
+                .await;
+                self.db.cache().invalidate(TABLES);
+                matches!(result, Ok(r) if r.rows_affected() > 0)
+            })
+        })
+    }
+
+    fn mark_all_read(&self, recipient: &str) -> usize {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let result = sqlx::query(
+                    "UPDATE notifications SET read_at = ? \
+                     WHERE recipient = ? AND read_at IS NULL",
+                )
+                .bind(now())
+                .bind(recipient)
+                .execute(self.db.writer())
+                .await;
+                self.db.cache().invalidate(TABLES);
+                result.map(|r| r.rows_affected() as usize).unwrap_or(0)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new(recipient: &str, title: &str) -> NewNotification {
+        NewNotification {
+            recipient: recipient.into(),
+            title: title.into(),
+            body: String::new(),
+            link: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_in_memory_read_state() {
+        let service = InMemoryNotificationService::new();
+        let first = service.create(new("ada", "First"));
+        service.create(new("ada", "Second"));
+        service.create(new("grace", "Other"));
+
+        let listed = service.list("ada", 10);
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].title, "Second");
+        assert_eq!(service.unread_count("ada"), 2);
+
+        // Only the recipient can mark theirs read
+        assert!(!service.mark_read("grace", first.id));
+        assert!(service.mark_read("ada", first.id));
+        assert_eq!(service.unread_count("ada"), 1);
+
+        assert_eq!(service.mark_all_read("ada"), 1);
+        assert_eq!(service.unread_count("ada"), 0);
+        assert_eq!(service.unread_count("grace"), 1);
+    }
+
+    #[test]
+    fn test_hub() {
+        let hub = NotificationHub::new();
+        let mut changes = hub.subscribe();
+        hub.changed("ada");
+        hub.shutdown();
+        assert_eq!(
+            changes.try_recv().unwrap(),
+            NotificationChange::Recipient("ada".into())
+        );
+        assert_eq!(changes.try_recv().unwrap(), NotificationChange::Shutdown);
+    }
+}
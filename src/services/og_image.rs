@@ -0,0 +1,219 @@
+//! Open Graph Images — 1200×630 PNG link previews rendered in-process
+//!
+//! A page's title and description are laid out in a small SVG, rasterized
+//! with resvg using the system fonts, and cached on disk by content hash.
+//! Nothing leaves the server: no external image service, no remote fonts.
+
+use resvg::{tiny_skia, usvg};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+use crate::utils::html::escape;
+
+/// Output size recommended by the Open Graph consumers that matter
+pub const WIDTH: u32 = 1200;
+pub const HEIGHT: u32 = 630;
+
+/// Longest title drawn, in characters; the rest is cut with an ellipsis
+pub const MAX_TITLE_CHARS: usize = 80;
+/// Longest description drawn, in characters
+pub const MAX_DESCRIPTION_CHARS: usize = 160;
+/// Cached files kept at most; past this, images are rendered but not stored,
+/// so arbitrary query strings can't fill the disk
+const MAX_CACHED: usize = 1000;
+
+/// Characters per line at the title and description font sizes
+const TITLE_LINE_CHARS: usize = 28;
+const DESCRIPTION_LINE_CHARS: usize = 60;
+
+/// Open Graph image service trait — allows a stub in tests
+pub trait OgImageService: Send + Sync {
+    /// PNG bytes for a preview card. Blocking: call from `spawn_blocking`.
+    fn render(&self, title: &str, description: &str) -> Result<Vec<u8>, String>;
+}
+
+/// Renders with resvg, caching PNGs in `cache_dir` when one is set
+pub struct ResvgOgImages {
+    site_name: String,
+    cache_dir: Option<PathBuf>,
+    /// System fonts, loaded on first render
+    fonts: OnceLock<Arc<usvg::fontdb::Database>>,
+}
+
+impl ResvgOgImages {
+    pub fn new(site_name: &str, cache_dir: Option<PathBuf>) -> Self {
+        Self {
+            site_name: site_name.to_string(),
+            cache_dir,
+            fonts: OnceLock::new(),
+        }
+    }
+
+    fn fonts(&self) -> Arc<usvg::fontdb::Database> {
+        self.fonts
+            .get_or_init(|| {
+                let mut db = usvg::fontdb::Database::new();
+                db.load_system_fonts();
+                if db.is_empty() {
+                    tracing::warn!("no system fonts found; OG images will have no text");
+                }
+                Arc::new(db)
+            })
+            .clone()
+    }
+
+    fn rasterize(&self, svg: &str) -> Result<Vec<u8>, String> {
+        let options = usvg::Options {
+            fontdb: self.fonts(),
+            ..usvg::Options::default()
+        };
+        let tree = usvg::Tree::from_str(svg, &options).map_err(|e| e.to_string())?;
+        let mut pixmap = tiny_skia::Pixmap::new(WIDTH, HEIGHT).ok_or("empty pixmap")?;
+        resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+        pixmap.encode_png().map_err(|e| e.to_string())
+    }
+
+    /// Cache path for a card, if caching is enabled
+    fn cache_path(&self, svg: &str) -> Option<PathBuf> {
+        let dir = self.cache_dir.as_ref()?;
+        let digest = hex::encode(Sha256::digest(svg.as_bytes()));
+        Some(dir.join(format!("{}.png", &digest[..32])))
+    }
+
+    fn store(&self, path: &PathBuf, png: &[u8]) {
+        let Some(dir) = path.parent() else { return };
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            tracing::warn!(error = %e, "can't create OG image cache");
+            return;
+        }
+        let cached = std::fs::read_dir(dir).map(|d| d.count()).unwrap_or(0);
+        if cached >= MAX_CACHED {
+            return;
+        }
+        if let Err(e) = std::fs::write(path, png) {
+            tracing::warn!(error = %e, "can't write OG image cache");
+        }
+    }
+}
+
+impl OgImageService for ResvgOgImages {
+    fn render(&self, title: &str, description: &str) -> Result<Vec<u8>, String> {
+        let svg = card_svg(&self.site_name, title, description);
+        let cache_path = self.cache_path(&svg);
+        if let Some(png) = cache_path.as_ref().and_then(|p| std::fs::read(p).ok()) {
+            return Ok(png);
+        }
+        let png = self.rasterize(&svg)?;
+        if let Some(path) = &cache_path {
+            self.store(path, &png);
+        }
+        Ok(png)
+    }
+}
+
+/// Cut `text` to `max` characters, ending in an ellipsis when shortened
+pub fn truncate(text: &str, max: usize) -> String {
+    let text = text.trim();
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max.saturating_sub(1)).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// Greedy word wrap into at most `max_lines` lines of about `width` chars
+fn wrap(text: &str, width: usize, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        let last = lines.pop().unwrap_or_default();
+        lines.push(truncate(&format!("{last} …"), width));
+    }
+    lines
+}
+
+/// `<text>` with one `<tspan>` per line, `line_height` apart
+fn text_block(lines: &[String], y: u32, line_height: u32, attrs: &str) -> String {
+    let mut svg = format!("<text x=\"80\" y=\"{y}\" {attrs}>");
+    for (i, line) in lines.iter().enumerate() {
+        let dy = if i == 0 { 0 } else { line_height };
+        svg.push_str(&format!(
+            "<tspan x=\"80\" dy=\"{dy}\">{}</tspan>",
+            escape(line)
+        ));
+    }
+    svg.push_str("</text>");
+    svg
+}
+
+/// The card: site name, wrapped title and description on a dark gradient
+pub fn card_svg(site_name: &str, title: &str, description: &str) -> String {
+    let title = wrap(&truncate(title, MAX_TITLE_CHARS), TITLE_LINE_CHARS, 3);
+    let description = wrap(
+        &truncate(description, MAX_DESCRIPTION_CHARS),
+        DESCRIPTION_LINE_CHARS,
+        3,
+    );
+    let fonts = "font-family=\"DejaVu Sans, Liberation Sans, Arial, Helvetica, sans-serif\"";
+    let description_y = 250 + 76 * title.len() as u32;
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\" viewBox=\"0 0 {WIDTH} {HEIGHT}\">\
+         <defs><linearGradient id=\"bg\" x1=\"0\" y1=\"0\" x2=\"1\" y2=\"1\">\
+         <stop offset=\"0\" stop-color=\"#0f172a\"/><stop offset=\"1\" stop-color=\"#1e3a8a\"/>\
+         </linearGradient></defs>\
+         <rect width=\"{WIDTH}\" height=\"{HEIGHT}\" fill=\"url(#bg)\"/>\
+         <rect x=\"80\" y=\"96\" width=\"96\" height=\"8\" rx=\"4\" fill=\"#3b82f6\"/>\
+         {site}{title}{description}</svg>",
+        site = text_block(
+            &[site_name.to_string()],
+            160,
+            0,
+            &format!("{fonts} font-size=\"32\" fill=\"#93c5fd\"")
+        ),
+        title = text_block(
+            &title,
+            250,
+            76,
+            &format!("{fonts} font-size=\"64\" font-weight=\"bold\" fill=\"#f8fafc\"")
+        ),
+        description = text_block(
+            &description,
+            description_y,
+            42,
+            &format!("{fonts} font-size=\"30\" fill=\"#cbd5e1\"")
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_card_layout() {
+        assert_eq!(truncate("  short  ", 10), "short");
+        assert_eq!(truncate("a longer title", 8), "a longe…");
+
+        let lines = wrap("one two three four five six", 9, 2);
+        assert_eq!(lines, ["one two", "three …"]);
+
+        let svg = card_svg("Site", "Tom & <Jerry>", "");
+        assert!(svg.contains("Tom &amp; &lt;Jerry&gt;"));
+        assert!(!svg.contains("<Jerry>"));
+    }
+}
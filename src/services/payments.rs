@@ -0,0 +1,514 @@
+//! Payments — a checkout flow behind a swappable provider
+//!
+//! A payment goes through three steps, whatever the provider:
+//!
+//! 1. [`Payments::create`] registers an intent with the provider (amount,
+//!    description) and keeps it here as [`PaymentStatus::RequiresConfirmation`].
+//! 2. [`Payments::confirm`] hands the provider the payer's method. Most
+//!    providers answer [`PaymentStatus::Processing`] and settle later.
+//! 3. The outcome arrives as a webhook, read by
+//!    [`PaymentProvider::parse_webhook`] and applied by
+//!    [`Payments::reconcile`]; until then the status page polls, and
+//!    [`Payments::refresh`] asks the provider directly in case a webhook is
+//!    late or lost. A final status (succeeded, failed) never changes again,
+//!    so repeated or out-of-order deliveries are harmless.
+//!
+//! `[payments] provider` picks the implementation. Only `fake` ships: it
+//! moves no money and takes the test cards in [`TEST_CARDS`]. A real
+//! provider implements [`PaymentProvider`], is added to [`from_config`], and
+//! has its webhooks routed with
+//! `crate::webhook_handler!("<provider>", "*", handlers::payments::reconcile)`.
+//! Real card details never touch this server: the provider's own fields
+//! collect them and hand the page a token to pass as the method.
+//!
+//! Provider calls may block (HTTP), so async handlers make them from
+//! `spawn_blocking`. Intents are kept in memory, like the rest of the demo,
+//! at most [`MAX_INTENTS`] of them with the oldest forgotten first; a shop
+//! would store them with its orders.
+
+use rand::RngCore;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::config::PaymentsConfig;
+
+/// Currency of every amount, in minor units (pence)
+pub const CURRENCY: &str = "gbp";
+
+/// How long an intent is kept
+const INTENT_TTL: Duration = Duration::from_secs(24 * 3600);
+/// Intents (and fake provider payments) kept before the oldest are
+/// forgotten early
+pub const MAX_INTENTS: usize = 10_000;
+
+/// Where a payment stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentStatus {
+    /// Created; waiting for the payer's method
+    RequiresConfirmation,
+    /// Confirmed; waiting for the provider's outcome
+    Processing,
+    Succeeded,
+    Failed,
+}
+
+impl PaymentStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::RequiresConfirmation => "requires_confirmation",
+            Self::Processing => "processing",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+        }
+    }
+
+    /// Succeeded or failed: nothing changes it any more
+    pub fn is_final(self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed)
+    }
+}
+
+/// What a provider says about a payment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Update {
+    pub status: PaymentStatus,
+    /// Why it failed, fit to show the payer; empty otherwise
+    pub failure: String,
+}
+
+impl Update {
+    pub fn status(status: PaymentStatus) -> Self {
+        Self {
+            status,
+            failure: String::new(),
+        }
+    }
+
+    pub fn failed(failure: impl Into<String>) -> Self {
+        Self {
+            status: PaymentStatus::Failed,
+            failure: failure.into(),
+        }
+    }
+}
+
+/// A payment as this app tracks it
+#[derive(Debug, Clone)]
+pub struct PaymentIntent {
+    /// Ours, random, used in URLs
+    pub id: String,
+    /// Recipient key of the session that created it, see
+    /// [`crate::services::comments::owner_key`]
+    pub owner: String,
+    /// The provider's id for it
+    pub reference: String,
+    pub amount_cents: u64,
+    pub description: String,
+    pub status: PaymentStatus,
+    pub failure: String,
+    created: Instant,
+}
+
+#[derive(Debug, Error)]
+pub enum PaymentError {
+    #[error("payment provider misconfigured: {0}")]
+    Config(String),
+    #[error("payment provider error: {0}")]
+    Provider(String),
+    #[error("no such payment")]
+    NotFound,
+}
+
+/// A payment provider — the fake one, or a real one slotted in
+pub trait PaymentProvider: Send + Sync {
+    /// Matches its `/webhooks/<name>` deliveries
+    fn name(&self) -> &'static str;
+    /// Register a payment, returning the provider's reference for it
+    fn create_intent(&self, amount_cents: u64, description: &str) -> Result<String, PaymentError>;
+    /// Take the payment with `method` (a card token, or a test card)
+    fn confirm(&self, reference: &str, method: &str) -> Result<Update, PaymentError>;
+    /// Where the payment stands now
+    fn retrieve(&self, reference: &str) -> Result<Update, PaymentError>;
+    /// The payment a webhook delivery is about and its new status; `None`
+    /// for events that aren't about a payment's outcome
+    fn parse_webhook(&self, event_type: &str, payload: &str) -> Option<(String, Update)>;
+    /// Methods the confirm form offers as `(value, label)`. Only test
+    /// providers have any; real ones bring their own card fields.
+    fn test_methods(&self) -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+}
+
+/// Build the configured provider
+pub fn from_config(config: &PaymentsConfig) -> Result<Box<dyn PaymentProvider>, PaymentError> {
+    match config.provider.as_str() {
+        "fake" => Ok(Box::new(FakeProvider::new(FAKE_SETTLE))),
+        other => Err(PaymentError::Config(format!("unknown provider {other:?}"))),
+    }
+}
+
+fn random_id(prefix: &str) -> String {
+    let mut bytes = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{prefix}{}", hex::encode(bytes))
+}
+
+/// Values by key, holding at most `max` and forgetting the oldest first.
+/// Keys are random and never reused, so insertion order is age.
+struct Capped<V> {
+    by_key: HashMap<String, V>,
+    /// Every key, oldest first
+    order: VecDeque<String>,
+    max: usize,
+}
+
+impl<V> Capped<V> {
+    fn new(max: usize) -> Self {
+        Self {
+            by_key: HashMap::new(),
+            order: VecDeque::new(),
+            max: max.max(1),
+        }
+    }
+
+    /// Store `value`, first dropping the oldest while they're `stale` or
+    /// there's no room
+    fn insert(&mut self, key: String, value: V, stale: impl Fn(&V) -> bool) {
+        while let Some(oldest) = self.order.front() {
+            let fresh = self.by_key.get(oldest).is_some_and(|v| !stale(v));
+            if fresh && self.by_key.len() < self.max {
+                break;
+            }
+            if let Some(oldest) = self.order.pop_front() {
+                self.by_key.remove(&oldest);
+            }
+        }
+        self.order.push_back(key.clone());
+        self.by_key.insert(key, value);
+    }
+}
+
+// =============================================================================
+// Intents
+// =============================================================================
+
+/// The provider and the intents made through it
+pub struct Payments {
+    provider: Box<dyn PaymentProvider>,
+    intents: RwLock<Capped<PaymentIntent>>,
+}
+
+impl Payments {
+    pub fn new(provider: Box<dyn PaymentProvider>) -> Self {
+        Self::with_max(provider, MAX_INTENTS)
+    }
+
+    /// Keeping at most `max_intents`
+    pub fn with_max(provider: Box<dyn PaymentProvider>, max_intents: usize) -> Self {
+        Self {
+            provider,
+            intents: RwLock::new(Capped::new(max_intents)),
+        }
+    }
+
+    pub fn provider(&self) -> &dyn PaymentProvider {
+        self.provider.as_ref()
+    }
+
+    /// Step 1: a new intent for `owner`
+    pub fn create(
+        &self,
+        owner: &str,
+        amount_cents: u64,
+        description: &str,
+    ) -> Result<PaymentIntent, PaymentError> {
+        let reference = self.provider.create_intent(amount_cents, description)?;
+        let intent = PaymentIntent {
+            id: random_id(""),
+            owner: owner.to_string(),
+            reference,
+            amount_cents,
+            description: description.to_string(),
+            status: PaymentStatus::RequiresConfirmation,
+            failure: String::new(),
+            created: Instant::now(),
+        };
+        self.intents
+            .write()
+            .unwrap()
+            .insert(intent.id.clone(), intent.clone(), |i| {
+                i.created.elapsed() >= INTENT_TTL
+            });
+        Ok(intent)
+    }
+
+    /// `owner`'s intent `id`
+    pub fn get(&self, owner: &str, id: &str) -> Option<PaymentIntent> {
+        self.intents
+            .read()
+            .unwrap()
+            .by_key
+            .get(id)
+            .filter(|i| i.owner == owner)
+            .cloned()
+    }
+
+    /// Step 2: pay with `method`. An intent that's past confirmation (a
+    /// double submit) comes back as it is.
+    pub fn confirm(
+        &self,
+        owner: &str,
+        id: &str,
+        method: &str,
+    ) -> Result<PaymentIntent, PaymentError> {
+        let intent = self.get(owner, id).ok_or(PaymentError::NotFound)?;
+        if intent.status != PaymentStatus::RequiresConfirmation {
+            return Ok(intent);
+        }
+        let update = self.provider.confirm(&intent.reference, method)?;
+        Ok(self.apply(&intent.reference, update).unwrap_or(intent))
+    }
+
+    /// `owner`'s intent, asking the provider first while it's processing
+    pub fn refresh(&self, owner: &str, id: &str) -> Option<PaymentIntent> {
+        let intent = self.get(owner, id)?;
+        if intent.status != PaymentStatus::Processing {
+            return Some(intent);
+        }
+        match self.provider.retrieve(&intent.reference) {
+            Ok(update) => self.apply(&intent.reference, update),
+            Err(e) => {
+                tracing::warn!(payment = %intent.id, error = %e, "payment status check failed");
+                Some(intent)
+            }
+        }
+    }
+
+    /// Step 3: apply a webhook delivery. `false` when it isn't about one of
+    /// our payments.
+    pub fn reconcile(&self, event_type: &str, payload: &str) -> bool {
+        let Some((reference, update)) = self.provider.parse_webhook(event_type, payload) else {
+            return false;
+        };
+        self.apply(&reference, update).is_some()
+    }
+
+    /// Move the intent with `reference` to `update`, unless it's final
+    fn apply(&self, reference: &str, update: Update) -> Option<PaymentIntent> {
+        let mut intents = self.intents.write().unwrap();
+        let intent = intents
+            .by_key
+            .values_mut()
+            .find(|i| i.reference == reference)?;
+        if !intent.status.is_final() && intent.status != update.status {
+            tracing::info!(
+                payment = %intent.id,
+                from = intent.status.as_str(),
+                to = update.status.as_str(),
+                "payment status changed"
+            );
+            intent.status = update.status;
+            intent.failure = update.failure;
+        }
+        Some(intent.clone())
+    }
+}
+
+// =============================================================================
+// Fake provider
+// =============================================================================
+
+/// How long the fake provider takes to settle a successful payment
+pub const FAKE_SETTLE: Duration = Duration::from_secs(3);
+
+/// Succeeds once it has settled
+pub const CARD_SUCCEEDS: &str = "4242424242424242";
+/// Declined at confirmation
+pub const CARD_DECLINED: &str = "4000000000000002";
+/// Processing until a webhook says otherwise
+pub const CARD_WEBHOOK: &str = "4000000000000077";
+
+/// The fake provider's test cards
+pub const TEST_CARDS: &[(&str, &str)] = &[
+    (CARD_SUCCEEDS, "4242 4242 4242 4242 — succeeds"),
+    (CARD_DECLINED, "4000 0000 0000 0002 — declined"),
+    (CARD_WEBHOOK, "4000 0000 0000 0077 — waits for a webhook"),
+];
+
+/// A payment on the fake provider's books
+struct FakePayment {
+    card: String,
+    confirmed: Option<Instant>,
+}
+
+/// Moves no money. Test cards choose the outcome; webhooks for it are
+/// `payment.succeeded` / `payment.failed` with a body of
+/// `{"payment": "<reference>", "failure": "..."}`.
+pub struct FakeProvider {
+    settle: Duration,
+    payments: Mutex<Capped<FakePayment>>,
+}
+
+impl FakeProvider {
+    pub fn new(settle: Duration) -> Self {
+        Self::with_max(settle, MAX_INTENTS)
+    }
+
+    /// Keeping at most `max_payments`
+    pub fn with_max(settle: Duration, max_payments: usize) -> Self {
+        Self {
+            settle,
+            payments: Mutex::new(Capped::new(max_payments)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FakeEvent {
+    payment: String,
+    #[serde(default)]
+    failure: String,
+}
+
+impl PaymentProvider for FakeProvider {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn create_intent(&self, amount_cents: u64, description: &str) -> Result<String, PaymentError> {
+        let reference = random_id("fake_pi_");
+        tracing::info!(%reference, amount_cents, currency = CURRENCY, %description, "fake payment created");
+        self.payments.lock().unwrap().insert(
+            reference.clone(),
+            FakePayment {
+                card: String::new(),
+                confirmed: None,
+            },
+            |_| false,
+        );
+        Ok(reference)
+    }
+
+    fn confirm(&self, reference: &str, method: &str) -> Result<Update, PaymentError> {
+        let mut payments = self.payments.lock().unwrap();
+        let payment = payments
+            .by_key
+            .get_mut(reference)
+            .ok_or(PaymentError::NotFound)?;
+        payment.card = method.chars().filter(|c| !c.is_whitespace()).collect();
+        payment.confirmed = Some(Instant::now());
+        Ok(match payment.card.as_str() {
+            CARD_SUCCEEDS | CARD_WEBHOOK => Update::status(PaymentStatus::Processing),
+            CARD_DECLINED => Update::failed("Your card was declined."),
+            _ => Update::failed("That isn't one of the test cards."),
+        })
+    }
+
+    fn retrieve(&self, reference: &str) -> Result<Update, PaymentError> {
+        let payments = self.payments.lock().unwrap();
+        let payment = payments
+            .by_key
+            .get(reference)
+            .ok_or(PaymentError::NotFound)?;
+        let Some(confirmed) = payment.confirmed else {
+            return Ok(Update::status(PaymentStatus::RequiresConfirmation));
+        };
+        Ok(match payment.card.as_str() {
+            CARD_SUCCEEDS if confirmed.elapsed() >= self.settle => {
+                Update::status(PaymentStatus::Succeeded)
+            }
+            CARD_SUCCEEDS | CARD_WEBHOOK => Update::status(PaymentStatus::Processing),
+            CARD_DECLINED => Update::failed("Your card was declined."),
+            _ => Update::failed("That isn't one of the test cards."),
+        })
+    }
+
+    fn parse_webhook(&self, event_type: &str, payload: &str) -> Option<(String, Update)> {
+        let event: FakeEvent = serde_json::from_str(payload).ok()?;
+        let update = match event_type {
+            "payment.succeeded" => Update::status(PaymentStatus::Succeeded),
+            "payment.failed" if event.failure.is_empty() => Update::failed("The payment failed."),
+            "payment.failed" => Update::failed(event.failure),
+            _ => return None,
+        };
+        Some((event.payment, update))
+    }
+
+    fn test_methods(&self) -> &'static [(&'static str, &'static str)] {
+        TEST_CARDS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payments(settle: Duration) -> Payments {
+        Payments::new(Box::new(FakeProvider::new(settle)))
+    }
+
+    #[test]
+    fn test_confirm_then_settle() {
+        let payments = payments(Duration::ZERO);
+        let intent = payments.create("me", 500, "Tip").unwrap();
+        assert_eq!(intent.status, PaymentStatus::RequiresConfirmation);
+        assert!(payments.get("someone else", &intent.id).is_none());
+
+        let confirmed = payments.confirm("me", &intent.id, CARD_SUCCEEDS).unwrap();
+        assert_eq!(confirmed.status, PaymentStatus::Processing);
+        let settled = payments.refresh("me", &intent.id).unwrap();
+        assert_eq!(settled.status, PaymentStatus::Succeeded);
+
+        let declined = payments.create("me", 500, "Tip").unwrap();
+        let declined = payments.confirm("me", &declined.id, CARD_DECLINED).unwrap();
+        assert_eq!(declined.status, PaymentStatus::Failed);
+        assert_eq!(declined.failure, "Your card was declined.");
+    }
+
+    #[test]
+    fn test_webhook_reconciliation_is_final() {
+        let payments = payments(FAKE_SETTLE);
+        let intent = payments.create("me", 1_000, "Tip").unwrap();
+        payments.confirm("me", &intent.id, CARD_WEBHOOK).unwrap();
+        assert_eq!(
+            payments.refresh("me", &intent.id).unwrap().status,
+            PaymentStatus::Processing
+        );
+
+        let failed = format!(
+            r#"{{"payment": "{}", "failure": "Insufficient funds"}}"#,
+            intent.reference
+        );
+        assert!(payments.reconcile("payment.failed", &failed));
+        let succeeded = format!(r#"{{"payment": "{}"}}"#, intent.reference);
+        assert!(payments.reconcile("payment.succeeded", &succeeded));
+        let intent = payments.get("me", &intent.id).unwrap();
+        assert_eq!(intent.status, PaymentStatus::Failed);
+        assert_eq!(intent.failure, "Insufficient funds");
+
+        assert!(!payments.reconcile("payment.succeeded", r#"{"payment": "fake_pi_other"}"#));
+        assert!(!payments.reconcile("customer.created", &succeeded));
+    }
+
+    #[test]
+    fn test_oldest_intents_go_first_when_full() {
+        let payments = Payments::with_max(Box::new(FakeProvider::with_max(Duration::ZERO, 2)), 2);
+        let intents: Vec<PaymentIntent> = (0..3)
+            .map(|_| payments.create("me", 500, "Tip").unwrap())
+            .collect();
+        assert_eq!(payments.intents.read().unwrap().by_key.len(), 2);
+        assert!(payments.get("me", &intents[0].id).is_none());
+        assert!(payments.get("me", &intents[1].id).is_some());
+
+        // The provider forgot the oldest payment too
+        let provider = payments.provider();
+        assert!(matches!(
+            provider.retrieve(&intents[0].reference),
+            Err(PaymentError::NotFound)
+        ));
+        assert!(provider.retrieve(&intents[2].reference).is_ok());
+    }
+}
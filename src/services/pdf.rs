@@ -0,0 +1,506 @@
+//! PDF — printable documents written in pure Rust (`pdf` feature)
+//!
+//! Not an HTML renderer: a page that should also download as a PDF describes
+//! itself once more as a [`Document`] of headings, paragraphs and tables,
+//! built from the same view data its template gets (see
+//! [`crate::handlers::documents`]). [`Document::render`] lays that out on A4
+//! pages with running page numbers.
+//!
+//! Text is set in Helvetica, one of the fonts every PDF reader ships, so
+//! nothing is embedded and files stay a few kilobytes. The catch is the
+//! encoding: WinAnsi covers Latin-1 and common punctuation, and anything else
+//! prints as `?`.
+
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+/// A4, in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+const TITLE_SIZE: f32 = 20.0;
+const HEADING_SIZE: f32 = 13.0;
+const BODY_SIZE: f32 = 10.0;
+const SMALL_SIZE: f32 = 8.0;
+/// Line height as a multiple of the font size
+const LEADING: f32 = 1.4;
+/// Padding inside table cells, left and right
+const CELL_PADDING: f32 = 4.0;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+
+/// Column alignment in a [`Table`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// A table column: heading, share of the page width (the shares are
+/// normalized, so `[3.0, 1.0]` is three quarters and a quarter) and alignment
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub title: String,
+    pub width: f32,
+    pub align: Align,
+}
+
+impl Column {
+    pub fn new(title: impl Into<String>, width: f32, align: Align) -> Self {
+        Self {
+            title: title.into(),
+            width,
+            align,
+        }
+    }
+}
+
+/// Rows of single-line cells; text too wide for its column is cut short
+/// with `…`
+#[derive(Debug, Clone)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+    /// Bold rows under a rule, e.g. totals
+    summary: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+            summary: Vec::new(),
+        }
+    }
+
+    pub fn row(mut self, cells: Vec<String>) -> Self {
+        self.rows.push(cells);
+        self
+    }
+
+    pub fn summary(mut self, cells: Vec<String>) -> Self {
+        self.summary.push(cells);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Block {
+    Heading(String),
+    Text(String),
+    /// Bold label, regular value, on one line
+    Field(String, String),
+    Table(Table),
+    Space(f32),
+}
+
+/// A document to render, built top to bottom
+#[derive(Debug, Clone)]
+pub struct Document {
+    title: String,
+    blocks: Vec<Block>,
+}
+
+impl Document {
+    /// `title` heads the first page and names the file in PDF viewers
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn heading(mut self, text: impl Into<String>) -> Self {
+        self.blocks.push(Block::Heading(text.into()));
+        self
+    }
+
+    /// A paragraph, wrapped to the page width; `\n` starts a new line
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.blocks.push(Block::Text(text.into()));
+        self
+    }
+
+    pub fn field(mut self, label: impl Into<String>, value: impl Into<String>) -> Self {
+        self.blocks.push(Block::Field(label.into(), value.into()));
+        self
+    }
+
+    pub fn table(mut self, table: Table) -> Self {
+        self.blocks.push(Block::Table(table));
+        self
+    }
+
+    /// Vertical gap, in points
+    pub fn space(mut self, points: f32) -> Self {
+        self.blocks.push(Block::Space(points));
+        self
+    }
+
+    /// The finished PDF file
+    pub fn render(&self) -> Vec<u8> {
+        let mut layout = Layout::new();
+        layout.line(&self.title, BOLD, TITLE_SIZE);
+        layout.gap(BODY_SIZE);
+        for block in &self.blocks {
+            match block {
+                Block::Heading(text) => {
+                    layout.gap(HEADING_SIZE * 0.5);
+                    // Keep a heading with at least two lines of what follows
+                    layout.reserve(line_height(HEADING_SIZE) + 2.0 * line_height(BODY_SIZE));
+                    layout.line(text, BOLD, HEADING_SIZE);
+                    layout.gap(2.0);
+                }
+                Block::Text(text) => {
+                    for line in wrap(text, BODY_SIZE, CONTENT_WIDTH) {
+                        layout.line(&line, REGULAR, BODY_SIZE);
+                    }
+                }
+                Block::Field(label, value) => layout.field(label, value),
+                Block::Table(table) => layout.table(table),
+                Block::Space(points) => layout.gap(*points),
+            }
+        }
+        write(&self.title, layout.finish())
+    }
+}
+
+/// Lays blocks out top to bottom, starting a new page when one is full
+struct Layout {
+    pages: Vec<Content>,
+    /// Baseline of the next line on the current page
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self {
+            pages: vec![Content::new()],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn page(&mut self) -> &mut Content {
+        self.pages.last_mut().expect("there is always a page")
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Content::new());
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    /// Start a new page unless `height` more points fit on this one
+    fn reserve(&mut self, height: f32) {
+        // Leave the bottom margin free for the page number
+        if self.y - height < MARGIN + SMALL_SIZE {
+            self.new_page();
+        }
+    }
+
+    fn gap(&mut self, points: f32) {
+        self.y -= points;
+    }
+
+    /// Advance a line of `size` text and return its baseline
+    fn advance(&mut self, size: f32) -> f32 {
+        let height = line_height(size);
+        self.reserve(height);
+        self.y -= height;
+        self.y + (height - size) / 2.0
+    }
+
+    fn line(&mut self, text: &str, font: Name, size: f32) {
+        let baseline = self.advance(size);
+        text_at(self.page(), MARGIN, baseline, font, size, text);
+    }
+
+    fn field(&mut self, label: &str, value: &str) {
+        let baseline = self.advance(BODY_SIZE);
+        let label_width = 110.0;
+        let value = truncate(value, BODY_SIZE, CONTENT_WIDTH - label_width);
+        let page = self.page();
+        text_at(page, MARGIN, baseline, BOLD, BODY_SIZE, label);
+        text_at(
+            page,
+            MARGIN + label_width,
+            baseline,
+            REGULAR,
+            BODY_SIZE,
+            &value,
+        );
+    }
+
+    fn table(&mut self, table: &Table) {
+        let total: f32 = table.columns.iter().map(|c| c.width.max(0.0)).sum();
+        let widths: Vec<f32> = table
+            .columns
+            .iter()
+            .map(|c| CONTENT_WIDTH * c.width.max(0.0) / total.max(f32::EPSILON))
+            .collect();
+        let header: Vec<String> = table.columns.iter().map(|c| c.title.clone()).collect();
+
+        // The header row, with a rule under it
+        let header_row = |layout: &mut Self| {
+            layout.reserve(3.0 * line_height(BODY_SIZE));
+            layout.cells(table, &widths, &header, BOLD, SMALL_SIZE);
+            layout.rule(0.75);
+        };
+        header_row(self);
+
+        for row in &table.rows {
+            if self.y - line_height(BODY_SIZE) < MARGIN + SMALL_SIZE {
+                // Repeat the header at the top of every page the table spans
+                self.new_page();
+                header_row(self);
+            }
+            self.cells(table, &widths, row, REGULAR, BODY_SIZE);
+        }
+        if !table.summary.is_empty() {
+            self.reserve(line_height(BODY_SIZE) * table.summary.len() as f32);
+            self.rule(0.75);
+            for row in &table.summary {
+                self.cells(table, &widths, row, BOLD, BODY_SIZE);
+            }
+        }
+        self.gap(BODY_SIZE);
+    }
+
+    fn cells(&mut self, table: &Table, widths: &[f32], row: &[String], font: Name, size: f32) {
+        let baseline = self.advance(size.max(BODY_SIZE));
+        let page = self.pages.last_mut().expect("there is always a page");
+        let mut left = MARGIN;
+        for ((column, width), cell) in table.columns.iter().zip(widths).zip(row) {
+            let text = truncate(cell, size, width - 2.0 * CELL_PADDING);
+            let x = match column.align {
+                Align::Left => left + CELL_PADDING,
+                Align::Right => left + width - CELL_PADDING - text_width(&text, size),
+            };
+            if !text.is_empty() {
+                text_at(page, x, baseline, font, size, &text);
+            }
+            left += width;
+        }
+    }
+
+    /// A hairline across the page just above the next line
+    fn rule(&mut self, width: f32) {
+        let y = self.y;
+        let page = self.page();
+        page.set_stroke_gray(0.6)
+            .set_line_width(width)
+            .move_to(MARGIN, y)
+            .line_to(PAGE_WIDTH - MARGIN, y)
+            .stroke();
+    }
+
+    /// Number the pages and hand them over
+    fn finish(mut self) -> Vec<Vec<u8>> {
+        let count = self.pages.len();
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            let label = format!("Page {} of {count}", i + 1);
+            let x = PAGE_WIDTH - MARGIN - text_width(&label, SMALL_SIZE);
+            text_at(page, x, MARGIN / 2.0, REGULAR, SMALL_SIZE, &label);
+        }
+        self.pages.into_iter().map(Content::finish).collect()
+    }
+}
+
+fn line_height(size: f32) -> f32 {
+    size * LEADING
+}
+
+fn text_at(page: &mut Content, x: f32, y: f32, font: Name, size: f32, text: &str) {
+    page.set_fill_gray(0.0)
+        .begin_text()
+        .set_font(font, size)
+        .next_line(x, y)
+        .show(Str(&encode(text)))
+        .end_text();
+}
+
+/// Assemble the file: catalog, page tree, fonts and one stream per page
+fn write(title: &str, pages: Vec<Vec<u8>>) -> Vec<u8> {
+    let mut pdf = Pdf::new();
+    let catalog_id = Ref::new(1);
+    let tree_id = Ref::new(2);
+    let regular_id = Ref::new(3);
+    let bold_id = Ref::new(4);
+    let info_id = Ref::new(5);
+    // Then a page and its content stream for every page
+    let page_ids: Vec<(Ref, Ref)> = (0..pages.len() as i32)
+        .map(|i| (Ref::new(6 + 2 * i), Ref::new(7 + 2 * i)))
+        .collect();
+
+    pdf.catalog(catalog_id).pages(tree_id);
+    pdf.pages(tree_id)
+        .kids(page_ids.iter().map(|(page, _)| *page))
+        .count(pages.len() as i32);
+    for (id, name) in [(regular_id, "Helvetica"), (bold_id, "Helvetica-Bold")] {
+        pdf.type1_font(id)
+            .base_font(Name(name.as_bytes()))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+    }
+    pdf.document_info(info_id)
+        .title(TextStr(title))
+        .producer(TextStr(concat!(
+            env!("CARGO_PKG_NAME"),
+            " ",
+            env!("CARGO_PKG_VERSION")
+        )));
+
+    for ((page_id, content_id), content) in page_ids.into_iter().zip(pages) {
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+            .parent(tree_id)
+            .contents(content_id);
+        page.resources()
+            .fonts()
+            .pair(REGULAR, regular_id)
+            .pair(BOLD, bold_id);
+        page.finish();
+        pdf.stream(content_id, &content);
+    }
+    pdf.finish()
+}
+
+/// Text in WinAnsiEncoding: Latin-1 maps straight through, a few common
+/// typographic characters have their own codes, anything else becomes `?`
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '€' => 0x80,
+            '…' => 0x85,
+            '•' => 0x95,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '–' => 0x96,
+            '—' => 0x97,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Helvetica advance widths for `' '..='~'`, in thousandths of an em
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278,
+    278, // ' '..'/'
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584,
+    556, // '0'..'?'
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722,
+    778, // '@'..'O'
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469,
+    556, // 'P'..'_'
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556,
+    556, // '`'..'o'
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // 'p'..'~'
+];
+
+/// Width of `text` at `size`. Bold is measured with the regular widths,
+/// which matches for digits and runs a little short for letters.
+fn text_width(text: &str, size: f32) -> f32 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c {
+            ' '..='~' => HELVETICA_WIDTHS[c as usize - 0x20] as u32,
+            '…' | '—' => 1000,
+            _ => 556,
+        })
+        .sum();
+    units as f32 * size / 1000.0
+}
+
+/// `text` shortened with `…` until it fits in `width`
+fn truncate(text: &str, size: f32, width: f32) -> String {
+    if text_width(text, size) <= width {
+        return text.to_string();
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate: String = chars.iter().collect::<String>().trim_end().to_string() + "…";
+        if text_width(&candidate, size) <= width {
+            return candidate;
+        }
+    }
+    String::new()
+}
+
+/// Break `text` into lines no wider than `width`. Words longer than a line
+/// are cut short rather than split.
+fn wrap(text: &str, size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{line} {word}")
+            };
+            if text_width(&candidate, size) <= width || line.is_empty() {
+                line = candidate;
+            } else {
+                lines.push(std::mem::take(&mut line));
+                line = word.to_string();
+            }
+        }
+        lines.push(truncate(&line, size, width));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let table = Table::new(vec![
+            Column::new("Item", 3.0, Align::Left),
+            Column::new("Amount", 1.0, Align::Right),
+        ])
+        .row(vec!["Hosting".into(), "12.00".into()])
+        .summary(vec!["Total".into(), "12.00".into()]);
+        let pdf = Document::new("Invoice")
+            .field("Number", "INV-1")
+            .table(table)
+            .render();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(text.contains("/Helvetica-Bold"));
+        assert!(text.contains("/Count 1"));
+    }
+
+    #[test]
+    fn test_long_tables_continue_on_new_pages() {
+        let mut table = Table::new(vec![Column::new("N", 1.0, Align::Right)]);
+        for i in 0..200 {
+            table = table.row(vec![i.to_string()]);
+        }
+        let pdf = Document::new("Long").table(table).render();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Count 4"));
+        // The header row is repeated on every page
+        assert_eq!(text.matches("(N) Tj").count(), 4);
+    }
+
+    #[test]
+    fn test_wrap_and_truncate() {
+        // 'a' is 556/1000 em: ten of them at 10pt are 55.6pt wide
+        let lines = wrap("aaaa aaaa aaaa", 10.0, 50.0);
+        assert_eq!(lines, vec!["aaaa aaaa", "aaaa"]);
+        assert_eq!(wrap("one\ntwo", 10.0, 500.0), vec!["one", "two"]);
+        assert_eq!(truncate("aaaaaaaaaa", 10.0, 30.0), "aaa…");
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("Café €5 — ok ✓"), b"Caf\xe9 \x805 \x97 ok ?");
+    }
+}
@@ -0,0 +1,555 @@
+//! Post Service — Markdown blog posts with draft/published states
+//!
+//! Bodies are stored as Markdown and rendered (then sanitized) on the way
+//! out with [`crate::services::sanitize::markdown`]. Every post has a unique
+//! slug, derived from its title unless one is given; clashes get a numeric
+//! suffix (`hello-world-2`). A post whose slug changes keeps its old ones in
+//! the slug history (see [`crate::services::slugs`]), so
+//! [`PostService::get_by_old_slug`] finds it for a redirect. Timestamps are
+//! RFC 3339 UTC strings, so they sort lexically and drop straight into feeds.
+
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+use super::slugs::{self, SlugHistory, SlugKind};
+
+/// Post slugs are unique site-wide: one history scope
+const SLUG_SCOPE: &str = "";
+
+/// Whether a post is visible on the public blog
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostStatus {
+    #[default]
+    Draft,
+    Published,
+}
+
+impl PostStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PostStatus::Draft => "draft",
+            PostStatus::Published => "published",
+        }
+    }
+
+    fn from_db(value: &str) -> Self {
+        match value {
+            "published" => PostStatus::Published,
+            _ => PostStatus::Draft,
+        }
+    }
+}
+
+/// Post data model
+#[derive(Debug, Clone, Serialize)]
+pub struct Post {
+    pub id: u32,
+    pub slug: String,
+    pub title: String,
+    /// Plain-text teaser for the index and feeds
+    pub summary: String,
+    /// Markdown source — render with `sanitize::markdown`
+    pub body: String,
+    pub status: PostStatus,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Set the first time the post is published, kept through later edits
+    pub published_at: Option<String>,
+}
+
+/// Fields an editor submits when creating or updating a post
+#[derive(Debug, Clone, Default)]
+pub struct PostInput {
+    pub title: String,
+    /// Desired slug; empty derives one from the title
+    pub slug: String,
+    pub summary: String,
+    pub body: String,
+    pub status: PostStatus,
+}
+
+/// Post service trait — defines operations for blog posts
+pub trait PostService: Send + Sync {
+    /// Every post including drafts, most recently updated first
+    fn list_all(&self) -> Vec<Post>;
+    /// A page of published posts, newest first
+    fn list_published(&self, offset: usize, limit: usize) -> Vec<Post>;
+    fn count_published(&self) -> usize;
+    fn get_by_id(&self, id: u32) -> Option<Post>;
+    fn get_by_slug(&self, slug: &str) -> Option<Post>;
+    /// The post that used to be at `slug`, to redirect to its current one
+    fn get_by_old_slug(&self, slug: &str) -> Option<Post>;
+    fn create(&self, input: PostInput) -> Post;
+    fn update(&self, id: u32, input: PostInput) -> Option<Post>;
+    fn delete(&self, id: u32) -> bool;
+}
+
+/// The slug for `input`, suffixed until it's not in `taken`
+fn unique_slug(input: &PostInput, taken: &[String]) -> String {
+    slugs::unique(&[&input.slug, &input.title], "post", |slug| {
+        taken.iter().any(|t| t == slug)
+    })
+}
+
+/// `published_at` after saving with `status`: stamped on first publish
+fn published_at(status: PostStatus, previous: Option<String>, now: &str) -> Option<String> {
+    match (status, previous) {
+        (_, Some(at)) => Some(at),
+        (PostStatus::Published, None) => Some(now.to_string()),
+        (PostStatus::Draft, None) => None,
+    }
+}
+
+fn now() -> String {
+    chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+const WELCOME_BODY: &str = "Posts are written in **Markdown** and sanitized before \
+they're rendered, so editors get formatting without getting a script tag through.\n\n\
+Sign in at `/admin` to write your own — drafts stay private until you publish them.";
+
+/// In-memory post storage (good for prototyping, tests)
+pub struct InMemoryPostService {
+    posts: RwLock<Vec<Post>>,
+    next_id: RwLock<u32>,
+    history: SlugHistory,
+}
+
+impl InMemoryPostService {
+    pub fn new() -> Self {
+        let seeded = "2024-01-01T00:00:00Z".to_string();
+        Self {
+            posts: RwLock::new(vec![Post {
+                id: 1,
+                slug: "hello-world".into(),
+                title: "Hello, World".into(),
+                summary: "A first post to show the blog end to end.".into(),
+                body: WELCOME_BODY.into(),
+                status: PostStatus::Published,
+                created_at: seeded.clone(),
+                updated_at: seeded.clone(),
+                published_at: Some(seeded),
+            }]),
+            next_id: RwLock::new(2),
+            history: SlugHistory::new(),
+        }
+    }
+}
+
+impl Default for InMemoryPostService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PostService for InMemoryPostService {
+    fn list_all(&self) -> Vec<Post> {
+        let mut posts = self.posts.read().unwrap().clone();
+        posts.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then(b.id.cmp(&a.id)));
+        posts
+    }
+
+    fn list_published(&self, offset: usize, limit: usize) -> Vec<Post> {
+        let mut posts: Vec<Post> = self
+            .posts
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|p| p.status == PostStatus::Published)
+            .cloned()
+            .collect();
+        posts.sort_by(|a, b| b.published_at.cmp(&a.published_at).then(b.id.cmp(&a.id)));
+        posts.into_iter().skip(offset).take(limit).collect()
+    }
+
+    fn count_published(&self) -> usize {
+        self.posts
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|p| p.status == PostStatus::Published)
+            .count()
+    }
+
+    fn get_by_id(&self, id: u32) -> Option<Post> {
+        self.posts
+            .read()
+            .unwrap()
+            .iter()
+            .find(|p| p.id == id)
+            .cloned()
+    }
+
+    fn get_by_slug(&self, slug: &str) -> Option<Post> {
+        self.posts
+            .read()
+            .unwrap()
+            .iter()
+            .find(|p| p.slug == slug)
+            .cloned()
+    }
+
+    fn get_by_old_slug(&self, slug: &str) -> Option<Post> {
+        self.get_by_id(self.history.resolve(SLUG_SCOPE, slug)?)
+    }
+
+    fn create(&self, input: PostInput) -> Post {
+        let mut posts = self.posts.write().unwrap();
+        let mut next_id = self.next_id.write().unwrap();
+        let taken: Vec<String> = posts.iter().map(|p| p.slug.clone()).collect();
+        let now = now();
+        let post = Post {
+            id: *next_id,
+            slug: unique_slug(&input, &taken),
+            published_at: published_at(input.status, None, &now),
+            title: input.title,
+            summary: input.summary,
+            body: input.body,
+            status: input.status,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        *next_id += 1;
+
+        self.history.claim(SLUG_SCOPE, &post.slug);
+        posts.push(post.clone());
+        post
+    }
+
+    fn update(&self, id: u32, input: PostInput) -> Option<Post> {
+        let mut posts = self.posts.write().unwrap();
+        let taken: Vec<String> = posts
+            .iter()
+            .filter(|p| p.id != id)
+            .map(|p| p.slug.clone())
+            .collect();
+        let post = posts.iter_mut().find(|p| p.id == id)?;
+        let now = now();
+        let slug = unique_slug(&input, &taken);
+        self.history.moved(SLUG_SCOPE, id, &post.slug, &slug);
+        post.slug = slug;
+        post.published_at = published_at(input.status, post.published_at.take(), &now);
+        post.title = input.title;
+        post.summary = input.summary;
+        post.body = input.body;
+        post.status = input.status;
+        post.updated_at = now;
+        Some(post.clone())
+    }
+
+    fn delete(&self, id: u32) -> bool {
+        let mut posts = self.posts.write().unwrap();
+        let len_before = posts.len();
+        posts.retain(|p| p.id != id);
+        self.history.forget(SLUG_SCOPE, id);
+        posts.len() < len_before
+    }
+}
+
+// ============================================================================
+// SQLx Implementation — SQLite-backed post storage
+// ============================================================================
+
+use crate::db::Db;
+
+pub struct SqlitePostService {
+    db: Db,
+}
+
+impl SqlitePostService {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+
+    /// Slugs of every post except `id`
+    async fn taken_slugs(&self, id: u32) -> Vec<String> {
+        sqlx::query_scalar("SELECT slug FROM posts WHERE id != ?")
+            .bind(id as i64)
+            .fetch_all(self.db.reader())
+            .await
+            .unwrap_or_default()
+    }
+}
+
+/// Row type returned by SQLx queries
+#[derive(sqlx::FromRow)]
+struct PostRow {
+    id: i64,
+    slug: String,
+    title: String,
+    summary: String,
+    body: String,
+    status: String,
+    created_at: String,
+    updated_at: String,
+    published_at: Option<String>,
+}
+
+impl From<PostRow> for Post {
+    fn from(row: PostRow) -> Self {
+        Post {
+            id: row.id as u32,
+            slug: row.slug,
+            title: row.title,
+            summary: row.summary,
+            body: row.body,
+            status: PostStatus::from_db(&row.status),
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            published_at: row.published_at,
+        }
+    }
+}
+
+const POST_COLUMNS: &str =
+    "id, slug, title, summary, body, status, created_at, updated_at, published_at";
+
+impl PostService for SqlitePostService {
+    fn list_all(&self) -> Vec<Post> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, PostRow>(&format!(
+                    "SELECT {POST_COLUMNS} FROM posts ORDER BY updated_at DESC, id DESC"
+                ))
+                .fetch_all(self.db.reader())
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(Post::from)
+                .collect()
+            })
+        })
+    }
+
+    fn list_published(&self, offset: usize, limit: usize) -> Vec<Post> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, PostRow>(&format!(
+                    "SELECT {POST_COLUMNS} FROM posts WHERE status = 'published' \
+                     ORDER BY published_at DESC, id DESC LIMIT ? OFFSET ?"
+                ))
+                .bind(limit as i64)
+                .bind(offset as i64)
+                .fetch_all(self.db.reader())
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(Post::from)
+                .collect()
+            })
+        })
+    }
+
+    fn count_published(&self) -> usize {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_scalar::<_, i64>(
+                    "SELECT COUNT(*) FROM posts WHERE status = 'published'",
+                )
+                .fetch_one(self.db.reader())
+                .await
+                .unwrap_or(0) as usize
+            })
+        })
+    }
+
+    fn get_by_id(&self, id: u32) -> Option<Post> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, PostRow>(&format!(
+                    "SELECT {POST_COLUMNS} FROM posts WHERE id = ?"
+                ))
+                .bind(id as i64)
+                .fetch_optional(self.db.reader())
+                .await
+                .ok()
+                .flatten()
+                .map(Post::from)
+            })
+        })
+    }
+
+    fn get_by_slug(&self, slug: &str) -> Option<Post> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, PostRow>(&format!(
+                    "SELECT {POST_COLUMNS} FROM posts WHERE slug = ?"
+                ))
+                .bind(slug)
+                .fetch_optional(self.db.reader())
+                .await
+                .ok()
+                .flatten()
+                .map(Post::from)
+            })
+        })
+    }
+
+    fn get_by_old_slug(&self, slug: &str) -> Option<Post> {
+        let id = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(slugs::resolve(
+                self.db.reader(),
+                SlugKind::Post,
+                SLUG_SCOPE,
+                slug,
+            ))
+        });
+        self.get_by_id(id.ok().flatten()?)
+    }
+
+    fn create(&self, input: PostInput) -> Post {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let slug = unique_slug(&input, &self.taken_slugs(0).await);
+                let now = now();
+                let mut tx = self
+                    .db
+                    .writer()
+                    .begin()
+                    .await
+                    .expect("Failed to start post transaction");
+                let row = sqlx::query_as::<_, PostRow>(&format!(
+                    "INSERT INTO posts (slug, title, summary, body, status, created_at, \
+                     updated_at, published_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?) \
+                     RETURNING {POST_COLUMNS}"
+                ))
+                .bind(&slug)
+                .bind(&input.title)
+                .bind(&input.summary)
+                .bind(&input.body)
+                .bind(input.status.as_str())
+                .bind(&now)
+                .bind(&now)
+                .bind(published_at(input.status, None, &now))
+                .fetch_one(&mut *tx)
+                .await
+                .expect("Failed to insert post");
+                slugs::claim(&mut tx, SlugKind::Post, SLUG_SCOPE, &slug)
+                    .await
+                    .expect("Failed to claim post slug");
+                tx.commit().await.expect("Failed to commit post");
+                Post::from(row)
+            })
+        })
+    }
+
+    fn update(&self, id: u32, input: PostInput) -> Option<Post> {
+        let existing = self.get_by_id(id)?;
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let slug = unique_slug(&input, &self.taken_slugs(id).await);
+                let now = now();
+                let mut tx = self.db.writer().begin().await.ok()?;
+                let post = sqlx::query_as::<_, PostRow>(&format!(
+                    "UPDATE posts SET slug = ?, title = ?, summary = ?, body = ?, status = ?, \
+                     updated_at = ?, published_at = ? WHERE id = ? RETURNING {POST_COLUMNS}"
+                ))
+                .bind(&slug)
+                .bind(&input.title)
+                .bind(&input.summary)
+                .bind(&input.body)
+                .bind(input.status.as_str())
+                .bind(&now)
+                .bind(published_at(input.status, existing.published_at, &now))
+                .bind(id as i64)
+                .fetch_optional(&mut *tx)
+                .await
+                .ok()
+                .flatten()
+                .map(Post::from)?;
+                slugs::moved(
+                    &mut tx,
+                    SlugKind::Post,
+                    SLUG_SCOPE,
+                    id,
+                    &existing.slug,
+                    &slug,
+                )
+                .await
+                .ok()?;
+                tx.commit().await.ok()?;
+                Some(post)
+            })
+        })
+    }
+
+    fn delete(&self, id: u32) -> bool {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let Ok(mut tx) = self.db.writer().begin().await else {
+                    return false;
+                };
+                let deleted = sqlx::query("DELETE FROM posts WHERE id = ?")
+                    .bind(id as i64)
+                    .execute(&mut *tx)
+                    .await
+                    .is_ok_and(|r| r.rows_affected() > 0);
+                deleted
+                    && slugs::forget(&mut tx, SlugKind::Post, SLUG_SCOPE, id)
+                        .await
+                        .is_ok()
+                    && tx.commit().await.is_ok()
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugs_and_publishing() {
+        assert_eq!(slugs::slugify("  Hello, World! "), "hello-world");
+        assert_eq!(slugs::slugify("Rust & HTMX: 2024"), "rust-htmx-2024");
+
+        let service = InMemoryPostService::new();
+        let draft = service.create(PostInput {
+            title: "Hello, World".into(),
+            ..PostInput::default()
+        });
+        assert_eq!(draft.slug, "hello-world-2");
+        assert!(draft.published_at.is_none());
+        assert_eq!(service.count_published(), 1);
+
+        let published = service
+            .update(
+                draft.id,
+                PostInput {
+                    title: "Renamed".into(),
+                    slug: "hello-world-2".into(),
+                    status: PostStatus::Published,
+                    ..PostInput::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(published.slug, "hello-world-2");
+        assert!(published.published_at.is_some());
+        assert_eq!(service.list_published(0, 1)[0].id, draft.id);
+    }
+
+    #[test]
+    fn test_renamed_post_keeps_old_slug() {
+        let service = InMemoryPostService::new();
+        let post = service.create(PostInput {
+            title: "Launch".into(),
+            ..PostInput::default()
+        });
+        let renamed = service
+            .update(
+                post.id,
+                PostInput {
+                    title: "Launch day".into(),
+                    ..PostInput::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(renamed.slug, "launch-day");
+        assert_eq!(service.get_by_old_slug("launch").unwrap().id, post.id);
+        assert!(service.get_by_old_slug("launch-day").is_none());
+
+        service.delete(post.id);
+        assert!(service.get_by_old_slug("launch").is_none());
+    }
+}
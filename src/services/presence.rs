@@ -0,0 +1,340 @@
+//! Presence Service — who's been around lately
+//!
+//! Every request touches its session's entry in an in-memory map, so tracking
+//! costs a hash insert rather than a write to the database. Entries are keyed
+//! like comment owners ([`crate::services::comments::owner_key`]) and carry the
+//! name the session goes by — empty for guests. A background task calls
+//! [`PresenceTracker::flush`] every [`FLUSH_INTERVAL`] to copy changed entries
+//! to a [`PresenceStore`], and [`PresenceTracker::load`] reads them back on
+//! startup so "last seen" survives a restart.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// Seen within this long counts as online
+pub const ONLINE_WINDOW: Duration = Duration::from_secs(5 * 60);
+/// How often changed entries are written to the store
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+/// Entries older than this are dropped from memory and the store
+const RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// When a session was last seen
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Seen {
+    /// Session key, see [`crate::services::comments::owner_key`]
+    #[serde(skip)]
+    pub key: String,
+    /// Empty for guests
+    pub name: String,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Presence store trait — where [`PresenceTracker`] persists entries
+pub trait PresenceStore: Send + Sync {
+    /// Insert or replace entries by key
+    fn save(&self, entries: &[Seen]);
+    /// Entries seen since `since`
+    fn load(&self, since: DateTime<Utc>) -> Vec<Seen>;
+    /// Delete entries last seen before `before`
+    fn prune(&self, before: DateTime<Utc>);
+}
+
+fn ago(duration: Duration) -> DateTime<Utc> {
+    Utc::now() - chrono::Duration::from_std(duration).unwrap_or_default()
+}
+
+/// Last-seen times, held in memory and flushed to a [`PresenceStore`]
+pub struct PresenceTracker {
+    seen: RwLock<HashMap<String, Seen>>,
+    // Keys touched since the last flush
+    dirty: Mutex<HashSet<String>>,
+    store: Arc<dyn PresenceStore>,
+}
+
+impl PresenceTracker {
+    pub fn new(store: Arc<dyn PresenceStore>) -> Self {
+        Self {
+            seen: RwLock::new(HashMap::new()),
+            dirty: Mutex::new(HashSet::new()),
+            store,
+        }
+    }
+
+    /// Read back entries from the store (call once, on startup)
+    pub fn load(&self) {
+        let entries = self.store.load(ago(RETENTION));
+        let mut seen = self.seen.write().unwrap();
+        for entry in entries {
+            seen.entry(entry.key.clone()).or_insert(entry);
+        }
+    }
+
+    /// Mark `key` as seen now. `name` replaces the name it goes by; `None`
+    /// keeps the one it has.
+    pub fn touch(&self, key: &str, name: Option<&str>) {
+        let now = Utc::now();
+        let mut seen = self.seen.write().unwrap();
+        let entry = seen.entry(key.to_string()).or_insert_with(|| Seen {
+            key: key.to_string(),
+            name: String::new(),
+            last_seen: now,
+        });
+        entry.last_seen = now;
+        if let Some(name) = name {
+            entry.name = name.trim().to_string();
+        }
+        self.dirty.lock().unwrap().insert(key.to_string());
+    }
+
+    /// Entries seen within [`ONLINE_WINDOW`], most recent first
+    pub fn online(&self) -> Vec<Seen> {
+        let since = ago(ONLINE_WINDOW);
+        let mut online: Vec<Seen> = self
+            .seen
+            .read()
+            .unwrap()
+            .values()
+            .filter(|entry| entry.last_seen >= since)
+            .cloned()
+            .collect();
+        online.sort_by_key(|entry| std::cmp::Reverse(entry.last_seen));
+        online
+    }
+
+    /// When anyone going by `name` was last seen (case-insensitive)
+    pub fn last_seen(&self, name: &str) -> Option<DateTime<Utc>> {
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        self.seen
+            .read()
+            .unwrap()
+            .values()
+            .filter(|entry| entry.name.eq_ignore_ascii_case(name))
+            .map(|entry| entry.last_seen)
+            .max()
+    }
+
+    /// Whether anyone going by `name` was seen within [`ONLINE_WINDOW`]
+    pub fn is_online(&self, name: &str) -> bool {
+        self.last_seen(name)
+            .is_some_and(|last_seen| last_seen >= ago(ONLINE_WINDOW))
+    }
+
+    /// Write entries touched since the last flush to the store and forget
+    /// ones past retention. Returns how many were written.
+    pub fn flush(&self) -> usize {
+        let keys: Vec<String> = self.dirty.lock().unwrap().drain().collect();
+        let cutoff = ago(RETENTION);
+        let entries: Vec<Seen> = {
+            let mut seen = self.seen.write().unwrap();
+            seen.retain(|_, entry| entry.last_seen >= cutoff);
+            keys.iter()
+                .filter_map(|key| seen.get(key).cloned())
+                .collect()
+        };
+        if !entries.is_empty() {
+            self.store.save(&entries);
+        }
+        self.store.prune(cutoff);
+        entries.len()
+    }
+}
+
+// ============================================================================
+// In-memory implementation
+// ============================================================================
+
+/// In-memory presence storage (good for prototyping, tests)
+pub struct InMemoryPresenceStore {
+    entries: RwLock<HashMap<String, Seen>>,
+}
+
+impl InMemoryPresenceStore {
+    pub fn new() -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for InMemoryPresenceStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PresenceStore for InMemoryPresenceStore {
+    fn save(&self, entries: &[Seen]) {
+        let mut stored = self.entries.write().unwrap();
+        for entry in entries {
+            stored.insert(entry.key.clone(), entry.clone());
+        }
+    }
+
+    fn load(&self, since: DateTime<Utc>) -> Vec<Seen> {
+        self.entries
+            .read()
+            .unwrap()
+            .values()
+            .filter(|entry| entry.last_seen >= since)
+            .cloned()
+            .collect()
+    }
+
+    fn prune(&self, before: DateTime<Utc>) {
+        self.entries
+            .write()
+            .unwrap()
+            .retain(|_, entry| entry.last_seen >= before);
+    }
+}
+
+// ============================================================================
+// SQLx Implementation — SQLite-backed presence storage
+// ============================================================================
+
+use crate::db::Db;
+
+/// `last_seen` column format, sortable as text
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub struct SqlitePresenceStore {
+    db: Db,
+}
+
+impl SqlitePresenceStore {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+}
+
+/// Row type returned by SQLx queries
+#[derive(sqlx::FromRow)]
+struct PresenceRow {
+    key: String,
+    name: String,
+    last_seen: String,
+}
+
+impl PresenceRow {
+    fn into_seen(self) -> Option<Seen> {
+        let last_seen = NaiveDateTime::parse_from_str(&self.last_seen, TIME_FORMAT).ok()?;
+        Some(Seen {
+            key: self.key,
+            name: self.name,
+            last_seen: last_seen.and_utc(),
+        })
+    }
+}
+
+impl PresenceStore for SqlitePresenceStore {
+    fn save(&self, entries: &[Seen]) {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let mut tx = match self.db.writer().begin().await {
+                    Ok(tx) => tx,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Failed to save presence");
+                        return;
+                    }
+                };
+                for entry in entries {
+                    let result = sqlx::query(
+                        "INSERT INTO presence (key, name, last_seen) VALUES (?, ?, ?) \
+                         ON CONFLICT (key) DO UPDATE SET \
+                         name = excluded.name, last_seen = excluded.last_seen",
+                    )
+                    .bind(&entry.key)
+                    .bind(&entry.name)
+                    .bind(entry.last_seen.format(TIME_FORMAT).to_string())
+                    .execute(&mut *tx)
+                    .await;
+                    if let Err(e) = result {
+                        tracing::warn!(error = %e, "Failed to save presence");
+                        return;
+                    }
+                }
+                if let Err(e) = tx.commit().await {
+                    tracing::warn!(error = %e, "Failed to save presence");
+                }
+            })
+        })
+    }
+
+    fn load(&self, since: DateTime<Utc>) -> Vec<Seen> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, PresenceRow>(
+                    "SELECT key, name, last_seen FROM presence WHERE last_seen >= ?",
+                )
+                .bind(since.format(TIME_FORMAT).to_string())
+                .fetch_all(self.db.reader())
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(PresenceRow::into_seen)
+                .collect()
+            })
+        })
+    }
+
+    fn prune(&self, before: DateTime<Utc>) {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let _ = sqlx::query("DELETE FROM presence WHERE last_seen < ?")
+                    .bind(before.format(TIME_FORMAT).to_string())
+                    .execute(self.db.writer())
+                    .await;
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_touch_and_is_online() {
+        let tracker = PresenceTracker::new(Arc::new(InMemoryPresenceStore::new()));
+        tracker.touch("a", Some("Ada"));
+        tracker.touch("b", None);
+        // Later touches without a name keep the one given
+        tracker.touch("a", None);
+
+        assert!(tracker.is_online("ada"));
+        assert!(!tracker.is_online("Grace"));
+        assert!(!tracker.is_online(""));
+        assert_eq!(tracker.online().len(), 2);
+
+        tracker
+            .seen
+            .write()
+            .unwrap()
+            .get_mut("a")
+            .unwrap()
+            .last_seen = ago(ONLINE_WINDOW) - chrono::Duration::seconds(1);
+        assert!(!tracker.is_online("Ada"));
+        assert!(tracker.last_seen("Ada").is_some());
+    }
+
+    #[test]
+    fn test_flush_and_load() {
+        let store = Arc::new(InMemoryPresenceStore::new());
+        let tracker = PresenceTracker::new(store.clone());
+        tracker.touch("a", Some("Ada"));
+        tracker.touch("a", None);
+        assert_eq!(tracker.flush(), 1);
+        // Nothing changed since
+        assert_eq!(tracker.flush(), 0);
+
+        let restarted = PresenceTracker::new(store);
+        restarted.load();
+        assert!(restarted.is_online("Ada"));
+    }
+}
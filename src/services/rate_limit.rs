@@ -0,0 +1,136 @@
+//! Rate Limiter — fixed-window request counting per key
+//!
+//! One in-memory limiter is shared by every caller; each names its own keys
+//! (`contact:<session>`) and passes the [`Limit`] it wants enforced, so
+//! policies live next to the handlers they protect. Counts reset on restart.
+//!
+//! [`InFlight`] counts requests being handled right now, for load shedding.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Keys tracked before expired windows are swept
+const SWEEP_THRESHOLD: usize = 10_000;
+
+/// At most `max` hits per `window`
+#[derive(Debug, Clone, Copy)]
+pub struct Limit {
+    pub max: u32,
+    pub window: Duration,
+}
+
+impl Limit {
+    pub const fn per_minute(max: u32) -> Self {
+        Self {
+            max,
+            window: Duration::from_secs(60),
+        }
+    }
+
+    pub const fn per_hour(max: u32) -> Self {
+        Self {
+            max,
+            window: Duration::from_secs(3600),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RateLimiter {
+    /// Key → (window start, hits in that window)
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a hit for `key`; `false` once the limit for the current window
+    /// is exceeded (rejected hits still count)
+    pub fn check(&self, key: &str, limit: Limit) -> bool {
+        self.check_at(key, limit, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, limit: Limit, now: Instant) -> bool {
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() >= SWEEP_THRESHOLD {
+            windows.retain(|_, (start, _)| now.duration_since(*start) < limit.window);
+        }
+        let (start, hits) = windows.entry(key.to_string()).or_insert((now, 0));
+        if now.duration_since(*start) >= limit.window {
+            *start = now;
+            *hits = 0;
+        }
+        *hits = hits.saturating_add(1);
+        *hits <= limit.max
+    }
+}
+
+/// Requests being handled right now
+#[derive(Debug, Default)]
+pub struct InFlight {
+    count: AtomicU32,
+}
+
+impl InFlight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a request in, unless `max` already are; dropping the guard
+    /// counts it out
+    pub fn enter(self: &Arc<Self>, max: u32) -> Option<InFlightGuard> {
+        self.count
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                (count < max).then_some(count + 1)
+            })
+            .ok()
+            .map(|_| InFlightGuard(self.clone()))
+    }
+
+    pub fn current(&self) -> u32 {
+        self.count.load(Ordering::Acquire)
+    }
+}
+
+/// One request counted by [`InFlight::enter`]
+#[derive(Debug)]
+pub struct InFlightGuard(Arc<InFlight>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.count.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_window() {
+        let limiter = RateLimiter::new();
+        let limit = Limit::per_minute(2);
+        let start = Instant::now();
+
+        assert!(limiter.check_at("a", limit, start));
+        assert!(limiter.check_at("a", limit, start));
+        assert!(!limiter.check_at("a", limit, start));
+        assert!(limiter.check_at("b", limit, start));
+        assert!(limiter.check_at("a", limit, start + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_in_flight_sheds_past_max() {
+        let in_flight = Arc::new(InFlight::new());
+        let first = in_flight.enter(2).unwrap();
+        let _second = in_flight.enter(2).unwrap();
+        assert!(in_flight.enter(2).is_none());
+        drop(first);
+        assert_eq!(in_flight.current(), 1);
+        assert!(in_flight.enter(2).is_some());
+    }
+}
@@ -0,0 +1,329 @@
+//! Redirects — path → target rules managed at `/admin/redirects`
+//!
+//! When a page moves, add a rule instead of a route: requests for
+//! `from_path` are answered `301` (moved for good) or `302` (for now) to the
+//! target, before routing, by [`crate::middleware::redirects`]. Rules match
+//! the whole path exactly, after URL normalization and with any tenant
+//! prefix taken off, so one rule covers every tenant. The request's query
+//! string is carried over unless the target has its own.
+//!
+//! A path has at most one rule; saving another for it replaces the first.
+//! [`validate`] keeps rules from pointing off-site except by absolute
+//! `http(s)` URL, from shadowing the admin pages, or from looping.
+
+use serde::Serialize;
+use std::sync::RwLock;
+
+/// Longest path or target accepted, in characters
+pub const MAX_RULE_CHARS: usize = 500;
+
+/// Paths no rule may redirect: the admin pages (so a bad rule can always be
+/// removed), assets and health checks
+const RESERVED: &[&str] = &["/admin", "/static", "/healthz"];
+
+/// One redirect rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Redirect {
+    pub id: u32,
+    pub from_path: String,
+    pub target: String,
+    /// `301` or `302`
+    pub status: u16,
+    /// `YYYY-MM-DD HH:MM:SS`, UTC
+    pub created_at: String,
+}
+
+impl Redirect {
+    pub fn permanent(&self) -> bool {
+        self.status == 301
+    }
+
+    /// Where a request with `query` goes
+    pub fn location(&self, query: Option<&str>) -> String {
+        match query {
+            Some(query) if !query.is_empty() && !self.target.contains('?') => {
+                format!("{}?{query}", self.target)
+            }
+            _ => self.target.clone(),
+        }
+    }
+}
+
+/// A rule as submitted, once [`validate`]d
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewRedirect {
+    pub from_path: String,
+    pub target: String,
+    pub status: u16,
+}
+
+/// Check a submitted rule; the message says what to fix
+pub fn validate(from_path: &str, target: &str, status: u16) -> Result<NewRedirect, String> {
+    let (from_path, target) = (from_path.trim(), target.trim());
+    if !from_path.starts_with('/') || from_path.starts_with("//") {
+        return Err("The path must start with a single /".into());
+    }
+    if from_path.contains(['?', '#']) || from_path.chars().any(char::is_whitespace) {
+        return Err("The path can't have a query, fragment or spaces".into());
+    }
+    if from_path == "/" {
+        return Err("The home page can't be redirected".into());
+    }
+    let reserved = RESERVED
+        .iter()
+        .any(|prefix| from_path == *prefix || from_path.starts_with(&format!("{prefix}/")));
+    if reserved {
+        return Err(format!("{from_path} can't be redirected"));
+    }
+    let local = target.starts_with('/') && !target.starts_with("//");
+    let remote = ["http://", "https://"].iter().any(|scheme| {
+        target
+            .get(..scheme.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(scheme))
+            && target.len() > scheme.len()
+    });
+    if !local && !remote {
+        return Err("The target must be a path starting with / or an http(s) URL".into());
+    }
+    if target.chars().any(char::is_whitespace) {
+        return Err("The target can't have spaces".into());
+    }
+    if [from_path, target]
+        .iter()
+        .any(|part| part.chars().count() > MAX_RULE_CHARS)
+    {
+        return Err(format!(
+            "Paths and targets are at most {MAX_RULE_CHARS} characters"
+        ));
+    }
+    let target_path = target.split(['?', '#']).next().unwrap_or_default();
+    if target_path == from_path {
+        return Err("A path can't redirect to itself".into());
+    }
+    if !matches!(status, 301 | 302) {
+        return Err("The status must be 301 or 302".into());
+    }
+    Ok(NewRedirect {
+        from_path: from_path.to_string(),
+        target: target.to_string(),
+        status,
+    })
+}
+
+/// Redirect service trait — defines storage for redirect rules
+pub trait RedirectService: Send + Sync {
+    /// Every rule, by path
+    fn list(&self) -> Vec<Redirect>;
+    /// Add a rule, replacing any other for the same path
+    fn save(&self, rule: NewRedirect) -> Redirect;
+    /// `false` if there was no such rule
+    fn delete(&self, id: u32) -> bool;
+
+    /// The rule for exactly `path`, if any
+    fn find(&self, path: &str) -> Option<Redirect> {
+        self.list().into_iter().find(|rule| rule.from_path == path)
+    }
+}
+
+fn now() -> String {
+    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// ============================================================================
+// In-memory implementation
+// ============================================================================
+
+/// In-memory rule storage (good for prototyping, tests)
+pub struct InMemoryRedirectService {
+    rules: RwLock<Vec<Redirect>>,
+    next_id: RwLock<u32>,
+}
+
+impl InMemoryRedirectService {
+    pub fn new() -> Self {
+        Self {
+            rules: RwLock::new(Vec::new()),
+            next_id: RwLock::new(1),
+        }
+    }
+}
+
+impl Default for InMemoryRedirectService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RedirectService for InMemoryRedirectService {
+    fn list(&self) -> Vec<Redirect> {
+        let mut rules = self.rules.read().unwrap().clone();
+        rules.sort_by(|a, b| a.from_path.cmp(&b.from_path));
+        rules
+    }
+
+    fn save(&self, rule: NewRedirect) -> Redirect {
+        let mut rules = self.rules.write().unwrap();
+        rules.retain(|r| r.from_path != rule.from_path);
+        let mut next_id = self.next_id.write().unwrap();
+        let saved = Redirect {
+            id: *next_id,
+            from_path: rule.from_path,
+            target: rule.target,
+            status: rule.status,
+            created_at: now(),
+        };
+        *next_id += 1;
+        rules.push(saved.clone());
+        saved
+    }
+
+    fn delete(&self, id: u32) -> bool {
+        let mut rules = self.rules.write().unwrap();
+        let before = rules.len();
+        rules.retain(|r| r.id != id);
+        rules.len() < before
+    }
+}
+
+// ============================================================================
+// SQLx Implementation — SQLite-backed rule storage
+// ============================================================================
+
+use crate::db::Db;
+
+pub struct SqliteRedirectService {
+    db: Db,
+}
+
+impl SqliteRedirectService {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+}
+
+/// Row type returned by SQLx queries
+#[derive(sqlx::FromRow)]
+struct RedirectRow {
+    id: i64,
+    from_path: String,
+    target: String,
+    status: i64,
+    created_at: String,
+}
+
+impl From<RedirectRow> for Redirect {
+    fn from(row: RedirectRow) -> Self {
+        Self {
+            id: row.id as u32,
+            from_path: row.from_path,
+            target: row.target,
+            status: row.status as u16,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// What the cached reads read, and so what every write invalidates
+const TABLES: &[&str] = &["redirects"];
+
+impl RedirectService for SqliteRedirectService {
+    // Every request looks here, so the whole (small) table is cached as one
+    // read and `find` searches that
+    fn list(&self) -> Vec<Redirect> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let load = || async {
+                    let rows = sqlx::query_as::<_, RedirectRow>(
+                        "SELECT id, from_path, target, status, created_at FROM redirects \
+                         ORDER BY from_path",
+                    )
+                    .fetch_all(self.db.reader())
+                    .await?;
+                    Ok(rows.into_iter().map(Redirect::from).collect())
+                };
+                self.db
+                    .cache()
+                    .get_or_load(TABLES, "redirects.list", (), load)
+                    .await
+                    .unwrap_or_default()
+            })
+        })
+    }
+
+    fn save(&self, rule: NewRedirect) -> Redirect {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let row = sqlx::query_as::<_, RedirectRow>(
+                    "INSERT INTO redirects (from_path, target, status, created_at) \
+                     VALUES (?, ?, ?, ?) \
+                     ON CONFLICT (from_path) DO UPDATE SET target = excluded.target, \
+                     status = excluded.status, created_at = excluded.created_at \
+                     RETURNING id, from_path, target, status, created_at",
+                )
+                .bind(&rule.from_path)
+                .bind(&rule.target)
+                .bind(rule.status as i64)
+                .bind(now())
+                .fetch_one(self.db.writer())
+                .await
+                .expect("Failed to save redirect");
+                self.db.cache().invalidate(TABLES);
+                row.into()
+            })
+        })
+    }
+
+    fn delete(&self, id: u32) -> bool {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let result = sqlx::query("DELETE FROM redirects WHERE id = ?")
+                    .bind(id as i64)
+                    .execute(self.db.writer())
+                    .await
+                    .expect("Failed to delete redirect");
+                self.db.cache().invalidate(TABLES);
+                result.rows_affected() > 0
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let rule = validate(" /old-page ", "/new-page", 301).unwrap();
+        assert_eq!(rule.from_path, "/old-page");
+        assert!(validate("/docs", "https://docs.example.com/", 302).is_ok());
+
+        assert!(validate("old-page", "/new-page", 301).is_err());
+        assert!(validate("/old?x=1", "/new-page", 301).is_err());
+        assert!(validate("/", "/new-page", 301).is_err());
+        assert!(validate("/admin/posts", "/", 301).is_err());
+        assert!(validate("/administrators", "/team", 301).is_ok());
+        assert!(validate("/old-page", "//evil.example", 301).is_err());
+        assert!(validate("/old-page", "javascript:alert(1)", 301).is_err());
+        assert!(validate("/old-page", "/old-page?x=1", 301).is_err());
+        assert!(validate("/old-page", "/new-page", 307).is_err());
+    }
+
+    #[test]
+    fn test_save_replaces_and_location_keeps_query() {
+        let service = InMemoryRedirectService::new();
+        service.save(validate("/old", "/new", 301).unwrap());
+        let replaced = service.save(validate("/old", "/newer", 302).unwrap());
+
+        assert_eq!(service.list().len(), 1);
+        let rule = service.find("/old").unwrap();
+        assert_eq!(rule, replaced);
+        assert!(!rule.permanent());
+        assert_eq!(rule.location(Some("page=2")), "/newer?page=2");
+        assert!(service.find("/old/").is_none());
+
+        assert!(service.delete(rule.id));
+        assert!(!service.delete(rule.id));
+        assert!(service.find("/old").is_none());
+    }
+}
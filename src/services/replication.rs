@@ -0,0 +1,197 @@
+//! Replication — cooperating with a continuous replicator such as Litestream
+//!
+//! Litestream copies the WAL to a replica as it grows, so it wants to decide
+//! when the WAL is folded back into the database. With `[replication]
+//! enabled` the app turns SQLite's automatic checkpoints off
+//! (`database.wal_autocheckpoint = 0`) and runs `PASSIVE` checkpoints itself,
+//! on a timer and at shutdown, each after the `pre_checkpoint_command` hook.
+//! `PASSIVE` never blocks or truncates the WAL under the replicator's feet.
+//!
+//! On startup, a missing database file can be restored from the replica with
+//! `restore_command` before the app connects; `post_restore_command` runs
+//! after a restore that brought a database back.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::config::{DatabaseConfig, ReplicationConfig};
+use crate::db::{self, Db, DbHealth};
+
+/// Runs the `[replication]` hooks and checkpoints, see the module docs
+pub struct Replication {
+    config: ReplicationConfig,
+    // None for in-memory databases, which have nothing to replicate
+    db_path: Option<PathBuf>,
+}
+
+impl Replication {
+    pub fn new(config: &ReplicationConfig, database: &DatabaseConfig) -> Self {
+        Self {
+            config: config.clone(),
+            db_path: db::file_path(database),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled && self.db_path.is_some()
+    }
+
+    /// When the database file is missing, run `restore_command`, then
+    /// `post_restore_command` if a database came back. `Ok(true)` when one
+    /// did; a failing command is an error, so the app doesn't start empty
+    /// and replicate that over the replica.
+    pub async fn restore_if_missing(&self) -> Result<bool, String> {
+        let Some(path) = self.db_path.as_deref().filter(|_| self.is_enabled()) else {
+            return Ok(false);
+        };
+        if self.config.restore_command.is_empty() || path.exists() {
+            return Ok(false);
+        }
+        tracing::info!(path = %path.display(), "Database missing, restoring from the replica");
+        run_hook("restore", &self.config.restore_command, path).await?;
+        if !path.exists() {
+            tracing::info!("No replica to restore, starting with an empty database");
+            return Ok(false);
+        }
+        tracing::info!(path = %path.display(), "Database restored");
+        run_hook("post-restore", &self.config.post_restore_command, path).await?;
+        Ok(true)
+    }
+
+    /// `pre_checkpoint_command`, then a `PASSIVE` checkpoint. A failing
+    /// hook skips the checkpoint; the WAL just grows until the next one.
+    pub async fn checkpoint(&self, db: &Db) -> Result<(), String> {
+        let Some(path) = self.db_path.as_deref().filter(|_| self.is_enabled()) else {
+            return Ok(());
+        };
+        run_hook("pre-checkpoint", &self.config.pre_checkpoint_command, path).await?;
+        // (busy, WAL pages, pages checkpointed)
+        let (busy, log, checkpointed): (i64, i64, i64) =
+            sqlx::query_as("PRAGMA wal_checkpoint(PASSIVE)")
+                .fetch_one(db.writer())
+                .await
+                .map_err(|e| e.to_string())?;
+        tracing::debug!(busy, log, checkpointed, "WAL checkpoint");
+        Ok(())
+    }
+}
+
+/// Run `command` through `sh -c` with `{db}` replaced by `path`. An empty
+/// command does nothing.
+async fn run_hook(name: &str, command: &str, path: &Path) -> Result<(), String> {
+    if command.is_empty() {
+        return Ok(());
+    }
+    let command = command.replace("{db}", &path.to_string_lossy());
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .output()
+        .await
+        .map_err(|e| format!("{name} hook: {e}"))?;
+    if output.status.success() {
+        tracing::debug!(hook = name, command = %command, "Replication hook ran");
+        Ok(())
+    } else {
+        let error = format!(
+            "{name} hook `{command}` failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        tracing::error!("{error}");
+        Err(error)
+    }
+}
+
+/// Checkpoint every `[replication] checkpoint_interval_secs`, skipping
+/// while the database is down
+pub fn schedule(replication: Arc<Replication>, db: Db, health: Arc<DbHealth>, every: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if health.is_up() {
+                let _ = replication.checkpoint(&db).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(name: &str) -> (PathBuf, DatabaseConfig) {
+        let dir = std::env::temp_dir().join(format!("app-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let database = DatabaseConfig {
+            url: format!("sqlite://{}/app.db?mode=rwc", dir.display()),
+            wal_autocheckpoint: 0,
+            ..DatabaseConfig::default()
+        };
+        (dir, database)
+    }
+
+    #[tokio::test]
+    async fn test_restores_a_missing_database() {
+        let (dir, database) = setup("restore");
+        std::fs::write(dir.join("replica.db"), "replica").unwrap();
+        let config = ReplicationConfig {
+            enabled: true,
+            restore_command: format!("cp {}/replica.db {{db}}", dir.display()),
+            post_restore_command: format!("touch {}/restored", dir.display()),
+            ..ReplicationConfig::default()
+        };
+        let replication = Replication::new(&config, &database);
+
+        assert_eq!(replication.restore_if_missing().await, Ok(true));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("app.db")).unwrap(),
+            "replica"
+        );
+        assert!(dir.join("restored").exists());
+        // There now, so nothing to do
+        assert_eq!(replication.restore_if_missing().await, Ok(false));
+
+        let failing = ReplicationConfig {
+            restore_command: "exit 3".into(),
+            ..config
+        };
+        std::fs::remove_file(dir.join("app.db")).unwrap();
+        assert!(Replication::new(&failing, &database)
+            .restore_if_missing()
+            .await
+            .is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_failing_hook_skips_the_checkpoint() {
+        let (dir, database) = setup("checkpoint");
+        let db = db::init_pool(&database).await.unwrap();
+        let mut config = ReplicationConfig {
+            enabled: true,
+            pre_checkpoint_command: "false".into(),
+            ..ReplicationConfig::default()
+        };
+        assert!(Replication::new(&config, &database)
+            .checkpoint(&db)
+            .await
+            .is_err());
+
+        config.pre_checkpoint_command = format!("touch {}/hooked", dir.display());
+        assert_eq!(
+            Replication::new(&config, &database).checkpoint(&db).await,
+            Ok(())
+        );
+        assert!(dir.join("hooked").exists());
+
+        db.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,372 @@
+//! Request Log — requests written to the database for `/admin/requests`
+//!
+//! With `[request_log] enabled`, [`request_logger`](crate::middleware::request_logger)
+//! hands each request's method, path, status, duration and request ID to
+//! [`RequestLog::record`]. Nothing identifying is kept: no IP, no query
+//! string, no headers. Records queue in a bounded channel and a background
+//! task writes them in one transaction per batch, at most every
+//! `flush_interval_ms`, so logging never waits on the database. When the
+//! queue is full (the database is down or far behind) records are dropped
+//! and counted, not buffered without bound. Records still queued at shutdown
+//! are lost.
+//!
+//! Records older than `retention_days` are deleted hourly.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::config::RequestLogConfig;
+use crate::db::Db;
+
+/// Records waiting to be written before new ones are dropped
+pub const QUEUE_CAPACITY: usize = 10_000;
+/// Most records written in one transaction
+const MAX_BATCH: usize = 500;
+/// How often old records are deleted
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Most rows a query returns
+pub const MAX_ROWS: usize = 100;
+
+/// What the cached reads read, and so what every write invalidates
+const TABLES: &[&str] = &["request_log"];
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// One request, as the middleware reports it
+#[derive(Debug, Clone)]
+pub struct RequestRecord {
+    pub method: String,
+    /// Without the query string
+    pub path: String,
+    pub status: u16,
+    pub duration: Duration,
+    pub request_id: String,
+}
+
+/// One request, as stored
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct LoggedRequest {
+    /// `YYYY-MM-DD HH:MM:SS`, UTC
+    pub created_at: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub duration_us: u64,
+    pub request_id: String,
+}
+
+/// Which requests to show; empty fields match everything
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default)]
+pub struct RequestFilter {
+    /// Part of the path
+    pub path: String,
+    /// `2` for 2xx … `5` for 5xx; `0` for any
+    pub status: u16,
+    /// Only the last this many minutes; `0` for everything kept
+    pub minutes: u64,
+}
+
+/// Durations of the matching requests, in microseconds (nearest rank)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Latency {
+    pub count: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+}
+
+/// The newest matching requests and their latency
+#[derive(Debug, Clone, Default)]
+pub struct RequestPage {
+    /// Newest first, at most [`MAX_ROWS`]
+    pub rows: Vec<LoggedRequest>,
+    pub latency: Latency,
+}
+
+/// Queue into the `request_log` table; disabled until started
+#[derive(Default)]
+pub struct RequestLog {
+    inner: Option<Inner>,
+    dropped: AtomicU64,
+}
+
+struct Inner {
+    tx: mpsc::Sender<(String, RequestRecord)>,
+    exclude: Vec<String>,
+    db: Db,
+}
+
+impl RequestLog {
+    /// Records nothing; [`RequestLog::query`] finds nothing
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Spawn the writer and start taking records (needs a Tokio runtime)
+    pub fn start(db: Db, config: &RequestLogConfig) -> Self {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(write_batches(
+            db.clone(),
+            rx,
+            Duration::from_millis(config.flush_interval_ms),
+            chrono::Duration::days(config.retention_days as i64),
+        ));
+        Self {
+            inner: Some(Inner {
+                tx,
+                exclude: config.exclude.clone(),
+                db,
+            }),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Queue a record; never waits
+    pub fn record(&self, record: RequestRecord) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        if inner
+            .exclude
+            .iter()
+            .any(|prefix| record.path.starts_with(prefix.as_str()))
+        {
+            return;
+        }
+        let now = chrono::Utc::now().format(TIME_FORMAT).to_string();
+        if inner.tx.try_send((now, record)).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records dropped because the queue was full, since startup
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// The newest requests matching `filter`, and percentiles over all of them
+    pub fn query(&self, filter: &RequestFilter) -> RequestPage {
+        let Some(inner) = &self.inner else {
+            return RequestPage::default();
+        };
+        let db = &inner.db;
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let since = match filter.minutes {
+                    0 => String::new(),
+                    minutes => (chrono::Utc::now() - chrono::Duration::minutes(minutes as i64))
+                        .format(TIME_FORMAT)
+                        .to_string(),
+                };
+                let pattern = format!("%{}%", escape_like(&filter.path));
+                let load = || async {
+                    let rows = sqlx::query_as::<_, LoggedRequest>(&format!(
+                        "SELECT created_at, method, path, status, duration_us, request_id \
+                         FROM request_log WHERE {WHERE} ORDER BY id DESC LIMIT ?"
+                    ))
+                    .bind(&since)
+                    .bind(&pattern)
+                    .bind(filter.status)
+                    .bind(filter.status)
+                    .bind(MAX_ROWS as i64)
+                    .fetch_all(db.reader())
+                    .await?;
+                    let count: i64 = sqlx::query_scalar(&format!(
+                        "SELECT COUNT(*) FROM request_log WHERE {WHERE}"
+                    ))
+                    .bind(&since)
+                    .bind(&pattern)
+                    .bind(filter.status)
+                    .bind(filter.status)
+                    .fetch_one(db.reader())
+                    .await?;
+                    let count = count as u64;
+                    let mut latency = Latency {
+                        count,
+                        ..Latency::default()
+                    };
+                    for (percent, value) in [
+                        (50, &mut latency.p50),
+                        (90, &mut latency.p90),
+                        (99, &mut latency.p99),
+                    ] {
+                        let Some(rank) = rank(percent, count) else {
+                            break;
+                        };
+                        let duration: i64 = sqlx::query_scalar(&format!(
+                            "SELECT duration_us FROM request_log WHERE {WHERE} \
+                             ORDER BY duration_us LIMIT 1 OFFSET ?"
+                        ))
+                        .bind(&since)
+                        .bind(&pattern)
+                        .bind(filter.status)
+                        .bind(filter.status)
+                        .bind(rank as i64)
+                        .fetch_one(db.reader())
+                        .await?;
+                        *value = duration as u64;
+                    }
+                    Ok(RequestPage { rows, latency })
+                };
+                db.cache()
+                    .get_or_load(TABLES, "request_log.query", filter, load)
+                    .await
+                    .unwrap_or_default()
+            })
+        })
+    }
+}
+
+/// Conditions every query shares: since, path pattern, status class (twice)
+const WHERE: &str = "created_at >= ? AND path LIKE ? ESCAPE '\\' \
+                     AND (? = 0 OR status / 100 = ?)";
+
+/// `text` matched literally inside a `LIKE` pattern
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Zero-based index of the `percent`th percentile among `count` sorted
+/// values, by nearest rank
+fn rank(percent: u64, count: u64) -> Option<u64> {
+    (count > 0).then(|| (percent * count).div_ceil(100).max(1) - 1)
+}
+
+/// The writer task: a batch per `flush_interval`, old records pruned hourly
+async fn write_batches(
+    db: Db,
+    mut rx: mpsc::Receiver<(String, RequestRecord)>,
+    flush_interval: Duration,
+    retention: chrono::Duration,
+) {
+    let mut batch = Vec::with_capacity(MAX_BATCH);
+    let mut pruned: Option<Instant> = None;
+    while rx.recv_many(&mut batch, MAX_BATCH).await > 0 {
+        if let Err(e) = write(&db, &batch).await {
+            tracing::warn!(error = %e, records = batch.len(), "Request log batch not written");
+        }
+        let full = batch.len() == MAX_BATCH;
+        batch.clear();
+
+        if pruned.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+            pruned = Some(Instant::now());
+            let cutoff = (chrono::Utc::now() - retention)
+                .format(TIME_FORMAT)
+                .to_string();
+            if let Err(e) = sqlx::query("DELETE FROM request_log WHERE created_at < ?")
+                .bind(cutoff)
+                .execute(db.writer())
+                .await
+            {
+                tracing::warn!(error = %e, "Old request log records not deleted");
+            }
+        }
+        // Let the next batch gather, unless this one left a backlog
+        if !full {
+            tokio::time::sleep(flush_interval).await;
+        }
+    }
+}
+
+async fn write(db: &Db, batch: &[(String, RequestRecord)]) -> Result<(), sqlx::Error> {
+    let mut tx = db.writer().begin().await?;
+    for (created_at, record) in batch {
+        sqlx::query(
+            "INSERT INTO request_log \
+             (created_at, method, path, status, duration_us, request_id) \
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(created_at)
+        .bind(&record.method)
+        .bind(&record.path)
+        .bind(record.status)
+        .bind(record.duration.as_micros() as i64)
+        .bind(&record.request_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    db.cache().invalidate(TABLES);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank() {
+        assert_eq!(rank(50, 0), None);
+        assert_eq!(rank(50, 1), Some(0));
+        assert_eq!(rank(99, 1), Some(0));
+        assert_eq!(rank(50, 10), Some(4));
+        assert_eq!(rank(90, 10), Some(8));
+        assert_eq!(rank(99, 200), Some(197));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_records_are_batched_and_filtered() {
+        let db = crate::db::init_pool(&crate::config::DatabaseConfig {
+            url: "sqlite::memory:".into(),
+            ..crate::config::DatabaseConfig::default()
+        })
+        .await
+        .unwrap();
+        let log = RequestLog::start(
+            db,
+            &RequestLogConfig {
+                enabled: true,
+                flush_interval_ms: 10,
+                ..RequestLogConfig::default()
+            },
+        );
+        let record = |path: &str, status: u16, ms: u64| RequestRecord {
+            method: "GET".into(),
+            path: path.into(),
+            status,
+            duration: Duration::from_millis(ms),
+            request_id: "req-1".into(),
+        };
+        for ms in 1..=10 {
+            log.record(record("/blog/hello_world", 200, ms));
+        }
+        log.record(record("/missing", 404, 50));
+        log.record(record("/static/css/app.css", 200, 1));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let all = log.query(&RequestFilter::default());
+        assert_eq!(all.latency.count, 11);
+        assert_eq!(all.rows[0].path, "/missing");
+
+        let blog = log.query(&RequestFilter {
+            path: "hello_".into(),
+            status: 2,
+            minutes: 60,
+        });
+        assert_eq!(blog.latency.count, 10);
+        assert_eq!(blog.latency.p50, 5_000);
+        assert_eq!(blog.latency.p90, 9_000);
+        assert_eq!(blog.latency.p99, 10_000);
+
+        let errors = log.query(&RequestFilter {
+            status: 4,
+            ..RequestFilter::default()
+        });
+        assert_eq!(errors.rows.len(), 1);
+        assert_eq!(log.dropped(), 0);
+    }
+}
@@ -0,0 +1,147 @@
+//! HTML Sanitization — allowlist cleaning for user-generated content
+//!
+//! Templates auto-escape everything by default, which is right for plain
+//! text. When users may submit *formatting*, run the input through [`clean`]
+//! with the [`Profile`] for that use-case and store or render the resulting
+//! [`SanitizedHtml`] with `|safe` — it's the only type that should ever be
+//! marked safe in a template. Markdown sources go through [`markdown`], which
+//! renders first and cleans the result.
+
+use ammonia::Builder;
+use pulldown_cmark::{html, Options, Parser};
+use serde::{Serialize, Serializer};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// What a piece of user content is allowed to contain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Inline emphasis only — names, titles, one-line labels
+    Inline,
+    /// Short discussion text: paragraphs, lists, quotes, code, links
+    Comment,
+    /// Long-form content: everything in `Comment` plus headings, images
+    /// and tables
+    RichText,
+}
+
+const INLINE_TAGS: &[&str] = &["b", "strong", "i", "em", "code", "br"];
+const COMMENT_TAGS: &[&str] = &["p", "a", "ul", "ol", "li", "blockquote", "pre"];
+const RICH_TEXT_TAGS: &[&str] = &[
+    "h2", "h3", "h4", "hr", "img", "del", "sub", "sup", "table", "thead", "tbody", "tr", "th", "td",
+];
+
+/// Only web and mail links — no `javascript:`, `data:` or custom schemes
+const URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+impl Profile {
+    fn builder(self) -> &'static Builder<'static> {
+        static INLINE: OnceLock<Builder<'static>> = OnceLock::new();
+        static COMMENT: OnceLock<Builder<'static>> = OnceLock::new();
+        static RICH_TEXT: OnceLock<Builder<'static>> = OnceLock::new();
+
+        match self {
+            Profile::Inline => INLINE.get_or_init(|| builder(&[INLINE_TAGS])),
+            Profile::Comment => COMMENT.get_or_init(|| builder(&[INLINE_TAGS, COMMENT_TAGS])),
+            Profile::RichText => {
+                RICH_TEXT.get_or_init(|| builder(&[INLINE_TAGS, COMMENT_TAGS, RICH_TEXT_TAGS]))
+            }
+        }
+    }
+}
+
+fn builder(tag_sets: &[&[&'static str]]) -> Builder<'static> {
+    let tags: HashSet<&str> = tag_sets
+        .iter()
+        .flat_map(|set| set.iter().copied())
+        .collect();
+    let mut builder = Builder::empty();
+    builder
+        .add_tag_attributes("a", ["href"])
+        .add_tag_attributes("img", ["src", "alt"])
+        .tags(tags)
+        .url_schemes(URL_SCHEMES.iter().copied().collect())
+        .link_rel(Some("nofollow noopener noreferrer"))
+        // Drop these with their content rather than leaving the text behind
+        .clean_content_tags(["script", "style"].into_iter().collect());
+    builder
+}
+
+/// HTML that has been through [`clean`]. Renders verbatim (use `|safe`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SanitizedHtml(String);
+
+impl SanitizedHtml {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.trim().is_empty()
+    }
+}
+
+impl fmt::Display for SanitizedHtml {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for SanitizedHtml {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// Strip everything `profile` doesn't allow from `input`
+pub fn clean(profile: Profile, input: &str) -> SanitizedHtml {
+    SanitizedHtml(profile.builder().clean(input).to_string())
+}
+
+/// Render Markdown to HTML, then [`clean`] it with `profile`. Raw HTML in
+/// the source is allowed through the renderer and filtered like any other.
+pub fn markdown(profile: Profile, input: &str) -> SanitizedHtml {
+    let parser = Parser::new_ext(
+        input,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH,
+    );
+    let mut rendered = String::with_capacity(input.len() * 3 / 2);
+    html::push_html(&mut rendered, parser);
+    clean(profile, &rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        let input = r#"<em>Hi</em><script>alert(1)</script><a href="javascript:alert(1)" onclick="x()">link</a><h2>Title</h2>"#;
+
+        assert_eq!(
+            clean(Profile::Inline, input).as_str(),
+            "<em>Hi</em>linkTitle"
+        );
+        assert_eq!(
+            clean(Profile::Comment, input).as_str(),
+            r#"<em>Hi</em><a rel="nofollow noopener noreferrer">link</a>Title"#
+        );
+        assert_eq!(
+            clean(Profile::RichText, input).as_str(),
+            r#"<em>Hi</em><a rel="nofollow noopener noreferrer">link</a><h2>Title</h2>"#
+        );
+    }
+
+    #[test]
+    fn test_markdown_is_cleaned() {
+        let html = markdown(
+            Profile::RichText,
+            "## Hi\n\n[x](javascript:alert(1)) <script>alert(1)</script>",
+        );
+        assert_eq!(
+            html.as_str(),
+            "<h2>Hi</h2>\n<p><a rel=\"nofollow noopener noreferrer\">x</a> </p>\n"
+        );
+    }
+}
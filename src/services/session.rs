@@ -2,21 +2,62 @@
 //!
 //! Provides server-side session management with:
 //! - Random 256-bit session IDs
-//! - HttpOnly, Secure, SameSite=Strict cookies
+//! - HttpOnly, Secure, SameSite=Strict cookies (`[sessions] secure`,
+//!   `same_site`)
 //! - In-memory session store (swap for Redis/DB in production)
 //! - Automatic cleanup of expired sessions
+//! - A cap on stored sessions, evicting the least recently used
+//! - Typed session data through [`SessionKey`]
+//!
+//! Every cookieless request gets a session, so a client that never sends
+//! the cookie back (a bot, a health check) would add one per request. The
+//! in-memory store holds at most `[sessions] max_sessions` and evicts the
+//! least recently used past that. With `[sessions] lazy`, a new visitor's
+//! session isn't stored at all until it matters: its ID is signed
+//! ([`LAZY_SESSION`]), so when the cookie comes back the middleware can store
+//! it without having kept anything; storing session data stores it too.
 
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use rand::RngCore;
-use std::collections::HashMap;
-use std::sync::RwLock;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
 use std::time::{Duration, Instant};
 
 /// Session cookie name — intentionally generic to avoid fingerprinting
 pub const SESSION_COOKIE: &str = "__Host-sid";
+/// Session cookie name with `[sessions] secure = false`, which the
+/// `__Host-` prefix doesn't allow
+pub const INSECURE_SESSION_COOKIE: &str = "sid";
 
 /// Session lifetime
-const SESSION_TTL: Duration = Duration::from_secs(3600); // 1 hour
+pub const SESSION_TTL: Duration = Duration::from_secs(3600); // 1 hour
+
+/// [`TokenSigner`](crate::services::TokenSigner) purpose of lazy session IDs
+pub const LAZY_SESSION: &str = "lazy-session";
+
+/// Least time between warnings that sessions are being evicted
+const EVICTION_WARN_EVERY: Duration = Duration::from_secs(60);
+
+/// A new random session ID
+pub fn new_id() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// ID of the current request's session, inserted into request extensions by
+/// the session middleware
+#[derive(Debug, Clone)]
+pub struct SessionId(pub String);
+
+/// Response extension naming the session a handler moved this one to with
+/// [`SessionStore::rotate`]; the session middleware sends its cookie and
+/// CSRF token instead
+#[derive(Debug, Clone)]
+pub struct RotatedSession(pub String);
 
 /// Session data stored server-side
 #[derive(Debug, Clone)]
@@ -29,6 +70,17 @@ pub struct Session {
 }
 
 impl Session {
+    /// A fresh, empty session; not stored
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            csrf_token: String::new(),
+            created_at: Instant::now(),
+            last_access: Instant::now(),
+            data: HashMap::new(),
+        }
+    }
+
     pub fn is_expired(&self) -> bool {
         self.last_access.elapsed() > SESSION_TTL
     }
@@ -36,30 +88,169 @@ impl Session {
 
 /// Session store trait — allows swapping in-memory for Redis, DB, etc.
 pub trait SessionStore: Send + Sync {
-    fn create(&self) -> Session;
+    /// Store a fresh session under `id`, replacing any other
+    fn create_with_id(&self, id: &str) -> Session;
     fn get(&self, id: &str) -> Option<Session>;
     fn touch(&self, id: &str);
     fn update_csrf(&self, id: &str, token: &str);
+    /// Stores the session first if it isn't (a lazy one), see the module docs
+    fn set_data(&self, id: &str, key: &str, value: String);
+    fn take_data(&self, id: &str, key: &str) -> Option<String>;
     fn destroy(&self, id: &str);
     fn cleanup_expired(&self);
+    /// Sessions that haven't expired
+    fn count(&self) -> usize;
+
+    /// Store a fresh session under a new random ID
+    fn create(&self) -> Session {
+        self.create_with_id(&new_id())
+    }
+
+    /// Move session `id`'s data to a new random ID and destroy it, so an ID
+    /// known before a change of privileges (signing in or out) is worthless
+    /// after it
+    fn rotate(&self, id: &str) -> Session {
+        let data = self.get(id).map(|session| session.data).unwrap_or_default();
+        self.destroy(id);
+        let session = self.create();
+        for (key, value) in data {
+            self.set_data(&session.id, &key, value);
+        }
+        self.get(&session.id).unwrap_or(session)
+    }
+}
+
+/// A session data key and the type stored under it, kept as JSON:
+///
+/// ```ignore
+/// const CART: SessionKey<Cart> = SessionKey::new("_cart");
+/// let cart = CART.get(store, &sid).unwrap_or_default();
+/// CART.set(store, &sid, &cart);
+/// ```
+///
+/// A value that no longer parses (its type changed since it was stored)
+/// reads as missing.
+pub struct SessionKey<T> {
+    key: &'static str,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> SessionKey<T> {
+    pub const fn new(key: &'static str) -> Self {
+        Self {
+            key,
+            _type: PhantomData,
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        self.key
+    }
+
+    /// The stored value, left in place
+    pub fn get(&self, store: &dyn SessionStore, id: &str) -> Option<T> {
+        let session = store.get(id)?;
+        serde_json::from_str(session.data.get(self.key)?).ok()
+    }
+
+    pub fn set(&self, store: &dyn SessionStore, id: &str, value: &T) {
+        if let Ok(json) = serde_json::to_string(value) {
+            store.set_data(id, self.key, json);
+        }
+    }
+
+    /// Remove the stored value, returning it
+    pub fn take(&self, store: &dyn SessionStore, id: &str) -> Option<T> {
+        serde_json::from_str(&store.take_data(id, self.key)?).ok()
+    }
 }
 
+/// Sessions stored by default before the least recently used are evicted
+pub const DEFAULT_MAX_SESSIONS: usize = 100_000;
+
 /// In-memory session store (suitable for single-instance deployments)
 pub struct InMemorySessionStore {
-    sessions: RwLock<HashMap<String, Session>>,
+    sessions: RwLock<Sessions>,
+    max_sessions: usize,
+    evicted: AtomicU64,
+    last_warning: Mutex<Option<Instant>>,
+}
+
+#[derive(Default)]
+struct Sessions {
+    by_id: HashMap<String, Session>,
+    /// `(last_access, id)` of every session, least recently used first
+    by_access: BTreeSet<(Instant, String)>,
+}
+
+impl Sessions {
+    fn remove(&mut self, id: &str) -> Option<Session> {
+        let session = self.by_id.remove(id)?;
+        self.by_access
+            .remove(&(session.last_access, session.id.clone()));
+        Some(session)
+    }
+
+    /// Sessions idle past [`SESSION_TTL`], least recently used first
+    fn expired(&self) -> impl Iterator<Item = &(Instant, String)> {
+        self.by_access
+            .iter()
+            .take_while(|(last_access, _)| last_access.elapsed() > SESSION_TTL)
+    }
 }
 
 impl InMemorySessionStore {
     pub fn new() -> Self {
+        Self::with_max(DEFAULT_MAX_SESSIONS)
+    }
+
+    /// A store holding at most `max_sessions`
+    pub fn with_max(max_sessions: usize) -> Self {
         Self {
-            sessions: RwLock::new(HashMap::new()),
+            sessions: RwLock::new(Sessions::default()),
+            max_sessions: max_sessions.max(1),
+            evicted: AtomicU64::new(0),
+            last_warning: Mutex::new(None),
+        }
+    }
+
+    /// Sessions evicted to stay under the cap, since startup
+    pub fn evicted(&self) -> u64 {
+        self.evicted.load(Ordering::Relaxed)
+    }
+
+    /// Store `session`, evicting the least recently used to make room
+    fn insert(&self, sessions: &mut Sessions, session: Session) {
+        sessions.remove(&session.id);
+        let mut evicted = 0;
+        while sessions.by_id.len() >= self.max_sessions {
+            let Some((_, id)) = sessions.by_access.pop_first() else {
+                break;
+            };
+            sessions.by_id.remove(&id);
+            evicted += 1;
+        }
+        sessions
+            .by_access
+            .insert((session.last_access, session.id.clone()));
+        sessions.by_id.insert(session.id.clone(), session);
+        if evicted > 0 {
+            self.evicted.fetch_add(evicted, Ordering::Relaxed);
+            self.warn_evicting();
         }
     }
 
-    fn generate_id() -> String {
-        let mut bytes = [0u8; 32];
-        rand::thread_rng().fill_bytes(&mut bytes);
-        URL_SAFE_NO_PAD.encode(bytes)
+    fn warn_evicting(&self) {
+        let mut last_warning = self.last_warning.lock().unwrap();
+        if last_warning.is_none_or(|at| at.elapsed() >= EVICTION_WARN_EVERY) {
+            *last_warning = Some(Instant::now());
+            tracing::warn!(
+                max_sessions = self.max_sessions,
+                evicted = self.evicted(),
+                "Session store full, evicting the least recently used sessions; \
+                 raise [sessions] max_sessions or turn on [sessions] lazy"
+            );
+        }
     }
 }
 
@@ -70,46 +261,138 @@ impl Default for InMemorySessionStore {
 }
 
 impl SessionStore for InMemorySessionStore {
-    fn create(&self) -> Session {
-        let session = Session {
-            id: Self::generate_id(),
-            csrf_token: String::new(),
-            created_at: Instant::now(),
-            last_access: Instant::now(),
-            data: HashMap::new(),
-        };
-        self.sessions
-            .write()
-            .unwrap()
-            .insert(session.id.clone(), session.clone());
+    fn create_with_id(&self, id: &str) -> Session {
+        let session = Session::new(id);
+        self.insert(&mut self.sessions.write().unwrap(), session.clone());
         session
     }
 
     fn get(&self, id: &str) -> Option<Session> {
         let sessions = self.sessions.read().unwrap();
-        sessions.get(id).filter(|s| !s.is_expired()).cloned()
+        sessions.by_id.get(id).filter(|s| !s.is_expired()).cloned()
     }
 
     fn touch(&self, id: &str) {
-        if let Some(session) = self.sessions.write().unwrap().get_mut(id) {
+        let mut sessions = self.sessions.write().unwrap();
+        let sessions = &mut *sessions;
+        if let Some(session) = sessions.by_id.get_mut(id) {
+            sessions
+                .by_access
+                .remove(&(session.last_access, session.id.clone()));
             session.last_access = Instant::now();
+            sessions
+                .by_access
+                .insert((session.last_access, session.id.clone()));
         }
     }
 
     fn update_csrf(&self, id: &str, token: &str) {
-        if let Some(session) = self.sessions.write().unwrap().get_mut(id) {
+        if let Some(session) = self.sessions.write().unwrap().by_id.get_mut(id) {
             session.csrf_token = token.to_string();
         }
     }
 
-    fn destroy(&self, id: &str) {
-        self.sessions.write().unwrap().remove(id);
+    fn set_data(&self, id: &str, key: &str, value: String) {
+        let mut sessions = self.sessions.write().unwrap();
+        if !sessions.by_id.contains_key(id) {
+            self.insert(&mut sessions, Session::new(id));
+        }
+        if let Some(session) = sessions.by_id.get_mut(id) {
+            session.data.insert(key.to_string(), value);
+        }
     }
 
-    fn cleanup_expired(&self) {
+    fn take_data(&self, id: &str, key: &str) -> Option<String> {
         self.sessions
             .write()
             .unwrap()
-            .retain(|_, s| !s.is_expired());
+            .by_id
+            .get_mut(id)
+            .and_then(|session| session.data.remove(key))
+    }
+
+    fn destroy(&self, id: &str) {
+        self.sessions.write().unwrap().remove(id);
+    }
+
+    fn cleanup_expired(&self) {
+        let mut sessions = self.sessions.write().unwrap();
+        let expired: Vec<String> = sessions.expired().map(|(_, id)| id.clone()).collect();
+        for id in expired {
+            sessions.remove(&id);
+        }
+    }
+
+    fn count(&self) -> usize {
+        let sessions = self.sessions.read().unwrap();
+        sessions.by_id.len() - sessions.expired().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_key_round_trip() {
+        const COUNTS: SessionKey<Vec<u32>> = SessionKey::new("counts");
+        let store = InMemorySessionStore::new();
+        let session = store.create();
+
+        assert_eq!(COUNTS.get(&store, &session.id), None);
+        COUNTS.set(&store, &session.id, &vec![1, 2]);
+        assert_eq!(COUNTS.get(&store, &session.id), Some(vec![1, 2]));
+        assert_eq!(COUNTS.take(&store, &session.id), Some(vec![1, 2]));
+        assert_eq!(COUNTS.get(&store, &session.id), None);
+
+        store.set_data(&session.id, COUNTS.key(), "not json".to_string());
+        assert_eq!(COUNTS.get(&store, &session.id), None);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let store = InMemorySessionStore::with_max(2);
+        let first = store.create();
+        let second = store.create();
+        // Used since, so `second` is now the least recent
+        std::thread::sleep(Duration::from_millis(1));
+        store.touch(&first.id);
+        let third = store.create();
+
+        assert!(store.get(&first.id).is_some());
+        assert!(store.get(&second.id).is_none());
+        assert!(store.get(&third.id).is_some());
+        assert_eq!(store.count(), 2);
+        assert_eq!(store.evicted(), 1);
+
+        store.destroy(&first.id);
+        store.create();
+        assert_eq!(store.evicted(), 1);
+    }
+
+    #[test]
+    fn test_saving_data_stores_a_lazy_session() {
+        let store = InMemorySessionStore::new();
+        let lazy = Session::new("lazy-id");
+        store.update_csrf(&lazy.id, "token");
+        assert!(store.get(&lazy.id).is_none());
+
+        store.set_data(&lazy.id, "_flash", "[]".to_string());
+        let stored = store.get(&lazy.id).unwrap();
+        assert_eq!(stored.data["_flash"], "[]");
+        assert_eq!(store.count(), 1);
+    }
+
+    #[test]
+    fn test_rotate_moves_data_to_a_new_id() {
+        let store = InMemorySessionStore::new();
+        let old = store.create();
+        store.set_data(&old.id, "_name", "Ada".to_string());
+
+        let new = store.rotate(&old.id);
+        assert_ne!(new.id, old.id);
+        assert!(store.get(&old.id).is_none());
+        assert_eq!(store.get(&new.id).unwrap().data["_name"], "Ada");
+        assert_eq!(store.count(), 1);
     }
 }
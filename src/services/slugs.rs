@@ -0,0 +1,237 @@
+//! Slugs — URL-safe names with collision suffixes and a history of old ones
+//!
+//! [`slugify`] turns a title into `[a-z0-9-]`; [`unique`] adds `-2`, `-3`, …
+//! until the slug is free. When a record's slug changes, the old one is kept
+//! in the history pointing at the record's id, so old links can answer `301`
+//! to wherever it lives now. History is by id, not slug, so a record renamed
+//! twice redirects from both of its old slugs in one hop.
+//!
+//! Current slugs win over history: a record may take a slug another one used
+//! to have, and that slug's redirect is dropped ("claimed"). Each record kind
+//! keeps its own history, scoped the way its slugs are unique (items per
+//! tenant, posts site-wide).
+//!
+//! In-memory stores keep a [`SlugHistory`]; SQLite stores call the functions
+//! below on the `slug_history` table, in the transaction that changes the
+//! slug.
+
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Longest generated slug, in characters
+pub const MAX_SLUG_CHARS: usize = 60;
+
+/// What a slug names; each kind has its own history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SlugKind {
+    Item,
+    Post,
+}
+
+impl SlugKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SlugKind::Item => "item",
+            SlugKind::Post => "post",
+        }
+    }
+}
+
+/// `Hello, World!` → `hello-world`. Empty when the text has no ASCII
+/// letters or digits.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.chars().take(MAX_SLUG_CHARS).collect();
+    slug.trim_end_matches('-').to_string()
+}
+
+/// The slug of the first of `sources` that has one (else `fallback`),
+/// suffixed until `taken` says it's free
+pub fn unique(sources: &[&str], fallback: &str, taken: impl Fn(&str) -> bool) -> String {
+    let base = sources
+        .iter()
+        .map(|source| slugify(source))
+        .find(|slug| !slug.is_empty())
+        .unwrap_or_else(|| fallback.to_string());
+    let mut slug = base.clone();
+    let mut n = 2;
+    while taken(&slug) {
+        slug = format!("{base}-{n}");
+        n += 1;
+    }
+    slug
+}
+
+// ============================================================================
+// In-memory history
+// ============================================================================
+
+/// Old slugs of one kind of record, by scope
+#[derive(Debug, Default)]
+pub struct SlugHistory {
+    // (scope, old slug) → id
+    old: RwLock<HashMap<(String, String), u32>>,
+}
+
+impl SlugHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `slug` is now a record's current slug: it redirects nowhere
+    pub fn claim(&self, scope: &str, slug: &str) {
+        self.old
+            .write()
+            .unwrap()
+            .remove(&(scope.to_string(), slug.to_string()));
+    }
+
+    /// Record `id` moved from `from` to `to`
+    pub fn moved(&self, scope: &str, id: u32, from: &str, to: &str) {
+        self.claim(scope, to);
+        if from != to {
+            self.old
+                .write()
+                .unwrap()
+                .insert((scope.to_string(), from.to_string()), id);
+        }
+    }
+
+    /// The id of the record that used to be at `slug`
+    pub fn resolve(&self, scope: &str, slug: &str) -> Option<u32> {
+        self.old
+            .read()
+            .unwrap()
+            .get(&(scope.to_string(), slug.to_string()))
+            .copied()
+    }
+
+    /// Drop a deleted record's old slugs
+    pub fn forget(&self, scope: &str, id: u32) {
+        self.old
+            .write()
+            .unwrap()
+            .retain(|(s, _), target| s != scope || *target != id);
+    }
+}
+
+// ============================================================================
+// SQLx — the slug_history table
+// ============================================================================
+
+/// [`SlugHistory::claim`] for SQLite stores
+pub async fn claim(
+    conn: &mut SqliteConnection,
+    kind: SlugKind,
+    scope: &str,
+    slug: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM slug_history WHERE kind = ? AND scope = ? AND slug = ?")
+        .bind(kind.as_str())
+        .bind(scope)
+        .bind(slug)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// [`SlugHistory::moved`] for SQLite stores
+pub async fn moved(
+    conn: &mut SqliteConnection,
+    kind: SlugKind,
+    scope: &str,
+    id: u32,
+    from: &str,
+    to: &str,
+) -> Result<(), sqlx::Error> {
+    claim(&mut *conn, kind, scope, to).await?;
+    if from != to {
+        sqlx::query(
+            "INSERT INTO slug_history (kind, scope, slug, target_id, created_at) \
+             VALUES (?, ?, ?, ?, ?) \
+             ON CONFLICT (kind, scope, slug) DO UPDATE SET \
+             target_id = excluded.target_id, created_at = excluded.created_at",
+        )
+        .bind(kind.as_str())
+        .bind(scope)
+        .bind(from)
+        .bind(id as i64)
+        .bind(chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .execute(conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// [`SlugHistory::resolve`] for SQLite stores
+pub async fn resolve(
+    pool: &SqlitePool,
+    kind: SlugKind,
+    scope: &str,
+    slug: &str,
+) -> Result<Option<u32>, sqlx::Error> {
+    let id: Option<i64> = sqlx::query_scalar(
+        "SELECT target_id FROM slug_history WHERE kind = ? AND scope = ? AND slug = ?",
+    )
+    .bind(kind.as_str())
+    .bind(scope)
+    .bind(slug)
+    .fetch_optional(pool)
+    .await?;
+    Ok(id.map(|id| id as u32))
+}
+
+/// [`SlugHistory::forget`] for SQLite stores
+pub async fn forget(
+    conn: &mut SqliteConnection,
+    kind: SlugKind,
+    scope: &str,
+    id: u32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM slug_history WHERE kind = ? AND scope = ? AND target_id = ?")
+        .bind(kind.as_str())
+        .bind(scope)
+        .bind(id as i64)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique() {
+        assert_eq!(slugify("  Hello, World! "), "hello-world");
+        let taken = ["hello-world".to_string(), "hello-world-2".to_string()];
+        let taken = |slug: &str| taken.iter().any(|t| t == slug);
+        assert_eq!(unique(&["", "Hello World"], "post", taken), "hello-world-3");
+        assert_eq!(unique(&["¡¿?!"], "post", taken), "post");
+    }
+
+    #[test]
+    fn test_history() {
+        let history = SlugHistory::new();
+        history.moved("acme", 7, "draft", "launch");
+        history.moved("acme", 7, "launch", "launch-day");
+        assert_eq!(history.resolve("acme", "draft"), Some(7));
+        assert_eq!(history.resolve("acme", "launch"), Some(7));
+        assert_eq!(history.resolve("other", "draft"), None);
+
+        // Another record takes an old slug: it no longer redirects
+        history.claim("acme", "launch");
+        assert_eq!(history.resolve("acme", "launch"), None);
+
+        history.forget("acme", 7);
+        assert_eq!(history.resolve("acme", "draft"), None);
+    }
+}
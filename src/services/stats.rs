@@ -0,0 +1,399 @@
+//! Stats — runtime figures for the status card
+//!
+//! The request logger reports every request with [`Stats::record_request`],
+//! and those over their route's latency budget with
+//! [`Stats::record_over_budget`];
+//! a background task calls [`Stats::sample`] every [`SAMPLE_INTERVAL`] to
+//! turn the count into a rate and record the number of active sessions. The
+//! last [`HISTORY_LEN`] samples feed the status card charts, and
+//! [`Stats::snapshot`] gathers everything else: totals, p95 latency, process
+//! memory and the database pools. Everything is in memory and starts over on
+//! restart.
+//!
+//! sqlx doesn't report how long queries wait for a connection, so
+//! [`Stats::probe`] measures it every [`PROBE_INTERVAL`]: it acquires a
+//! connection from each pool the way a query arriving then would, records
+//! the wait in a histogram ([`ACQUIRE_BUCKETS`]) and warns when the wait
+//! passes `[database] slow_acquire_ms`, with the pool's figures and what to
+//! change.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+use crate::db::Db;
+
+/// Time between samples
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+/// Samples kept per series (five minutes at the default interval)
+pub const HISTORY_LEN: usize = 30;
+/// Most recent request latencies kept for percentiles
+pub const LATENCY_WINDOW: usize = 1000;
+/// Time between connection acquire probes
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+/// Upper bounds of the acquire wait histogram; one more bucket counts
+/// anything slower
+pub const ACQUIRE_BUCKETS: [Duration; 6] = [
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(25),
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+];
+/// Least time between slow-acquire warnings for one pool
+const WARN_EVERY: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+struct History {
+    /// Request count at the previous sample
+    last_requests: u64,
+    /// Requests per minute
+    request_rate: VecDeque<f64>,
+    active_sessions: VecDeque<f64>,
+}
+
+/// The database connection pools, see [`Db`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pool {
+    Reader,
+    Writer,
+}
+
+impl Pool {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Reader => "reader",
+            Self::Writer => "writer",
+        }
+    }
+}
+
+#[derive(Default)]
+struct AcquireHistory {
+    /// Waits per [`ACQUIRE_BUCKETS`] bucket, then the overflow bucket
+    buckets: [u64; ACQUIRE_BUCKETS.len() + 1],
+    /// Waits over the slow threshold
+    slow: u64,
+    last_warning: Option<Instant>,
+}
+
+/// One pool's figures
+#[derive(Debug, Clone, Default)]
+pub struct PoolSnapshot {
+    /// Open connections
+    pub size: u32,
+    pub idle: usize,
+    pub max: u32,
+    /// Probed acquire waits per [`ACQUIRE_BUCKETS`] bucket, then the
+    /// overflow bucket
+    pub acquire_waits: Vec<u64>,
+    /// Probed waits over `[database] slow_acquire_ms`
+    pub slow_acquires: u64,
+}
+
+impl PoolSnapshot {
+    /// Upper bound of the bucket holding the 95th percentile wait; `None`
+    /// before the first probe, and for the overflow bucket
+    pub fn p95_wait(&self) -> Option<Duration> {
+        let total: u64 = self.acquire_waits.iter().sum();
+        let rank = (total * 95).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (i, count) in self.acquire_waits.iter().enumerate() {
+            seen += count;
+            if total > 0 && seen >= rank {
+                return ACQUIRE_BUCKETS.get(i).copied();
+            }
+        }
+        None
+    }
+}
+
+/// Point-in-time figures, see [`Stats::snapshot`]
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub started_at: SystemTime,
+    pub requests: u64,
+    /// Responses with a 5xx status
+    pub server_errors: u64,
+    /// `None` until a request has been recorded
+    pub p95_latency: Option<Duration>,
+    /// Requests slower than their route's `[latency]` budget
+    pub over_budget: u64,
+    /// Resident memory of this process; `None` where the platform won't say
+    pub memory_bytes: Option<u64>,
+    pub db_reader: PoolSnapshot,
+    pub db_writer: PoolSnapshot,
+    pub active_sessions: usize,
+}
+
+pub struct Stats {
+    started_at: SystemTime,
+    requests: AtomicU64,
+    server_errors: AtomicU64,
+    over_budget: AtomicU64,
+    latencies: Mutex<VecDeque<Duration>>,
+    history: Mutex<History>,
+    /// Reader, then writer
+    acquires: Mutex<[AcquireHistory; 2]>,
+    /// Reused between snapshots; only this process is ever refreshed
+    system: Mutex<System>,
+}
+
+fn push<T>(series: &mut VecDeque<T>, value: T, capacity: usize) {
+    if series.len() == capacity {
+        series.pop_front();
+    }
+    series.push_back(value);
+}
+
+/// Nearest-rank percentile (`p` in 0–100) of `values`
+fn percentile(mut values: Vec<Duration>, p: usize) -> Option<Duration> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    let rank = (values.len() * p).div_ceil(100).max(1);
+    Some(values[rank - 1])
+}
+
+impl Stats {
+    pub fn new(started_at: SystemTime) -> Self {
+        Self {
+            started_at,
+            requests: AtomicU64::new(0),
+            server_errors: AtomicU64::new(0),
+            over_budget: AtomicU64::new(0),
+            latencies: Mutex::new(VecDeque::with_capacity(LATENCY_WINDOW)),
+            history: Mutex::new(History::default()),
+            acquires: Mutex::new(Default::default()),
+            system: Mutex::new(System::new()),
+        }
+    }
+
+    pub fn record_request(&self, latency: Duration, status: u16) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if status >= 500 {
+            self.server_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        push(&mut self.latencies.lock().unwrap(), latency, LATENCY_WINDOW);
+    }
+
+    /// Count a request that took longer than its route's budget
+    pub fn record_over_budget(&self) {
+        self.over_budget.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Close the current interval
+    pub fn sample(&self, active_sessions: usize) {
+        let requests = self.requests.load(Ordering::Relaxed);
+        let mut history = self.history.lock().unwrap();
+        let per_minute = 60.0 / SAMPLE_INTERVAL.as_secs_f64();
+        let rate = requests.saturating_sub(history.last_requests) as f64 * per_minute;
+        history.last_requests = requests;
+        push(&mut history.request_rate, rate, HISTORY_LEN);
+        push(
+            &mut history.active_sessions,
+            active_sessions as f64,
+            HISTORY_LEN,
+        );
+    }
+
+    /// Requests per minute, oldest sample first
+    pub fn request_rate(&self) -> Vec<f64> {
+        self.history
+            .lock()
+            .unwrap()
+            .request_rate
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    /// Active sessions, oldest sample first
+    pub fn active_sessions(&self) -> Vec<f64> {
+        self.history
+            .lock()
+            .unwrap()
+            .active_sessions
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    /// Current figures. Reads this process's memory, so call it per render
+    /// rather than per request.
+    pub fn snapshot(&self, db: &Db, active_sessions: usize) -> Snapshot {
+        let latencies: Vec<Duration> = self.latencies.lock().unwrap().iter().copied().collect();
+        Snapshot {
+            started_at: self.started_at,
+            requests: self.requests.load(Ordering::Relaxed),
+            server_errors: self.server_errors.load(Ordering::Relaxed),
+            p95_latency: percentile(latencies, 95),
+            over_budget: self.over_budget.load(Ordering::Relaxed),
+            memory_bytes: self.memory(),
+            db_reader: self.pool(db, Pool::Reader),
+            db_writer: self.pool(db, Pool::Writer),
+            active_sessions,
+        }
+    }
+
+    fn pool(&self, db: &Db, pool: Pool) -> PoolSnapshot {
+        let sqlx_pool = match pool {
+            Pool::Reader => db.reader(),
+            Pool::Writer => db.writer(),
+        };
+        let acquires = self.acquires.lock().unwrap();
+        let history = &acquires[pool as usize];
+        PoolSnapshot {
+            size: sqlx_pool.size(),
+            idle: sqlx_pool.num_idle(),
+            max: sqlx_pool.options().get_max_connections(),
+            acquire_waits: history.buckets.to_vec(),
+            slow_acquires: history.slow,
+        }
+    }
+
+    /// Record one acquire wait. True when it's over `slow` and the pool
+    /// hasn't been warned about in the last minute.
+    pub fn record_acquire(&self, pool: Pool, wait: Duration, slow: Duration) -> bool {
+        let mut acquires = self.acquires.lock().unwrap();
+        let history = &mut acquires[pool as usize];
+        let bucket = ACQUIRE_BUCKETS
+            .iter()
+            .position(|bound| wait <= *bound)
+            .unwrap_or(ACQUIRE_BUCKETS.len());
+        history.buckets[bucket] += 1;
+        if wait <= slow {
+            return false;
+        }
+        history.slow += 1;
+        let due = history
+            .last_warning
+            .is_none_or(|at| at.elapsed() >= WARN_EVERY);
+        if due {
+            history.last_warning = Some(Instant::now());
+        }
+        due
+    }
+
+    /// Time acquiring a connection from each pool and warn about slow
+    /// ones; see the module docs. Failed acquires are left to
+    /// [`DbHealth`](crate::db::DbHealth).
+    pub async fn probe(&self, db: &Db, slow: Duration) {
+        for (pool, sqlx_pool) in [(Pool::Reader, db.reader()), (Pool::Writer, db.writer())] {
+            let started = Instant::now();
+            let Ok(connection) = sqlx_pool.acquire().await else {
+                continue;
+            };
+            let wait = started.elapsed();
+            drop(connection);
+            if !self.record_acquire(pool, wait, slow) {
+                continue;
+            }
+            let size = sqlx_pool.size();
+            let max = sqlx_pool.options().get_max_connections();
+            let hint = match pool {
+                Pool::Reader if size >= max => {
+                    "every read connection was busy; raise database.max_read_connections or find the slow queries"
+                }
+                Pool::Reader => "opening a read connection was slow; check the disk and busy_timeout_ms",
+                Pool::Writer => {
+                    "writes are queuing for the single writer; look for long write transactions or batch writes"
+                }
+            };
+            tracing::warn!(
+                pool = pool.name(),
+                waited_ms = wait.as_millis() as u64,
+                threshold_ms = slow.as_millis() as u64,
+                size,
+                idle = sqlx_pool.num_idle(),
+                max,
+                "Slow database connection acquire: {hint}"
+            );
+        }
+    }
+
+    fn memory(&self) -> Option<u64> {
+        let pid = Pid::from_u32(std::process::id());
+        let mut system = self.system.lock().unwrap();
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            true,
+            ProcessRefreshKind::nothing().with_memory(),
+        );
+        system.process(pid).map(|p| p.memory())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples() {
+        let stats = Stats::new(SystemTime::now());
+        for _ in 0..5 {
+            stats.record_request(Duration::from_millis(1), 200);
+        }
+        stats.sample(2);
+        stats.sample(3);
+        assert_eq!(stats.request_rate(), vec![30.0, 0.0]);
+        assert_eq!(stats.active_sessions(), vec![2.0, 3.0]);
+
+        for _ in 0..HISTORY_LEN {
+            stats.sample(1);
+        }
+        assert_eq!(stats.active_sessions().len(), HISTORY_LEN);
+    }
+
+    #[test]
+    fn test_acquire_histogram() {
+        let stats = Stats::new(SystemTime::now());
+        let slow = Duration::from_millis(250);
+        let writer = |stats: &Stats| {
+            let history = &stats.acquires.lock().unwrap()[Pool::Writer as usize];
+            (history.buckets, history.slow)
+        };
+        for _ in 0..19 {
+            assert!(!stats.record_acquire(Pool::Writer, Duration::from_micros(300), slow));
+        }
+        // Warned once a minute, counted every time
+        assert!(stats.record_acquire(Pool::Writer, Duration::from_secs(2), slow));
+        assert!(!stats.record_acquire(Pool::Writer, Duration::from_millis(400), slow));
+        assert_eq!(writer(&stats), ([19, 0, 0, 0, 1, 0, 1], 2));
+
+        let snapshot = |waits: Vec<u64>| PoolSnapshot {
+            acquire_waits: waits,
+            ..PoolSnapshot::default()
+        };
+        assert_eq!(snapshot(vec![0; 7]).p95_wait(), None);
+        assert_eq!(
+            snapshot(vec![95, 5, 0, 0, 0, 0, 0]).p95_wait(),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(
+            snapshot(vec![90, 0, 10, 0, 0, 0, 0]).p95_wait(),
+            Some(Duration::from_millis(25))
+        );
+        assert_eq!(snapshot(vec![0, 0, 0, 0, 0, 0, 1]).p95_wait(), None);
+    }
+
+    #[test]
+    fn test_percentile() {
+        let millis = |ms: Vec<u64>| ms.into_iter().map(Duration::from_millis).collect();
+        assert_eq!(percentile(Vec::new(), 95), None);
+        assert_eq!(
+            percentile(millis(vec![7]), 95),
+            Some(Duration::from_millis(7))
+        );
+        // 95th of 1..=100 is 95; order doesn't matter
+        assert_eq!(
+            percentile(millis((1..=100).rev().collect()), 95),
+            Some(Duration::from_millis(95))
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Subscriber Service — newsletter signups with double opt-in
+//!
+//! A signup starts out [`SubscriberStatus::Pending`] and only becomes
+//! `Confirmed` once the address owner follows the emailed link. Unsubscribing
+//! keeps the row (as `Unsubscribed`) so exports show who left. Addresses are
+//! stored as given by [`normalize_email`].
+
+use serde::Serialize;
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriberStatus {
+    Pending,
+    Confirmed,
+    Unsubscribed,
+}
+
+impl SubscriberStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Confirmed => "confirmed",
+            Self::Unsubscribed => "unsubscribed",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "confirmed" => Self::Confirmed,
+            "unsubscribed" => Self::Unsubscribed,
+            _ => Self::Pending,
+        }
+    }
+}
+
+/// Subscriber data model
+#[derive(Debug, Clone, Serialize)]
+pub struct Subscriber {
+    pub id: u32,
+    pub email: String,
+    pub status: SubscriberStatus,
+    /// RFC 3339 UTC timestamps
+    pub created_at: String,
+    pub confirmed_at: Option<String>,
+}
+
+/// Subscriber service trait — defines operations for the mailing list
+pub trait SubscriberService: Send + Sync {
+    /// Every subscriber, oldest first
+    fn list_all(&self) -> Vec<Subscriber>;
+    fn get_by_email(&self, email: &str) -> Option<Subscriber>;
+    /// Record a signup. New and previously unsubscribed addresses become
+    /// pending; anyone else is returned unchanged.
+    fn subscribe(&self, email: &str) -> Subscriber;
+    /// Confirm a pending signup. `None` for unknown or unsubscribed addresses.
+    fn confirm(&self, email: &str) -> Option<Subscriber>;
+    /// `false` when the address isn't on the list
+    fn unsubscribe(&self, email: &str) -> bool;
+}
+
+/// Trimmed and lowercased, so one inbox maps to one row
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_ascii_lowercase()
+}
+
+fn now() -> String {
+    chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// In-memory subscriber storage (good for prototyping, tests)
+pub struct InMemorySubscriberService {
+    subscribers: RwLock<Vec<Subscriber>>,
+}
+
+impl InMemorySubscriberService {
+    pub fn new() -> Self {
+        Self {
+            subscribers: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for InMemorySubscriberService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SubscriberService for InMemorySubscriberService {
+    fn list_all(&self) -> Vec<Subscriber> {
+        self.subscribers.read().unwrap().clone()
+    }
+
+    fn get_by_email(&self, email: &str) -> Option<Subscriber> {
+        self.subscribers
+            .read()
+            .unwrap()
+            .iter()
+            .find(|s| s.email == email)
+            .cloned()
+    }
+
+    fn subscribe(&self, email: &str) -> Subscriber {
+        let mut subscribers = self.subscribers.write().unwrap();
+        if let Some(existing) = subscribers.iter_mut().find(|s| s.email == email) {
+            if existing.status == SubscriberStatus::Unsubscribed {
+                existing.status = SubscriberStatus::Pending;
+                existing.confirmed_at = None;
+            }
+            return existing.clone();
+        }
+        let subscriber = Subscriber {
+            id: subscribers.iter().map(|s| s.id).max().unwrap_or(0) + 1,
+            email: email.to_string(),
+            status: SubscriberStatus::Pending,
+            created_at: now(),
+            confirmed_at: None,
+        };
+        subscribers.push(subscriber.clone());
+        subscriber
+    }
+
+    fn confirm(&self, email: &str) -> Option<Subscriber> {
+        let mut subscribers = self.subscribers.write().unwrap();
+        let subscriber = subscribers
+            .iter_mut()
+            .find(|s| s.email == email && s.status != SubscriberStatus::Unsubscribed)?;
+        if subscriber.status == SubscriberStatus::Pending {
+            subscriber.status = SubscriberStatus::Confirmed;
+            subscriber.confirmed_at = Some(now());
+        }
+        Some(subscriber.clone())
+    }
+
+    fn unsubscribe(&self, email: &str) -> bool {
+        let mut subscribers = self.subscribers.write().unwrap();
+        match subscribers.iter_mut().find(|s| s.email == email) {
+            Some(subscriber) => {
+                subscriber.status = SubscriberStatus::Unsubscribed;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// ============================================================================
+// SQLx Implementation — SQLite-backed subscriber storage
+// ============================================================================
+
+use crate::db::Db;
+
+pub struct SqliteSubscriberService {
+    db: Db,
+}
+
+impl SqliteSubscriberService {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+}
+
+/// Row type returned by SQLx queries
+#[derive(sqlx::FromRow)]
+struct SubscriberRow {
+    id: i64,
+    email: String,
+    status: String,
+    created_at: String,
+    confirmed_at: Option<String>,
+}
+
+impl From<SubscriberRow> for Subscriber {
+    fn from(row: SubscriberRow) -> Self {
+        Subscriber {
+            id: row.id as u32,
+            email: row.email,
+            status: SubscriberStatus::parse(&row.status),
+            created_at: row.created_at,
+            confirmed_at: row.confirmed_at,
+        }
+    }
+}
+
+const SUBSCRIBER_COLUMNS: &str = "id, email, status, created_at, confirmed_at";
+
+impl SubscriberService for SqliteSubscriberService {
+    fn list_all(&self) -> Vec<Subscriber> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, SubscriberRow>(&format!(
+                    "SELECT {SUBSCRIBER_COLUMNS} FROM subscribers ORDER BY id"
+                ))
+                .fetch_all(self.db.reader())
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(Subscriber::from)
+                .collect()
+            })
+        })
+    }
+
+    fn get_by_email(&self, email: &str) -> Option<Subscriber> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, SubscriberRow>(&format!(
+                    "SELECT {SUBSCRIBER_COLUMNS} FROM subscribers WHERE email = ?"
+                ))
+                .bind(email)
+                .fetch_optional(self.db.reader())
+                .await
+                .ok()
+                .flatten()
+                .map(Subscriber::from)
+            })
+        })
+    }
+
+    fn subscribe(&self, email: &str) -> Subscriber {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let row = sqlx::query_as::<_, SubscriberRow>(&format!(
+                    "INSERT INTO subscribers (email, status, created_at) VALUES (?, 'pending', ?) \
+                     ON CONFLICT (email) DO UPDATE SET \
+                       status = CASE WHEN status = 'unsubscribed' THEN 'pending' ELSE status END, \
+                       confirmed_at = CASE WHEN status = 'unsubscribed' THEN NULL ELSE confirmed_at END \
+                     RETURNING {SUBSCRIBER_COLUMNS}"
+                ))
+                .bind(email)
+                .bind(now())
+                .fetch_one(self.db.writer())
+                .await
+                .expect("Failed to insert subscriber");
+                Subscriber::from(row)
+            })
+        })
+    }
+
+    fn confirm(&self, email: &str) -> Option<Subscriber> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let _ = sqlx::query(
+                    "UPDATE subscribers SET status = 'confirmed', confirmed_at = ? \
+                     WHERE email = ? AND status = 'pending'",
+                )
+                .bind(now())
+                .bind(email)
+                .execute(self.db.writer())
+                .await;
+                sqlx::query_as::<_, SubscriberRow>(&format!(
+                    "SELECT {SUBSCRIBER_COLUMNS} FROM subscribers \
+                     WHERE email = ? AND status = 'confirmed'"
+                ))
+                .bind(email)
+                .fetch_optional(self.db.reader())
+                .await
+                .ok()
+                .flatten()
+                .map(Subscriber::from)
+            })
+        })
+    }
+
+    fn unsubscribe(&self, email: &str) -> bool {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let result =
+                    sqlx::query("UPDATE subscribers SET status = 'unsubscribed' WHERE email = ?")
+                        .bind(email)
+                        .execute(self.db.writer())
+                        .await;
+                matches!(result, Ok(r) if r.rows_affected() > 0)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_double_opt_in_lifecycle() {
+        let service = InMemorySubscriberService::new();
+        let email = normalize_email("  Ada@Example.com ");
+        assert_eq!(email, "ada@example.com");
+
+        assert_eq!(service.subscribe(&email).status, SubscriberStatus::Pending);
+        let confirmed = service.confirm(&email).unwrap();
+        assert_eq!(confirmed.status, SubscriberStatus::Confirmed);
+        assert!(confirmed.confirmed_at.is_some());
+        // Signing up again doesn't reset a confirmed subscriber
+        assert_eq!(
+            service.subscribe(&email).status,
+            SubscriberStatus::Confirmed
+        );
+
+        assert!(service.unsubscribe(&email));
+        assert!(service.confirm(&email).is_none());
+        assert_eq!(service.subscribe(&email).status, SubscriberStatus::Pending);
+        assert_eq!(service.list_all().len(), 1);
+        assert!(!service.unsubscribe("nobody@example.com"));
+    }
+}
@@ -0,0 +1,340 @@
+//! Tenant Service — which tenant a request belongs to
+//!
+//! [`Tenants`] is built from `[tenancy]` in the config and resolves each
+//! request to a tenant, by subdomain (`acme.example.com`) or path prefix
+//! (`/t/acme/demo`), see [`TenancyMode`]. The tenant middleware makes the
+//! result the [current](current) tenant for the rest of the request, and
+//! repositories scope their queries by it — handlers never pass it along.
+//! Handlers that do need it take a [`TenantId`].
+//!
+//! Each tenant can rename the app and override design tokens; both reach
+//! templates as `globals.tenant`.
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::future::Future;
+
+use crate::config::{TenancyConfig, TenancyMode};
+
+/// Slug of the tenant served when nothing else matches
+pub const DEFAULT_TENANT: &str = "default";
+/// Path mode: requests under `/t/<slug>` belong to `<slug>`
+pub const PATH_PREFIX: &str = "/t/";
+
+/// A tenant as templates see it (`globals.tenant`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Tenant {
+    pub slug: String,
+    /// Replaces the app name in the layout; empty keeps it
+    pub name: String,
+    /// Path mode: `/t/<slug>`, to put in front of links. Empty otherwise.
+    pub prefix: String,
+    /// Design token overrides
+    pub theme: Vec<ThemeVar>,
+    pub has_theme: bool,
+}
+
+impl Default for Tenant {
+    fn default() -> Self {
+        Self {
+            slug: DEFAULT_TENANT.to_string(),
+            name: String::new(),
+            prefix: String::new(),
+            theme: Vec::new(),
+            has_theme: false,
+        }
+    }
+}
+
+/// One `--<name>: <value>` override
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThemeVar {
+    pub name: String,
+    pub value: String,
+}
+
+/// Slugs double as subdomains and path segments
+pub(crate) fn valid_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug.len() <= 63
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !slug.starts_with('-')
+}
+
+/// Token names and values end up inside a `<style>` element, so both are
+/// limited to characters that can't close it or start another rule
+pub(crate) fn valid_theme_var(name: &str, value: &str) -> bool {
+    let name_ok = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    let value_ok = !value.trim().is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || " #(),.%-".contains(c));
+    name_ok && value_ok
+}
+
+/// Where a request was resolved to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved {
+    pub tenant: Tenant,
+    /// Path mode: the path with the tenant prefix removed
+    pub path: Option<String>,
+}
+
+/// The configured tenants, see the module docs
+#[derive(Debug, Clone)]
+pub struct Tenants {
+    mode: TenancyMode,
+    base_domain: String,
+    tenants: BTreeMap<String, Tenant>,
+}
+
+impl Default for Tenants {
+    fn default() -> Self {
+        Self::new(&TenancyConfig::default())
+    }
+}
+
+impl Tenants {
+    /// Build from config. Invalid slugs and theme tokens are skipped with a
+    /// warning.
+    pub fn new(config: &TenancyConfig) -> Self {
+        let mut tenants = BTreeMap::new();
+        tenants.insert(DEFAULT_TENANT.to_string(), Tenant::default());
+        for (slug, tenant) in &config.tenants {
+            if !valid_slug(slug) {
+                tracing::warn!(slug, "Skipping tenant with an invalid slug");
+                continue;
+            }
+            let theme: Vec<ThemeVar> = tenant
+                .theme
+                .iter()
+                .filter(|(name, value)| {
+                    let valid = valid_theme_var(name, value);
+                    if !valid {
+                        tracing::warn!(slug, token = %name, "Skipping invalid theme token");
+                    }
+                    valid
+                })
+                .map(|(name, value)| ThemeVar {
+                    name: name.clone(),
+                    value: value.trim().to_string(),
+                })
+                .collect();
+            let prefix = match config.mode {
+                TenancyMode::Path if slug != DEFAULT_TENANT => format!("{PATH_PREFIX}{slug}"),
+                _ => String::new(),
+            };
+            tenants.insert(
+                slug.clone(),
+                Tenant {
+                    slug: slug.clone(),
+                    name: tenant.name.trim().to_string(),
+                    prefix,
+                    has_theme: !theme.is_empty(),
+                    theme,
+                },
+            );
+        }
+        Self {
+            mode: config.mode,
+            base_domain: config.base_domain.trim().to_ascii_lowercase(),
+            tenants,
+        }
+    }
+
+    pub fn get(&self, slug: &str) -> Option<&Tenant> {
+        self.tenants.get(slug)
+    }
+
+    fn default_tenant(&self) -> Tenant {
+        self.tenants[DEFAULT_TENANT].clone()
+    }
+
+    /// Match a request to a tenant: `None` when it names one that doesn't
+    /// exist. `current_url` is HTMX's `HX-Current-URL`, which lets a
+    /// fragment fetched without the path prefix inherit the page's tenant.
+    pub fn resolve(
+        &self,
+        host: Option<&str>,
+        path: &str,
+        current_url: Option<&str>,
+    ) -> Option<Resolved> {
+        match self.mode {
+            TenancyMode::Off => Some(Resolved {
+                tenant: self.default_tenant(),
+                path: None,
+            }),
+            TenancyMode::Subdomain => {
+                let host = host.unwrap_or_default().to_ascii_lowercase();
+                let host = host.split(':').next().unwrap_or_default();
+                let slug = host
+                    .strip_suffix(self.base_domain.as_str())
+                    .and_then(|rest| rest.strip_suffix('.'));
+                let tenant = match slug {
+                    Some(slug) => self.get(slug)?.clone(),
+                    None => self.default_tenant(),
+                };
+                Some(Resolved { tenant, path: None })
+            }
+            TenancyMode::Path => match split_prefix(path) {
+                Some((slug, rest)) => Some(Resolved {
+                    tenant: self.get(slug)?.clone(),
+                    path: Some(rest.to_string()),
+                }),
+                None => {
+                    let tenant = current_url
+                        .map(|url| url.split_once("://").map_or(url, |(_, rest)| rest))
+                        .and_then(|url| url.find('/').map(|i| &url[i..]))
+                        .and_then(split_prefix)
+                        .and_then(|(slug, _)| self.get(slug))
+                        .cloned()
+                        .unwrap_or_else(|| self.default_tenant());
+                    Some(Resolved { tenant, path: None })
+                }
+            },
+        }
+    }
+}
+
+/// `/t/acme/demo` → (`acme`, `/demo`); `/t/acme` → (`acme`, `/`)
+fn split_prefix(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix(PATH_PREFIX)?;
+    let (slug, rest) = match rest.find(['/', '?']) {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    if slug.is_empty() {
+        return None;
+    }
+    Some((slug, if rest.is_empty() { "/" } else { rest }))
+}
+
+// ============================================================================
+// Current tenant
+// ============================================================================
+
+tokio::task_local! {
+    static CURRENT_TENANT: String;
+}
+
+/// Slug of the tenant the current request belongs to — [`DEFAULT_TENANT`]
+/// outside a request (tests, background tasks)
+pub fn current() -> String {
+    CURRENT_TENANT
+        .try_with(Clone::clone)
+        .unwrap_or_else(|_| DEFAULT_TENANT.to_string())
+}
+
+/// Run `future` (the rest of the request) as `tenant`
+pub async fn scope<F: Future>(tenant: String, future: F) -> F::Output {
+    CURRENT_TENANT.scope(tenant, future).await
+}
+
+/// Synchronous [`scope`], e.g. for tests
+pub fn sync_scope<R>(tenant: &str, f: impl FnOnce() -> R) -> R {
+    CURRENT_TENANT.sync_scope(tenant.to_string(), f)
+}
+
+/// The request's tenant slug, as resolved by the tenant middleware
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantId(pub String);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for TenantId {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<TenantId>()
+            .cloned()
+            .unwrap_or_else(|| TenantId(DEFAULT_TENANT.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TenantConfig;
+
+    fn config(mode: TenancyMode) -> TenancyConfig {
+        let mut tenants = BTreeMap::new();
+        tenants.insert(
+            "acme".to_string(),
+            TenantConfig {
+                name: "Acme".into(),
+                theme: BTreeMap::from([
+                    ("color-brand".into(), "#e11d48".into()),
+                    ("color-border".into(), "red; } body { x".into()),
+                ]),
+            },
+        );
+        tenants.insert("Bad Slug".to_string(), TenantConfig::default());
+        TenancyConfig {
+            mode,
+            base_domain: "example.com".into(),
+            tenants,
+        }
+    }
+
+    fn slug(resolved: Option<Resolved>) -> Option<String> {
+        resolved.map(|r| r.tenant.slug)
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let tenants = Tenants::new(&config(TenancyMode::Path));
+        assert!(tenants.get("bad slug").is_none());
+        let acme = tenants.get("acme").unwrap();
+        assert_eq!(acme.prefix, "/t/acme");
+        // The token that would break out of the rule is dropped
+        assert_eq!(acme.theme.len(), 1);
+        assert_eq!(acme.theme[0].name, "color-brand");
+    }
+
+    #[test]
+    fn test_resolve_subdomain() {
+        let tenants = Tenants::new(&config(TenancyMode::Subdomain));
+        let resolve = |host| slug(tenants.resolve(Some(host), "/", None));
+        assert_eq!(resolve("acme.example.com"), Some("acme".into()));
+        assert_eq!(resolve("ACME.example.com:8000"), Some("acme".into()));
+        assert_eq!(resolve("example.com"), Some(DEFAULT_TENANT.into()));
+        assert_eq!(resolve("other.example.com"), None);
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let tenants = Tenants::new(&config(TenancyMode::Path));
+        let resolved = tenants.resolve(None, "/t/acme/demo", None).unwrap();
+        assert_eq!(resolved.tenant.slug, "acme");
+        assert_eq!(resolved.path.as_deref(), Some("/demo"));
+        let root = tenants.resolve(None, "/t/acme", None).unwrap();
+        assert_eq!(root.path.as_deref(), Some("/"));
+        assert_eq!(slug(tenants.resolve(None, "/t/nope/demo", None)), None);
+
+        // Fragments inherit the tenant of the page that asked for them
+        let fragment = tenants.resolve(
+            None,
+            "/partials/item-list",
+            Some("http://localhost:8000/t/acme/demo"),
+        );
+        assert_eq!(slug(fragment), Some("acme".into()));
+        assert_eq!(
+            slug(tenants.resolve(None, "/demo", None)),
+            Some(DEFAULT_TENANT.into())
+        );
+    }
+
+    #[test]
+    fn test_current() {
+        assert_eq!(current(), DEFAULT_TENANT);
+        assert_eq!(sync_scope("acme", current), "acme");
+    }
+}
@@ -0,0 +1,262 @@
+//! Webhook Event Service — a ledger of incoming webhook deliveries
+//!
+//! Providers retry deliveries they don't see acknowledged, so the same event
+//! can arrive more than once. Events are keyed by provider and the provider's
+//! own delivery id; [`WebhookEventService::record`] returns `None` for one
+//! already on file, and the receiver skips it.
+
+use serde::Serialize;
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookStatus {
+    /// Stored, handlers not yet run
+    Received,
+    Processed,
+    /// A handler returned an error (it may still succeed on retry)
+    Failed,
+}
+
+impl WebhookStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Received => "received",
+            Self::Processed => "processed",
+            Self::Failed => "failed",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "processed" => Self::Processed,
+            "failed" => Self::Failed,
+            _ => Self::Received,
+        }
+    }
+}
+
+/// Webhook event data model
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEvent {
+    pub id: u32,
+    pub provider: String,
+    /// The provider's delivery id
+    pub event_id: String,
+    pub event_type: String,
+    /// Raw request body
+    pub payload: String,
+    pub status: WebhookStatus,
+    /// RFC 3339 UTC timestamp
+    pub received_at: String,
+}
+
+/// A verified delivery, before it's stored
+#[derive(Debug, Clone)]
+pub struct NewWebhookEvent {
+    pub provider: String,
+    pub event_id: String,
+    pub event_type: String,
+    pub payload: String,
+}
+
+/// Webhook event service trait — defines storage for received deliveries
+pub trait WebhookEventService: Send + Sync {
+    /// Store a delivery. `None` when this provider's `event_id` is already
+    /// on file.
+    fn record(&self, event: NewWebhookEvent) -> Option<WebhookEvent>;
+    fn set_status(&self, id: u32, status: WebhookStatus);
+    /// Most recent first
+    fn list_recent(&self, limit: usize) -> Vec<WebhookEvent>;
+}
+
+fn now() -> String {
+    chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// In-memory event storage (good for prototyping, tests)
+pub struct InMemoryWebhookEventService {
+    events: RwLock<Vec<WebhookEvent>>,
+}
+
+impl InMemoryWebhookEventService {
+    pub fn new() -> Self {
+        Self {
+            events: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for InMemoryWebhookEventService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookEventService for InMemoryWebhookEventService {
+    fn record(&self, event: NewWebhookEvent) -> Option<WebhookEvent> {
+        let mut events = self.events.write().unwrap();
+        if events
+            .iter()
+            .any(|e| e.provider == event.provider && e.event_id == event.event_id)
+        {
+            return None;
+        }
+        let event = WebhookEvent {
+            id: events.iter().map(|e| e.id).max().unwrap_or(0) + 1,
+            provider: event.provider,
+            event_id: event.event_id,
+            event_type: event.event_type,
+            payload: event.payload,
+            status: WebhookStatus::Received,
+            received_at: now(),
+        };
+        events.push(event.clone());
+        Some(event)
+    }
+
+    fn set_status(&self, id: u32, status: WebhookStatus) {
+        if let Some(event) = self.events.write().unwrap().iter_mut().find(|e| e.id == id) {
+            event.status = status;
+        }
+    }
+
+    fn list_recent(&self, limit: usize) -> Vec<WebhookEvent> {
+        self.events
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+// ============================================================================
+// SQLx Implementation — SQLite-backed event storage
+// ============================================================================
+
+use crate::db::Db;
+
+pub struct SqliteWebhookEventService {
+    db: Db,
+}
+
+impl SqliteWebhookEventService {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+}
+
+/// Row type returned by SQLx queries
+#[derive(sqlx::FromRow)]
+struct WebhookEventRow {
+    id: i64,
+    provider: String,
+    event_id: String,
+    event_type: String,
+    payload: String,
+    status: String,
+    received_at: String,
+}
+
+impl From<WebhookEventRow> for WebhookEvent {
+    fn from(row: WebhookEventRow) -> Self {
+        WebhookEvent {
+            id: row.id as u32,
+            provider: row.provider,
+            event_id: row.event_id,
+            event_type: row.event_type,
+            payload: row.payload,
+            status: WebhookStatus::parse(&row.status),
+            received_at: row.received_at,
+        }
+    }
+}
+
+const EVENT_COLUMNS: &str = "id, provider, event_id, event_type, payload, status, received_at";
+
+impl WebhookEventService for SqliteWebhookEventService {
+    fn record(&self, event: NewWebhookEvent) -> Option<WebhookEvent> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                // A duplicate inserts nothing, so RETURNING yields no row
+                sqlx::query_as::<_, WebhookEventRow>(&format!(
+                    "INSERT INTO webhook_events \
+                     (provider, event_id, event_type, payload, received_at) \
+                     VALUES (?, ?, ?, ?, ?) \
+                     ON CONFLICT (provider, event_id) DO NOTHING \
+                     RETURNING {EVENT_COLUMNS}"
+                ))
+                .bind(&event.provider)
+                .bind(&event.event_id)
+                .bind(&event.event_type)
+                .bind(&event.payload)
+                .bind(now())
+                .fetch_optional(self.db.writer())
+                .await
+                .expect("Failed to insert webhook event")
+                .map(WebhookEvent::from)
+            })
+        })
+    }
+
+    fn set_status(&self, id: u32, status: WebhookStatus) {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let _ = sqlx::query("UPDATE webhook_events SET status = ? WHERE id = ?")
+                    .bind(status.as_str())
+                    .bind(id as i64)
+                    .execute(self.db.writer())
+                    .await;
+            })
+        })
+    }
+
+    fn list_recent(&self, limit: usize) -> Vec<WebhookEvent> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                sqlx::query_as::<_, WebhookEventRow>(&format!(
+                    "SELECT {EVENT_COLUMNS} FROM webhook_events ORDER BY id DESC LIMIT ?"
+                ))
+                .bind(limit as i64)
+                .fetch_all(self.db.reader())
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(WebhookEvent::from)
+                .collect()
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delivery(provider: &str, event_id: &str) -> NewWebhookEvent {
+        NewWebhookEvent {
+            provider: provider.into(),
+            event_id: event_id.into(),
+            event_type: "push".into(),
+            payload: "{}".into(),
+        }
+    }
+
+    #[test]
+    fn test_redelivery_is_ignored() {
+        let service = InMemoryWebhookEventService::new();
+        let event = service.record(delivery("github", "abc")).unwrap();
+        assert_eq!(event.status, WebhookStatus::Received);
+        assert!(service.record(delivery("github", "abc")).is_none());
+        // Ids are only unique per provider
+        assert!(service.record(delivery("generic", "abc")).is_some());
+
+        service.set_status(event.id, WebhookStatus::Processed);
+        let recent = service.list_recent(10);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[1].status, WebhookStatus::Processed);
+    }
+}
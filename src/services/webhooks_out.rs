@@ -0,0 +1,221 @@
+//! Outgoing Webhooks — signed JSON events POSTed to subscribed endpoints
+//!
+//! Code announces what happened with [`EventPublisher::publish`]
+//! (`item.created`); every endpoint under `[[webhooks.endpoints]]` that
+//! wants the event gets a POST on the job queue, retried there on failure.
+//! Requests use the same scheme the receiver accepts for generic providers
+//! ([`crate::handlers::webhooks`]), so two instances can talk to each other:
+//!
+//! - `X-Webhook-Id` — unique per event, stable across retries
+//! - `X-Webhook-Event` — the event name
+//! - `X-Webhook-Signature: sha256=<hex>` — HMAC-SHA256 of the body with the
+//!   endpoint's secret
+//!
+//! The last [`LOG_CAPACITY`] attempts are kept in memory for the admin
+//! delivery log.
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::WebhookEndpoint;
+use crate::services::jobs::JobQueue;
+
+/// Delivery attempts kept for the admin log
+pub const LOG_CAPACITY: usize = 100;
+/// How long an endpoint gets to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One POST to one endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    pub event_id: String,
+    pub event: String,
+    pub url: String,
+    /// 1 for the first try
+    pub attempt: u32,
+    /// Answered with a 2xx
+    pub ok: bool,
+    /// HTTP status, or why there wasn't one
+    pub outcome: String,
+    /// `YYYY-MM-DD HH:MM:SS` UTC
+    pub at: String,
+}
+
+/// Event publisher trait — allows a recording publisher in tests
+pub trait EventPublisher: Send + Sync {
+    /// Send `event` with `data` to every endpoint subscribed to it
+    fn publish(&self, event: &str, data: serde_json::Value);
+    /// Recent delivery attempts, newest first
+    fn deliveries(&self) -> Vec<Delivery>;
+}
+
+/// Body of every webhook request
+#[derive(Serialize)]
+struct Envelope<'a> {
+    id: &'a str,
+    event: &'a str,
+    created_at: String,
+    data: serde_json::Value,
+}
+
+/// POSTs events over HTTP(S) from the job queue
+pub struct WebhookPublisher {
+    endpoints: Vec<WebhookEndpoint>,
+    jobs: Arc<dyn JobQueue>,
+    agent: ureq::Agent,
+    log: Arc<Mutex<VecDeque<Delivery>>>,
+}
+
+impl WebhookPublisher {
+    pub fn new(endpoints: Vec<WebhookEndpoint>, jobs: Arc<dyn JobQueue>) -> Self {
+        let endpoints: Vec<WebhookEndpoint> = endpoints
+            .into_iter()
+            .filter(|e| !e.url.is_empty())
+            .collect();
+        for endpoint in endpoints.iter().filter(|e| e.secret.is_empty()) {
+            tracing::warn!(url = %endpoint.url, "webhook endpoint has no secret; requests go unsigned");
+        }
+        Self {
+            endpoints,
+            jobs,
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+            log: Arc::new(Mutex::new(VecDeque::with_capacity(LOG_CAPACITY))),
+        }
+    }
+}
+
+impl EventPublisher for WebhookPublisher {
+    fn publish(&self, event: &str, data: serde_json::Value) {
+        let targets: Vec<&WebhookEndpoint> =
+            self.endpoints.iter().filter(|e| e.wants(event)).collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        let event_id = uuid::Uuid::new_v4().to_string();
+        let body = serde_json::to_string(&Envelope {
+            id: &event_id,
+            event,
+            created_at: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            data,
+        })
+        .expect("JSON values always serialize");
+
+        for endpoint in targets {
+            let request = self
+                .agent
+                .post(&endpoint.url)
+                .set("Content-Type", "application/json")
+                .set("X-Webhook-Id", &event_id)
+                .set("X-Webhook-Event", event);
+            let request = if endpoint.secret.is_empty() {
+                request
+            } else {
+                request.set(
+                    "X-Webhook-Signature",
+                    &signature(endpoint.secret.as_bytes(), &body),
+                )
+            };
+
+            let (body, log) = (body.clone(), self.log.clone());
+            let (event_id, event, url) =
+                (event_id.clone(), event.to_string(), endpoint.url.clone());
+            let attempts = AtomicU32::new(0);
+            self.jobs.enqueue(
+                "webhook-out",
+                Box::new(move || {
+                    let (ok, outcome) = match request.clone().send_string(&body) {
+                        Ok(response) => (true, response.status().to_string()),
+                        Err(ureq::Error::Status(code, _)) => (false, code.to_string()),
+                        Err(e) => (false, e.kind().to_string()),
+                    };
+                    record(
+                        &log,
+                        Delivery {
+                            event_id: event_id.clone(),
+                            event: event.clone(),
+                            url: url.clone(),
+                            attempt: attempts.fetch_add(1, Ordering::Relaxed) + 1,
+                            ok,
+                            outcome: outcome.clone(),
+                            at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                        },
+                    );
+                    if ok {
+                        Ok(())
+                    } else {
+                        Err(format!("{url} answered {outcome}"))
+                    }
+                }),
+            );
+        }
+    }
+
+    fn deliveries(&self) -> Vec<Delivery> {
+        self.log.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+fn record(log: &Mutex<VecDeque<Delivery>>, delivery: Delivery) {
+    let mut log = log.lock().unwrap();
+    if log.len() == LOG_CAPACITY {
+        log.pop_back();
+    }
+    log.push_front(delivery);
+}
+
+/// `sha256=<hex>` over `body`
+pub fn signature(secret: &[u8], body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::jobs::Job;
+
+    /// Runs each job once, right away
+    struct InlineQueue;
+
+    impl JobQueue for InlineQueue {
+        fn enqueue(&self, _name: &'static str, job: Job) {
+            let _ = job();
+        }
+    }
+
+    #[test]
+    fn test_deliveries_are_logged() {
+        let endpoint = WebhookEndpoint {
+            // Nothing listens on the discard port
+            url: "http://127.0.0.1:9/hook".into(),
+            secret: "s3cret".into(),
+            events: vec!["item.created".into()],
+        };
+        let publisher = WebhookPublisher::new(vec![endpoint], Arc::new(InlineQueue));
+
+        publisher.publish("comment.created", serde_json::json!({}));
+        assert!(publisher.deliveries().is_empty());
+
+        publisher.publish("item.created", serde_json::json!({ "id": 1 }));
+        let deliveries = publisher.deliveries();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].event, "item.created");
+        assert_eq!(deliveries[0].attempt, 1);
+        assert!(!deliveries[0].ok);
+    }
+
+    #[test]
+    fn test_signature() {
+        assert_eq!(
+            signature(b"key", "The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+}
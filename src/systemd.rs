@@ -0,0 +1,134 @@
+//! systemd — socket activation and readiness notifications
+//!
+//! With a `.socket` unit, systemd binds the port and hands the process the
+//! listening socket (`LISTEN_FDS`), so the app needs no right to bind it and
+//! connections queue while it restarts. [`listener`] takes that socket over;
+//! without one, `main` binds `[server] host:port` itself.
+//!
+//! Under `Type=notify`, [`notify`] tells systemd through `$NOTIFY_SOCKET`
+//! when the app is ready to take requests (`READY=1`) and when it starts
+//! shutting down (`STOPPING=1`). Outside systemd both are no-ops.
+
+use std::io;
+use std::net::TcpListener;
+
+/// First file descriptor systemd passes (`SD_LISTEN_FDS_START`)
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// Readiness: listening, serving requests
+pub const READY: &str = "READY=1";
+/// Shutting down: no new connections
+pub const STOPPING: &str = "STOPPING=1";
+
+/// The listening socket systemd passed this process, set non-blocking for
+/// tokio. `None` when it passed none, or passed them to another process
+/// (`LISTEN_PID`). With several, the first is used.
+#[cfg(unix)]
+pub fn listener() -> io::Result<Option<TcpListener>> {
+    use std::os::fd::FromRawFd;
+
+    let ours = std::env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id());
+    let count = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|count| count.parse::<i32>().ok())
+        .unwrap_or(0);
+    if !ours || count < 1 {
+        return Ok(None);
+    }
+    if count > 1 {
+        tracing::warn!(count, "systemd passed several sockets; using the first");
+    }
+    // SAFETY: systemd passed descriptors LISTEN_FDS_START.. to this very
+    // process (LISTEN_PID), and nothing else has taken ownership of them
+    let passed = unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START) };
+    // A copy marked close-on-exec, so commands the app runs (replication,
+    // backups) don't inherit the socket; the original is closed
+    let listener = passed.try_clone()?;
+    drop(passed);
+    // Fails unless it's really a bound TCP socket
+    listener.local_addr().map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("systemd's socket isn't a TCP listener: {e}"),
+        )
+    })?;
+    listener.set_nonblocking(true)?;
+    Ok(Some(listener))
+}
+
+#[cfg(not(unix))]
+pub fn listener() -> io::Result<Option<TcpListener>> {
+    Ok(None)
+}
+
+/// Send `state` (e.g. [`READY`]) to systemd; does nothing without
+/// `$NOTIFY_SOCKET`. A name starting with `@` is an abstract socket.
+#[cfg(unix)]
+pub fn notify(state: &str) -> io::Result<()> {
+    match std::env::var_os("NOTIFY_SOCKET") {
+        Some(path) => send(&path, state),
+        None => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn send(path: &std::ffi::OsStr, state: &str) -> io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let socket = UnixDatagram::unbound()?;
+    match path.to_str().and_then(|path| path.strip_prefix('@')) {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &address)?;
+        }
+        _ => {
+            socket.send_to(state.as_bytes(), path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixDatagram;
+
+    #[test]
+    fn test_notify() {
+        let path = std::env::temp_dir().join(format!("notify-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let systemd = UnixDatagram::bind(&path).unwrap();
+
+        send(path.as_os_str(), READY).unwrap();
+        send(path.as_os_str(), STOPPING).unwrap();
+
+        let mut buf = [0; 64];
+        let n = systemd.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1");
+        let n = systemd.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"STOPPING=1");
+        std::fs::remove_file(&path).unwrap();
+
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+            let name = format!("notify-test-{}", std::process::id());
+            let address = std::os::unix::net::SocketAddr::from_abstract_name(&name).unwrap();
+            let systemd = UnixDatagram::bind_addr(&address).unwrap();
+            send(format!("@{name}").as_ref(), READY).unwrap();
+            let n = systemd.recv(&mut buf).unwrap();
+            assert_eq!(&buf[..n], b"READY=1");
+        }
+    }
+}
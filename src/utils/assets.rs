@@ -0,0 +1,272 @@
+//! Static assets — the `asset()` template helper and `app assets vendor`
+//!
+//! Templates link static files with `asset("css/app.css")` rather than a
+//! literal `/static/...` path, so a file can be served under a fingerprinted
+//! name: [`asset`] answers with the bundle's URL once the server has built
+//! it (see [`crate::utils::css`]), and with the plain `/static/` path
+//! otherwise.
+//!
+//! Third-party JavaScript is served from `static/js`, never from a CDN.
+//! `static/js/vendor.toml` pins each file: the version, where it's
+//! downloaded from and the SHA-384 it has to hash to. [`vendor`] downloads
+//! every pinned file, refuses the lot if one doesn't match its pin, writes
+//! them into `static/js`, and swaps each file's old SRI hash for the new one
+//! wherever it appears under `src/`, `templates/` and `tests/`: the CSP
+//! constants in `middleware`, the `integrity` attributes in
+//! `components/_head.html`, the security page and the template snapshots.
+//!
+//! An asset without a pin (a new one, or a version bump with `sha384`
+//! cleared) is only written with `--pin`, which records the hash of what was
+//! downloaded: trust on first use, checked on every run after that.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha384};
+
+use crate::utils::css;
+
+/// The manifest, relative to the project root
+pub const MANIFEST: &str = "static/js/vendor.toml";
+
+/// Where vendored files are written, relative to the project root
+const VENDOR_DIR: &str = "static/js";
+
+/// Directories searched for SRI hashes to update (the template snapshots
+/// under `tests/` embed them too)
+const SRI_DIRS: &[&str] = &["src", "templates", "tests"];
+
+/// Largest download accepted
+const MAX_ASSET_BYTES: u64 = 2 * 1024 * 1024;
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Kept at the top of the manifest when `--pin` rewrites it
+const MANIFEST_HEADER: &str = "\
+# Pinned third-party JavaScript, served from static/js (never from a CDN).
+# `app assets vendor` downloads each `url`, checks that it hashes to `sha384`,
+# writes it to `file` and updates the file's SRI hash in src/, templates/, tests/.
+# To bump a version, edit `version` and `url` and clear `sha384`; then
+# `app assets vendor --pin` records the new hash.
+#
+# An empty `sha384` means the file in static/js is a local stand-in, not the
+# upstream release, and nothing has been checked against upstream yet. The
+# first `--pin` run downloads the release, replaces the stand-in with it and
+# records the hash of what was downloaded (never of the stand-in), so check
+# that hash against the one the project publishes before committing.
+";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VendorManifest {
+    #[serde(rename = "asset", default)]
+    pub assets: Vec<VendoredAsset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VendoredAsset {
+    pub name: String,
+    pub version: String,
+    pub url: String,
+    /// File name inside `static/js`
+    pub file: String,
+    /// SRI form (`sha384-...`); empty = not pinned yet
+    #[serde(default)]
+    pub sha384: String,
+}
+
+impl VendorManifest {
+    pub fn load(root: &Path) -> anyhow::Result<Self> {
+        let path = root.join(MANIFEST);
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        let manifest: Self =
+            toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+        for asset in &manifest.assets {
+            if asset.file.is_empty()
+                || asset.file.contains(['/', '\\'])
+                || asset.file.starts_with('.')
+            {
+                bail!("{}: `file` must be a plain file name", asset.name);
+            }
+        }
+        Ok(manifest)
+    }
+
+    fn save(&self, root: &Path) -> anyhow::Result<()> {
+        let body = toml::to_string(self)?;
+        std::fs::write(root.join(MANIFEST), format!("{MANIFEST_HEADER}\n{body}"))?;
+        Ok(())
+    }
+}
+
+/// `asset(path)` in templates: the URL to link `path` (relative to
+/// `static/`) with, see the module docs
+pub fn asset(path: &str) -> String {
+    if path == css::BUNDLE_ASSET {
+        if let Some(bundle) = css::current() {
+            return bundle.href();
+        }
+    }
+    if path == css::TAILWIND_ASSET {
+        if let Some(tailwind) = css::tailwind() {
+            return tailwind.href();
+        }
+    }
+    format!("/static/{path}")
+}
+
+/// The SRI hash of `bytes`, as used in `integrity` attributes and the CSP
+pub fn sri(bytes: &[u8]) -> String {
+    format!(
+        "sha384-{}",
+        base64::engine::general_purpose::STANDARD.encode(Sha384::digest(bytes))
+    )
+}
+
+/// Download, check and write every asset in the manifest under `root` (the
+/// project root), see the module docs. Returns a line per asset and per
+/// updated file, for the terminal.
+pub fn vendor(root: &Path, pin: bool) -> anyhow::Result<Vec<String>> {
+    let mut manifest = VendorManifest::load(root)?;
+    let agent = ureq::AgentBuilder::new().timeout(DOWNLOAD_TIMEOUT).build();
+
+    // Everything is downloaded and checked before anything is written
+    let mut downloads = Vec::with_capacity(manifest.assets.len());
+    for asset in &manifest.assets {
+        let mut bytes = Vec::new();
+        agent
+            .get(&asset.url)
+            .call()
+            .with_context(|| format!("downloading {} {}", asset.name, asset.version))?
+            .into_reader()
+            .take(MAX_ASSET_BYTES + 1)
+            .read_to_end(&mut bytes)
+            .with_context(|| format!("downloading {} {}", asset.name, asset.version))?;
+        if bytes.len() as u64 > MAX_ASSET_BYTES {
+            bail!("{}: larger than {MAX_ASSET_BYTES} bytes", asset.url);
+        }
+        let hash = sri(&bytes);
+        if asset.sha384.is_empty() {
+            if !pin {
+                bail!(
+                    "{} {} has no sha384 pin; rerun with --pin to record {hash}",
+                    asset.name,
+                    asset.version
+                );
+            }
+        } else if asset.sha384 != hash {
+            bail!(
+                "{} {}: checksum mismatch, pinned {} but downloaded {hash}",
+                asset.name,
+                asset.version,
+                asset.sha384
+            );
+        }
+        downloads.push((bytes, hash));
+    }
+
+    let mut report = Vec::new();
+    let mut repinned = false;
+    for (asset, (bytes, hash)) in manifest.assets.iter_mut().zip(downloads) {
+        let path = root.join(VENDOR_DIR).join(&asset.file);
+        let old = std::fs::read(&path).ok().map(|old| sri(&old));
+        if old.as_deref() == Some(hash.as_str()) {
+            report.push(format!("{} {}: unchanged", asset.name, asset.version));
+        } else {
+            std::fs::write(&path, &bytes).with_context(|| format!("writing {}", path.display()))?;
+            report.push(format!(
+                "{} {}: wrote {} ({hash})",
+                asset.name,
+                asset.version,
+                path.display()
+            ));
+            if let Some(old) = old {
+                for file in replace_sri(root, &old, &hash)? {
+                    report.push(format!("  updated SRI hash in {}", file.display()));
+                }
+            }
+        }
+        if asset.sha384.is_empty() {
+            asset.sha384 = hash;
+            repinned = true;
+        }
+    }
+    if repinned {
+        manifest.save(root)?;
+        report.push(format!("recorded new pins in {MANIFEST}"));
+    }
+    Ok(report)
+}
+
+/// Replace `old` with `new` in every source, template and snapshot file under
+/// [`SRI_DIRS`]; returns the files changed
+fn replace_sri(root: &Path, old: &str, new: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let mut changed = Vec::new();
+    let mut dirs: Vec<PathBuf> = SRI_DIRS.iter().map(|dir| root.join(dir)).collect();
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            let ext = path.extension().and_then(|ext| ext.to_str());
+            if !matches!(ext, Some("rs" | "html" | "snap")) {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path)?;
+            if text.contains(old) {
+                std::fs::write(&path, text.replace(old, new))?;
+                changed.push(path);
+            }
+        }
+    }
+    changed.sort();
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_head_integrity_matches_files() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let head = std::fs::read_to_string(root.join("templates/components/_head.html")).unwrap();
+        let manifest = VendorManifest::load(root).unwrap();
+        for asset in &manifest.assets {
+            let bytes = std::fs::read(root.join(VENDOR_DIR).join(&asset.file)).unwrap();
+            let tag = format!("src=\"/static/js/{}\"", asset.file);
+            let integrity = head[head.find(&tag).unwrap()..]
+                .split("integrity=\"")
+                .nth(1)
+                .and_then(|rest| rest.split('"').next())
+                .unwrap();
+            assert_eq!(integrity, sri(&bytes), "{}", asset.file);
+        }
+    }
+
+    #[test]
+    fn test_replace_sri() {
+        let root = std::env::temp_dir().join(format!("app-assets-test-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("templates/components")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let head = root.join("templates/components/_head.html");
+        std::fs::write(&head, r#"<script integrity="sha384-old"></script>"#).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "const X: &str = \"other\";").unwrap();
+
+        let changed = replace_sri(&root, "sha384-old", "sha384-new").unwrap();
+        assert_eq!(changed, vec![head.clone()]);
+        assert!(std::fs::read_to_string(&head)
+            .unwrap()
+            .contains("sha384-new"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
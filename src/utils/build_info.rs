@@ -0,0 +1,71 @@
+//! What's running: crate version plus what `build.rs` embedded — the git
+//! commit, the build time and the enabled cargo features.
+
+use serde::Serialize;
+
+/// Build information, see the module docs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BuildInfo {
+    /// Crate version, e.g. `0.1.0`
+    pub version: &'static str,
+    /// Full commit hash, `-dirty` when built with uncommitted changes;
+    /// `unknown` outside a git checkout without `GIT_SHA`
+    pub git_sha: &'static str,
+    /// First 7 characters of `git_sha`
+    pub git_sha_short: &'static str,
+    /// RFC 3339, UTC
+    pub built_at: &'static str,
+    /// Enabled cargo features, comma-separated (empty when none)
+    pub features: &'static str,
+}
+
+impl BuildInfo {
+    pub const fn current() -> Self {
+        const GIT_SHA: &str = env!("APP_GIT_SHA");
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_sha: GIT_SHA,
+            git_sha_short: short(GIT_SHA),
+            built_at: env!("APP_BUILT_AT"),
+            features: env!("APP_FEATURES"),
+        }
+    }
+
+    /// `0.1.0 (abc1234)`
+    pub fn describe(&self) -> String {
+        format!("{} ({})", self.version, self.git_sha_short)
+    }
+}
+
+impl Default for BuildInfo {
+    fn default() -> Self {
+        Self::current()
+    }
+}
+
+const fn short(sha: &'static str) -> &'static str {
+    match sha.split_at_checked(7) {
+        Some((short, _)) if sha.len() >= 40 => short,
+        _ => sha,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_sha() {
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(short(sha), "0123456");
+        assert_eq!(
+            short(concat!(
+                "0123456789abcdef0123456789abcdef01234567",
+                "-dirty"
+            )),
+            "0123456"
+        );
+        assert_eq!(short("unknown"), "unknown");
+        assert_eq!(BuildInfo::current().version, env!("CARGO_PKG_VERSION"));
+    }
+}
@@ -0,0 +1,115 @@
+//! Copy buttons — one click puts a value on the clipboard
+//!
+//! Like [`crate::utils::tabs`], the markup is built here and printed with
+//! `{{ button|safe }}`. The vendored `static/js/clipboard.js` does the
+//! copying and `static/js/copy.js` (both on every page) relabels the button
+//! "Copied" for a moment:
+//!
+//! ```ignore
+//! CopyButton::text("https://example.com/s/abc").label("Copy link")
+//! CopyButton::target("#snippet-item_list")   // an element's text, an input's value
+//! ```
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::utils::html::escape;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Source {
+    Text(String),
+    /// A selector
+    Target(String),
+}
+
+/// A `<button>` that copies a fixed text or another element's content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyButton {
+    source: Source,
+    label: String,
+    /// Accessible name when `label` alone doesn't say what gets copied
+    aria_label: String,
+}
+
+impl CopyButton {
+    /// Copies `text` itself
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::new(Source::Text(text.into()))
+    }
+
+    /// Copies the element `selector` names: its text, or an input's value
+    pub fn target(selector: impl Into<String>) -> Self {
+        Self::new(Source::Target(selector.into()))
+    }
+
+    fn new(source: Source) -> Self {
+        Self {
+            source,
+            label: "Copy".to_string(),
+            aria_label: String::new(),
+        }
+    }
+
+    /// The button's text, `Copy` by default
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    /// Names the button for screen readers, e.g. `Copy the share link`
+    pub fn aria_label(mut self, aria_label: impl Into<String>) -> Self {
+        self.aria_label = aria_label.into();
+        self
+    }
+}
+
+impl fmt::Display for CopyButton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (attr, value) = match &self.source {
+            Source::Text(text) => ("data-clipboard-text", text),
+            Source::Target(selector) => ("data-clipboard-target", selector),
+        };
+        let aria_label = if self.aria_label.is_empty() {
+            String::new()
+        } else {
+            format!(" aria-label=\"{}\"", escape(&self.aria_label))
+        };
+        write!(
+            f,
+            "<button type=\"button\" class=\"btn btn-outline-secondary btn-sm\" {attr}=\"{}\"{aria_label}>\
+             <i class=\"bi bi-clipboard\"></i> {}</button>",
+            escape(value),
+            escape(&self.label)
+        )
+    }
+}
+
+/// Rendered markup, as templates print it with `|safe`
+impl Serialize for CopyButton {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_button_escapes_its_source() {
+        assert_eq!(
+            CopyButton::text("a \"b\" & <c>").to_string(),
+            "<button type=\"button\" class=\"btn btn-outline-secondary btn-sm\" \
+             data-clipboard-text=\"a &quot;b&quot; &amp; &lt;c&gt;\">\
+             <i class=\"bi bi-clipboard\"></i> Copy</button>"
+        );
+        let button = CopyButton::target("#snippet-x")
+            .label("Copy HTML")
+            .aria_label("Copy the <x> snippet")
+            .to_string();
+        assert!(button.contains(
+            r##"data-clipboard-target="#snippet-x" aria-label="Copy the &lt;x&gt; snippet">"##
+        ));
+        assert!(button.ends_with("</i> Copy HTML</button>"));
+    }
+}
@@ -0,0 +1,324 @@
+//! Cookies — reading the `Cookie` header and writing `Set-Cookie`
+//!
+//! [`Cookies`] is the jar a request sent, parsed once: take it as an
+//! extractor in a handler, or build it from the headers in middleware.
+//! [`SetCookie`] builds a `Set-Cookie` value attribute by attribute, so
+//! `Secure`, `SameSite` and friends come from config instead of a format
+//! string.
+//!
+//! Signed cookies go through the app's [`TokenSigner`]: the value carries an
+//! HMAC tag (and expiry) under a purpose derived from the cookie name, so a
+//! client can read it but not change it, and a value signed for one cookie
+//! isn't accepted as another.
+//!
+//! Whether a cookie can be `Secure` depends on how the request arrived: the
+//! app speaks plain HTTP, so HTTPS means a TLS-terminating proxy in front,
+//! which only [`is_https`] believes when told to (`[server] trust_proxy`).
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap, HeaderValue, Uri},
+};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt;
+use std::time::Duration;
+
+use crate::services::TokenSigner;
+
+/// The `SameSite` attribute
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSite {
+    /// Never sent on cross-site requests
+    #[default]
+    Strict,
+    /// Sent on top-level cross-site navigations (following a link in)
+    Lax,
+    /// Always sent; browsers require `Secure` with it
+    None,
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        })
+    }
+}
+
+/// The cookies a request sent, by name. A name sent twice keeps the first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cookies {
+    pairs: Vec<(String, String)>,
+}
+
+impl Cookies {
+    /// Every `Cookie` header's `name=value` pairs; malformed ones are skipped
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let pairs = headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|pair| {
+                let (name, value) = pair.trim().split_once('=')?;
+                let value = value.trim().trim_matches('"');
+                (!name.is_empty()).then(|| (name.trim().to_string(), value.to_string()))
+            })
+            .collect();
+        Self { pairs }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The value of a cookie set with [`SetCookie::signed`], if its tag
+    /// checks out and it hasn't expired
+    pub fn get_signed(&self, name: &str, signer: &TokenSigner) -> Option<String> {
+        signer.verify(&purpose(name), self.get(name)?)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Cookies {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+/// Whether the request came over HTTPS: an `https` request URI, or with
+/// `trust_proxy`, the client-facing hop's `X-Forwarded-Proto` or
+/// `Forwarded: proto=`. Without `trust_proxy` those headers are ignored, as
+/// any client can send them.
+pub fn is_https(uri: &Uri, headers: &HeaderMap, trust_proxy: bool) -> bool {
+    if uri.scheme_str() == Some("https") {
+        return true;
+    }
+    if !trust_proxy {
+        return false;
+    }
+    let first = |name: header::HeaderName| {
+        let value = headers.get(name)?.to_str().ok()?;
+        Some(value.split(',').next()?.trim().to_string())
+    };
+    if let Some(proto) = first(header::FORWARDED).and_then(|hop| {
+        hop.split(';').find_map(|pair| {
+            let (key, value) = pair.trim().split_once('=')?;
+            key.eq_ignore_ascii_case("proto")
+                .then(|| value.trim_matches('"').to_string())
+        })
+    }) {
+        return proto.eq_ignore_ascii_case("https");
+    }
+    first(header::HeaderName::from_static("x-forwarded-proto"))
+        .is_some_and(|proto| proto.eq_ignore_ascii_case("https"))
+}
+
+/// [`TokenSigner`] purpose of a signed cookie's value
+fn purpose(name: &str) -> String {
+    format!("cookie:{name}")
+}
+
+/// One `Set-Cookie` value: `Path=/`, `HttpOnly`, `SameSite=Strict` and a
+/// session lifetime unless changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetCookie {
+    name: String,
+    value: String,
+    path: String,
+    max_age: Option<Duration>,
+    http_only: bool,
+    secure: bool,
+    same_site: SameSite,
+}
+
+impl SetCookie {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            path: "/".into(),
+            max_age: None,
+            http_only: true,
+            secure: false,
+            same_site: SameSite::Strict,
+        }
+    }
+
+    /// A cookie whose value [`Cookies::get_signed`] accepts until `ttl` is up
+    /// (never, for `None`)
+    pub fn signed(
+        name: impl Into<String>,
+        value: &str,
+        signer: &TokenSigner,
+        ttl: Option<Duration>,
+    ) -> Self {
+        let name = name.into();
+        let value = signer.sign(&purpose(&name), value, ttl);
+        Self::new(name, value)
+    }
+
+    /// Tells the browser to drop `name` (set on the same path)
+    pub fn removal(name: impl Into<String>) -> Self {
+        Self::new(name, "").max_age(Duration::ZERO)
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Whether scripts can't read it (default `true`)
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// Whether it's only sent over HTTPS (default `false`). Browsers treat
+    /// `localhost` as secure, so this works in development too.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    pub fn header_value(&self) -> HeaderValue {
+        // Names and values come from the app, not the client; anything
+        // unsendable is a bug
+        HeaderValue::try_from(self.to_string()).expect("cookie is a valid header value")
+    }
+
+    /// Add to a response's headers, next to any other `Set-Cookie`
+    pub fn append_to(&self, headers: &mut HeaderMap) {
+        headers.append(header::SET_COOKIE, self.header_value());
+    }
+}
+
+impl fmt::Display for SetCookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}; Path={}", self.name, self.value, self.path)?;
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+        if self.secure {
+            f.write_str("; Secure")?;
+        }
+        write!(f, "; SameSite={}", self.same_site)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cookie_headers() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            header::COOKIE,
+            "theme=dark; __Host-sid=abc=; x".parse().unwrap(),
+        );
+        headers.append(header::COOKIE, "lang=\"en\"; theme=light".parse().unwrap());
+        let cookies = Cookies::from_headers(&headers);
+
+        assert_eq!(cookies.get("__Host-sid"), Some("abc="));
+        assert_eq!(cookies.get("theme"), Some("dark"));
+        assert_eq!(cookies.get("lang"), Some("en"));
+        assert_eq!(cookies.get("x"), None);
+        assert_eq!(Cookies::from_headers(&HeaderMap::new()).get("theme"), None);
+    }
+
+    #[test]
+    fn test_set_cookie_attributes() {
+        let cookie = SetCookie::new("__Host-sid", "abc")
+            .max_age(Duration::from_secs(3600))
+            .secure(true)
+            .same_site(SameSite::Lax);
+        assert_eq!(
+            cookie.to_string(),
+            "__Host-sid=abc; Path=/; Max-Age=3600; HttpOnly; Secure; SameSite=Lax"
+        );
+        assert_eq!(
+            SetCookie::removal("theme").http_only(false).to_string(),
+            "theme=; Path=/; Max-Age=0; SameSite=Strict"
+        );
+    }
+
+    #[test]
+    fn test_is_https() {
+        let uri: Uri = "/about".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        assert!(!is_https(&uri, &headers, true));
+        assert!(is_https(
+            &"https://example.com/".parse().unwrap(),
+            &headers,
+            false
+        ));
+
+        headers.insert("x-forwarded-proto", "https, http".parse().unwrap());
+        assert!(is_https(&uri, &headers, true));
+        assert!(!is_https(&uri, &headers, false));
+
+        // `Forwarded` wins over `X-Forwarded-Proto`
+        headers.insert(
+            header::FORWARDED,
+            "for=192.0.2.1;proto=http".parse().unwrap(),
+        );
+        assert!(!is_https(&uri, &headers, true));
+        headers.insert(
+            header::FORWARDED,
+            "for=192.0.2.1; Proto=HTTPS".parse().unwrap(),
+        );
+        assert!(is_https(&uri, &headers, true));
+    }
+
+    #[test]
+    fn test_signed_cookies() {
+        let signer = TokenSigner::new("secret");
+        let cookie = SetCookie::signed("prefs", "compact", &signer, None);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            format!("prefs={}", cookie.value).parse().unwrap(),
+        );
+        let cookies = Cookies::from_headers(&headers);
+        assert_eq!(
+            cookies.get_signed("prefs", &signer).as_deref(),
+            Some("compact")
+        );
+        assert!(cookies
+            .get_signed("prefs", &TokenSigner::new("other"))
+            .is_none());
+
+        // The same value under another name doesn't verify
+        headers.insert(
+            header::COOKIE,
+            format!("theme={}", cookie.value).parse().unwrap(),
+        );
+        assert!(Cookies::from_headers(&headers)
+            .get_signed("theme", &signer)
+            .is_none());
+    }
+}
@@ -0,0 +1,228 @@
+//! CSS bundle — `static/css` as one minified, fingerprinted stylesheet
+//!
+//! At startup every `*.css` file in `static/css` is run through lightningcss
+//! in file-name order: nesting is lowered for [`targets`], everything is
+//! minified, and the results are concatenated into one bundle named after
+//! its content, e.g. `app.3f2a9c1b.css`. [`serve`] answers for it under
+//! `/static/dist/` with a year-long `immutable` cache, since a change gets a
+//! new name. Templates link it through the asset helper,
+//! `asset("css/app.css")` (see [`crate::utils::assets::asset`]).
+//!
+//! Custom properties (`var(--…)`) pass through as they are; every target
+//! supports them. Debug builds rebuild the bundle when live reload sees a
+//! change under `static/`, before the browser is told to reload.
+//!
+//! With the `tailwind` feature, `build.rs` compiles the Tailwind utilities
+//! the templates use into a second stylesheet, embedded in the binary and
+//! served the same way as `tailwind.<hash>.css` (`asset("css/tailwind.css")`).
+//! It changes only when the binary is rebuilt.
+
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use anyhow::Context;
+use axum::{
+    extract,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use lightningcss::{
+    printer::PrinterOptions,
+    stylesheet::{MinifyOptions, ParserFlags, ParserOptions, StyleSheet},
+    targets::{Browsers, Targets},
+};
+use sha2::{Digest, Sha256};
+
+/// What the bundle is asked for as, through `asset()`
+pub const BUNDLE_ASSET: &str = "css/app.css";
+
+/// What the Tailwind stylesheet is asked for as, through `asset()`
+pub const TAILWIND_ASSET: &str = "css/tailwind.css";
+
+/// Whether this build has the Tailwind stylesheet; `globals.tailwind` in
+/// templates
+pub const TAILWIND: bool = cfg!(feature = "tailwind");
+
+/// Source directory, relative to the working directory
+pub const SOURCE_DIR: &str = "static/css";
+
+/// URL prefix of the bundle
+const DIST_PREFIX: &str = "/static/dist/";
+
+static CURRENT: RwLock<Option<Arc<CssBundle>>> = RwLock::new(None);
+
+/// A built bundle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CssBundle {
+    /// `app.<first 8 hex digits of its SHA-256>.css`
+    pub file_name: String,
+    pub css: String,
+}
+
+impl CssBundle {
+    /// Where the bundle is served
+    pub fn href(&self) -> String {
+        format!("{DIST_PREFIX}{}", self.file_name)
+    }
+}
+
+/// Browsers the output has to work in; nesting is lowered for these
+fn targets() -> Targets {
+    // Versions are encoded as major << 16 | minor << 8 | patch
+    Targets::from(Browsers {
+        chrome: Some(100 << 16),
+        edge: Some(100 << 16),
+        firefox: Some(100 << 16),
+        safari: Some(15 << 16),
+        ios_saf: Some(15 << 16),
+        ..Browsers::default()
+    })
+}
+
+/// Bundle every `*.css` file in `dir`, see the module docs
+pub fn bundle(dir: &Path) -> anyhow::Result<CssBundle> {
+    let mut files: Vec<_> = std::fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "css"))
+        .collect();
+    files.sort();
+
+    let mut css = String::new();
+    for path in files {
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        css.push_str(&minify(&path.display().to_string(), &source)?);
+        css.push('\n');
+    }
+    Ok(fingerprinted("app", css))
+}
+
+/// `css` named `<stem>.<first 8 hex digits of its SHA-256>.css`
+fn fingerprinted(stem: &str, css: String) -> CssBundle {
+    let hash = hex::encode(Sha256::digest(css.as_bytes()));
+    CssBundle {
+        file_name: format!("{stem}.{}.css", &hash[..8]),
+        css,
+    }
+}
+
+/// The Tailwind stylesheet `build.rs` compiled into this build
+#[cfg(feature = "tailwind")]
+pub fn tailwind() -> Option<&'static CssBundle> {
+    static TAILWIND_BUNDLE: std::sync::OnceLock<CssBundle> = std::sync::OnceLock::new();
+    Some(TAILWIND_BUNDLE.get_or_init(|| {
+        fingerprinted(
+            "tailwind",
+            include_str!(concat!(env!("OUT_DIR"), "/tailwind.css")).to_string(),
+        )
+    }))
+}
+
+/// The Tailwind stylesheet `build.rs` compiled into this build
+#[cfg(not(feature = "tailwind"))]
+pub fn tailwind() -> Option<&'static CssBundle> {
+    None
+}
+
+fn minify(filename: &str, source: &str) -> anyhow::Result<String> {
+    let mut sheet = StyleSheet::parse(
+        source,
+        ParserOptions {
+            filename: filename.to_string(),
+            flags: ParserFlags::NESTING,
+            ..ParserOptions::default()
+        },
+    )
+    .map_err(|e| anyhow::anyhow!("{filename}: {e}"))?;
+    sheet
+        .minify(MinifyOptions {
+            targets: targets(),
+            ..MinifyOptions::default()
+        })
+        .map_err(|e| anyhow::anyhow!("{filename}: {e}"))?;
+    let printed = sheet
+        .to_css(PrinterOptions {
+            minify: true,
+            targets: targets(),
+            ..PrinterOptions::default()
+        })
+        .map_err(|e| anyhow::anyhow!("{filename}: {e}"))?;
+    Ok(printed.code)
+}
+
+/// Build the bundle from [`SOURCE_DIR`] and make it the one templates link
+/// to and [`serve`] answers with
+pub fn install() -> anyhow::Result<Arc<CssBundle>> {
+    let bundle = Arc::new(bundle(Path::new(SOURCE_DIR))?);
+    *CURRENT.write().unwrap() = Some(bundle.clone());
+    Ok(bundle)
+}
+
+/// The installed bundle; `None` outside the server (e.g. snapshot tests)
+pub fn current() -> Option<Arc<CssBundle>> {
+    CURRENT.read().unwrap().clone()
+}
+
+/// `GET /static/dist/:file` — the installed bundle or the Tailwind
+/// stylesheet, cached for good
+pub async fn serve(extract::Path(file): extract::Path<String>) -> Response {
+    let css = match (current(), tailwind()) {
+        (Some(bundle), _) if bundle.file_name == file => bundle.css.clone(),
+        (_, Some(tailwind)) if tailwind.file_name == file => tailwind.css.clone(),
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+    (
+        [
+            (header::CONTENT_TYPE, "text/css; charset=utf-8"),
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        css,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_lowers_nesting_and_fingerprints() {
+        let dir = std::env::temp_dir().join(format!("app-css-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("b.css"),
+            ".card {\n  color: red;\n  & .title { font-weight: 600; }\n}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("a.css"), ":root {\n  --brand: #6366f1;\n}\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not css").unwrap();
+
+        let bundle = bundle(&dir).unwrap();
+        assert_eq!(
+            bundle.css,
+            ":root{--brand:#6366f1}\n.card{color:red}.card .title{font-weight:600}\n"
+        );
+        assert!(bundle.file_name.starts_with("app.") && bundle.file_name.len() == 16);
+        assert_eq!(bundle.href(), format!("/static/dist/{}", bundle.file_name));
+
+        // Same input, same name; different input, different name
+        assert_eq!(super::bundle(&dir).unwrap().file_name, bundle.file_name);
+        std::fs::write(dir.join("a.css"), ":root { --brand: #000; }").unwrap();
+        assert_ne!(super::bundle(&dir).unwrap().file_name, bundle.file_name);
+
+        // Errors name the file
+        std::fs::write(dir.join("c.css"), ".broken { color: red; } }").unwrap();
+        let error = super::bundle(&dir).unwrap_err().to_string();
+        assert!(error.contains("c.css"), "{error}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_static_css_bundles() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(SOURCE_DIR);
+        let bundle = bundle(&dir).unwrap();
+        assert!(bundle.css.contains(".tag-chip:hover{"));
+        assert!(bundle.css.contains(".bi-house"));
+    }
+}
@@ -0,0 +1,166 @@
+//! Favicons — the icon set `build.rs` renders from `static/favicon.svg`
+//!
+//! One source SVG becomes every size browsers and home screens ask for: 16
+//! and 32 px PNGs for tabs, 180 px for iOS, 192 and 512 px for the web app
+//! manifest (see [`crate::handlers::pwa`]), and a `favicon.ico` (16, 32 and
+//! 48 px) for clients that only try `/favicon.ico`. Changing the SVG and
+//! rebuilding is all it takes.
+//!
+//! The files are embedded in the binary under names carrying their hash, so
+//! [`serve`] answers `/static/icons/` with a year-long `immutable` cache.
+//! `/favicon.ico` keeps its fixed name and is cached for a day.
+//! `favicon_links()` in templates emits the `<link>` tags, see [`links`].
+
+use axum::{
+    extract,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// One file of the set
+#[derive(Debug)]
+pub struct Favicon {
+    /// Name before fingerprinting: `favicon.svg`, `icon-32.png`, `favicon.ico`
+    pub name: &'static str,
+    /// Name it's served under, e.g. `icon-32.1a2b3c4d.png`
+    pub file_name: &'static str,
+    /// Width and height in pixels; 0 for the SVG and the `.ico`
+    pub size: u32,
+    pub content_type: &'static str,
+    pub bytes: &'static [u8],
+}
+
+impl Favicon {
+    /// Where the file is served
+    pub fn href(&self) -> String {
+        format!("{ICONS_PREFIX}{}", self.file_name)
+    }
+}
+
+/// Every file `build.rs` rendered
+pub static FAVICONS: &[Favicon] = include!(concat!(env!("OUT_DIR"), "/favicons.rs"));
+
+/// URL prefix of the set
+const ICONS_PREFIX: &str = "/static/icons/";
+
+/// Fingerprinted names never change
+const ICON_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// `/favicon.ico` is asked for by a fixed name
+const ICO_CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// What [`links`] emits: a file of the set and its tag
+const LINKS: &[(&str, &str)] = &[
+    (
+        "favicon.svg",
+        r#"<link rel="icon" type="image/svg+xml" href="{href}">"#,
+    ),
+    (
+        "icon-32.png",
+        r#"<link rel="icon" type="image/png" sizes="32x32" href="{href}">"#,
+    ),
+    (
+        "icon-16.png",
+        r#"<link rel="icon" type="image/png" sizes="16x16" href="{href}">"#,
+    ),
+    (
+        "icon-180.png",
+        r#"<link rel="apple-touch-icon" sizes="180x180" href="{href}">"#,
+    ),
+];
+
+/// The file built as `name` (`icon-192.png`)
+pub fn get(name: &str) -> Option<&'static Favicon> {
+    FAVICONS.iter().find(|favicon| favicon.name == name)
+}
+
+/// The files pages link to, in [`links`] order
+pub fn linked() -> impl Iterator<Item = &'static Favicon> {
+    LINKS.iter().filter_map(|(name, _)| get(name))
+}
+
+/// `favicon_links()` in templates: the `<link>` tags for the set, for
+/// `components/_head.html`
+pub fn links() -> String {
+    LINKS
+        .iter()
+        .filter_map(|(name, tag)| get(name).map(|favicon| tag.replace("{href}", &favicon.href())))
+        .map(|tag| tag + "\n")
+        .collect()
+}
+
+/// `GET /static/icons/:file` — a file of the set, cached for good
+pub async fn serve(extract::Path(file): extract::Path<String>) -> Response {
+    match FAVICONS.iter().find(|favicon| favicon.file_name == file) {
+        Some(favicon) => respond(favicon, ICON_CACHE_CONTROL),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// `GET /favicon.ico`
+pub async fn favicon_ico() -> Response {
+    match get("favicon.ico") {
+        Some(favicon) => respond(favicon, ICO_CACHE_CONTROL),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+fn respond(favicon: &Favicon, cache_control: &'static str) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, favicon.content_type),
+            (header::CACHE_CONTROL, cache_control),
+        ],
+        favicon.bytes,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    #[test]
+    fn test_favicon_set() {
+        for size in [16, 32, 48, 180, 192, 512] {
+            let favicon = get(&format!("icon-{size}.png")).unwrap();
+            assert_eq!(favicon.size, size);
+            assert!(favicon.bytes.starts_with(PNG_SIGNATURE));
+            // IHDR: width and height, big-endian, at bytes 16..24
+            assert_eq!(favicon.bytes[16..20], size.to_be_bytes());
+            assert_eq!(favicon.bytes[20..24], size.to_be_bytes());
+            assert!(favicon.file_name.starts_with(&format!("icon-{size}.")));
+            assert_eq!(
+                favicon.file_name.len(),
+                format!("icon-{size}.png").len() + 9
+            );
+        }
+        assert!(get("favicon.svg").unwrap().bytes.starts_with(b"<svg"));
+    }
+
+    #[test]
+    fn test_favicon_ico() {
+        let ico = get("favicon.ico").unwrap().bytes;
+        // Type 1 (icon), three images: 16, 32 and 48 px PNGs
+        assert_eq!(ico[..6], [0, 0, 1, 0, 3, 0]);
+        for (i, size) in [16u8, 32, 48].into_iter().enumerate() {
+            let entry = &ico[6 + 16 * i..6 + 16 * (i + 1)];
+            assert_eq!(entry[..2], [size, size]);
+            let length = u32::from_le_bytes(entry[8..12].try_into().unwrap()) as usize;
+            let offset = u32::from_le_bytes(entry[12..16].try_into().unwrap()) as usize;
+            assert!(ico[offset..offset + length].starts_with(PNG_SIGNATURE));
+        }
+    }
+
+    #[test]
+    fn test_links() {
+        let links = links();
+        assert_eq!(links.lines().count(), 4);
+        assert!(links.contains(&format!(
+            r#"<link rel="apple-touch-icon" sizes="180x180" href="/static/icons/{}">"#,
+            get("icon-180.png").unwrap().file_name
+        )));
+    }
+}
@@ -0,0 +1,554 @@
+//! Form fields — labelled, accessible controls built in Rust
+//!
+//! askama and minijinja disagree on how macros are imported and called, so
+//! form controls are built here instead: a handler describes each field with
+//! [`Field`] and the template prints it with `{{ field|safe }}`. Every field
+//! renders the same way in both engines:
+//!
+//! - a `<label for>` (or a `<legend>` for radio groups) tied to the control's id;
+//! - an optional hint, linked through `aria-describedby`;
+//! - an error slot, `<div id="<id>-error">`, always present so HTMX can target
+//!   it. A field with an error is marked `aria-invalid="true"` and points
+//!   `aria-describedby` at the message.
+//!
+//! Everything computed at runtime (values, labels, errors) is escaped.
+//!
+//! Forms carry their CSRF token by including `components/_csrf.html`: HTMX
+//! requests send it as the `X-CSRF-Token` header from `<body hx-headers>`,
+//! and a form posted without JavaScript sends it as the [`CSRF_FIELD`] field,
+//! which the CSRF middleware accepts in place of the header.
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::utils::html::escape;
+
+/// Name of the hidden CSRF input rendered by `components/_csrf.html`
+pub const CSRF_FIELD: &str = "_csrf";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Kind {
+    /// `<input type="...">`: text, email, password, url, number, date…
+    Input(&'static str),
+    Textarea,
+    Select,
+    Checkbox {
+        checked: bool,
+    },
+    Radios,
+    File,
+    /// Text input with a listbox of suggestions from a source
+    Combobox {
+        src: &'static str,
+    },
+    /// Month grid from `/partials/datepicker` over a hidden input
+    DatePicker,
+}
+
+/// One `<option>` or radio button
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Choice {
+    value: String,
+    label: String,
+}
+
+/// A form control with its label, hint and error slot; see the module docs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    kind: Kind,
+    name: &'static str,
+    id: String,
+    label: String,
+    value: String,
+    hint: String,
+    error: String,
+    required: bool,
+    // Extra attributes on the control, already escaped; `None` for boolean ones
+    attrs: Vec<(&'static str, Option<String>)>,
+    choices: Vec<Choice>,
+}
+
+impl Field {
+    fn new(kind: Kind, name: &'static str, label: impl Into<String>) -> Self {
+        Self {
+            kind,
+            name,
+            id: format!("field-{name}"),
+            label: label.into(),
+            value: String::new(),
+            hint: String::new(),
+            error: String::new(),
+            required: false,
+            attrs: Vec::new(),
+            choices: Vec::new(),
+        }
+    }
+
+    /// `<input type="text">`
+    pub fn text(name: &'static str, label: impl Into<String>) -> Self {
+        Self::input("text", name, label)
+    }
+
+    /// Any other single-line `<input>`: `email`, `password`, `url`, `number`…
+    pub fn input(input_type: &'static str, name: &'static str, label: impl Into<String>) -> Self {
+        Self::new(Kind::Input(input_type), name, label)
+    }
+
+    pub fn textarea(name: &'static str, label: impl Into<String>) -> Self {
+        Self::new(Kind::Textarea, name, label)
+    }
+
+    /// `<select>` over `(value, label)` pairs; [`value`](Self::value) picks
+    /// the selected one
+    pub fn select<V: Into<String>, L: Into<String>>(
+        name: &'static str,
+        label: impl Into<String>,
+        options: impl IntoIterator<Item = (V, L)>,
+    ) -> Self {
+        Self::new(Kind::Select, name, label).choices(options)
+    }
+
+    /// A single checkbox, submitted as `name=1` when ticked
+    pub fn checkbox(name: &'static str, label: impl Into<String>, checked: bool) -> Self {
+        Self::new(Kind::Checkbox { checked }, name, label)
+    }
+
+    /// Radio buttons in a `<fieldset>` captioned by `legend`;
+    /// [`value`](Self::value) picks the checked one
+    pub fn radios<V: Into<String>, L: Into<String>>(
+        name: &'static str,
+        legend: impl Into<String>,
+        options: impl IntoIterator<Item = (V, L)>,
+    ) -> Self {
+        Self::new(Kind::Radios, name, legend).choices(options)
+    }
+
+    /// `<input type="file">`; the form needs `hx-encoding="multipart/form-data"`
+    pub fn file(name: &'static str, label: impl Into<String>) -> Self {
+        Self::new(Kind::File, name, label)
+    }
+
+    /// A text input suggesting values from the autocomplete source `src`
+    /// (see [`crate::services::autocomplete`]) in an ARIA combobox. The page
+    /// loads `static/js/combobox.js`, which shows the list and handles the
+    /// keyboard; without JavaScript it's a plain text input.
+    pub fn combobox(name: &'static str, label: impl Into<String>, src: &'static str) -> Self {
+        Self::new(Kind::Combobox { src }, name, label)
+    }
+
+    /// A `YYYY-MM-DD` date chosen from a month grid that HTMX loads from
+    /// `/partials/datepicker` (see [`crate::handlers::calendar`]), with month
+    /// and day names in the visitor's language. Until then, and without
+    /// JavaScript, it's an `<input type="date">`.
+    pub fn date_picker(name: &'static str, label: impl Into<String>) -> Self {
+        Self::new(Kind::DatePicker, name, label)
+    }
+
+    fn choices<V: Into<String>, L: Into<String>>(
+        mut self,
+        options: impl IntoIterator<Item = (V, L)>,
+    ) -> Self {
+        self.choices = options
+            .into_iter()
+            .map(|(value, label)| Choice {
+                value: value.into(),
+                label: label.into(),
+            })
+            .collect();
+        self
+    }
+
+    /// Replace the default id, `field-<name>`; needed when two forms on a
+    /// page share a field name
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// The submitted value, so a form re-rendered with errors keeps it
+    pub fn value(mut self, value: impl Into<String>) -> Self {
+        self.value = value.into();
+        self
+    }
+
+    /// Help text under the control
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = hint.into();
+        self
+    }
+
+    /// Validation message for the error slot; empty clears it
+    pub fn error(mut self, error: impl Into<String>) -> Self {
+        self.error = error.into();
+        self
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Any other attribute on the control: `maxlength`, `autocomplete`,
+    /// `placeholder`, `rows`, `accept`, `hx-*`…
+    pub fn attr(mut self, name: &'static str, value: impl fmt::Display) -> Self {
+        self.attrs.push((name, Some(escape(&value.to_string()))));
+        self
+    }
+
+    /// A boolean attribute on the control, e.g. `multiple` or `autofocus`
+    pub fn flag(mut self, name: &'static str) -> Self {
+        self.attrs.push((name, None));
+        self
+    }
+
+    pub fn has_error(&self) -> bool {
+        !self.error.is_empty()
+    }
+
+    fn hint_id(&self) -> String {
+        format!("{}-hint", self.id)
+    }
+
+    fn error_id(&self) -> String {
+        format!("{}-error", self.id)
+    }
+
+    /// `id`, `name`, the extra attributes and the ARIA wiring shared by
+    /// every control
+    fn write_common(&self, out: &mut String) {
+        out.push_str(&format!(
+            r#" id="{}" name="{}""#,
+            escape(&self.id),
+            escape(self.name)
+        ));
+        for (name, value) in &self.attrs {
+            match value {
+                Some(value) => out.push_str(&format!(r#" {name}="{value}""#)),
+                None => out.push_str(&format!(" {name}")),
+            }
+        }
+        if self.required {
+            out.push_str(" required");
+        }
+        if self.has_error() {
+            out.push_str(r#" aria-invalid="true""#);
+        }
+        let described = self.described_by();
+        if !described.is_empty() {
+            out.push_str(&format!(r#" aria-describedby="{}""#, escape(&described)));
+        }
+    }
+
+    /// The hint and error ids, space-separated, for `aria-describedby`
+    fn described_by(&self) -> String {
+        [
+            (!self.hint.is_empty()).then(|| self.hint_id()),
+            self.has_error().then(|| self.error_id()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+
+    fn write_label(&self, out: &mut String) {
+        out.push_str(&format!(
+            "\n    <label for=\"{}\" class=\"form-label\">{}</label>",
+            escape(&self.id),
+            escape(&self.label)
+        ));
+    }
+
+    fn write_control(&self, out: &mut String) {
+        let invalid = if self.has_error() { " is-invalid" } else { "" };
+        match &self.kind {
+            Kind::Input(input_type) => {
+                self.write_label(out);
+                out.push_str(&format!(
+                    "\n    <input type=\"{input_type}\" class=\"form-control{invalid}\""
+                ));
+                self.write_common(out);
+                if !self.value.is_empty() {
+                    out.push_str(&format!(r#" value="{}""#, escape(&self.value)));
+                }
+                out.push('>');
+            }
+            Kind::File => {
+                self.write_label(out);
+                out.push_str(&format!(
+                    "\n    <input type=\"file\" class=\"form-control{invalid}\""
+                ));
+                self.write_common(out);
+                out.push('>');
+            }
+            Kind::Combobox { src } => {
+                let list_id = escape(&format!("{}-list", self.id));
+                self.write_label(out);
+                out.push_str("\n    <div class=\"combobox\">");
+                out.push_str(&format!(
+                    "\n        <input type=\"text\" class=\"form-control{invalid}\" role=\"combobox\" \
+                     aria-autocomplete=\"list\" aria-expanded=\"false\" aria-controls=\"{list_id}\" autocomplete=\"off\" \
+                     hx-get=\"/partials/autocomplete?src={}&amp;field={}\" \
+                     hx-trigger=\"input changed delay:200ms, focus\" hx-target=\"#{list_id}\" hx-sync=\"this:replace\"",
+                    escape(src),
+                    escape(self.name)
+                ));
+                self.write_common(out);
+                if !self.value.is_empty() {
+                    out.push_str(&format!(r#" value="{}""#, escape(&self.value)));
+                }
+                out.push_str(&format!(
+                    ">\n        <ul id=\"{list_id}\" class=\"combobox-list\" role=\"listbox\" aria-label=\"{}\" hidden></ul>\
+                     \n        <div class=\"visually-hidden\" role=\"status\" data-combobox-status></div>\
+                     \n    </div>",
+                    escape(&self.label)
+                ));
+            }
+            Kind::DatePicker => {
+                // The picker replaces the wrapper and the input with its own
+                // group, labelled by the label's id
+                let described = self.described_by();
+                let params: Vec<(&str, &str)> = [
+                    ("field", self.id.as_str()),
+                    ("name", self.name),
+                    ("describedby", &described),
+                    ("selected", &self.value),
+                ]
+                .into_iter()
+                .filter(|(_, value)| !value.is_empty())
+                .collect();
+                let query = serde_urlencoded::to_string(params).unwrap_or_default();
+                out.push_str(&format!(
+                    "\n    <label id=\"{id}-label\" for=\"{id}\" class=\"form-label\">{}</label>\
+                     \n    <div id=\"{id}-picker\" class=\"datepicker-loading\" \
+                     hx-get=\"/partials/datepicker?{}\" hx-trigger=\"load\" hx-swap=\"outerHTML\">\
+                     \n        <input type=\"date\" class=\"form-control{invalid}\"",
+                    escape(&self.label),
+                    escape(&query),
+                    id = escape(&self.id),
+                ));
+                self.write_common(out);
+                if !self.value.is_empty() {
+                    out.push_str(&format!(r#" value="{}""#, escape(&self.value)));
+                }
+                out.push_str(">\n    </div>");
+            }
+            Kind::Textarea => {
+                self.write_label(out);
+                out.push_str(&format!("\n    <textarea class=\"form-control{invalid}\""));
+                self.write_common(out);
+                out.push_str(&format!(">{}</textarea>", escape(&self.value)));
+            }
+            Kind::Select => {
+                self.write_label(out);
+                out.push_str(&format!("\n    <select class=\"form-control{invalid}\""));
+                self.write_common(out);
+                out.push('>');
+                for choice in &self.choices {
+                    let selected = if choice.value == self.value {
+                        " selected"
+                    } else {
+                        ""
+                    };
+                    out.push_str(&format!(
+                        "\n        <option value=\"{}\"{selected}>{}</option>",
+                        escape(&choice.value),
+                        escape(&choice.label)
+                    ));
+                }
+                out.push_str("\n    </select>");
+            }
+            Kind::Checkbox { checked } => {
+                out.push_str("\n    <div class=\"form-check\">");
+                out.push_str(&format!(
+                    "\n        <input type=\"checkbox\" class=\"form-check-input{invalid}\" value=\"1\""
+                ));
+                self.write_common(out);
+                if *checked {
+                    out.push_str(" checked");
+                }
+                out.push_str(&format!(
+                    ">\n        <label for=\"{}\" class=\"text-sm\">{}</label>\n    </div>",
+                    escape(&self.id),
+                    escape(&self.label)
+                ));
+            }
+            Kind::Radios => {
+                // The group carries the ARIA wiring; each radio only its own id
+                out.push_str(&format!(
+                    "\n    <fieldset id=\"{}\" class=\"form-fieldset\"",
+                    escape(&self.id)
+                ));
+                if self.has_error() {
+                    out.push_str(&format!(
+                        r#" aria-invalid="true" aria-describedby="{}""#,
+                        escape(&self.error_id())
+                    ));
+                }
+                out.push_str(&format!(
+                    ">\n        <legend class=\"form-label\">{}</legend>",
+                    escape(&self.label)
+                ));
+                for (i, choice) in self.choices.iter().enumerate() {
+                    let id = format!("{}-{i}", self.id);
+                    let checked = if choice.value == self.value {
+                        " checked"
+                    } else {
+                        ""
+                    };
+                    out.push_str(&format!(
+                        "\n        <div class=\"form-check\">\
+                         \n            <input type=\"radio\" id=\"{id}\" name=\"{}\" value=\"{}\" class=\"form-check-input\"{}{checked}>\
+                         \n            <label for=\"{id}\" class=\"text-sm\">{}</label>\
+                         \n        </div>",
+                        escape(self.name),
+                        escape(&choice.value),
+                        if self.required { " required" } else { "" },
+                        escape(&choice.label),
+                        id = escape(&id),
+                    ));
+                }
+                out.push_str("\n    </fieldset>");
+            }
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::from(r#"<div class="form-field mb-3">"#);
+        self.write_control(&mut out);
+        if !self.hint.is_empty() {
+            out.push_str(&format!(
+                "\n    <div id=\"{}\" class=\"form-hint\">{}</div>",
+                escape(&self.hint_id()),
+                escape(&self.hint)
+            ));
+        }
+        out.push_str(&format!(
+            "\n    <div id=\"{}\" class=\"field-error\" aria-live=\"polite\">{}</div>\n</div>",
+            escape(&self.error_id()),
+            escape(&self.error)
+        ));
+        f.write_str(&out)
+    }
+}
+
+/// Rendered markup, as templates print it with `|safe`
+impl Serialize for Field {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The [`CSRF_FIELD`] value in a urlencoded form body
+pub fn csrf_token_in(body: &[u8]) -> Option<String> {
+    serde_urlencoded::from_bytes::<Vec<(String, String)>>(body)
+        .ok()?
+        .into_iter()
+        .find_map(|(name, value)| (name == CSRF_FIELD).then_some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csrf_token_in() {
+        assert_eq!(
+            csrf_token_in(b"name=Ada&_csrf=abc.d%2Be&message=hi").as_deref(),
+            Some("abc.d+e")
+        );
+        assert_eq!(csrf_token_in(b"name=Ada"), None);
+        assert_eq!(csrf_token_in(&[0xff, 0xfe]), None);
+    }
+
+    #[test]
+    fn test_text_field_wires_label_hint_and_error() {
+        let field = Field::input("email", "email", "Email")
+            .id("contact-email")
+            .value("a\"b")
+            .hint("We reply here")
+            .error("That email address doesn't look right")
+            .required()
+            .attr("maxlength", 254)
+            .to_string();
+        assert!(field.contains(r#"<label for="contact-email" class="form-label">Email</label>"#));
+        assert!(field.contains(
+            r#"<input type="email" class="form-control is-invalid" id="contact-email" name="email" maxlength="254" required aria-invalid="true" aria-describedby="contact-email-hint contact-email-error" value="a&quot;b">"#
+        ));
+        assert!(
+            field.contains(r#"<div id="contact-email-hint" class="form-hint">We reply here</div>"#)
+        );
+        assert!(field.contains(
+            r#"<div id="contact-email-error" class="field-error" aria-live="polite">That email address doesn&#x27;t look right</div>"#
+        ));
+    }
+
+    #[test]
+    fn test_valid_field_keeps_an_empty_error_slot() {
+        let field = Field::textarea("message", "Message").to_string();
+        assert!(!field.contains("aria-invalid"));
+        assert!(!field.contains("aria-describedby"));
+        assert!(field.contains(
+            r#"<div id="field-message-error" class="field-error" aria-live="polite"></div>"#
+        ));
+    }
+
+    #[test]
+    fn test_choices_mark_the_value_and_escape() {
+        let select = Field::select("size", "Size", [("s", "Small"), ("m", "<Medium>")])
+            .value("m")
+            .to_string();
+        assert!(select.contains(r#"<option value="s">Small</option>"#));
+        assert!(select.contains(r#"<option value="m" selected>&lt;Medium&gt;</option>"#));
+
+        let radios = Field::radios("plan", "Plan", [("free", "Free"), ("pro", "Pro")])
+            .value("pro")
+            .error("Pick one")
+            .to_string();
+        assert!(radios.contains(r#"<fieldset id="field-plan" class="form-fieldset" aria-invalid="true" aria-describedby="field-plan-error">"#));
+        assert!(radios.contains(r#"<legend class="form-label">Plan</legend>"#));
+        assert!(radios.contains(
+            r#"id="field-plan-1" name="plan" value="pro" class="form-check-input" checked>"#
+        ));
+    }
+
+    #[test]
+    fn test_combobox_controls_its_listbox() {
+        let field = Field::combobox("tag", "Tag", "tags")
+            .value("ops")
+            .to_string();
+        assert!(field.contains(r#"role="combobox" aria-autocomplete="list" aria-expanded="false" aria-controls="field-tag-list""#));
+        assert!(field.contains(r#"hx-get="/partials/autocomplete?src=tags&amp;field=tag""#));
+        assert!(field.contains(r##"hx-target="#field-tag-list""##));
+        assert!(field.contains(r#" id="field-tag" name="tag" value="ops">"#));
+        assert!(field.contains(r#"<ul id="field-tag-list" class="combobox-list" role="listbox" aria-label="Tag" hidden></ul>"#));
+    }
+
+    #[test]
+    fn test_date_picker_loads_its_grid() {
+        let field = Field::date_picker("due", "Due")
+            .value("2024-03-14")
+            .hint("Any weekday")
+            .to_string();
+        assert!(field.contains(
+            r#"<label id="field-due-label" for="field-due" class="form-label">Due</label>"#
+        ));
+        assert!(field.contains(r#"hx-get="/partials/datepicker?field=field-due&amp;name=due&amp;describedby=field-due-hint&amp;selected=2024-03-14" hx-trigger="load""#));
+        assert!(field.contains(r#"<input type="date" class="form-control" id="field-due" name="due" aria-describedby="field-due-hint" value="2024-03-14">"#));
+    }
+
+    #[test]
+    fn test_attributes_are_escaped() {
+        let field = Field::file("avatar", "Avatar")
+            .attr("accept", "image/*\" onclick=\"x")
+            .flag("multiple")
+            .to_string();
+        assert!(field.contains(r#"accept="image/*&quot; onclick=&quot;x" multiple>"#));
+        assert_eq!(
+            serde_json::to_value(Field::checkbox("agree", "Agree", true)).unwrap(),
+            serde_json::Value::String(Field::checkbox("agree", "Agree", true).to_string())
+        );
+    }
+}
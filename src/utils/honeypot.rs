@@ -0,0 +1,14 @@
+//! Honeypot — a form field people never see and bots fill in
+//!
+//! Forms include `components/_honeypot.html`, which renders an off-screen
+//! text input named [`FIELD`]. Handlers deserialize it with
+//! `#[serde(default)]` and check [`is_tripped`] before doing any work. A
+//! tripped form should look like it succeeded, so bots learn nothing.
+
+/// Name of the hidden input
+pub const FIELD: &str = "website";
+
+/// Whether the hidden field came back filled in
+pub fn is_tripped(value: &str) -> bool {
+    !value.trim().is_empty()
+}
@@ -0,0 +1,311 @@
+//! Host allowlist — which `Host` headers the app answers
+//!
+//! Anything that echoes the host back (URL normalization's redirects,
+//! absolute links, a cache keyed by URL) trusts the `Host` header. A client
+//! can send any, and a DNS-rebinding page makes a browser send its own name
+//! to a local server. With `[server] allowed_hosts` set,
+//! [`crate::middleware::allowed_hosts`] turns away requests for any other
+//! host before they get that far.
+//!
+//! Patterns are a host name or IP address (`example.com`, `127.0.0.1`,
+//! `[::1]`), `*.` and a domain for every name under it (`*.example.com`, not
+//! `example.com` itself; `*.onion` for any onion service), or `*` for any
+//! host. Names compare without case, port or trailing dot.
+//!
+//! [`HostInfo`] is what a request's host means to the app — the admin host,
+//! a tenant's subdomain, or the site — as
+//! [`HostRouter`](crate::routes::HostRouter) dispatched it. Handlers take it
+//! as an extractor; templates read it as `globals.host`.
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap, Uri},
+};
+use serde::Serialize;
+use std::convert::Infallible;
+
+use crate::config::{AppConfig, TenancyMode};
+
+/// The host name in a `Host` header (or URI authority): lowercased, without
+/// port or trailing dot. `None` if it isn't a host name, IPv4 address or
+/// bracketed IPv6 address.
+pub fn host_name(value: &str) -> Option<String> {
+    let value = value.trim();
+    if let Some(address) = value.strip_prefix('[') {
+        let (address, rest) = address.split_once(']')?;
+        if !rest.is_empty() && !rest.strip_prefix(':').is_some_and(valid_port) {
+            return None;
+        }
+        let address: std::net::Ipv6Addr = address.parse().ok()?;
+        return Some(format!("[{address}]"));
+    }
+    let (host, port) = match value.split_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (value, None),
+    };
+    if port.is_some_and(|port| !valid_port(port)) {
+        return None;
+    }
+    let host = host.strip_suffix('.').unwrap_or(host).to_ascii_lowercase();
+    valid_name(&host).then_some(host)
+}
+
+fn valid_port(port: &str) -> bool {
+    !port.is_empty() && port.len() <= 5 && port.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Dot-separated labels of letters, digits and inner hyphens (IPv4
+/// addresses included); a v3 onion address's 56 characters fit a label
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+}
+
+/// Whether `host` (as from [`host_name`]) fits `pattern`
+pub fn matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    let pattern = pattern.strip_suffix('.').unwrap_or(&pattern);
+    if pattern == "*" {
+        return true;
+    }
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+        None => host == pattern,
+    }
+}
+
+/// Whether any of `patterns` allows `host`
+pub fn allowed(patterns: &[String], host: &str) -> bool {
+    patterns.iter().any(|pattern| matches(pattern, host))
+}
+
+/// Why `pattern` can't be an `allowed_hosts` entry, if it can't
+pub fn check_pattern(pattern: &str) -> Result<(), String> {
+    let name = pattern.strip_prefix("*.").unwrap_or(pattern);
+    // A name `host_name` takes as is, bar case and trailing dot: no port
+    let bare = name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase();
+    if pattern == "*" || host_name(name).is_some_and(|host| host == bare) {
+        return Ok(());
+    }
+    Err(format!(
+        "\"{pattern}\" must be a host name or address without port or scheme, \"*.\" and a domain, or \"*\""
+    ))
+}
+
+/// Whether `value` is an origin as browsers send it in `Origin`: `http` or
+/// `https`, `://`, a host name or address, maybe a port, nothing more
+pub fn is_origin(value: &str) -> bool {
+    let Some(authority) = value
+        .strip_prefix("https://")
+        .or_else(|| value.strip_prefix("http://"))
+    else {
+        return false;
+    };
+    // As `host_name` has it: lowercase, no trailing dot; then the port
+    host_name(authority).is_some_and(|host| {
+        authority
+            .strip_prefix(host.as_str())
+            .is_some_and(|port| port.is_empty() || port.starts_with(':'))
+    })
+}
+
+/// The host a request names, as sent: the `Host` header, or over HTTP/2
+/// (which has none) the URI's authority
+pub fn request_host<'a>(headers: &'a HeaderMap, uri: &'a Uri) -> Option<&'a str> {
+    headers
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| uri.authority().map(|authority| authority.as_str()))
+}
+
+/// Which router a host is dispatched to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostKind {
+    /// Any host but the two below
+    #[default]
+    Site,
+    /// `[admin] host`
+    Admin,
+    /// A subdomain of `[tenancy] base_domain`, in subdomain mode
+    Tenant,
+}
+
+/// The host a request was made to, see the module docs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HostInfo {
+    /// As from [`host_name`]; empty when the request named none (or an
+    /// invalid one)
+    pub name: String,
+    /// Subdomain mode: what comes before `[tenancy] base_domain` (`acme`
+    /// for `acme.example.com`). Empty on the base domain and other hosts.
+    pub subdomain: String,
+    pub kind: HostKind,
+}
+
+impl HostInfo {
+    /// From the request's [`request_host`]
+    pub fn from_request(headers: &HeaderMap, uri: &Uri, config: &AppConfig) -> Self {
+        Self::new(request_host(headers, uri), config)
+    }
+
+    /// From a `Host` header value
+    pub fn new(value: Option<&str>, config: &AppConfig) -> Self {
+        let Some(name) = value.and_then(host_name) else {
+            return Self::default();
+        };
+        let subdomain = match config.tenancy.mode {
+            TenancyMode::Subdomain if !config.tenancy.base_domain.is_empty() => name
+                .strip_suffix(config.tenancy.base_domain.to_ascii_lowercase().as_str())
+                .and_then(|rest| rest.strip_suffix('.'))
+                .unwrap_or_default()
+                .to_string(),
+            _ => String::new(),
+        };
+        let kind = if !config.admin.host.is_empty() && matches(&config.admin.host, &name) {
+            HostKind::Admin
+        } else if !subdomain.is_empty() {
+            HostKind::Tenant
+        } else {
+            HostKind::Site
+        };
+        Self {
+            name,
+            subdomain,
+            kind,
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for HostInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<HostInfo>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_name() {
+        assert_eq!(
+            host_name("Example.COM:8000").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(host_name("example.com.").as_deref(), Some("example.com"));
+        assert_eq!(host_name("127.0.0.1:80").as_deref(), Some("127.0.0.1"));
+        assert_eq!(host_name("[::1]:8000").as_deref(), Some("[::1]"));
+        assert_eq!(host_name("[::1]").as_deref(), Some("[::1]"));
+
+        assert_eq!(host_name(""), None);
+        assert_eq!(host_name("example.com:"), None);
+        assert_eq!(host_name("example.com:http"), None);
+        assert_eq!(host_name("evil.com/x"), None);
+        assert_eq!(host_name("user@example.com"), None);
+        assert_eq!(host_name("[not-ipv6]"), None);
+        assert_eq!(host_name("-bad.example.com"), None);
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("example.com", "example.com"));
+        assert!(matches("Example.com.", "example.com"));
+        assert!(!matches("example.com", "www.example.com"));
+
+        assert!(matches("*.example.com", "acme.example.com"));
+        assert!(matches("*.example.com", "a.b.example.com"));
+        assert!(!matches("*.example.com", "example.com"));
+        assert!(!matches("*.example.com", "evilexample.com"));
+
+        let onion = format!("{}.onion", "a".repeat(56));
+        let onion = host_name(&onion).unwrap();
+        assert!(matches("*.onion", &onion));
+        assert!(matches("*", "anything.test"));
+        assert!(allowed(&["localhost".into(), "[::1]".into()], "[::1]"));
+    }
+
+    #[test]
+    fn test_check_pattern() {
+        for good in [
+            "example.com",
+            "*.example.com",
+            "*.onion",
+            "*",
+            "127.0.0.1",
+            "[::1]",
+        ] {
+            assert!(check_pattern(good).is_ok(), "{good}");
+        }
+        for bad in [
+            "",
+            "https://example.com",
+            "example.com:8000",
+            "ex*.com",
+            "*example.com",
+            "*.*",
+        ] {
+            assert!(check_pattern(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_is_origin() {
+        assert!(is_origin("https://example.com"));
+        assert!(is_origin("http://localhost:8000"));
+        assert!(is_origin("https://[::1]:8443"));
+        assert!(!is_origin("example.com"));
+        assert!(!is_origin("https://example.com/"));
+        assert!(!is_origin("https://Example.com"));
+        assert!(!is_origin("ftp://example.com"));
+        assert!(!is_origin("https://example.com:x"));
+    }
+
+    #[test]
+    fn test_host_info() {
+        let mut config = AppConfig::default();
+        config.admin.host = "admin.example.com".into();
+        config.tenancy.mode = TenancyMode::Subdomain;
+        config.tenancy.base_domain = "example.com".into();
+
+        let info = |host| HostInfo::new(Some(host), &config);
+        assert_eq!(info("Admin.example.com:8000").kind, HostKind::Admin);
+        let acme = info("acme.example.com");
+        assert_eq!(acme.kind, HostKind::Tenant);
+        assert_eq!(acme.subdomain, "acme");
+        assert_eq!(acme.name, "acme.example.com");
+        assert_eq!(info("example.com").kind, HostKind::Site);
+        assert_eq!(info("elsewhere.test").subdomain, "");
+        assert_eq!(HostInfo::new(None, &config), HostInfo::default());
+
+        // HTTP/2 has no Host header, only the authority
+        let uri: Uri = "https://acme.example.com/about".parse().unwrap();
+        let info = HostInfo::from_request(&HeaderMap::new(), &uri, &config);
+        assert_eq!(info.subdomain, "acme");
+
+        config.tenancy.mode = TenancyMode::Off;
+        assert_eq!(
+            HostInfo::new(Some("acme.example.com"), &config).kind,
+            HostKind::Site
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! HTML Fragments — escaped-by-default markup built outside templates
+//!
+//! Middleware and error paths sometimes need a small fragment without a
+//! template. [`HtmlFragment`] only accepts `&'static str` as raw markup, so
+//! anything computed at runtime has to go through [`HtmlFragment::text`] and
+//! gets entity-escaped.
+
+use axum::response::{Html, IntoResponse, Response};
+use std::fmt;
+
+/// Escape text for HTML content or quoted attributes: `&`, `<`, `>`, `"` and
+/// `'`. Matches askama's escaping byte for byte.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// An HTML fragment assembled from trusted literals and escaped text
+#[derive(Debug, Clone, Default)]
+pub struct HtmlFragment {
+    html: String,
+}
+
+impl HtmlFragment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append trusted markup. Only literals qualify — runtime strings go
+    /// through [`text`](Self::text).
+    pub fn raw(mut self, markup: &'static str) -> Self {
+        self.html.push_str(markup);
+        self
+    }
+
+    /// Append entity-escaped text
+    pub fn text(mut self, text: impl AsRef<str>) -> Self {
+        self.html.push_str(&escape(text.as_ref()));
+        self
+    }
+
+    /// The standard `.alert` box used for HTMX error swaps
+    pub fn alert(level: &'static str, icon: &'static str, title: &str, body: &str) -> Self {
+        Self::new()
+            .raw(r#"<div class="alert alert-"#)
+            .raw(level)
+            .raw(r#"" role="alert">"#)
+            .raw("\n    <div class=\"alert-title\"><i class=\"bi bi-")
+            .raw(icon)
+            .raw(r#""></i> <strong>"#)
+            .text(title)
+            .raw("</strong></div>\n    <div class=\"alert-body\">")
+            .text(body)
+            .raw("</div>\n</div>")
+    }
+
+    pub fn into_html(self) -> Html<String> {
+        Html(self.html)
+    }
+}
+
+impl fmt::Display for HtmlFragment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.html)
+    }
+}
+
+impl IntoResponse for HtmlFragment {
+    fn into_response(self) -> Response {
+        self.into_html().into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_escapes_dynamic_text() {
+        let fragment =
+            HtmlFragment::alert("danger", "x-circle", "Error <1>", "<img src=x onerror='y'>");
+        let html = fragment.to_string();
+        assert!(html.contains("<strong>Error &lt;1&gt;</strong>"));
+        assert!(html.contains("&lt;img src=x onerror=&#x27;y&#x27;&gt;"));
+        assert!(!html.contains("<img"));
+    }
+}
@@ -0,0 +1,407 @@
+//! HTML Diffs — a re-rendered fragment as swaps of just the children that changed
+//!
+//! [`diff_fragments`](crate::middleware::diff_fragments) keeps the [`Shape`]
+//! of the last render it sent into a target. When the next render has the
+//! same children, by id and in order, [`patch`] answers with only those that
+//! changed, each marked `hx-swap-oob="true"` so HTMX swaps it in by id.
+//!
+//! [`split`] is a scanner for the markup our templates produce, not an HTML
+//! parser: it follows tags, quoted attributes, comments, void elements and
+//! raw text (`<script>`, `<style>`, …) and gives up (`None`) on anything it
+//! can't match up, which sends the full render instead.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+/// Elements without an end tag
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+/// Elements whose content isn't markup
+const RAW_TEXT: &[&str] = &["script", "style", "textarea", "title"];
+
+/// A top-level node of a fragment: an element, or text that isn't just
+/// whitespace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node<'a> {
+    /// The element's `id`, if it has one
+    pub id: Option<&'a str>,
+    /// The node's markup, end tag included
+    pub html: &'a str,
+    /// The start tag's length; 0 for text
+    start_tag: usize,
+    /// The end tag's length; 0 for text and void elements
+    end_tag: usize,
+}
+
+impl<'a> Node<'a> {
+    /// `<div id="x" class="…">`, or empty for text
+    pub fn start_tag(&self) -> &'a str {
+        &self.html[..self.start_tag]
+    }
+
+    /// The element's content, between its tags
+    pub fn inner(&self) -> &'a str {
+        &self.html[self.start_tag..self.html.len() - self.end_tag]
+    }
+
+    /// The element marked for an out-of-band swap, which replaces the
+    /// element with its id wherever that is on the page
+    pub fn out_of_band(&self) -> String {
+        let name = 1 + self.html[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or(self.html.len() - 1);
+        format!(
+            "{} hx-swap-oob=\"true\"{}",
+            &self.html[..name],
+            &self.html[name..]
+        )
+    }
+}
+
+/// A tag starting at some `<`
+struct Tag<'a> {
+    /// Lowercased
+    name: String,
+    closing: bool,
+    self_closing: bool,
+    id: Option<&'a str>,
+    /// Just past the `>`
+    end: usize,
+}
+
+impl Tag<'_> {
+    fn is_void(&self) -> bool {
+        self.self_closing || VOID.contains(&self.name.as_str())
+    }
+}
+
+/// The tag at `start` (a `<`), or `None` when it isn't one (a stray `<` in
+/// text) or doesn't end
+fn tag_at(html: &str, start: usize) -> Option<Tag<'_>> {
+    let bytes = html.as_bytes();
+    let mut i = start + 1;
+    let closing = bytes.get(i) == Some(&b'/');
+    if closing {
+        i += 1;
+    }
+    let name_start = i;
+    while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'-') {
+        i += 1;
+    }
+    if i == name_start || !bytes[name_start].is_ascii_alphabetic() {
+        return None;
+    }
+    let name = html[name_start..i].to_ascii_lowercase();
+
+    let mut id = None;
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        match bytes.get(i)? {
+            b'>' => {
+                return Some(Tag {
+                    name,
+                    closing,
+                    self_closing: false,
+                    id,
+                    end: i + 1,
+                })
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'>') => {
+                return Some(Tag {
+                    name,
+                    closing,
+                    self_closing: true,
+                    id,
+                    end: i + 2,
+                })
+            }
+            _ => {}
+        }
+        let attr_start = i;
+        while i < bytes.len() && !matches!(bytes[i], b'=' | b'>' | b'/') {
+            if bytes[i].is_ascii_whitespace() {
+                break;
+            }
+            i += 1;
+        }
+        if i == attr_start {
+            // A `/` not closing the tag
+            i += 1;
+            continue;
+        }
+        let attr = &html[attr_start..i];
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if bytes.get(i) != Some(&b'=') {
+            continue;
+        }
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let value = match *bytes.get(i)? {
+            quote @ (b'"' | b'\'') => {
+                let len = html[i + 1..].find(quote as char)?;
+                let value = &html[i + 1..i + 1 + len];
+                i += len + 2;
+                value
+            }
+            _ => {
+                let value_start = i;
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                    i += 1;
+                }
+                &html[value_start..i]
+            }
+        };
+        if attr.eq_ignore_ascii_case("id") {
+            id = Some(value);
+        }
+    }
+}
+
+/// Where the raw text element opened by `tag` ends: just past its end tag,
+/// and that tag's length
+fn raw_text_end(html: &str, tag: &Tag) -> Option<(usize, usize)> {
+    let close = format!("</{}", tag.name);
+    let rest = &html[tag.end..];
+    let at = tag.end + rest.to_ascii_lowercase().find(&close)?;
+    let end = at + html[at..].find('>')? + 1;
+    Some((end, end - at))
+}
+
+/// Where the element opened by `tag` ends: just past its end tag, and that
+/// tag's length
+fn element_end(html: &str, tag: &Tag) -> Option<(usize, usize)> {
+    if tag.is_void() {
+        return Some((tag.end, 0));
+    }
+    if RAW_TEXT.contains(&tag.name.as_str()) {
+        return raw_text_end(html, tag);
+    }
+    let mut open = vec![tag.name.clone()];
+    let mut i = tag.end;
+    loop {
+        let at = i + html[i..].find('<')?;
+        if html[at..].starts_with("<!--") {
+            i = at + html[at..].find("-->")? + 3;
+            continue;
+        }
+        let Some(inner) = tag_at(html, at) else {
+            i = at + 1;
+            continue;
+        };
+        if inner.closing {
+            // An end tag closes its element and anything left open inside
+            if let Some(depth) = open.iter().rposition(|name| *name == inner.name) {
+                open.truncate(depth);
+            }
+            if open.is_empty() {
+                return Some((inner.end, inner.end - at));
+            }
+            i = inner.end;
+        } else if inner.is_void() {
+            i = inner.end;
+        } else if RAW_TEXT.contains(&inner.name.as_str()) {
+            i = raw_text_end(html, &inner)?.0;
+        } else {
+            i = inner.end;
+            open.push(inner.name);
+        }
+    }
+}
+
+/// A fragment's top-level nodes, leaving out comments and whitespace.
+/// `None` when the markup doesn't match up (a stray end tag, an element that
+/// never ends) or is a whole document.
+pub fn split(html: &str) -> Option<Vec<Node<'_>>> {
+    let mut nodes = Vec::new();
+    let mut i = 0;
+    while i < html.len() {
+        let rest = &html[i..];
+        let trimmed = rest.trim_start();
+        if trimmed.len() != rest.len() {
+            i += rest.len() - trimmed.len();
+            continue;
+        }
+        if rest.starts_with("<!--") {
+            i += rest.find("-->")? + 3;
+            continue;
+        }
+        if rest.starts_with("<!") {
+            return None;
+        }
+        if let Some(tag) = rest.starts_with('<').then(|| tag_at(html, i)).flatten() {
+            if tag.closing {
+                return None;
+            }
+            let (end, end_tag) = element_end(html, &tag)?;
+            nodes.push(Node {
+                id: tag.id,
+                html: &html[i..end],
+                start_tag: tag.end - i,
+                end_tag,
+            });
+            i = end;
+        } else {
+            let len = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| *c == '<')
+                .map_or(rest.len(), |(at, _)| at);
+            nodes.push(Node {
+                id: None,
+                html: rest[..len].trim_end(),
+                start_tag: 0,
+                end_tag: 0,
+            });
+            i += len;
+        }
+    }
+    Some(nodes)
+}
+
+/// The children of a render sent into `#target`: those of its root element
+/// when that is the target itself (an `outerHTML` swap), with the root's
+/// start tag, or else its top-level nodes (`innerHTML`). `None` when a child
+/// has no id, or a duplicate one, or the markup is beyond [`split`].
+pub fn children<'a>(html: &'a str, target: &str) -> Option<(&'a str, Vec<Node<'a>>)> {
+    let nodes = split(html)?;
+    let (root, children) = match nodes.as_slice() {
+        [root] if root.id == Some(target) => (root.start_tag(), split(root.inner())?),
+        _ => ("", nodes),
+    };
+    let mut ids = HashSet::new();
+    children
+        .iter()
+        .all(|child| child.id.is_some_and(|id| ids.insert(id)))
+        .then_some((root, children))
+}
+
+/// What a render looked like, to diff the next one against: hashes of the
+/// root's start tag and of each child, by id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shape {
+    root: u64,
+    children: Vec<(String, u64)>,
+}
+
+impl Shape {
+    pub fn new(root: &str, children: &[Node]) -> Self {
+        Self {
+            root: hash(root),
+            children: children
+                .iter()
+                .map(|child| (child.id.unwrap_or_default().to_string(), hash(child.html)))
+                .collect(),
+        }
+    }
+}
+
+fn hash(html: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    html.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The out-of-band swaps turning a page showing `previous` into `current`
+/// (whose `children` they are): the changed children, concatenated, or an
+/// empty string when nothing changed. `None` when the two don't line up — a
+/// different root, or children added, removed or reordered.
+pub fn patch(previous: &Shape, current: &Shape, children: &[Node]) -> Option<String> {
+    let lines_up = previous.root == current.root
+        && previous.children.len() == current.children.len()
+        && previous
+            .children
+            .iter()
+            .zip(&current.children)
+            .all(|((before, _), (after, _))| before == after);
+    if !lines_up {
+        return None;
+    }
+    Some(
+        previous
+            .children
+            .iter()
+            .zip(&current.children)
+            .zip(children)
+            .filter(|(((_, before), (_, after)), _)| before != after)
+            .map(|(_, child)| child.out_of_band())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let html = r#"
+            <!-- a comment -->
+            <div id="a" class="x"><p>One <br> two</p><img src="/i.png"></div>
+            <section id='b' data-x=1><script>if (a < b) { x = "</div>"; }</script></section>
+            <input id="c" type="text"/>
+            text
+        "#;
+        let nodes = split(html).unwrap();
+        let ids: Vec<_> = nodes.iter().map(|node| node.id).collect();
+        assert_eq!(ids, [Some("a"), Some("b"), Some("c"), None]);
+        assert_eq!(nodes[0].start_tag(), r#"<div id="a" class="x">"#);
+        assert_eq!(nodes[0].inner(), r#"<p>One <br> two</p><img src="/i.png">"#);
+        assert!(nodes[1].html.ends_with("</script></section>"));
+        assert_eq!(nodes[2].inner(), "");
+        assert_eq!(nodes[3].html, "text");
+
+        assert_eq!(split("<div><p>unclosed"), None);
+        assert_eq!(split("</div>"), None);
+        assert_eq!(split("<!DOCTYPE html><html></html>"), None);
+    }
+
+    #[test]
+    fn test_children() {
+        let inner = r#"<div id="a">1</div> <div id="b">2</div>"#;
+        let (root, children) = children(inner, "target").unwrap();
+        assert_eq!(root, "");
+        assert_eq!(children.len(), 2);
+
+        let outer = r#"<ul id="list" class="l"><li id="x">1</li><li id="y">2</li></ul>"#;
+        let (root, children) = super::children(outer, "list").unwrap();
+        assert_eq!(root, r#"<ul id="list" class="l">"#);
+        assert_eq!(children[1].id, Some("y"));
+
+        assert!(super::children(r#"<div id="a">1</div><div>2</div>"#, "t").is_none());
+        assert!(super::children(r#"<div id="a">1</div><div id="a">2</div>"#, "t").is_none());
+        assert!(super::children(r#"<div id="a">1</div> loose text"#, "t").is_none());
+    }
+
+    #[test]
+    fn test_patch() {
+        let before = r#"<div id="a">1</div><div id="b">2</div><div id="c">3</div>"#;
+        let after = r#"<div id="a">1</div><div id="b">two</div><div id="c">3</div>"#;
+        let shape = |html| {
+            let (root, children) = children(html, "t").unwrap();
+            Shape::new(root, &children)
+        };
+        let (root, nodes) = children(after, "t").unwrap();
+        let current = Shape::new(root, &nodes);
+
+        assert_eq!(
+            patch(&shape(before), &current, &nodes).unwrap(),
+            r#"<div hx-swap-oob="true" id="b">two</div>"#
+        );
+        assert_eq!(patch(&current, &current, &nodes).unwrap(), "");
+        let reordered = shape(r#"<div id="b">2</div><div id="a">1</div><div id="c">3</div>"#);
+        assert_eq!(patch(&reordered, &current, &nodes), None);
+        assert_eq!(
+            patch(&shape(r#"<div id="a">1</div>"#), &current, &nodes),
+            None
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! HTMX Response Helpers
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// Whether the request was made by HTMX (`HX-Request: true`)
+pub fn is_htmx(headers: &HeaderMap) -> bool {
+    headers.get("HX-Request").is_some_and(|v| v == "true")
+}
+
+/// Whether the client takes HTML: no `Accept`, or one giving `text/html` —
+/// or failing that `text/*`, or failing that `*/*` — a non-zero `q`.
+/// Browsers and HTMX always do; a probe asking for `text/plain` doesn't.
+pub fn accepts_html(headers: &HeaderMap) -> bool {
+    let ranges: Vec<(String, bool)> = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let media = parts.next()?.trim().to_ascii_lowercase();
+            let refused = parts.any(|param| {
+                param.trim().split_once('=').is_some_and(|(name, q)| {
+                    name.trim().eq_ignore_ascii_case("q")
+                        && q.trim().parse::<f32>().is_ok_and(|q| q <= 0.0)
+                })
+            });
+            (!media.is_empty()).then_some((media, !refused))
+        })
+        .collect();
+    if ranges.is_empty() {
+        return true;
+    }
+    ["text/html", "text/*", "*/*"]
+        .iter()
+        .find_map(|media| {
+            ranges
+                .iter()
+                .find(|(range, _)| range == media)
+                .map(|(_, accepted)| *accepted)
+        })
+        .unwrap_or(false)
+}
+
+/// Send the browser to `location`: `HX-Redirect` for HTMX requests (a plain
+/// 3xx would be followed inside the XHR and swapped in), `303 See Other`
+/// for everything else. Anything but a local path (`/...`, not `//...` or
+/// `/\...`) goes to `/` instead, so a redirect can never leave the site.
+pub fn redirect(headers: &HeaderMap, location: &str) -> Response {
+    let location = local(location);
+    if is_htmx(headers) {
+        (StatusCode::OK, [("HX-Redirect", location)]).into_response()
+    } else {
+        (StatusCode::SEE_OTHER, [(header::LOCATION, location)]).into_response()
+    }
+}
+
+/// `301 Moved Permanently` to `location`, for an old URL of a page that
+/// still exists (a renamed slug, see [`crate::services::slugs`]). HTMX
+/// follows it inside the XHR like the browser would, so boosted links to the
+/// old URL land on the page too. Local paths only, as with [`redirect`].
+pub fn moved_permanently(location: &str) -> Response {
+    (
+        StatusCode::MOVED_PERMANENTLY,
+        [(header::LOCATION, local(location))],
+    )
+        .into_response()
+}
+
+/// `location` if it's a local path (`/...`, not `//...` or `/\...`), else `/`
+fn local(location: &str) -> HeaderValue {
+    Some(location)
+        .filter(|l| l.starts_with('/') && !l.starts_with("//") && !l.starts_with("/\\"))
+        .and_then(|l| HeaderValue::from_str(l).ok())
+        .unwrap_or(HeaderValue::from_static("/"))
+}
+
+/// The status HTMX treats as "stop polling": `286` cancels the `every Ns`
+/// trigger of the element that made the request
+pub fn stop_polling_status() -> StatusCode {
+    StatusCode::from_u16(286).expect("valid status")
+}
+
+/// A polled element's last answer: swapped in like any other response, but
+/// with status `286`, so the element stops polling. Ends a poll loop from the
+/// server once there is nothing left to wait for:
+///
+/// ```ignore
+/// if job.finished {
+///     return HxStopPolling(JobPartial::from(job)).into_response();
+/// }
+/// ```
+///
+/// Error responses keep their status, so a failing poll still looks like one.
+#[derive(Debug, Clone)]
+pub struct HxStopPolling<T>(pub T);
+
+impl<T: IntoResponse> IntoResponse for HxStopPolling<T> {
+    fn into_response(self) -> Response {
+        let mut response = self.0.into_response();
+        if response.status().is_success() {
+            *response.status_mut() = stop_polling_status();
+        }
+        response
+    }
+}
+
+/// A response swapped in with a View Transition: sets
+/// `HX-Reswap: <swap> transition:true`, so the browser animates between the
+/// old and new content (where it supports `document.startViewTransition`,
+/// and unless the user prefers reduced motion, see `static/css/app.css`).
+/// `swap` is the swap style the element already asked for, e.g. `outerHTML`:
+///
+/// ```ignore
+/// HxTransition("outerHTML", ItemListPartial { .. }).into_response()
+/// ```
+#[derive(Debug, Clone)]
+pub struct HxTransition<T>(pub &'static str, pub T);
+
+impl<T: IntoResponse> IntoResponse for HxTransition<T> {
+    fn into_response(self) -> Response {
+        let mut response = self.1.into_response();
+        let reswap = format!("{} transition:true", self.0);
+        if let Ok(value) = HeaderValue::from_str(&reswap) {
+            response.headers_mut().insert("HX-Reswap", value);
+        }
+        response
+    }
+}
+
+/// A response morphed into the page instead of replacing it: sets
+/// `HX-Reswap: morph`, so the vendored idiomorph extension
+/// (`static/js/idiomorph.js`) updates the target in place. Elements that are
+/// still there keep their state: focus, what was typed into a field, scroll
+/// position, a CSS transition in flight. Swap the whole target the way
+/// `outerHTML` would; the response needs the target's root element, ids
+/// included.
+#[derive(Debug, Clone)]
+pub struct HxMorph<T>(pub T);
+
+impl<T: IntoResponse> IntoResponse for HxMorph<T> {
+    fn into_response(self) -> Response {
+        let mut response = self.0.into_response();
+        response
+            .headers_mut()
+            .insert("HX-Reswap", HeaderValue::from_static("morph"));
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accepts_html() {
+        let accept = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, value.parse().unwrap());
+            accepts_html(&headers)
+        };
+        assert!(accepts_html(&HeaderMap::new()));
+        assert!(accept("text/html,application/xhtml+xml,*/*;q=0.8"));
+        assert!(accept("*/*"));
+        assert!(accept("text/*"));
+        assert!(!accept("text/plain"));
+        assert!(!accept("application/json, text/plain;q=0.5"));
+        assert!(!accept("text/html;q=0, */*"));
+        assert!(!accept("*/*; q=0"));
+    }
+
+    #[test]
+    fn test_stop_polling_keeps_errors() {
+        let response = HxStopPolling("done").into_response();
+        assert_eq!(response.status().as_u16(), 286);
+        let response = HxStopPolling((StatusCode::NOT_FOUND, "gone")).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_transition_reswap() {
+        let response = HxTransition("outerHTML", "list").into_response();
+        assert_eq!(response.headers()["HX-Reswap"], "outerHTML transition:true");
+    }
+}
@@ -0,0 +1,199 @@
+//! Dev Live-Reload — file watcher + SSE push (debug builds only)
+//!
+//! Watches `templates/` and `static/` with notify and broadcasts an event to
+//! every open `/dev/reload` EventSource. A stylesheet change rebuilds the CSS
+//! bundle ([`crate::utils::css`]) first. [`inject_script`] adds dev-reload.js to
+//! full pages in debug builds; it subscribes and reloads the tab. Nothing here
+//! is compiled into release builds.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Response,
+    },
+};
+use notify::{RecursiveMode, Watcher};
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+
+/// Directories watched for changes
+const WATCHED_DIRS: &[&str] = &["templates", "static"];
+
+/// Quiet period before a burst of file events is reported as one change
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Script tag injected before `</body>` on full pages
+const SCRIPT_TAG: &str =
+    r#"<script src="/static/js/dev-reload.js" data-dev-reload="/dev/reload" defer></script>"#;
+
+/// What changed on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReloadEvent {
+    Templates,
+    Static,
+    /// Server is shutting down — close open streams so shutdown isn't blocked
+    Shutdown,
+}
+
+impl ReloadEvent {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReloadEvent::Templates => "templates",
+            ReloadEvent::Static => "static",
+            ReloadEvent::Shutdown => "shutdown",
+        }
+    }
+}
+
+/// Broadcasts file changes to connected browsers
+pub struct LiveReload {
+    tx: broadcast::Sender<ReloadEvent>,
+    // Dropping the watcher stops it — keep it alive with the service
+    _watcher: Mutex<Option<notify::RecommendedWatcher>>,
+}
+
+impl LiveReload {
+    /// Start watching [`WATCHED_DIRS`]. Watch failures are logged, not fatal —
+    /// live-reload is a convenience, not something to refuse to start over.
+    pub fn start() -> Arc<Self> {
+        let (tx, _) = broadcast::channel(16);
+        let (fs_tx, mut fs_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                if !event.kind.is_access() {
+                    let _ = fs_tx.send(event);
+                }
+            }
+        })
+        .and_then(|mut watcher| {
+            for dir in WATCHED_DIRS {
+                watcher.watch(Path::new(dir), RecursiveMode::Recursive)?;
+            }
+            Ok(watcher)
+        });
+
+        let watcher = match watcher {
+            Ok(watcher) => {
+                tracing::info!("Live-reload watching {}", WATCHED_DIRS.join(", "));
+                Some(watcher)
+            }
+            Err(e) => {
+                tracing::warn!("Live-reload disabled: {}", e);
+                None
+            }
+        };
+
+        // Debounce: editors often write a file several times per save
+        let sender = tx.clone();
+        tokio::spawn(async move {
+            while let Some(first) = fs_rx.recv().await {
+                let mut templates_changed = touches_templates(&first);
+                let mut css_changed = touches_css(&first);
+                while let Ok(Some(next)) = tokio::time::timeout(DEBOUNCE, fs_rx.recv()).await {
+                    templates_changed |= touches_templates(&next);
+                    css_changed |= touches_css(&next);
+                }
+                // Rebuild first, so the reloaded page links the new bundle. A
+                // broken stylesheet keeps the last good one.
+                if css_changed {
+                    if let Err(e) = crate::utils::css::install() {
+                        tracing::error!("CSS bundle not rebuilt: {e:#}");
+                    }
+                }
+                let event = if templates_changed {
+                    ReloadEvent::Templates
+                } else {
+                    ReloadEvent::Static
+                };
+                tracing::debug!("Live-reload: {} changed", event.as_str());
+                let _ = sender.send(event);
+            }
+        });
+
+        Arc::new(Self {
+            tx,
+            _watcher: Mutex::new(watcher),
+        })
+    }
+
+    /// End every open stream (call before graceful shutdown)
+    pub fn shutdown(&self) {
+        let _ = self.tx.send(ReloadEvent::Shutdown);
+    }
+
+    fn subscribe(&self) -> impl Stream<Item = Result<Event, Infallible>> {
+        BroadcastStream::new(self.tx.subscribe())
+            // A lagged receiver only missed duplicate reloads — skip it
+            .filter_map(Result::ok)
+            .take_while(|event| *event != ReloadEvent::Shutdown)
+            .map(|event| Ok(Event::default().event("reload").data(event.as_str())))
+    }
+}
+
+fn touches_css(event: &notify::Event) -> bool {
+    event
+        .paths
+        .iter()
+        .any(|p| p.extension().is_some_and(|ext| ext == "css"))
+}
+
+fn touches_templates(event: &notify::Event) -> bool {
+    event
+        .paths
+        .iter()
+        .any(|p| p.components().any(|c| c.as_os_str() == "templates"))
+}
+
+// ─── Handler + Middleware ───────────────────────────────────────────────────
+
+/// GET /dev/reload — SSE stream of file-change events
+pub async fn reload_events(
+    State(live_reload): State<Arc<LiveReload>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    Sse::new(live_reload.subscribe()).keep_alive(KeepAlive::default())
+}
+
+/// Inject the dev-reload.js subscriber into full HTML pages.
+/// Partials (no `</body>`) pass through untouched.
+pub async fn inject_script(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/html"));
+    if !is_html {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+
+    let html = String::from_utf8_lossy(&bytes);
+    let body = match html.rfind("</body>") {
+        Some(pos) => {
+            let mut injected = String::with_capacity(html.len() + SCRIPT_TAG.len());
+            injected.push_str(&html[..pos]);
+            injected.push_str(SCRIPT_TAG);
+            injected.push_str(&html[pos..]);
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Body::from(injected)
+        }
+        None => Body::from(bytes),
+    };
+
+    Response::from_parts(parts, body)
+}
@@ -0,0 +1,189 @@
+//! Loading states — skeleton placeholders and request indicators
+//!
+//! Every page loads `static/js/app.js`, which applies the conventions below to
+//! each HTMX request, so templates only opt in with markup:
+//!
+//! - The request's target gets `aria-busy="true"` until the response is in.
+//!   A target with the `loading-region` class also dims, after a short delay
+//!   so fast responses don't flicker.
+//! - A form that makes a request has its submit buttons disabled until the
+//!   response is in, so a double click can't post twice. A button with
+//!   `data-loading-text` shows that text meanwhile. Forms posted without
+//!   HTMX are covered too.
+//! - [`Indicator`] is a spinner with the `htmx-indicator` class, shown while
+//!   a request names it with `hx-indicator`; it produces the selector and the
+//!   element together, so the two can't drift apart.
+//! - [`Skeleton`] is placeholder content for a region that loads later
+//!   (`hx-trigger="load"`, tab panels).
+//!
+//! Like [`crate::utils::tabs`], the markup is built here and printed with
+//! `|safe`.
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::utils::html::escape;
+
+/// Screen reader text of placeholders and indicators, unless given another
+const DEFAULT_LABEL: &str = "Loading…";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    Lines(usize),
+    List(usize),
+    Card,
+}
+
+/// Grey pulsing placeholder shapes, announced once as loading
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skeleton {
+    shape: Shape,
+    label: String,
+}
+
+impl Skeleton {
+    /// A paragraph of `count` lines, the last one shorter
+    pub fn lines(count: usize) -> Self {
+        Self::new(Shape::Lines(count))
+    }
+
+    /// `count` rows of an avatar beside two lines, like a comment thread
+    pub fn list(count: usize) -> Self {
+        Self::new(Shape::List(count))
+    }
+
+    /// An image-sized block above a title and two lines
+    pub fn card() -> Self {
+        Self::new(Shape::Card)
+    }
+
+    fn new(shape: Shape) -> Self {
+        Self {
+            shape,
+            label: DEFAULT_LABEL.to_string(),
+        }
+    }
+
+    /// What screen readers announce, e.g. `Loading comments…`
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+}
+
+fn lines(out: &mut String, count: usize) {
+    for i in 0..count {
+        let short = if i + 1 == count && count > 1 {
+            " skeleton-short"
+        } else {
+            ""
+        };
+        out.push_str(&format!(
+            "<div class=\"skeleton skeleton-text{short}\"></div>"
+        ));
+    }
+}
+
+impl fmt::Display for Skeleton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut shapes = String::new();
+        match self.shape {
+            Shape::Lines(count) => lines(&mut shapes, count),
+            Shape::List(count) => {
+                for _ in 0..count {
+                    shapes.push_str(
+                        "<div class=\"skeleton-row\"><div class=\"skeleton skeleton-avatar\"></div><div class=\"skeleton-stack\">",
+                    );
+                    lines(&mut shapes, 2);
+                    shapes.push_str("</div></div>");
+                }
+            }
+            Shape::Card => {
+                shapes.push_str(
+                    "<div class=\"skeleton skeleton-block\"></div><div class=\"skeleton skeleton-title\"></div>",
+                );
+                lines(&mut shapes, 2);
+            }
+        }
+        write!(
+            f,
+            "<div class=\"skeleton-group\" role=\"status\"><span class=\"visually-hidden\">{}</span>\
+             <div aria-hidden=\"true\">{shapes}</div></div>",
+            escape(&self.label)
+        )
+    }
+}
+
+/// Rendered markup, as templates print it with `|safe`
+impl Serialize for Skeleton {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A spinner shown while a request that names it is in flight:
+/// `hx-indicator="{{ indicator.selector }}"` on the requesting element and
+/// `{{ indicator.html|safe }}` where the spinner goes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Indicator {
+    /// `#<id>`, for `hx-indicator`
+    pub selector: String,
+    pub html: String,
+}
+
+impl Indicator {
+    /// The spinner element gets `id`; `label` is shown beside it, and read out
+    pub fn new(id: &str, label: &str) -> Self {
+        Self {
+            selector: format!("#{id}"),
+            html: format!(
+                "<span id=\"{}\" class=\"htmx-indicator text-sm text-muted\" role=\"status\">\
+                 <span class=\"spinner\" aria-hidden=\"true\"></span> {}</span>",
+                escape(id),
+                escape(label)
+            ),
+        }
+    }
+
+    /// The ` hx-indicator="#id"` attribute, for markup built in Rust
+    pub fn attr(&self) -> String {
+        format!(" hx-indicator=\"{}\"", escape(&self.selector))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skeleton_shapes() {
+        let lines = Skeleton::lines(2).to_string();
+        assert!(lines.starts_with(
+            "<div class=\"skeleton-group\" role=\"status\"><span class=\"visually-hidden\">Loading…</span>"
+        ));
+        assert!(lines.contains(
+            "<div class=\"skeleton skeleton-text\"></div><div class=\"skeleton skeleton-text skeleton-short\"></div>"
+        ));
+        assert_eq!(
+            Skeleton::list(3)
+                .to_string()
+                .matches("skeleton-avatar")
+                .count(),
+            3
+        );
+        assert!(Skeleton::card()
+            .label("Loading <post>")
+            .to_string()
+            .contains(">Loading &lt;post&gt;</span>"));
+    }
+
+    #[test]
+    fn test_indicator_selector_matches_its_element() {
+        let indicator = Indicator::new("save-indicator", "Saving…");
+        assert_eq!(indicator.selector, "#save-indicator");
+        assert_eq!(indicator.attr(), " hx-indicator=\"#save-indicator\"");
+        assert!(indicator
+            .html
+            .starts_with("<span id=\"save-indicator\" class=\"htmx-indicator"));
+    }
+}
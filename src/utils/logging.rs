@@ -1,14 +1,13 @@
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use tracing_subscriber::{
+    layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry,
+};
 
-/// Initialize tracing/logging based on config
-pub fn init_logging(log_level: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let filter = if log_level.contains('=') {
-        log_level.to_string()
-    } else {
-        format!("app={},tower_http=debug", log_level)
-    };
-
-    let env_filter = EnvFilter::try_new(&filter).unwrap_or_else(|_| EnvFilter::new("info"));
+/// Initialize tracing/logging based on config. The returned handle changes
+/// the level later, e.g. on config reload.
+pub fn init_logging(log_level: &str) -> Result<LogFilter, Box<dyn std::error::Error>> {
+    let env_filter =
+        EnvFilter::try_new(directives(log_level)).unwrap_or_else(|_| EnvFilter::new("info"));
+    let (env_filter, handle) = reload::Layer::new(env_filter);
 
     tracing_subscriber::registry()
         .with(env_filter)
@@ -19,5 +18,27 @@ pub fn init_logging(log_level: &str) -> Result<(), Box<dyn std::error::Error>> {
         )
         .init();
 
-    Ok(())
+    Ok(LogFilter(handle))
+}
+
+/// A bare level applies to the app (and request traces); anything with an
+/// `=` is a full filter directive
+pub fn directives(log_level: &str) -> String {
+    if log_level.contains('=') {
+        log_level.to_string()
+    } else {
+        format!("app={},tower_http=debug", log_level)
+    }
+}
+
+/// Swaps the active log filter
+#[derive(Clone)]
+pub struct LogFilter(reload::Handle<EnvFilter, Registry>);
+
+impl LogFilter {
+    /// Replace the filter with `directives`, e.g. `app=info,app::db=debug`
+    pub fn set(&self, directives: &str) -> Result<(), String> {
+        let filter = EnvFilter::try_new(directives).map_err(|e| e.to_string())?;
+        self.0.reload(filter).map_err(|e| e.to_string())
+    }
 }
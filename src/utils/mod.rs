@@ -1,2 +1,21 @@
+pub mod assets;
+pub mod build_info;
+pub mod clipboard;
+pub mod cookies;
+pub mod css;
+pub mod csv;
+pub mod favicons;
+pub mod forms;
+pub mod honeypot;
+pub mod hosts;
+pub mod html;
+pub mod html_diff;
+pub mod htmx;
+#[cfg(debug_assertions)]
+pub mod live_reload;
+pub mod loading;
 pub mod logging;
+pub mod shortcuts;
+pub mod tabs;
 pub mod templates;
+pub mod urls;
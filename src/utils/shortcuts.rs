@@ -0,0 +1,67 @@
+//! Keyboard shortcuts — navigation keys declared next to the routes they open
+//!
+//! A page registers its shortcut where it's handled, with
+//! [`shortcut!`](crate::shortcut):
+//!
+//! ```ignore
+//! crate::shortcut!("g d", "/demo", "Go to the demo");
+//! crate::shortcut!("g b", "/blog");   // described by its path
+//! ```
+//!
+//! Keys follow the vendored `static/js/mousetrap.js`: a space separates the
+//! steps of a sequence, `+` joins modifiers (`mod+k`). Every page lists the
+//! registered shortcuts as hidden boosted links (`globals.shortcuts`), which
+//! `static/js/shortcuts.js` binds; `?` opens `partials/shortcuts.html`, the
+//! help dialog.
+
+use serde::Serialize;
+
+/// A `shortcut!` declaration, collected at link time
+#[derive(Debug, Serialize)]
+pub struct Shortcut {
+    pub keys: &'static str,
+    /// Path the keys open, below the tenant's prefix
+    pub href: &'static str,
+    /// What the help dialog says it does
+    pub label: &'static str,
+}
+
+inventory::collect!(Shortcut);
+
+/// Register a navigation shortcut; the label defaults to the path
+#[macro_export]
+macro_rules! shortcut {
+    ($keys:literal, $href:literal) => {
+        $crate::shortcut!($keys, $href, $href);
+    };
+    ($keys:literal, $href:literal, $label:literal) => {
+        $crate::render::inventory::submit! {
+            $crate::utils::shortcuts::Shortcut {
+                keys: $keys,
+                href: $href,
+                label: $label,
+            }
+        }
+    };
+}
+
+/// Every registered shortcut, sorted by keys
+pub fn registered() -> Vec<&'static Shortcut> {
+    let mut shortcuts: Vec<&'static Shortcut> = inventory::iter::<Shortcut>.into_iter().collect();
+    shortcuts.sort_by_key(|shortcut| shortcut.keys);
+    shortcuts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registered_keys_are_unique() {
+        let shortcuts = registered();
+        assert!(shortcuts.iter().any(|shortcut| shortcut.href == "/demo"));
+        for pair in shortcuts.windows(2) {
+            assert_ne!(pair[0].keys, pair[1].keys, "bound twice");
+        }
+    }
+}
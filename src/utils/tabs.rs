@@ -0,0 +1,131 @@
+//! Tabs — an ARIA tab list whose panels load on first use
+//!
+//! Like [`crate::utils::forms`], the markup is built here and printed with
+//! `{{ tabs|safe }}`. Each tab names a partial; its button carries
+//! `hx-get` with `hx-trigger="click once"`, so a panel is fetched the first
+//! time its tab opens and kept after that; until then it holds a
+//! [`Skeleton`]. `static/js/tabs.js` (loaded by the page) switches
+//! `aria-selected` and the `hidden` panels, handles the arrow keys, and keeps
+//! the URL hash on the open panel's id: a link to `/demo#demo-tabs-tags`
+//! opens that tab.
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::utils::html::escape;
+use crate::utils::loading::Skeleton;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Tab {
+    slug: &'static str,
+    label: String,
+    src: String,
+}
+
+/// A tab list and its panels; the first tab opens unless the hash names another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tabs {
+    id: String,
+    label: String,
+    tabs: Vec<Tab>,
+}
+
+impl Tabs {
+    /// `id` prefixes every tab and panel id; `label` names the tab list for
+    /// screen readers
+    pub fn new(id: impl Into<String>, label: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            label: label.into(),
+            tabs: Vec::new(),
+        }
+    }
+
+    /// A tab showing `label` whose panel is loaded from `src`. `slug` ends
+    /// the panel's id, `<id>-<slug>`, which is also its URL hash.
+    pub fn tab(
+        mut self,
+        slug: &'static str,
+        label: impl Into<String>,
+        src: impl Into<String>,
+    ) -> Self {
+        self.tabs.push(Tab {
+            slug,
+            label: label.into(),
+            src: src.into(),
+        });
+        self
+    }
+
+    fn panel_id(&self, tab: &Tab) -> String {
+        escape(&format!("{}-{}", self.id, tab.slug))
+    }
+}
+
+impl fmt::Display for Tabs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = format!(
+            "<div id=\"{}\" class=\"tabs\" data-tabs>\
+             \n    <div class=\"tab-nav\" role=\"tablist\" aria-label=\"{}\">",
+            escape(&self.id),
+            escape(&self.label)
+        );
+        // Only the open tab is in the tab order; the arrow keys reach the rest
+        for (i, tab) in self.tabs.iter().enumerate() {
+            let panel = self.panel_id(tab);
+            let (class, selected, tabindex) = if i == 0 {
+                (" active", "true", "0")
+            } else {
+                ("", "false", "-1")
+            };
+            out.push_str(&format!(
+                "\n        <button type=\"button\" role=\"tab\" id=\"{panel}-tab\" class=\"tab-btn{class}\" \
+                 aria-selected=\"{selected}\" aria-controls=\"{panel}\" tabindex=\"{tabindex}\" \
+                 hx-get=\"{}\" hx-trigger=\"click once\" hx-target=\"#{panel}\">{}</button>",
+                escape(&tab.src),
+                escape(&tab.label)
+            ));
+        }
+        out.push_str("\n    </div>");
+        for (i, tab) in self.tabs.iter().enumerate() {
+            let panel = self.panel_id(tab);
+            out.push_str(&format!(
+                "\n    <div role=\"tabpanel\" id=\"{panel}\" class=\"tab-panel\" aria-labelledby=\"{panel}-tab\" tabindex=\"0\"{}>\
+                 \n        {}\
+                 \n    </div>",
+                if i == 0 { "" } else { " hidden" },
+                Skeleton::lines(3).label(format!("Loading {}…", tab.label))
+            ));
+        }
+        out.push_str("\n</div>");
+        f.write_str(&out)
+    }
+}
+
+/// Rendered markup, as templates print it with `|safe`
+impl Serialize for Tabs {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tabs_wire_buttons_to_lazy_panels() {
+        let tabs = Tabs::new("t", "Sections")
+            .tab("one", "One", "/partials/one")
+            .tab("two", "Two <2>", "/partials/two?a=1&b=2")
+            .to_string();
+        assert!(tabs.contains(r#"role="tablist" aria-label="Sections""#));
+        assert!(tabs.contains(r##"<button type="button" role="tab" id="t-one-tab" class="tab-btn active" aria-selected="true" aria-controls="t-one" tabindex="0" hx-get="/partials/one" hx-trigger="click once" hx-target="#t-one">One</button>"##));
+        assert!(tabs.contains(r#"tabindex="-1" hx-get="/partials/two?a=1&amp;b=2""#));
+        assert!(tabs.contains(">Two &lt;2&gt;</button>"));
+        assert!(tabs.contains(r#"<div role="tabpanel" id="t-one" class="tab-panel" aria-labelledby="t-one-tab" tabindex="0">"#));
+        assert!(tabs.contains(
+            r#"id="t-two" class="tab-panel" aria-labelledby="t-two-tab" tabindex="0" hidden>"#
+        ));
+    }
+}
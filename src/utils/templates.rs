@@ -1,14 +1,48 @@
-#[cfg(debug_assertions)]
-use minijinja::Environment;
+use minijinja::{AutoEscape, Environment, State, Value};
 use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::Path;
 
-/// Render a template from disk (debug mode hot-reload).
-/// In release mode, askama compiled templates are used instead.
-#[cfg(debug_assertions)]
-pub fn render_template<T: Serialize>(name: &str, context: T) -> Result<String, String> {
+use crate::render::{registered_templates, TemplateKind};
+use crate::utils::html;
+
+/// Template root, relative to the working directory
+const TEMPLATE_DIR: &str = "templates";
+
+fn environment() -> Environment<'static> {
     let mut env = Environment::new();
-    env.set_loader(minijinja::path_loader("templates"));
+    env.set_loader(minijinja::path_loader(TEMPLATE_DIR));
+    // Escape strings exactly like askama so both engines emit the same bytes
+    // (minijinja's own escaper also escapes `/`)
+    env.set_formatter(|out, state, value| match value.as_str() {
+        Some(text) if state.auto_escape() == AutoEscape::Html && !value.is_safe() => {
+            out.write_str(&html::escape(text)).map_err(Into::into)
+        }
+        _ => minijinja::escape_formatter(out, state, value),
+    });
+    // Template functions — askama resolves these to methods on the view
+    // struct generated by define_page!/define_partial!
+    env.add_function("breadcrumbs", breadcrumbs);
+    env.add_function("is_online", |name: &str| crate::globals::is_online(name));
+    env.add_function("asset", |path: &str| crate::utils::assets::asset(path));
+    env.add_function("favicon_links", crate::utils::favicons::links);
+    env
+}
 
+/// `breadcrumbs()` — the current request's trail, from `globals`
+fn breadcrumbs(state: &State) -> Value {
+    state
+        .lookup("globals")
+        .and_then(|globals| globals.get_attr("breadcrumbs").ok())
+        .unwrap_or_default()
+}
+
+/// Render a template from disk via minijinja.
+///
+/// Debug builds serve every request through this (hot-reload). Release builds
+/// serve compiled askama templates and only call this from the snapshot tests.
+pub fn render_template<T: Serialize>(name: &str, context: T) -> Result<String, String> {
+    let env = environment();
     let template = env
         .get_template(name)
         .map_err(|e| format!("Template load error: {}", e))?;
@@ -17,7 +51,258 @@ pub fn render_template<T: Serialize>(name: &str, context: T) -> Result<String, S
         .map_err(|e| format!("Template render error: {}", e))
 }
 
-#[cfg(not(debug_assertions))]
-pub fn render_template<T: Serialize>(_name: &str, _context: T) -> Result<String, String> {
-    Err("Runtime templates not available in release mode".to_string())
+// =============================================================================
+// Parity Checker — askama vs minijinja vs define_page! declarations
+// =============================================================================
+
+/// Check every template on disk against both engines and every
+/// `define_page!` / `define_partial!` declaration against its template.
+///
+/// Debug builds render from disk with minijinja, so a template edit that
+/// askama can't parse — or a variable nobody passes in — otherwise only shows
+/// up as a 500 at request time, or as a broken release build much later.
+///
+/// Returns one line per problem. Declared fields a template never reads are
+/// logged as warnings rather than treated as errors.
+pub fn check_template_parity() -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    let env = environment();
+
+    // 1. Every file must parse under both engines
+    let mut files = Vec::new();
+    collect_templates(Path::new(TEMPLATE_DIR), &mut files);
+    for name in &files {
+        let source = match std::fs::read_to_string(Path::new(TEMPLATE_DIR).join(name)) {
+            Ok(source) => source,
+            Err(e) => {
+                errors.push(format!("{name}: unreadable: {e}"));
+                continue;
+            }
+        };
+        if let Err(e) = askama_parser::Ast::from_str(&source, &askama_parser::Syntax::default()) {
+            errors.push(format!("{name}: askama parse error: {e}"));
+        }
+        if let Err(e) = env.get_template(name) {
+            errors.push(format!("{name}: minijinja parse error: {e}"));
+        }
+    }
+
+    // 2. Every declaration must cover what its template (and layouts) read
+    let builtins: BTreeSet<String> = env.globals().map(|(name, _)| name.to_string()).collect();
+    let mut specs: Vec<_> = registered_templates().collect();
+    specs.sort_by_key(|spec| spec.path);
+
+    for spec in specs {
+        let mut required = BTreeSet::new();
+        if let Err(e) = required_variables(&env, spec.path, &mut required, &mut Vec::new()) {
+            errors.push(format!("{} ({}): {e}", spec.name, spec.path));
+            continue;
+        }
+        let extends = env.get_template(spec.path).ok().and_then(|t| {
+            referenced_templates(t.source())
+                .into_iter()
+                .find(|(tag, _)| *tag == "extends")
+                .map(|(_, layout)| layout)
+        });
+        match (spec.kind, spec.layout, extends) {
+            (TemplateKind::Partial, _, Some(layout)) => errors.push(format!(
+                "{} ({}): partials render without a layout but this extends {layout}",
+                spec.name, spec.path
+            )),
+            (TemplateKind::Page, Some(declared), actual) if actual.as_deref() != Some(declared) => {
+                errors.push(format!(
+                    "{} ({}): declared layout {declared} but template extends {}",
+                    spec.name,
+                    spec.path,
+                    actual.as_deref().unwrap_or("nothing")
+                ))
+            }
+            _ => {}
+        }
+
+        let declared: BTreeSet<String> = spec.fields.iter().map(|f| f.to_string()).collect();
+        let implicit: BTreeSet<String> = spec.implicit.iter().map(|f| f.to_string()).collect();
+
+        let missing: Vec<_> = required
+            .difference(&declared)
+            .filter(|name| !implicit.contains(*name) && !builtins.contains(*name))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            errors.push(format!(
+                "{} ({}): template uses undeclared variables: {}",
+                spec.name,
+                spec.path,
+                missing.join(", ")
+            ));
+        }
+
+        let unused: Vec<_> = declared.difference(&required).cloned().collect();
+        if !unused.is_empty() {
+            tracing::warn!(
+                template = spec.path,
+                "{} declares fields its template never reads: {}",
+                spec.name,
+                unused.join(", ")
+            );
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Variables read by `name` plus everything it extends or includes
+///
+/// `chain` holds the templates currently being expanded, so only a cycle is
+/// cut short: a template included from two places is checked at both, as
+/// each site binds different variables around it.
+fn required_variables(
+    env: &Environment<'_>,
+    name: &str,
+    required: &mut BTreeSet<String>,
+    chain: &mut Vec<String>,
+) -> Result<(), String> {
+    if chain.iter().any(|entered| entered == name) {
+        return Ok(());
+    }
+    let template = env.get_template(name).map_err(|e| e.to_string())?;
+    required.extend(template.undeclared_variables(false));
+
+    // Includes see this template's loop variables, so those aren't required
+    // from the context — e.g. `{% for comment in comments %}{% include %}`
+    let bound = bound_variables(template.source());
+    chain.push(name.to_string());
+    for (kind, dependency) in referenced_templates(template.source()) {
+        let mut dependency_required = BTreeSet::new();
+        required_variables(env, &dependency, &mut dependency_required, chain)?;
+        if kind == "include" {
+            dependency_required.retain(|variable| !bound.contains(variable));
+        }
+        required.extend(dependency_required);
+    }
+    chain.pop();
+    Ok(())
+}
+
+/// Names bound by `{% for ... in %}` and `{% set ... = %}` tags
+fn bound_variables(source: &str) -> BTreeSet<String> {
+    source
+        .split("{%")
+        .skip(1)
+        .filter_map(|tag| {
+            let tag = tag
+                .split("%}")
+                .next()?
+                .trim()
+                .trim_start_matches('-')
+                .trim();
+            let targets = match tag.strip_prefix("for ") {
+                Some(rest) => rest.split(" in ").next()?,
+                None => tag.strip_prefix("set ")?.split('=').next()?,
+            };
+            Some(
+                targets
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .flatten()
+        .collect()
+}
+
+/// Literal targets of `{% extends "..." %}` and `{% include "..." %}` tags,
+/// paired with the tag name
+fn referenced_templates(source: &str) -> Vec<(&'static str, String)> {
+    source
+        .split("{%")
+        .skip(1)
+        .filter_map(|tag| {
+            let tag = tag
+                .split("%}")
+                .next()?
+                .trim()
+                .trim_start_matches('-')
+                .trim();
+            let (kind, rest) = ["extends", "include"]
+                .into_iter()
+                .find_map(|kind| Some((kind, tag.strip_prefix(kind)?)))?;
+            let quoted = rest.trim();
+            let quote = quoted.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let target = quoted[1..].split(quote).next()?;
+            Some((kind, target.to_string()))
+        })
+        .collect()
+}
+
+/// All `.html` files under `dir`, as paths relative to the template root
+fn collect_templates(dir: &Path, out: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_templates(&path, out);
+        } else if path.extension().is_some_and(|ext| ext == "html") {
+            if let Ok(relative) = path.strip_prefix(TEMPLATE_DIR) {
+                out.push(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    out.sort();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_referenced_templates() {
+        let source = r#"{% extends "base.html" %}{% block x %}{%- include 'components/_a.html' -%}{% if y %}{% endif %}{% endblock %}"#;
+        assert_eq!(
+            referenced_templates(source),
+            vec![
+                ("extends", "base.html".to_string()),
+                ("include", "components/_a.html".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bound_variables() {
+        let source =
+            "{% for (k, v) in pairs %}{%- for item in items -%}{% set total = 1 %}{% if x %}";
+        let bound: Vec<String> = bound_variables(source).into_iter().collect();
+        assert_eq!(bound, ["item", "k", "total", "v"]);
+    }
+
+    #[test]
+    fn test_required_variables_per_include_site() {
+        let mut env = Environment::new();
+        env.add_template(
+            "page.html",
+            r#"{% include "a.html" %}{% include "b.html" %}"#,
+        )
+        .unwrap();
+        env.add_template(
+            "a.html",
+            r#"{% for item in items %}{% include "_row.html" %}{% endfor %}"#,
+        )
+        .unwrap();
+        env.add_template("b.html", r#"{% include "_row.html" %}"#)
+            .unwrap();
+        env.add_template("_row.html", "{{ item }}").unwrap();
+
+        let mut required = BTreeSet::new();
+        required_variables(&env, "page.html", &mut required, &mut Vec::new()).unwrap();
+        let required: Vec<String> = required.into_iter().collect();
+        // b.html includes the row outside any loop, so `item` must be passed
+        assert_eq!(required, ["item", "items"]);
+    }
 }
@@ -0,0 +1,156 @@
+//! URL normalization — one address per page
+//!
+//! `/about`, `/about/` and `//about` would otherwise be three pages with the
+//! same content. [`normalize`] picks the canonical form per `[urls]`
+//! ([`UrlsConfig`]): trailing slash stripped, added or left alone, runs of
+//! slashes collapsed, the host lowercased. Anything else is answered with a
+//! `308` to it (see [`crate::middleware::normalize_url`]), which keeps the
+//! method and body, so a form posted to the wrong spelling still arrives.
+//!
+//! Routes are registered without trailing slashes. With `trailing_slash =
+//! "add"` the slashed form is canonical, so it is routed with the slash
+//! taken off ([`Normalized::Route`]) instead of redirected. Paths whose last
+//! segment has a dot (`/feed.xml`, `/static/js/app.js`) never get one.
+
+use crate::config::{TrailingSlash, UrlsConfig};
+
+/// What to do with a request URL
+#[derive(Debug, PartialEq, Eq)]
+pub enum Normalized {
+    /// Already canonical
+    Unchanged,
+    /// Canonical, but routed as this path (`trailing_slash = "add"`)
+    Route(String),
+    /// Not canonical: `308` to this location
+    Redirect(String),
+}
+
+/// `path` in canonical form
+pub fn canonical_path(path: &str, config: &UrlsConfig) -> String {
+    let mut canonical = String::with_capacity(path.len());
+    for c in path.chars() {
+        // Browsers read `\` as `/`, so `/\evil.example` would leave the site
+        let c = if c == '\\' { '/' } else { c };
+        if c == '/' && config.collapse_slashes && canonical.ends_with('/') {
+            continue;
+        }
+        canonical.push(c);
+    }
+    // A location starting `//` names another host, whatever the config
+    while canonical.starts_with("//") {
+        canonical.remove(0);
+    }
+    match config.trailing_slash {
+        TrailingSlash::Strip => {
+            let trimmed = canonical.trim_end_matches('/');
+            canonical = if trimmed.is_empty() {
+                "/".to_string()
+            } else {
+                trimmed.to_string()
+            };
+        }
+        TrailingSlash::Add => {
+            let last = canonical.rsplit('/').next().unwrap_or_default();
+            if !canonical.ends_with('/') && !last.contains('.') {
+                canonical.push('/');
+            }
+        }
+        TrailingSlash::Keep => {}
+    }
+    canonical
+}
+
+/// Where a request for `host` + `path` (+ `query`) belongs
+pub fn normalize(
+    host: Option<&str>,
+    path: &str,
+    query: Option<&str>,
+    config: &UrlsConfig,
+) -> Normalized {
+    let canonical = canonical_path(path, config);
+    let lower_host = host
+        .filter(|host| config.lowercase_host && host.bytes().any(|b| b.is_ascii_uppercase()))
+        .map(str::to_ascii_lowercase);
+
+    if canonical != path || lower_host.is_some() {
+        let mut location = match lower_host {
+            // Scheme-relative, so it works behind a TLS-terminating proxy
+            Some(host) => format!("//{host}{canonical}"),
+            None => canonical,
+        };
+        if let Some(query) = query {
+            location.push('?');
+            location.push_str(query);
+        }
+        return Normalized::Redirect(location);
+    }
+
+    if config.trailing_slash == TrailingSlash::Add && path.len() > 1 && path.ends_with('/') {
+        return Normalized::Route(path.trim_end_matches('/').to_string());
+    }
+    Normalized::Unchanged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(trailing_slash: TrailingSlash) -> UrlsConfig {
+        UrlsConfig {
+            trailing_slash,
+            ..UrlsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_canonical_path() {
+        let strip = config(TrailingSlash::Strip);
+        assert_eq!(canonical_path("/about/", &strip), "/about");
+        assert_eq!(canonical_path("/blog//hello///", &strip), "/blog/hello");
+        assert_eq!(canonical_path("/", &strip), "/");
+        assert_eq!(canonical_path("//", &strip), "/");
+
+        let add = config(TrailingSlash::Add);
+        assert_eq!(canonical_path("/about", &add), "/about/");
+        assert_eq!(canonical_path("/items/feed.xml", &add), "/items/feed.xml");
+        assert_eq!(canonical_path("/", &add), "/");
+
+        let keep = UrlsConfig {
+            trailing_slash: TrailingSlash::Keep,
+            collapse_slashes: false,
+            lowercase_host: false,
+        };
+        assert_eq!(canonical_path("/a//b/", &keep), "/a//b/");
+        // Never a scheme-relative location
+        assert_eq!(canonical_path("//evil.example/", &keep), "/evil.example/");
+        assert_eq!(canonical_path("/\\evil.example/", &keep), "/evil.example/");
+        assert_eq!(canonical_path("/\\evil.example/", &strip), "/evil.example");
+    }
+
+    #[test]
+    fn test_normalize() {
+        let strip = config(TrailingSlash::Strip);
+        assert_eq!(
+            normalize(Some("localhost:8000"), "/about", None, &strip),
+            Normalized::Unchanged
+        );
+        assert_eq!(
+            normalize(None, "/blog/", Some("page=2"), &strip),
+            Normalized::Redirect("/blog?page=2".into())
+        );
+        assert_eq!(
+            normalize(Some("Example.COM"), "/about", None, &strip),
+            Normalized::Redirect("//example.com/about".into())
+        );
+
+        let add = config(TrailingSlash::Add);
+        assert_eq!(
+            normalize(None, "/about/", None, &add),
+            Normalized::Route("/about".into())
+        );
+        assert_eq!(
+            normalize(None, "/about", None, &add),
+            Normalized::Redirect("/about/".into())
+        );
+    }
+}
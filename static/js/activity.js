@@ -0,0 +1,31 @@
+/* activity.js — live updates for the admin activity feed.
+ * Each event from the feed's stream is a server-rendered day group holding one
+ * entry: its entry joins the top group when the day matches, otherwise the
+ * whole group goes on top. The stream closes when HTMX removes the feed.
+ */
+(function () {
+    var feed = document.getElementById('activity-feed');
+    if (!feed || feed.activityStream || !window.EventSource) return;
+
+    var source = new EventSource(feed.getAttribute('data-activity-stream'));
+    feed.activityStream = source;
+    feed.addEventListener('htmx:beforeCleanupElement', function (e) {
+        if (e.target === feed) source.close();
+    });
+
+    source.addEventListener('activity', function (e) {
+        var template = document.createElement('template');
+        template.innerHTML = e.data.trim();
+        var group = template.content.querySelector('.activity-day');
+        if (!group) return;
+
+        var empty = feed.querySelector('.activity-empty');
+        if (empty) empty.remove();
+        var top = feed.querySelector('.activity-day');
+        if (top && top.getAttribute('data-day') === group.getAttribute('data-day')) {
+            top.querySelector('.activity-items').prepend(group.querySelector('.activity-item'));
+        } else {
+            feed.insertBefore(group, top || feed.firstChild);
+        }
+    });
+})();
@@ -1,55 +1,399 @@
-/* app.js — Minimal UI interactions. This is the ONLY custom JS besides HTMX.
- * SRI-hash this file and add to CSP if you want belt-and-suspenders.
- * Total: ~30 lines. Fully auditable.
+/* app.js — shared UI behaviour, on every page (both layouts).
+ * Error toasts, CSRF refresh, error answers (handlers::error_fragment),
+ * offline queueing, the service worker, fragment diffs, print buttons, and
+ * the loading conventions of utils::loading:
+ *
+ *   - the target of every HTMX request is aria-busy="true" until the response
+ *     is in (.loading-region dims with it, see app.css "Loading States");
+ *   - a form making a request is aria-busy too and its submit buttons are
+ *     disabled, showing data-loading-text if they have it, then restored;
+ *   - forms posted without HTMX get the same disabled buttons, so a double
+ *     click doesn't post twice.
  */
+(function () {
+    // Auto-dismiss error toasts after 5 seconds
+    document.body.addEventListener('htmx:afterSwap', function (e) {
+        if (e.detail.target && e.detail.target.id === 'error-toast') {
+            setTimeout(function () {
+                e.detail.target.innerHTML = '';
+            }, 5000);
+        }
+    });
+
+    // Update CSRF token from response headers on every HTMX request
+    document.body.addEventListener('htmx:afterRequest', function (e) {
+        var token = e.detail.xhr && e.detail.xhr.getResponseHeader('X-CSRF-Token');
+        if (token) {
+            // Update the hx-headers on body with the fresh CSRF token
+            document.body.setAttribute('hx-headers', JSON.stringify({ 'X-CSRF-Token': token }));
+        }
+    });
+
+    // ── Error answers ───────────────────────────────────────────────────────
+    // HTMX leaves 4xx/5xx responses unswapped. Their body is an error fragment
+    // (handlers::error_fragment): show it in the closest data-error-target
+    // region of the element that asked, else where HX-Retarget says, else the
+    // toast. A request that got no answer at all shows the layout's
+    // #send-error template instead. "Try again" reissues the failed request.
+
+    var failed = null;
+
+    function errorRegion(elt, xhr) {
+        var owner = elt.closest && elt.closest('[data-error-target]');
+        var selector = owner ? owner.getAttribute('data-error-target')
+            : (xhr && xhr.getResponseHeader('HX-Retarget')) || '#error-toast';
+        return document.querySelector(selector) || document.getElementById('error-toast');
+    }
+
+    function showError(e, html) {
+        var region = errorRegion(e.detail.elt, e.detail.xhr);
+        if (!region) return;
+        failed = { elt: e.detail.elt, config: e.detail.requestConfig };
+        region.innerHTML = html;
+    }
 
-// Sidebar toggle
-document.getElementById('sidebar-toggle').addEventListener('click', function () {
-    document.getElementById('sidebar').classList.toggle('collapsed');
-});
-
-// Theme toggle — uses CSS [data-theme] attribute, no localStorage (no fingerprinting)
-// If you want persistence, the server can set a theme cookie instead.
-document.getElementById('theme-toggle').addEventListener('click', function () {
-    var html = document.documentElement;
-    var next = html.getAttribute('data-theme') === 'dark' ? 'light' : 'dark';
-    html.setAttribute('data-theme', next);
-});
-
-// Auto-dismiss error toasts after 5 seconds
-document.body.addEventListener('htmx:afterSwap', function (e) {
-    if (e.detail.target && e.detail.target.id === 'error-toast') {
+    // Automatic retries: an answer with X-Retry-After-Ms (load shed) is
+    // worth repeating unchanged. GETs are, after that long, then twice as
+    // long each time, plus jitter so shed clients don't return in step. The
+    // error shows once RETRY_LIMIT retries have failed.
+    var RETRY_LIMIT = 5;
+    var RETRY_CAP_MS = 30000;
+
+    function retryLater(e) {
+        var delay = parseInt(e.detail.xhr.getResponseHeader('X-Retry-After-Ms'), 10);
+        var config = e.detail.requestConfig;
+        var elt = e.detail.elt;
+        // Boosted navigation would need its history entry, so it isn't retried
+        if (!(delay >= 0) || config.verb !== 'get' || config.boosted) return false;
+        var attempt = elt.htmxRetryAttempt || 0;
+        if (attempt >= RETRY_LIMIT) return false;
+        elt.htmxRetryAttempt = attempt + 1;
+        var wait = Math.min(delay * Math.pow(2, attempt), RETRY_CAP_MS);
         setTimeout(function () {
-            e.detail.target.innerHTML = '';
-        }, 5000);
-    }
-});
-
-// Update CSRF token from response headers on every HTMX request
-document.body.addEventListener('htmx:afterRequest', function (e) {
-    var token = e.detail.xhr && e.detail.xhr.getResponseHeader('X-CSRF-Token');
-    if (token) {
-        // Update the hx-headers on body with the fresh CSRF token
-        document.body.setAttribute('hx-headers', JSON.stringify({ 'X-CSRF-Token': token }));
-    }
-});
-
-// SPA navigation — update sidebar active state and page title after content swap
-function updateNavState() {
-    var path = window.location.pathname;
-    document.querySelectorAll('.sidebar-nav .nav-link').forEach(function (link) {
-        link.classList.remove('active');
-        if (link.getAttribute('href') === path) {
-            link.classList.add('active');
-        }
-    });
-    var titles = { '/': 'Home', '/demo': 'Demo', '/components': 'Components', '/security': 'Security', '/about': 'About' };
-    document.title = (titles[path] || 'Page') + ' - Axum HTMX App';
-}
-
-// Forward navigation (HTMX push)
-document.body.addEventListener('htmx:pushedIntoHistory', updateNavState);
-// Back/forward button
-window.addEventListener('popstate', function () {
-    setTimeout(updateNavState, 10);
-});
+            if (elt.isConnected) htmx.ajax('get', config.path, { source: elt });
+        }, wait * (1 + Math.random() / 2));
+        return true;
+    }
+
+    document.body.addEventListener('htmx:afterRequest', function (e) {
+        if (e.detail.successful) delete e.detail.elt.htmxRetryAttempt;
+    });
+
+    document.body.addEventListener('htmx:responseError', function (e) {
+        if (retryLater(e)) return;
+        var xhr = e.detail.xhr;
+        var type = xhr.getResponseHeader('Content-Type') || '';
+        // Only our own fragments are trusted markup; anything else is a bare
+        // status from a proxy, shown as the no-answer fragment
+        if (type.indexOf('text/html') === 0 && xhr.responseText.indexOf('data-error-retry') !== -1) {
+            showError(e, xhr.responseText);
+        } else {
+            showError(e, document.getElementById('send-error').innerHTML);
+        }
+    });
+
+    document.body.addEventListener('htmx:sendError', function (e) {
+        goOffline();
+        // A queueable submission waits for the connection instead
+        if (queueable(e.detail.elt)) {
+            enqueue(e.detail.elt, e.detail.requestConfig);
+            return;
+        }
+        var template = document.getElementById('send-error');
+        if (template) showError(e, template.innerHTML);
+    });
+
+    document.addEventListener('click', function (e) {
+        var button = e.target.closest && e.target.closest('[data-error-retry], [data-error-dismiss]');
+        if (!button) return;
+        var alert = button.closest('.alert');
+        if (alert) alert.remove();
+        if (!button.hasAttribute('data-error-retry')) return;
+        var request = failed;
+        failed = null;
+        // The page the request came from is gone (or it was a boosted
+        // navigation): load the URL itself
+        if (!request || !request.elt.isConnected || request.config.boosted) {
+            if (request && request.config.boosted && request.config.verb === 'get') {
+                window.location.href = request.config.path;
+            } else {
+                window.location.reload();
+            }
+            return;
+        }
+        // Asked for by hand: automatic retries start over
+        delete request.elt.htmxRetryAttempt;
+        htmx.ajax(request.config.verb, request.config.path, { source: request.elt });
+    });
+
+    // ── Offline ─────────────────────────────────────────────────────────────
+    // A request that gets no answer, or the browser's offline event, shows
+    // #offline-banner and polls /healthz (backing off) until the server
+    // answers. Meanwhile a form marked data-offline-queue="name" isn't sent:
+    // its values are kept in memory, and replayed in order once the server is
+    // back. Every such submission carries an Idempotency-Key, the same one on
+    // replay, so one that did arrive before the connection dropped isn't saved
+    // twice (see middleware::idempotency). The queue is lost on reload.
+
+    var offline = false;
+    var queue = [];
+    var flushing = false;
+    var probeTimer = null;
+    var PROBE_MIN_MS = 2000;
+    var PROBE_MAX_MS = 30000;
+
+    function newKey() {
+        var bytes = new Uint8Array(16);
+        crypto.getRandomValues(bytes);
+        return Array.prototype.map.call(bytes, function (b) {
+            return ('0' + b.toString(16)).slice(-2);
+        }).join('');
+    }
+
+    function updateBanner() {
+        var banner = document.getElementById('offline-banner');
+        if (!banner) return;
+        banner.hidden = !offline && queue.length === 0;
+        var title = banner.querySelector('[data-offline-title]');
+        if (title) title.textContent = offline ? "You're offline" : 'Back online, sending…';
+        var count = banner.querySelector('[data-offline-count]');
+        if (count) {
+            count.textContent = queue.length === 0 ? ''
+                : queue.length + (queue.length === 1 ? ' submission' : ' submissions') + ' waiting.';
+        }
+    }
+
+    function goOffline() {
+        if (!offline) {
+            offline = true;
+            updateBanner();
+        }
+        if (!probeTimer) probe(PROBE_MIN_MS);
+    }
+
+    function probe(wait) {
+        probeTimer = setTimeout(function () {
+            fetch('/healthz', { cache: 'no-store' }).then(function (response) {
+                if (!response.ok) throw new Error(response.status);
+                probeTimer = null;
+                goOnline();
+            }).catch(function () {
+                probe(Math.min(wait * 2, PROBE_MAX_MS));
+            });
+        }, wait);
+    }
+
+    function goOnline() {
+        offline = false;
+        updateBanner();
+        flush();
+    }
+
+    // One at a time, in order; a failure puts us back offline
+    function flush() {
+        if (offline || flushing || queue.length === 0) return;
+        var entry = queue[0];
+        var source = entry.elt.isConnected ? entry.elt
+            : document.querySelector('[data-offline-queue="' + entry.name + '"]');
+        if (!source) {
+            queue.shift();
+            updateBanner();
+            flush();
+            return;
+        }
+        flushing = true;
+        htmx.ajax(entry.verb, entry.path, {
+            source: source,
+            values: entry.values,
+            headers: { 'Idempotency-Key': entry.key },
+        }).then(function () {
+            flushing = false;
+            if (!offline) {
+                queue.shift();
+                updateBanner();
+                flush();
+            }
+        }, function () {
+            flushing = false;
+            goOffline();
+        });
+    }
+
+    function queued(key) {
+        return queue.some(function (entry) { return entry.key === key; });
+    }
+
+    // Form values as htmx.ajax takes them: name -> list of values
+    function snapshot(formData) {
+        var values = {};
+        formData.forEach(function (value, name) {
+            (values[name] = values[name] || []).push(value);
+        });
+        return values;
+    }
+
+    function enqueue(elt, config) {
+        var key = config.headers['Idempotency-Key'];
+        if (queued(key)) return;
+        queue.push({
+            elt: elt,
+            name: elt.getAttribute('data-offline-queue'),
+            verb: config.verb,
+            path: config.path,
+            values: snapshot(config.formData),
+            key: key,
+        });
+        if (elt.tagName === 'FORM') elt.reset();
+        updateBanner();
+    }
+
+    function queueable(elt) {
+        return elt.hasAttribute && elt.hasAttribute('data-offline-queue');
+    }
+
+    document.body.addEventListener('htmx:configRequest', function (e) {
+        if (queueable(e.detail.elt) && !e.detail.headers['Idempotency-Key']) {
+            e.detail.headers['Idempotency-Key'] = newKey();
+        }
+    });
+
+    // Offline: keep it for later instead of sending it into the void
+    document.body.addEventListener('htmx:beforeRequest', function (e) {
+        if (!offline || !queueable(e.detail.elt)) return;
+        var config = e.detail.requestConfig;
+        // A replay in progress is the queue's own request
+        if (queued(config.headers['Idempotency-Key'])) return;
+        e.preventDefault();
+        enqueue(e.detail.elt, config);
+    });
+
+    window.addEventListener('offline', goOffline);
+    window.addEventListener('online', function () {
+        if (probeTimer) clearTimeout(probeTimer);
+        probe(0);
+    });
+    if (!navigator.onLine) goOffline();
+
+    // The service worker (handlers::pwa) shows /offline for a page load that
+    // gets no answer at all
+    if ('serviceWorker' in navigator) {
+        navigator.serviceWorker.register('/sw.js', { scope: '/' }).catch(function () {});
+    }
+
+    // ── Fragment diffs ──────────────────────────────────────────────────────
+    // A target remembers the X-Fragment-Digest of the render it shows and
+    // sends it back with its next request, so middleware::diff_fragments can
+    // answer with just the changed children (out-of-band swaps). The digest
+    // is set once the swap has settled, on the element now holding the id:
+    // a target that was replaced some other way asks for a full render.
+
+    document.body.addEventListener('htmx:configRequest', function (e) {
+        var digest = e.detail.target && e.detail.target.getAttribute('data-fragment-digest');
+        if (digest) e.detail.headers['X-Fragment-Digest'] = digest;
+    });
+
+    document.body.addEventListener('htmx:afterSettle', function (e) {
+        var xhr = e.detail.xhr;
+        var config = e.detail.requestConfig;
+        var digest = xhr && xhr.getResponseHeader('X-Fragment-Digest');
+        var id = config && config.headers && config.headers['HX-Target'];
+        var target = id && document.getElementById(id);
+        if (!target) return;
+        if (digest) {
+            target.setAttribute('data-fragment-digest', digest);
+        } else {
+            target.removeAttribute('data-fragment-digest');
+        }
+    });
+
+    // ── Print ───────────────────────────────────────────────────────────────
+    // A [data-print] button prints the page (see app.css "Printed documents")
+
+    document.addEventListener('click', function (e) {
+        if (e.target.closest && e.target.closest('[data-print]')) window.print();
+    });
+
+    // Collapsed <details> would print as their summary alone: the print view
+    // (?view=print) opens them all, and printing any other page opens them
+    // for the printout only
+    function openDetails() {
+        var closed = document.querySelectorAll('#page-content details:not([open])');
+        Array.prototype.forEach.call(closed, function (d) { d.open = true; });
+        return closed;
+    }
+    if (document.body.classList.contains('print-view')) openDetails();
+
+    var openedForPrint = [];
+    window.addEventListener('beforeprint', function () { openedForPrint = openDetails(); });
+    window.addEventListener('afterprint', function () {
+        Array.prototype.forEach.call(openedForPrint, function (d) { d.open = false; });
+        openedForPrint = [];
+    });
+
+    // ── Loading states ──────────────────────────────────────────────────────
+
+    // Submit buttons of a form, including ones outside it with form="id"
+    function submitButtons(form) {
+        return Array.prototype.filter.call(form.elements, function (el) {
+            return (el.tagName === 'BUTTON' && el.type === 'submit') ||
+                (el.tagName === 'INPUT' && (el.type === 'submit' || el.type === 'image'));
+        });
+    }
+
+    function busy(form) {
+        form.setAttribute('aria-busy', 'true');
+        submitButtons(form).forEach(function (button) {
+            // Remember buttons that were disabled anyway, to leave them so
+            if (button.hasAttribute('data-loading-was')) return;
+            button.setAttribute('data-loading-was', button.disabled ? 'disabled' : '');
+            button.disabled = true;
+            var text = button.getAttribute('data-loading-text');
+            if (text && button.tagName === 'BUTTON') {
+                button.setAttribute('data-loading-label', button.innerHTML);
+                button.textContent = text;
+            }
+        });
+    }
+
+    function idle(form) {
+        form.removeAttribute('aria-busy');
+        submitButtons(form).forEach(function (button) {
+            if (!button.hasAttribute('data-loading-was')) return;
+            button.disabled = button.getAttribute('data-loading-was') === 'disabled';
+            button.removeAttribute('data-loading-was');
+            if (button.hasAttribute('data-loading-label')) {
+                button.innerHTML = button.getAttribute('data-loading-label');
+                button.removeAttribute('data-loading-label');
+            }
+        });
+    }
+
+    document.body.addEventListener('htmx:beforeRequest', function (e) {
+        // Cancelled (queued while offline): it won't get an afterRequest
+        if (e.defaultPrevented) return;
+        var target = e.detail.target;
+        if (target && target !== document.body) target.setAttribute('aria-busy', 'true');
+        if (e.detail.elt.tagName === 'FORM') busy(e.detail.elt);
+    });
+
+    document.body.addEventListener('htmx:afterRequest', function (e) {
+        var target = e.detail.target;
+        if (target) target.removeAttribute('aria-busy');
+        if (e.detail.elt.tagName === 'FORM') idle(e.detail.elt);
+    });
+
+    // Plain form posts: disable after the submit is under way (disabling
+    // first would drop the clicked button's name=value from the request)
+    document.addEventListener('submit', function (e) {
+        var form = e.target;
+        // HTMX cancels the submits it takes over, and GET searches can repeat
+        if (e.defaultPrevented || form.method !== 'post') return;
+        setTimeout(function () { busy(form); }, 0);
+    });
+
+    // Back/forward cache restores the page as it was left — busy included
+    window.addEventListener('pageshow', function (e) {
+        if (!e.persisted) return;
+        document.querySelectorAll('form[aria-busy]').forEach(idle);
+    });
+})();
@@ -0,0 +1,123 @@
+/* combobox.js — keyboard support for <input role="combobox">.
+ * HTMX fetches the options (/partials/autocomplete) into the listbox named by
+ * aria-controls; this script shows and hides it, numbers the options and
+ * moves the active one with the arrow keys (aria-activedescendant). Enter
+ * picks the active option, Escape closes the list (or clears the input when
+ * it's already closed), and clicking an option picks it too. Picking sets
+ * the input's value and fires `change`.
+ */
+(function () {
+    // Loaded again whenever HTMX swaps a page with a combobox in — listen only once
+    if (window.comboboxEnhanced) return;
+    window.comboboxEnhanced = true;
+
+    function listFor(input) {
+        return document.getElementById(input.getAttribute('aria-controls'));
+    }
+
+    function inputFor(list) {
+        return document.querySelector('[role="combobox"][aria-controls="' + list.id + '"]');
+    }
+
+    function options(list) {
+        return Array.prototype.slice.call(list.querySelectorAll('[role="option"]'));
+    }
+
+    function setActive(input, option) {
+        var list = listFor(input);
+        options(list).forEach(function (each) {
+            var active = each === option;
+            each.setAttribute('aria-selected', active ? 'true' : 'false');
+            each.classList.toggle('active', active);
+        });
+        if (option) {
+            input.setAttribute('aria-activedescendant', option.id);
+            option.scrollIntoView({ block: 'nearest' });
+        } else {
+            input.removeAttribute('aria-activedescendant');
+        }
+    }
+
+    function open(input, show) {
+        var list = listFor(input);
+        list.hidden = !show;
+        input.setAttribute('aria-expanded', show ? 'true' : 'false');
+        if (!show) setActive(input, null);
+    }
+
+    function pick(input, option) {
+        input.value = option.getAttribute('data-value');
+        open(input, false);
+        input.dispatchEvent(new Event('change', { bubbles: true }));
+    }
+
+    // New options arrived: number them, show the list if there's anything in it
+    document.addEventListener('htmx:afterSwap', function (e) {
+        var list = e.detail.target;
+        if (!list || list.getAttribute('role') !== 'listbox') return;
+        var input = inputFor(list);
+        if (!input) return;
+        var found = options(list);
+        found.forEach(function (option, i) {
+            option.id = list.id + '-' + i;
+        });
+        open(input, list.children.length > 0 && document.activeElement === input);
+        var status = list.parentElement.querySelector('[data-combobox-status]');
+        if (status) {
+            status.textContent = found.length === 1 ? '1 suggestion' : found.length + ' suggestions';
+        }
+    });
+
+    document.addEventListener('keydown', function (e) {
+        var input = e.target;
+        if (!input.matches || !input.matches('[role="combobox"]')) return;
+        var list = listFor(input);
+        var found = options(list);
+        var current = document.getElementById(input.getAttribute('aria-activedescendant'));
+        var index = found.indexOf(current);
+
+        if (e.key === 'ArrowDown' || e.key === 'ArrowUp') {
+            if (!found.length) return;
+            e.preventDefault();
+            if (list.hidden) {
+                open(input, true);
+            }
+            var step = e.key === 'ArrowDown' ? 1 : -1;
+            var next = index === -1
+                ? (step === 1 ? 0 : found.length - 1)
+                : (index + step + found.length) % found.length;
+            setActive(input, found[next]);
+        } else if (e.key === 'Enter') {
+            // Without an active option Enter submits the form as usual
+            if (!list.hidden && current) {
+                e.preventDefault();
+                pick(input, current);
+            }
+        } else if (e.key === 'Escape') {
+            if (!list.hidden) {
+                e.preventDefault();
+                open(input, false);
+            } else if (input.value) {
+                e.preventDefault();
+                input.value = '';
+            }
+        } else if (e.key === 'Tab') {
+            open(input, false);
+        }
+    });
+
+    // mousedown, not click: it comes before the input loses focus
+    document.addEventListener('mousedown', function (e) {
+        var option = e.target.closest && e.target.closest('[role="option"]');
+        if (!option) return;
+        var input = inputFor(option.closest('[role="listbox"]'));
+        if (!input) return;
+        e.preventDefault();
+        pick(input, option);
+    });
+
+    document.addEventListener('focusout', function (e) {
+        var input = e.target;
+        if (input.matches && input.matches('[role="combobox"]')) open(input, false);
+    });
+})();
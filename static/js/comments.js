@@ -0,0 +1,63 @@
+/* comments.js — optimistic updates for the comments demo.
+ * Posting shows the comment straight away, marked pending; deleting fades it.
+ * The server answers with the re-rendered thread, which replaces both. If the
+ * request fails, the pending comment is flagged and the fade is undone.
+ */
+(function () {
+    // Loaded again whenever HTMX swaps the demo page in — listen only once
+    if (window.commentsOptimistic) return;
+    window.commentsOptimistic = true;
+
+    function pendingComment(form) {
+        var body = form.querySelector('textarea[name="body"]');
+        if (!body || !body.value.trim()) return null;
+
+        var comment = document.createElement('div');
+        comment.className = 'comment comment-pending';
+        var meta = document.createElement('div');
+        meta.className = 'comment-meta text-xs text-muted';
+        meta.textContent = 'Posting…';
+        var text = document.createElement('div');
+        text.className = 'comment-body text-sm';
+        text.textContent = body.value; // never innerHTML — the server sanitizes
+        comment.appendChild(meta);
+        comment.appendChild(text);
+        return comment;
+    }
+
+    document.body.addEventListener('htmx:beforeRequest', function (e) {
+        var elt = e.detail.elt;
+        if (elt.hasAttribute('data-optimistic-comment')) {
+            var pending = pendingComment(elt);
+            if (!pending) return;
+            var parent = elt.closest('.comment');
+            if (parent) {
+                var indent = Math.min(Number(parent.getAttribute('data-indent')) + 1, 4);
+                pending.style.marginLeft = 'calc(' + indent + ' * var(--space-6))';
+                parent.after(pending);
+            } else {
+                var list = elt.parentElement.querySelector('.comment-list');
+                if (list) list.appendChild(pending);
+            }
+            elt.pendingComment = pending;
+        } else if (elt.hasAttribute('data-optimistic-delete')) {
+            var target = document.querySelector(elt.getAttribute('data-optimistic-delete'));
+            if (target) target.classList.add('comment-removing');
+        }
+    });
+
+    document.body.addEventListener('htmx:afterRequest', function (e) {
+        if (e.detail.successful) return;
+        var elt = e.detail.elt;
+        if (elt.pendingComment) {
+            elt.pendingComment.classList.add('comment-failed');
+            elt.pendingComment.querySelector('.comment-meta').textContent = 'Not posted';
+            elt.pendingComment = null;
+        }
+        var selector = elt.getAttribute('data-optimistic-delete');
+        if (selector) {
+            var target = document.querySelector(selector);
+            if (target) target.classList.remove('comment-removing');
+        }
+    });
+})();
@@ -0,0 +1,51 @@
+/* copy.js — copy and share buttons, on every page (see utils::clipboard).
+ * The vendored ClipboardJS copies for any [data-clipboard-text] or
+ * [data-clipboard-target] button; this briefly relabels the button "Copied"
+ * ("Copy failed" when the browser refuses). Buttons with
+ * data-share-url stay hidden unless the browser has a share sheet
+ * (navigator.share), which they open. Without JS the text is still
+ * selectable.
+ */
+(function () {
+    if (!window.ClipboardJS) return;
+
+    function relabel(button, text) {
+        if (!button.hasAttribute('data-label')) {
+            button.setAttribute('data-label', button.innerHTML);
+        }
+        button.textContent = text;
+        clearTimeout(button.copyTimer);
+        button.copyTimer = setTimeout(function () {
+            button.innerHTML = button.getAttribute('data-label');
+            button.removeAttribute('data-label');
+        }, 1500);
+    }
+
+    new ClipboardJS('[data-clipboard-text], [data-clipboard-target]')
+        .on('success', function (e) {
+            relabel(e.trigger, 'Copied');
+        })
+        .on('error', function (e) {
+            relabel(e.trigger, 'Copy failed');
+        });
+
+    function enhanceShare(root) {
+        if (!navigator.share || !root.querySelectorAll) return;
+        root.querySelectorAll('[data-share-url][hidden]').forEach(function (button) {
+            button.hidden = false;
+        });
+    }
+
+    document.addEventListener('click', function (e) {
+        var button = e.target.closest && e.target.closest('[data-share-url]');
+        if (!button || !navigator.share) return;
+        navigator.share({
+            title: button.getAttribute('data-share-title') || document.title,
+            url: button.getAttribute('data-share-url'),
+        }).catch(function () { /* dismissed */ });
+    });
+
+    // htmx:load fires for the initial page and every swapped-in fragment
+    document.addEventListener('htmx:load', function (e) { enhanceShare(e.detail.elt); });
+    enhanceShare(document);
+})();
@@ -0,0 +1,19 @@
+/* dev-reload.js — live reload, debug builds only (see utils::live_reload).
+ * Debug builds inject this script with data-dev-reload set to the SSE
+ * endpoint; release builds never serve a page that loads it.
+ */
+(function () {
+    var script = document.querySelector('script[data-dev-reload]');
+    if (!script || !window.EventSource) return;
+
+    var source = new EventSource(script.getAttribute('data-dev-reload'));
+    var lost = false;
+    source.addEventListener('reload', function () {
+        window.location.reload();
+    });
+    // Server restarted (e.g. after a Rust change) — reload once it's back
+    source.addEventListener('error', function () { lost = true; });
+    source.addEventListener('open', function () {
+        if (lost) window.location.reload();
+    });
+})();
@@ -0,0 +1,130 @@
+/*
+ * Stand-in for the htmx head-support extension (htmx 2)
+ *
+ * NOT the upstream release: a local implementation of the behaviour below,
+ * kept until the real file is vendored. `app assets vendor --pin` replaces
+ * it with htmx-ext-head-support 2.0.1 as listed in static/js/vendor.toml,
+ * where it has no pin yet.
+ *
+ * Merges the <head> of a response into the page's <head>:
+ * - boosted navigation and history restores use the "merge" strategy: head
+ *   elements the new page doesn't have are removed, new ones are added, and
+ *   identical ones are kept as they are (scripts aren't run again);
+ * - other requests "append" new elements only;
+ * - <head hx-head="..."> in the response picks the strategy; an element with
+ *   hx-head="re-eval" is re-added (and re-run) every time; hx-preserve="true"
+ *   keeps an element whatever the new page says.
+ *
+ * Events on document.body: htmx:beforeHeadMerge (cancel to skip the merge),
+ * htmx:addingHeadElement and htmx:removingHeadElement (cancel to skip that
+ * element), htmx:afterHeadMerge with { added, kept, removed }.
+ *
+ * Enable with hx-ext="head-support" on <body>.
+ */
+(function () {
+    'use strict';
+
+    var api = null;
+
+    function mergeHead(newContent, defaultMergeStrategy) {
+        if (!newContent || newContent.indexOf('<head') === -1) {
+            return;
+        }
+        // SVGs can carry their own <head>-like markup; keep them out of the match
+        var withoutSvgs = newContent.replace(/<svg(\s[^>]*>|>)([\s\S]*?)<\/svg>/gim, '');
+        var headTag = withoutSvgs.match(/(<head(\s[^>]*>|>)([\s\S]*?)<\/head>)/im);
+        if (!headTag) {
+            return;
+        }
+
+        var htmlDoc = document.createElement('html');
+        htmlDoc.innerHTML = headTag[0];
+        var newHead = htmlDoc.querySelector('head');
+        var currentHead = document.head;
+        if (newHead == null) {
+            return;
+        }
+
+        var added = [];
+        var removed = [];
+        var kept = [];
+        var toAppend = [];
+
+        // New head elements by markup, so identical ones can be matched
+        var newByMarkup = new Map();
+        Array.prototype.forEach.call(newHead.children, function (child) {
+            newByMarkup.set(child.outerHTML, child);
+        });
+
+        var strategy = api.getAttributeValue(newHead, 'hx-head') || defaultMergeStrategy;
+
+        Array.prototype.forEach.call(currentHead.children, function (current) {
+            var inNew = newByMarkup.has(current.outerHTML);
+            var reEval = current.getAttribute('hx-head') === 're-eval';
+            var preserved = api.getAttributeValue(current, 'hx-preserve') === 'true';
+            if (inNew || preserved) {
+                if (reEval) {
+                    removed.push(current);
+                } else {
+                    newByMarkup.delete(current.outerHTML);
+                    kept.push(current);
+                }
+            } else if (strategy === 'append') {
+                if (reEval) {
+                    removed.push(current);
+                    toAppend.push(current);
+                }
+            } else if (api.triggerEvent(document.body, 'htmx:removingHeadElement', { headElement: current }) !== false) {
+                removed.push(current);
+            }
+        });
+
+        newByMarkup.forEach(function (node) {
+            toAppend.push(node);
+        });
+
+        toAppend.forEach(function (node) {
+            // A contextual fragment, so scripts run when inserted
+            var fragment = document.createRange().createContextualFragment(node.outerHTML);
+            if (api.triggerEvent(document.body, 'htmx:addingHeadElement', { headElement: fragment }) !== false) {
+                currentHead.appendChild(fragment);
+                added.push(fragment);
+            }
+        });
+
+        removed.forEach(function (element) {
+            if (element.parentNode === currentHead) {
+                currentHead.removeChild(element);
+            }
+        });
+
+        api.triggerEvent(document.body, 'htmx:afterHeadMerge', { added: added, kept: kept, removed: removed });
+    }
+
+    htmx.defineExtension('head-support', {
+        init: function (apiRef) {
+            api = apiRef;
+
+            htmx.on('htmx:afterSwap', function (evt) {
+                var xhr = evt.detail.xhr;
+                if (xhr && api.triggerEvent(document.body, 'htmx:beforeHeadMerge', evt.detail)) {
+                    mergeHead(xhr.response, evt.detail.boosted ? 'merge' : 'append');
+                }
+            });
+
+            htmx.on('htmx:historyRestore', function (evt) {
+                if (api.triggerEvent(document.body, 'htmx:beforeHeadMerge', evt.detail)) {
+                    if (evt.detail.cacheMiss) {
+                        mergeHead(evt.detail.serverResponse, 'merge');
+                    } else if (evt.detail.item) {
+                        mergeHead(evt.detail.item.head, 'merge');
+                    }
+                }
+            });
+
+            htmx.on('htmx:historyItemCreated', function (evt) {
+                evt.detail.item.head = document.head.outerHTML;
+            });
+        }
+    });
+})();
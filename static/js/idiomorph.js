@@ -0,0 +1,244 @@
+/*
+ * Stand-in for Idiomorph's htmx 2 extension
+ *
+ * NOT the upstream library: a local implementation of the behaviour below,
+ * kept until the real file is vendored. `app assets vendor --pin` replaces
+ * it with idiomorph 0.3.0 as listed in static/js/vendor.toml, where it has
+ * no pin yet.
+ *
+ * Morphs the existing DOM into a response instead of replacing it: elements
+ * are matched by the ids they (and their descendants) carry, then by tag name,
+ * and matched elements are updated in place: attributes synced, children
+ * morphed recursively. Nodes that survive keep their state (focus, caret,
+ * scroll position, open <details>, running CSS transitions, listeners).
+ *
+ * Swap styles, once hx-ext="morph" is on an ancestor:
+ * - hx-swap="morph" / "morph:outerHTML": morph the target itself;
+ * - hx-swap="morph:innerHTML": morph only its children.
+ *
+ * Form fields keep what the user entered unless the response changes their
+ * markup (value, checked, selected); the focused field keeps it regardless.
+ */
+(function () {
+    'use strict';
+
+    // Element -> ids of the element and all of its descendants
+    function idSets(roots) {
+        var sets = new Map();
+        roots.forEach(function (root) {
+            if (root.nodeType !== Node.ELEMENT_NODE) {
+                return;
+            }
+            var withIds = Array.prototype.slice.call(root.querySelectorAll('[id]'));
+            if (root.id) {
+                withIds.push(root);
+            }
+            withIds.forEach(function (elt) {
+                var current = elt;
+                while (current) {
+                    if (!sets.has(current)) {
+                        sets.set(current, new Set());
+                    }
+                    sets.get(current).add(elt.id);
+                    if (roots.indexOf(current) !== -1) {
+                        break;
+                    }
+                    current = current.parentElement;
+                }
+            });
+        });
+        return sets;
+    }
+
+    function sameKind(a, b) {
+        return a.nodeType === b.nodeType && a.nodeName === b.nodeName;
+    }
+
+    function idMatch(ctx, oldNode, newNode) {
+        if (!sameKind(oldNode, newNode)) {
+            return false;
+        }
+        if (oldNode.id && oldNode.id === newNode.id) {
+            return true;
+        }
+        var oldIds = ctx.oldIds.get(oldNode);
+        var newIds = ctx.newIds.get(newNode);
+        if (!oldIds || !newIds) {
+            return false;
+        }
+        var shared = false;
+        newIds.forEach(function (id) {
+            shared = shared || oldIds.has(id);
+        });
+        return shared;
+    }
+
+    function softMatch(oldNode, newNode) {
+        return sameKind(oldNode, newNode) && (oldNode.id || '') === (newNode.id || '');
+    }
+
+    function syncAttributes(oldElt, newElt) {
+        Array.prototype.slice.call(newElt.attributes).forEach(function (attr) {
+            if (oldElt.getAttribute(attr.name) !== attr.value) {
+                oldElt.setAttribute(attr.name, attr.value);
+            }
+        });
+        Array.prototype.slice.call(oldElt.attributes).forEach(function (attr) {
+            if (!newElt.hasAttribute(attr.name)) {
+                oldElt.removeAttribute(attr.name);
+            }
+        });
+    }
+
+    // The server's idea of a form field: what its markup says it holds
+    function markedUpState(elt) {
+        if (elt instanceof HTMLInputElement) {
+            return elt.getAttribute('value') + '|' + elt.hasAttribute('checked');
+        }
+        if (elt instanceof HTMLTextAreaElement) {
+            return elt.defaultValue;
+        }
+        if (elt instanceof HTMLOptionElement) {
+            return elt.hasAttribute('selected');
+        }
+        return null;
+    }
+
+    // Fields keep what the user entered unless the response marks up a
+    // different value; the focused one keeps it either way
+    function syncInputState(oldElt, newElt, before) {
+        if (before === null || before === markedUpState(newElt) ||
+            oldElt === document.activeElement) {
+            return;
+        }
+        if (oldElt instanceof HTMLInputElement) {
+            oldElt.checked = newElt.checked;
+            oldElt.value = newElt.value;
+        } else if (oldElt instanceof HTMLTextAreaElement) {
+            oldElt.value = newElt.value;
+        } else {
+            oldElt.selected = newElt.selected;
+        }
+    }
+
+    function morphNode(ctx, oldNode, newNode) {
+        if (oldNode.nodeType === Node.ELEMENT_NODE) {
+            var before = markedUpState(oldNode);
+            syncAttributes(oldNode, newNode);
+            // A textarea's children are its value, synced below
+            if (!(oldNode instanceof HTMLTextAreaElement)) {
+                morphChildren(ctx, oldNode, newNode);
+            } else if (oldNode.defaultValue !== newNode.defaultValue) {
+                var value = oldNode.value;
+                oldNode.defaultValue = newNode.defaultValue;
+                oldNode.value = value;
+            }
+            syncInputState(oldNode, newNode, before);
+        } else if (oldNode.nodeValue !== newNode.nodeValue) {
+            oldNode.nodeValue = newNode.nodeValue;
+        }
+        return oldNode;
+    }
+
+    function morphChildren(ctx, oldParent, newParent) {
+        var insertionPoint = oldParent.firstChild;
+        Array.prototype.slice.call(newParent.childNodes).forEach(function (newChild) {
+            var match = null;
+            for (var candidate = insertionPoint; candidate; candidate = candidate.nextSibling) {
+                if (idMatch(ctx, candidate, newChild)) {
+                    match = candidate;
+                    break;
+                }
+            }
+            // No id to go by: reuse the node in place if it's the same kind
+            // and carries no ids the new content wants elsewhere
+            if (!match && insertionPoint && softMatch(insertionPoint, newChild) &&
+                !ctx.oldIds.has(insertionPoint)) {
+                match = insertionPoint;
+            }
+
+            if (match) {
+                if (match !== insertionPoint) {
+                    oldParent.insertBefore(match, insertionPoint);
+                }
+                morphNode(ctx, match, newChild);
+                insertionPoint = match.nextSibling;
+            } else {
+                oldParent.insertBefore(document.importNode(newChild, true), insertionPoint);
+            }
+        });
+        while (insertionPoint) {
+            var next = insertionPoint.nextSibling;
+            oldParent.removeChild(insertionPoint);
+            insertionPoint = next;
+        }
+    }
+
+    /*
+     * Morph `oldNode` into `newContent` (a node list). Returns the nodes now
+     * standing for the new content, for htmx to settle and process.
+     */
+    function morph(oldNode, newContent, morphStyle) {
+        var newNodes = Array.prototype.slice.call(newContent);
+        var ctx = {
+            oldIds: idSets([oldNode]),
+            newIds: idSets(newNodes)
+        };
+
+        if (morphStyle === 'innerHTML') {
+            var wrapper = document.createElement('div');
+            newNodes.forEach(function (node) {
+                wrapper.appendChild(node);
+            });
+            morphChildren(ctx, oldNode, wrapper);
+            return Array.prototype.slice.call(oldNode.childNodes);
+        }
+
+        // outerHTML: morph into the matching new node, insert the others
+        // around it
+        var parent = oldNode.parentNode;
+        var target = newNodes.filter(function (node) {
+            return idMatch(ctx, oldNode, node);
+        })[0] || newNodes.filter(function (node) {
+            return softMatch(oldNode, node);
+        })[0];
+        if (!target) {
+            newNodes.forEach(function (node) {
+                parent.insertBefore(node, oldNode);
+            });
+            parent.removeChild(oldNode);
+            return newNodes;
+        }
+        var result = [];
+        var after = null;
+        newNodes.forEach(function (node) {
+            if (node === target) {
+                after = morphNode(ctx, oldNode, node);
+                result.push(after);
+            } else {
+                parent.insertBefore(node, after ? after.nextSibling : oldNode);
+                if (after) {
+                    after = node;
+                }
+                result.push(node);
+            }
+        });
+        return result;
+    }
+
+    window.Idiomorph = { morph: morph };
+
+    htmx.defineExtension('morph', {
+        isInlineSwap: function (swapStyle) {
+            return swapStyle === 'morph' || swapStyle === 'morph:outerHTML';
+        },
+        handleSwap: function (swapStyle, target, fragment) {
+            if (swapStyle === 'morph' || swapStyle === 'morph:outerHTML') {
+                return morph(target, fragment.childNodes, 'outerHTML');
+            }
+            if (swapStyle === 'morph:innerHTML') {
+                return morph(target, fragment.childNodes, 'innerHTML');
+            }
+        }
+    });
+})();
@@ -0,0 +1,175 @@
+/*
+ * Stand-in for Mousetrap
+ *
+ * NOT the upstream library: a local implementation of the parts described
+ * below, kept until the real file is vendored. `app assets vendor --pin`
+ * replaces it with mousetrap 1.6.5 as listed in static/js/vendor.toml,
+ * where it has no pin yet.
+ *
+ * Keyboard shortcuts, API-compatible with the parts of Mousetrap that htmx
+ * pages use:
+ *
+ *   Mousetrap.bind('?', function (e) { ... });
+ *   Mousetrap.bind('g i', function (e) { ... });      // g, then i
+ *   Mousetrap.bind(['mod+k', 'ctrl+k'], callback);
+ *   Mousetrap.unbind('g i');
+ *   Mousetrap.trigger('?');
+ *   Mousetrap.reset();
+ *
+ * Keys are names as `KeyboardEvent.key` gives them, lower-cased (`a`, `?`,
+ * `/`), or one of `esc`, `enter`, `space`, `tab`, `backspace`, `del`, `up`,
+ * `down`, `left`, `right`, `home`, `end`, `pageup`, `pagedown`. Modifiers
+ * `ctrl`, `alt`, `shift`, `meta` and `mod` (meta on macOS, ctrl elsewhere)
+ * join with `+`. Spaces separate the steps of a sequence, which must be typed
+ * within a second of each other.
+ *
+ * A callback returning `false` prevents the default action. Nothing fires
+ * while typing in an input, select, textarea or contenteditable element
+ * unless it has the `mousetrap` class; replace `Mousetrap.stopCallback` to
+ * change that.
+ */
+(function (global) {
+    'use strict';
+
+    var SEQUENCE_TIMEOUT = 1000;
+
+    var NAMES = {
+        escape: 'esc', esc: 'esc', enter: 'enter', ' ': 'space', tab: 'tab',
+        backspace: 'backspace', delete: 'del', arrowup: 'up', arrowdown: 'down',
+        arrowleft: 'left', arrowright: 'right', home: 'home', end: 'end',
+        pageup: 'pageup', pagedown: 'pagedown'
+    };
+
+    var MODIFIERS = ['ctrl', 'alt', 'shift', 'meta'];
+
+    var isMac = /Mac|iPod|iPhone|iPad/.test(navigator.platform);
+
+    // combo string -> callback; sequences are stored under their full text
+    var bindings = {};
+    var pending = [];
+    var timer = null;
+
+    // `mod+K` -> `ctrl+k` (or `meta+k` on macOS), modifiers in a fixed order
+    function normalize(combo) {
+        var parts = combo.toLowerCase().split('+');
+        var key = parts.pop() || '+';
+        var mods = parts.map(function (mod) {
+            return mod === 'mod' ? (isMac ? 'meta' : 'ctrl') : mod;
+        });
+        var ordered = MODIFIERS.filter(function (mod) { return mods.indexOf(mod) !== -1; });
+        return ordered.concat(NAMES[key] || key).join('+');
+    }
+
+    function normalizeSequence(keys) {
+        return keys.trim().split(/\s+/).map(normalize).join(' ');
+    }
+
+    // The combo an event stands for. Shift is left out for printable keys,
+    // since it's already in the character: `?` rather than `shift+?`.
+    function comboFor(e) {
+        var key = e.key.toLowerCase();
+        var name = NAMES[key] || key;
+        var printable = name.length === 1;
+        var mods = MODIFIERS.filter(function (mod) {
+            return e[mod + 'Key'] && !(mod === 'shift' && printable);
+        });
+        return mods.concat(name).join('+');
+    }
+
+    function resetSequence() {
+        pending = [];
+        clearTimeout(timer);
+        timer = null;
+    }
+
+    function fire(callback, e, combo) {
+        if (callback(e, combo) === false && e) {
+            e.preventDefault();
+            e.stopPropagation();
+        }
+    }
+
+    function startsSequence(prefix) {
+        return Object.keys(bindings).some(function (keys) {
+            return keys.indexOf(prefix + ' ') === 0;
+        });
+    }
+
+    function onKeyDown(e) {
+        if (!e.key || ['Control', 'Alt', 'Shift', 'Meta'].indexOf(e.key) !== -1) {
+            return;
+        }
+        if (Mousetrap.stopCallback(e, e.target)) {
+            resetSequence();
+            return;
+        }
+        var combo = comboFor(e);
+        var candidate = pending.concat(combo).join(' ');
+        if (bindings[candidate]) {
+            resetSequence();
+            fire(bindings[candidate], e, candidate);
+            return;
+        }
+        if (startsSequence(candidate)) {
+            pending.push(combo);
+            clearTimeout(timer);
+            timer = setTimeout(resetSequence, SEQUENCE_TIMEOUT);
+            return;
+        }
+        // A broken sequence: the key may still start something on its own
+        resetSequence();
+        if (candidate !== combo) {
+            if (bindings[combo]) {
+                fire(bindings[combo], e, combo);
+            } else if (startsSequence(combo)) {
+                pending.push(combo);
+                timer = setTimeout(resetSequence, SEQUENCE_TIMEOUT);
+            }
+        }
+    }
+
+    function each(keys, fn) {
+        (Array.isArray(keys) ? keys : [keys]).forEach(function (one) {
+            fn(normalizeSequence(one));
+        });
+    }
+
+    var Mousetrap = {
+        bind: function (keys, callback) {
+            each(keys, function (sequence) { bindings[sequence] = callback; });
+            return Mousetrap;
+        },
+
+        unbind: function (keys) {
+            each(keys, function (sequence) { delete bindings[sequence]; });
+            return Mousetrap;
+        },
+
+        trigger: function (keys) {
+            each(keys, function (sequence) {
+                if (bindings[sequence]) {
+                    fire(bindings[sequence], null, sequence);
+                }
+            });
+            return Mousetrap;
+        },
+
+        reset: function () {
+            bindings = {};
+            resetSequence();
+            return Mousetrap;
+        },
+
+        stopCallback: function (e, element) {
+            if (!element || !element.closest || element.closest('.mousetrap')) {
+                return false;
+            }
+            return element.isContentEditable ||
+                ['INPUT', 'SELECT', 'TEXTAREA'].indexOf(element.tagName) !== -1;
+        }
+    };
+
+    document.addEventListener('keydown', onKeyDown);
+
+    global.Mousetrap = Mousetrap;
+})(window);
@@ -0,0 +1,22 @@
+/* notifications.js — live unread badge for the header bell.
+ * Subscribes to the session's notification stream and fires a `notifications`
+ * event on <body>; the bell listens for it (hx-trigger="notifications from:body")
+ * and refetches itself. Without EventSource the badge still updates on every
+ * page load and after mark-as-read.
+ */
+(function () {
+    var menu = document.querySelector('[data-notification-stream]');
+    if (!menu) return;
+
+    if (window.EventSource) {
+        var source = new EventSource(menu.getAttribute('data-notification-stream'));
+        source.addEventListener('notifications', function () {
+            htmx.trigger(document.body, 'notifications');
+        });
+    }
+
+    // Close the dropdown when clicking elsewhere
+    document.addEventListener('click', function (e) {
+        if (menu.open && !menu.contains(e.target)) menu.open = false;
+    });
+})();
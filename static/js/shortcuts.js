@@ -0,0 +1,38 @@
+/* shortcuts.js — keyboard shortcuts (see utils::shortcuts).
+ * Binds each [data-shortcut] element's keys with the vendored Mousetrap and
+ * clicks it when they're typed; the layout renders the registered shortcuts
+ * as hidden boosted links, so navigating keeps the SPA swap. `?` (or the
+ * header's keyboard button) opens the help dialog, which HTMX loads from
+ * /partials/shortcuts the first time; Esc closes it.
+ */
+(function () {
+    var dialog = document.getElementById('shortcut-help');
+    if (!dialog || !window.Mousetrap) return;
+
+    function showHelp() {
+        // Loads the list on first open (hx-trigger="shortcut-help from:body once")
+        htmx.trigger(document.body, 'shortcut-help');
+        if (!dialog.open) dialog.showModal();
+        return false;
+    }
+
+    function bindAll() {
+        Mousetrap.reset();
+        Mousetrap.bind('?', showHelp);
+        document.querySelectorAll('[data-shortcut]').forEach(function (el) {
+            Mousetrap.bind(el.getAttribute('data-shortcut'), function () {
+                if (dialog.open) dialog.close();
+                el.click();
+                return false;
+            });
+        });
+    }
+
+    document.addEventListener('click', function (e) {
+        if (e.target.closest && e.target.closest('[data-shortcut-help]')) showHelp();
+    });
+
+    // Pages may carry their own [data-shortcut] elements; rebind after swaps
+    document.addEventListener('htmx:load', bindAll);
+    bindAll();
+})();
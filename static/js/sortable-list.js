@@ -0,0 +1,24 @@
+/* sortable-list.js — drag handles for <ul data-sortable> (see sortable.js).
+ * Dropping an item dispatches `end`, which the surrounding form turns into a
+ * POST of its hidden `id` inputs, now in the new order (hx-trigger="end").
+ * The server answers with the list out of band, so positions come back
+ * fresh. The move buttons post the same form without dragging.
+ */
+(function () {
+    // Loaded again whenever HTMX swaps the demo page in — listen only once
+    if (window.sortableListEnhanced) return;
+    window.sortableListEnhanced = true;
+
+    function enhanceAll(root) {
+        var lists = root.querySelectorAll ? root.querySelectorAll('[data-sortable]') : [];
+        lists.forEach(function (list) {
+            if (!Sortable.get(list)) {
+                Sortable.create(list, { handle: '.sortable-handle' });
+            }
+        });
+    }
+
+    // htmx:load fires for the initial page and every swapped-in fragment
+    document.addEventListener('htmx:load', function (e) { enhanceAll(e.detail.elt); });
+    enhanceAll(document);
+})();
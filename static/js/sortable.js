@@ -0,0 +1,192 @@
+/*
+ * Stand-in for SortableJS
+ *
+ * NOT the upstream library: a local implementation of the parts described
+ * below, kept until the real file is vendored. `app assets vendor --pin`
+ * replaces it with sortablejs 1.15.2 as listed in static/js/vendor.toml,
+ * where it has no pin yet.
+ *
+ * Drag-and-drop reordering of a list's children, API-compatible with the
+ * parts of SortableJS that htmx pages use:
+ *
+ *   Sortable.create(el, { handle: '.grip', onEnd: function (evt) { ... } })
+ *
+ * Options: `draggable` (selector for the items, default: every child),
+ * `handle` (only drag from inside this selector), `filter` (never drag from
+ * these), `disabled`, `ghostClass` / `chosenClass` (added while dragging),
+ * and the `onStart`, `onUpdate`, `onSort` and `onEnd` callbacks. The same
+ * events are dispatched on `el` as bubbling DOM events (`start`, `update`,
+ * `sort`, `end`) carrying `item`, `from`, `to`, `oldIndex` and `newIndex`, so
+ * `hx-trigger="end"` on the list or an ancestor posts the new order.
+ *
+ * Dragging uses pointer events, so mouse, pen and touch all work.
+ */
+(function (global) {
+    'use strict';
+
+    var DRAG_THRESHOLD = 4;
+
+    function Sortable(el, options) {
+        if (!(this instanceof Sortable)) {
+            return new Sortable(el, options);
+        }
+        this.el = el;
+        this.options = Object.assign({
+            draggable: null,
+            handle: null,
+            filter: null,
+            disabled: false,
+            ghostClass: 'sortable-ghost',
+            chosenClass: 'sortable-chosen',
+            dataIdAttr: 'data-id'
+        }, options || {});
+        this._onPointerDown = this._onPointerDown.bind(this);
+        this._onPointerMove = this._onPointerMove.bind(this);
+        this._onPointerUp = this._onPointerUp.bind(this);
+        el.addEventListener('pointerdown', this._onPointerDown);
+        el.sortable = this;
+    }
+
+    Sortable.create = function (el, options) {
+        return new Sortable(el, options);
+    };
+
+    Sortable.get = function (el) {
+        return el.sortable;
+    };
+
+    Sortable.prototype = {
+        constructor: Sortable,
+
+        option: function (name, value) {
+            if (value === undefined) {
+                return this.options[name];
+            }
+            this.options[name] = value;
+        },
+
+        // The `data-id` of every item, in order
+        toArray: function () {
+            var attr = this.options.dataIdAttr;
+            return this._items().map(function (item) {
+                return item.getAttribute(attr);
+            });
+        },
+
+        destroy: function () {
+            this.el.removeEventListener('pointerdown', this._onPointerDown);
+            this._stop();
+            delete this.el.sortable;
+        },
+
+        _items: function () {
+            var selector = this.options.draggable;
+            return Array.prototype.filter.call(this.el.children, function (child) {
+                return !selector || child.matches(selector);
+            });
+        },
+
+        // The item (a direct child of the list) that `node` is in
+        _itemFor: function (node) {
+            while (node && node.parentNode !== this.el) {
+                node = node.parentNode;
+            }
+            return node && this._items().indexOf(node) !== -1 ? node : null;
+        },
+
+        _onPointerDown: function (e) {
+            var options = this.options;
+            if (options.disabled || this._drag || (e.pointerType === 'mouse' && e.button !== 0)) {
+                return;
+            }
+            if (options.filter && e.target.closest(options.filter)) {
+                return;
+            }
+            if (options.handle && !e.target.closest(options.handle)) {
+                return;
+            }
+            var item = this._itemFor(e.target);
+            if (!item) {
+                return;
+            }
+            this._drag = {
+                item: item,
+                pointerId: e.pointerId,
+                x: e.clientX,
+                y: e.clientY,
+                oldIndex: this._items().indexOf(item),
+                started: false
+            };
+            document.addEventListener('pointermove', this._onPointerMove);
+            document.addEventListener('pointerup', this._onPointerUp);
+            document.addEventListener('pointercancel', this._onPointerUp);
+        },
+
+        _onPointerMove: function (e) {
+            var drag = this._drag;
+            if (!drag || e.pointerId !== drag.pointerId) {
+                return;
+            }
+            if (!drag.started) {
+                if (Math.abs(e.clientX - drag.x) + Math.abs(e.clientY - drag.y) < DRAG_THRESHOLD) {
+                    return;
+                }
+                drag.started = true;
+                drag.item.classList.add(this.options.chosenClass, this.options.ghostClass);
+                this.el.style.touchAction = 'none';
+                this._dispatch('start', drag.item, drag.oldIndex, drag.oldIndex);
+            }
+            e.preventDefault();
+            var under = document.elementFromPoint(e.clientX, e.clientY);
+            var target = under && this._itemFor(under);
+            if (!target || target === drag.item) {
+                return;
+            }
+            var rect = target.getBoundingClientRect();
+            var after = e.clientY > rect.top + rect.height / 2;
+            this.el.insertBefore(drag.item, after ? target.nextSibling : target);
+        },
+
+        _onPointerUp: function (e) {
+            var drag = this._drag;
+            if (!drag || e.pointerId !== drag.pointerId) {
+                return;
+            }
+            this._stop();
+            if (!drag.started) {
+                return;
+            }
+            drag.item.classList.remove(this.options.chosenClass, this.options.ghostClass);
+            this.el.style.touchAction = '';
+            var newIndex = this._items().indexOf(drag.item);
+            if (newIndex !== drag.oldIndex) {
+                this._dispatch('update', drag.item, drag.oldIndex, newIndex);
+                this._dispatch('sort', drag.item, drag.oldIndex, newIndex);
+            }
+            this._dispatch('end', drag.item, drag.oldIndex, newIndex);
+        },
+
+        _stop: function () {
+            document.removeEventListener('pointermove', this._onPointerMove);
+            document.removeEventListener('pointerup', this._onPointerUp);
+            document.removeEventListener('pointercancel', this._onPointerUp);
+            this._drag = null;
+        },
+
+        _dispatch: function (name, item, oldIndex, newIndex) {
+            var event = new CustomEvent(name, { bubbles: true, cancelable: true });
+            event.item = item;
+            event.from = this.el;
+            event.to = this.el;
+            event.oldIndex = oldIndex;
+            event.newIndex = newIndex;
+            var callback = this.options['on' + name.charAt(0).toUpperCase() + name.slice(1)];
+            if (callback) {
+                callback.call(this, event);
+            }
+            this.el.dispatchEvent(event);
+        }
+    };
+
+    global.Sortable = Sortable;
+})(window);
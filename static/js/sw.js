@@ -0,0 +1,52 @@
+/* sw.js — the service worker, registered by app.js.
+ * Served as /sw.js by handlers::pwa, which defines OFFLINE (the offline
+ * page), SHELL (it and every file it loads) and VERSION above this file.
+ *
+ * Installing caches the shell under shell-VERSION; activating drops older
+ * versions. Pages always come from the network: only a page load that fails
+ * gets the offline page. The shell's files are fetched as usual too, and
+ * only taken from the cache while the network is down.
+ */
+'use strict';
+
+var CACHE = 'shell-' + VERSION;
+
+self.addEventListener('install', function (event) {
+    event.waitUntil(
+        caches.open(CACHE)
+            .then(function (cache) { return cache.addAll(SHELL); })
+            .then(function () { return self.skipWaiting(); })
+    );
+});
+
+self.addEventListener('activate', function (event) {
+    event.waitUntil(
+        caches.keys()
+            .then(function (keys) {
+                return Promise.all(keys
+                    .filter(function (key) { return key !== CACHE; })
+                    .map(function (key) { return caches.delete(key); }));
+            })
+            .then(function () { return self.clients.claim(); })
+    );
+});
+
+self.addEventListener('fetch', function (event) {
+    var request = event.request;
+    if (request.method !== 'GET') return;
+
+    if (request.mode === 'navigate') {
+        event.respondWith(fetch(request).catch(function () {
+            return caches.match(OFFLINE, { cacheName: CACHE });
+        }));
+        return;
+    }
+
+    var url = new URL(request.url);
+    if (url.origin !== self.location.origin || SHELL.indexOf(url.pathname) === -1) return;
+    event.respondWith(fetch(request).catch(function () {
+        return caches.match(url.pathname, { cacheName: CACHE }).then(function (cached) {
+            return cached || Response.error();
+        });
+    }));
+});
@@ -0,0 +1,94 @@
+/* tabs.js — tab switching for [data-tabs] (see utils::tabs).
+ * Each tab's button fetches its panel with hx-get on the first click; this
+ * script shows that panel and hides the rest, moves between tabs with the
+ * arrow keys, Home and End, and keeps location.hash on the open panel's id so
+ * deep links and the hashchange of in-page links open the right tab.
+ */
+(function () {
+    // Loaded again whenever HTMX swaps a page with tabs in — listen only once
+    if (window.tabsEnhanced) return;
+    window.tabsEnhanced = true;
+
+    function tabsOf(list) {
+        return Array.prototype.slice.call(list.querySelectorAll('[role="tab"]'));
+    }
+
+    function select(tab) {
+        var list = tab.closest('[role="tablist"]');
+        tabsOf(list).forEach(function (each) {
+            var active = each === tab;
+            each.setAttribute('aria-selected', active ? 'true' : 'false');
+            each.tabIndex = active ? 0 : -1;
+            each.classList.toggle('active', active);
+            document.getElementById(each.getAttribute('aria-controls')).hidden = !active;
+        });
+    }
+
+    // replaceState: switching tabs shouldn't fill up the back button
+    function setHash(tab) {
+        history.replaceState(history.state, '', '#' + tab.getAttribute('aria-controls'));
+    }
+
+    // Open `tab` as if clicked, so its hx-get runs the first time
+    function open(tab) {
+        // The tab may not be processed yet: scripts in swapped content load asynchronously
+        htmx.process(tab);
+        tab.click();
+    }
+
+    // The tab whose panel the hash names, if any
+    function tabForHash() {
+        var id = decodeURIComponent(location.hash.slice(1));
+        if (!id) return null;
+        var panel = document.getElementById(id);
+        if (!panel || panel.getAttribute('role') !== 'tabpanel') return null;
+        return document.querySelector('[role="tab"][aria-controls="' + CSS.escape(id) + '"]');
+    }
+
+    function enhance(root) {
+        var sets = root.querySelectorAll ? root.querySelectorAll('[data-tabs]:not([data-tabs-ready])') : [];
+        sets.forEach(function (set) {
+            set.setAttribute('data-tabs-ready', '');
+            var linked = tabForHash();
+            var tab = linked && set.contains(linked)
+                ? linked
+                : set.querySelector('[role="tab"][aria-selected="true"]');
+            if (tab) open(tab);
+        });
+    }
+
+    document.addEventListener('click', function (e) {
+        var tab = e.target.closest && e.target.closest('[data-tabs] [role="tab"]');
+        if (!tab) return;
+        select(tab);
+        // Only a real click changes the hash; open() doesn't
+        if (e.isTrusted) setHash(tab);
+    });
+
+    document.addEventListener('keydown', function (e) {
+        var tab = e.target.closest && e.target.closest('[data-tabs] [role="tab"]');
+        if (!tab) return;
+        var all = tabsOf(tab.closest('[role="tablist"]'));
+        var index = all.indexOf(tab);
+        var next = {
+            ArrowRight: all[(index + 1) % all.length],
+            ArrowLeft: all[(index - 1 + all.length) % all.length],
+            Home: all[0],
+            End: all[all.length - 1],
+        }[e.key];
+        if (!next) return;
+        e.preventDefault();
+        next.focus();
+        open(next);
+        setHash(next);
+    });
+
+    window.addEventListener('hashchange', function () {
+        var tab = tabForHash();
+        if (tab) open(tab);
+    });
+
+    // htmx:load fires for the initial page and every swapped-in fragment
+    document.addEventListener('htmx:load', function (e) { enhance(e.detail.elt); });
+    enhance(document);
+})();
@@ -0,0 +1,103 @@
+/* tag-input.js — chip editor for <input data-tag-input>.
+ * The original input keeps holding the comma-separated value the server
+ * expects; it's just hidden behind a row of chips plus a text box. Enter or a
+ * comma turns the typed text into a chip, Backspace on an empty box removes
+ * the last one. Without JS the plain comma-separated input still works.
+ */
+(function () {
+    // Loaded again whenever HTMX swaps the demo page in — listen only once
+    if (window.tagInputEnhanced) return;
+    window.tagInputEnhanced = true;
+
+    var MAX_TAGS = 5;
+
+    // Mirrors items::normalize_tag on the server, which has the final say
+    function normalize(raw) {
+        return raw.trim().toLowerCase()
+            .replace(/[\s_-]+/g, '-')
+            .replace(/[^a-z0-9-]/g, '')
+            .replace(/^-+|-+$/g, '')
+            .slice(0, 24)
+            .replace(/-+$/, '');
+    }
+
+    function enhance(input) {
+        var tags = [];
+        var editor = document.createElement('div');
+        editor.className = 'tag-input form-control form-control-sm';
+        var text = document.createElement('input');
+        text.type = 'text';
+        text.placeholder = input.placeholder;
+        text.setAttribute('aria-label', input.placeholder || 'Tags');
+
+        function sync() {
+            input.value = tags.join(',');
+            editor.querySelectorAll('.tag-chip').forEach(function (chip) { chip.remove(); });
+            tags.forEach(function (tag) {
+                var chip = document.createElement('span');
+                chip.className = 'tag-chip tag-chip-sm';
+                chip.textContent = '#' + tag;
+                var remove = document.createElement('button');
+                remove.type = 'button';
+                remove.className = 'tag-chip-remove';
+                remove.setAttribute('aria-label', 'Remove ' + tag);
+                remove.textContent = '×';
+                remove.addEventListener('click', function () {
+                    tags = tags.filter(function (t) { return t !== tag; });
+                    sync();
+                    text.focus();
+                });
+                chip.appendChild(remove);
+                editor.insertBefore(chip, text);
+            });
+            text.disabled = tags.length >= MAX_TAGS;
+        }
+
+        function commit() {
+            text.value.split(',').forEach(function (raw) {
+                var tag = normalize(raw);
+                if (tag && tags.indexOf(tag) === -1 && tags.length < MAX_TAGS) tags.push(tag);
+            });
+            text.value = '';
+            sync();
+        }
+
+        text.addEventListener('keydown', function (e) {
+            if (e.key === 'Enter' || e.key === ',') {
+                e.preventDefault();
+                commit();
+            } else if (e.key === 'Backspace' && !text.value && tags.length) {
+                tags.pop();
+                sync();
+            }
+        });
+        text.addEventListener('blur', commit);
+        editor.addEventListener('click', function (e) {
+            if (e.target === editor) text.focus();
+        });
+        // Pick up anything still in the box when the form goes out
+        if (input.form) input.form.addEventListener('htmx:configRequest', function (e) {
+            commit();
+            e.detail.parameters.tags = input.value;
+        });
+
+        input.value.split(',').forEach(function (raw) {
+            var tag = normalize(raw);
+            if (tag && tags.indexOf(tag) === -1) tags.push(tag);
+        });
+        input.type = 'hidden';
+        input.removeAttribute('data-tag-input');
+        editor.appendChild(text);
+        input.after(editor);
+        sync();
+    }
+
+    function enhanceAll(root) {
+        var inputs = root.querySelectorAll ? root.querySelectorAll('input[data-tag-input]') : [];
+        inputs.forEach(enhance);
+    }
+
+    // htmx:load fires for the initial page and every swapped-in fragment
+    document.addEventListener('htmx:load', function (e) { enhanceAll(e.detail.elt); });
+    enhanceAll(document);
+})();
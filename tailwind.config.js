@@ -0,0 +1,16 @@
+// Tailwind CLI (v3) config, used by build.rs with the `tailwind` feature.
+// Classes are purged to what templates/ uses; `tw-` keeps them apart from
+// the app's own utility classes (`mb-2`, `text-sm`, ...), and preflight is
+// off so the app's base styles stay in charge.
+module.exports = {
+  content: ['./templates/**/*.html'],
+  prefix: 'tw-',
+  corePlugins: {
+    preflight: false,
+  },
+  darkMode: ['class', '[data-theme="dark"]'],
+  theme: {
+    extend: {},
+  },
+  plugins: [],
+};
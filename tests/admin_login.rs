@@ -0,0 +1,104 @@
+//! Admin sign-in across the router
+//!
+//! Signing in or out moves the session to a new ID, so the ID a visitor had
+//! before (perhaps one planted by someone else) never carries admin rights.
+//! Attempts are limited per session by `[rate_limits.admin_login]`.
+
+mod common;
+
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, Method, StatusCode},
+    response::Response,
+};
+use tower::Service;
+
+use app::{
+    middleware,
+    models::AppState,
+    routes,
+    services::{AdminAuth, LiveConfig},
+};
+
+async fn state() -> Arc<AppState> {
+    common::state(|config, services| {
+        config.rate_limits.admin_login.per_session = 3;
+        services.admin = AdminAuth::new("pw");
+        services.live = Arc::new(LiveConfig::new(config.clone()));
+    })
+    .await
+}
+
+/// The `name=value` part of the response's session cookie
+fn session_cookie(response: &Response) -> String {
+    response.headers()[header::SET_COOKIE]
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string()
+}
+
+fn request(method: Method, path: &str, cookie: &str, body: &str) -> Request {
+    Request::builder()
+        .method(method)
+        .uri(path)
+        .header(header::COOKIE, cookie)
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// `POST path` as the session behind `cookie`, with its CSRF token
+async fn post<S>(app: &S, path: &str, cookie: &str, body: &str) -> Response
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone,
+{
+    let page = common::send(app, request(Method::GET, "/admin/login", cookie, "")).await;
+    let token = page.headers()["x-csrf-token"].clone();
+    let mut post = request(Method::POST, path, cookie, body);
+    post.headers_mut().insert("x-csrf-token", token);
+    common::send(app, post).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sign_in_and_out_move_the_session() {
+    let state = state().await;
+    let app = middleware::stack(routes::router(state.clone()), state);
+
+    let planted = session_cookie(&common::get(&app, "/admin/login").await);
+    let signed_in = post(&app, "/admin/login", &planted, "password=pw").await;
+    assert_eq!(signed_in.status(), StatusCode::SEE_OTHER);
+    let admin = session_cookie(&signed_in);
+    assert_ne!(admin, planted);
+
+    let as_planted = common::send(&app, request(Method::GET, "/admin/posts", &planted, "")).await;
+    assert_eq!(as_planted.status(), StatusCode::SEE_OTHER, "old ID");
+    let as_admin = common::send(&app, request(Method::GET, "/admin/posts", &admin, "")).await;
+    assert_eq!(as_admin.status(), StatusCode::OK);
+
+    let signed_out = post(&app, "/admin/logout", &admin, "").await;
+    let visitor = session_cookie(&signed_out);
+    assert_ne!(visitor, admin);
+    let as_admin = common::send(&app, request(Method::GET, "/admin/posts", &admin, "")).await;
+    assert_eq!(as_admin.status(), StatusCode::SEE_OTHER, "signed out");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sign_in_attempts_are_limited() {
+    let state = state().await;
+    let app = middleware::stack(routes::router(state.clone()), state);
+
+    let cookie = session_cookie(&common::get(&app, "/admin/login").await);
+    for _ in 0..3 {
+        let response = post(&app, "/admin/login", &cookie, "password=guess").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let response = post(&app, "/admin/login", &cookie, "password=pw").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
@@ -0,0 +1,159 @@
+//! API tokens on the exports and feeds
+//!
+//! `Authorization: Bearer` lets a script in where the token's scopes allow,
+//! including past the admin guard of `/admin/subscribers.csv`. Unknown,
+//! revoked or under-scoped tokens are refused; requests without one are
+//! unaffected unless `[admin] private_feeds` is on. Answers let in by a token
+//! or an admin's session are never cached.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, StatusCode},
+};
+
+use app::{
+    middleware,
+    models::AppState,
+    routes,
+    services::{
+        admin::ADMIN_KEY,
+        api_tokens::{self, Scope},
+        AdminAuth,
+    },
+};
+
+async fn state(private_feeds: bool) -> Arc<AppState> {
+    common::state(|config, services| {
+        config.admin.private_feeds = private_feeds;
+        services.admin = AdminAuth::new("pw");
+    })
+    .await
+}
+
+fn token(state: &AppState, scopes: &[Scope]) -> (u32, String) {
+    let token = api_tokens::validate("test", scopes).unwrap();
+    let (token, secret) = state.services.api_tokens.create(token);
+    (token.id, secret)
+}
+
+fn request(path: &str, bearer: Option<&str>) -> Request {
+    let mut request = Request::builder().uri(path);
+    if let Some(secret) = bearer {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {secret}"));
+    }
+    request.body(Body::empty()).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tokens_by_scope() {
+    let state = state(false).await;
+    let app = middleware::stack(routes::router(state.clone()), state.clone());
+    let (_, feeds) = token(&state, &[Scope::Feeds]);
+    let (exports_id, exports) = token(&state, &[Scope::Exports]);
+
+    let cases = [
+        ("/items/feed.xml", None, StatusCode::OK),
+        ("/items/feed.xml", Some(feeds.as_str()), StatusCode::OK),
+        ("/calendar.ics", Some(feeds.as_str()), StatusCode::OK),
+        (
+            "/items/feed.xml",
+            Some(exports.as_str()),
+            StatusCode::FORBIDDEN,
+        ),
+        (
+            "/items/feed.xml",
+            Some("aht_unknown"),
+            StatusCode::UNAUTHORIZED,
+        ),
+        ("/items/export.csv", Some(exports.as_str()), StatusCode::OK),
+        (
+            "/admin/subscribers.csv",
+            Some(exports.as_str()),
+            StatusCode::OK,
+        ),
+        (
+            "/admin/subscribers.csv",
+            Some(feeds.as_str()),
+            StatusCode::FORBIDDEN,
+        ),
+    ];
+    for (path, bearer, status) in cases {
+        let response = common::send(&app, request(path, bearer)).await;
+        assert_eq!(response.status(), status, "{path} with {bearer:?}");
+    }
+
+    let refused = common::send(&app, request("/blog/feed.xml", Some("aht_unknown"))).await;
+    assert_eq!(
+        refused.headers()[header::WWW_AUTHENTICATE],
+        r#"Bearer error="invalid_token""#
+    );
+
+    // Without a token the admin export still wants a signed-in admin
+    let anonymous = common::get(&app, "/admin/subscribers.csv").await;
+    assert_ne!(anonymous.status(), StatusCode::OK);
+
+    assert!(state.services.api_tokens.revoke(exports_id));
+    let revoked = common::send(&app, request("/admin/subscribers.csv", Some(&exports))).await;
+    assert_eq!(revoked.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_private_feeds() {
+    let state = state(true).await;
+    let app = middleware::stack(routes::router(state.clone()), state.clone());
+    let (_, feeds) = token(&state, &[Scope::Feeds]);
+
+    let anonymous = common::get(&app, "/blog/feed.xml").await;
+    assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(anonymous.headers()[header::WWW_AUTHENTICATE], "Bearer");
+    assert_eq!(
+        common::get(&app, "/items/export.csv").await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let with_token = common::send(&app, request("/blog/feed.xml", Some(&feeds))).await;
+    assert_eq!(with_token.status(), StatusCode::OK);
+    assert_eq!(
+        with_token.headers()[header::CACHE_CONTROL],
+        "private, no-store"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_private_feeds_for_signed_in_admins() {
+    let state = state(true).await;
+    let app = middleware::stack(routes::router(state.clone()), state.clone());
+    let sessions = state.services.sessions.as_ref();
+    let admin = sessions.create();
+    sessions.set_data(&admin.id, ADMIN_KEY, "1".into());
+    let visitor = sessions.create();
+    let cookie_name = state.config.sessions.cookie_name(false);
+
+    for path in ["/blog/feed.xml", "/items/feed.xml", "/calendar.ics"] {
+        let mut as_admin = request(path, None);
+        as_admin.headers_mut().insert(
+            header::COOKIE,
+            format!("{cookie_name}={}", admin.id).parse().unwrap(),
+        );
+        let response = common::send(&app, as_admin).await;
+        assert_eq!(response.status(), StatusCode::OK, "{path}");
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            "private, no-store",
+            "{path}"
+        );
+
+        let mut as_visitor = request(path, None);
+        as_visitor.headers_mut().insert(
+            header::COOKIE,
+            format!("{cookie_name}={}", visitor.id).parse().unwrap(),
+        );
+        let response = common::send(&app, as_visitor).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{path}");
+    }
+}
@@ -0,0 +1,32 @@
+//! Calendar months at the edge of the date range
+//!
+//! A month chrono can parse but not step past would panic computing the
+//! previous or next month, in the calendar and in the date picker; those
+//! count as no month, so the current one is shown instead.
+
+mod common;
+
+use axum::http::StatusCode;
+
+use app::{middleware, routes};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_months_out_of_range() {
+    let state = common::state(|_, _| {}).await;
+    let app = middleware::stack(routes::router(state.clone()), state);
+
+    for month in [
+        "%2B262142-12",
+        "262142-12",
+        "-262143-01",
+        "10000-01",
+        "0000-01",
+    ] {
+        for path in ["/calendar", "/partials/calendar", "/partials/datepicker"] {
+            let response = common::get(&app, &format!("{path}?month={month}")).await;
+            assert_eq!(response.status(), StatusCode::OK, "{path}?month={month}");
+        }
+    }
+    let picker = "/partials/datepicker?field=f&name=due&selected=%2B262142-12-31";
+    assert_eq!(common::get(&app, picker).await.status(), StatusCode::OK);
+}
@@ -0,0 +1,54 @@
+//! Fixtures shared by the router-level integration tests
+//!
+//! Each test binary compiles its own copy and uses only some of it.
+#![allow(dead_code)]
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use axum::{body::Body, extract::Request, response::Response};
+use tower::{Service, ServiceExt};
+
+use app::{
+    config::{AppConfig, DatabaseConfig},
+    db,
+    models::AppState,
+    services::Services,
+};
+
+/// App state over an in-memory database, default config and services
+/// adjusted by `setup` first
+pub async fn state(setup: impl FnOnce(&mut AppConfig, &mut Services)) -> Arc<AppState> {
+    let db = db::init_pool(&DatabaseConfig {
+        url: "sqlite::memory:".into(),
+        ..DatabaseConfig::default()
+    })
+    .await
+    .unwrap();
+    let mut config = AppConfig::default();
+    let mut services = Services::new_default(SystemTime::now());
+    setup(&mut config, &mut services);
+    Arc::new(AppState::new(services, db, config))
+}
+
+/// Send `request` through `app`
+pub async fn send<S>(app: &S, request: Request) -> Response
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone,
+{
+    let Ok(response) = app.clone().oneshot(request).await;
+    response
+}
+
+/// `GET path` through `app`
+pub async fn get<S>(app: &S, path: &str) -> Response
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone,
+{
+    send(
+        app,
+        Request::builder().uri(path).body(Body::empty()).unwrap(),
+    )
+    .await
+}
@@ -0,0 +1,85 @@
+//! `/embed/<widget>` for other sites' iframes
+//!
+//! Only the configured origins may frame a widget, and only with an API
+//! token for embeds in the query string. Everything else keeps
+//! `frame-ancestors 'none'`.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+
+use app::{
+    middleware,
+    models::AppState,
+    routes,
+    services::api_tokens::{self, Scope},
+};
+
+async fn state(frame_ancestors: &[&str]) -> Arc<AppState> {
+    common::state(|config, _| {
+        config.embeds.frame_ancestors = frame_ancestors.iter().map(|s| s.to_string()).collect();
+    })
+    .await
+}
+
+fn token(state: &AppState, scope: Scope) -> String {
+    let token = api_tokens::validate("dashboard", &[scope]).unwrap();
+    state.services.api_tokens.create(token).1
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_embed_headers_and_access() {
+    let state = state(&["https://tools.example.com", "https://grafana.example.com"]).await;
+    let app = middleware::stack(routes::router(state.clone()), state.clone());
+    let embeds = token(&state, Scope::Embeds);
+    let feeds = token(&state, Scope::Feeds);
+
+    for widget in ["status", "items"] {
+        let response = common::get(&app, &format!("/embed/{widget}?token={embeds}")).await;
+        assert_eq!(response.status(), StatusCode::OK, "{widget}");
+        let h = response.headers();
+        let csp = h[header::CONTENT_SECURITY_POLICY].to_str().unwrap();
+        assert!(
+            csp.contains("frame-ancestors https://tools.example.com https://grafana.example.com;")
+        );
+        assert!(!h.contains_key(header::X_FRAME_OPTIONS));
+        assert!(!h.contains_key(header::SET_COOKIE));
+        assert_eq!(h["cross-origin-resource-policy"], "cross-origin");
+    }
+
+    let cases = [
+        ("/embed/status", StatusCode::UNAUTHORIZED),
+        ("/embed/status?token=aht_unknown", StatusCode::UNAUTHORIZED),
+        (
+            &*format!("/embed/status?token={feeds}"),
+            StatusCode::FORBIDDEN,
+        ),
+        (
+            &*format!("/embed/nope?token={embeds}"),
+            StatusCode::NOT_FOUND,
+        ),
+    ];
+    for (path, status) in cases {
+        assert_eq!(common::get(&app, path).await.status(), status, "{path}");
+    }
+
+    // Pages stay unframeable
+    let page = common::get(&app, "/about").await;
+    let csp = page.headers()[header::CONTENT_SECURITY_POLICY]
+        .to_str()
+        .unwrap();
+    assert!(csp.contains("frame-ancestors 'none';"));
+    assert_eq!(page.headers()[header::X_FRAME_OPTIONS], "DENY");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_embeds_off_without_origins() {
+    let state = state(&[]).await;
+    let app = middleware::stack(routes::router(state.clone()), state.clone());
+    let embeds = token(&state, Scope::Embeds);
+
+    let response = common::get(&app, &format!("/embed/status?token={embeds}")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,114 @@
+//! Polled fragments answered with just what changed
+//!
+//! A poll whose page still shows the last render gets the changed children
+//! as out-of-band swaps, or a 204 when nothing changed. A first poll, a stale
+//! digest or a render that doesn't line up gets the whole fragment.
+
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::StatusCode,
+    middleware::from_fn,
+    response::{Html, Response},
+    routing::get,
+    Router,
+};
+
+use app::{
+    middleware::{diff_fragments, FRAGMENT_DIGEST},
+    models::AppState,
+    services::session::SessionId,
+};
+
+/// What the card shows on each poll
+const RENDERS: [&str; 4] = [
+    r#"<div id="a">1</div><div id="b">2</div>"#,
+    r#"<div id="a">1</div><div id="b">two</div>"#,
+    r#"<div id="a">1</div><div id="b">two</div>"#,
+    r#"<div id="b">two</div><div id="a">1</div>"#,
+];
+
+fn card() -> Router {
+    let polls = Arc::new(AtomicUsize::new(0));
+    Router::new().route(
+        "/card",
+        get(move || async move { Html(RENDERS[polls.fetch_add(1, Ordering::SeqCst)]) })
+            .route_layer(from_fn(diff_fragments)),
+    )
+}
+
+async fn poll(app: &Router, state: &Arc<AppState>, digest: Option<&str>) -> (Response, String) {
+    let mut request = Request::builder()
+        .uri("/card")
+        .header("HX-Request", "true")
+        .header("HX-Target", "card")
+        .extension(state.clone())
+        .extension(SessionId("session".into()));
+    if let Some(digest) = digest {
+        request = request.header(FRAGMENT_DIGEST, digest);
+    }
+    let response = common::send(app, request.body(Body::empty()).unwrap()).await;
+    let (parts, body) = response.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+    (
+        Response::from_parts(parts, Body::empty()),
+        String::from_utf8(body.to_vec()).unwrap(),
+    )
+}
+
+fn digest(response: &Response) -> String {
+    response.headers()[FRAGMENT_DIGEST]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_polls_get_diffs() {
+    let state = common::state(|_, _| {}).await;
+    let app = card();
+
+    // First poll: the whole card
+    let (response, body) = poll(&app, &state, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body, RENDERS[0]);
+    let first = digest(&response);
+
+    // `#b` changed: just that, out of band
+    let (response, body) = poll(&app, &state, Some(&first)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["HX-Reswap"], "none");
+    assert_eq!(body, r#"<div hx-swap-oob="true" id="b">two</div>"#);
+    let second = digest(&response);
+    assert_ne!(first, second);
+
+    // Nothing changed
+    let (response, body) = poll(&app, &state, Some(&second)).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(body, "");
+    assert_eq!(digest(&response), second);
+
+    // Reordered children don't line up: the whole card
+    let (response, body) = poll(&app, &state, Some(&second)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key("HX-Reswap"));
+    assert_eq!(body, RENDERS[3]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stale_digest_gets_everything() {
+    let state = common::state(|_, _| {}).await;
+    let app = card();
+
+    let (response, _) = poll(&app, &state, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    // Another tab, showing something else
+    let (response, body) = poll(&app, &state, Some("0000000000000000")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body, RENDERS[1]);
+}
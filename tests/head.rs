@@ -0,0 +1,141 @@
+//! `HEAD` across the router
+//!
+//! Every `GET` route is asked twice through [`middleware::stack`], as `main`
+//! serves it: once with `GET`, once with `HEAD`. `HEAD` must answer at once,
+//! with `GET`'s status and content type and an empty body — including event
+//! streams, streamed downloads, the long poll, redirects and the 404 page.
+//! The routes are every sitemap path plus the ones below.
+//!
+//! The long poll's `GET` parks until the items change, so only its `HEAD`
+//! is asked: it must be answered like a poll that timed out.
+
+mod common;
+
+use std::time::Duration;
+
+use axum::{
+    body::{Body, HttpBody},
+    extract::Request,
+    http::{header, Method, StatusCode},
+    response::Response,
+};
+
+use app::{handlers::seo, middleware, routes};
+
+/// Routes the sitemap doesn't list
+const EXTRA_PATHS: &[&str] = &[
+    "/healthz",
+    "/version",
+    "/robots.txt",
+    "/sitemap.xml",
+    "/items/feed.xml",
+    "/items/export.csv",
+    "/calendar.ics",
+    "/favicon.ico",
+    "/manifest.webmanifest",
+    "/sw.js",
+    "/offline",
+    "/partials/item-list",
+    "/partials/status-card",
+    "/notifications/stream",
+    "/static/js/htmx.min.js",
+    // A redirect, a 404, an admin page without a session
+    "/about/",
+    "/no-such-page",
+    "/admin/requests",
+];
+
+/// Routes whose `GET` parks until something changes; only `HEAD` is checked
+const PARKED_PATHS: &[&str] = &["/partials/item-list/changes?since=0"];
+
+/// How long a request may take; the long poll would park for 25 seconds
+const PATIENCE: Duration = Duration::from_secs(5);
+
+fn request(method: Method, path: &str) -> Request {
+    Request::builder()
+        .method(method)
+        .uri(path)
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn content_type(response: &Response) -> Option<&str> {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_head_matches_get_without_a_body() {
+    let state = common::state(|_, _| {}).await;
+    let app = middleware::stack(routes::router(state.clone()), state);
+
+    let mut paths = seo::sitemap_paths();
+    paths.extend_from_slice(EXTRA_PATHS);
+    for path in paths {
+        let head = tokio::time::timeout(PATIENCE, common::send(&app, request(Method::HEAD, path)))
+            .await
+            .unwrap_or_else(|_| panic!("HEAD {path} took over {PATIENCE:?}"));
+        // Streams never end, so GET's body isn't read
+        let get = tokio::time::timeout(PATIENCE, common::send(&app, request(Method::GET, path)))
+            .await
+            .unwrap_or_else(|_| panic!("GET {path} took over {PATIENCE:?}"));
+
+        assert_ne!(head.status(), StatusCode::METHOD_NOT_ALLOWED, "HEAD {path}");
+        assert_eq!(head.status(), get.status(), "HEAD {path}: status");
+        assert_eq!(
+            content_type(&head),
+            content_type(&get),
+            "HEAD {path}: content type"
+        );
+        assert_eq!(
+            head.headers().get(header::LOCATION),
+            get.headers().get(header::LOCATION),
+            "HEAD {path}: location"
+        );
+        assert!(
+            head.headers().get(header::CONTENT_LENGTH).is_some()
+                || get
+                    .body()
+                    .size_hint()
+                    .exact()
+                    .is_none_or(|length| length == 0),
+            "HEAD {path}: a body of known length needs its Content-Length"
+        );
+        let body = axum::body::to_bytes(head.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty(), "HEAD {path}: {} body bytes", body.len());
+    }
+
+    for path in PARKED_PATHS {
+        let head = tokio::time::timeout(PATIENCE, common::send(&app, request(Method::HEAD, path)))
+            .await
+            .unwrap_or_else(|_| panic!("HEAD {path} took over {PATIENCE:?}"));
+        assert_eq!(head.status(), StatusCode::OK, "HEAD {path}");
+        let body = axum::body::to_bytes(head.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty(), "HEAD {path}: {} body bytes", body.len());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_head_content_length_is_gets() {
+    let state = common::state(|_, _| {}).await;
+    let app = middleware::stack(routes::router(state.clone()), state);
+
+    for path in ["/robots.txt", "/static/js/htmx.min.js", "/healthz"] {
+        let head = common::send(&app, request(Method::HEAD, path)).await;
+        let get = common::send(&app, request(Method::GET, path)).await;
+        let body = axum::body::to_bytes(get.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            head.headers()[header::CONTENT_LENGTH],
+            body.len().to_string(),
+            "HEAD {path}"
+        );
+    }
+}
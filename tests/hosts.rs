@@ -0,0 +1,66 @@
+//! Host dispatch across the router
+//!
+//! With `[admin] host` set, the admin pages are served on that host only:
+//! the site's host and tenant subdomains answer them with the 404 page, and
+//! the admin host still serves the rest of the site around them.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, StatusCode},
+};
+
+use app::{
+    config::{TenancyMode, TenantConfig},
+    middleware,
+    models::AppState,
+    routes,
+    services::{AdminAuth, Tenants},
+};
+
+async fn state() -> Arc<AppState> {
+    common::state(|config, services| {
+        config.admin.host = "admin.example.com".into();
+        config.tenancy.mode = TenancyMode::Subdomain;
+        config.tenancy.base_domain = "example.com".into();
+        config
+            .tenancy
+            .tenants
+            .insert("acme".into(), TenantConfig::default());
+        services.admin = AdminAuth::new("pw");
+        services.tenants = Arc::new(Tenants::new(&config.tenancy));
+    })
+    .await
+}
+
+fn request(host: &str, path: &str) -> Request {
+    Request::builder()
+        .uri(path)
+        .header(header::HOST, host)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_admin_pages_only_on_admin_host() {
+    let state = state().await;
+    let app = middleware::stack(routes::router(state.clone()), state);
+
+    let cases = [
+        ("admin.example.com", "/admin/login", StatusCode::OK),
+        ("admin.example.com:8000", "/admin/login", StatusCode::OK),
+        ("admin.example.com", "/about", StatusCode::OK),
+        ("example.com", "/admin/login", StatusCode::NOT_FOUND),
+        ("example.com", "/about", StatusCode::OK),
+        ("acme.example.com", "/admin/login", StatusCode::NOT_FOUND),
+        ("acme.example.com", "/about", StatusCode::OK),
+    ];
+    for (host, path, status) in cases {
+        let response = common::send(&app, request(host, path)).await;
+        assert_eq!(response.status(), status, "{host}{path}");
+    }
+}
@@ -0,0 +1,88 @@
+//! `Idempotency-Key` claims that don't end in a stored answer
+//!
+//! A handler that panics, or a request dropped before it's answered, must
+//! release its key, so the client's retry runs the handler instead of
+//! getting a 409 for the next 24 hours.
+
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body, extract::Request, http::StatusCode, middleware::from_fn, routing::post, Router,
+};
+use tower::ServiceExt;
+use tower_http::catch_panic::CatchPanicLayer;
+
+use app::{
+    middleware::{idempotency, panic_response, IDEMPOTENCY_KEY},
+    models::AppState,
+    services::session::SessionId,
+};
+
+fn request(state: &Arc<AppState>) -> Request {
+    Request::builder()
+        .method("POST")
+        .uri("/save")
+        .header(IDEMPOTENCY_KEY, "key-1")
+        .extension(state.clone())
+        .extension(SessionId("session".into()))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_panicking_handler_releases_the_key() {
+    let state = common::state(|_, _| {}).await;
+    let calls = Arc::new(AtomicUsize::new(0));
+    let handler_calls = calls.clone();
+    let app = Router::new()
+        .route(
+            "/save",
+            post(move || async move {
+                if handler_calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    panic!("first attempt fails");
+                }
+                StatusCode::CREATED
+            }),
+        )
+        .layer(from_fn(idempotency))
+        .layer(CatchPanicLayer::custom(panic_response));
+
+    let first = common::send(&app, request(&state)).await;
+    assert_eq!(first.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let retry = common::send(&app, request(&state)).await;
+    assert_eq!(retry.status(), StatusCode::CREATED);
+    let replay = common::send(&app, request(&state)).await;
+    assert_eq!(replay.status(), StatusCode::CREATED);
+    assert_eq!(replay.headers()["idempotent-replayed"], "true");
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_dropped_request_releases_the_key() {
+    let state = common::state(|_, _| {}).await;
+    let calls = Arc::new(AtomicUsize::new(0));
+    let handler_calls = calls.clone();
+    let app = Router::new()
+        .route(
+            "/save",
+            post(move || async move {
+                if handler_calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    std::future::pending::<()>().await;
+                }
+                StatusCode::CREATED
+            }),
+        )
+        .layer(from_fn(idempotency));
+
+    let first = app.clone().oneshot(request(&state));
+    assert!(tokio::time::timeout(Duration::from_millis(50), first)
+        .await
+        .is_err());
+    let retry = common::send(&app, request(&state)).await;
+    assert_eq!(retry.status(), StatusCode::CREATED);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,73 @@
+//! Per-listener routes across the router
+//!
+//! Each request carries the [`ListenerInfo`] of the listener it came in on:
+//! `/metrics` is served only where `metrics = true`, the admin pages
+//! everywhere but where `admin = false`. Elsewhere both are the 404 page, as
+//! if the routes didn't exist.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, StatusCode},
+};
+
+use app::{middleware, models::AppState, routes, server::ListenerInfo, services::AdminAuth};
+
+async fn state() -> Arc<AppState> {
+    common::state(|_, services| services.admin = AdminAuth::new("pw")).await
+}
+
+fn request(listener: Option<&ListenerInfo>, path: &str) -> Request {
+    let mut request = Request::builder().uri(path).body(Body::empty()).unwrap();
+    if let Some(listener) = listener {
+        request.extensions_mut().insert(listener.clone());
+    }
+    request
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_routes_follow_listener_flags() {
+    let state = state().await;
+    let app = middleware::stack(routes::router(state.clone()), state);
+
+    let main = ListenerInfo::default();
+    let monitoring = ListenerInfo {
+        name: "monitoring".into(),
+        metrics: true,
+        admin: false,
+        tls: false,
+    };
+    let cases = [
+        (None, "/metrics", StatusCode::NOT_FOUND),
+        (None, "/admin/login", StatusCode::OK),
+        (Some(&main), "/metrics", StatusCode::NOT_FOUND),
+        (Some(&main), "/admin/login", StatusCode::OK),
+        (Some(&main), "/about", StatusCode::OK),
+        (Some(&monitoring), "/metrics", StatusCode::OK),
+        (Some(&monitoring), "/admin/login", StatusCode::NOT_FOUND),
+        (Some(&monitoring), "/admin/posts", StatusCode::NOT_FOUND),
+        (Some(&monitoring), "/about", StatusCode::OK),
+    ];
+    for (listener, path, status) in cases {
+        let response = common::send(&app, request(listener, path)).await;
+        let name = listener.map_or("none", |listener| &listener.name);
+        assert_eq!(response.status(), status, "{name}: {path}");
+    }
+
+    let response = common::send(&app, request(Some(&monitoring), "/metrics")).await;
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/plain; version=0.0.4; charset=utf-8"
+    );
+    // No session for a scraper
+    assert!(response.headers().get(header::SET_COOKIE).is_none());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains("# TYPE app_requests_total counter"), "{body}");
+}
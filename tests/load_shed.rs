@@ -0,0 +1,33 @@
+//! Load shedding across the router
+//!
+//! Past `[rate_limits.load_shed] max_in_flight` pages get a 503, but health
+//! checks and the event streams don't: a stream holds its slot for as long
+//! as the tab is open, so counting streams would let idle tabs shed everyone
+//! else.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+
+use app::{middleware, routes, services::LiveConfig};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_streams_are_never_shed() {
+    let state = common::state(|config, services| {
+        config.rate_limits.load_shed.max_in_flight = 0;
+        services.live = Arc::new(LiveConfig::new(config.clone()));
+    })
+    .await;
+    let app = middleware::stack(routes::router(state.clone()), state);
+
+    let page = common::get(&app, "/about").await;
+    assert_eq!(page.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(page.headers()["x-retry-after-ms"], "1000");
+
+    for path in ["/healthz", "/notifications/stream"] {
+        let response = common::get(&app, path).await;
+        assert_eq!(response.status(), StatusCode::OK, "{path}");
+    }
+}
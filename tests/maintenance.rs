@@ -0,0 +1,31 @@
+//! Maintenance mode across the router
+//!
+//! Visitors get the 503 page; the admin area, health checks and assets keep
+//! working, so an admin can still sign in.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+
+use app::{middleware, routes, services::LiveConfig};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_admin_area_stays_reachable() {
+    let state = common::state(|config, services| {
+        config.maintenance.enabled = true;
+        services.live = Arc::new(LiveConfig::new(config.clone()));
+    })
+    .await;
+    let app = middleware::stack(routes::router(state.clone()), state);
+
+    for path in ["/", "/about", "/administer"] {
+        let response = common::get(&app, path).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE, "{path}");
+    }
+    for path in ["/admin", "/admin/login", "/healthz"] {
+        let response = common::get(&app, path).await;
+        assert_ne!(response.status(), StatusCode::SERVICE_UNAVAILABLE, "{path}");
+    }
+}
@@ -0,0 +1,97 @@
+//! `OPTIONS` and `405` across the router
+//!
+//! Every route answers `OPTIONS` with `204` and an `Allow` header listing
+//! its methods, `OPTIONS` included, and a method it doesn't take with `405`,
+//! the same `Allow` header and the error fragment. The routes are every
+//! sitemap path plus a few with more methods.
+
+mod common;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, Method, StatusCode},
+};
+
+use app::{handlers::seo, middleware, routes};
+
+/// Routes and the `Allow` header they should send
+const ALLOWED: &[(&str, &str)] = &[
+    ("/comments/1", "GET,HEAD,PUT,DELETE,OPTIONS"),
+    ("/items", "POST,OPTIONS"),
+    ("/contact", "GET,HEAD,POST,OPTIONS"),
+    ("/static/js/htmx.min.js", "GET,HEAD,OPTIONS"),
+];
+
+fn request(method: Method, path: &str) -> Request {
+    Request::builder()
+        .method(method)
+        .uri(path)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_options_lists_allowed_methods() {
+    let state = common::state(|_, _| {}).await;
+    let app = middleware::stack(routes::router(state.clone()), state);
+
+    let sitemap = seo::sitemap_paths();
+    let paths = sitemap
+        .iter()
+        .map(|path| (*path, None))
+        .chain(ALLOWED.iter().map(|(path, allow)| (*path, Some(*allow))));
+    for (path, expected) in paths {
+        let response = common::send(&app, request(Method::OPTIONS, path)).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT, "OPTIONS {path}");
+        let allow = response.headers()[header::ALLOW].to_str().unwrap();
+        match expected {
+            Some(expected) => assert_eq!(allow, expected, "OPTIONS {path}"),
+            // Pages; some take a form too
+            None => assert!(
+                allow.starts_with("GET,HEAD,") && allow.ends_with(",OPTIONS"),
+                "OPTIONS {path}: {allow}"
+            ),
+        }
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty(), "OPTIONS {path}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_method_not_allowed_has_allow_and_fragment() {
+    let state = common::state(|_, _| {}).await;
+    let app = middleware::stack(routes::router(state.clone()), state);
+
+    // A session and its CSRF token, so the CSRF check lets DELETE through
+    let page = common::get(&app, "/about").await;
+    let cookie = page.headers()[header::SET_COOKIE]
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+    let token = page.headers()["x-csrf-token"].clone();
+
+    let mut delete = request(Method::DELETE, "/about");
+    delete
+        .headers_mut()
+        .insert(header::COOKIE, cookie.parse().unwrap());
+    delete.headers_mut().insert("x-csrf-token", token);
+    delete
+        .headers_mut()
+        .insert("hx-request", "true".parse().unwrap());
+    let response = common::send(&app, delete).await;
+
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()[header::ALLOW], "GET,HEAD,OPTIONS");
+    assert_eq!(response.headers()["hx-retarget"], "#error-toast");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains("Method not allowed"), "{body}");
+}
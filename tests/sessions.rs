@@ -0,0 +1,35 @@
+//! Session exemptions across the router
+//!
+//! Paths under `[sessions] exempt` get no cookie and store nothing; every
+//! other page starts a session.
+
+mod common;
+
+use axum::http::{header, StatusCode};
+
+use app::{middleware, routes};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_exempt_paths_get_no_session() {
+    let state = common::state(|_, _| {}).await;
+    let app = middleware::stack(routes::router(state.clone()), state.clone());
+    let sessions = &state.services.sessions;
+
+    for path in ["/sw.js", "/healthz", "/robots.txt"] {
+        let before = sessions.count();
+        let response = common::get(&app, path).await;
+        assert_eq!(response.status(), StatusCode::OK, "{path}");
+        assert!(
+            response.headers().get(header::SET_COOKIE).is_none(),
+            "{path}"
+        );
+        assert_eq!(sessions.count(), before, "{path}");
+    }
+
+    let before = sessions.count();
+    let response = common::get(&app, "/about").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = response.headers().get(header::SET_COOKIE).unwrap();
+    assert!(cookie.to_str().unwrap().starts_with("sid="));
+    assert_eq!(sessions.count(), before + 1);
+}
@@ -0,0 +1,73 @@
+//! Graceful shutdown of the server
+//!
+//! Once told to stop, [`serve`] stops accepting but waits for the requests
+//! already in flight to finish before it returns.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{routing::get, Router};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{oneshot, Notify},
+};
+
+use app::{
+    config::AppConfig,
+    server::{serve, ListenerInfo},
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_in_flight_request_finishes() {
+    // The handler says when it's started, then waits to be let go
+    let started = Arc::new(Notify::new());
+    let finish = Arc::new(Notify::new());
+    let app = Router::new().route(
+        "/slow",
+        get({
+            let (started, finish) = (started.clone(), finish.clone());
+            move || async move {
+                started.notify_one();
+                finish.notified().await;
+                "done"
+            }
+        }),
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (stop, stopped) = oneshot::channel::<()>();
+    let server = tokio::spawn(async move {
+        let config = AppConfig::default().server;
+        let listeners = vec![(listener.into(), ListenerInfo::default())];
+        serve(listeners, app, &config, async {
+            let _ = stopped.await;
+        })
+        .await
+    });
+
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    started.notified().await;
+
+    // Shutting down waits for the request
+    stop.send(()).unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!server.is_finished());
+
+    finish.notify_one();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(response.ends_with("done"), "{response}");
+
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("serve returns once the request is done")
+        .unwrap()
+        .unwrap();
+}
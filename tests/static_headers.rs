@@ -0,0 +1,92 @@
+//! Cross-origin headers for static files
+//!
+//! With `[static_headers.fonts]`, the fonts get its
+//! `Cross-Origin-Resource-Policy` and answer the origins it allows with
+//! `Access-Control-Allow-Origin`; other static files and pages stay
+//! `same-origin` and send no CORS headers.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, StatusCode},
+    response::Response,
+};
+
+use app::{config::StaticHeadersConfig, middleware, models::AppState, routes};
+
+const FONT: &str = "/static/fonts/bootstrap-icons.woff2";
+const EMBEDDER: &str = "https://embed.example.com";
+
+async fn state() -> Arc<AppState> {
+    common::state(|config, _| {
+        config.static_headers.insert(
+            "fonts".into(),
+            StaticHeadersConfig {
+                extensions: vec!["woff2".into(), "woff".into()],
+                cross_origin_resource_policy: "cross-origin".into(),
+                allow_origins: vec![EMBEDDER.into()],
+            },
+        );
+    })
+    .await
+}
+
+fn request(path: &str, origin: Option<&str>) -> Request {
+    let mut request = Request::builder().uri(path);
+    if let Some(origin) = origin {
+        request = request.header(header::ORIGIN, origin);
+    }
+    request.body(Body::empty()).unwrap()
+}
+
+fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_static_headers_per_kind() {
+    let state = state().await;
+    let app = middleware::stack(routes::router(state.clone()), state);
+
+    let font = common::send(&app, request(FONT, Some(EMBEDDER))).await;
+    assert_eq!(font.status(), StatusCode::OK);
+    assert_eq!(
+        header(&font, "cross-origin-resource-policy"),
+        Some("cross-origin")
+    );
+    assert_eq!(header(&font, "access-control-allow-origin"), Some(EMBEDDER));
+    assert!(font
+        .headers()
+        .get_all(header::VARY)
+        .iter()
+        .any(|v| v == "origin"));
+
+    let stranger = common::send(&app, request(FONT, Some("https://elsewhere.test"))).await;
+    assert_eq!(
+        header(&stranger, "cross-origin-resource-policy"),
+        Some("cross-origin")
+    );
+    assert_eq!(header(&stranger, "access-control-allow-origin"), None);
+
+    for path in ["/static/js/htmx.min.js", "/about"] {
+        let response = common::send(&app, request(path, Some(EMBEDDER))).await;
+        assert_eq!(response.status(), StatusCode::OK, "{path}");
+        assert_eq!(
+            header(&response, "cross-origin-resource-policy"),
+            Some("same-origin"),
+            "{path}"
+        );
+        assert_eq!(
+            header(&response, "access-control-allow-origin"),
+            None,
+            "{path}"
+        );
+    }
+}
@@ -0,0 +1,1843 @@
+//! Template snapshot tests
+//!
+//! Every page and partial is rendered with fixture data through both engines:
+//! askama (what release builds serve) and minijinja (what debug builds serve).
+//! Both outputs must match the same snapshot, so the two engines can't drift
+//! apart without a test failing.
+//!
+//! Update snapshots after an intentional template change with
+//! `INSTA_UPDATE=always cargo test --test templates` (or `cargo insta review`).
+
+use std::collections::BTreeMap;
+
+use app::config::{AnnouncementConfig, TenancyConfig, TenancyMode, TenantConfig};
+use app::globals::{self, TemplateGlobals};
+use app::handlers::activity::{
+    ActivityDay, ActivityDayPartial, ActivityEntry, ActivityFeedPartial, ActivityFilter,
+    AdminActivityPage,
+};
+use app::handlers::admin::{
+    AdminBackupsPage, AdminConfigPage, AdminLoginPage, AdminPostEditPage, AdminPostRow,
+    AdminPostsPage, AdminRedirectsPage, AdminTokensPage, AdminWebhooksPage, ApiTokensPartial,
+    BackupRow, LogLevelPartial, PostPreviewPartial, RedirectsPartial, TokenRow,
+    WebhookDeliveriesPartial,
+};
+use app::handlers::blog::{BlogIndexPage, BlogPostPage, PostSummary, PostView};
+use app::handlers::calendar::{
+    self, CalendarDay, CalendarPage, CalendarPartial, CalendarWeek, DatePickerQuery, DayEvent,
+};
+use app::handlers::cart::{
+    CartCheckoutPartial, CartPartial, CartView, CheckoutErrors, CheckoutFields, CheckoutForm,
+    Receipt,
+};
+use app::handlers::comments::{CommentEditPartial, CommentThreadPartial, CommentView};
+use app::handlers::contact::{
+    ContactErrors, ContactFields, ContactForm, ContactFormPartial, ContactPage, ContactSentPartial,
+};
+use app::handlers::documents::{
+    sample_invoice, InvoicePage, ReportPage, ReportRow, ReportView, TagCount,
+};
+use app::handlers::embed::{EmbedItemsPartial, EmbedPage};
+use app::handlers::gallery;
+use app::handlers::items::{title_field, ItemPage};
+use app::handlers::newsletter::{
+    NewsletterConfirmPage, NewsletterPendingPartial, NewsletterSignupPartial,
+    NewsletterUnsubscribePage, NewsletterUnsubscribedPartial,
+};
+use app::handlers::not_found::{NotFoundPage, NotFoundReportPartial};
+use app::handlers::notifications::{
+    NotificationBell, NotificationBellPartial, NotificationListPartial, NotificationView,
+};
+use app::handlers::partials::{
+    AutocompletePartial, DemoTabPartial, GreetingPartial, ItemListChangesPartial, ItemListPartial,
+    MorphDemoPartial, ProgressPartial, ShortcutHelp, ShortcutsPartial, SortableListPartial,
+    StatusCardPartial, TabFact, TagFilter,
+};
+use app::handlers::payments::{amount_field, method_field, PayPage, PaymentPartial, PaymentView};
+use app::handlers::presence::OnlineUsersPartial;
+use app::handlers::pwa::OfflinePage;
+use app::handlers::request_log::{AdminRequestsPage, FilterOption, RequestLogPartial, RequestRow};
+use app::handlers::share::{Lifetime, ShareLinkPartial, SharePartial};
+use app::handlers::templates::{
+    demo_indicator, demo_tabs, field_examples, tag_search, AboutPage, ComponentsPage, DemoPage,
+    ErrorPage, HomePage, MaintenancePage, SecurityPage,
+};
+use app::services::autocomplete::Suggestion;
+use app::services::avatars;
+use app::services::breadcrumbs::{BreadcrumbService, RouteBreadcrumbs};
+use app::services::cart::Cart;
+use app::services::charts;
+use app::services::flash::FlashMessage;
+use app::services::i18n;
+use app::services::items::Item;
+use app::services::live_config::ConfigRow;
+use app::services::not_found::NotFoundHit;
+use app::services::payments::TEST_CARDS;
+use app::services::presence::Seen;
+use app::services::redirects::Redirect;
+use app::services::sanitize::{self, Profile};
+use app::services::tenants::Tenants;
+use app::services::webhooks_out::Delivery;
+use app::utils::build_info::BuildInfo;
+use app::utils::clipboard::CopyButton;
+use app::utils::loading::Skeleton;
+
+/// Render a template with both engines and snapshot the result. Rendering runs
+/// inside fixture [`TemplateGlobals`] so layout globals are covered too.
+///
+/// The engines disagree on trailing whitespace around block tags, so lines are
+/// right-trimmed and blank lines dropped before comparing.
+macro_rules! assert_engines_match {
+    ($snapshot:literal, $template:expr) => {
+        assert_engines_match!($snapshot, $template, fixture_globals())
+    };
+    ($snapshot:literal, $template:expr, $globals:expr) => {{
+        let template = $template;
+        let (askama, minijinja) = globals::sync_scope($globals, || {
+            (
+                template.render_askama().expect("askama render failed"),
+                template
+                    .render_minijinja()
+                    .expect("minijinja render failed"),
+            )
+        });
+        let (askama, minijinja) = (normalize(&askama), normalize(&minijinja));
+        assert_eq!(
+            askama, minijinja,
+            "askama and minijinja output diverged for {}",
+            $snapshot
+        );
+        insta::assert_snapshot!($snapshot, askama);
+    }};
+}
+
+fn normalize(html: &str) -> String {
+    html.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Globals for a nested route under `/demo`, so the sidebar highlights Demo
+fn fixture_globals() -> TemplateGlobals {
+    TemplateGlobals {
+        current_path: "/demo/fixture".into(),
+        current_page: globals::nav_page("/demo/fixture"),
+        csrf_token: "fixture-csrf-token".into(),
+        flash: vec![FlashMessage::new("success", "Saved <b>changes</b>")],
+        user_name: "Ada".into(),
+        breadcrumbs: RouteBreadcrumbs::default().trail("/demo/fixture"),
+        build: fixture_build(),
+        ..TemplateGlobals::default()
+    }
+}
+
+/// Fixed build information, so snapshots don't change with every commit
+fn fixture_build() -> BuildInfo {
+    BuildInfo {
+        version: "0.0.0-fixture",
+        git_sha: "0123456789abcdef0123456789abcdef01234567",
+        git_sha_short: "0123456",
+        built_at: "2026-01-01T00:00:00Z",
+        features: "pdf",
+    }
+}
+
+fn fixture_items() -> Vec<Item> {
+    vec![
+        Item {
+            id: 1,
+            slug: "write-tests".into(),
+            title: "Write tests".into(),
+            description: "Snapshot every template".into(),
+            done: true,
+            tags: vec!["testing".into()],
+            created_at: "2024-01-01T00:00:00Z".into(),
+            tenant: "default".into(),
+            position: 1024,
+        },
+        Item {
+            id: 2,
+            slug: "escape-html".into(),
+            title: "Escape <html>".into(),
+            description: "Untrusted \"quotes\" & ampersands".into(),
+            done: false,
+            tags: vec!["security".into(), "testing".into()],
+            created_at: "2024-01-02T00:00:00Z".into(),
+            tenant: "default".into(),
+            position: 2048,
+        },
+    ]
+}
+
+fn fixture_tag_filters(active: &str) -> Vec<TagFilter> {
+    ["security", "testing"]
+        .into_iter()
+        .map(|name| TagFilter {
+            name: name.into(),
+            active: name == active,
+        })
+        .collect()
+}
+
+fn fixture_activity_entry(id: u32, kind: &str, icon: &str, link: &str) -> ActivityEntry {
+    ActivityEntry {
+        id,
+        kind: kind.into(),
+        icon: icon.into(),
+        actor: "Grace <Hopper>".into(),
+        avatar: avatars::url("Grace <Hopper>"),
+        summary: "Created item \"<b>Ship</b>\"".into(),
+        link: link.into(),
+        has_link: !link.is_empty(),
+        time: "09:30".into(),
+    }
+}
+
+fn fixture_activity_days() -> Vec<ActivityDay> {
+    vec![
+        ActivityDay {
+            date: "2024-03-05".into(),
+            label: "Today".into(),
+            heading: true,
+            entries: vec![
+                fixture_activity_entry(3, "item.created", "check2-square", "/demo"),
+                fixture_activity_entry(2, "admin.sign_in_failed", "shield-lock", ""),
+            ],
+        },
+        ActivityDay {
+            date: "2024-03-01".into(),
+            label: "Friday 1 March 2024".into(),
+            heading: true,
+            entries: vec![fixture_activity_entry(
+                1,
+                "comment.created",
+                "chat-left-text",
+                "/demo#comment-1",
+            )],
+        },
+    ]
+}
+
+fn fixture_comment(id: u32, indent: usize, body: &str) -> CommentView {
+    CommentView {
+        id,
+        author: "Grace <Hopper>".into(),
+        avatar: avatars::url("Grace <Hopper>"),
+        body: sanitize::clean(Profile::Comment, body),
+        created_at: "2024-01-02 03:04:05".into(),
+        edited: false,
+        deleted: false,
+        indent,
+        can_edit: false,
+    }
+}
+
+fn fixture_comments() -> Vec<CommentView> {
+    vec![
+        CommentView {
+            deleted: true,
+            ..fixture_comment(1, 0, "")
+        },
+        CommentView {
+            edited: true,
+            can_edit: true,
+            ..fixture_comment(2, 1, "<p>Nice <a href=\"https://example.com\">link</a></p>")
+        },
+        fixture_comment(3, 0, "<script>alert('x')</script><b>bold</b>"),
+    ]
+}
+
+const FIXTURE_MARKDOWN: &str =
+    "## Heading\n\nSome *text* with [a link](https://example.com).\n\n<script>alert('x')</script>";
+
+fn fixture_post_summaries() -> Vec<PostSummary> {
+    vec![
+        PostSummary {
+            slug: "second-post".into(),
+            title: "Second <post>".into(),
+            summary: "Untrusted \"summary\" & more".into(),
+            date: "2024-02-01".into(),
+        },
+        PostSummary {
+            slug: "hello-world".into(),
+            title: "Hello, World".into(),
+            summary: String::new(),
+            date: "2024-01-01".into(),
+        },
+    ]
+}
+
+#[test]
+fn templates_match_their_declarations() {
+    if let Err(errors) = app::utils::templates::check_template_parity() {
+        panic!("template parity errors:\n{}", errors.join("\n"));
+    }
+}
+
+// =============================================================================
+// Pages
+// =============================================================================
+
+#[test]
+fn home_page() {
+    assert_engines_match!("home_page", HomePage {});
+}
+
+/// A path-mode tenant with a name and theme: prefixed links, renamed brand
+/// and token overrides
+#[test]
+fn home_page_tenant() {
+    let config = TenancyConfig {
+        mode: TenancyMode::Path,
+        base_domain: String::new(),
+        tenants: BTreeMap::from([(
+            "acme".to_string(),
+            TenantConfig {
+                name: "Acme <Corp>".into(),
+                theme: BTreeMap::from([
+                    ("color-brand".into(), "#e11d48".into()),
+                    ("radius-md".into(), "0".into()),
+                ]),
+            },
+        )]),
+    };
+    let tenant = Tenants::new(&config).get("acme").cloned().unwrap();
+    assert_engines_match!(
+        "home_page_tenant",
+        HomePage {},
+        TemplateGlobals {
+            tenant,
+            ..fixture_globals()
+        }
+    );
+}
+
+/// The `[announcement]` banner above the content
+#[test]
+fn home_page_announcement() {
+    assert_engines_match!(
+        "home_page_announcement",
+        HomePage {},
+        TemplateGlobals {
+            announcement: AnnouncementConfig {
+                message: "Maintenance <tonight> at 22:00 UTC".into(),
+                level: "warning".into(),
+            },
+            ..fixture_globals()
+        }
+    );
+}
+
+/// The database-outage banner
+#[test]
+fn home_page_db_down() {
+    assert_engines_match!(
+        "home_page_db_down",
+        HomePage {},
+        TemplateGlobals {
+            db_down: true,
+            ..fixture_globals()
+        }
+    );
+}
+
+/// The read-only mode banner
+#[test]
+fn home_page_read_only() {
+    assert_engines_match!(
+        "home_page_read_only",
+        HomePage {},
+        TemplateGlobals {
+            read_only: true,
+            ..fixture_globals()
+        }
+    );
+}
+
+#[test]
+fn maintenance_page() {
+    assert_engines_match!(
+        "maintenance_page",
+        MaintenancePage {
+            message: "Back at <b>noon</b>.".into(),
+        }
+    );
+}
+
+#[test]
+fn offline_page() {
+    assert_engines_match!("offline_page", OfflinePage {});
+}
+
+#[test]
+fn embed_items_page() {
+    let items = fixture_items();
+    let body = EmbedItemsPartial {
+        empty: items.is_empty(),
+        items,
+    }
+    .render_askama()
+    .unwrap();
+    assert_engines_match!(
+        "embed_items_page",
+        EmbedPage {
+            widget: "items".into(),
+            body,
+            refresh_seconds: 60,
+        }
+    );
+}
+
+#[test]
+fn embed_items_partial_empty() {
+    assert_engines_match!(
+        "embed_items_partial_empty",
+        EmbedItemsPartial {
+            items: Vec::new(),
+            empty: true,
+        }
+    );
+}
+
+#[test]
+fn error_page() {
+    assert_engines_match!(
+        "error_page",
+        ErrorPage {
+            request_id: "3f1c2a4e-8b7d-4c6a-9e0f-1a2b3c4d5e6f".into(),
+        }
+    );
+}
+
+#[test]
+fn about_page() {
+    assert_engines_match!("about_page", AboutPage {});
+}
+
+#[test]
+fn demo_page() {
+    assert_engines_match!(
+        "demo_page",
+        DemoPage {
+            tag: "backend".into(),
+            tag_search: tag_search("backend"),
+            tabs: demo_tabs(),
+            loading_indicator: demo_indicator(),
+            loading_skeleton: Skeleton::lines(2).label("Loading the greeting…"),
+        }
+    );
+}
+
+#[test]
+fn components_page() {
+    assert_engines_match!(
+        "components_page",
+        ComponentsPage {
+            gallery: gallery::items(),
+            fields: field_examples(),
+            copy_input: CopyButton::target("#copy-example"),
+            copy_text: CopyButton::text("Hello <clipboard> & \"quotes\""),
+            missing: vec!["WidgetPartial"],
+            has_missing: true
+        }
+    );
+}
+
+#[test]
+fn security_page() {
+    assert_engines_match!("security_page", SecurityPage {});
+}
+
+#[test]
+fn blog_index_page() {
+    assert_engines_match!(
+        "blog_index_page",
+        BlogIndexPage {
+            posts: fixture_post_summaries(),
+            empty: false,
+            page: 2,
+            prev_page: 1,
+            next_page: 3,
+        }
+    );
+}
+
+#[test]
+fn blog_index_page_empty() {
+    assert_engines_match!(
+        "blog_index_page_empty",
+        BlogIndexPage {
+            posts: vec![],
+            empty: true,
+            page: 1,
+            prev_page: 0,
+            next_page: 0,
+        }
+    );
+}
+
+#[test]
+fn blog_post_page() {
+    assert_engines_match!(
+        "blog_post_page",
+        BlogPostPage {
+            post: PostView {
+                slug: "draft-post".into(),
+                title: "Draft <post>".into(),
+                summary: "A summary".into(),
+                body: sanitize::markdown(Profile::RichText, FIXTURE_MARKDOWN),
+                date: "2024-01-01".into(),
+                draft: true,
+            },
+        }
+    );
+}
+
+// Head tags overridden per response: title, description, noindex, canonical
+#[test]
+fn blog_post_page_seo() {
+    let page = BlogPostPage {
+        post: PostView {
+            slug: "hello-world".into(),
+            title: "Hello & welcome".into(),
+            summary: "A first post".into(),
+            body: sanitize::markdown(Profile::RichText, FIXTURE_MARKDOWN),
+            date: "2024-01-01".into(),
+            draft: true,
+        },
+    };
+    assert_engines_match!(
+        "blog_post_page_seo",
+        page.seo(|seo| seo
+            .title("Hello & welcome")
+            .description("A first post")
+            .canonical("/blog/hello-world")
+            .noindex())
+    );
+}
+
+#[test]
+fn contact_page() {
+    assert_engines_match!(
+        "contact_page",
+        ContactPage {
+            form: ContactFields::default()
+        }
+    );
+}
+
+#[test]
+fn contact_form_partial() {
+    assert_engines_match!(
+        "contact_form_partial",
+        ContactFormPartial {
+            form: ContactFields::new(
+                &ContactForm {
+                    name: "Ada <script>".into(),
+                    email: "ada@".into(),
+                    message: "Hi".into(),
+                    website: String::new(),
+                },
+                ContactErrors {
+                    email: "That email address doesn't look right".into(),
+                    message: "Messages must be 10 to 5000 characters".into(),
+                    ..ContactErrors::default()
+                },
+            )
+        }
+    );
+}
+
+#[test]
+fn calendar_page() {
+    assert_engines_match!(
+        "calendar_page",
+        CalendarPage {
+            month: "2024-03".into(),
+        }
+    );
+}
+
+fn fixture_day(date: &str, day: u32, events: Vec<DayEvent>) -> CalendarDay {
+    CalendarDay {
+        date: date.into(),
+        day,
+        outside: day > 20,
+        today: day == 5,
+        events,
+        more: 0,
+    }
+}
+
+#[test]
+fn calendar_partial() {
+    let days = vec![
+        fixture_day("2024-02-26", 26, Vec::new()),
+        fixture_day("2024-02-27", 27, Vec::new()),
+        fixture_day("2024-03-01", 1, Vec::new()),
+        fixture_day(
+            "2024-03-05",
+            5,
+            vec![
+                DayEvent {
+                    title: "Planning <& review>".into(),
+                    time: "09:00".into(),
+                    all_day: false,
+                },
+                DayEvent {
+                    title: "Release".into(),
+                    time: String::new(),
+                    all_day: true,
+                },
+            ],
+        ),
+        CalendarDay {
+            more: 2,
+            ..fixture_day(
+                "2024-03-06",
+                6,
+                vec![DayEvent {
+                    title: "Offsite".into(),
+                    time: String::new(),
+                    all_day: true,
+                }],
+            )
+        },
+    ];
+    assert_engines_match!(
+        "calendar_partial",
+        CalendarPartial {
+            prev: "2024-02".into(),
+            next: "2024-04".into(),
+            label: "March 2024".into(),
+            show_today: true,
+            weeks: vec![CalendarWeek { days }],
+        }
+    );
+}
+
+#[test]
+fn invoice_page() {
+    assert_engines_match!(
+        "invoice_page",
+        InvoicePage {
+            invoice: sample_invoice(),
+            pdf: true,
+        }
+    );
+}
+
+#[test]
+fn invoice_page_without_pdf() {
+    assert_engines_match!(
+        "invoice_page_without_pdf",
+        InvoicePage {
+            invoice: sample_invoice(),
+            pdf: false,
+        }
+    );
+}
+
+fn fixture_report(tags: Vec<TagCount>) -> ReportView {
+    ReportView {
+        generated: "2024-03-01 09:30 UTC".into(),
+        total: 2,
+        done: 1,
+        pending: 1,
+        no_tags: tags.is_empty(),
+        tags,
+        items: vec![
+            ReportRow {
+                id: 1,
+                title: "Set up project".into(),
+                status: "Done".into(),
+                tags: "backend setup".into(),
+            },
+            ReportRow {
+                id: 2,
+                title: "Add <database>".into(),
+                status: "Pending".into(),
+                tags: String::new(),
+            },
+        ],
+    }
+}
+
+#[test]
+fn report_page() {
+    assert_engines_match!(
+        "report_page",
+        ReportPage {
+            report: fixture_report(vec![
+                TagCount {
+                    name: "backend".into(),
+                    count: 1,
+                },
+                TagCount {
+                    name: "setup".into(),
+                    count: 1,
+                },
+            ]),
+            pdf: true,
+        }
+    );
+}
+
+#[test]
+fn report_page_untagged() {
+    assert_engines_match!(
+        "report_page_untagged",
+        ReportPage {
+            report: fixture_report(Vec::new()),
+            pdf: false,
+        }
+    );
+}
+
+/// `?view=print`: no sidebar, header or breadcrumbs, and no Print view link
+#[test]
+fn report_page_print_view() {
+    assert_engines_match!(
+        "report_page_print_view",
+        ReportPage {
+            report: fixture_report(Vec::new()),
+            pdf: false,
+        },
+        TemplateGlobals {
+            print_view: true,
+            ..fixture_globals()
+        }
+    );
+}
+
+fn fixture_deliveries() -> Vec<Delivery> {
+    let delivery = |attempt, ok, outcome: &str| Delivery {
+        event_id: "0b6e1c1e-7d3f-4f8e-9a51-2f8d0c7e4a10".into(),
+        event: "item.created".into(),
+        url: "https://hooks.example.com/in?a=1&b=<2>".into(),
+        attempt,
+        ok,
+        outcome: outcome.into(),
+        at: "2026-01-02 03:04:05".into(),
+    };
+    vec![delivery(2, true, "200"), delivery(1, false, "503")]
+}
+
+#[test]
+fn admin_webhooks_page() {
+    assert_engines_match!(
+        "admin_webhooks_page",
+        AdminWebhooksPage {
+            endpoints: vec!["https://hooks.example.com/in — item.created".into()],
+            no_endpoints: false,
+            deliveries: fixture_deliveries(),
+            empty: false,
+        }
+    );
+}
+
+#[test]
+fn admin_config_page() {
+    let row = |key: &str, value: &str, reloadable| ConfigRow {
+        key: key.into(),
+        value: value.into(),
+        reloadable,
+    };
+    assert_engines_match!(
+        "admin_config_page",
+        AdminConfigPage {
+            rows: vec![
+                row("admin.password", "••••••••", false),
+                row("maintenance.enabled", "true", true),
+                row("server.port", "8000", false),
+            ],
+            last_reload: "2026-03-14 09:30:00 UTC".into(),
+            error: String::new(),
+            restart_needed: "server".into(),
+            read_only: true,
+            read_only_pinned: true,
+        },
+        // Admins also see the commit in the sidebar footer
+        TemplateGlobals {
+            is_admin: true,
+            ..fixture_globals()
+        }
+    );
+}
+
+#[test]
+fn admin_config_page_rejected() {
+    assert_engines_match!(
+        "admin_config_page_rejected",
+        AdminConfigPage {
+            rows: Vec::new(),
+            last_reload: String::new(),
+            error: "1 configuration error(s):\n  server.port: must not be 0".into(),
+            restart_needed: String::new(),
+            read_only: false,
+            read_only_pinned: false,
+        },
+        TemplateGlobals {
+            build: BuildInfo {
+                features: "",
+                ..fixture_build()
+            },
+            ..fixture_globals()
+        }
+    );
+}
+
+#[test]
+fn admin_backups_page() {
+    let row = |stamp: &str, size: &str, created: &str| BackupRow {
+        name: format!("backup-{stamp}.db"),
+        size: size.into(),
+        created: created.into(),
+        url: format!("/backups/download?token=fixture-{stamp}"),
+    };
+    assert_engines_match!(
+        "admin_backups_page",
+        AdminBackupsPage {
+            enabled: true,
+            dir: "data/backups".into(),
+            interval_hours: 24,
+            keep: 7,
+            backups: vec![
+                row("20260314-093000-000", "2.4 MB", "2026-03-14 09:30 UTC"),
+                row("20260313-093000-000", "2.3 MB", "2026-03-13 09:30 UTC"),
+            ],
+            empty: false,
+        }
+    );
+}
+
+#[test]
+fn admin_backups_page_disabled() {
+    assert_engines_match!(
+        "admin_backups_page_disabled",
+        AdminBackupsPage {
+            enabled: false,
+            dir: String::new(),
+            interval_hours: 0,
+            keep: 7,
+            backups: Vec::new(),
+            empty: true,
+        }
+    );
+}
+
+#[test]
+fn log_level_partial() {
+    assert_engines_match!(
+        "log_level_partial",
+        LogLevelPartial {
+            directives: "app=info,tower_http=debug,app::services::mail=debug".into(),
+            configured: "info".into(),
+            extra: "app::services::mail=debug".into(),
+            expires: "14:05 UTC".into(),
+        }
+    );
+}
+
+#[test]
+fn log_level_partial_configured() {
+    assert_engines_match!(
+        "log_level_partial_configured",
+        LogLevelPartial {
+            directives: "app=info,tower_http=debug".into(),
+            configured: "info".into(),
+            extra: String::new(),
+            expires: String::new(),
+        }
+    );
+}
+
+#[test]
+fn admin_activity_page() {
+    assert_engines_match!(
+        "admin_activity_page",
+        AdminActivityPage {
+            subject: "comment".into(),
+            filters: vec![
+                ActivityFilter {
+                    subject: String::new(),
+                    label: "All".into(),
+                    active: false,
+                },
+                ActivityFilter {
+                    subject: "comment".into(),
+                    label: "Comments".into(),
+                    active: true,
+                },
+            ],
+        }
+    );
+}
+
+#[test]
+fn newsletter_confirm_page() {
+    assert_engines_match!(
+        "newsletter_confirm_page",
+        NewsletterConfirmPage {
+            confirmed: true,
+            email: "ada@example.com".into(),
+            unsubscribe_url: "https://example.com/newsletter/unsubscribe?token=abc".into(),
+        }
+    );
+}
+
+#[test]
+fn newsletter_confirm_page_expired() {
+    assert_engines_match!(
+        "newsletter_confirm_page_expired",
+        NewsletterConfirmPage {
+            confirmed: false,
+            email: String::new(),
+            unsubscribe_url: String::new(),
+        }
+    );
+}
+
+#[test]
+fn newsletter_unsubscribe_page() {
+    assert_engines_match!(
+        "newsletter_unsubscribe_page",
+        NewsletterUnsubscribePage {
+            valid: true,
+            token: "abc.def".into(),
+            email: "ada@example.com".into(),
+        }
+    );
+}
+
+#[test]
+fn admin_login_page() {
+    assert_engines_match!("admin_login_page", AdminLoginPage {});
+}
+
+#[test]
+fn admin_posts_page() {
+    assert_engines_match!(
+        "admin_posts_page",
+        AdminPostsPage {
+            posts: vec![
+                AdminPostRow {
+                    id: 2,
+                    slug: "draft-post".into(),
+                    title: "Draft <post>".into(),
+                    published: false,
+                    updated: "2024-02-01 10:00".into(),
+                },
+                AdminPostRow {
+                    id: 1,
+                    slug: "hello-world".into(),
+                    title: "Hello, World".into(),
+                    published: true,
+                    updated: "2024-01-01 00:00".into(),
+                },
+            ],
+            empty: false,
+        }
+    );
+}
+
+#[test]
+fn admin_post_edit_page() {
+    assert_engines_match!(
+        "admin_post_edit_page",
+        AdminPostEditPage {
+            id: 2,
+            title: "Draft \"post\"".into(),
+            slug: "draft-post".into(),
+            summary: String::new(),
+            body: FIXTURE_MARKDOWN.into(),
+            published: false,
+            preview: sanitize::markdown(Profile::RichText, FIXTURE_MARKDOWN),
+        }
+    );
+}
+
+// =============================================================================
+// Partials
+// =============================================================================
+
+#[test]
+fn post_preview_partial() {
+    assert_engines_match!(
+        "post_preview_partial",
+        PostPreviewPartial {
+            preview: sanitize::markdown(Profile::RichText, FIXTURE_MARKDOWN),
+        }
+    );
+}
+
+#[test]
+fn status_card_partial() {
+    assert_engines_match!(
+        "status_card_partial",
+        StatusCardPartial {
+            status: "healthy".into(),
+            uptime: "1d 2h 3m".into(),
+            started: "2026-01-02 03:04 UTC".into(),
+            version: "0.1.0".into(),
+            requests: "1234".into(),
+            server_errors: "2".into(),
+            p95_latency: "12 ms".into(),
+            over_budget: "0".into(),
+            memory: "48.2 MB".into(),
+            db_pool: "1 / 2".into(),
+            db_max: "5".into(),
+            db_read_wait: "≤ 1.0 ms".into(),
+            db_write_wait: "≤ 25 ms".into(),
+            db_slow_acquires: "1".into(),
+            slow_template: "pages/blog/index.html".into(),
+            slow_template_engine: "minijinja".into(),
+            slow_template_mean: "3.2 ms".into(),
+            slow_template_renders: "14".into(),
+            request_rate: "42".into(),
+            sessions: "3".into(),
+            request_chart: charts::sparkline(&[6.0, 42.0, 18.0], "Requests per minute"),
+            session_chart: charts::bars(&[1.0, 3.0, 2.0], "Active sessions"),
+            db_wait_chart: charts::bars(
+                &[120.0, 8.0, 2.0, 0.0, 1.0, 0.0, 0.0],
+                "Connection waits by duration"
+            ),
+        }
+    );
+}
+
+#[test]
+fn item_list_partial() {
+    assert_engines_match!(
+        "item_list_partial",
+        ItemListPartial {
+            items: fixture_items(),
+            empty: false,
+            tags: fixture_tag_filters(""),
+            active_tag: String::new(),
+            version: 0,
+        }
+    );
+}
+
+#[test]
+fn item_list_partial_filtered() {
+    assert_engines_match!(
+        "item_list_partial_filtered",
+        ItemListPartial {
+            items: fixture_items().into_iter().skip(1).collect(),
+            empty: false,
+            tags: fixture_tag_filters("security"),
+            active_tag: "security".into(),
+            version: 3,
+        }
+    );
+}
+
+#[test]
+fn item_list_partial_empty() {
+    assert_engines_match!(
+        "item_list_partial_empty",
+        ItemListPartial {
+            items: vec![],
+            empty: true,
+            tags: vec![],
+            active_tag: "missing".into(),
+            version: 0,
+        }
+    );
+}
+
+#[test]
+fn item_list_partial_none() {
+    assert_engines_match!(
+        "item_list_partial_none",
+        ItemListPartial {
+            items: vec![],
+            empty: true,
+            tags: vec![],
+            active_tag: String::new(),
+            version: 0,
+        }
+    );
+}
+
+#[test]
+fn item_list_changes_partial() {
+    assert_engines_match!(
+        "item_list_changes_partial",
+        ItemListChangesPartial {
+            version: 7,
+            active_tag: "security".into(),
+        }
+    );
+}
+
+#[test]
+fn datepicker_partial() {
+    let today = chrono::NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+    assert_engines_match!(
+        "datepicker_partial",
+        calendar::date_picker(
+            &i18n::FR,
+            &DatePickerQuery {
+                field: "field-due".into(),
+                name: "due".into(),
+                describedby: "field-due-hint".into(),
+                selected: "2024-03-14".into(),
+                focus: "day".into(),
+                ..DatePickerQuery::default()
+            },
+            today,
+        )
+    );
+}
+
+#[test]
+fn autocomplete_partial() {
+    assert_engines_match!(
+        "autocomplete_partial",
+        AutocompletePartial {
+            suggestions: vec![
+                Suggestion {
+                    value: "backend".into(),
+                    label: "#backend".into(),
+                    hint: String::new(),
+                },
+                Suggestion {
+                    value: "\"><script>".into(),
+                    label: "<b>Deploy</b>".into(),
+                    hint: "done".into(),
+                },
+            ],
+            no_matches: false,
+        }
+    );
+    assert_engines_match!(
+        "autocomplete_partial_no_matches",
+        AutocompletePartial {
+            suggestions: vec![],
+            no_matches: true,
+        }
+    );
+}
+
+#[test]
+fn demo_tab_partial() {
+    assert_engines_match!(
+        "demo_tab_partial",
+        DemoTabPartial {
+            facts: vec![
+                TabFact {
+                    label: "#<ops>".into(),
+                    value: "2".into(),
+                },
+                TabFact {
+                    label: "Uptime".into(),
+                    value: "1h 5m".into(),
+                },
+            ],
+            loaded_at: "09:30:00 UTC".into(),
+        }
+    );
+}
+
+#[test]
+fn sortable_list_partial() {
+    assert_engines_match!(
+        "sortable_list_partial",
+        SortableListPartial {
+            items: fixture_items(),
+            oob: false,
+            focus_up: 0,
+            focus_down: 0,
+        }
+    );
+}
+
+#[test]
+fn sortable_list_partial_oob() {
+    assert_engines_match!(
+        "sortable_list_partial_oob",
+        SortableListPartial {
+            items: fixture_items(),
+            oob: true,
+            focus_up: 2,
+            focus_down: 0,
+        }
+    );
+}
+
+#[test]
+fn shortcuts_partial() {
+    assert_engines_match!(
+        "shortcuts_partial",
+        ShortcutsPartial {
+            shortcuts: vec![
+                ShortcutHelp {
+                    keys: vec!["?"],
+                    label: "Show keyboard shortcuts",
+                },
+                ShortcutHelp {
+                    keys: vec!["g", "<d>"],
+                    label: "Go to the \"demo\"",
+                },
+            ],
+        }
+    );
+}
+
+#[test]
+fn share_partial() {
+    assert_engines_match!(
+        "share_partial",
+        SharePartial {
+            path: "/blog?tag=a&b".into(),
+            title: "Blog <posts>".into(),
+            lifetimes: vec![
+                Lifetime {
+                    hours: 1,
+                    label: "1 hour",
+                    selected: false,
+                },
+                Lifetime {
+                    hours: 24,
+                    label: "1 day",
+                    selected: true,
+                },
+            ],
+        }
+    );
+}
+
+#[test]
+fn share_link_partial() {
+    let url = "https://example.com/s/L2Jsb2c.1767225600.tag";
+    assert_engines_match!(
+        "share_link_partial",
+        ShareLinkPartial {
+            title: "Blog <posts>".into(),
+            url: url.into(),
+            copy: CopyButton::text(url).aria_label("Copy the share link"),
+            expires: "2026-01-01 00:00 UTC".into(),
+            form_url: "/partials/share?path=%2Fblog&title=Blog".into(),
+        }
+    );
+}
+
+#[test]
+fn cart_partial() {
+    let mut cart = Cart::default();
+    cart.add("mug", 2);
+    cart.add("hoodie", 1);
+    assert_engines_match!(
+        "cart_partial",
+        CartPartial {
+            cart: CartView::new(&cart),
+            checkout: CheckoutFields::new(
+                &CheckoutForm {
+                    name: "Ada <Lovelace>".into(),
+                    email: "ada@".into(),
+                },
+                CheckoutErrors {
+                    email: "That email address doesn't look right".into(),
+                    ..CheckoutErrors::default()
+                },
+            ),
+        }
+    );
+}
+
+#[test]
+fn cart_checkout_partial() {
+    assert_engines_match!(
+        "cart_checkout_partial",
+        CartCheckoutPartial {
+            cart: CartView::new(&Cart::default()),
+            checkout: CheckoutFields::default(),
+            receipt: Receipt {
+                order: "INV-2024-0007".into(),
+                name: "Ada <Lovelace>".into(),
+                email: "ada@example.com".into(),
+                items: "1 item".into(),
+                total: "£14.40".into(),
+                invoice: "/invoices/7".into(),
+            },
+        }
+    );
+}
+
+fn payment_view(status: &'static str, failure: &str) -> PaymentView {
+    PaymentView {
+        id: "3f2a9c".into(),
+        amount: "£10.00".into(),
+        description: "Tip <jar>".into(),
+        reference: "fake_pi_7d41e0".into(),
+        status,
+        failure: failure.into(),
+    }
+}
+
+#[test]
+fn item_page() {
+    let item = fixture_items().remove(0);
+    assert_engines_match!(
+        "item_page",
+        ItemPage {
+            title: title_field(&item.title, ""),
+            item,
+        }
+    );
+}
+
+#[test]
+fn item_page_invalid() {
+    let item = fixture_items().remove(0);
+    assert_engines_match!(
+        "item_page_invalid",
+        ItemPage {
+            title: title_field("", "Title can't be empty"),
+            item,
+        }
+    );
+}
+
+#[test]
+fn pay_page() {
+    assert_engines_match!(
+        "pay_page",
+        PayPage {
+            amount: amount_field(),
+            provider: "fake",
+        }
+    );
+}
+
+#[test]
+fn payment_partial() {
+    assert_engines_match!(
+        "payment_partial",
+        PaymentPartial {
+            payment: payment_view("requires_confirmation", ""),
+            method: method_field(TEST_CARDS),
+        }
+    );
+}
+
+#[test]
+fn payment_partial_processing() {
+    assert_engines_match!(
+        "payment_partial_processing",
+        PaymentPartial {
+            payment: payment_view("processing", ""),
+            method: method_field(&[]),
+        }
+    );
+}
+
+#[test]
+fn payment_partial_failed() {
+    assert_engines_match!(
+        "payment_partial_failed",
+        PaymentPartial {
+            payment: payment_view("failed", "Your card was <declined>."),
+            method: method_field(&[]),
+        }
+    );
+}
+
+#[test]
+fn greeting_partial() {
+    assert_engines_match!(
+        "greeting_partial",
+        GreetingPartial {
+            name: sanitize::clean(Profile::Inline, "<em>Ada</em><script>alert('x')</script>"),
+        }
+    );
+}
+
+#[test]
+fn progress_partial() {
+    assert_engines_match!(
+        "progress_partial",
+        ProgressPartial {
+            started: 1_773_480_600_000,
+            percent: 0,
+            done: false,
+            start: true,
+        }
+    );
+}
+
+#[test]
+fn progress_partial_done() {
+    assert_engines_match!(
+        "progress_partial_done",
+        ProgressPartial {
+            started: 1_773_480_600_000,
+            percent: 100,
+            done: true,
+            start: false,
+        }
+    );
+}
+
+#[test]
+fn morph_demo_partial() {
+    assert_engines_match!(
+        "morph_demo_partial",
+        MorphDemoPartial {
+            mode: "morph",
+            time: "09:30:15".into(),
+            percent: 51,
+        }
+    );
+}
+
+#[test]
+fn comment_thread_partial() {
+    assert_engines_match!(
+        "comment_thread_partial",
+        CommentThreadPartial {
+            comments: fixture_comments(),
+            empty: false,
+        }
+    );
+}
+
+#[test]
+fn comment_thread_partial_empty() {
+    assert_engines_match!(
+        "comment_thread_partial_empty",
+        CommentThreadPartial {
+            comments: vec![],
+            empty: true,
+        }
+    );
+}
+
+#[test]
+fn comment_edit_partial() {
+    assert_engines_match!(
+        "comment_edit_partial",
+        CommentEditPartial {
+            comment: fixture_comment(2, 1, ""),
+            body: "<b>raw</b> & \"quoted\"".into(),
+        }
+    );
+}
+
+#[test]
+fn activity_feed_partial() {
+    assert_engines_match!(
+        "activity_feed_partial",
+        ActivityFeedPartial {
+            subject: String::new(),
+            days: fixture_activity_days(),
+            empty: false,
+            more: true,
+            next: 1,
+            last_day: "2024-03-01".into(),
+            first_page: true,
+        }
+    );
+}
+
+#[test]
+fn activity_feed_partial_next_page() {
+    let mut days = fixture_activity_days();
+    days[0].heading = false;
+    assert_engines_match!(
+        "activity_feed_partial_next_page",
+        ActivityFeedPartial {
+            subject: "item".into(),
+            days,
+            empty: false,
+            more: false,
+            next: 1,
+            last_day: "2024-03-01".into(),
+            first_page: false,
+        }
+    );
+}
+
+#[test]
+fn activity_feed_partial_empty() {
+    assert_engines_match!(
+        "activity_feed_partial_empty",
+        ActivityFeedPartial {
+            subject: String::new(),
+            days: vec![],
+            empty: true,
+            more: false,
+            next: 0,
+            last_day: String::new(),
+            first_page: true,
+        }
+    );
+}
+
+#[test]
+fn activity_day_partial() {
+    assert_engines_match!(
+        "activity_day_partial",
+        ActivityDayPartial {
+            day: fixture_activity_days().remove(1),
+        }
+    );
+}
+
+#[test]
+fn notification_bell_partial() {
+    assert_engines_match!(
+        "notification_bell_partial",
+        NotificationBellPartial {
+            bell: NotificationBell::new(12, false),
+        }
+    );
+}
+
+#[test]
+fn notification_list_partial() {
+    let notification = |id: u32, title: &str, link: &str, unread: bool| NotificationView {
+        id,
+        title: title.into(),
+        body: if unread {
+            "Body <b>text</b>".into()
+        } else {
+            String::new()
+        },
+        link: link.into(),
+        has_link: !link.is_empty(),
+        created_at: "2024-01-02 03:04:05".into(),
+        unread,
+    };
+    assert_engines_match!(
+        "notification_list_partial",
+        NotificationListPartial {
+            notifications: vec![
+                notification(2, "Grace <Hopper> replied", "/demo#comment-3", true),
+                notification(1, "Welcome", "", false),
+            ],
+            empty: false,
+            has_unread: true,
+            bell: NotificationBell::new(1, true),
+        }
+    );
+}
+
+#[test]
+fn notification_list_partial_empty() {
+    assert_engines_match!(
+        "notification_list_partial_empty",
+        NotificationListPartial {
+            notifications: vec![],
+            empty: true,
+            has_unread: false,
+            bell: NotificationBell::new(0, true),
+        }
+    );
+}
+
+fn fixture_seen(key: &str, name: &str) -> Seen {
+    Seen {
+        key: key.into(),
+        name: name.into(),
+        last_seen: chrono::Utc::now(),
+    }
+}
+
+#[test]
+fn online_users_partial() {
+    assert_engines_match!(
+        "online_users_partial",
+        OnlineUsersPartial::new(vec![
+            fixture_seen("a", "Ada <script>"),
+            fixture_seen("b", ""),
+            fixture_seen("c", "Grace"),
+            fixture_seen("d", ""),
+        ])
+    );
+}
+
+#[test]
+fn online_users_partial_empty() {
+    assert_engines_match!(
+        "online_users_partial_empty",
+        OnlineUsersPartial::new(vec![])
+    );
+}
+
+#[test]
+fn contact_sent_partial() {
+    assert_engines_match!(
+        "contact_sent_partial",
+        ContactSentPartial {
+            name: "Ada <script>".into(),
+        }
+    );
+}
+
+#[test]
+fn newsletter_signup_partial() {
+    assert_engines_match!("newsletter_signup_partial", NewsletterSignupPartial {});
+}
+
+#[test]
+fn newsletter_pending_partial() {
+    assert_engines_match!(
+        "newsletter_pending_partial",
+        NewsletterPendingPartial {
+            email: "ada@example.com".into(),
+        }
+    );
+}
+
+#[test]
+fn newsletter_unsubscribed_partial() {
+    assert_engines_match!(
+        "newsletter_unsubscribed_partial",
+        NewsletterUnsubscribedPartial {
+            email: "ada@example.com".into(),
+        }
+    );
+}
+
+#[test]
+fn webhook_deliveries_partial() {
+    assert_engines_match!(
+        "webhook_deliveries_partial",
+        WebhookDeliveriesPartial {
+            deliveries: fixture_deliveries(),
+            empty: false,
+        }
+    );
+}
+
+#[test]
+fn webhook_deliveries_partial_empty() {
+    assert_engines_match!(
+        "webhook_deliveries_partial_empty",
+        WebhookDeliveriesPartial {
+            deliveries: Vec::new(),
+            empty: true,
+        }
+    );
+}
+
+#[test]
+fn admin_redirects_page() {
+    assert_engines_match!(
+        "admin_redirects_page",
+        AdminRedirectsPage {
+            rules: vec![
+                Redirect {
+                    id: 2,
+                    from_path: "/docs".into(),
+                    target: "https://docs.example.com/?ref=site".into(),
+                    status: 302,
+                    created_at: "2024-01-02 08:15:00".into(),
+                },
+                Redirect {
+                    id: 1,
+                    from_path: "/old-about".into(),
+                    target: "/about".into(),
+                    status: 301,
+                    created_at: "2024-01-01 12:00:00".into(),
+                },
+            ],
+            empty: false,
+        }
+    );
+}
+
+#[test]
+fn redirects_partial_empty() {
+    assert_engines_match!(
+        "redirects_partial_empty",
+        RedirectsPartial {
+            rules: Vec::new(),
+            empty: true,
+        }
+    );
+}
+
+fn token_rows() -> Vec<TokenRow> {
+    vec![
+        TokenRow {
+            id: 2,
+            name: "Nightly export".into(),
+            scopes: "exports, feeds".into(),
+            prefix: "aht_3f2a9c".into(),
+            created: "2024-01-02 08:15".into(),
+            last_used: "Never".into(),
+            revoked: false,
+        },
+        TokenRow {
+            id: 1,
+            name: "Old feed reader".into(),
+            scopes: "feeds".into(),
+            prefix: "aht_0b7d4e".into(),
+            created: "2024-01-01 12:00".into(),
+            last_used: "2024-01-01 18:30".into(),
+            revoked: true,
+        },
+    ]
+}
+
+#[test]
+fn admin_tokens_page() {
+    assert_engines_match!(
+        "admin_tokens_page",
+        AdminTokensPage {
+            tokens: token_rows(),
+            empty: false,
+            secret: String::new(),
+            copy: CopyButton::target("#api-token-secret").aria_label("Copy the token"),
+        }
+    );
+}
+
+#[test]
+fn api_tokens_partial_created() {
+    assert_engines_match!(
+        "api_tokens_partial_created",
+        ApiTokensPartial {
+            tokens: token_rows(),
+            empty: false,
+            secret: format!("aht_3f2a9c{}", "0".repeat(58)),
+            copy: CopyButton::target("#api-token-secret").aria_label("Copy the token"),
+        }
+    );
+}
+
+fn request_row(status: u16, duration: &str) -> RequestRow {
+    RequestRow {
+        created_at: "2024-01-01 12:00:00".into(),
+        method: "GET".into(),
+        path: "/blog/hello-world".into(),
+        status,
+        duration: duration.into(),
+        request_id: "3f2a9c1e-0b7d-4e8a-9f61-2c5d8e7a1b40".into(),
+        warning: (400..500).contains(&status),
+        error: status >= 500,
+    }
+}
+
+#[test]
+fn admin_requests_page() {
+    let option = |value: &str, label: &str, selected: bool| FilterOption {
+        value: value.into(),
+        label: label.into(),
+        selected,
+    };
+    assert_engines_match!(
+        "admin_requests_page",
+        AdminRequestsPage {
+            enabled: true,
+            path: "/blog".into(),
+            statuses: vec![option("0", "Any status", true), option("5", "5xx", false)],
+            windows: vec![
+                option("0", "Everything kept", false),
+                option("60", "Last hour", true)
+            ],
+            rows: vec![
+                request_row(200, "4.2 ms"),
+                request_row(404, "1.0 ms"),
+                request_row(500, "120.5 ms"),
+            ],
+            empty: false,
+            count: 3,
+            p50: "4.2 ms".into(),
+            p90: "120.5 ms".into(),
+            p99: "120.5 ms".into(),
+            dropped: 0,
+        }
+    );
+}
+
+#[test]
+fn admin_requests_page_disabled() {
+    assert_engines_match!(
+        "admin_requests_page_disabled",
+        AdminRequestsPage {
+            enabled: false,
+            path: String::new(),
+            statuses: Vec::new(),
+            windows: Vec::new(),
+            rows: Vec::new(),
+            empty: true,
+            count: 0,
+            p50: "0.0 ms".into(),
+            p90: "0.0 ms".into(),
+            p99: "0.0 ms".into(),
+            dropped: 0,
+        }
+    );
+}
+
+#[test]
+fn request_log_partial_empty_with_drops() {
+    assert_engines_match!(
+        "request_log_partial_empty_with_drops",
+        RequestLogPartial {
+            rows: Vec::new(),
+            empty: true,
+            count: 0,
+            p50: "0.0 ms".into(),
+            p90: "0.0 ms".into(),
+            p99: "0.0 ms".into(),
+            dropped: 42,
+        }
+    );
+}
+
+#[test]
+fn not_found_page() {
+    assert_engines_match!(
+        "not_found_page",
+        NotFoundPage {
+            path: "/abuot".into(),
+            suggestions: vec!["/about".into()],
+            empty: false,
+        }
+    );
+}
+
+#[test]
+fn not_found_page_no_suggestions() {
+    assert_engines_match!(
+        "not_found_page_no_suggestions",
+        NotFoundPage {
+            path: "/<wp-login>.php".into(),
+            suggestions: Vec::new(),
+            empty: true,
+        }
+    );
+}
+
+#[test]
+fn not_found_report_partial() {
+    assert_engines_match!(
+        "not_found_report_partial",
+        NotFoundReportPartial {
+            hits: vec![
+                NotFoundHit {
+                    path: "/abuot".into(),
+                    count: 12,
+                    last_seen: "2024-01-01 12:00:00".into(),
+                    referer: "https://example.com/links".into(),
+                },
+                NotFoundHit {
+                    path: "/wp-login.php".into(),
+                    count: 3,
+                    last_seen: "2024-01-01 09:30:00".into(),
+                    referer: String::new(),
+                },
+            ],
+            empty: false,
+        }
+    );
+}